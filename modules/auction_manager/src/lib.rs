@@ -1,27 +1,62 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use frame_support::{decl_event, decl_module, decl_storage, traits::Get, Parameter};
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::{EnsureOrigin, Get},
+	weights::Weight,
+	Parameter,
+};
 use orml_traits::{
 	arithmetic::{self, Signed},
 	Auction, AuctionHandler, MultiCurrency, MultiCurrencyExtended, OnNewBidResult,
 };
-use rstd::convert::{TryFrom, TryInto};
+use rstd::{
+	convert::{TryFrom, TryInto},
+	prelude::*,
+};
 use sp_runtime::{
 	traits::{
 		AccountIdConversion, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, MaybeSerializeDeserialize, Member,
-		SimpleArithmetic,
+		Saturating, SimpleArithmetic, Zero,
 	},
-	ModuleId, RuntimeDebug,
+	DispatchResult, ModuleId, RuntimeDebug,
 };
-use support::{AuctionManager, Rate};
-use system::ensure_root;
+use support::{AuctionManager, AuctionManagerExtended, CDPTreasury, Rate, STORAGE_ACCESS_WEIGHT};
+
+#[cfg(feature = "std")]
+use support::{AuditResult, Auditor};
 
 mod mock;
 mod tests;
 
 const MODULE_ID: ModuleId = ModuleId(*b"aca/amgr");
 
+/// Layouts of the module storage, in the order they are migrated through.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum StorageVersion {
+	/// Before the storage is versioned, all the funds are held by the module account.
+	V0,
+	/// The funds of each currency are held by its own sub-account.
+	V1,
+}
+
+impl Default for StorageVersion {
+	fn default() -> Self {
+		StorageVersion::V0
+	}
+}
+
+impl StorageVersion {
+	/// The version migrated to from this one, `None` for the latest.
+	pub fn next(self) -> Option<Self> {
+		match self {
+			StorageVersion::V0 => Some(StorageVersion::V1),
+			StorageVersion::V1 => None,
+		}
+	}
+}
+
 #[cfg_attr(feature = "std", derive(PartialEq, Eq))]
 #[derive(Encode, Decode, Clone, RuntimeDebug)]
 pub struct AuctionItem<AccountId, CurrencyId, Balance, BlockNumber> {
@@ -32,6 +67,24 @@ pub struct AuctionItem<AccountId, CurrencyId, Balance, BlockNumber> {
 	start_time: BlockNumber,
 }
 
+/// Auction of stable coin surplus, bid in native currency which is burned after the auction.
+#[cfg_attr(feature = "std", derive(PartialEq, Eq))]
+#[derive(Encode, Decode, Clone, RuntimeDebug)]
+pub struct SurplusAuctionItem<Balance, BlockNumber> {
+	amount: Balance,
+	start_time: BlockNumber,
+}
+
+/// Auction of newly issued native currency for a fixed amount of stable coin, the native amount
+/// decreases as the bid increases.
+#[cfg_attr(feature = "std", derive(PartialEq, Eq))]
+#[derive(Encode, Decode, Clone, RuntimeDebug)]
+pub struct DebitAuctionItem<Balance, BlockNumber> {
+	amount: Balance,
+	fix: Balance,
+	start_time: BlockNumber,
+}
+
 type AuctionIdOf<T> =
 	<<T as Trait>::Auction as Auction<<T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber>>::AuctionId;
 
@@ -54,11 +107,15 @@ pub trait Trait: system::Trait {
 		Balance = Self::Balance,
 		Amount = Self::Amount,
 	>;
-	type Auction: Auction<Self::AccountId, Self::BlockNumber>;
+	type Auction: Auction<Self::AccountId, Self::BlockNumber, Balance = Self::Balance>;
 	type MinimumIncrementSize: Get<Rate>;
 	type AuctionTimeToClose: Get<Self::BlockNumber>;
 	type AuctionDurationSoftCap: Get<Self::BlockNumber>;
 	type GetStableCurrencyId: Get<Self::CurrencyId>;
+	type GetNativeCurrencyId: Get<Self::CurrencyId>;
+	type Treasury: CDPTreasury<Self::AccountId, CurrencyId = Self::CurrencyId, Balance = Self::Balance>;
+	/// Origin of the calls updating the parameters of the module.
+	type UpdateOrigin: EnsureOrigin<Self::Origin>;
 }
 
 decl_event!(
@@ -68,47 +125,203 @@ decl_event!(
 		CurrencyId = <T as Trait>::CurrencyId,
 		Balance = <T as Trait>::Balance,
 	{
+		/// Collateral auction created (auction_id, currency_id, amount, target)
 		CollateralAuction(AuctionId, CurrencyId, Balance, Balance),
+		/// Surplus auction created (auction_id, amount)
+		SurplusAuction(AuctionId, Balance),
+		/// Debit auction created (auction_id, initial_amount, fix_debit)
+		DebitAuction(AuctionId, Balance, Balance),
+		CancelAuction(AuctionId),
 	}
 );
 
+decl_error! {
+	/// Error for auction manager module.
+	pub enum Error for Module<T: Trait> {
+		AuctionNotExists,
+		BalanceOverflow,
+		RefundFailed,
+		TransferFailed,
+	}
+}
+
 decl_storage! {
 	trait Store for Module<T: Trait> as AuctionManager {
 		MaximumAuctionSize get(fn maximum_auction_size): map T::CurrencyId => T::Balance;
-		Auctions get(fn auctions): map AuctionIdOf<T> =>
+		Auctions get(fn auctions): linked_map AuctionIdOf<T> =>
 			Option<AuctionItem<T::AccountId, T::CurrencyId, T::Balance, T::BlockNumber>>;
+		SurplusAuctions get(fn surplus_auctions): linked_map AuctionIdOf<T> =>
+			Option<SurplusAuctionItem<T::Balance, T::BlockNumber>>;
+		DebitAuctions get(fn debit_auctions): linked_map AuctionIdOf<T> =>
+			Option<DebitAuctionItem<T::Balance, T::BlockNumber>>;
 		TotalCollateralInAuction get(fn total_collateral_in_auction): map T::CurrencyId => T::Balance;
-		BadDebtPool get(fn bad_debt_pool): T::Balance;
-		SurplusPool get(fn surplus_pool): T::Balance;
+		TotalSurplusInAuction get(fn total_surplus_in_auction): T::Balance;
+		TotalDebitInAuction get(fn total_debit_in_auction): T::Balance;
+
+		Version get(fn storage_version): StorageVersion;
 	}
 }
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
 		fn deposit_event() = default;
 
 		fn set_maximum_auction_size(origin, currency_id: T::CurrencyId, size: T::Balance) {
-			ensure_root(origin)?;
+			T::UpdateOrigin::ensure_origin(origin)?;
 			<MaximumAuctionSize<T>>::insert(currency_id, size);
 		}
-
-		fn on_finalize(_now: T::BlockNumber) {
-			let amount = rstd::cmp::min(Self::bad_debt_pool(), Self::surplus_pool());
-			if amount > 0.into() {
-				if T::Currency::withdraw(T::GetStableCurrencyId::get(), &Self::account_id(), amount).is_ok() {
-					<BadDebtPool<T>>::mutate(|debt| *debt -= amount);
-					<SurplusPool<T>>::mutate(|surplus| *surplus -= amount);
-				}
-			}
-		}
 	}
 }
 
 impl<T: Trait> Module<T> {
+	/// The module account, which held all the funds before `StorageVersion::V1`.
 	pub fn account_id() -> T::AccountId {
 		MODULE_ID.into_account()
 	}
 
+	/// The sub-account holding the funds of `currency_id`: the collateral in auction, the native currency bid for
+	/// surplus, or the stable coin paid for debit.
+	pub fn sub_account_id(currency_id: T::CurrencyId) -> T::AccountId {
+		MODULE_ID.into_sub_account(currency_id)
+	}
+
+	/// Transfer `amount` of `currency_id` from `from` to the sub-account holding it.
+	fn hold(currency_id: T::CurrencyId, from: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		T::Currency::transfer(currency_id, from, &Self::sub_account_id(currency_id), amount)
+			.map_err(|_| Error::<T>::TransferFailed.into())
+	}
+
+	/// Transfer `amount` of `currency_id` from the sub-account holding it to `to`.
+	fn release(currency_id: T::CurrencyId, to: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		T::Currency::transfer(currency_id, &Self::sub_account_id(currency_id), to, amount)
+			.map_err(|_| Error::<T>::TransferFailed.into())
+	}
+
+	/// Apply the pending migrations in order and return the weight consumed. Only the version is read once the
+	/// storage is up to date, so it's safe to run again.
+	pub fn on_runtime_upgrade() -> Weight {
+		let mut version = Self::storage_version();
+		let mut weight = STORAGE_ACCESS_WEIGHT;
+		while let Some(next) = version.next() {
+			weight = weight
+				.saturating_add(Self::migrate_to(next))
+				.saturating_add(STORAGE_ACCESS_WEIGHT);
+			<Version>::put(next);
+			version = next;
+		}
+		weight
+	}
+
+	/// Migrate the storage to the layout of `version` from the one before it, returns the weight consumed.
+	fn migrate_to(version: StorageVersion) -> Weight {
+		match version {
+			// the first version is never migrated to
+			StorageVersion::V0 => 0,
+			StorageVersion::V1 => Self::migrate_to_sub_accounts(),
+		}
+	}
+
+	/// Move the balances of the module account to their sub-accounts. Only the collaterals of the live auctions,
+	/// the native currency and the stable coin are ever held.
+	fn migrate_to_sub_accounts() -> Weight {
+		let module_account = Self::account_id();
+		let mut weight: Weight = 0;
+
+		let mut currency_ids = vec![T::GetNativeCurrencyId::get(), T::GetStableCurrencyId::get()];
+		for (_, auction_item) in <Auctions<T>>::enumerate() {
+			if !currency_ids.contains(&auction_item.currency_id) {
+				currency_ids.push(auction_item.currency_id);
+			}
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT);
+		}
+
+		for currency_id in currency_ids {
+			let balance = T::Currency::balance(currency_id, &module_account);
+			if !balance.is_zero() {
+				let _ = Self::hold(currency_id, &module_account, balance);
+			}
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT * 3);
+		}
+
+		weight
+	}
+
+	/// Stable coin the collateral auctions in progress are to raise.
+	pub fn total_target_in_auction() -> T::Balance {
+		<Auctions<T>>::enumerate().fold(Zero::zero(), |total, (_, auction_item)| {
+			total.saturating_add(auction_item.target)
+		})
+	}
+
+	/// Check the totals in auction against the live auctions, the collaterals in auction against the balances
+	/// of their sub-accounts, and that nothing is left in the module account.
+	#[cfg(feature = "std")]
+	pub fn audit(currency_ids: &[T::CurrencyId]) -> AuditResult<T::CurrencyId> {
+		let module_account = Self::account_id();
+		let stable_currency_id = T::GetStableCurrencyId::get();
+		let native_currency_id = T::GetNativeCurrencyId::get();
+		let mut auditor = Auditor::default();
+
+		for &currency_id in currency_ids {
+			let total_collateral_in_auction = Self::total_collateral_in_auction(currency_id);
+			let collateral_in_auctions = <Auctions<T>>::enumerate()
+				.filter(|(_, auction_item)| auction_item.currency_id == currency_id)
+				.fold(Zero::zero(), |total: T::Balance, (_, auction_item)| {
+					total.saturating_add(auction_item.amount)
+				});
+			auditor.check(
+				"auction manager: collateral in auction",
+				currency_id,
+				total_collateral_in_auction,
+				collateral_in_auctions,
+			);
+			auditor.check(
+				"auction manager: collateral held",
+				currency_id,
+				total_collateral_in_auction,
+				T::Currency::balance(currency_id, &Self::sub_account_id(currency_id)),
+			);
+		}
+
+		let held_currency_ids = currency_ids
+			.iter()
+			.chain(&[stable_currency_id, native_currency_id])
+			.cloned()
+			.collect::<Vec<_>>();
+		for currency_id in held_currency_ids {
+			auditor.check(
+				"auction manager: left in module account",
+				currency_id,
+				Zero::zero(),
+				T::Currency::balance(currency_id, &module_account),
+			);
+		}
+
+		let surplus_in_auctions = <SurplusAuctions<T>>::enumerate()
+			.fold(Zero::zero(), |total: T::Balance, (_, item)| {
+				total.saturating_add(item.amount)
+			});
+		auditor.check(
+			"auction manager: surplus in auction",
+			stable_currency_id,
+			Self::total_surplus_in_auction(),
+			surplus_in_auctions,
+		);
+		let debit_in_auctions = <DebitAuctions<T>>::enumerate().fold(Zero::zero(), |total: T::Balance, (_, item)| {
+			total.saturating_add(item.fix)
+		});
+		auditor.check(
+			"auction manager: debit in auction",
+			stable_currency_id,
+			Self::total_debit_in_auction(),
+			debit_in_auctions,
+		);
+
+		auditor.finish()
+	}
+
 	/// Check `new_price` is larger than minimum increment
 	/// Formula: bid_price - last_price >= max(last_price, target) * minimum_increment_size
 	pub fn check_minimum_increment(
@@ -126,27 +339,48 @@ impl<T: Trait> Module<T> {
 
 		false
 	}
-}
 
-impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionIdOf<T>> for Module<T> {
-	fn on_new_bid(
+	/// Calculate min_increment_size and auction_time_to_close according to elapsed time
+	fn get_minimum_increment_size_and_time_to_close(
+		now: T::BlockNumber,
+		start_time: T::BlockNumber,
+	) -> (Rate, T::BlockNumber) {
+		if now >= start_time + T::AuctionDurationSoftCap::get() {
+			T::MinimumIncrementSize::get()
+				.checked_mul(&Rate::from_natural(2))
+				.and_then(|increment| Some((increment, T::AuctionTimeToClose::get() / 2.into())))
+				.unwrap_or((T::MinimumIncrementSize::get(), T::AuctionTimeToClose::get()))
+		} else {
+			(T::MinimumIncrementSize::get(), T::AuctionTimeToClose::get())
+		}
+	}
+
+	/// Refund stable coin to `who` from the treasury surplus, the part of it which has been
+	/// offset against bad debt is issued again as bad debt
+	fn refund_stable_coin(who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let from_surplus = rstd::cmp::min(amount, T::Treasury::get_surplus_pool());
+		let shortfall = amount - from_surplus;
+		ensure!(
+			T::Treasury::get_debit_pool().checked_add(&shortfall).is_some(),
+			Error::<T>::BalanceOverflow
+		);
+		T::Treasury::withdraw_surplus(who, from_surplus)?;
+		if shortfall > 0.into() {
+			T::Treasury::issue_debit(who, shortfall)?;
+		}
+
+		Ok(())
+	}
+
+	fn collateral_auction_bid_handler(
 		now: T::BlockNumber,
 		id: AuctionIdOf<T>,
 		new_bid: (T::AccountId, T::Balance),
 		last_bid: Option<(T::AccountId, T::Balance)>,
 	) -> OnNewBidResult<T::BlockNumber> {
 		if let Some(mut auction_item) = Self::auctions(id) {
-			// calculate min_increment_size and auction_time_to_close according to elapsed time
-
 			let (minimum_increment_size, auction_time_to_close) =
-				if now >= auction_item.start_time + T::AuctionDurationSoftCap::get() {
-					T::MinimumIncrementSize::get()
-						.checked_mul(&Rate::from_natural(2))
-						.and_then(|increment| Some((increment, T::AuctionTimeToClose::get() / 2.into())))
-						.unwrap_or((T::MinimumIncrementSize::get(), T::AuctionTimeToClose::get()))
-				} else {
-					(T::MinimumIncrementSize::get(), T::AuctionTimeToClose::get())
-				};
+				Self::get_minimum_increment_size_and_time_to_close(now, auction_item.start_time);
 
 			// get last price, if these's no bid set 0
 			let last_price: T::Balance = match last_bid {
@@ -154,29 +388,19 @@ impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionI
 				Some((_, price)) => price,
 			};
 
-			let stable_currency_id = T::GetStableCurrencyId::get();
 			let payment = rstd::cmp::min(auction_item.target, new_bid.1);
 
 			// check new price is larger than minimum increment
-			// check new bidder has enough stable coin
+			// first: deduct amount of stablecoin from new bidder, add this to the treasury surplus
 			if Self::check_minimum_increment(&new_bid.1, &last_price, &auction_item.target, &minimum_increment_size)
-				&& T::Currency::balance(stable_currency_id, &(new_bid.0)) >= payment
-				&& Self::surplus_pool().checked_add(&payment).is_some()
+				&& T::Treasury::deposit_surplus(&(new_bid.0), payment).is_ok()
 			{
-				let module_account = Self::account_id();
-
-				// first: deduct amount of stablecoin from new bidder, add this to auction manager module
-				T::Currency::transfer(stable_currency_id, &(new_bid.0), &module_account, payment)
-					.expect("never failed after balance check");
-				<SurplusPool<T>>::mutate(|surplus| *surplus += payment);
-
-				// second: if these's bid before, return stablecoin from auction manager module to last bidder
+				// second: if these's bid before, return stablecoin from the treasury surplus to last bidder
 				if let Some((last_bidder, last_price)) = last_bid {
 					let refund = rstd::cmp::min(last_price, auction_item.target);
 
-					T::Currency::transfer(stable_currency_id, &module_account, &last_bidder, refund)
+					T::Treasury::withdraw_surplus(&last_bidder, refund)
 						.expect("never failed because payment >= refund");
-					<SurplusPool<T>>::mutate(|surplus| *surplus -= refund);
 				}
 
 				// third: if bid_price > target, the auction is in reverse, refund collateral to it's origin from auction manager module
@@ -191,13 +415,8 @@ impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionI
 
 					// ensure have sufficient collateral in auction module
 					if Self::total_collateral_in_auction(auction_item.currency_id) >= deduct_amount {
-						T::Currency::transfer(
-							auction_item.currency_id,
-							&module_account,
-							&(auction_item.owner),
-							deduct_amount,
-						)
-						.expect("never failed after balance check");
+						Self::release(auction_item.currency_id, &(auction_item.owner), deduct_amount)
+							.expect("never failed after balance check");
 						<TotalCollateralInAuction<T>>::mutate(auction_item.currency_id, |balance| {
 							*balance -= deduct_amount
 						});
@@ -221,17 +440,176 @@ impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionI
 		}
 	}
 
-	fn on_auction_ended(id: AuctionIdOf<T>, winner: Option<(T::AccountId, T::Balance)>) {
-		if let (Some(auction_item), Some((bidder, _))) = (Self::auctions(id), winner) {
+	fn surplus_auction_bid_handler(
+		now: T::BlockNumber,
+		id: AuctionIdOf<T>,
+		new_bid: (T::AccountId, T::Balance),
+		last_bid: Option<(T::AccountId, T::Balance)>,
+	) -> OnNewBidResult<T::BlockNumber> {
+		if let Some(surplus_auction) = Self::surplus_auctions(id) {
+			let (minimum_increment_size, auction_time_to_close) =
+				Self::get_minimum_increment_size_and_time_to_close(now, surplus_auction.start_time);
+			let last_price: T::Balance = match last_bid {
+				None => 0.into(),
+				Some((_, price)) => price,
+			};
+			let native_currency_id = T::GetNativeCurrencyId::get();
+
+			// hold the native currency of new bidder in auction manager module, and refund last bidder
+			if new_bid.1 > 0.into()
+				&& Self::check_minimum_increment(&new_bid.1, &last_price, &0.into(), &minimum_increment_size)
+				&& Self::hold(native_currency_id, &(new_bid.0), new_bid.1).is_ok()
+			{
+				if let Some((last_bidder, last_price)) = last_bid {
+					Self::release(native_currency_id, &last_bidder, last_price)
+						.expect("never failed because last bid is held by module account");
+				}
+
+				return OnNewBidResult {
+					accept_bid: true,
+					auction_end: Some(Some(now + auction_time_to_close)),
+				};
+			}
+		}
+
+		OnNewBidResult {
+			accept_bid: false,
+			auction_end: None,
+		}
+	}
+
+	fn debit_auction_bid_handler(
+		now: T::BlockNumber,
+		id: AuctionIdOf<T>,
+		new_bid: (T::AccountId, T::Balance),
+		last_bid: Option<(T::AccountId, T::Balance)>,
+	) -> OnNewBidResult<T::BlockNumber> {
+		if let Some(mut debit_auction) = Self::debit_auctions(id) {
+			let (minimum_increment_size, auction_time_to_close) =
+				Self::get_minimum_increment_size_and_time_to_close(now, debit_auction.start_time);
+			let last_price: T::Balance = match last_bid {
+				None => 0.into(),
+				Some((_, price)) => price,
+			};
+			let stable_currency_id = T::GetStableCurrencyId::get();
+
+			// every bidder pays the fixed debit, a larger bid means accepting less native currency
+			if new_bid.1 >= debit_auction.fix
+				&& Self::check_minimum_increment(&new_bid.1, &last_price, &debit_auction.fix, &minimum_increment_size)
+				&& Self::hold(stable_currency_id, &(new_bid.0), debit_auction.fix).is_ok()
+			{
+				if let Some((last_bidder, _)) = last_bid {
+					Self::release(stable_currency_id, &last_bidder, debit_auction.fix)
+						.expect("never failed because last payment is held by module account");
+				}
+
+				if new_bid.1 > debit_auction.fix {
+					debit_auction.amount = debit_auction
+						.amount
+						.checked_mul(rstd::cmp::max(&last_price, &debit_auction.fix))
+						.and_then(|n| n.checked_div(&new_bid.1))
+						.unwrap_or(debit_auction.amount);
+					<DebitAuctions<T>>::insert(id, debit_auction);
+				}
+
+				return OnNewBidResult {
+					accept_bid: true,
+					auction_end: Some(Some(now + auction_time_to_close)),
+				};
+			}
+		}
+
+		OnNewBidResult {
+			accept_bid: false,
+			auction_end: None,
+		}
+	}
+
+	fn collateral_auction_end_handler(
+		auction_item: AuctionItem<T::AccountId, T::CurrencyId, T::Balance, T::BlockNumber>,
+		winner: Option<(T::AccountId, T::Balance)>,
+	) {
+		let amount = rstd::cmp::min(
+			auction_item.amount,
+			Self::total_collateral_in_auction(auction_item.currency_id),
+		);
+		if let Some((bidder, _)) = winner {
 			// these's bidder for this auction, transfer collateral to bidder
-			let amount = rstd::cmp::min(
-				auction_item.amount,
-				Self::total_collateral_in_auction(auction_item.currency_id),
-			);
-			T::Currency::transfer(auction_item.currency_id, &Self::account_id(), &bidder, amount)
-				.expect("never failed because use");
-			<TotalCollateralInAuction<T>>::mutate(auction_item.currency_id, |balance| *balance -= amount);
+			Self::release(auction_item.currency_id, &bidder, amount).expect("never failed because use");
+		} else {
+			// no bidder, the collateral goes back to the treasury
+			T::Treasury::deposit_collateral(
+				&Self::sub_account_id(auction_item.currency_id),
+				auction_item.currency_id,
+				amount,
+			)
+			.expect("never failed because collateral in auction is held by module account");
+		}
+		<TotalCollateralInAuction<T>>::mutate(auction_item.currency_id, |balance| *balance -= amount);
+	}
+
+	fn surplus_auction_end_handler(
+		surplus_auction: SurplusAuctionItem<T::Balance, T::BlockNumber>,
+		winner: Option<(T::AccountId, T::Balance)>,
+	) {
+		if let Some((bidder, bid_price)) = winner {
+			// burn the native currency paid by winner and transfer the surplus to winner
+			let native_currency_id = T::GetNativeCurrencyId::get();
+			T::Currency::withdraw(native_currency_id, &Self::sub_account_id(native_currency_id), bid_price)
+				.expect("never failed because bid is held by module account");
+			T::Treasury::withdraw_surplus(&bidder, surplus_auction.amount)
+				.expect("never failed because surplus in auction is never offset");
+		}
+		<TotalSurplusInAuction<T>>::mutate(|balance| *balance -= surplus_auction.amount);
+	}
+
+	fn debit_auction_end_handler(
+		debit_auction: DebitAuctionItem<T::Balance, T::BlockNumber>,
+		winner: Option<(T::AccountId, T::Balance)>,
+	) {
+		if let Some((bidder, _)) = winner {
+			// the fixed debit paid by winner goes to the treasury surplus to offset bad debt,
+			// and the native currency is issued to winner
+			T::Treasury::deposit_surplus(&Self::sub_account_id(T::GetStableCurrencyId::get()), debit_auction.fix)
+				.expect("never failed because payment is held by module account");
+			T::Currency::deposit(T::GetNativeCurrencyId::get(), &bidder, debit_auction.amount)
+				.expect("never failed unless the native currency overflows");
+		}
+		<TotalDebitInAuction<T>>::mutate(|balance| *balance -= debit_auction.fix);
+	}
+}
+
+impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionIdOf<T>> for Module<T> {
+	fn on_new_bid(
+		now: T::BlockNumber,
+		id: AuctionIdOf<T>,
+		new_bid: (T::AccountId, T::Balance),
+		last_bid: Option<(T::AccountId, T::Balance)>,
+	) -> OnNewBidResult<T::BlockNumber> {
+		if <Auctions<T>>::exists(id) {
+			Self::collateral_auction_bid_handler(now, id, new_bid, last_bid)
+		} else if <SurplusAuctions<T>>::exists(id) {
+			Self::surplus_auction_bid_handler(now, id, new_bid, last_bid)
+		} else if <DebitAuctions<T>>::exists(id) {
+			Self::debit_auction_bid_handler(now, id, new_bid, last_bid)
+		} else {
+			OnNewBidResult {
+				accept_bid: false,
+				auction_end: None,
+			}
+		}
+	}
+
+	fn on_auction_ended(id: AuctionIdOf<T>, winner: Option<(T::AccountId, T::Balance)>) {
+		if let Some(auction_item) = Self::auctions(id) {
+			Self::collateral_auction_end_handler(auction_item, winner);
 			<Auctions<T>>::remove(id);
+		} else if let Some(surplus_auction) = Self::surplus_auctions(id) {
+			Self::surplus_auction_end_handler(surplus_auction, winner);
+			<SurplusAuctions<T>>::remove(id);
+		} else if let Some(debit_auction) = Self::debit_auctions(id) {
+			Self::debit_auction_end_handler(debit_auction, winner);
+			<DebitAuctions<T>>::remove(id);
 		}
 	}
 }
@@ -239,36 +617,21 @@ impl<T: Trait> AuctionHandler<T::AccountId, T::Balance, T::BlockNumber, AuctionI
 impl<T: Trait> AuctionManager<T::AccountId> for Module<T> {
 	type CurrencyId = T::CurrencyId;
 	type Balance = T::Balance;
-	type Amount = T::Amount;
-
-	fn increase_surplus(increment: Self::Balance) {
-		if Self::surplus_pool().checked_add(&increment).is_some()
-			&& T::Currency::balance(T::GetStableCurrencyId::get(), &Self::account_id())
-				.checked_add(&increment)
-				.is_some()
-		{
-			T::Currency::deposit(T::GetStableCurrencyId::get(), &Self::account_id(), increment)
-				.expect("never failed after overflow check");
-			<SurplusPool<T>>::mutate(|surplus| *surplus += increment);
-		}
-	}
+	type AuctionId = AuctionIdOf<T>;
 
 	fn new_collateral_auction(
 		who: T::AccountId,
 		currency_id: Self::CurrencyId,
 		amount: Self::Balance,
 		target: Self::Balance,
-		bad_debt: Self::Balance,
-	) {
+	) -> Vec<Self::AuctionId> {
+		let mut auction_ids = Vec::new();
 		if Self::total_collateral_in_auction(currency_id)
 			.checked_add(&amount)
-			.is_some() && T::Currency::balance(T::GetStableCurrencyId::get(), &Self::account_id())
-			.checked_add(&amount)
-			.is_some() && Self::bad_debt_pool().checked_add(&bad_debt).is_some()
+			.is_some()
+			&& T::Treasury::withdraw_collateral(&Self::sub_account_id(currency_id), currency_id, amount).is_ok()
 		{
-			T::Currency::deposit(currency_id, &Self::account_id(), amount).expect("never failed after overflow check");
 			<TotalCollateralInAuction<T>>::mutate(currency_id, |balance| *balance += amount);
-			<BadDebtPool<T>>::mutate(|debt| *debt += bad_debt);
 
 			let maximum_auction_size = <Module<T>>::maximum_auction_size(currency_id);
 			let mut unhandled_amount: Self::Balance = amount;
@@ -296,6 +659,7 @@ impl<T: Trait> AuctionManager<T::AccountId> for Module<T> {
 					start_time: block_number,
 				};
 				<Auctions<T>>::insert(auction_id, aution_item);
+				auction_ids.push(auction_id);
 				<Module<T>>::deposit_event(RawEvent::CollateralAuction(
 					auction_id,
 					currency_id,
@@ -308,5 +672,131 @@ impl<T: Trait> AuctionManager<T::AccountId> for Module<T> {
 				unhandled_target -= lot_target;
 			}
 		}
+
+		auction_ids
+	}
+
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId> {
+		let total_surplus_in_auction = Self::total_surplus_in_auction().checked_add(&amount)?;
+		if amount == 0.into() || total_surplus_in_auction > T::Treasury::get_surplus_pool() {
+			return None;
+		}
+
+		let block_number = <system::Module<T>>::block_number();
+		let auction_id: AuctionIdOf<T> = T::Auction::new_auction(block_number, None);
+		<SurplusAuctions<T>>::insert(
+			auction_id,
+			SurplusAuctionItem {
+				amount,
+				start_time: block_number,
+			},
+		);
+		<TotalSurplusInAuction<T>>::put(total_surplus_in_auction);
+		<Module<T>>::deposit_event(RawEvent::SurplusAuction(auction_id, amount));
+
+		Some(auction_id)
+	}
+
+	fn new_debit_auction(initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId> {
+		let total_debit_in_auction = Self::total_debit_in_auction().checked_add(&fix_debit)?;
+		if fix_debit == 0.into() || total_debit_in_auction > T::Treasury::get_debit_pool() {
+			return None;
+		}
+
+		let block_number = <system::Module<T>>::block_number();
+		let auction_id: AuctionIdOf<T> = T::Auction::new_auction(block_number, None);
+		<DebitAuctions<T>>::insert(
+			auction_id,
+			DebitAuctionItem {
+				amount: initial_amount,
+				fix: fix_debit,
+				start_time: block_number,
+			},
+		);
+		<TotalDebitInAuction<T>>::put(total_debit_in_auction);
+		<Module<T>>::deposit_event(RawEvent::DebitAuction(auction_id, initial_amount, fix_debit));
+
+		Some(auction_id)
+	}
+
+	fn cancel_auction(id: Self::AuctionId) -> DispatchResult {
+		let last_bid = T::Auction::auction_info(id).and_then(|info| info.bid);
+
+		if let Some(auction_item) = Self::auctions(id) {
+			// refund the stable coin paid by the last bidder
+			if let Some((bidder, bid_price)) = last_bid {
+				Self::refund_stable_coin(&bidder, rstd::cmp::min(bid_price, auction_item.target))?;
+			}
+
+			// the collateral of the lot goes back to the treasury
+			let amount = rstd::cmp::min(
+				auction_item.amount,
+				Self::total_collateral_in_auction(auction_item.currency_id),
+			);
+			T::Treasury::deposit_collateral(
+				&Self::sub_account_id(auction_item.currency_id),
+				auction_item.currency_id,
+				amount,
+			)?;
+			<TotalCollateralInAuction<T>>::mutate(auction_item.currency_id, |balance| *balance -= amount);
+			<Auctions<T>>::remove(id);
+		} else if let Some(surplus_auction) = Self::surplus_auctions(id) {
+			// refund the native currency held for the last bidder
+			if let Some((bidder, bid_price)) = last_bid {
+				Self::release(T::GetNativeCurrencyId::get(), &bidder, bid_price)
+					.map_err(|_| Error::<T>::RefundFailed)?;
+			}
+			<TotalSurplusInAuction<T>>::mutate(|balance| *balance -= surplus_auction.amount);
+			<SurplusAuctions<T>>::remove(id);
+		} else if let Some(debit_auction) = Self::debit_auctions(id) {
+			// refund the fixed debit held for the last bidder
+			if let Some((bidder, _)) = last_bid {
+				Self::release(T::GetStableCurrencyId::get(), &bidder, debit_auction.fix)
+					.map_err(|_| Error::<T>::RefundFailed)?;
+			}
+			<TotalDebitInAuction<T>>::mutate(|balance| *balance -= debit_auction.fix);
+			<DebitAuctions<T>>::remove(id);
+		} else {
+			return Err(Error::<T>::AuctionNotExists.into());
+		}
+
+		T::Auction::remove_auction(id);
+		<Module<T>>::deposit_event(RawEvent::CancelAuction(id));
+
+		Ok(())
+	}
+
+	fn get_total_collateral_in_auction(currency_id: Self::CurrencyId) -> Self::Balance {
+		Self::total_collateral_in_auction(currency_id)
+	}
+
+	fn get_total_surplus_in_auction() -> Self::Balance {
+		Self::total_surplus_in_auction()
+	}
+
+	fn get_total_debit_in_auction() -> Self::Balance {
+		Self::total_debit_in_auction()
+	}
+}
+
+impl<T: Trait> AuctionManagerExtended<T::AccountId> for Module<T> {
+	fn emergency_cancel_all(max_per_block: u32) -> u32 {
+		let auction_ids: Vec<AuctionIdOf<T>> = <Auctions<T>>::enumerate()
+			.map(|(id, _)| id)
+			.chain(<SurplusAuctions<T>>::enumerate().map(|(id, _)| id))
+			.chain(<DebitAuctions<T>>::enumerate().map(|(id, _)| id))
+			.take(max_per_block as usize)
+			.collect();
+
+		auction_ids
+			.into_iter()
+			.filter(|id| Self::cancel_auction(*id).is_ok())
+			.count() as u32
+	}
+
+	fn active_auction_count() -> u32 {
+		(<Auctions<T>>::enumerate().count()
+			+ <SurplusAuctions<T>>::enumerate().count()
+			+ <DebitAuctions<T>>::enumerate().count()) as u32
 	}
 }
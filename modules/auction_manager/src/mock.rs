@@ -3,8 +3,14 @@
 #![cfg(test)]
 
 use frame_support::{impl_outer_origin, parameter_types};
+use orml_traits::PriceProvider;
 use primitives::H256;
-use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	ModuleId, Perbill,
+};
+use support::{FixedU128Ext, Price, Ratio};
 
 use super::*;
 
@@ -20,18 +26,30 @@ parameter_types! {
 	pub const MaximumBlockWeight: u32 = 1024;
 	pub const MaximumBlockLength: u32 = 2 * 1024;
 	pub const AvailableBlockRatio: Perbill = Perbill::one();
-	pub const MinimumIncrementSize: Rate = Rate::from_rational(1, 20);
+	pub const MinimumIncrementSize: Rate = Rate::from_percent(5);
 	pub const AuctionTimeToClose: u64 = 100;
 	pub const AuctionDurationSoftCap: u64 = 2000;
 	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC];
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
+	pub const GetNativeCurrencyId: CurrencyId = ACA;
 }
 
 pub type AccountId = u64;
 pub type BlockNumber = u64;
 pub type AuctionId = u64;
-pub type CurrencyId = u32;
+pub type CurrencyId = module_primitives::CurrencyId;
 pub type Balance = u64;
 pub type Amount = i64;
+pub type Share = u64;
 
 impl system::Trait for Runtime {
 	type Origin = Origin;
@@ -49,6 +67,7 @@ impl system::Trait for Runtime {
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = ();
 }
 
 impl orml_tokens::Trait for Runtime {
@@ -67,6 +86,57 @@ impl orml_auction::Trait for Runtime {
 }
 pub type Auction = orml_auction::Module<Runtime>;
 
+/// No feed prices, the collaterals of the treasury are never sold.
+pub struct MockPriceSource;
+impl PriceProvider<CurrencyId, Price> for MockPriceSource {
+	fn get_price(_base: CurrencyId, _quote: CurrencyId) -> Option<Price> {
+		None
+	}
+}
+
+impl dex::Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+impl cdp_treasury::Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = AuctionManagerModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = DexModule;
+	type PriceSource = MockPriceSource;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = cdp_treasury::Module<Runtime>;
+
 impl Trait for Runtime {
 	type Event = ();
 	type Currency = Tokens;
@@ -78,13 +148,18 @@ impl Trait for Runtime {
 	type AuctionTimeToClose = AuctionTimeToClose;
 	type AuctionDurationSoftCap = AuctionDurationSoftCap;
 	type GetStableCurrencyId = GetStableCurrencyId;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
 }
 pub type AuctionManagerModule = Module<Runtime>;
 
 pub const ALICE: AccountId = 1;
 pub const BOB: AccountId = 2;
-pub const AUSD: CurrencyId = 1;
-pub const BTC: CurrencyId = 2;
+pub const CAROL: AccountId = 3;
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
 
 pub struct ExtBuilder {
 	currency_id: Vec<CurrencyId>,
@@ -95,15 +170,15 @@ pub struct ExtBuilder {
 impl Default for ExtBuilder {
 	fn default() -> Self {
 		Self {
-			currency_id: vec![AUSD, BTC],
-			endowed_accounts: vec![ALICE, BOB],
+			currency_id: vec![ACA, AUSD, BTC],
+			endowed_accounts: vec![ALICE, BOB, CAROL],
 			initial_balance: 1000,
 		}
 	}
 }
 
 impl ExtBuilder {
-	pub fn build(self) -> runtime_io::TestExternalities {
+	pub fn build(self) -> AuditedExternalities {
 		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
 
 		orml_tokens::GenesisConfig::<Runtime> {
@@ -114,6 +189,20 @@ impl ExtBuilder {
 		.assimilate_storage(&mut t)
 		.unwrap();
 
-		t.into()
+		AuditedExternalities(t.into())
+	}
+}
+
+/// Externalities auditing the accounting after every execution.
+pub struct AuditedExternalities(runtime_io::TestExternalities);
+
+impl AuditedExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			assert_eq!(AuctionManagerModule::audit(&[BTC]), Ok(()));
+			assert_eq!(CdpTreasuryModule::audit(&[BTC]), Ok(()));
+			result
+		})
 	}
 }
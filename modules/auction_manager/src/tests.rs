@@ -3,13 +3,24 @@
 #![cfg(test)]
 
 use super::*;
-use frame_support::assert_ok;
-use mock::{Auction, AuctionManagerModule, ExtBuilder, Origin, Tokens, ALICE, AUSD, BOB, BTC};
+use frame_support::{assert_noop, assert_ok};
+use mock::{
+	Auction, AuctionManagerModule, CdpTreasuryModule, ExtBuilder, Origin, Runtime, Tokens, ACA, ALICE, AUSD, BOB, BTC,
+	CAROL,
+};
 use sp_runtime::traits::OnFinalize;
+use support::{InvariantViolation, STORAGE_ACCESS_WEIGHT};
+
+fn new_collateral_auction_from_treasury(amount: u64, target: u64, bad_debt: u64) -> Vec<u64> {
+	assert_ok!(CdpTreasuryModule::deposit_collateral(&CAROL, BTC, amount));
+	assert_ok!(CdpTreasuryModule::on_system_debit(bad_debt));
+	AuctionManagerModule::new_collateral_auction(ALICE, BTC, amount, target)
+}
 
 #[test]
 fn set_maximum_auction_size_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		assert!(AuctionManagerModule::set_maximum_auction_size(Origin::signed(ALICE), BTC, 20).is_err());
 		assert_ok!(AuctionManagerModule::set_maximum_auction_size(Origin::ROOT, BTC, 20));
 		assert_eq!(AuctionManagerModule::maximum_auction_size(BTC), 20);
 	});
@@ -18,38 +29,90 @@ fn set_maximum_auction_size_work() {
 #[test]
 fn new_collateral_auction_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 10, 100, 90);
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 90);
+		assert_eq!(
+			AuctionManagerModule::new_collateral_auction(ALICE, BTC, 10, 100),
+			vec![]
+		);
+		assert_eq!(new_collateral_auction_from_treasury(10, 100, 90), vec![0]);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 90);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 0);
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 10);
+		assert_eq!(Tokens::balance(BTC, &AuctionManagerModule::sub_account_id(BTC)), 10);
 		assert_eq!(Auction::auctions_count(), 1);
 	});
 }
 
+#[test]
+fn new_collateral_auction_split_lots_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(AuctionManagerModule::set_maximum_auction_size(Origin::ROOT, BTC, 4));
+		assert_eq!(new_collateral_auction_from_treasury(10, 100, 90), vec![0, 1, 2]);
+		assert_eq!(Auction::auctions_count(), 3);
+		assert_eq!(AuctionManagerModule::total_target_in_auction(), 100);
+	});
+}
+
+#[test]
+fn cancel_auction_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			AuctionManagerModule::cancel_auction(0),
+			Error::<Runtime>::AuctionNotExists
+		);
+		new_collateral_auction_from_treasury(10, 100, 90);
+		assert_ok!(Auction::bid(Origin::signed(BOB), 0, 20));
+		assert_eq!(Tokens::balance(AUSD, &BOB), 980);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 20);
+		assert_eq!(AuctionManagerModule::total_target_in_auction(), 100);
+		assert_ok!(AuctionManagerModule::cancel_auction(0));
+		assert_eq!(AuctionManagerModule::total_target_in_auction(), 0);
+		assert_eq!(Tokens::balance(AUSD, &BOB), 1000);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 90);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 10);
+		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+		assert_eq!(AuctionManagerModule::auctions(0), None);
+	});
+}
+
+#[test]
+fn cancel_auction_after_surplus_offset_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		new_collateral_auction_from_treasury(10, 100, 90);
+		assert_ok!(Auction::bid(Origin::signed(BOB), 0, 20));
+		CdpTreasuryModule::on_finalize(1);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 70);
+		assert_ok!(AuctionManagerModule::cancel_auction(0));
+		assert_eq!(Tokens::balance(AUSD, &BOB), 1000);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 90);
+	});
+}
+
 #[test]
 fn on_new_bid_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 10, 100, 90);
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 90);
+		new_collateral_auction_from_treasury(10, 100, 90);
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 10);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(10, 0, (BOB, 4), None).accept_bid,
 			false
 		);
 		assert_eq!(AuctionManagerModule::on_new_bid(10, 0, (BOB, 5), None).accept_bid, true);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 5);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 5);
 	});
 }
 
 #[test]
 fn bid_when_soft_cap_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 10, 100, 90);
+		new_collateral_auction_from_treasury(10, 100, 90);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(10, 0, (BOB, 5), None).auction_end,
 			Some(Some(110))
 		);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 5);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 5);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(2111, 0, (BOB, 10), Some((BOB, 5))).accept_bid,
 			false
@@ -64,11 +127,11 @@ fn bid_when_soft_cap_work() {
 #[test]
 fn reverse_collateral_auction_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 100, 200, 90);
+		new_collateral_auction_from_treasury(100, 200, 90);
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 100);
 		assert_eq!(Tokens::balance(BTC, &ALICE), 1000);
 		assert_eq!(Tokens::balance(AUSD, &BOB), 1000);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(1, 0, (BOB, 200), None).accept_bid,
 			true
@@ -76,7 +139,7 @@ fn reverse_collateral_auction_work() {
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 100);
 		assert_eq!(Tokens::balance(BTC, &ALICE), 1000);
 		assert_eq!(Tokens::balance(AUSD, &BOB), 800);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 200);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 200);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(2, 0, (BOB, 400), Some((BOB, 200))).accept_bid,
 			true
@@ -84,18 +147,17 @@ fn reverse_collateral_auction_work() {
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 50);
 		assert_eq!(Tokens::balance(BTC, &ALICE), 1050);
 		assert_eq!(Tokens::balance(AUSD, &BOB), 800);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 200);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 200);
 	});
 }
 
 #[test]
 fn on_auction_ended_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 100, 200, 90);
+		new_collateral_auction_from_treasury(100, 200, 90);
 		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 100);
 		assert_eq!(Tokens::balance(BTC, &BOB), 1000);
 		assert_eq!(Tokens::balance(AUSD, &BOB), 1000);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(1, 0, (BOB, 200), None).accept_bid,
 			true
@@ -108,23 +170,185 @@ fn on_auction_ended_work() {
 }
 
 #[test]
-fn on_finalize_work() {
+fn on_auction_ended_without_bid_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		new_collateral_auction_from_treasury(100, 200, 90);
+		AuctionManagerModule::on_auction_ended(0, None);
+		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+		assert_eq!(AuctionManagerModule::auctions(0), None);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+	});
+}
+
+#[test]
+fn collateral_auction_payment_offset_bad_debt_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		AuctionManagerModule::new_collateral_auction(ALICE, BTC, 100, 200, 150);
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 150);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
-		AuctionManagerModule::on_finalize(1);
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 150);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
+		new_collateral_auction_from_treasury(100, 200, 150);
 		assert_eq!(
 			AuctionManagerModule::on_new_bid(2, 0, (BOB, 100), None).accept_bid,
 			true
 		);
 		AuctionManagerModule::on_auction_ended(0, Some((BOB, 100)));
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 150);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 100);
-		AuctionManagerModule::on_finalize(2);
-		assert_eq!(AuctionManagerModule::bad_debt_pool(), 50);
-		assert_eq!(AuctionManagerModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 150);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 100);
+		CdpTreasuryModule::on_finalize(2);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 50);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+	});
+}
+
+#[test]
+fn surplus_auction_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(AuctionManagerModule::new_surplus_auction(100), None);
+		assert_ok!(CdpTreasuryModule::on_system_surplus(100));
+		assert_eq!(AuctionManagerModule::new_surplus_auction(100), Some(0));
+		assert_eq!(AuctionManagerModule::new_surplus_auction(1), None);
+		assert_eq!(AuctionManagerModule::total_surplus_in_auction(), 100);
+
+		assert_eq!(AuctionManagerModule::on_new_bid(1, 0, (BOB, 0), None).accept_bid, false);
+		assert_eq!(AuctionManagerModule::on_new_bid(1, 0, (BOB, 10), None).accept_bid, true);
+		assert_eq!(Tokens::balance(ACA, &BOB), 990);
+		assert_eq!(
+			AuctionManagerModule::on_new_bid(2, 0, (ALICE, 20), Some((BOB, 10))).accept_bid,
+			true
+		);
+		assert_eq!(Tokens::balance(ACA, &BOB), 1000);
+		assert_eq!(Tokens::balance(ACA, &ALICE), 980);
+		assert_eq!(Tokens::balance(ACA, &AuctionManagerModule::sub_account_id(ACA)), 20);
+
+		AuctionManagerModule::on_auction_ended(0, Some((ALICE, 20)));
+		assert_eq!(Tokens::balance(ACA, &AuctionManagerModule::sub_account_id(ACA)), 0);
+		assert_eq!(Tokens::balance(AUSD, &ALICE), 1100);
+		assert_eq!(AuctionManagerModule::total_surplus_in_auction(), 0);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+	});
+}
+
+#[test]
+fn debit_auction_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(AuctionManagerModule::new_debit_auction(200, 100), None);
+		assert_ok!(CdpTreasuryModule::on_system_debit(100));
+		assert_eq!(AuctionManagerModule::new_debit_auction(200, 100), Some(0));
+		assert_eq!(AuctionManagerModule::new_debit_auction(200, 1), None);
+		assert_eq!(AuctionManagerModule::total_debit_in_auction(), 100);
+
+		assert_eq!(
+			AuctionManagerModule::on_new_bid(1, 0, (BOB, 99), None).accept_bid,
+			false
+		);
+		assert_eq!(
+			AuctionManagerModule::on_new_bid(1, 0, (BOB, 100), None).accept_bid,
+			true
+		);
+		assert_eq!(Tokens::balance(AUSD, &BOB), 900);
+		assert_eq!(
+			AuctionManagerModule::on_new_bid(2, 0, (ALICE, 200), Some((BOB, 100))).accept_bid,
+			true
+		);
+		assert_eq!(Tokens::balance(AUSD, &BOB), 1000);
+		assert_eq!(Tokens::balance(AUSD, &ALICE), 900);
+		assert_eq!(Tokens::balance(AUSD, &AuctionManagerModule::sub_account_id(AUSD)), 100);
+		assert_eq!(
+			AuctionManagerModule::debit_auctions(0).map(|item| item.amount),
+			Some(100)
+		);
+
+		AuctionManagerModule::on_auction_ended(0, Some((ALICE, 200)));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 1100);
+		assert_eq!(AuctionManagerModule::total_debit_in_auction(), 0);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 100);
+		CdpTreasuryModule::on_finalize(3);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+	});
+}
+
+#[test]
+fn emergency_cancel_all_drain_across_blocks_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(AuctionManagerModule::set_maximum_auction_size(Origin::ROOT, BTC, 4));
+		new_collateral_auction_from_treasury(10, 100, 90);
+		assert_ok!(CdpTreasuryModule::on_system_surplus(100));
+		AuctionManagerModule::new_surplus_auction(100);
+		AuctionManagerModule::new_debit_auction(200, 90);
+		assert_eq!(AuctionManagerModule::active_auction_count(), 5);
+
+		// mock shutdown driver, cancel at most 2 auctions each block
+		let mut cancelled_per_block = vec![];
+		while AuctionManagerModule::active_auction_count() > 0 {
+			cancelled_per_block.push(AuctionManagerModule::emergency_cancel_all(2));
+		}
+		assert_eq!(cancelled_per_block, vec![2, 2, 1]);
+		assert_eq!(AuctionManagerModule::emergency_cancel_all(2), 0);
+		assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+		assert_eq!(AuctionManagerModule::total_surplus_in_auction(), 0);
+		assert_eq!(AuctionManagerModule::total_debit_in_auction(), 0);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 10);
+	});
+}
+
+#[test]
+fn on_runtime_upgrade_moves_funds_to_sub_accounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		// written before the storage is versioned, with everything held by the module account
+		let module_account = AuctionManagerModule::account_id();
+		assert_eq!(new_collateral_auction_from_treasury(10, 100, 90), vec![0]);
+		assert_ok!(CdpTreasuryModule::on_system_surplus(100));
+		assert_eq!(AuctionManagerModule::new_surplus_auction(100), Some(1));
+		assert_eq!(AuctionManagerModule::on_new_bid(1, 1, (BOB, 20), None).accept_bid, true);
+		for &currency_id in &[BTC, ACA] {
+			let sub_account = AuctionManagerModule::sub_account_id(currency_id);
+			assert_ok!(<Tokens as MultiCurrency<_>>::transfer(
+				currency_id,
+				&sub_account,
+				&module_account,
+				Tokens::balance(currency_id, &sub_account)
+			));
+		}
+		assert!(!<Version>::exists());
+		assert_eq!(AuctionManagerModule::storage_version(), StorageVersion::V0);
+		assert_eq!(
+			AuctionManagerModule::audit(&[BTC]),
+			Err(vec![
+				InvariantViolation {
+					invariant: "auction manager: collateral held",
+					currency_id: BTC,
+					recorded: 10,
+					actual: 0,
+				},
+				InvariantViolation {
+					invariant: "auction manager: left in module account",
+					currency_id: BTC,
+					recorded: 0,
+					actual: 10,
+				},
+				InvariantViolation {
+					invariant: "auction manager: left in module account",
+					currency_id: ACA,
+					recorded: 0,
+					actual: 20,
+				},
+			])
+		);
+
+		assert!(AuctionManagerModule::on_runtime_upgrade() > STORAGE_ACCESS_WEIGHT * 2);
+		assert_eq!(AuctionManagerModule::storage_version(), StorageVersion::V1);
+		assert_eq!(AuctionManagerModule::storage_version().next(), None);
+		assert_eq!(Tokens::balance(BTC, &AuctionManagerModule::sub_account_id(BTC)), 10);
+		assert_eq!(Tokens::balance(ACA, &AuctionManagerModule::sub_account_id(ACA)), 20);
+		assert_eq!(Tokens::balance(BTC, &module_account), 0);
+		assert_eq!(Tokens::balance(ACA, &module_account), 0);
+		assert_eq!(AuctionManagerModule::audit(&[BTC]), Ok(()));
+
+		// nothing pending, only the version is read
+		assert_eq!(AuctionManagerModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT);
+
+		// the migrated funds are released from the sub-accounts
+		AuctionManagerModule::on_auction_ended(0, Some((BOB, 100)));
+		AuctionManagerModule::on_auction_ended(1, Some((BOB, 20)));
+		assert_eq!(Tokens::balance(BTC, &BOB), 1010);
+		assert_eq!(Tokens::balance(BTC, &AuctionManagerModule::sub_account_id(BTC)), 0);
+		assert_eq!(Tokens::balance(ACA, &AuctionManagerModule::sub_account_id(ACA)), 0);
 	});
 }
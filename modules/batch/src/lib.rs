@@ -0,0 +1,79 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{decl_error, decl_event, decl_module, ensure, traits::Get, Parameter};
+use rstd::prelude::*;
+use sp_runtime::{traits::Dispatchable, DispatchError};
+use system::ensure_signed;
+
+mod mock;
+mod tests;
+
+/// Checks a batch before any of its calls is dispatched.
+///
+/// The pinned substrate has no storage transactions, the writes of the calls dispatched before a failing call are
+/// kept. So a batch is only all-or-nothing if every call the checker accepts succeeds when dispatched after the
+/// calls before it.
+pub trait BatchChecker<AccountId, Call> {
+	/// `Err((index, error))` if the call at `index` is refused or would fail after the calls before it.
+	fn check_batch(who: &AccountId, calls: &[Call]) -> Result<(), (u32, DispatchError)>;
+}
+
+pub trait Trait: system::Trait {
+	type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+	type Call: Parameter + Dispatchable<Origin = Self::Origin>;
+	type BatchChecker: BatchChecker<Self::AccountId, <Self as Trait>::Call>;
+	/// The most calls a batch can hold.
+	type MaxBatchSize: Get<u32>;
+}
+
+decl_event!(
+	pub enum Event {
+		/// All the calls of the batch succeeded.
+		BatchCompleted,
+		/// The batch was refused by the checker before any call was dispatched (index, error)
+		BatchRefused(u32, DispatchError),
+		/// A call accepted by the checker failed, the calls before it were dispatched (index, error)
+		BatchInterrupted(u32, DispatchError),
+	}
+);
+
+decl_error! {
+	/// Error for batch module.
+	pub enum Error for Module<T: Trait> {
+		InvalidBatchSize,
+		CallNotAllowed,
+		BalanceTooLow,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Dispatch `calls` in order only if the checker accepts all of them, nothing is dispatched otherwise.
+		pub fn batch_atomic(origin, calls: Vec<<T as Trait>::Call>) {
+			let who = ensure_signed(origin.clone())?;
+			ensure!(
+				!calls.is_empty() && calls.len() <= T::MaxBatchSize::get() as usize,
+				Error::<T>::InvalidBatchSize,
+			);
+
+			if let Err((index, error)) = T::BatchChecker::check_batch(&who, &calls) {
+				Self::deposit_event(Event::BatchRefused(index, error.clone()));
+				return Err(error);
+			}
+
+			for (index, call) in calls.into_iter().enumerate() {
+				if let Err(error) = call.dispatch(origin.clone()) {
+					let error: DispatchError = error.into();
+					Self::deposit_event(Event::BatchInterrupted(index as u32, error.clone()));
+					return Err(error);
+				}
+			}
+
+			Self::deposit_event(Event::BatchCompleted);
+		}
+	}
+}
@@ -0,0 +1,93 @@
+//! Unit tests for the batch module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{BatchModule, Call, ExtBuilder, Origin, Runtime, System, TestEvent, ALICE};
+
+fn remark(data: Vec<u8>) -> Call {
+	Call::System(system::Call::remark(data))
+}
+
+fn last_event() -> TestEvent {
+	System::events().pop().expect("an event was deposited").event
+}
+
+#[test]
+fn batch_atomic_dispatches_all_calls() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(BatchModule::batch_atomic(
+			Origin::signed(ALICE),
+			vec![remark(vec![1]), remark(vec![2])]
+		));
+		assert_eq!(last_event(), TestEvent::batch(Event::BatchCompleted));
+	});
+}
+
+#[test]
+fn batch_atomic_requires_signed_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			BatchModule::batch_atomic(Origin::ROOT, vec![remark(vec![1])]),
+			DispatchError::BadOrigin,
+		);
+	});
+}
+
+#[test]
+fn batch_atomic_bounds_the_batch_size() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			BatchModule::batch_atomic(Origin::signed(ALICE), vec![]),
+			Error::<Runtime>::InvalidBatchSize,
+		);
+		assert_noop!(
+			BatchModule::batch_atomic(Origin::signed(ALICE), vec![remark(vec![1]); 4]),
+			Error::<Runtime>::InvalidBatchSize,
+		);
+		assert_ok!(BatchModule::batch_atomic(
+			Origin::signed(ALICE),
+			vec![remark(vec![1]); 3]
+		));
+	});
+}
+
+#[test]
+fn batch_atomic_dispatches_nothing_when_refused() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		// the root call before the refused one isn't dispatched, it would interrupt the batch
+		assert_eq!(
+			BatchModule::batch_atomic(
+				Origin::signed(ALICE),
+				vec![Call::System(system::Call::set_heap_pages(1)), remark(vec![])]
+			),
+			Err(Error::<Runtime>::CallNotAllowed.into())
+		);
+		assert_eq!(
+			last_event(),
+			TestEvent::batch(Event::BatchRefused(1, Error::<Runtime>::CallNotAllowed.into()))
+		);
+		assert_eq!(System::events().len(), 1);
+	});
+}
+
+#[test]
+fn batch_atomic_reports_the_call_interrupting_it() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_eq!(
+			BatchModule::batch_atomic(
+				Origin::signed(ALICE),
+				vec![remark(vec![1]), Call::System(system::Call::set_heap_pages(1))]
+			),
+			Err(DispatchError::BadOrigin)
+		);
+		assert_eq!(
+			last_event(),
+			TestEvent::batch(Event::BatchInterrupted(1, DispatchError::BadOrigin))
+		);
+	});
+}
@@ -1,19 +1,39 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use codec::{Decode, Encode};
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::{EnsureOrigin, Get},
+	weights::SimpleDispatchInfo,
+};
 use orml_traits::{arithmetic::Signed, MultiCurrency, MultiCurrencyExtended, PriceProvider};
 use orml_utilities::FixedU128;
-use rstd::{convert::TryInto, marker, prelude::*, result};
-use sp_runtime::traits::{Bounded, CheckedAdd, CheckedSub, Convert};
-use support::{AuctionManager, ExchangeRate, Price, Rate, Ratio, RiskManager};
-use system::ensure_root;
+use primitives::offchain::StorageKind;
+use rstd::{convert::TryInto, prelude::*};
+use sp_runtime::{
+	traits::{Bounded, CheckedAdd, CheckedSub, Convert, One, Saturating, ValidateUnsigned, Zero},
+	transaction_validity::{InvalidTransaction, TransactionValidity},
+	DispatchError, DispatchResult, RuntimeDebug,
+};
+use support::{
+	unsigned_keeper_transaction, unsigned_urgent_priority, AuctionManager, CDPTreasury, DebitConvertor, DexManager,
+	ExchangeRate, FixedU128Ext, OnEmergencyShutdown, Position, Price, PriceProviderExtended, Rate, Ratio, RiskManager,
+};
+use system::{ensure_none, offchain::SubmitUnsignedTransaction};
+use vaults::PositionOf;
 
 mod debit_exchange_rate_convertor;
 pub use debit_exchange_rate_convertor::DebitExchangeRateConvertor;
 
+mod stability_fee_model;
+pub use stability_fee_model::{KinkedStabilityFee, StabilityFeeModel};
+
 mod mock;
 mod tests;
 
+/// The key of the offchain worker's cursor of the positions to check next, in the persistent local storage.
+const OFFCHAIN_LIQUIDATION_CURSOR: &[u8] = b"acala/cdp-engine/liquidation-cursor";
+
 type BalanceOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
 type CurrencyIdOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
 type DebitBalanceOf<T> =
@@ -22,22 +42,72 @@ type AmountOf<T> = <<T as vaults::Trait>::Currency as MultiCurrencyExtended<<T a
 type DebitAmountOf<T> =
 	<<T as vaults::Trait>::DebitCurrency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
 
+/// A position with the values derived from it, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PositionInfo<Balance, DebitBalance> {
+	pub collateral: Balance,
+	pub debit: DebitBalance,
+	pub debit_value: Balance,
+	/// `None` if the price of collateral is unavailable.
+	pub collateral_ratio: Option<Ratio>,
+	pub liquidation_ratio: Ratio,
+	/// `None` if the position has no collateral.
+	pub liquidation_price: Option<Price>,
+}
+
+/// The positions of collateral `currency_id` all together, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct CollateralInfo<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	pub total_collateral: Balance,
+	pub total_debit_value: Balance,
+	/// `None` if the price of collateral is unavailable.
+	pub total_collateral_value: Option<Balance>,
+	/// `Ratio::max_value()` without debit, `None` if the price of collateral is unavailable.
+	pub collateral_ratio: Option<Ratio>,
+}
+
+/// The positions of all the collaterals, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct SystemInfo<CurrencyId, Balance> {
+	pub collaterals: Vec<CollateralInfo<CurrencyId, Balance>>,
+	pub total_debit_value: Balance,
+	/// Total collateral value over total debit value, `Ratio::max_value()` without debit. `None` if the price of any
+	/// collateral is unavailable.
+	pub collateral_ratio: Option<Ratio>,
+}
+
 pub trait Trait: system::Trait + vaults::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 	type AuctionManagerHandler: AuctionManager<
 		Self::AccountId,
 		CurrencyId = CurrencyIdOf<Self>,
 		Balance = BalanceOf<Self>,
-		Amount = AmountOf<Self>,
 	>;
 	type Currency: MultiCurrencyExtended<Self::AccountId>;
 	type PriceSource: PriceProvider<CurrencyIdOf<Self>, FixedU128>;
 	type CollateralCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
 	type GlobalStabilityFee: Get<Rate>;
+	/// The stability fee charged on top of the global and the collateral's own, given the utilization of the debit
+	/// value cap of the collateral.
+	type StabilityFeeModel: StabilityFeeModel<CurrencyIdOf<Self>, BalanceOf<Self>>;
 	type DefaultLiquidationRatio: Get<Ratio>;
 	type DefaulDebitExchangeRate: Get<ExchangeRate>;
 	type MinimumDebitValue: Get<BalanceOf<Self>>;
 	type GetStableCurrencyId: Get<CurrencyIdOf<Self>>;
+	type Treasury: CDPTreasury<Self::AccountId, CurrencyId = CurrencyIdOf<Self>, Balance = BalanceOf<Self>>;
+	/// The dex the collateral is sold on to close positions.
+	type Dex: DexManager<Self::AccountId, CurrencyIdOf<Self>, BalanceOf<Self>>;
+	/// Origin of the calls updating the parameters of the module.
+	type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	/// The max slippage from the feed price the collateral of unsafe positions is sold at with dex.
+	type MaxSlippageSwapWithDEX: Get<Ratio>;
+	/// The runtime call the liquidations of the offchain worker are submitted in.
+	type Call: From<Call<Self>>;
+	/// Submits the liquidations of the offchain worker as unsigned transactions.
+	type SubmitTransaction: SubmitUnsignedTransaction<Self, <Self as Trait>::Call>;
+	/// The most positions the offchain worker checks per block.
+	type MaxPositionsCheckedPerBlock: Get<u32>;
 }
 
 decl_event!(
@@ -47,25 +117,48 @@ decl_event!(
 		CurrencyId = CurrencyIdOf<T>,
 		Balance = BalanceOf<T>,
 	{
+		/// Liquidate the unsafe cdp (currency_id, account, collateral_amount, bad_debt)
 		LiquidateUnsafeCdp(CurrencyId, AccountId, Balance, Balance),
+		/// Settle the debit of cdp after shutdown (currency_id, account, settled_collateral, bad_debt)
+		SettleCdpInDebit(CurrencyId, AccountId, Balance, Balance),
+		/// Close cdp by selling collateral on the dex for its debit (currency_id, account, sold_collateral, debit_value)
+		CloseCdpByDex(CurrencyId, AccountId, Balance, Balance),
+		/// The stability fee of the collateral updated, `None` leaves the global stability fee alone
+		UpdateStabilityFee(CurrencyId, Option<Rate>),
+		/// The liquidation ratio of the collateral updated, `None` falls back to the default liquidation ratio
+		UpdateLiquidationRatio(CurrencyId, Option<Ratio>),
+		/// The liquidation penalty of the collateral updated, `None` for no penalty
+		UpdateLiquidationPenalty(CurrencyId, Option<Rate>),
+		/// The required collateral ratio of the collateral updated, `None` for no requirement
+		UpdateRequiredCollateralRatio(CurrencyId, Option<Ratio>),
+		UpdateMaximumTotalDebitValue(CurrencyId, Balance),
+		UpdateMinimumCollateralAmount(CurrencyId, Balance),
+		/// Borrowing against the collateral is halted (currency_id)
+		CollateralFrozen(CurrencyId),
+		/// Borrowing against the collateral is resumed (currency_id)
+		CollateralThawed(CurrencyId),
 	}
 );
 
 decl_error! {
 	/// Error for cdp engine module.
-	pub enum Error {
+	pub enum Error for Module<T: Trait> {
 		ExceedDebitValueHardCap,
 		DebitAmountConvertFailed,
 		AmountConvertFailed,
 		BelowRequiredCollateralRatio,
 		BelowLiquidationRatio,
 		CollateralRatioStillSafe,
-		UpdatePositionFailed,
 		NotValidCurrencyId,
 		RemainDebitValueTooSmall,
-		GrabCollateralAndDebitFailed,
 		BalanceOverflow,
 		InvalidFeedPrice,
+		AlreadyShutdown,
+		MustAfterShutdown,
+		NoDebitToSettle,
+		BelowMinimumCollateralAmount,
+		ExceedMaxSlippage,
+		CollateralIsFrozen,
 	}
 }
 
@@ -76,12 +169,20 @@ decl_storage! {
 		pub LiquidationPenalty get(fn liquidation_penalty): map CurrencyIdOf<T> => Option<Rate>;
 		pub RequiredCollateralRatio get(fn required_collateral_ratio): map CurrencyIdOf<T> => Option<Ratio>;
 		pub MaximumTotalDebitValue get(fn maximum_total_debit_value): map CurrencyIdOf<T> => BalanceOf<T>;
+		/// The least collateral of a position which isn't empty, positions below it are uneconomical to liquidate.
+		pub MinimumCollateralAmount get(fn minimum_collateral_amount): map CurrencyIdOf<T> => BalanceOf<T>;
 		pub DebitExchangeRate get(fn debit_exchange_rate): map CurrencyIdOf<T> => Option<ExchangeRate>;
+		/// The collaterals which can't be borrowed against or withdrawn from positions with debit, as when their
+		/// price can't be trusted.
+		pub FrozenCollaterals get(fn is_collateral_frozen): map CurrencyIdOf<T> => bool;
+		pub IsShutdown get(fn is_shutdown): bool;
 	}
 }
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
 		fn deposit_event() = default;
 
 		pub fn set_collateral_params(
@@ -92,14 +193,20 @@ decl_module! {
 			liquidation_penalty: Option<Option<Rate>>,
 			required_collateral_ratio: Option<Option<Ratio>>,
 			maximum_total_debit_value: Option<BalanceOf<T>>,
+			minimum_collateral_amount: Option<BalanceOf<T>>,
 		) {
-			ensure_root(origin)?;
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(
+				T::CollateralCurrencyIds::get().contains(&currency_id),
+				Error::<T>::NotValidCurrencyId,
+			);
 			if let Some(update) = stability_fee {
 				if let Some(val) = update {
 					<StabilityFee<T>>::insert(currency_id, val);
 				} else {
 					<StabilityFee<T>>::remove(currency_id);
 				}
+				Self::deposit_event(RawEvent::UpdateStabilityFee(currency_id, update));
 			}
 			if let Some(update) = liquidation_ratio {
 				if let Some(val) = update {
@@ -107,6 +214,7 @@ decl_module! {
 				} else {
 					<LiquidationRatio<T>>::remove(currency_id);
 				}
+				Self::deposit_event(RawEvent::UpdateLiquidationRatio(currency_id, update));
 			}
 			if let Some(update) = liquidation_penalty {
 				if let Some(val) = update {
@@ -114,6 +222,7 @@ decl_module! {
 				} else {
 					<LiquidationPenalty<T>>::remove(currency_id);
 				}
+				Self::deposit_event(RawEvent::UpdateLiquidationPenalty(currency_id, update));
 			}
 			if let Some(update) = required_collateral_ratio {
 				if let Some(val) = update {
@@ -121,59 +230,257 @@ decl_module! {
 				} else {
 					<RequiredCollateralRatio<T>>::remove(currency_id);
 				}
+				Self::deposit_event(RawEvent::UpdateRequiredCollateralRatio(currency_id, update));
 			}
 			if let Some(val) = maximum_total_debit_value {
 				<MaximumTotalDebitValue<T>>::insert(currency_id, val);
+				Self::deposit_event(RawEvent::UpdateMaximumTotalDebitValue(currency_id, val));
+			}
+			if let Some(val) = minimum_collateral_amount {
+				<MinimumCollateralAmount<T>>::insert(currency_id, val);
+				Self::deposit_event(RawEvent::UpdateMinimumCollateralAmount(currency_id, val));
+			}
+		}
+
+		/// Halt or resume borrowing against `currency_id`. Repayments, deposits and liquidations still go through
+		/// while it is frozen, and so does withdrawing the collateral of a position left without debit.
+		pub fn set_collateral_frozen(origin, currency_id: CurrencyIdOf<T>, frozen: bool) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(
+				T::CollateralCurrencyIds::get().contains(&currency_id),
+				Error::<T>::NotValidCurrencyId,
+			);
+			if frozen {
+				<FrozenCollaterals<T>>::insert(currency_id, true);
+				Self::deposit_event(RawEvent::CollateralFrozen(currency_id));
+			} else {
+				<FrozenCollaterals<T>>::remove(currency_id);
+				Self::deposit_event(RawEvent::CollateralThawed(currency_id));
 			}
 		}
 
+		/// Liquidate the unsafe position of `who` in `currency_id`, submitted unsigned by the offchain worker.
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
+		fn liquidate(origin, currency_id: CurrencyIdOf<T>, who: T::AccountId) {
+			ensure_none(origin)?;
+			Self::liquidate_unsafe_cdp(who, currency_id)?;
+		}
+
+		fn offchain_worker(_now: T::BlockNumber) {
+			Self::submit_unsafe_cdp_liquidations();
+		}
+
 		fn on_finalize(_now: T::BlockNumber) {
-			let global_stability_fee = T::GlobalStabilityFee::get();
+			// stop accumulating stability fee after shutdown
+			if Self::is_shutdown() {
+				return;
+			}
+
 			// handle all kinds of collateral type
 			for currency_id in T::CollateralCurrencyIds::get() {
-				let debit_exchange_rate = Self::debit_exchange_rate(currency_id).unwrap_or(T::DefaulDebitExchangeRate::get());
-				let stability_fee_rate = Self::stability_fee(currency_id).unwrap_or(Rate::from_parts(0)).checked_add(&global_stability_fee).unwrap_or(Rate::max_value());
 				let total_debits = <vaults::Module<T>>::total_debits(currency_id);
-				if stability_fee_rate > Rate::from_parts(0) && total_debits > 0.into() {
-					let debit_exchange_rate_increment = debit_exchange_rate.checked_mul(&stability_fee_rate).unwrap_or(ExchangeRate::max_value());
+				let stability_fee_rate = Self::get_stability_fee(currency_id);
+				if total_debits.is_zero() || stability_fee_rate == Rate::from_parts(0) {
+					continue;
+				}
 
-					// update exchange rate
-					let new_debit_exchange_rate = debit_exchange_rate.checked_add(&debit_exchange_rate_increment).unwrap_or(ExchangeRate::max_value());
-					<DebitExchangeRate<T>>::insert(currency_id, new_debit_exchange_rate);
+				// compound the exchange rate by the stability fee
+				let debit_exchange_rate = Self::debit_exchange_rate(currency_id).unwrap_or(T::DefaulDebitExchangeRate::get());
+				let debit_exchange_rate_increment = debit_exchange_rate.checked_mul(&stability_fee_rate).unwrap_or(ExchangeRate::max_value());
+				let new_debit_exchange_rate = debit_exchange_rate.checked_add(&debit_exchange_rate_increment).unwrap_or(ExchangeRate::max_value());
+				let total_debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, total_debits);
+				<DebitExchangeRate<T>>::insert(currency_id, new_debit_exchange_rate);
 
-					// issue stablecoin to surplus pool
-					let total_debit_value = DebitExchangeRateConvertor::<T>::convert((currency_id, total_debits));
-					let issued_stable_coin_balance = debit_exchange_rate_increment.checked_mul_int(&total_debit_value).unwrap_or(BalanceOf::<T>::max_value());
-					T::AuctionManagerHandler::increase_surplus(issued_stable_coin_balance);
-				}
+				// issue the stable coin the debits grew by to surplus pool, the debits are valued rounded up as
+				// when they are repaid, so the issuance adds up to the stability fee repaid
+				let new_total_debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, total_debits);
+				let _ = T::Treasury::on_system_surplus(new_total_debit_value.saturating_sub(total_debit_value));
 			}
 		}
 	}
 }
 
 impl<T: Trait> Module<T> {
+	pub fn get_liquidation_ratio(currency_id: CurrencyIdOf<T>) -> Ratio {
+		Self::liquidation_ratio(currency_id).unwrap_or(T::DefaultLiquidationRatio::get())
+	}
+
+	/// The stability fee of the collateral on top of the global stability fee and the fee of the stability fee
+	/// model at the current utilization of the debit value cap, saturating on overflow.
+	pub fn get_stability_fee(currency_id: CurrencyIdOf<T>) -> Rate {
+		let model_fee = T::StabilityFeeModel::fee(
+			currency_id,
+			Self::total_debit_value(currency_id),
+			Self::maximum_total_debit_value(currency_id),
+		);
+
+		Self::stability_fee(currency_id)
+			.unwrap_or(Rate::from_parts(0))
+			.checked_add(&T::GlobalStabilityFee::get())
+			.and_then(|fee| fee.checked_add(&model_fee))
+			.unwrap_or(Rate::max_value())
+	}
+
+	/// The collateral ratio of a position, `Ratio::max_value()` if the position has no debit.
 	pub fn calculate_collateral_ratio(
 		currency_id: CurrencyIdOf<T>,
 		collateral_balance: BalanceOf<T>,
 		debit_balance: DebitBalanceOf<T>,
 		price: Price,
 	) -> Ratio {
-		let locked_collateral_value = TryInto::<u128>::try_into(
-			price
-				.checked_mul_int(&collateral_balance)
-				.unwrap_or(BalanceOf::<T>::max_value()),
+		let locked_collateral_value = price
+			.checked_mul_int(&collateral_balance)
+			.unwrap_or(BalanceOf::<T>::max_value());
+		let debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
+
+		Ratio::checked_from_rational(locked_collateral_value, debit_value).unwrap_or_else(Ratio::max_value)
+	}
+
+	/// The price of collateral in stable coin at which the position reaches the liquidation ratio,
+	/// `None` if the position has no collateral.
+	pub fn calculate_liquidation_price(
+		currency_id: CurrencyIdOf<T>,
+		collateral_balance: BalanceOf<T>,
+		debit_balance: DebitBalanceOf<T>,
+	) -> Option<Price> {
+		let debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
+		let liquidation_value = Self::get_liquidation_ratio(currency_id).checked_mul_int(&debit_value)?;
+
+		Price::checked_from_rational(liquidation_value, collateral_balance)
+	}
+
+	/// The position of `who` in `currency_id` at the current price and parameters.
+	pub fn position_info(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+	) -> PositionInfo<BalanceOf<T>, DebitBalanceOf<T>> {
+		let Position { collateral, debit } = <vaults::Module<T>>::positions(who, currency_id);
+		let collateral_ratio =
+			<T as Trait>::PriceSource::get_relative_price(T::GetStableCurrencyId::get(), currency_id)
+				.map(|price| Self::calculate_collateral_ratio(currency_id, collateral, debit, price));
+
+		PositionInfo {
+			collateral,
+			debit,
+			debit_value: DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit),
+			collateral_ratio,
+			liquidation_ratio: Self::get_liquidation_ratio(currency_id),
+			liquidation_price: Self::calculate_liquidation_price(currency_id, collateral, debit),
+		}
+	}
+
+	/// The stable coin all the positions of `currency_id` owe, from the total debits the vaults keep up to date.
+	pub fn total_debit_value(currency_id: CurrencyIdOf<T>) -> BalanceOf<T> {
+		DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(
+			currency_id,
+			<vaults::Module<T>>::total_debits(currency_id),
 		)
-		.unwrap_or(u128::max_value());
-		let debit_value =
-			TryInto::<u128>::try_into(DebitExchangeRateConvertor::<T>::convert((currency_id, debit_balance)))
-				.unwrap_or(u128::max_value());
+	}
+
+	/// The positions of collateral `currency_id` all together, at the current price and exchange rate.
+	pub fn collateral_info(currency_id: CurrencyIdOf<T>) -> CollateralInfo<CurrencyIdOf<T>, BalanceOf<T>> {
+		let total_collateral = <vaults::Module<T>>::total_collaterals(currency_id);
+		let total_debits = <vaults::Module<T>>::total_debits(currency_id);
+		let feed_price = <T as Trait>::PriceSource::get_relative_price(T::GetStableCurrencyId::get(), currency_id);
+
+		CollateralInfo {
+			currency_id,
+			total_collateral,
+			total_debit_value: Self::total_debit_value(currency_id),
+			total_collateral_value: feed_price.and_then(|price| price.checked_mul_int(&total_collateral)),
+			collateral_ratio: feed_price
+				.map(|price| Self::calculate_collateral_ratio(currency_id, total_collateral, total_debits, price)),
+		}
+	}
+
+	/// The positions of all the collaterals, at the current prices and exchange rates.
+	pub fn system_info() -> SystemInfo<CurrencyIdOf<T>, BalanceOf<T>> {
+		let collaterals: Vec<_> = T::CollateralCurrencyIds::get()
+			.into_iter()
+			.map(Self::collateral_info)
+			.collect();
+		let total_debit_value = collaterals.iter().fold(Zero::zero(), |total: BalanceOf<T>, info| {
+			total.saturating_add(info.total_debit_value)
+		});
+		let collateral_ratio = collaterals
+			.iter()
+			.try_fold(Zero::zero(), |total: BalanceOf<T>, info| {
+				info.total_collateral_value.map(|value| total.saturating_add(value))
+			})
+			.map(|total_collateral_value| {
+				Ratio::checked_from_rational(total_collateral_value, total_debit_value).unwrap_or_else(Ratio::max_value)
+			});
+
+		SystemInfo {
+			collaterals,
+			total_debit_value,
+			collateral_ratio,
+		}
+	}
+
+	/// The largest debit `who` can borrow more in `currency_id` right now, an adjustment of exactly it passes the
+	/// validation of `update_position` and one more doesn't. Zero if the feed price is missing, the debit value cap
+	/// is reached, or even the smallest debit allowed can't be borrowed.
+	///
+	/// The ratios, the cap and the exchange rate all round, so the largest debit within them is searched for with the
+	/// same checks the adjustment goes through, in at most as many steps as the bits of the debit.
+	pub fn max_issuable_debit(who: &T::AccountId, currency_id: CurrencyIdOf<T>) -> DebitBalanceOf<T> {
+		if Self::ensure_can_update_position(who, currency_id, Zero::zero(), One::one()).is_err() {
+			return Zero::zero();
+		}
+
+		// the checks which fail once the debit is too large, the remaining debit value is only too small
+		let within_limits = |debit_balance: DebitBalanceOf<T>| -> bool {
+			let debit_amount = match TryInto::<DebitAmountOf<T>>::try_into(debit_balance) {
+				Ok(amount) => amount,
+				Err(_) => return false,
+			};
+			let adjustment_within_ratios =
+				match Self::check_position_adjustment(who, currency_id, Zero::zero(), debit_amount) {
+					Ok(()) => true,
+					Err(e) => e == DispatchError::from(Error::<T>::RemainDebitValueTooSmall),
+				};
+			adjustment_within_ratios && Self::check_debit_cap(currency_id, debit_amount).is_ok()
+		};
+
+		let mut low: DebitBalanceOf<T> = Zero::zero();
+		let mut high =
+			TryInto::<DebitBalanceOf<T>>::try_into(DebitAmountOf::<T>::max_value()).unwrap_or_else(|_| Zero::zero());
+		while low < high {
+			let mid = low + (high - low + One::one()) / 2.into();
+			if within_limits(mid) {
+				low = mid;
+			} else {
+				high = mid - One::one();
+			}
+		}
 
-		Ratio::from_rational(locked_collateral_value, debit_value)
+		match TryInto::<DebitAmountOf<T>>::try_into(low) {
+			Ok(debit_amount)
+				if !low.is_zero()
+					&& Self::check_position_update(who, currency_id, Zero::zero(), debit_amount).is_ok() =>
+			{
+				low
+			}
+			_ => Zero::zero(),
+		}
+	}
+
+	/// A position is unsafe when its collateral ratio is below the liquidation ratio,
+	/// a position without debit is always safe.
+	pub fn is_cdp_unsafe(
+		currency_id: CurrencyIdOf<T>,
+		collateral_balance: BalanceOf<T>,
+		debit_balance: DebitBalanceOf<T>,
+		price: Price,
+	) -> bool {
+		let collateral_ratio = Self::calculate_collateral_ratio(currency_id, collateral_balance, debit_balance, price);
+		Self::get_liquidation_ratio(currency_id).is_more_than(&collateral_ratio)
 	}
 
 	pub fn exceed_debit_value_cap(currency_id: CurrencyIdOf<T>, debit_balance: DebitBalanceOf<T>) -> bool {
 		let hard_cap = Self::maximum_total_debit_value(currency_id);
-		let issue = DebitExchangeRateConvertor::<T>::convert((currency_id, debit_balance));
+		let issue = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
 		issue > hard_cap
 	}
 
@@ -182,65 +489,390 @@ impl<T: Trait> Module<T> {
 		currency_id: CurrencyIdOf<T>,
 		collateral_adjustment: AmountOf<T>,
 		debit_adjustment: DebitAmountOf<T>,
-	) -> result::Result<(), Error> {
+	) -> DispatchResult {
+		Self::ensure_can_update_position(&who, currency_id, collateral_adjustment, debit_adjustment)?;
+		<vaults::Module<T>>::update_position(who, currency_id, collateral_adjustment, debit_adjustment)
+	}
+
+	/// Ensure the position of `who` can be adjusted by `update_position`, the balances it moves are left unchecked.
+	pub fn check_position_update(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		collateral_adjustment: AmountOf<T>,
+		debit_adjustment: DebitAmountOf<T>,
+	) -> DispatchResult {
+		Self::ensure_can_update_position(who, currency_id, collateral_adjustment, debit_adjustment)?;
+		<vaults::Module<T>>::check_position_update(who, currency_id, collateral_adjustment, debit_adjustment)
+	}
+
+	fn ensure_can_update_position(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		collateral_adjustment: AmountOf<T>,
+		debit_adjustment: DebitAmountOf<T>,
+	) -> DispatchResult {
 		ensure!(
 			T::CollateralCurrencyIds::get().contains(&currency_id),
-			Error::NotValidCurrencyId,
+			Error::<T>::NotValidCurrencyId,
 		);
-		<vaults::Module<T>>::update_position(who, currency_id, collateral_adjustment, debit_adjustment)
-			.map_err(|_| Error::UpdatePositionFailed)?;
-
+		// only the remaining collateral of settled cdp can be withdrawn after shutdown
+		if Self::is_shutdown() {
+			ensure!(
+				debit_adjustment.is_zero()
+					&& !collateral_adjustment.is_positive()
+					&& <vaults::Module<T>>::debits(who, currency_id).is_zero(),
+				Error::<T>::AlreadyShutdown,
+			);
+		}
 		Ok(())
 	}
 
-	// TODO: how to trigger cdp liquidation
-	pub fn liquidate_unsafe_cdp(who: T::AccountId, currency_id: CurrencyIdOf<T>) -> result::Result<(), Error> {
-		let debit_balance = <vaults::Module<T>>::debits(&who, currency_id);
-		let collateral_balance: BalanceOf<T> = <vaults::Module<T>>::collaterals(&who, currency_id);
+	/// Close the position of `who` by selling at most `max_collateral_to_sell` of its collateral on the dex for the
+	/// stable coin its debit is worth, the rest of the collateral goes back to `who`. The collateral is sold from
+	/// the vaults account before the position changes, so nothing changes if the dex can't cover the debit.
+	pub fn close_position_by_dex(
+		who: T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		max_collateral_to_sell: BalanceOf<T>,
+	) -> DispatchResult {
+		ensure!(
+			T::CollateralCurrencyIds::get().contains(&currency_id),
+			Error::<T>::NotValidCurrencyId,
+		);
+		ensure!(!Self::is_shutdown(), Error::<T>::AlreadyShutdown);
+		let Position {
+			collateral: collateral_balance,
+			debit: debit_balance,
+		} = <vaults::Module<T>>::positions(&who, currency_id);
 
-		// ensure the cdp is unsafe
-		let feed_price = <T as Trait>::PriceSource::get_price(T::GetStableCurrencyId::get(), currency_id)
-			.ok_or(Error::InvalidFeedPrice)?;
-		let collateral_ratio =
-			Self::calculate_collateral_ratio(currency_id, collateral_balance, debit_balance, feed_price);
-		let liquidation_ratio = if let Some(ratio) = Self::liquidation_ratio(currency_id) {
-			ratio
+		// rounded up so the stable coin received always covers the debit
+		let debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
+		let vaults_account = <vaults::Module<T>>::account_id();
+		let sold_collateral = if debit_value.is_zero() {
+			Zero::zero()
 		} else {
-			T::DefaultLiquidationRatio::get()
+			<T as Trait>::Dex::exchange_currency_with_exact_target(
+				vaults_account.clone(),
+				(currency_id, rstd::cmp::min(max_collateral_to_sell, collateral_balance)),
+				(T::GetStableCurrencyId::get(), debit_value),
+			)?
 		};
-		ensure!(collateral_ratio < liquidation_ratio, Error::CollateralRatioStillSafe);
+		<T as vaults::Trait>::Currency::transfer(T::GetStableCurrencyId::get(), &vaults_account, &who, debit_value)?;
+		let sold_amount =
+			TryInto::<AmountOf<T>>::try_into(sold_collateral).map_err(|_| Error::<T>::AmountConvertFailed)?;
+		<vaults::Module<T>>::update_collaterals_and_debits(who.clone(), currency_id, -sold_amount, Zero::zero())?;
+
+		// repay the debit with the stable coin and withdraw the collateral left
+		let collateral_amount = TryInto::<AmountOf<T>>::try_into(collateral_balance - sold_collateral)
+			.map_err(|_| Error::<T>::AmountConvertFailed)?;
+		let debit_amount =
+			TryInto::<DebitAmountOf<T>>::try_into(debit_balance).map_err(|_| Error::<T>::AmountConvertFailed)?;
+		<vaults::Module<T>>::update_position(who.clone(), currency_id, -collateral_amount, -debit_amount)?;
+
+		Self::deposit_event(RawEvent::CloseCdpByDex(currency_id, who, sold_collateral, debit_value));
+
+		Ok(())
+	}
+
+	/// Ensure the position of `who` in `currency_id` is below the liquidation ratio at the feed price, returns the
+	/// position and the feed price.
+	fn ensure_cdp_unsafe(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+	) -> Result<(PositionOf<T>, Price), DispatchError> {
+		ensure!(!Self::is_shutdown(), Error::<T>::AlreadyShutdown);
+
+		let position = <vaults::Module<T>>::positions(who, currency_id);
+		let feed_price = <T as Trait>::PriceSource::get_relative_price(T::GetStableCurrencyId::get(), currency_id)
+			.ok_or(Error::<T>::InvalidFeedPrice)?;
+		ensure!(
+			Self::is_cdp_unsafe(currency_id, position.collateral, position.debit, feed_price),
+			Error::<T>::CollateralRatioStillSafe
+		);
+
+		Ok((position, feed_price))
+	}
+
+	/// Confiscate enough of the unsafe position of `who` in `currency_id` to bring it back to the required collateral
+	/// ratio, see `calculate_liquidation_amounts`, and cover the debit confiscated plus the liquidation penalty by
+	/// selling the collateral with dex if the slippage is acceptable, the rest of the collateral confiscated is left
+	/// for the owner. Otherwise the collateral is handed over to the treasury and sold in a collateral auction.
+	pub fn liquidate_unsafe_cdp(who: T::AccountId, currency_id: CurrencyIdOf<T>) -> DispatchResult {
+		let (
+			Position {
+				collateral: collateral_balance,
+				debit: debit_balance,
+			},
+			feed_price,
+		) = Self::ensure_cdp_unsafe(&who, currency_id)?;
+		let (liquidated_collateral, liquidated_debit) =
+			Self::calculate_liquidation_amounts(currency_id, collateral_balance, debit_balance, feed_price);
 
 		// grab collaterals and debits from unsafe cdp
-		let grab_amount =
-			TryInto::<AmountOf<T>>::try_into(collateral_balance).map_err(|_| Error::AmountConvertFailed)?;
-		let grab_debit_amount =
-			TryInto::<DebitAmountOf<T>>::try_into(debit_balance).map_err(|_| Error::AmountConvertFailed)?;
-		<vaults::Module<T>>::update_collaterals_and_debits(who.clone(), currency_id, -grab_amount, -grab_debit_amount)
-			.map_err(|_| Error::GrabCollateralAndDebitFailed)?;
-
-		// create collateral auction
-		let bad_debt = DebitExchangeRateConvertor::<T>::convert((currency_id, debit_balance));
-		let mut target = bad_debt;
-		if let Some(penalty_ratio) = Self::liquidation_penalty(currency_id) {
-			target = target
-				.checked_add(
-					&penalty_ratio
-						.checked_mul_int(&target)
-						.unwrap_or(BalanceOf::<T>::max_value()),
-				)
-				.unwrap_or(BalanceOf::<T>::max_value());
-		}
-		T::AuctionManagerHandler::new_collateral_auction(
+		<vaults::Module<T>>::confiscate_collateral_and_debit(
 			who.clone(),
 			currency_id,
-			collateral_balance,
-			target,
+			liquidated_collateral,
+			liquidated_debit,
+		)?;
+
+		// the bad debt is recorded by the treasury, to be offset by the collateral sold. The collateral is sold for
+		// the debit it covers at the feed price plus the penalty, what it can't cover is left in the debit pool for
+		// the treasury to hand to debit auctions
+		let bad_debt = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, liquidated_debit);
+		T::Treasury::on_system_debit(bad_debt)?;
+
+		let (_, covered_debit_value) = Self::calculate_collateral_to_seize(
 			bad_debt,
+			Self::liquidation_penalty(currency_id).unwrap_or(Rate::from_parts(0)),
+			feed_price,
+			liquidated_collateral,
 		);
+		let target = Self::liquidation_target(currency_id, covered_debit_value);
+		let vaults_account = <vaults::Module<T>>::account_id();
+		match Self::swap_collateral_for_target(currency_id, liquidated_collateral, target, feed_price) {
+			Ok(sold_collateral) => {
+				T::Treasury::deposit_surplus(&vaults_account, target)?;
+				<T as vaults::Trait>::Currency::transfer(
+					currency_id,
+					&vaults_account,
+					&who,
+					liquidated_collateral - sold_collateral,
+				)?;
+			}
+			Err(_) => {
+				T::Treasury::deposit_collateral(&vaults_account, currency_id, liquidated_collateral)?;
+				T::AuctionManagerHandler::new_collateral_auction(
+					who.clone(),
+					currency_id,
+					liquidated_collateral,
+					target,
+				);
+			}
+		}
 		Self::deposit_event(RawEvent::LiquidateUnsafeCdp(
 			currency_id,
 			who,
+			liquidated_collateral,
+			bad_debt,
+		));
+
+		Ok(())
+	}
+
+	/// The debit value plus the liquidation penalty of the collateral, saturating on overflow.
+	fn liquidation_target(currency_id: CurrencyIdOf<T>, debit_value: BalanceOf<T>) -> BalanceOf<T> {
+		match Self::liquidation_penalty(currency_id) {
+			Some(penalty_ratio) => debit_value.saturating_add(
+				penalty_ratio
+					.checked_mul_int(&debit_value)
+					.unwrap_or_else(Bounded::max_value),
+			),
+			None => debit_value,
+		}
+	}
+
+	/// The collateral to seize at `price` for `debit_value` plus the `penalty` on it, and the debit value it covers.
+	/// All the available collateral is seized if it isn't enough, covering only as much of the debit value as it's
+	/// worth after the penalty, the rest of the debit value is bad debt nothing is seized for.
+	pub fn calculate_collateral_to_seize(
+		debit_value: BalanceOf<T>,
+		penalty: Rate,
+		price: Price,
+		available_collateral: BalanceOf<T>,
+	) -> (BalanceOf<T>, BalanceOf<T>) {
+		let target = Ratio::from_natural(1)
+			.checked_add(&penalty)
+			.and_then(|confiscated_per_debit| confiscated_per_debit.checked_mul_int(&debit_value))
+			.unwrap_or_else(Bounded::max_value);
+		let needed_collateral = Price::checked_from_rational(FixedU128::accuracy(), price.deconstruct())
+			.and_then(|reciprocal| reciprocal.checked_mul_int(&target));
+
+		match needed_collateral {
+			Some(collateral) if collateral <= available_collateral => (collateral, debit_value),
+			_ => {
+				let available_value = price
+					.checked_mul_int(&available_collateral)
+					.unwrap_or_else(Bounded::max_value);
+				let covered_debit_value = Ratio::checked_from_rational(available_value, target)
+					.and_then(|share| share.checked_mul_int(&debit_value))
+					.map_or(debit_value, |value| rstd::cmp::min(value, debit_value));
+				(available_collateral, covered_debit_value)
+			}
+		}
+	}
+
+	/// The collateral and the debit of an unsafe position to liquidate, the debit is just enough to bring the
+	/// position back to the required collateral ratio once the collateral worth it plus the penalty at the feed
+	/// price is confiscated with it. The whole position is liquidated if the required collateral ratio isn't above
+	/// the liquidation ratio, or if the rest would be below the minimum collateral amount or debit value.
+	pub fn calculate_liquidation_amounts(
+		currency_id: CurrencyIdOf<T>,
+		collateral_balance: BalanceOf<T>,
+		debit_balance: DebitBalanceOf<T>,
+		feed_price: Price,
+	) -> (BalanceOf<T>, DebitBalanceOf<T>) {
+		let whole_position = (collateral_balance, debit_balance);
+		let target_ratio = match Self::required_collateral_ratio(currency_id) {
+			Some(ratio) if ratio.is_more_than(&Self::get_liquidation_ratio(currency_id)) => ratio,
+			_ => return whole_position,
+		};
+
+		// the share of the debit to liquidate is
+		// (target ratio - collateral ratio) / (target ratio - 1 - liquidation penalty)
+		let collateral_ratio =
+			Self::calculate_collateral_ratio(currency_id, collateral_balance, debit_balance, feed_price);
+		let penalty = Self::liquidation_penalty(currency_id).unwrap_or(Rate::from_parts(0));
+		let confiscated_per_debit = Ratio::from_natural(1)
+			.checked_add(&penalty)
+			.unwrap_or_else(Ratio::max_value);
+		let liquidated_debit = match Ratio::checked_from_rational(
+			target_ratio.saturating_sub(&collateral_ratio).deconstruct(),
+			target_ratio.saturating_sub(&confiscated_per_debit).deconstruct(),
+		)
+		.and_then(|share| share.checked_mul_int(&debit_balance))
+		{
+			// one more to make up for the rounding down
+			Some(debit) if debit < debit_balance => debit.saturating_add(One::one()),
+			_ => return whole_position,
+		};
+
+		let (liquidated_collateral, _) = Self::calculate_collateral_to_seize(
+			DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, liquidated_debit),
+			penalty,
+			feed_price,
 			collateral_balance,
+		);
+		if liquidated_collateral == collateral_balance {
+			return whole_position;
+		}
+
+		// the rest of the position is left to the owner only if it isn't dust
+		let rest_debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(
+			currency_id,
+			debit_balance - liquidated_debit,
+		);
+		if collateral_balance - liquidated_collateral < Self::minimum_collateral_amount(currency_id)
+			|| rest_debit_value < T::MinimumDebitValue::get()
+		{
+			return whole_position;
+		}
+
+		(liquidated_collateral, liquidated_debit)
+	}
+
+	/// Sell at most `max_supply_amount` of the collateral held by the vaults for exactly `target` stable coin with
+	/// dex, at no more than the max slippage from the feed price. Returns the collateral sold, fails without
+	/// swapping otherwise.
+	fn swap_collateral_for_target(
+		currency_id: CurrencyIdOf<T>,
+		max_supply_amount: BalanceOf<T>,
+		target: BalanceOf<T>,
+		feed_price: Price,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		let stable_currency_id = T::GetStableCurrencyId::get();
+		let supply_amount = <T as Trait>::Dex::get_supply_amount(currency_id, stable_currency_id, target)?;
+		ensure!(supply_amount <= max_supply_amount, Error::<T>::ExceedMaxSlippage);
+
+		// the target must be worth no less than the collateral sold at the feed price, minus the slippage
+		let supply_value = feed_price
+			.checked_mul_int(&supply_amount)
+			.unwrap_or_else(Bounded::max_value);
+		let min_target = supply_value.saturating_sub(
+			T::MaxSlippageSwapWithDEX::get()
+				.checked_mul_int(&supply_value)
+				.unwrap_or_else(Bounded::max_value),
+		);
+		ensure!(target >= min_target, Error::<T>::ExceedMaxSlippage);
+
+		<T as Trait>::Dex::exchange_currency_with_exact_target(
+			<vaults::Module<T>>::account_id(),
+			(currency_id, supply_amount),
+			(stable_currency_id, target),
+		)
+	}
+
+	/// Check the next positions from where the last block stopped, at most `MaxPositionsCheckedPerBlock` of them,
+	/// and submit the liquidation of the unsafe ones as unsigned transactions. The owners are paged through per
+	/// collateral from the one the last block stopped at, those who opened a position since are checked in the next
+	/// sweep.
+	fn submit_unsafe_cdp_liquidations() {
+		let collateral_currency_ids = T::CollateralCurrencyIds::get();
+		let (mut currency_index, mut start) =
+			runtime_io::offchain::local_storage_get(StorageKind::PERSISTENT, OFFCHAIN_LIQUIDATION_CURSOR)
+				.and_then(|cursor| <(u32, Option<T::AccountId>)>::decode(&mut &cursor[..]).ok())
+				.filter(|(currency_index, _)| (*currency_index as usize) < collateral_currency_ids.len())
+				.unwrap_or((0, None));
+		let mut remaining = T::MaxPositionsCheckedPerBlock::get();
+
+		// every collateral is visited at most once per block
+		for _ in 0..collateral_currency_ids.len() {
+			let currency_id = collateral_currency_ids[currency_index as usize];
+			let (owners, next) = <vaults::Module<T>>::position_owners(currency_id, start.take(), remaining);
+			remaining = remaining.saturating_sub(owners.len() as u32);
+			for who in owners {
+				if Self::ensure_cdp_unsafe(&who, currency_id).is_ok() {
+					let _ = T::SubmitTransaction::submit_unsigned(Call::liquidate(currency_id, who));
+				}
+			}
+			if next.is_none() {
+				currency_index = (currency_index + 1) % collateral_currency_ids.len() as u32;
+			}
+			if next.is_some() || remaining.is_zero() {
+				Self::set_offchain_liquidation_cursor(currency_index, next);
+				return;
+			}
+		}
+		Self::set_offchain_liquidation_cursor(currency_index, None);
+	}
+
+	/// Record the collateral and the owner the next block continues from, the first owner if `None`.
+	fn set_offchain_liquidation_cursor(currency_index: u32, owner: Option<T::AccountId>) {
+		runtime_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			OFFCHAIN_LIQUIDATION_CURSOR,
+			&(currency_index, owner).encode(),
+		);
+	}
+
+	/// Settle the debit of cdp after shutdown, the collateral worth the debit at the locked price
+	/// is handed over to the treasury together with the debit, the rest is returned to the owner.
+	pub fn settle_cdp_has_debit(who: T::AccountId, currency_id: CurrencyIdOf<T>) -> DispatchResult {
+		ensure!(Self::is_shutdown(), Error::<T>::MustAfterShutdown);
+		let Position {
+			collateral: collateral_balance,
+			debit: debit_balance,
+		} = <vaults::Module<T>>::positions(&who, currency_id);
+		ensure!(!debit_balance.is_zero(), Error::<T>::NoDebitToSettle);
+
+		let price = <T as Trait>::PriceSource::get_relative_price(T::GetStableCurrencyId::get(), currency_id)
+			.ok_or(Error::<T>::InvalidFeedPrice)?;
+		let bad_debt = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
+		let (settle_collateral, _) =
+			Self::calculate_collateral_to_seize(bad_debt, Rate::from_parts(0), price, collateral_balance);
+
+		// grab all collaterals and debits
+		<vaults::Module<T>>::confiscate_collateral_and_debit(
+			who.clone(),
+			currency_id,
+			collateral_balance,
+			debit_balance,
+		)?;
+
+		let vaults_account = <vaults::Module<T>>::account_id();
+		T::Treasury::deposit_collateral(&vaults_account, currency_id, settle_collateral)?;
+		T::Treasury::on_system_debit(bad_debt)?;
+		<T as vaults::Trait>::Currency::transfer(
+			currency_id,
+			&vaults_account,
+			&who,
+			collateral_balance - settle_collateral,
+		)?;
+
+		Self::deposit_event(RawEvent::SettleCdpInDebit(
+			currency_id,
+			who,
+			settle_collateral,
 			bad_debt,
 		));
 
@@ -248,92 +880,149 @@ impl<T: Trait> Module<T> {
 	}
 }
 
-impl<T: Trait> RiskManager<T::AccountId, CurrencyIdOf<T>, AmountOf<T>, DebitAmountOf<T>> for Module<T> {
-	type Error = Error;
+/// Only the liquidations of the positions which are unsafe right now are accepted, one per position at a time.
+impl<T: Trait> ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		match call {
+			Call::liquidate(currency_id, who) => {
+				let (position, feed_price) =
+					Self::ensure_cdp_unsafe(who, *currency_id).map_err(|_| InvalidTransaction::Stale)?;
+				// the further below the liquidation ratio, the sooner it's liquidated
+				let liquidation_ratio = Self::get_liquidation_ratio(*currency_id);
+				let collateral_ratio =
+					Self::calculate_collateral_ratio(*currency_id, position.collateral, position.debit, feed_price);
+				let urgency = Ratio::checked_from_rational(
+					liquidation_ratio.saturating_sub(&collateral_ratio).deconstruct(),
+					liquidation_ratio.deconstruct(),
+				)
+				.unwrap_or_else(|| Ratio::from_natural(1));
+				Ok(unsigned_keeper_transaction(
+					unsigned_urgent_priority(urgency),
+					(b"cdp_engine/liquidate", who, currency_id),
+				))
+			}
+			_ => Err(InvalidTransaction::Call.into()),
+		}
+	}
+}
 
+impl<T: Trait> OnEmergencyShutdown for Module<T> {
+	fn on_emergency_shutdown() {
+		<IsShutdown>::put(true);
+	}
+}
+
+impl<T: Trait> RiskManager<T::AccountId, CurrencyIdOf<T>, AmountOf<T>, DebitAmountOf<T>> for Module<T> {
 	fn check_position_adjustment(
 		account_id: &T::AccountId,
 		currency_id: CurrencyIdOf<T>,
 		collateral_amount: AmountOf<T>,
 		debit_amount: DebitAmountOf<T>,
-	) -> Result<(), Self::Error> {
-		let mut debit_balance = <vaults::Module<T>>::debits(account_id, currency_id);
-		let mut collateral_balance = <vaults::Module<T>>::collaterals(account_id, currency_id);
+	) -> DispatchResult {
+		let position = <vaults::Module<T>>::positions(account_id, currency_id);
+		let was_empty = position.is_empty();
+		let Position {
+			collateral: mut collateral_balance,
+			debit: mut debit_balance,
+		} = position;
 
 		// calculate new debit balance and collateral balance after position adjustment
 		let collateral_balance_adjustment =
-			TryInto::<BalanceOf<T>>::try_into(collateral_amount.abs()).map_err(|_| Error::AmountConvertFailed)?;
+			TryInto::<BalanceOf<T>>::try_into(collateral_amount.abs()).map_err(|_| Error::<T>::AmountConvertFailed)?;
 		if collateral_amount.is_positive() {
 			collateral_balance = collateral_balance
 				.checked_add(&collateral_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
+				.ok_or(Error::<T>::BalanceOverflow)?;
 		} else {
 			collateral_balance = collateral_balance
 				.checked_sub(&collateral_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
+				.ok_or(Error::<T>::BalanceOverflow)?;
 		}
 
-		let debit_balance_adjustment =
-			TryInto::<DebitBalanceOf<T>>::try_into(debit_amount.abs()).map_err(|_| Error::DebitAmountConvertFailed)?;
+		let debit_balance_adjustment = TryInto::<DebitBalanceOf<T>>::try_into(debit_amount.abs())
+			.map_err(|_| Error::<T>::DebitAmountConvertFailed)?;
 		if debit_amount.is_positive() {
 			debit_balance = debit_balance
 				.checked_add(&debit_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
+				.ok_or(Error::<T>::BalanceOverflow)?;
 		} else {
 			debit_balance = debit_balance
 				.checked_sub(&debit_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
+				.ok_or(Error::<T>::BalanceOverflow)?;
+		}
+
+		// a frozen collateral can't back more debit, the collateral is only released once the debit is repaid
+		if Self::is_collateral_frozen(currency_id) {
+			ensure!(
+				!debit_amount.is_positive() && (!collateral_amount.is_negative() || debit_balance.is_zero()),
+				Error::<T>::CollateralIsFrozen,
+			);
 		}
 
-		let debit_value = DebitExchangeRateConvertor::<T>::convert((currency_id, debit_balance));
+		// the position is left empty or with the minimum collateral, but a position already below the minimum,
+		// as after the minimum is raised, can still be repaid and topped up
+		if !Position::new(collateral_balance, debit_balance).is_empty()
+			&& collateral_balance < Self::minimum_collateral_amount(currency_id)
+		{
+			ensure!(
+				!was_empty && !debit_amount.is_positive() && !collateral_amount.is_negative(),
+				Error::<T>::BelowMinimumCollateralAmount,
+			);
+		}
 
-		if debit_value != 0.into() {
+		let debit_value = DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(currency_id, debit_balance);
+
+		// only the adjustments which borrow more or withdraw collateral are held to the ratios, the rest can only
+		// improve the position, and are allowed even below the liquidation ratio
+		let worsens_ratio = debit_amount.is_positive() || collateral_amount.is_negative();
+		if debit_value != 0.into() && worsens_ratio {
 			// check the required collateral ratio
-			let feed_price = <T as Trait>::PriceSource::get_price(T::GetStableCurrencyId::get(), currency_id)
-				.ok_or(Error::InvalidFeedPrice)?;
+			let feed_price = <T as Trait>::PriceSource::get_relative_price(T::GetStableCurrencyId::get(), currency_id)
+				.ok_or(Error::<T>::InvalidFeedPrice)?;
 			let collateral_ratio =
 				Self::calculate_collateral_ratio(currency_id, collateral_balance, debit_balance, feed_price);
 			if let Some(required_collateral_ratio) = Self::required_collateral_ratio(currency_id) {
 				ensure!(
-					collateral_ratio >= required_collateral_ratio,
-					Error::BelowRequiredCollateralRatio
+					!required_collateral_ratio.is_more_than(&collateral_ratio),
+					Error::<T>::BelowRequiredCollateralRatio
 				);
 			}
 
 			// check the liquidation ratio
-			let liquidation_ratio = if let Some(ratio) = Self::liquidation_ratio(currency_id) {
-				ratio
-			} else {
-				T::DefaultLiquidationRatio::get()
-			};
-			ensure!(collateral_ratio >= liquidation_ratio, Error::BelowLiquidationRatio);
+			ensure!(
+				!Self::get_liquidation_ratio(currency_id).is_more_than(&collateral_ratio),
+				Error::<T>::BelowLiquidationRatio
+			);
+		}
 
-			// check the minimum_debit_value
+		// check the minimum_debit_value
+		if debit_value != 0.into() {
 			ensure!(
 				debit_value >= T::MinimumDebitValue::get(),
-				Error::RemainDebitValueTooSmall,
+				Error::<T>::RemainDebitValueTooSmall,
 			);
 		}
 
 		Ok(())
 	}
 
-	fn check_debit_cap(currency_id: CurrencyIdOf<T>, debit_amount: DebitAmountOf<T>) -> Result<(), Self::Error> {
-		let mut total_debit_balance = <vaults::Module<T>>::total_debits(currency_id);
-		let debit_balance_adjustment =
-			TryInto::<DebitBalanceOf<T>>::try_into(debit_amount.abs()).map_err(|_| Error::DebitAmountConvertFailed)?;
-		if debit_amount.is_positive() {
-			total_debit_balance = total_debit_balance
-				.checked_add(&debit_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
-		} else {
-			total_debit_balance = total_debit_balance
-				.checked_sub(&debit_balance_adjustment)
-				.ok_or(Error::BalanceOverflow)?;
+	fn check_debit_cap(currency_id: CurrencyIdOf<T>, debit_amount: DebitAmountOf<T>) -> DispatchResult {
+		// repayments are never blocked, even once the cap is lowered below the debits outstanding
+		if !debit_amount.is_positive() {
+			return Ok(());
 		}
+
+		// the debits are valued at the exchange rate the stability fee accrued to
+		let debit_balance_adjustment =
+			TryInto::<DebitBalanceOf<T>>::try_into(debit_amount).map_err(|_| Error::<T>::DebitAmountConvertFailed)?;
+		let total_debit_balance = <vaults::Module<T>>::total_debits(currency_id)
+			.checked_add(&debit_balance_adjustment)
+			.ok_or(Error::<T>::BalanceOverflow)?;
 		ensure!(
 			!Self::exceed_debit_value_cap(currency_id, total_debit_balance),
-			Error::ExceedDebitValueHardCap
+			Error::<T>::ExceedDebitValueHardCap
 		);
 
 		Ok(())
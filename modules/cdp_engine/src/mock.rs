@@ -2,17 +2,36 @@
 
 #![cfg(test)]
 
-use frame_support::{impl_outer_origin, parameter_types};
-use primitives::H256;
-use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use frame_support::{impl_outer_event, impl_outer_origin, parameter_types};
+use primitives::{
+	offchain::{
+		testing::{TestOffchainExt, TestTransactionPoolExt},
+		OffchainExt, TransactionPoolExt,
+	},
+	H256,
+};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	DispatchResult, ModuleId, Perbill,
+};
+use std::cell::RefCell;
+use system::offchain::TransactionSubmitter;
 
 use orml_traits::PriceProvider;
-use support::AuctionManager;
+use support::{AuctionManager, AuctionManagerExtended, FixedU128Ext};
 
 use super::*;
 
 mod cdp_engine {
-	pub use crate::Event;
+	pub use super::super::*;
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Runtime {
+		cdp_engine<T>,
+		vaults<T>,
+	}
 }
 
 impl_outer_origin! {
@@ -27,12 +46,26 @@ parameter_types! {
 	pub const TransferFee: u64 = 0;
 	pub const CreationFee: u64 = 2;
 	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
-	pub const GlobalStabilityFee: Rate = Rate::from_parts(0);
-	pub const DefaultLiquidationRatio: Ratio = Ratio::from_rational(3, 2);
+	pub const PositionOwnersToMigrate: Vec<AccountId> = vec![ALICE, BOB, CAROL];
+	pub const GlobalStabilityFee: Rate = Rate::from_percent(0);
+	pub const DefaultLiquidationRatio: Ratio = Ratio::from_percent(150);
 	pub const DefaulDebitExchangeRate: ExchangeRate = ExchangeRate::from_natural(1);
 	pub const MinimumDebitValue: Balance = 2;
 	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
 	pub const GetNativeCurrencyId: CurrencyId = ACA;
+	pub const MaxPositionsCheckedPerBlock: u32 = 2;
+	pub const StabilityFeeKink: Ratio = Ratio::from_percent(50);
+	pub const StabilityFeeSlopeBelowKink: Rate = Rate::from_percent(1);
+	pub const StabilityFeeSlopeAboveKink: Rate = Rate::from_percent(10);
 
 }
 
@@ -41,14 +74,17 @@ pub type BlockNumber = u64;
 pub type Balance = u64;
 pub type DebitBalance = u64;
 pub type Amount = i64;
-pub type CurrencyId = u32;
+pub type Share = u64;
+pub type AuctionId = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
 pub const ALICE: AccountId = 1;
 pub const BOB: AccountId = 2;
+pub const CAROL: AccountId = 3;
 
-pub const ACA: CurrencyId = 0;
-pub const AUSD: CurrencyId = 1;
-pub const BTC: CurrencyId = 2;
-pub const DOT: CurrencyId = 3;
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Runtime;
@@ -63,13 +99,15 @@ impl system::Trait for Runtime {
 	type AccountId = AccountId;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = ();
 }
+pub type System = system::Module<Runtime>;
 
 impl orml_tokens::Trait for Runtime {
 	type Event = ();
@@ -114,56 +152,178 @@ impl debits::Trait for Runtime {
 pub type DebitsCurrency = debits::Module<Runtime>;
 
 impl vaults::Trait for Runtime {
-	type Event = ();
+	type Event = TestEvent;
 	type Convert = DebitExchangeRateConvertor<Runtime>;
 	type Currency = Currencies;
 	type DebitCurrency = DebitsCurrency;
 	type RiskManager = CdpEngineModule;
+	type OnUpdateLoan = ();
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = PositionOwnersToMigrate;
 }
 pub type VaultsModule = vaults::Module<Runtime>;
 
+pub type Extrinsic = TestXt<Call<Runtime>, ()>;
+
+thread_local! {
+	static BTC_PRICE: RefCell<Option<Price>> = RefCell::new(Some(Price::from_natural(1)));
+}
+
 pub struct MockPriceSource;
+impl MockPriceSource {
+	pub fn set_btc_price(price: Option<Price>) {
+		BTC_PRICE.with(|v| *v.borrow_mut() = price);
+	}
+}
 impl PriceProvider<CurrencyId, Price> for MockPriceSource {
-	#[allow(unused_variables)]
 	fn get_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
 		match (base, quote) {
-			(1, 2) => Some(Price::from_natural(1)),
+			(AUSD, BTC) => BTC_PRICE.with(|v| *v.borrow()),
 			_ => None,
 		}
 	}
 }
 
+thread_local! {
+	static KINKED_STABILITY_FEE: RefCell<bool> = RefCell::new(false);
+}
+
+pub type MockKinkedStabilityFee =
+	KinkedStabilityFee<StabilityFeeKink, StabilityFeeSlopeBelowKink, StabilityFeeSlopeAboveKink>;
+
+/// No fee on top of the configured stability fee unless the kinked curve is switched on.
+pub struct MockStabilityFeeModel;
+impl MockStabilityFeeModel {
+	pub fn set_kinked(kinked: bool) {
+		KINKED_STABILITY_FEE.with(|v| *v.borrow_mut() = kinked);
+	}
+}
+impl StabilityFeeModel<CurrencyId, Balance> for MockStabilityFeeModel {
+	fn fee(currency_id: CurrencyId, total_debit_value: Balance, debit_ceiling: Balance) -> Rate {
+		if KINKED_STABILITY_FEE.with(|v| *v.borrow()) {
+			MockKinkedStabilityFee::fee(currency_id, total_debit_value, debit_ceiling)
+		} else {
+			<() as StabilityFeeModel<CurrencyId, Balance>>::fee(currency_id, total_debit_value, debit_ceiling)
+		}
+	}
+}
+
 pub struct MockAuctionManager;
 impl AuctionManager<AccountId> for MockAuctionManager {
 	type CurrencyId = CurrencyId;
 	type Balance = Balance;
-	type Amount = Amount;
-
-	#[allow(unused_variables)]
-	fn increase_surplus(increment: Self::Balance) {}
-
+	type AuctionId = AuctionId;
 	#[allow(unused_variables)]
 	fn new_collateral_auction(
 		who: AccountId,
 		currency_id: Self::CurrencyId,
 		amount: Self::Balance,
 		target: Self::Balance,
-		bad_debt: Self::Balance,
-	) {
+	) -> Vec<Self::AuctionId> {
+		vec![]
+	}
+
+	#[allow(unused_variables)]
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn new_debit_auction(initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn cancel_auction(id: Self::AuctionId) -> DispatchResult {
+		Ok(())
+	}
+
+	#[allow(unused_variables)]
+	fn get_total_collateral_in_auction(currency_id: Self::CurrencyId) -> Self::Balance {
+		0
+	}
+
+	fn get_total_surplus_in_auction() -> Self::Balance {
+		0
+	}
+
+	fn get_total_debit_in_auction() -> Self::Balance {
+		0
 	}
 }
 
-impl Trait for Runtime {
+impl AuctionManagerExtended<AccountId> for MockAuctionManager {
+	fn emergency_cancel_all(_max_per_block: u32) -> u32 {
+		0
+	}
+
+	fn active_auction_count() -> u32 {
+		0
+	}
+}
+
+impl dex::Trait for Runtime {
 	type Event = ();
+	type Currency = Currencies;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+impl cdp_treasury::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = MockAuctionManager;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = DexModule;
+	type PriceSource = MockPriceSource;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = cdp_treasury::Module<Runtime>;
+
+impl Trait for Runtime {
+	type Event = TestEvent;
 	type AuctionManagerHandler = MockAuctionManager;
 	type Currency = Currencies;
 	type PriceSource = MockPriceSource;
 	type CollateralCurrencyIds = CollateralCurrencyIds;
 	type GlobalStabilityFee = GlobalStabilityFee;
+	type StabilityFeeModel = MockStabilityFeeModel;
 	type DefaultLiquidationRatio = DefaultLiquidationRatio;
 	type DefaulDebitExchangeRate = DefaulDebitExchangeRate;
 	type MinimumDebitValue = MinimumDebitValue;
 	type GetStableCurrencyId = GetStableCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type Dex = DexModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type Call = Call<Runtime>;
+	type SubmitTransaction = TransactionSubmitter<(), Call<Runtime>, Extrinsic>;
+	type MaxPositionsCheckedPerBlock = MaxPositionsCheckedPerBlock;
 }
 pub type CdpEngineModule = Module<Runtime>;
 
@@ -177,7 +337,7 @@ impl Default for ExtBuilder {
 	fn default() -> Self {
 		Self {
 			currency_ids: vec![ACA, BTC, DOT],
-			endowed_accounts: vec![ALICE, BOB],
+			endowed_accounts: vec![ALICE, BOB, CAROL],
 			initial_balance: 1000,
 		}
 	}
@@ -185,6 +345,8 @@ impl Default for ExtBuilder {
 
 impl ExtBuilder {
 	pub fn build(self) -> runtime_io::TestExternalities {
+		MockPriceSource::set_btc_price(Some(Price::from_natural(1)));
+		MockStabilityFeeModel::set_kinked(false);
 		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
 
 		orml_tokens::GenesisConfig::<Runtime> {
@@ -197,4 +359,23 @@ impl ExtBuilder {
 
 		t.into()
 	}
+
+	/// Build with the offchain extensions registered, with a function taking the transactions submitted by the
+	/// offchain worker so far.
+	pub fn build_with_offchain(self) -> (runtime_io::TestExternalities, impl Fn() -> Vec<Extrinsic>) {
+		let mut ext = self.build();
+		let (offchain, _) = TestOffchainExt::new();
+		let (pool, pool_state) = TestTransactionPoolExt::new();
+		ext.register_extension(OffchainExt::new(offchain));
+		ext.register_extension(TransactionPoolExt::new(pool));
+		let submitted_transactions = move || {
+			pool_state
+				.write()
+				.transactions
+				.drain(..)
+				.map(|transaction| Extrinsic::decode(&mut &*transaction).unwrap())
+				.collect()
+		};
+		(ext, submitted_transactions)
+	}
 }
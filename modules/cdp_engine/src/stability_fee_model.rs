@@ -0,0 +1,56 @@
+use super::*;
+use rstd::marker::PhantomData;
+
+/// The stability fee of the collateral on top of the configured stability fee, given how much of the debit value
+/// cap of the collateral is used.
+pub trait StabilityFeeModel<CurrencyId, Balance> {
+	fn fee(currency_id: CurrencyId, total_debit_value: Balance, debit_ceiling: Balance) -> Rate;
+}
+
+/// No fee on top of the configured stability fee.
+impl<CurrencyId, Balance> StabilityFeeModel<CurrencyId, Balance> for () {
+	fn fee(_currency_id: CurrencyId, _total_debit_value: Balance, _debit_ceiling: Balance) -> Rate {
+		Rate::from_parts(0)
+	}
+}
+
+/// A fee growing linearly with the utilization of the debit value cap, by `SlopeBelowKink` per whole cap until
+/// the utilization reaches `Kink`, and by the steeper `SlopeAboveKink` from there on. A zero cap is fully used.
+pub struct KinkedStabilityFee<Kink, SlopeBelowKink, SlopeAboveKink>(
+	PhantomData<(Kink, SlopeBelowKink, SlopeAboveKink)>,
+);
+
+impl<CurrencyId, Balance, Kink, SlopeBelowKink, SlopeAboveKink> StabilityFeeModel<CurrencyId, Balance>
+	for KinkedStabilityFee<Kink, SlopeBelowKink, SlopeAboveKink>
+where
+	Balance: Zero + TryInto<u128>,
+	Kink: Get<Ratio>,
+	SlopeBelowKink: Get<Rate>,
+	SlopeAboveKink: Get<Rate>,
+{
+	fn fee(_currency_id: CurrencyId, total_debit_value: Balance, debit_ceiling: Balance) -> Rate {
+		if total_debit_value.is_zero() {
+			return Rate::from_parts(0);
+		}
+		let utilization =
+			Ratio::checked_from_rational(total_debit_value, debit_ceiling).unwrap_or(Ratio::from_natural(1));
+		let kink = Kink::get();
+
+		let utilization_below_kink = if utilization.is_more_than(&kink) {
+			kink
+		} else {
+			utilization
+		};
+		let fee_below_kink = utilization_below_kink
+			.checked_mul(&SlopeBelowKink::get())
+			.unwrap_or_else(Rate::max_value);
+		let fee_above_kink = utilization
+			.saturating_sub(&kink)
+			.checked_mul(&SlopeAboveKink::get())
+			.unwrap_or_else(Rate::max_value);
+
+		fee_below_kink
+			.checked_add(&fee_above_kink)
+			.unwrap_or_else(Rate::max_value)
+	}
+}
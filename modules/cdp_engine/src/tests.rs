@@ -3,13 +3,38 @@
 #![cfg(test)]
 
 use super::*;
+use frame_support::weights::{DispatchClass, GetDispatchInfo};
 use frame_support::{assert_noop, assert_ok};
-use mock::{CdpEngineModule, Currencies, ExtBuilder, Origin, VaultsModule, ACA, ALICE, AUSD, BTC, DOT};
-use sp_runtime::traits::OnFinalize;
+use mock::{
+	Balance, CdpEngineModule, CdpTreasuryModule, Currencies, DebitBalance, DexModule, ExtBuilder,
+	MockKinkedStabilityFee, MockPriceSource, MockStabilityFeeModel, Origin, Runtime, System, TestEvent, VaultsModule,
+	ACA, ALICE, AUSD, BOB, BTC, CAROL, DOT,
+};
+use sp_runtime::{
+	traits::{OffchainWorker, OnFinalize},
+	transaction_validity::TransactionPriority,
+};
+use support::{unsigned_pool_order, UNSIGNED_KEEPER_LONGEVITY, UNSIGNED_REPORT_PRIORITY};
 
 #[test]
 fn set_collateral_params_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert!(CdpEngineModule::set_collateral_params(
+			Origin::signed(ALICE),
+			BTC,
+			None,
+			None,
+			None,
+			None,
+			Some(10000),
+			None
+		)
+		.is_err());
+		assert_noop!(
+			CdpEngineModule::set_collateral_params(Origin::ROOT, ACA, None, None, None, None, Some(10000), None),
+			Error::<Runtime>::NotValidCurrencyId
+		);
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
@@ -18,6 +43,7 @@ fn set_collateral_params_work() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			Some(100),
 		));
 		assert_eq!(
 			CdpEngineModule::stability_fee(BTC),
@@ -36,82 +62,180 @@ fn set_collateral_params_work() {
 			Some(Ratio::from_rational(9, 5))
 		);
 		assert_eq!(CdpEngineModule::maximum_total_debit_value(BTC), 10000);
+		assert_eq!(CdpEngineModule::minimum_collateral_amount(BTC), 100);
+		let update_liquidation_ratio_event =
+			TestEvent::cdp_engine(RawEvent::UpdateLiquidationRatio(BTC, Some(Ratio::from_rational(3, 2))));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == update_liquidation_ratio_event));
+		let update_minimum_collateral_amount_event =
+			TestEvent::cdp_engine(RawEvent::UpdateMinimumCollateralAmount(BTC, 100));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == update_minimum_collateral_amount_event));
+
+		// only the parameters given are updated, and removed with `Some(None)`
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(None),
+			None,
+			None,
+			None,
+			None,
+		));
+		assert_eq!(CdpEngineModule::liquidation_ratio(BTC), None);
+		assert_eq!(
+			CdpEngineModule::required_collateral_ratio(BTC),
+			Some(Ratio::from_rational(9, 5))
+		);
+		let remove_liquidation_ratio_event = TestEvent::cdp_engine(RawEvent::UpdateLiquidationRatio(BTC, None));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == remove_liquidation_ratio_event));
 	});
 }
 
 #[test]
-fn calculate_collateral_ratio_work() {
+fn collateral_params_fall_back_to_the_defaults() {
 	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(CdpEngineModule::get_liquidation_ratio(BTC), Ratio::from_percent(150));
+		assert_eq!(CdpEngineModule::get_stability_fee(BTC), Rate::from_percent(0));
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
 			Some(Some(Rate::from_rational(1, 100000))),
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
-			Some(10000),
+			None,
+			None,
+			None,
+			None,
 		));
-		assert_eq!(
-			CdpEngineModule::calculate_collateral_ratio(BTC, 100, 50, Price::from_rational(1, 1)),
-			Ratio::from_rational(100, 50)
+		assert_eq!(CdpEngineModule::get_liquidation_ratio(BTC), Ratio::from_rational(3, 2));
+		assert_eq!(CdpEngineModule::get_stability_fee(BTC), Rate::from_rational(1, 100000));
+		assert_eq!(CdpEngineModule::get_liquidation_ratio(DOT), Ratio::from_percent(150));
+	});
+}
+
+#[test]
+fn set_collateral_frozen_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert!(CdpEngineModule::set_collateral_frozen(Origin::signed(ALICE), BTC, true).is_err());
+		assert_noop!(
+			CdpEngineModule::set_collateral_frozen(Origin::ROOT, ACA, true),
+			Error::<Runtime>::NotValidCurrencyId
 		);
+		assert_ok!(CdpEngineModule::set_collateral_frozen(Origin::ROOT, BTC, true));
+		let frozen_event = TestEvent::cdp_engine(RawEvent::CollateralFrozen(BTC));
+		assert!(System::events().iter().any(|record| record.event == frozen_event));
+		assert_eq!(CdpEngineModule::is_collateral_frozen(BTC), true);
+		assert_eq!(CdpEngineModule::is_collateral_frozen(DOT), false);
+
+		assert_ok!(CdpEngineModule::set_collateral_frozen(Origin::ROOT, BTC, false));
+		let thawed_event = TestEvent::cdp_engine(RawEvent::CollateralThawed(BTC));
+		assert!(System::events().iter().any(|record| record.event == thawed_event));
+		assert_eq!(CdpEngineModule::is_collateral_frozen(BTC), false);
 	});
 }
 
 #[test]
-fn exceed_debit_value_cap_work() {
+fn frozen_collateral_only_allows_improving_the_position_and_exiting() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
+			None,
+			None,
 			Some(10000),
+			None,
 		));
-		assert_eq!(CdpEngineModule::exceed_debit_value_cap(BTC, 9999), false);
-		assert_eq!(CdpEngineModule::exceed_debit_value_cap(BTC, 10001), true);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 500, 200));
+		assert_ok!(CdpEngineModule::set_collateral_frozen(Origin::ROOT, BTC, true));
+
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 1),
+			Error::<Runtime>::CollateralIsFrozen,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, -1, 0),
+			Error::<Runtime>::CollateralIsFrozen,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(BOB, BTC, 1000, 100),
+			Error::<Runtime>::CollateralIsFrozen,
+		);
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 0);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 10, 0));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -100));
+
+		// the position can be closed in one go
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -510, -100));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
 	});
 }
 
 #[test]
-fn check_position_adjustment_ratio_work() {
+fn frozen_collateral_can_be_withdrawn_once_the_debit_is_repaid() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
+			None,
+			None,
 			Some(10000),
+			None,
 		));
-		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_frozen(Origin::ROOT, BTC, true));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -500));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -1000, 0));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
 	});
 }
 
 #[test]
-fn check_position_adjustment_ratio_when_invalid_feedprice() {
+fn frozen_collateral_can_be_liquidated() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
-			DOT,
-			Some(Some(Rate::from_rational(1, 100000))),
+			BTC,
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
+			None,
+			None,
 			Some(10000),
+			None,
 		));
-		assert_noop!(
-			CdpEngineModule::check_position_adjustment(&ALICE, DOT, 100, 50),
-			Error::InvalidFeedPrice,
-		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_frozen(Origin::ROOT, BTC, true));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
 	});
 }
 
 #[test]
-fn check_position_adjustment_ratio_below_required_ratio() {
+fn calculate_collateral_ratio_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
@@ -121,128 +245,254 @@ fn check_position_adjustment_ratio_below_required_ratio() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
-		assert_noop!(
-			CdpEngineModule::check_position_adjustment(&ALICE, BTC, 89, 50),
-			Error::BelowRequiredCollateralRatio
+		assert_eq!(
+			CdpEngineModule::calculate_collateral_ratio(BTC, 100, 50, Price::from_rational(1, 1)),
+			Ratio::from_rational(100, 50)
 		);
 	});
 }
 
 #[test]
-fn check_debit_cap_work() {
+fn calculate_collateral_ratio_without_debit() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(
+			CdpEngineModule::calculate_collateral_ratio(BTC, 100, 0, Price::from_rational(1, 1)),
+			Ratio::max_value()
+		);
+		assert_eq!(
+			CdpEngineModule::calculate_collateral_ratio(BTC, 0, 0, Price::from_rational(1, 1)),
+			Ratio::max_value()
+		);
+	});
+}
+
+#[test]
+fn calculate_liquidation_price_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
-			Some(10000),
+			None,
+			None,
+			None,
+			None,
 		));
-		assert_ok!(CdpEngineModule::check_debit_cap(BTC, 9999));
+		assert_eq!(
+			CdpEngineModule::calculate_liquidation_price(BTC, 100, 50),
+			Some(Price::from_rational(3, 4))
+		);
+		assert_eq!(CdpEngineModule::calculate_liquidation_price(BTC, 0, 50), None);
 	});
 }
 
 #[test]
-fn check_debit_cap_exceed() {
+fn position_info_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
+			None,
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
-		assert_noop!(
-			CdpEngineModule::check_debit_cap(BTC, 10001),
-			Error::ExceedDebitValueHardCap,
+		assert_eq!(
+			CdpEngineModule::position_info(&ALICE, BTC),
+			PositionInfo {
+				collateral: 0,
+				debit: 0,
+				debit_value: 0,
+				collateral_ratio: Some(Ratio::max_value()),
+				liquidation_ratio: Ratio::from_rational(3, 2),
+				liquidation_price: None,
+			}
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_eq!(
+			CdpEngineModule::position_info(&ALICE, BTC),
+			PositionInfo {
+				collateral: 100,
+				debit: 50,
+				debit_value: 50,
+				collateral_ratio: Some(Ratio::from_rational(100, 50)),
+				liquidation_ratio: Ratio::from_rational(3, 2),
+				liquidation_price: Some(Price::from_rational(3, 4)),
+			}
 		);
 	});
 }
 
 #[test]
-fn update_position_work() {
+fn position_info_without_feed_price() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, DOT, 100, 50));
+		assert_eq!(
+			CdpEngineModule::position_info(&ALICE, DOT),
+			PositionInfo {
+				collateral: 100,
+				debit: 50,
+				debit_value: 50,
+				collateral_ratio: None,
+				liquidation_ratio: Ratio::from_rational(3, 2),
+				liquidation_price: Some(Price::from_rational(3, 4)),
+			}
+		);
+	});
+}
+
+#[test]
+fn max_issuable_debit_passes_validation_and_one_more_does_not() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
+			None,
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 0));
+		<DebitExchangeRate<Runtime>>::insert(BTC, ExchangeRate::from_rational(11, 10));
+
+		// 504 is worth 555 rounded up, 1000 / 555 is just above the required collateral ratio
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 504);
 		assert_noop!(
-			CdpEngineModule::update_position(ALICE, ACA, 100, 50),
-			Error::NotValidCurrencyId,
+			CdpEngineModule::update_position(ALICE, BTC, 0, 505),
+			Error::<Runtime>::BelowRequiredCollateralRatio,
 		);
-		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
-		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
-		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
-		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
-		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
-		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
-		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
-		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
-		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, 504));
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 0);
+	});
+}
+
+#[test]
+fn max_issuable_debit_within_debit_value_cap() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(300),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 100));
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 200);
 		assert_noop!(
-			CdpEngineModule::update_position(ALICE, BTC, 0, 20),
-			Error::UpdatePositionFailed,
+			CdpEngineModule::update_position(ALICE, BTC, 0, 201),
+			Error::<Runtime>::ExceedDebitValueHardCap,
 		);
-		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -20));
-		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
-		assert_eq!(Currencies::balance(AUSD, &ALICE), 30);
-		assert_eq!(VaultsModule::debits(ALICE, BTC), 30);
-		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, 200));
+
+		// the cap is exhausted
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 0);
+		assert_eq!(CdpEngineModule::max_issuable_debit(&BOB, BTC), 0);
 	});
 }
 
 #[test]
-fn remain_debit_value_too_small_check() {
+fn max_issuable_debit_without_feed_price() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			None,
 			Some(Some(Ratio::from_rational(3, 2))),
-			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
+			None,
+			None,
 			Some(10000),
+			None,
 		));
-		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
-		assert_noop!(
-			CdpEngineModule::update_position(ALICE, BTC, 0, -49),
-			Error::UpdatePositionFailed,
-		);
-		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -100, -50));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 0));
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 666);
+		MockPriceSource::set_btc_price(None);
+		assert_eq!(CdpEngineModule::max_issuable_debit(&ALICE, BTC), 0);
 	});
 }
 
+/// The totals the vaults keep up to date against the positions of all the accounts added up.
+fn assert_system_info_matches_positions() {
+	let system_info = CdpEngineModule::system_info();
+	for (collateral_info, &currency_id) in system_info.collaterals.iter().zip(&[BTC, DOT]) {
+		let positions: Vec<_> = [ALICE, BOB, CAROL]
+			.iter()
+			.map(|who| VaultsModule::positions(who, currency_id))
+			.collect();
+		let total_collateral: Balance = positions.iter().map(|position| position.collateral).sum();
+		let total_debits: DebitBalance = positions.iter().map(|position| position.debit).sum();
+		assert_eq!(collateral_info.currency_id, currency_id);
+		assert_eq!(collateral_info.total_collateral, total_collateral);
+		assert_eq!(VaultsModule::total_debits(currency_id), total_debits);
+		assert_eq!(
+			collateral_info.total_debit_value,
+			DebitExchangeRateConvertor::<Runtime>::convert_debit_to_balance_ceil(currency_id, total_debits)
+		);
+	}
+	assert_eq!(
+		system_info.total_debit_value,
+		system_info
+			.collaterals
+			.iter()
+			.map(|collateral_info| collateral_info.total_debit_value)
+			.sum()
+	);
+}
+
 #[test]
-fn liquidate_unsafe_cdp_work() {
+fn system_info_follows_positions() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Rate::from_rational(1, 10))),
 			Some(Some(Ratio::from_rational(3, 2))),
 			Some(Some(Rate::from_rational(2, 10))),
-			Some(Some(Ratio::from_rational(9, 5))),
+			None,
 			Some(10000),
+			None,
 		));
-		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
-		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
-		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
-		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
-		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
-		assert_noop!(
-			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
-			Error::CollateralRatioStillSafe,
+		assert_system_info_matches_positions();
+		assert_eq!(
+			CdpEngineModule::collateral_info(BTC).collateral_ratio,
+			Some(Ratio::max_value())
+		);
+
+		// opens
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 300));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 500, 100));
+		assert_ok!(VaultsModule::update_collaterals_and_debits(CAROL, DOT, 100, 50));
+		assert_system_info_matches_positions();
+
+		// accrual
+		CdpEngineModule::on_finalize(1);
+		assert_system_info_matches_positions();
+		assert_eq!(
+			CdpEngineModule::collateral_info(BTC),
+			CollateralInfo {
+				currency_id: BTC,
+				total_collateral: 1500,
+				total_debit_value: 440,
+				total_collateral_value: Some(1500),
+				collateral_ratio: Some(Ratio::from_rational(1500, 440)),
+			}
 		);
+
+		// adjusts
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -100, 50));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 0, -50));
+		assert_system_info_matches_positions();
+
+		// liquidations
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
@@ -250,39 +500,1187 @@ fn liquidate_unsafe_cdp_work() {
 			Some(Some(Ratio::from_rational(3, 1))),
 			None,
 			None,
+			None,
 			None
 		));
 		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
-		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
-		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
-		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
-		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_system_info_matches_positions();
+		assert_eq!(
+			CdpEngineModule::collateral_info(BTC),
+			CollateralInfo {
+				currency_id: BTC,
+				total_collateral: 500,
+				total_debit_value: 55,
+				total_collateral_value: Some(500),
+				collateral_ratio: Some(Ratio::from_rational(500, 55)),
+			}
+		);
+
+		// the collateral ratio of the whole system is unknown without the price of DOT
+		let system_info = CdpEngineModule::system_info();
+		assert_eq!(system_info.total_debit_value, 105);
+		assert_eq!(system_info.collateral_ratio, None);
+		MockPriceSource::set_btc_price(None);
+		assert_eq!(CdpEngineModule::collateral_info(BTC).collateral_ratio, None);
 	});
 }
 
 #[test]
-fn liquidate_unsafe_cdp_when_invalid_feedprice() {
+fn is_cdp_unsafe_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_noop!(
-			CdpEngineModule::liquidate_unsafe_cdp(ALICE, DOT),
-			Error::InvalidFeedPrice,
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			None,
+			None
+		));
+		assert_eq!(
+			CdpEngineModule::is_cdp_unsafe(BTC, 100, 50, Price::from_rational(1, 1)),
+			false
+		);
+		assert_eq!(
+			CdpEngineModule::is_cdp_unsafe(BTC, 100, 80, Price::from_rational(1, 1)),
+			true
+		);
+		assert_eq!(
+			CdpEngineModule::is_cdp_unsafe(BTC, 0, 0, Price::from_rational(1, 1)),
+			false
 		);
 	});
 }
 
 #[test]
-fn on_finalize_work() {
+fn exceed_debit_value_cap_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
-			Some(Some(Rate::from_rational(1, 100))),
+			Some(Some(Rate::from_rational(1, 100000))),
 			Some(Some(Ratio::from_rational(3, 2))),
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
-		assert_ok!(CdpEngineModule::set_collateral_params(
+		assert_eq!(CdpEngineModule::exceed_debit_value_cap(BTC, 9999), false);
+		assert_eq!(CdpEngineModule::exceed_debit_value_cap(BTC, 10001), true);
+	});
+}
+
+#[test]
+fn check_position_adjustment_ratio_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 100, 50));
+	});
+}
+
+#[test]
+fn check_position_adjustment_ratio_when_invalid_feedprice() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			DOT,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			CdpEngineModule::check_position_adjustment(&ALICE, DOT, 100, 50),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+	});
+}
+
+#[test]
+fn only_improving_adjustments_without_feed_price() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 200, 50));
+
+		MockPriceSource::set_btc_price(None);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 10),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, -10, 0),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 10, 0));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -10));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 210);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 40);
+
+		MockPriceSource::set_btc_price(Some(Price::from_natural(1)));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -10, 10));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 200);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+	});
+}
+
+#[test]
+fn check_position_adjustment_ratio_below_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			CdpEngineModule::check_position_adjustment(&ALICE, BTC, 89, 50),
+			Error::<Runtime>::BelowRequiredCollateralRatio
+		);
+	});
+}
+
+#[test]
+fn check_position_adjustment_improving_above_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 10, 0));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 0, -10));
+	});
+}
+
+#[test]
+fn check_position_adjustment_improving_below_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		// 200% is now below both ratios
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(5, 2))),
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+		));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 10, 0));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 0, -10));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 10, -10));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 110);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 40);
+	});
+}
+
+#[test]
+fn check_position_adjustment_worsening_above_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 200, 50));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, -50, 0));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, 0, 50));
+		// right at the required ratio
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, -110, 0));
+	});
+}
+
+#[test]
+fn check_position_adjustment_worsening_below_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_noop!(
+			CdpEngineModule::check_position_adjustment(&ALICE, BTC, -20, 0),
+			Error::<Runtime>::BelowRequiredCollateralRatio
+		);
+		// more collateral doesn't make up for the debit borrowed with it
+		assert_noop!(
+			CdpEngineModule::check_position_adjustment(&ALICE, BTC, 10, 20),
+			Error::<Runtime>::BelowRequiredCollateralRatio
+		);
+
+		// only the liquidation ratio is left without a required ratio
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			None,
+			None,
+			Some(None),
+			None,
+			None,
+		));
+		assert_ok!(CdpEngineModule::check_position_adjustment(&ALICE, BTC, -20, 0));
+		assert_noop!(
+			CdpEngineModule::check_position_adjustment(&ALICE, BTC, -30, 0),
+			Error::<Runtime>::BelowLiquidationRatio
+		);
+	});
+}
+
+#[test]
+fn check_debit_cap_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::check_debit_cap(BTC, 9999));
+	});
+}
+
+#[test]
+fn check_debit_cap_exceed() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			CdpEngineModule::check_debit_cap(BTC, 10001),
+			Error::<Runtime>::ExceedDebitValueHardCap,
+		);
+	});
+}
+
+#[test]
+fn debit_value_cap_includes_the_accrued_stability_fee() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 10))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(100),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 90));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 1000, 10));
+		assert_noop!(
+			CdpEngineModule::update_position(BOB, BTC, 0, 1),
+			Error::<Runtime>::ExceedDebitValueHardCap,
+		);
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 0, -10));
+		assert_ok!(CdpEngineModule::check_debit_cap(BTC, 10));
+
+		// the 90 debits are worth 99 once the fee accrues
+		CdpEngineModule::on_finalize(1);
+		assert_noop!(
+			CdpEngineModule::update_position(BOB, BTC, 0, 1),
+			Error::<Runtime>::ExceedDebitValueHardCap,
+		);
+	});
+}
+
+#[test]
+fn lowered_debit_value_cap_only_blocks_borrowing() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(100),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 100));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			None,
+			None,
+			None,
+			Some(50),
+			None,
+		));
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 1),
+			Error::<Runtime>::ExceedDebitValueHardCap,
+		);
+
+		// still above the cap after the repayment, the collateral can still be adjusted
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -100, -10));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 90);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 900);
+
+		// liquidation takes the whole debit regardless of the cap
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(11, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		assert_eq!(VaultsModule::total_debits(BTC), 0);
+	});
+}
+
+#[test]
+fn update_position_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, ACA, 100, 50),
+			Error::<Runtime>::NotValidCurrencyId,
+		);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 20),
+			Error::<Runtime>::BelowRequiredCollateralRatio,
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -20));
+		let position_updated_event = TestEvent::vaults(vaults::RawEvent::PositionUpdated(ALICE, BTC, 0, -20));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_updated_event));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 30);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 30);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+	});
+}
+
+#[test]
+fn check_position_update_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+		));
+		assert_eq!(
+			CdpEngineModule::check_position_update(&ALICE, ACA, 100, 50),
+			Err(Error::<Runtime>::NotValidCurrencyId.into()),
+		);
+		assert_eq!(
+			CdpEngineModule::check_position_update(&ALICE, BTC, 100, 70),
+			Err(Error::<Runtime>::BelowRequiredCollateralRatio.into()),
+		);
+		// the collateral balance of alice is left to update_position
+		assert_ok!(CdpEngineModule::check_position_update(&ALICE, BTC, 2000, 50));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+
+		CdpEngineModule::on_emergency_shutdown();
+		assert_eq!(
+			CdpEngineModule::check_position_update(&ALICE, BTC, 100, 50),
+			Err(Error::<Runtime>::AlreadyShutdown.into()),
+		);
+	});
+}
+
+#[test]
+fn remain_debit_value_too_small_check() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, -49),
+			Error::<Runtime>::RemainDebitValueTooSmall,
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -100, -50));
+	});
+}
+
+#[test]
+fn minimum_collateral_amount_check() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			Some(100),
+		));
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 99, 10),
+			Error::<Runtime>::BelowMinimumCollateralAmount,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 99, 0),
+			Error::<Runtime>::BelowMinimumCollateralAmount,
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 10));
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, -1, 0),
+			Error::<Runtime>::BelowMinimumCollateralAmount,
+		);
+
+		// the position is below the raised minimum, it can only be repaid, topped up or closed
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(200),
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -4));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 50, 0));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 150);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 6);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 1),
+			Error::<Runtime>::BelowMinimumCollateralAmount,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, -10, 0),
+			Error::<Runtime>::BelowMinimumCollateralAmount,
+		);
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -150, -6));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::CollateralRatioStillSafe,
+		);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		let position_confiscated_event = TestEvent::vaults(vaults::RawEvent::PositionConfiscated(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_confiscated_event));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 50);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+	});
+}
+
+#[test]
+fn calculate_collateral_to_seize_work() {
+	let penalty = Rate::from_rational(2, 10);
+	// overcollateralized, the collateral worth the debit value plus the penalty is seized
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_natural(1), 1000),
+		(600, 500)
+	);
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_natural(2), 1000),
+		(300, 500)
+	);
+	// exactly collateralized
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_natural(1), 600),
+		(600, 500)
+	);
+	// undercollateralized, all the collateral is seized for the debit value it covers with the penalty
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_natural(1), 300),
+		(300, 250)
+	);
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_natural(2), 200),
+		(200, 333)
+	);
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, penalty, Price::from_parts(0), 1000),
+		(1000, 0)
+	);
+	assert_eq!(
+		CdpEngineModule::calculate_collateral_to_seize(500, Rate::from_parts(0), Price::from_natural(1), 500),
+		(500, 500)
+	);
+}
+
+#[test]
+fn liquidate_unsafe_cdp_by_dex() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(BTC, &BOB, 100000));
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(AUSD, &BOB, 100000));
+		assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, BTC));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			100000,
+			100000,
+			0,
+			None
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+
+		// the bad debt with the 20% penalty is bought with the collateral, the rest is left for the owner
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		let liquidate_event = TestEvent::cdp_engine(RawEvent::LiquidateUnsafeCdp(BTC, ALICE, 1000, 500));
+		assert!(System::events().iter().any(|record| record.event == liquidate_event));
+		assert_eq!(DexModule::liquidity_pool(BTC), (100604, 99400));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 396);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 500);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &VaultsModule::account_id()), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 500);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 600);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 0);
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_by_auction_when_slippage_too_large() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(AUSD, &BOB, 10000));
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(BTC, &BOB, 10000));
+		assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, BTC));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+
+		// 639 of the collateral for the target of 600 is beyond the 1% slippage
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 500);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 1000);
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_back_to_required_collateral_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(5, 2))),
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None
+		));
+		assert_eq!(
+			CdpEngineModule::calculate_liquidation_amounts(BTC, 1000, 500, Price::from_natural(1)),
+			(333, 278)
+		);
+
+		// 278 of the debit and 333 of the collateral covering it with the 20% penalty are confiscated
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		let liquidate_event = TestEvent::cdp_engine(RawEvent::LiquidateUnsafeCdp(BTC, ALICE, 333, 278));
+		assert!(System::events().iter().any(|record| record.event == liquidate_event));
+		let position_confiscated_event = TestEvent::vaults(vaults::RawEvent::PositionConfiscated(ALICE, BTC, 333, 278));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_confiscated_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 667);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 222);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 278);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 333);
+		let collateral_ratio = CdpEngineModule::calculate_collateral_ratio(BTC, 667, 222, Price::from_natural(1));
+		assert!(!Ratio::from_rational(3, 1).is_more_than(&collateral_ratio));
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::CollateralRatioStillSafe,
+		);
+	});
+}
+
+#[test]
+fn liquidate_whole_unsafe_cdp_when_rest_is_too_small() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(5, 2))),
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			Some(700),
+		));
+
+		// the 667 left would be below the minimum collateral amount
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 500);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 1000);
+	});
+}
+
+#[test]
+fn liquidate_should_be_unsigned_and_operational() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		let call = Call::<Runtime>::liquidate(BTC, ALICE);
+		assert_eq!(call.get_dispatch_info().class, DispatchClass::Operational);
+		assert_eq!(
+			<CdpEngineModule as ValidateUnsigned>::validate_unsigned(&call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_noop!(
+			CdpEngineModule::liquidate(Origin::NONE, BTC, ALICE),
+			Error::<Runtime>::CollateralRatioStillSafe,
+		);
+
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		let validity = <CdpEngineModule as ValidateUnsigned>::validate_unsigned(&call).unwrap();
+		assert_eq!(validity.provides, vec![(b"cdp_engine/liquidate", ALICE, BTC).encode()]);
+		assert_eq!(validity.longevity, UNSIGNED_KEEPER_LONGEVITY);
+		assert!(CdpEngineModule::liquidate(Origin::signed(BOB), BTC, ALICE).is_err());
+		assert_ok!(CdpEngineModule::liquidate(Origin::NONE, BTC, ALICE));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+	});
+}
+
+#[test]
+fn liquidations_are_ordered_by_urgency_in_the_pool() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 100, 60));
+		assert_ok!(CdpEngineModule::update_position(CAROL, BTC, 100, 20));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+
+		// a third and four ninths below the liquidation ratio, carol is still safe
+		let alice = <CdpEngineModule as ValidateUnsigned>::validate_unsigned(&Call::liquidate(BTC, ALICE)).unwrap();
+		let bob = <CdpEngineModule as ValidateUnsigned>::validate_unsigned(&Call::liquidate(BTC, BOB)).unwrap();
+		assert!(bob.priority > alice.priority);
+		assert!(alice.priority > TransactionPriority::max_value() / 2);
+		assert!(alice.priority > UNSIGNED_REPORT_PRIORITY);
+
+		// the duplicates submitted by every validator are kept once, the most urgent first
+		assert_eq!(
+			unsigned_pool_order::<CdpEngineModule>(vec![
+				Call::liquidate(BTC, ALICE),
+				Call::liquidate(BTC, CAROL),
+				Call::liquidate(BTC, BOB),
+				Call::liquidate(BTC, ALICE),
+				Call::liquidate(BTC, BOB),
+			]),
+			vec![Call::liquidate(BTC, BOB), Call::liquidate(BTC, ALICE)]
+		);
+
+		// bob tops up the collateral before the liquidation is included
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 100, 0));
+		assert_eq!(
+			unsigned_pool_order::<CdpEngineModule>(vec![Call::liquidate(BTC, BOB), Call::liquidate(BTC, ALICE)]),
+			vec![Call::liquidate(BTC, ALICE)]
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_submits_unsafe_cdp_liquidations() {
+	let (mut ext, submitted_transactions) = ExtBuilder::default().build_with_offchain();
+	ext.execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 400, 100));
+		CdpEngineModule::offchain_worker(1);
+		assert_eq!(submitted_transactions(), vec![]);
+
+		// only ALICE is below 300%
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		CdpEngineModule::offchain_worker(1);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].0, None);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, ALICE));
+	});
+}
+
+#[test]
+fn offchain_worker_checks_positions_from_the_cursor() {
+	let (mut ext, submitted_transactions) = ExtBuilder::default().build_with_offchain();
+	ext.execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 400, 100));
+		assert_ok!(CdpEngineModule::update_position(CAROL, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+
+		// two positions are checked per block from the last owner to open one, the sweep starts over once all are
+		// checked
+		CdpEngineModule::offchain_worker(1);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, CAROL));
+		CdpEngineModule::offchain_worker(2);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, ALICE));
+		CdpEngineModule::offchain_worker(3);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, CAROL));
+
+		// the owner the sweep stopped at has closed its position, the next block starts over
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		CdpEngineModule::offchain_worker(4);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, CAROL));
+	});
+}
+
+#[test]
+fn offchain_worker_checks_the_positions_linked_by_the_vaults_migration() {
+	let (mut ext, submitted_transactions) = ExtBuilder::default().build_with_offchain();
+	ext.execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		// positions from before their owners were linked, only ALICE is below 300%
+		<vaults::Positions<Runtime>>::insert(ALICE, BTC, Position::new(100, 50));
+		<vaults::Positions<Runtime>>::insert(BOB, BTC, Position::new(400, 100));
+		<vaults::TotalCollaterals<Runtime>>::insert(BTC, 500);
+		<vaults::TotalDebits<Runtime>>::insert(BTC, 150);
+		assert_ok!(Currencies::deposit(BTC, &VaultsModule::account_id(), 500));
+		CdpEngineModule::offchain_worker(1);
+		assert_eq!(submitted_transactions(), vec![]);
+
+		VaultsModule::on_runtime_upgrade();
+		CdpEngineModule::offchain_worker(2);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].1, Call::liquidate(BTC, ALICE));
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_without_debit() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::CollateralRatioStillSafe,
+		);
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_when_invalid_feedprice() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, DOT),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+	});
+}
+
+#[test]
+fn liquidate_unsafe_cdp_refuses_without_feed_price() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		// unsafe at the last price, but the price is gone
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 1))),
+			None,
+			None,
+			None,
+			None
+		));
+		MockPriceSource::set_btc_price(None);
+
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::InvalidFeedPrice,
+		);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+
+		MockPriceSource::set_btc_price(Some(Price::from_natural(1)));
+		assert_ok!(CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+	});
+}
+
+#[test]
+fn kinked_stability_fee_work() {
+	assert_eq!(MockKinkedStabilityFee::fee(BTC, 0, 1000), Rate::from_parts(0));
+	assert_eq!(
+		MockKinkedStabilityFee::fee(BTC, 400, 1000),
+		Rate::from_rational(4, 1000)
+	);
+	assert_eq!(
+		MockKinkedStabilityFee::fee(BTC, 500, 1000),
+		Rate::from_rational(5, 1000)
+	);
+	assert_eq!(
+		MockKinkedStabilityFee::fee(BTC, 800, 1000),
+		Rate::from_rational(35, 1000)
+	);
+	// the debit value exceeds the cap after the cap is lowered
+	assert_eq!(
+		MockKinkedStabilityFee::fee(BTC, 1500, 1000),
+		Rate::from_rational(105, 1000)
+	);
+	// a zero cap is fully used
+	assert_eq!(MockKinkedStabilityFee::fee(BTC, 1, 0), Rate::from_rational(55, 1000));
+}
+
+#[test]
+fn stability_fee_rises_when_utilization_crosses_the_kink() {
+	ExtBuilder::default().build().execute_with(|| {
+		MockStabilityFeeModel::set_kinked(true);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			None,
+			None,
+			None,
+			Some(1000),
+			None,
+		));
+		assert_eq!(CdpEngineModule::get_stability_fee(BTC), Rate::from_parts(0));
+
+		// 40% of the cap is used, below the kink
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 400));
+		assert_eq!(CdpEngineModule::get_stability_fee(BTC), Rate::from_rational(4, 1000));
+		CdpEngineModule::on_finalize(1);
+		assert_eq!(
+			CdpEngineModule::debit_exchange_rate(BTC),
+			Some(ExchangeRate::from_rational(1004, 1000))
+		);
+
+		// 804 of the cap of 1000 is used, above the kink of 50% the fee grows ten times as fast
+		assert_ok!(CdpEngineModule::update_position(BOB, BTC, 1000, 400));
+		assert_eq!(CdpEngineModule::total_debit_value(BTC), 804);
+		assert_eq!(CdpEngineModule::get_stability_fee(BTC), Rate::from_rational(354, 10000));
+		CdpEngineModule::on_finalize(2);
+		assert_eq!(
+			CdpEngineModule::debit_exchange_rate(BTC),
+			Some(ExchangeRate::from_rational(10395416, 10000000))
+		);
+	});
+}
+
+#[test]
+fn on_finalize_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			DOT,
 			Some(Some(Rate::from_rational(2, 100))),
@@ -290,6 +1688,7 @@ fn on_finalize_work() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
 		CdpEngineModule::on_finalize(1);
 		assert_eq!(CdpEngineModule::debit_exchange_rate(BTC), None);
@@ -309,6 +1708,8 @@ fn on_finalize_work() {
 			Some(ExchangeRate::from_rational(10201, 10000))
 		);
 		assert_eq!(CdpEngineModule::debit_exchange_rate(DOT), None);
+		// the debit is worth 30.603 now, rounded up to 31 to repay
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(AUSD, &ALICE, 1));
 		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 0, -30));
 		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
 		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
@@ -320,3 +1721,93 @@ fn on_finalize_work() {
 		assert_eq!(CdpEngineModule::debit_exchange_rate(DOT), None);
 	});
 }
+
+#[test]
+fn stability_fee_compounds_the_debit_value() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 10))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 1000, 500));
+		for block in 1..4 {
+			CdpEngineModule::on_finalize(block);
+		}
+
+		// 500 * 1.1 ^ 3 is owed rounded up, the fee accrued is issued to the surplus pool
+		assert_eq!(
+			CdpEngineModule::debit_exchange_rate(BTC),
+			Some(ExchangeRate::from_rational(1331, 1000))
+		);
+		assert_eq!(
+			DebitExchangeRateConvertor::<Runtime>::convert_debit_to_balance_floor(BTC, 500),
+			665
+		);
+		assert_eq!(
+			DebitExchangeRateConvertor::<Runtime>::convert_debit_to_balance_ceil(BTC, 500),
+			666
+		);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 500);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 166);
+	});
+}
+
+#[test]
+fn settle_cdp_has_debit_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_noop!(
+			CdpEngineModule::settle_cdp_has_debit(ALICE, BTC),
+			Error::<Runtime>::MustAfterShutdown,
+		);
+
+		CdpEngineModule::on_emergency_shutdown();
+		assert_eq!(CdpEngineModule::is_shutdown(), true);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 10),
+			Error::<Runtime>::AlreadyShutdown,
+		);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, -10, 0),
+			Error::<Runtime>::AlreadyShutdown,
+		);
+		assert_noop!(
+			CdpEngineModule::liquidate_unsafe_cdp(ALICE, BTC),
+			Error::<Runtime>::AlreadyShutdown,
+		);
+		assert_noop!(
+			CdpEngineModule::settle_cdp_has_debit(BOB, BTC),
+			Error::<Runtime>::NoDebitToSettle,
+		);
+
+		// the collateral worth the debit goes to the treasury with the debit, the rest is returned
+		assert_ok!(CdpEngineModule::settle_cdp_has_debit(ALICE, BTC));
+		let position_confiscated_event = TestEvent::vaults(vaults::RawEvent::PositionConfiscated(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_confiscated_event));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 50);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 50);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 950);
+		assert_eq!(Currencies::balance(BTC, &VaultsModule::account_id()), 0);
+	});
+}
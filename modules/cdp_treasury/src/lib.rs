@@ -0,0 +1,615 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::{EnsureOrigin, Get},
+	weights::Weight,
+};
+use orml_traits::{MultiCurrency, PriceProvider};
+use rstd::prelude::*;
+use sp_runtime::{
+	traits::{AccountIdConversion, Bounded, CheckedAdd, CheckedSub, Saturating, Zero},
+	DispatchResult, ModuleId, RuntimeDebug,
+};
+use support::{
+	AuctionManager, CDPTreasury, DexManager, OnEmergencyShutdown, Price, PriceProviderExtended, Ratio,
+	STORAGE_ACCESS_WEIGHT,
+};
+
+#[cfg(feature = "std")]
+use support::{AuditResult, Auditor};
+
+mod mock;
+mod tests;
+
+const MODULE_ID: ModuleId = ModuleId(*b"aca/cdpt");
+
+type BalanceOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type ShareOf<T> =
+	<<T as Trait>::Dex as DexManager<<T as system::Trait>::AccountId, CurrencyIdOf<T>, BalanceOf<T>>>::Share;
+
+/// Layouts of the module storage, in the order they are migrated through.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum StorageVersion {
+	/// Before the storage is versioned, all the funds are held by the module account.
+	V0,
+	/// The funds of each currency are held by its own sub-account.
+	V1,
+}
+
+impl Default for StorageVersion {
+	fn default() -> Self {
+		StorageVersion::V0
+	}
+}
+
+impl StorageVersion {
+	/// The version migrated to from this one, `None` for the latest.
+	pub fn next(self) -> Option<Self> {
+		match self {
+			StorageVersion::V0 => Some(StorageVersion::V1),
+			StorageVersion::V1 => None,
+		}
+	}
+}
+
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: MultiCurrency<Self::AccountId>;
+	type GetStableCurrencyId: Get<CurrencyIdOf<Self>>;
+	type AuctionManagerHandler: AuctionManager<
+		Self::AccountId,
+		CurrencyId = CurrencyIdOf<Self>,
+		Balance = BalanceOf<Self>,
+	>;
+	/// Origin of the calls updating the parameters of the module.
+	type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	/// The dex the treasury provides liquidity to, its base currency must be the stable currency.
+	type Dex: DexManager<Self::AccountId, CurrencyIdOf<Self>, BalanceOf<Self>>;
+	type PriceSource: PriceProvider<CurrencyIdOf<Self>, Price>;
+	/// The collaterals sold for stable coin once above their retention.
+	type CollateralCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The max slippage from the feed price the collaterals are sold at.
+	type MaxSlippageSwapWithDEX: Get<Ratio>;
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+		CurrencyId = CurrencyIdOf<T>,
+		Share = ShareOf<T>,
+	{
+		/// Update the fixed size of surplus auctions (size)
+		UpdateSurplusAuctionFixedSize(Balance),
+		/// Update the surplus kept by the treasury (size)
+		UpdateSurplusBufferSize(Balance),
+		/// Update the initial native amount of debit auctions (amount)
+		UpdateInitialAmountPerDebitAuction(Balance),
+		/// Update the fixed size of debit auctions (size)
+		UpdateDebitAuctionFixedSize(Balance),
+		/// Stable coin issued to the treasury as surplus (amount)
+		SystemSurplus(Balance),
+		/// Bad debt recorded by the treasury (amount)
+		SystemDebit(Balance),
+		/// Stable coin issued to account and recorded as bad debt (account, amount)
+		IssueDebit(AccountId, Balance),
+		/// Stable coin transferred from account to the surplus (account, amount)
+		DepositSurplus(AccountId, Balance),
+		/// Stable coin transferred from the surplus to account (account, amount)
+		WithdrawSurplus(AccountId, Balance),
+		/// Collateral transferred from account to the treasury (account, currency_id, amount)
+		DepositCollateral(AccountId, CurrencyId, Balance),
+		/// Collateral transferred from the treasury to account (account, currency_id, amount)
+		WithdrawCollateral(AccountId, CurrencyId, Balance),
+		/// Surplus burned to offset the same amount of bad debt (amount)
+		OffsetSurplusAndDebit(Balance),
+		/// Collateral and surplus added as dex liquidity (currency_id, collateral_amount, stable_amount, share_amount)
+		DepositLiquidityToDex(CurrencyId, Balance, Balance, Share),
+		/// Dex liquidity withdrawn back to collateral and surplus (currency_id, collateral_amount, stable_amount, share_amount)
+		WithdrawLiquidityFromDex(CurrencyId, Balance, Balance, Share),
+		/// Update the collateral kept by the treasury, `None` for none of it sold (currency_id, retention)
+		UpdateCollateralRetention(CurrencyId, Option<Balance>),
+		/// Collateral above the retention sold for surplus with dex (currency_id, collateral_amount, stable_amount)
+		SwapCollateralToStable(CurrencyId, Balance, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for cdp treasury module.
+	pub enum Error for Module<T: Trait> {
+		SurplusPoolOverflow,
+		DebitPoolOverflow,
+		CollateralOverflow,
+		SurplusNotEnough,
+		CollateralNotEnough,
+		StableCoinTransferFailed,
+		CollateralTransferFailed,
+		ShareNotEnough,
+		AlreadyShutdown,
+		InvalidFeedPrice,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as CdpTreasury {
+		pub SurplusAuctionFixedSize get(fn surplus_auction_fixed_size): BalanceOf<T>;
+		pub SurplusBufferSize get(fn surplus_buffer_size): BalanceOf<T>;
+		pub InitialAmountPerDebitAuction get(fn initial_amount_per_debit_auction): BalanceOf<T>;
+		pub DebitAuctionFixedSize get(fn debit_auction_fixed_size): BalanceOf<T>;
+		/// The collateral kept by the treasury, the rest is sold for stable coin. None of it is sold if unset.
+		pub CollateralRetention get(fn collateral_retention): map CurrencyIdOf<T> => Option<BalanceOf<T>>;
+
+		pub DebitPool get(fn debit_pool): BalanceOf<T>;
+		pub SurplusPool get(fn surplus_pool): BalanceOf<T>;
+		pub TotalCollaterals get(fn total_collaterals): map CurrencyIdOf<T> => BalanceOf<T>;
+		/// Dex shares of the treasury, the liquidity isn't counted in the surplus pool or the total collaterals.
+		pub DexShares get(fn dex_shares): linked_map CurrencyIdOf<T> => ShareOf<T>;
+		pub IsShutdown get(fn is_shutdown): bool;
+
+		Version get(fn storage_version): StorageVersion;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		pub fn set_debit_and_surplus_handle_params(
+			origin,
+			surplus_auction_fixed_size: Option<BalanceOf<T>>,
+			surplus_buffer_size: Option<BalanceOf<T>>,
+			initial_amount_per_debit_auction: Option<BalanceOf<T>>,
+			debit_auction_fixed_size: Option<BalanceOf<T>>,
+		) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			if let Some(amount) = surplus_auction_fixed_size {
+				<SurplusAuctionFixedSize<T>>::put(amount);
+				Self::deposit_event(RawEvent::UpdateSurplusAuctionFixedSize(amount));
+			}
+			if let Some(amount) = surplus_buffer_size {
+				<SurplusBufferSize<T>>::put(amount);
+				Self::deposit_event(RawEvent::UpdateSurplusBufferSize(amount));
+			}
+			if let Some(amount) = initial_amount_per_debit_auction {
+				<InitialAmountPerDebitAuction<T>>::put(amount);
+				Self::deposit_event(RawEvent::UpdateInitialAmountPerDebitAuction(amount));
+			}
+			if let Some(amount) = debit_auction_fixed_size {
+				<DebitAuctionFixedSize<T>>::put(amount);
+				Self::deposit_event(RawEvent::UpdateDebitAuctionFixedSize(amount));
+			}
+		}
+
+		pub fn set_collateral_retention(origin, currency_id: CurrencyIdOf<T>, retention: Option<BalanceOf<T>>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			match retention {
+				Some(amount) => <CollateralRetention<T>>::insert(currency_id, amount),
+				None => <CollateralRetention<T>>::remove(currency_id),
+			}
+			Self::deposit_event(RawEvent::UpdateCollateralRetention(currency_id, retention));
+		}
+
+		/// Add the collateral of `currency_id` and the available surplus as liquidity of its dex pool, at no more
+		/// than the given amounts.
+		pub fn deposit_liquidity_to_dex(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			collateral_amount: BalanceOf<T>,
+			stable_amount: BalanceOf<T>,
+		) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(!Self::is_shutdown(), Error::<T>::AlreadyShutdown);
+			ensure!(
+				Self::total_collaterals(currency_id) >= collateral_amount,
+				Error::<T>::CollateralNotEnough
+			);
+			ensure!(Self::available_surplus() >= stable_amount, Error::<T>::SurplusNotEnough);
+
+			// the liquidity of a collateral is provided from its sub-account, the unused stable coin goes back
+			let stable_currency_id = T::GetStableCurrencyId::get();
+			let collateral_account = Self::sub_account_id(currency_id);
+			Self::release(stable_currency_id, &collateral_account, stable_amount)?;
+			let result = T::Dex::add_liquidity(&collateral_account, currency_id, collateral_amount, stable_amount);
+			let used_stable_amount = result.as_ref().map_or_else(|_| Zero::zero(), |(_, amount, _)| *amount);
+			Self::hold(stable_currency_id, &collateral_account, stable_amount - used_stable_amount)?;
+			let (collateral_increment, stable_increment, share_increment) = result?;
+			<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance -= collateral_increment);
+			<SurplusPool<T>>::mutate(|surplus| *surplus -= stable_increment);
+			<DexShares<T>>::mutate(currency_id, |share| *share += share_increment);
+			Self::deposit_event(RawEvent::DepositLiquidityToDex(
+				currency_id,
+				collateral_increment,
+				stable_increment,
+				share_increment,
+			));
+		}
+
+		pub fn withdraw_liquidity_from_dex(origin, currency_id: CurrencyIdOf<T>, share_amount: ShareOf<T>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			Self::withdraw_dex_liquidity(currency_id, share_amount)?;
+		}
+
+		fn on_initialize(_now: T::BlockNumber) {
+			// the collaterals are kept for settlement after shutdown
+			if !Self::is_shutdown() {
+				for currency_id in T::CollateralCurrencyIds::get() {
+					let _ = Self::swap_collateral_to_stable(currency_id);
+				}
+			}
+		}
+
+		fn on_finalize(_now: T::BlockNumber) {
+			Self::offset_surplus_and_debit();
+			// no more surplus and debit auctions after shutdown
+			if !Self::is_shutdown() {
+				Self::trigger_auctions();
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The module account, which held all the funds before `StorageVersion::V1`.
+	pub fn account_id() -> T::AccountId {
+		MODULE_ID.into_account()
+	}
+
+	/// The sub-account holding the funds of `currency_id`, the surplus for the stable currency and the total
+	/// collaterals for the others. The dex liquidity of a collateral is provided from its sub-account too.
+	pub fn sub_account_id(currency_id: CurrencyIdOf<T>) -> T::AccountId {
+		MODULE_ID.into_sub_account(currency_id)
+	}
+
+	/// Transfer `amount` of `currency_id` from `from` to the sub-account holding it.
+	fn hold(currency_id: CurrencyIdOf<T>, from: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+		if amount.is_zero() {
+			return Ok(());
+		}
+		T::Currency::transfer(currency_id, from, &Self::sub_account_id(currency_id), amount)
+			.map_err(|_| Self::transfer_error(currency_id).into())
+	}
+
+	/// Transfer `amount` of `currency_id` from the sub-account holding it to `to`.
+	fn release(currency_id: CurrencyIdOf<T>, to: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+		if amount.is_zero() {
+			return Ok(());
+		}
+		T::Currency::transfer(currency_id, &Self::sub_account_id(currency_id), to, amount)
+			.map_err(|_| Self::transfer_error(currency_id).into())
+	}
+
+	fn transfer_error(currency_id: CurrencyIdOf<T>) -> Error<T> {
+		if currency_id == T::GetStableCurrencyId::get() {
+			Error::<T>::StableCoinTransferFailed
+		} else {
+			Error::<T>::CollateralTransferFailed
+		}
+	}
+
+	/// Apply the pending migrations in order and return the weight consumed. Only the version is read once the
+	/// storage is up to date, so it's safe to run again.
+	pub fn on_runtime_upgrade() -> Weight {
+		let mut version = Self::storage_version();
+		let mut weight = STORAGE_ACCESS_WEIGHT;
+		while let Some(next) = version.next() {
+			weight = weight
+				.saturating_add(Self::migrate_to(next))
+				.saturating_add(STORAGE_ACCESS_WEIGHT);
+			<Version>::put(next);
+			version = next;
+		}
+		weight
+	}
+
+	/// Migrate the storage to the layout of `version` from the one before it, returns the weight consumed.
+	fn migrate_to(version: StorageVersion) -> Weight {
+		match version {
+			// the first version is never migrated to
+			StorageVersion::V0 => 0,
+			StorageVersion::V1 => Self::migrate_to_sub_accounts(),
+		}
+	}
+
+	/// Withdraw the dex liquidity of the module account back to the treasury, then move its balances of the stable
+	/// currency and the collaterals to their sub-accounts. The liquidity can be provided again from the
+	/// sub-accounts.
+	fn migrate_to_sub_accounts() -> Weight {
+		let module_account = Self::account_id();
+		let mut weight: Weight = 0;
+
+		for (currency_id, share_amount) in <DexShares<T>>::enumerate().collect::<Vec<_>>() {
+			// the shares are kept if they can't be withdrawn, for the audit to report them
+			if let Ok((collateral_amount, stable_amount)) =
+				T::Dex::remove_liquidity(&module_account, currency_id, share_amount)
+			{
+				<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance += collateral_amount);
+				<SurplusPool<T>>::mutate(|surplus| *surplus += stable_amount);
+				<DexShares<T>>::remove(currency_id);
+			}
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT * 8);
+		}
+
+		let currency_ids =
+			rstd::iter::once(T::GetStableCurrencyId::get()).chain(T::CollateralCurrencyIds::get().into_iter());
+		for currency_id in currency_ids {
+			let balance = T::Currency::balance(currency_id, &module_account);
+			let _ = Self::hold(currency_id, &module_account, balance);
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT * 3);
+		}
+
+		weight
+	}
+
+	/// Surplus which isn't in surplus auction.
+	pub fn available_surplus() -> BalanceOf<T> {
+		Self::surplus_pool()
+			.checked_sub(&T::AuctionManagerHandler::get_total_surplus_in_auction())
+			.unwrap_or_else(Zero::zero)
+	}
+
+	/// Bad debt which isn't in debit auction.
+	pub fn available_debit() -> BalanceOf<T> {
+		Self::debit_pool()
+			.checked_sub(&T::AuctionManagerHandler::get_total_debit_in_auction())
+			.unwrap_or_else(Zero::zero)
+	}
+
+	/// Sell the collateral of `currency_id` above its retention for surplus with dex, at no more than the max
+	/// slippage from the feed price. Fails without a feed price, or if the dex pool is too shallow.
+	fn swap_collateral_to_stable(currency_id: CurrencyIdOf<T>) -> DispatchResult {
+		let supply_amount = match Self::collateral_retention(currency_id)
+			.and_then(|retention| Self::total_collaterals(currency_id).checked_sub(&retention))
+		{
+			Some(amount) if !amount.is_zero() => amount,
+			_ => return Ok(()),
+		};
+
+		let stable_currency_id = T::GetStableCurrencyId::get();
+		let feed_amount = T::PriceSource::get_value(stable_currency_id, currency_id, supply_amount)
+			.ok_or(Error::<T>::InvalidFeedPrice)?;
+		let min_target_amount = feed_amount.saturating_sub(
+			T::MaxSlippageSwapWithDEX::get()
+				.checked_mul_int(&feed_amount)
+				.unwrap_or_else(Bounded::max_value),
+		);
+		ensure!(!min_target_amount.is_zero(), Error::<T>::InvalidFeedPrice);
+
+		let collateral_account = Self::sub_account_id(currency_id);
+		let (_, target_amount) = T::Dex::exchange_currency(
+			collateral_account.clone(),
+			(currency_id, supply_amount),
+			(stable_currency_id, min_target_amount),
+		)?;
+		Self::hold(stable_currency_id, &collateral_account, target_amount)?;
+		<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance -= supply_amount);
+		<SurplusPool<T>>::mutate(|surplus| *surplus += target_amount);
+		Self::deposit_event(RawEvent::SwapCollateralToStable(
+			currency_id,
+			supply_amount,
+			target_amount,
+		));
+
+		Ok(())
+	}
+
+	/// Withdraw `share_amount` of the dex liquidity of the treasury, back to the collateral of `currency_id` and
+	/// the surplus.
+	fn withdraw_dex_liquidity(currency_id: CurrencyIdOf<T>, share_amount: ShareOf<T>) -> DispatchResult {
+		let shares = Self::dex_shares(currency_id);
+		ensure!(shares >= share_amount, Error::<T>::ShareNotEnough);
+
+		let collateral_account = Self::sub_account_id(currency_id);
+		let (collateral_amount, stable_amount) =
+			T::Dex::remove_liquidity(&collateral_account, currency_id, share_amount)?;
+		Self::hold(T::GetStableCurrencyId::get(), &collateral_account, stable_amount)?;
+		<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance += collateral_amount);
+		<SurplusPool<T>>::mutate(|surplus| *surplus += stable_amount);
+		if shares == share_amount {
+			<DexShares<T>>::remove(currency_id);
+		} else {
+			<DexShares<T>>::insert(currency_id, shares - share_amount);
+		}
+		Self::deposit_event(RawEvent::WithdrawLiquidityFromDex(
+			currency_id,
+			collateral_amount,
+			stable_amount,
+			share_amount,
+		));
+
+		Ok(())
+	}
+
+	/// Check the surplus pool and the collaterals against the balances of their sub-accounts, and that nothing is
+	/// left elsewhere. The debit pool is only accounting, no balance stands for it.
+	#[cfg(feature = "std")]
+	pub fn audit(currency_ids: &[CurrencyIdOf<T>]) -> AuditResult<CurrencyIdOf<T>> {
+		let stable_currency_id = T::GetStableCurrencyId::get();
+		let module_account = Self::account_id();
+		let mut auditor = Auditor::default();
+
+		auditor.check(
+			"cdp treasury: surplus held",
+			stable_currency_id,
+			Self::surplus_pool(),
+			T::Currency::balance(stable_currency_id, &Self::sub_account_id(stable_currency_id)),
+		);
+		auditor.check(
+			"cdp treasury: left in module account",
+			stable_currency_id,
+			Zero::zero(),
+			T::Currency::balance(stable_currency_id, &module_account),
+		);
+		for &currency_id in currency_ids {
+			let collateral_account = Self::sub_account_id(currency_id);
+			auditor.check(
+				"cdp treasury: collaterals held",
+				currency_id,
+				Self::total_collaterals(currency_id),
+				T::Currency::balance(currency_id, &collateral_account),
+			);
+			auditor.check(
+				"cdp treasury: stable coin left in collateral account",
+				stable_currency_id,
+				Zero::zero(),
+				T::Currency::balance(stable_currency_id, &collateral_account),
+			);
+			auditor.check(
+				"cdp treasury: left in module account",
+				currency_id,
+				Zero::zero(),
+				T::Currency::balance(currency_id, &module_account),
+			);
+		}
+
+		auditor.finish()
+	}
+
+	/// Burn the available surplus to offset the same amount of available bad debt.
+	fn offset_surplus_and_debit() {
+		let offset_amount = rstd::cmp::min(Self::available_surplus(), Self::available_debit());
+		if !offset_amount.is_zero()
+			&& T::Currency::withdraw(
+				T::GetStableCurrencyId::get(),
+				&Self::sub_account_id(T::GetStableCurrencyId::get()),
+				offset_amount,
+			)
+			.is_ok()
+		{
+			<SurplusPool<T>>::mutate(|surplus| *surplus -= offset_amount);
+			<DebitPool<T>>::mutate(|debit| *debit -= offset_amount);
+			Self::deposit_event(RawEvent::OffsetSurplusAndDebit(offset_amount));
+		}
+	}
+
+	/// Hand the residual surplus or bad debt to auctions, at most one of each per block.
+	fn trigger_auctions() {
+		let surplus_auction_fixed_size = Self::surplus_auction_fixed_size();
+		if !surplus_auction_fixed_size.is_zero()
+			&& Self::surplus_buffer_size()
+				.checked_add(&surplus_auction_fixed_size)
+				.map_or(false, |threshold| Self::available_surplus() >= threshold)
+		{
+			T::AuctionManagerHandler::new_surplus_auction(surplus_auction_fixed_size);
+		}
+
+		let debit_auction_fixed_size = Self::debit_auction_fixed_size();
+		if !debit_auction_fixed_size.is_zero() && Self::available_debit() >= debit_auction_fixed_size {
+			T::AuctionManagerHandler::new_debit_auction(
+				Self::initial_amount_per_debit_auction(),
+				debit_auction_fixed_size,
+			);
+		}
+	}
+}
+
+impl<T: Trait> CDPTreasury<T::AccountId> for Module<T> {
+	type CurrencyId = CurrencyIdOf<T>;
+	type Balance = BalanceOf<T>;
+
+	fn get_surplus_pool() -> Self::Balance {
+		Self::surplus_pool()
+	}
+
+	fn get_debit_pool() -> Self::Balance {
+		Self::debit_pool()
+	}
+
+	fn get_total_collaterals(currency_id: Self::CurrencyId) -> Self::Balance {
+		Self::total_collaterals(currency_id)
+	}
+
+	fn on_system_debit(amount: Self::Balance) -> DispatchResult {
+		let new_debit_pool = Self::debit_pool()
+			.checked_add(&amount)
+			.ok_or(Error::<T>::DebitPoolOverflow)?;
+		<DebitPool<T>>::put(new_debit_pool);
+		Self::deposit_event(RawEvent::SystemDebit(amount));
+
+		Ok(())
+	}
+
+	fn on_system_surplus(amount: Self::Balance) -> DispatchResult {
+		let new_surplus_pool = Self::surplus_pool()
+			.checked_add(&amount)
+			.ok_or(Error::<T>::SurplusPoolOverflow)?;
+		let stable_currency_id = T::GetStableCurrencyId::get();
+		T::Currency::deposit(stable_currency_id, &Self::sub_account_id(stable_currency_id), amount)
+			.map_err(|_| Error::<T>::SurplusPoolOverflow)?;
+		<SurplusPool<T>>::put(new_surplus_pool);
+		Self::deposit_event(RawEvent::SystemSurplus(amount));
+
+		Ok(())
+	}
+
+	fn issue_debit(who: &T::AccountId, amount: Self::Balance) -> DispatchResult {
+		let new_debit_pool = Self::debit_pool()
+			.checked_add(&amount)
+			.ok_or(Error::<T>::DebitPoolOverflow)?;
+		T::Currency::deposit(T::GetStableCurrencyId::get(), who, amount).map_err(|_| Error::<T>::DebitPoolOverflow)?;
+		<DebitPool<T>>::put(new_debit_pool);
+		Self::deposit_event(RawEvent::IssueDebit(who.clone(), amount));
+
+		Ok(())
+	}
+
+	fn deposit_surplus(from: &T::AccountId, amount: Self::Balance) -> DispatchResult {
+		let new_surplus_pool = Self::surplus_pool()
+			.checked_add(&amount)
+			.ok_or(Error::<T>::SurplusPoolOverflow)?;
+		Self::hold(T::GetStableCurrencyId::get(), from, amount)?;
+		<SurplusPool<T>>::put(new_surplus_pool);
+		Self::deposit_event(RawEvent::DepositSurplus(from.clone(), amount));
+
+		Ok(())
+	}
+
+	fn withdraw_surplus(to: &T::AccountId, amount: Self::Balance) -> DispatchResult {
+		let new_surplus_pool = Self::surplus_pool()
+			.checked_sub(&amount)
+			.ok_or(Error::<T>::SurplusNotEnough)?;
+		Self::release(T::GetStableCurrencyId::get(), to, amount)?;
+		<SurplusPool<T>>::put(new_surplus_pool);
+		Self::deposit_event(RawEvent::WithdrawSurplus(to.clone(), amount));
+
+		Ok(())
+	}
+
+	fn deposit_collateral(from: &T::AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult {
+		let new_total_collaterals = Self::total_collaterals(currency_id)
+			.checked_add(&amount)
+			.ok_or(Error::<T>::CollateralOverflow)?;
+		Self::hold(currency_id, from, amount)?;
+		<TotalCollaterals<T>>::insert(currency_id, new_total_collaterals);
+		Self::deposit_event(RawEvent::DepositCollateral(from.clone(), currency_id, amount));
+
+		Ok(())
+	}
+
+	fn withdraw_collateral(to: &T::AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult {
+		ensure!(
+			Self::total_collaterals(currency_id) >= amount,
+			Error::<T>::CollateralNotEnough
+		);
+		Self::release(currency_id, to, amount)?;
+		<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance -= amount);
+		Self::deposit_event(RawEvent::WithdrawCollateral(to.clone(), currency_id, amount));
+
+		Ok(())
+	}
+}
+
+impl<T: Trait> OnEmergencyShutdown for Module<T> {
+	/// All the dex liquidity is withdrawn, for the collaterals and the surplus to be settled.
+	fn on_emergency_shutdown() {
+		<IsShutdown>::put(true);
+		for (currency_id, share_amount) in <DexShares<T>>::enumerate().collect::<Vec<_>>() {
+			let _ = Self::withdraw_dex_liquidity(currency_id, share_amount);
+		}
+	}
+}
@@ -0,0 +1,274 @@
+//! Mocks for the cdp treasury module.
+
+#![cfg(test)]
+
+use frame_support::{impl_outer_origin, parameter_types, traits::Contains};
+use orml_traits::PriceProvider;
+use primitives::H256;
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	DispatchResult, ModuleId, Perbill,
+};
+use support::{FixedU128Ext, Price, Rate, Ratio};
+
+use super::*;
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+
+// Workaround for https://github.com/rust-lang/rust/issues/26925 . Remove when sorted.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC];
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(10);
+}
+
+pub type AccountId = u64;
+pub type BlockNumber = u64;
+pub type AuctionId = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
+pub type Balance = u64;
+pub type Amount = i64;
+pub type Share = u64;
+
+impl system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type ModuleToIndex = ();
+}
+
+impl orml_tokens::Trait for Runtime {
+	type Event = ();
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+impl dex::Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CAROL: AccountId = 3;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+
+thread_local! {
+	static BTC_PRICE: std::cell::RefCell<Option<Price>> = std::cell::RefCell::new(Some(Price::from_natural(10)));
+	static TOTAL_SURPLUS_IN_AUCTION: std::cell::RefCell<Balance> = std::cell::RefCell::new(0);
+	static TOTAL_DEBIT_IN_AUCTION: std::cell::RefCell<Balance> = std::cell::RefCell::new(0);
+	static SURPLUS_AUCTIONS: std::cell::RefCell<u32> = std::cell::RefCell::new(0);
+	static DEBIT_AUCTIONS: std::cell::RefCell<u32> = std::cell::RefCell::new(0);
+}
+
+pub struct MockPriceSource;
+impl MockPriceSource {
+	pub fn set_btc_price(price: Option<Price>) {
+		BTC_PRICE.with(|v| *v.borrow_mut() = price);
+	}
+}
+impl PriceProvider<CurrencyId, Price> for MockPriceSource {
+	fn get_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
+		match (base, quote) {
+			(AUSD, BTC) => BTC_PRICE.with(|v| *v.borrow()),
+			_ => None,
+		}
+	}
+}
+
+/// Records the auctions requested by the treasury.
+pub struct MockAuctionManager;
+impl MockAuctionManager {
+	pub fn surplus_auctions() -> u32 {
+		SURPLUS_AUCTIONS.with(|v| *v.borrow())
+	}
+
+	pub fn debit_auctions() -> u32 {
+		DEBIT_AUCTIONS.with(|v| *v.borrow())
+	}
+}
+
+impl AuctionManager<AccountId> for MockAuctionManager {
+	type CurrencyId = CurrencyId;
+	type Balance = Balance;
+	type AuctionId = AuctionId;
+	#[allow(unused_variables)]
+	fn new_collateral_auction(
+		who: AccountId,
+		currency_id: Self::CurrencyId,
+		amount: Self::Balance,
+		target: Self::Balance,
+	) -> Vec<Self::AuctionId> {
+		vec![]
+	}
+
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId> {
+		TOTAL_SURPLUS_IN_AUCTION.with(|v| *v.borrow_mut() += amount);
+		SURPLUS_AUCTIONS.with(|v| *v.borrow_mut() += 1);
+		Some(0)
+	}
+
+	fn new_debit_auction(_initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId> {
+		TOTAL_DEBIT_IN_AUCTION.with(|v| *v.borrow_mut() += fix_debit);
+		DEBIT_AUCTIONS.with(|v| *v.borrow_mut() += 1);
+		Some(0)
+	}
+
+	fn cancel_auction(_id: Self::AuctionId) -> DispatchResult {
+		Ok(())
+	}
+
+	fn get_total_collateral_in_auction(_currency_id: Self::CurrencyId) -> Self::Balance {
+		0
+	}
+
+	fn get_total_surplus_in_auction() -> Self::Balance {
+		TOTAL_SURPLUS_IN_AUCTION.with(|v| *v.borrow())
+	}
+
+	fn get_total_debit_in_auction() -> Self::Balance {
+		TOTAL_DEBIT_IN_AUCTION.with(|v| *v.borrow())
+	}
+}
+
+/// The council whose members update the parameters of the treasury instead of root.
+pub struct CouncilMembers;
+impl Contains<AccountId> for CouncilMembers {
+	fn sorted_members() -> Vec<AccountId> {
+		vec![CAROL]
+	}
+}
+
+impl Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = MockAuctionManager;
+	type UpdateOrigin = system::EnsureSignedBy<CouncilMembers, AccountId>;
+	type Dex = DexModule;
+	type PriceSource = MockPriceSource;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = Module<Runtime>;
+
+pub struct ExtBuilder {
+	currency_id: Vec<CurrencyId>,
+	endowed_accounts: Vec<AccountId>,
+	initial_balance: Balance,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			currency_id: vec![AUSD, BTC],
+			endowed_accounts: vec![ALICE, BOB],
+			initial_balance: 1000,
+		}
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> AuditedExternalities {
+		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+		orml_tokens::GenesisConfig::<Runtime> {
+			tokens: self.currency_id,
+			initial_balance: self.initial_balance,
+			endowed_accounts: self.endowed_accounts,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		dex::GenesisConfig::<Runtime> {
+			enabled_trading_pairs: vec![BTC],
+			initial_pools: vec![],
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		AuditedExternalities(t.into())
+	}
+}
+
+/// Externalities auditing the accounting after every execution.
+pub struct AuditedExternalities(runtime_io::TestExternalities);
+
+impl AuditedExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			assert_eq!(CdpTreasuryModule::audit(&[BTC]), Ok(()));
+			assert_eq!(
+				DexModule::audit(
+					&[AUSD, BTC],
+					&[
+						ALICE,
+						BOB,
+						CdpTreasuryModule::account_id(),
+						CdpTreasuryModule::sub_account_id(BTC),
+					]
+				),
+				Ok(())
+			);
+			result
+		})
+	}
+}
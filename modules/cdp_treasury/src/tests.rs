@@ -0,0 +1,501 @@
+//! Unit tests for the cdp treasury module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{
+	Balance, CdpTreasuryModule, DexModule, ExtBuilder, MockAuctionManager, MockPriceSource, Origin, Runtime, Tokens,
+	ALICE, AUSD, BOB, BTC, CAROL,
+};
+use sp_runtime::traits::{OnFinalize, OnInitialize};
+use support::{InvariantViolation, STORAGE_ACCESS_WEIGHT};
+
+#[test]
+fn set_debit_and_surplus_handle_params_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(CdpTreasuryModule::set_debit_and_surplus_handle_params(
+			Origin::signed(ALICE),
+			Some(100),
+			None,
+			None,
+			None
+		)
+		.is_err());
+		assert!(
+			CdpTreasuryModule::set_debit_and_surplus_handle_params(Origin::ROOT, Some(100), None, None, None).is_err()
+		);
+		assert_ok!(CdpTreasuryModule::set_debit_and_surplus_handle_params(
+			Origin::signed(CAROL),
+			Some(100),
+			Some(1000),
+			Some(200),
+			Some(300),
+		));
+		assert_eq!(CdpTreasuryModule::surplus_auction_fixed_size(), 100);
+		assert_eq!(CdpTreasuryModule::surplus_buffer_size(), 1000);
+		assert_eq!(CdpTreasuryModule::initial_amount_per_debit_auction(), 200);
+		assert_eq!(CdpTreasuryModule::debit_auction_fixed_size(), 300);
+	});
+}
+
+#[test]
+fn on_system_debit_and_surplus_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::on_system_debit(100));
+		assert_eq!(CdpTreasuryModule::debit_pool(), 100);
+		assert_ok!(CdpTreasuryModule::on_system_surplus(50));
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 50);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 50);
+		assert_noop!(
+			CdpTreasuryModule::on_system_debit(Balance::max_value()),
+			Error::<Runtime>::DebitPoolOverflow
+		);
+	});
+}
+
+#[test]
+fn issue_debit_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::issue_debit(&ALICE, 100));
+		assert_eq!(Tokens::balance(AUSD, &ALICE), 1100);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 100);
+	});
+}
+
+#[test]
+fn deposit_and_withdraw_surplus_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::deposit_surplus(&ALICE, 100));
+		assert_eq!(Tokens::balance(AUSD, &ALICE), 900);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 100);
+		assert_noop!(
+			CdpTreasuryModule::withdraw_surplus(&BOB, 101),
+			Error::<Runtime>::SurplusNotEnough
+		);
+		assert_ok!(CdpTreasuryModule::withdraw_surplus(&BOB, 60));
+		assert_eq!(Tokens::balance(AUSD, &BOB), 1060);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 40);
+	});
+}
+
+#[test]
+fn deposit_and_withdraw_collateral_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			CdpTreasuryModule::deposit_collateral(&ALICE, BTC, 1001),
+			Error::<Runtime>::CollateralTransferFailed
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&ALICE, BTC, 100));
+		assert_eq!(Tokens::balance(BTC, &ALICE), 900);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+		assert_noop!(
+			CdpTreasuryModule::withdraw_collateral(&BOB, BTC, 101),
+			Error::<Runtime>::CollateralNotEnough
+		);
+		assert_ok!(CdpTreasuryModule::withdraw_collateral(&BOB, BTC, 30));
+		assert_eq!(Tokens::balance(BTC, &BOB), 1030);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 70);
+	});
+}
+
+#[test]
+fn offset_surplus_and_debit_on_finalize_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::on_system_surplus(500));
+		assert_ok!(CdpTreasuryModule::on_system_debit(300));
+		CdpTreasuryModule::on_finalize(1);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 200);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 200);
+	});
+}
+
+#[test]
+fn offset_before_triggering_auctions_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::set_debit_and_surplus_handle_params(
+			Origin::signed(CAROL),
+			Some(100),
+			Some(100),
+			Some(1000),
+			Some(100),
+		));
+		assert_ok!(CdpTreasuryModule::on_system_surplus(500));
+		assert_ok!(CdpTreasuryModule::on_system_debit(200));
+
+		// 200 of surplus offsets all debit, the residual 300 triggers a surplus auction, no debit auction
+		CdpTreasuryModule::on_finalize(1);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 300);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+		assert_eq!(MockAuctionManager::surplus_auctions(), 1);
+		assert_eq!(MockAuctionManager::debit_auctions(), 0);
+
+		// surplus in auction isn't offset, the residual debit triggers a debit auction
+		assert_ok!(CdpTreasuryModule::on_system_debit(300));
+		CdpTreasuryModule::on_finalize(2);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 100);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 100);
+		assert_eq!(MockAuctionManager::surplus_auctions(), 1);
+		assert_eq!(MockAuctionManager::debit_auctions(), 1);
+	});
+}
+
+#[test]
+fn no_auctions_after_emergency_shutdown() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::set_debit_and_surplus_handle_params(
+			Origin::signed(CAROL),
+			Some(100),
+			Some(100),
+			Some(1000),
+			Some(100),
+		));
+		assert_ok!(CdpTreasuryModule::on_system_surplus(500));
+		assert_ok!(CdpTreasuryModule::on_system_debit(200));
+		CdpTreasuryModule::on_emergency_shutdown();
+		assert_eq!(CdpTreasuryModule::is_shutdown(), true);
+
+		// surplus still offsets debit, but the residual isn't auctioned
+		CdpTreasuryModule::on_finalize(1);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 300);
+		assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+		assert_eq!(MockAuctionManager::surplus_auctions(), 0);
+		assert_eq!(MockAuctionManager::debit_auctions(), 0);
+	});
+}
+
+#[test]
+fn deposit_and_withdraw_liquidity_to_dex_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&ALICE, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 100));
+		assert_ok!(CdpTreasuryModule::on_system_surplus(1000));
+
+		assert!(CdpTreasuryModule::deposit_liquidity_to_dex(Origin::signed(ALICE), BTC, 100, 1000).is_err());
+		assert_noop!(
+			CdpTreasuryModule::deposit_liquidity_to_dex(Origin::signed(CAROL), BTC, 101, 1000),
+			Error::<Runtime>::CollateralNotEnough
+		);
+		assert_noop!(
+			CdpTreasuryModule::deposit_liquidity_to_dex(Origin::signed(CAROL), BTC, 100, 1001),
+			Error::<Runtime>::SurplusNotEnough
+		);
+		assert_ok!(CdpTreasuryModule::deposit_liquidity_to_dex(
+			Origin::signed(CAROL),
+			BTC,
+			100,
+			1000
+		));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 0);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+		assert_eq!(CdpTreasuryModule::dex_shares(BTC), 1000);
+		assert_eq!(DexModule::liquidity_pool(BTC), (200, 2000));
+		assert_eq!(DexModule::shares(BTC, CdpTreasuryModule::sub_account_id(BTC)), 1000);
+
+		assert!(CdpTreasuryModule::withdraw_liquidity_from_dex(Origin::signed(ALICE), BTC, 400).is_err());
+		assert_noop!(
+			CdpTreasuryModule::withdraw_liquidity_from_dex(Origin::signed(CAROL), BTC, 1001),
+			Error::<Runtime>::ShareNotEnough
+		);
+		assert_ok!(CdpTreasuryModule::withdraw_liquidity_from_dex(
+			Origin::signed(CAROL),
+			BTC,
+			400
+		));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 40);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 400);
+		assert_eq!(CdpTreasuryModule::dex_shares(BTC), 600);
+		assert_eq!(DexModule::liquidity_pool(BTC), (160, 1600));
+
+		assert_ok!(CdpTreasuryModule::withdraw_liquidity_from_dex(
+			Origin::signed(CAROL),
+			BTC,
+			600
+		));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 1000);
+		assert_eq!(CdpTreasuryModule::dex_shares(BTC), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 1000));
+	});
+}
+
+#[test]
+fn emergency_shutdown_withdraws_dex_liquidity() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&ALICE, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 100));
+		assert_ok!(CdpTreasuryModule::on_system_surplus(1000));
+		assert_ok!(CdpTreasuryModule::deposit_liquidity_to_dex(
+			Origin::signed(CAROL),
+			BTC,
+			100,
+			1000
+		));
+
+		CdpTreasuryModule::on_emergency_shutdown();
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 100);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 1000);
+		assert_eq!(CdpTreasuryModule::dex_shares(BTC), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 1000));
+		assert_noop!(
+			CdpTreasuryModule::deposit_liquidity_to_dex(Origin::signed(CAROL), BTC, 100, 1000),
+			Error::<Runtime>::AlreadyShutdown
+		);
+	});
+}
+
+#[test]
+fn set_collateral_retention_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(CdpTreasuryModule::set_collateral_retention(Origin::signed(ALICE), BTC, Some(10)).is_err());
+		assert_ok!(CdpTreasuryModule::set_collateral_retention(
+			Origin::signed(CAROL),
+			BTC,
+			Some(10)
+		));
+		assert_eq!(CdpTreasuryModule::collateral_retention(BTC), Some(10));
+		assert_ok!(CdpTreasuryModule::set_collateral_retention(
+			Origin::signed(CAROL),
+			BTC,
+			None
+		));
+		assert_eq!(CdpTreasuryModule::collateral_retention(BTC), None);
+	});
+}
+
+#[test]
+fn swap_collateral_to_stable_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&ALICE, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 20));
+
+		// nothing is sold without a retention
+		assert_ok!(CdpTreasuryModule::swap_collateral_to_stable(BTC));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 20);
+
+		assert_ok!(CdpTreasuryModule::set_collateral_retention(
+			Origin::signed(CAROL),
+			BTC,
+			Some(10)
+		));
+		MockPriceSource::set_btc_price(None);
+		assert_noop!(
+			CdpTreasuryModule::swap_collateral_to_stable(BTC),
+			Error::<Runtime>::InvalidFeedPrice
+		);
+
+		// 10 BTC is worth 100 at the feed price, no less than 90 is accepted
+		MockPriceSource::set_btc_price(Some(Price::from_natural(10)));
+		assert_ok!(CdpTreasuryModule::swap_collateral_to_stable(BTC));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 10);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 90);
+		assert_eq!(DexModule::liquidity_pool(BTC), (110, 910));
+
+		// nothing above the retention
+		assert_ok!(CdpTreasuryModule::swap_collateral_to_stable(BTC));
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 10);
+	});
+}
+
+#[test]
+fn swap_collateral_to_stable_rejects_slippage() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 20));
+		assert_ok!(CdpTreasuryModule::set_collateral_retention(
+			Origin::signed(CAROL),
+			BTC,
+			Some(10)
+		));
+
+		// no liquidity at all
+		assert_noop!(
+			CdpTreasuryModule::swap_collateral_to_stable(BTC),
+			dex::Error::<Runtime>::LiquidityPoolNotExists
+		);
+
+		// 10 BTC is worth 110 at the feed price, but the pool only pays 90
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&ALICE, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		MockPriceSource::set_btc_price(Some(Price::from_natural(11)));
+		assert_noop!(
+			CdpTreasuryModule::swap_collateral_to_stable(BTC),
+			dex::Error::<Runtime>::InacceptablePrice
+		);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 20);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 1000));
+	});
+}
+
+#[test]
+fn swap_collateral_to_stable_on_initialize() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&ALICE, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 20));
+		assert_ok!(CdpTreasuryModule::set_collateral_retention(
+			Origin::signed(CAROL),
+			BTC,
+			Some(10)
+		));
+		CdpTreasuryModule::on_initialize(1);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 10);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 90);
+
+		// the collaterals are kept for settlement after shutdown
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&BOB, BTC, 10));
+		CdpTreasuryModule::on_emergency_shutdown();
+		CdpTreasuryModule::on_initialize(2);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 20);
+	});
+}
+
+#[test]
+fn funds_are_held_by_sub_accounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ne!(
+			CdpTreasuryModule::sub_account_id(AUSD),
+			CdpTreasuryModule::sub_account_id(BTC)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&ALICE, BTC, 100));
+		assert_ok!(CdpTreasuryModule::deposit_surplus(&ALICE, 100));
+		assert_eq!(Tokens::balance(BTC, &CdpTreasuryModule::sub_account_id(BTC)), 100);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 100);
+		assert_eq!(Tokens::balance(BTC, &CdpTreasuryModule::account_id()), 0);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::account_id()), 0);
+
+		// the unused stable coin goes back to the surplus account
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&BOB, BTC, 100, 500),
+			(100, 500, 500)
+		);
+		assert_ok!(CdpTreasuryModule::deposit_liquidity_to_dex(
+			Origin::signed(CAROL),
+			BTC,
+			10,
+			100
+		));
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 50);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 50);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(BTC)), 0);
+		assert_eq!(Tokens::balance(BTC, &CdpTreasuryModule::sub_account_id(BTC)), 90);
+
+		assert_ok!(CdpTreasuryModule::withdraw_collateral(&BOB, BTC, 90));
+		assert_ok!(CdpTreasuryModule::withdraw_surplus(&BOB, 50));
+		assert_eq!(Tokens::balance(BTC, &CdpTreasuryModule::sub_account_id(BTC)), 0);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 0);
+	});
+}
+
+#[test]
+fn audit_checks_each_sub_account() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpTreasuryModule::deposit_collateral(&ALICE, BTC, 100));
+		assert_ok!(CdpTreasuryModule::deposit_surplus(&ALICE, 50));
+		assert_eq!(CdpTreasuryModule::audit(&[BTC]), Ok(()));
+
+		// the totals still match, but the stable coin is held by the wrong sub-account
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(
+			AUSD,
+			&CdpTreasuryModule::sub_account_id(AUSD),
+			&CdpTreasuryModule::sub_account_id(BTC),
+			10
+		));
+		assert_eq!(
+			CdpTreasuryModule::audit(&[BTC]),
+			Err(vec![
+				InvariantViolation {
+					invariant: "cdp treasury: surplus held",
+					currency_id: AUSD,
+					recorded: 50,
+					actual: 40,
+				},
+				InvariantViolation {
+					invariant: "cdp treasury: stable coin left in collateral account",
+					currency_id: AUSD,
+					recorded: 0,
+					actual: 10,
+				},
+			])
+		);
+
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(
+			AUSD,
+			&CdpTreasuryModule::sub_account_id(BTC),
+			&CdpTreasuryModule::sub_account_id(AUSD),
+			10
+		));
+		assert_eq!(CdpTreasuryModule::audit(&[BTC]), Ok(()));
+	});
+}
+
+#[test]
+fn on_runtime_upgrade_moves_funds_to_sub_accounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		// written before the storage is versioned, with everything held by the module account
+		let module_account = CdpTreasuryModule::account_id();
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(
+			AUSD,
+			&ALICE,
+			&module_account,
+			200
+		));
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(BTC, &ALICE, &module_account, 60));
+		<SurplusPool<Runtime>>::put(100);
+		<TotalCollaterals<Runtime>>::insert(BTC, 50);
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&BOB, BTC, 100, 1000),
+			(100, 1000, 1000)
+		);
+		assert_ok!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&module_account, BTC, 10, 100),
+			(10, 100, 100)
+		);
+		<DexShares<Runtime>>::insert(BTC, 100);
+		assert!(!<Version>::exists());
+		assert_eq!(CdpTreasuryModule::storage_version(), StorageVersion::V0);
+		assert!(CdpTreasuryModule::audit(&[BTC]).is_err());
+
+		assert!(CdpTreasuryModule::on_runtime_upgrade() > STORAGE_ACCESS_WEIGHT * 2);
+		assert_eq!(CdpTreasuryModule::storage_version(), StorageVersion::V1);
+		assert_eq!(CdpTreasuryModule::storage_version().next(), None);
+		assert_eq!(CdpTreasuryModule::dex_shares(BTC), 0);
+		assert_eq!(DexModule::shares(BTC, &module_account), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 1000));
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 200);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 60);
+		assert_eq!(Tokens::balance(AUSD, &CdpTreasuryModule::sub_account_id(AUSD)), 200);
+		assert_eq!(Tokens::balance(BTC, &CdpTreasuryModule::sub_account_id(BTC)), 60);
+		assert_eq!(Tokens::balance(AUSD, &module_account), 0);
+		assert_eq!(Tokens::balance(BTC, &module_account), 0);
+		assert_eq!(CdpTreasuryModule::audit(&[BTC]), Ok(()));
+
+		// nothing pending, only the version is read
+		assert_eq!(CdpTreasuryModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT);
+		assert_eq!(CdpTreasuryModule::surplus_pool(), 200);
+	});
+}
+
+#[test]
+fn migrations_are_left_to_the_runtime_upgrade() {
+	ExtBuilder::default().build().execute_with(|| {
+		CdpTreasuryModule::on_initialize(1);
+		assert_eq!(CdpTreasuryModule::storage_version(), StorageVersion::V0);
+		CdpTreasuryModule::on_runtime_upgrade();
+		assert_eq!(CdpTreasuryModule::storage_version(), StorageVersion::V1);
+	});
+}
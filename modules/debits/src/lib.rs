@@ -5,7 +5,8 @@ use rstd::{
 	convert::{TryFrom, TryInto},
 	result,
 };
-use sp_runtime::traits::{Convert, MaybeSerializeDeserialize, Member, SimpleArithmetic};
+use sp_runtime::traits::{MaybeSerializeDeserialize, Member, SimpleArithmetic};
+use support::DebitConvertor;
 use traits::{
 	arithmetic::{self, Signed},
 	MultiCurrency, MultiCurrencyExtended,
@@ -21,7 +22,8 @@ pub trait Trait: system::Trait {
 	type Currency: MultiCurrencyExtended<Self::AccountId, CurrencyId = Self::CurrencyId>;
 	type GetStableCurrencyId: Get<Self::CurrencyId>;
 	type DebitBalance: Parameter + Member + SimpleArithmetic + Default + Copy + MaybeSerializeDeserialize;
-	type Convert: Convert<(Self::CurrencyId, Self::DebitBalance), BalanceOf<Self>>;
+	/// Mints the stable coin the debit is worth rounded down, and burns it rounded up.
+	type Convert: DebitConvertor<Self::CurrencyId, Self::DebitBalance, BalanceOf<Self>>;
 	type DebitAmount: Signed
 		+ TryInto<Self::DebitBalance>
 		+ TryFrom<Self::DebitBalance>
@@ -34,12 +36,14 @@ pub trait Trait: system::Trait {
 }
 
 decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+	}
 }
 
 decl_error! {
 	/// Error for debit module.
-	pub enum Error {
+	pub enum Error for Module<T: Trait> {
 		DebitDepositFailed,
 		DebitWithdrawFailed,
 		AmountIntoDebitBalanceFailed,
@@ -51,7 +55,7 @@ impl<T: Trait> Module<T> {}
 impl<T: Trait> MultiCurrency<T::AccountId> for Module<T> {
 	type Balance = T::DebitBalance;
 	type CurrencyId = T::CurrencyId;
-	type Error = Error;
+	type Error = Error<T>;
 
 	// be of no effect
 	fn ensure_can_withdraw(
@@ -87,9 +91,9 @@ impl<T: Trait> MultiCurrency<T::AccountId> for Module<T> {
 		who: &T::AccountId,
 		debit_amount: Self::Balance,
 	) -> result::Result<(), Self::Error> {
-		let stable_coin_amount: BalanceOf<T> = T::Convert::convert((currency_id, debit_amount));
+		let stable_coin_amount = T::Convert::convert_debit_to_balance_floor(currency_id, debit_amount);
 		T::Currency::deposit(T::GetStableCurrencyId::get(), who, stable_coin_amount)
-			.map_err(|_| Error::DebitDepositFailed)
+			.map_err(|_| Error::<T>::DebitDepositFailed)
 	}
 
 	fn withdraw(
@@ -97,9 +101,9 @@ impl<T: Trait> MultiCurrency<T::AccountId> for Module<T> {
 		who: &T::AccountId,
 		debit_amount: Self::Balance,
 	) -> result::Result<(), Self::Error> {
-		let stable_coin_amount: BalanceOf<T> = T::Convert::convert((currency_id, debit_amount));
+		let stable_coin_amount = T::Convert::convert_debit_to_balance_ceil(currency_id, debit_amount);
 		T::Currency::withdraw(T::GetStableCurrencyId::get(), who, stable_coin_amount)
-			.map_err(|_| Error::DebitWithdrawFailed)
+			.map_err(|_| Error::<T>::DebitWithdrawFailed)
 	}
 
 	// be of no effect
@@ -116,8 +120,8 @@ impl<T: Trait> MultiCurrencyExtended<T::AccountId> for Module<T> {
 		who: &T::AccountId,
 		debit_amount: Self::Amount,
 	) -> Result<(), Self::Error> {
-		let debit_balance =
-			TryInto::<Self::Balance>::try_into(debit_amount.abs()).map_err(|_| Error::AmountIntoDebitBalanceFailed)?;
+		let debit_balance = TryInto::<Self::Balance>::try_into(debit_amount.abs())
+			.map_err(|_| Error::<T>::AmountIntoDebitBalanceFailed)?;
 		if debit_amount.is_positive() {
 			Self::deposit(currency_id, who, debit_balance)
 		} else {
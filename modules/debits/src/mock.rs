@@ -5,6 +5,7 @@
 use frame_support::{impl_outer_event, impl_outer_origin, parameter_types};
 use primitives::H256;
 use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use support::{DebitExchangeRateConvertor, ExchangeRate, ExchangeRateProvider};
 
 use super::*;
 
@@ -36,7 +37,7 @@ type BlockNumber = u64;
 pub type Balance = u64;
 pub type DebitBalance = u32;
 pub type Amount = i64;
-pub type CurrencyId = u32;
+pub type CurrencyId = module_primitives::CurrencyId;
 
 impl system::Trait for Runtime {
 	type Origin = Origin;
@@ -54,6 +55,7 @@ impl system::Trait for Runtime {
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = ();
 }
 
 impl orml_tokens::Trait for Runtime {
@@ -101,22 +103,20 @@ impl Trait for Runtime {
 	type Currency = Currencies;
 	type GetStableCurrencyId = GetStableCurrencyId;
 	type DebitBalance = DebitBalance;
-	type Convert = ConvertHandler;
+	type Convert = DebitExchangeRateConvertor<MockExchangeRateProvider>;
 	type DebitAmount = Amount;
 }
 pub type DebitsModule = Module<Runtime>;
 
 pub const ALICE: AccountId = 1;
-pub const ACA: CurrencyId = 0;
-pub const AUSD: CurrencyId = 1;
-pub const BTC: CurrencyId = 2;
-
-pub struct ConvertHandler;
-impl Convert<(CurrencyId, DebitBalance), Balance> for ConvertHandler {
-	fn convert(a: (CurrencyId, DebitBalance)) -> Balance {
-		let debit_balance: u32 = (a.1 / DebitBalance::from(2u32)).into();
-		let balance = debit_balance as u64;
-		balance
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+
+pub struct MockExchangeRateProvider;
+impl ExchangeRateProvider<CurrencyId> for MockExchangeRateProvider {
+	fn get_exchange_rate(_currency_id: CurrencyId) -> ExchangeRate {
+		ExchangeRate::from_rational(1, 2)
 	}
 }
 
@@ -32,3 +32,13 @@ fn withdraw_should_work() {
 		assert_eq!(Currencies::balance(AUSD, &ALICE), 950);
 	});
 }
+
+#[test]
+fn deposit_rounds_down_and_withdraw_rounds_up() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DebitsModule::deposit(AUSD, &ALICE, 101));
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 1050);
+		assert_ok!(DebitsModule::withdraw(AUSD, &ALICE, 101));
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 999);
+	});
+}
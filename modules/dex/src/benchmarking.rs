@@ -0,0 +1,153 @@
+//! Benchmarks of the dispatchables of the dex, each dispatched from its worst case: the pools already hold liquidity
+//! and shares, the caller is a new share holder, and the swaps go through two pools.
+
+use super::*;
+use primitives::hashing::blake2_256;
+use sp_runtime::traits::Dispatchable;
+use std::time::Instant;
+
+/// The liquidity of both sides of each pool set up.
+const POOL_AMOUNT: u32 = 1_000_000_000;
+
+/// The supply amount of each swap, small enough for any price impact limit of the pools.
+const SWAP_AMOUNT: u32 = 1_000_000;
+
+/// A dispatchable of the dex from its worst case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Benchmark {
+	/// `swap_with_exact_supply` of the base currency for an other currency.
+	SwapWithExactSupplyFromBase,
+	/// `swap_with_exact_supply` of an other currency for the base currency.
+	SwapWithExactSupplyToBase,
+	/// `swap_with_exact_supply` between two other currencies, through both their pools.
+	SwapWithExactSupply,
+	/// `swap_with_exact_target` between two other currencies, through both their pools.
+	SwapWithExactTarget,
+	/// `swap_by_path` through a path of this many currencies.
+	SwapByPath(u32),
+	/// `swap_currency_batch` of this many allocations, each through two pools.
+	SwapCurrencyBatch(u32),
+	/// `add_liquidity` to a pool without liquidity.
+	AddInitialLiquidity,
+	/// `add_liquidity` to a pool with liquidity.
+	AddLiquidity,
+	/// `withdraw_liquidity` of all the shares of the caller.
+	WithdrawLiquidity,
+}
+
+/// All the benchmarks, the ones with a component at its smallest and its largest value.
+pub fn benchmarks<T: Trait>() -> Vec<Benchmark> {
+	vec![
+		Benchmark::SwapWithExactSupplyFromBase,
+		Benchmark::SwapWithExactSupplyToBase,
+		Benchmark::SwapWithExactSupply,
+		Benchmark::SwapWithExactTarget,
+		Benchmark::SwapByPath(2),
+		// no currency is visited twice, so the longest path goes through the base currency once
+		Benchmark::SwapByPath(T::MaxSwapPathLength::get().min(3)),
+		Benchmark::SwapCurrencyBatch(1),
+		Benchmark::SwapCurrencyBatch(T::MaxBatchSize::get()),
+		Benchmark::AddInitialLiquidity,
+		Benchmark::AddLiquidity,
+		Benchmark::WithdrawLiquidity,
+	]
+}
+
+/// The accounts of the benchmarks, the caller at 0 and the liquidity providers of the pools at 1 and 2. Hashed from
+/// `index` so they're distinct however short the account ids are.
+pub fn account<T: Trait>(index: u32) -> T::AccountId {
+	T::AccountId::decode(&mut &blake2_256(&(b"dex/benchmark", index).encode())[..]).unwrap_or_default()
+}
+
+/// Fund `who` with `amount` of each of `currency_ids`.
+fn fund<T: Trait>(who: &T::AccountId, currency_ids: &[CurrencyIdOf<T>], amount: u32) -> DispatchResult {
+	for &currency_id in currency_ids {
+		T::Currency::deposit(currency_id, who, amount.into())?;
+	}
+	Ok(())
+}
+
+/// Enable the pool of `currency_id` and add liquidity to it from an account of its own.
+fn setup_pool<T: Trait>(currency_id: CurrencyIdOf<T>, provider: u32) -> DispatchResult {
+	let provider = account::<T>(provider);
+	fund::<T>(&provider, &[currency_id, T::GetBaseCurrencyId::get()], POOL_AMOUNT)?;
+	<TradingPairStatus<T>>::insert(currency_id, true);
+	Module::<T>::do_add_liquidity(&provider, currency_id, POOL_AMOUNT.into(), POOL_AMOUNT.into(), 0.into()).map(|_| ())
+}
+
+/// Prepare the worst case of `benchmark` with the pools of the other currencies `currency_ids`, returns the account
+/// to dispatch the call from and the call.
+pub fn setup<T: Trait>(
+	benchmark: Benchmark,
+	currency_ids: (CurrencyIdOf<T>, CurrencyIdOf<T>),
+) -> Result<(T::AccountId, Call<T>), DispatchError> {
+	let base_currency_id = T::GetBaseCurrencyId::get();
+	let (currency_id, other_currency_id) = currency_ids;
+	let caller = account::<T>(0);
+
+	if benchmark != Benchmark::AddInitialLiquidity {
+		setup_pool::<T>(currency_id, 1)?;
+		setup_pool::<T>(other_currency_id, 2)?;
+	}
+	fund::<T>(
+		&caller,
+		&[base_currency_id, currency_id, other_currency_id],
+		POOL_AMOUNT,
+	)?;
+
+	let call = match benchmark {
+		Benchmark::SwapWithExactSupplyFromBase => {
+			Call::swap_with_exact_supply((base_currency_id, SWAP_AMOUNT.into()), (currency_id, 0.into()), None)
+		}
+		Benchmark::SwapWithExactSupplyToBase => {
+			Call::swap_with_exact_supply((currency_id, SWAP_AMOUNT.into()), (base_currency_id, 0.into()), None)
+		}
+		Benchmark::SwapWithExactSupply => {
+			Call::swap_with_exact_supply((currency_id, SWAP_AMOUNT.into()), (other_currency_id, 0.into()), None)
+		}
+		Benchmark::SwapWithExactTarget => Call::swap_with_exact_target(
+			(currency_id, POOL_AMOUNT.into()),
+			(other_currency_id, SWAP_AMOUNT.into()),
+			None,
+		),
+		Benchmark::SwapByPath(path_length) => {
+			// the paths alternate between the base currency and the other currencies
+			let path = vec![currency_id, base_currency_id, other_currency_id]
+				.into_iter()
+				.take(path_length as usize)
+				.collect();
+			Call::swap_by_path(path, SWAP_AMOUNT.into(), 0.into(), None)
+		}
+		Benchmark::SwapCurrencyBatch(batch_size) => Call::swap_currency_batch(
+			currency_id,
+			(0..batch_size)
+				.map(|_| (other_currency_id, SWAP_AMOUNT.into(), 0.into()))
+				.collect(),
+		),
+		Benchmark::AddInitialLiquidity => {
+			<TradingPairStatus<T>>::insert(currency_id, true);
+			Call::add_liquidity(currency_id, POOL_AMOUNT.into(), POOL_AMOUNT.into(), 0.into(), None)
+		}
+		Benchmark::AddLiquidity => {
+			Call::add_liquidity(currency_id, POOL_AMOUNT.into(), POOL_AMOUNT.into(), 0.into(), None)
+		}
+		Benchmark::WithdrawLiquidity => {
+			let (_, _, shares) =
+				Module::<T>::do_add_liquidity(&caller, currency_id, POOL_AMOUNT.into(), POOL_AMOUNT.into(), 0.into())?;
+			Call::withdraw_liquidity(currency_id, shares, 0.into(), 0.into(), None)
+		}
+	};
+	Ok((caller, call))
+}
+
+/// The nanoseconds of dispatching the call of `benchmark` from its worst case, see `setup`.
+pub fn measure<T: Trait>(
+	benchmark: Benchmark,
+	currency_ids: (CurrencyIdOf<T>, CurrencyIdOf<T>),
+) -> Result<u128, DispatchError> {
+	let (caller, call) = setup::<T>(benchmark, currency_ids)?;
+	let start = Instant::now();
+	call.dispatch(system::RawOrigin::Signed(caller).into())
+		.map_err(Into::<DispatchError>::into)?;
+	Ok(start.elapsed().as_nanos())
+}
@@ -1,25 +1,97 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get, Parameter};
-use orml_traits::{MultiCurrency, MultiCurrencyExtended};
+use codec::{Decode, Encode};
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::{EnsureOrigin, Get},
+	weights::{SimpleDispatchInfo, Weight},
+	Parameter,
+};
+use orml_traits::{MultiCurrency, MultiCurrencyExtended, PriceProvider};
 use orml_utilities::FixedU128;
-use rstd::{convert::TryInto, result};
+use primitives::{U256, U512};
+use rstd::{
+	convert::{TryFrom, TryInto},
+	prelude::*,
+};
 use sp_runtime::{
 	traits::{
-		AccountIdConversion, Bounded, CheckedAdd, CheckedSub, MaybeSerializeDeserialize, Member, SimpleArithmetic,
+		AccountIdConversion, CheckedAdd, CheckedSub, Convert, MaybeSerializeDeserialize, Member, Saturating,
+		SimpleArithmetic, StaticLookup, UniqueSaturatedInto, ValidateUnsigned,
 	},
-	ModuleId,
+	transaction_validity::{InvalidTransaction, TransactionValidity},
+	DispatchError, DispatchResult, ModuleId, RuntimeDebug,
 };
-use support::DexManager;
-use system::{self as system, ensure_signed};
+use support::{
+	unsigned_keeper_transaction, DexManager, Linkage, OnUpdateShares, Price, Ratio, STORAGE_ACCESS_WEIGHT,
+	UNSIGNED_REPORT_PRIORITY,
+};
+use system::{self as system, ensure_none, ensure_root, ensure_signed, offchain::SubmitUnsignedTransaction};
+
+#[cfg(feature = "std")]
+use sp_runtime::traits::Zero;
+#[cfg(feature = "std")]
+use support::{AuditResult, Auditor};
 
+#[cfg(feature = "runtime-benchmarks")]
+pub mod benchmarking;
 mod mock;
 mod tests;
+pub mod weights;
+
+pub use weights::WeightInfo;
 
 const MODULE_ID: ModuleId = ModuleId(*b"aca/dexm");
 
+/// The max number of cumulative price observations kept for each pool, at most one per block.
+pub const MAX_PRICE_OBSERVATIONS: usize = 32;
+
 type BalanceOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
 type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type AmountOf<T> = <<T as Trait>::Currency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
+
+/// Layouts of the module storage, in the order they are migrated through.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum StorageVersion {
+	/// Before the storage is versioned.
+	V0,
+	/// The layout of `V0`, with the version recorded.
+	V1,
+	/// The shares of each account mirrored in the balance of the share currency of the pool.
+	V2,
+	/// The holders of the shares from before the share holders were tracked linked in `ShareHolderLinkage`.
+	V3,
+}
+
+impl Default for StorageVersion {
+	fn default() -> Self {
+		StorageVersion::V0
+	}
+}
+
+/// The reserves and shares of a pool, and the swaps with it since it was initialized.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct PoolInfo<Balance, Share> {
+	pub other_reserve: Balance,
+	pub base_reserve: Balance,
+	pub total_shares: Share,
+	/// The base currency swapped with the pool, saturated at the max balance.
+	pub cumulative_volume: Balance,
+	/// The swaps with the pool, saturated at the max value.
+	pub trade_count: u64,
+}
+
+impl StorageVersion {
+	/// The version migrated to from this one, `None` for the latest.
+	pub fn next(self) -> Option<Self> {
+		match self {
+			StorageVersion::V0 => Some(StorageVersion::V1),
+			StorageVersion::V1 => Some(StorageVersion::V2),
+			StorageVersion::V2 => Some(StorageVersion::V3),
+			StorageVersion::V3 => None,
+		}
+	}
+}
 
 pub trait Trait: system::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -27,453 +99,2473 @@ pub trait Trait: system::Trait {
 	type Share: Parameter + Member + SimpleArithmetic + Default + Copy + MaybeSerializeDeserialize;
 	type GetBaseCurrencyId: Get<CurrencyIdOf<Self>>;
 	type GetExchangeFee: Get<FixedU128>;
+	/// The shares locked to the module account on the first deposit to a pool, so the first liquidity provider
+	/// can't inflate the price of a share.
+	type MinimumLiquidity: Get<Self::Share>;
+	/// The min shares issued by adding liquidity, smaller deposits are dust.
+	type MinimumShareIncrement: Get<Self::Share>;
+	/// The max number of currencies in the path of a swap.
+	type MaxSwapPathLength: Get<u32>;
+	/// The max allocations of a batch swap.
+	type MaxBatchSize: Get<u32>;
+	/// The fraction of the exchange fee of swaps taken out of the pools by the protocol.
+	type GetProtocolFeeRate: Get<FixedU128>;
+	/// The module id of the account receiving the protocol fees.
+	type TreasuryModuleId: Get<ModuleId>;
+	/// The max share holders refunded per block while closing pools.
+	type MaxLiquidityRefundsPerBlock: Get<u32>;
+	/// The min base currency in a pool for its price to be provided, thinner pools are too cheap to move.
+	type GetMinimumPoolSizeForPricing: Get<BalanceOf<Self>>;
+	/// The currency the shares of the pool of each currency are minted in, `None` for the currencies which can't
+	/// have a pool. Only this module should update the balances of the share currencies.
+	type DexShareCurrencyIds: Convert<CurrencyIdOf<Self>, Option<CurrencyIdOf<Self>>>;
+	/// The pools which may have shares from before they were minted in the share currencies, migrated to `V2` and `V3`.
+	type PoolCurrencyIdsToMigrate: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The accounts which may hold shares of the pools to migrate from before the share holders were tracked, linked
+	/// with the module account in `V3`.
+	type ShareHoldersToMigrate: Get<Vec<Self::AccountId>>;
+	/// The prices the prices of the pools are compared with by the offchain worker.
+	type PriceSource: PriceProvider<CurrencyIdOf<Self>, Price>;
+	/// The currencies of the pools the offchain worker compares with the price source.
+	type PriceDeviationCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The relative deviation of the price of a pool from the price source past which it's reported.
+	type PriceDeviationThreshold: Get<Ratio>;
+	/// The min blocks between two reports of the price deviation of a pool.
+	type PriceDeviationReportInterval: Get<Self::BlockNumber>;
+	/// The runtime call the reports of the offchain worker are submitted in.
+	type Call: From<Call<Self>>;
+	/// Submits the reports of the offchain worker as unsigned transactions.
+	type SubmitTransaction: SubmitUnsignedTransaction<Self, <Self as Trait>::Call>;
+	type OnUpdateShares: OnUpdateShares<Self::AccountId, CurrencyIdOf<Self>, Self::Share>;
+	/// Origin of the calls updating the parameters of the module.
+	type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	/// The weights of the dispatchables.
+	type WeightInfo: WeightInfo;
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as Trait>::Share,
+		Balance = BalanceOf<T>,
+		CurrencyId = CurrencyIdOf<T>,
+	{
+		/// Liquidity added by the account to the pool of the currency, with the amounts actually taken in the order
+		/// of the pool: other currency amount, base currency amount, then the shares issued. What's left of the max
+		/// amounts stays with the account.
+		AddLiquidity(AccountId, CurrencyId, Balance, Balance, Share),
+		/// Liquidity withdrawn by the account from the pool of the currency: other currency amount, base currency
+		/// amount, then the shares redeemed.
+		WithdrawLiquidity(AccountId, CurrencyId, Balance, Balance, Share),
+		/// Swap by the account: supply currency, actual supply amount, target currency, actual target amount, in
+		/// both modes, then the fee charged in the target currency. A swap between two other currencies also
+		/// charges a fee in the base currency on its first leg, only recorded in the cumulative fee of the pools.
+		Swap(AccountId, CurrencyId, Balance, CurrencyId, Balance, Balance),
+		/// Shares of the pool of the currency transferred: from, to, currency, shares.
+		TransferShares(AccountId, AccountId, CurrencyId, Share),
+		/// The exchange fee of the pool of the currency updated, `None` for the default fee.
+		UpdateExchangeFee(CurrencyId, Option<FixedU128>),
+		/// Liquidity and swaps enabled with the pool of the currency.
+		EnableTradingPair(CurrencyId),
+		/// Liquidity and swaps disabled with the pool of the currency, the liquidity can still be withdrawn.
+		DisableTradingPair(CurrencyId),
+		/// The max price impact of the swaps with the pool of the currency updated, `None` for no limit.
+		UpdateMaxPriceImpact(CurrencyId, Option<Ratio>),
+		/// The max other currency the pool of the currency can be added liquidity up to updated, `None` for no cap.
+		UpdateMaxOtherCurrencyAmount(CurrencyId, Option<Balance>),
+		/// The pool of the currency closed, all its liquidity returned to the share holders.
+		PoolClosed(CurrencyId),
+		/// The last shares of the pool of the currency withdrawn, with whatever was left in the pool.
+		PoolDestroyed(CurrencyId),
+		/// The price of the pool of the currency deviates from the price source past the threshold: currency, price
+		/// of the pool, price of the source, both in the base currency.
+		PriceDeviation(CurrencyId, Price, Price),
+		/// Swaps and adding liquidity stopped with all the pools, the liquidity can still be withdrawn.
+		Pause,
+		/// Swaps and adding liquidity resumed.
+		Unpause,
+	}
+);
+
+decl_error! {
+	/// Error for cdp dex module.
+	pub enum Error for Module<T: Trait> {
+		BaseCurrencyIdNotAllowed,
+		TokenNotEnough,
+		ShareNotEnough,
+		InvalidBalance,
+		CanNotSwapItself,
+		InacceptablePrice,
+		InvalidLiquidityIncrement,
+		InsufficientLiquidity,
+		CanNotTransferToSelf,
+		InvalidExchangeFee,
+		PastDeadline,
+		TradingPairNotAllowed,
+		UnacceptableLiquidityWithdrawn,
+		InvalidSwapPath,
+		LiquidityPoolNotExists,
+		NumOverflow,
+		InvalidWithdrawAmounts,
+		UnacceptableShareIncrement,
+		Paused,
+		ExceedPriceImpactLimit,
+		ShareIncrementTooSmall,
+		PoolIsClosing,
+		InvalidBatchSize,
+		InvalidCurrencyId,
+		ExceedPoolCapacity,
+		PriceDeviationNotReportable,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Dex {
+		LiquidityPool get(fn liquidity_pool): map CurrencyIdOf<T> => (BalanceOf<T>, BalanceOf<T>);
+		TotalShares get(fn total_shares): map CurrencyIdOf<T> => T::Share;
+		Shares get(fn shares): double_map CurrencyIdOf<T>, blake2_256(T::AccountId) => T::Share;
+		ExchangeFee get(fn exchange_fee): map CurrencyIdOf<T> => Option<FixedU128>;
+		/// The protocol fees of each currency paid to the treasury account.
+		ProtocolFees get(fn protocol_fees): map CurrencyIdOf<T> => BalanceOf<T>;
+		/// The exchange fees retained by the pool of each currency, in the order of the pool: other currency, base
+		/// currency.
+		CumulativeFee get(fn cumulative_fee): map CurrencyIdOf<T> => (BalanceOf<T>, BalanceOf<T>);
+		/// The base currency swapped with the pool of each currency, saturated at the max balance.
+		CumulativeVolume get(fn cumulative_volume): map CurrencyIdOf<T> => BalanceOf<T>;
+		/// The swaps with the pool of each currency, the legs of a swap through two pools are counted with each pool.
+		TradeCount get(fn trade_count): map CurrencyIdOf<T> => u64;
+		/// Whether liquidity can be added to and swapped with the pool of the currency.
+		TradingPairStatus get(fn trading_pair_status): map CurrencyIdOf<T> => bool;
+		/// The max relative change of the spot price of each pool by a swap, no limit without one.
+		MaxPriceImpact get(fn max_price_impact): map CurrencyIdOf<T> => Option<Ratio>;
+		/// The max other currency reserve of each pool liquidity can be added up to, no cap without one. Swaps can
+		/// still move the reserve past it.
+		MaxOtherCurrencyAmount get(fn max_other_currency_amount): map CurrencyIdOf<T> => Option<BalanceOf<T>>;
+		/// The accounts holding shares of each pool, including the module account with the locked shares, linked
+		/// from the last one to become a holder. The holders from before they were tracked are linked once their
+		/// shares are updated, or by `register_share_holders`.
+		ShareHolderLinkage get(fn share_holder_linkage): double_map CurrencyIdOf<T>, blake2_256(T::AccountId) =>
+			Option<Linkage<T::AccountId>>;
+		/// The first of the share holders of each pool.
+		FirstShareHolder get(fn first_share_holder): map CurrencyIdOf<T> => Option<T::AccountId>;
+		/// The pools being closed, in the order they're closed, their share holders are refunded over the blocks.
+		ClosingPools get(fn closing_pools): Vec<CurrencyIdOf<T>>;
+		/// Whether swaps and adding liquidity are stopped with all the pools.
+		Paused get(fn paused): bool;
+		/// The spot price of the other currency of each pool in the base currency accumulated over the blocks, and the
+		/// block it's accumulated to. The price is taken at the end of the blocks and the accumulator wraps on
+		/// overflow.
+		CumulativePrice get(fn cumulative_price): map CurrencyIdOf<T> => (FixedU128, T::BlockNumber);
+		/// The recent cumulative prices of each pool, the oldest first.
+		PriceObservations get(fn price_observations): map CurrencyIdOf<T> => Vec<(FixedU128, T::BlockNumber)>;
+		/// The block the price deviation of each pool was last reported in.
+		LastPriceDeviationReport get(fn last_price_deviation_report): map CurrencyIdOf<T> => Option<T::BlockNumber>;
+		Version get(fn storage_version): StorageVersion;
+	}
+
+	add_extra_genesis {
+		config(enabled_trading_pairs): Vec<CurrencyIdOf<T>>;
+		// the pools seeded from the accounts as `(who, currency_id, other_currency_amount, base_currency_amount)`,
+		// their trading pairs are enabled
+		config(initial_pools): Vec<(T::AccountId, CurrencyIdOf<T>, BalanceOf<T>, BalanceOf<T>)>;
+		build(|config: &GenesisConfig<T>| {
+			config.enabled_trading_pairs.iter().for_each(|currency_id| {
+				<TradingPairStatus<T>>::insert(currency_id, true);
+			});
+			config.initial_pools.iter().for_each(|(who, currency_id, other_currency_amount, base_currency_amount)| {
+				assert!(
+					Module::<T>::total_shares(currency_id) == 0.into(),
+					"duplicate currency id in the initial pools"
+				);
+				assert!(
+					*other_currency_amount != 0.into() && *base_currency_amount != 0.into(),
+					"zero amount in the initial pools"
+				);
+				<TradingPairStatus<T>>::insert(currency_id, true);
+				Module::<T>::do_add_liquidity(who, *currency_id, *other_currency_amount, *base_currency_amount, 0.into())
+					.expect("the initial pool is added from the balances of the account");
+			});
+		});
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		fn on_initialize(_now: T::BlockNumber) {
+			Self::refund_closing_pools(T::MaxLiquidityRefundsPerBlock::get());
+		}
+
+		fn offchain_worker(_now: T::BlockNumber) {
+			Self::submit_price_deviation_reports();
+		}
+
+		/// Record the deviation of the price of the pool of `currency_id` from the price source right now, submitted
+		/// unsigned by the offchain worker.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::report_price_deviation())]
+		fn report_price_deviation(origin, currency_id: CurrencyIdOf<T>) {
+			ensure_none(origin)?;
+			let (dex_price, oracle_price) = Self::reportable_price_deviation(currency_id)?;
+			<LastPriceDeviationReport<T>>::insert(currency_id, <system::Module<T>>::block_number());
+			Self::deposit_event(RawEvent::PriceDeviation(currency_id, dex_price, oracle_price));
+		}
+
+		/// Swap exactly the supply amount for at least the target amount, no later than the `deadline` block.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::swap_with_exact_supply())]
+		fn swap_with_exact_supply(
+			origin,
+			supply: (CurrencyIdOf<T>, BalanceOf<T>),
+			target: (CurrencyIdOf<T>, BalanceOf<T>),
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_swap_with_exact_supply(who, supply, target)?;
+		}
+
+		/// Swap at most the supply amount for exactly the target amount, no later than the `deadline` block.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::swap_with_exact_target())]
+		fn swap_with_exact_target(
+			origin,
+			supply: (CurrencyIdOf<T>, BalanceOf<T>),
+			target: (CurrencyIdOf<T>, BalanceOf<T>),
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_swap_with_exact_target(who, supply, target)?;
+		}
+
+		/// Swap exactly the supply amount of the first currency of `path` for at least the target amount of the
+		/// last one, through the pools between each adjacent pair, no later than the `deadline` block.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::swap_by_path(T::MaxSwapPathLength::get()))]
+		fn swap_by_path(
+			origin,
+			path: Vec<CurrencyIdOf<T>>,
+			supply_amount: BalanceOf<T>,
+			min_target_amount: BalanceOf<T>,
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_swap_by_path(who, &path, supply_amount, min_target_amount)?;
+		}
+
+		/// Swap exactly the supply amount of `supply_currency_id` of each allocation `(target_currency_id,
+		/// supply_amount, min_target_amount)` for at least its min target amount, either all of them or none.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::swap_currency_batch(T::MaxBatchSize::get()))]
+		fn swap_currency_batch(
+			origin,
+			supply_currency_id: CurrencyIdOf<T>,
+			allocations: Vec<(CurrencyIdOf<T>, BalanceOf<T>, BalanceOf<T>)>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::do_swap_currency_batch(who, supply_currency_id, &allocations)?;
+		}
+
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::add_liquidity())]
+		/// Add at most the max amounts at the price of the pool, for at least `min_share_increment`, zero for no
+		/// minimum, no later than the `deadline` block. The amounts the pool doesn't take stay with the account.
+		fn add_liquidity(
+			origin,
+			other_currency_id: CurrencyIdOf<T>,
+			max_other_currency_amount: BalanceOf<T>,
+			max_base_currency_amount: BalanceOf<T>,
+			min_share_increment: T::Share,
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_add_liquidity(
+				&who,
+				other_currency_id,
+				max_other_currency_amount,
+				max_base_currency_amount,
+				min_share_increment,
+			)?;
+		}
+
+		/// Withdraw the liquidity of `share_amount`, receiving at least the min amounts, zero for no minimum.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::withdraw_liquidity())]
+		fn withdraw_liquidity(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			share_amount: T::Share,
+			min_withdraw_other: BalanceOf<T>,
+			min_withdraw_base: BalanceOf<T>,
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_withdraw_liquidity(&who, currency_id, share_amount, min_withdraw_other, min_withdraw_base)?;
+		}
+
+		/// Withdraw the liquidity of the least shares worth at least the amounts, which have to be at the price of
+		/// the pool within a unit of either currency.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::withdraw_liquidity_by_amounts())]
+		fn withdraw_liquidity_by_amounts(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			other_currency_amount: BalanceOf<T>,
+			base_currency_amount: BalanceOf<T>,
+			deadline: Option<T::BlockNumber>
+		) {
+			let who = ensure_signed(origin)?;
+			Self::ensure_before_deadline(deadline)?;
+			Self::do_withdraw_liquidity_by_amounts(&who, currency_id, other_currency_amount, base_currency_amount)?;
+		}
+
+		/// Transfer `share_amount` of the liquidity shares of the pool of `currency_id` to `to`.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::transfer_shares())]
+		fn transfer_shares(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			to: <T::Lookup as StaticLookup>::Source,
+			share_amount: T::Share
+		) {
+			let from = ensure_signed(origin)?;
+			let to = T::Lookup::lookup(to)?;
+			Self::do_transfer_shares(&from, &to, currency_id, share_amount)?;
+		}
+
+		/// Set the exchange fee of the pool of `currency_id`, `None` to fall back to the default fee.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_exchange_fee())]
+		fn set_exchange_fee(origin, currency_id: CurrencyIdOf<T>, fee: Option<FixedU128>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(currency_id != T::GetBaseCurrencyId::get(), Error::<T>::BaseCurrencyIdNotAllowed);
+			match fee {
+				Some(rate) => {
+					ensure!(rate < FixedU128::from_natural(1), Error::<T>::InvalidExchangeFee);
+					<ExchangeFee<T>>::insert(currency_id, rate);
+				}
+				None => <ExchangeFee<T>>::remove(currency_id),
+			}
+			Self::deposit_event(RawEvent::UpdateExchangeFee(currency_id, fee));
+		}
+
+		/// Set the max relative change of the spot price of the pool of `currency_id` by a swap, `None` for no limit.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_max_price_impact())]
+		fn set_max_price_impact(origin, currency_id: CurrencyIdOf<T>, limit: Option<Ratio>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(currency_id != T::GetBaseCurrencyId::get(), Error::<T>::BaseCurrencyIdNotAllowed);
+			match limit {
+				Some(ratio) => <MaxPriceImpact<T>>::insert(currency_id, ratio),
+				None => <MaxPriceImpact<T>>::remove(currency_id),
+			}
+			Self::deposit_event(RawEvent::UpdateMaxPriceImpact(currency_id, limit));
+		}
+
+		/// Set the max other currency reserve of the pool of `currency_id` liquidity can be added up to, `None` for
+		/// no cap.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_max_other_currency_amount())]
+		fn set_max_other_currency_amount(origin, currency_id: CurrencyIdOf<T>, amount: Option<BalanceOf<T>>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(currency_id != T::GetBaseCurrencyId::get(), Error::<T>::BaseCurrencyIdNotAllowed);
+			match amount {
+				Some(amount) => <MaxOtherCurrencyAmount<T>>::insert(currency_id, amount),
+				None => <MaxOtherCurrencyAmount<T>>::remove(currency_id),
+			}
+			Self::deposit_event(RawEvent::UpdateMaxOtherCurrencyAmount(currency_id, amount));
+		}
+
+		/// Enable adding liquidity to and swapping with the pool of `currency_id`.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::enable_trading_pair())]
+		fn enable_trading_pair(origin, currency_id: CurrencyIdOf<T>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(currency_id != T::GetBaseCurrencyId::get(), Error::<T>::BaseCurrencyIdNotAllowed);
+			ensure!(
+				T::DexShareCurrencyIds::convert(currency_id).is_some(),
+				Error::<T>::InvalidCurrencyId
+			);
+			ensure!(!Self::closing_pools().contains(&currency_id), Error::<T>::PoolIsClosing);
+			<TradingPairStatus<T>>::insert(currency_id, true);
+			Self::deposit_event(RawEvent::EnableTradingPair(currency_id));
+		}
+
+		/// Disable adding liquidity to and swapping with the pool of `currency_id`, the liquidity providers can
+		/// still withdraw.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::disable_trading_pair())]
+		fn disable_trading_pair(origin, currency_id: CurrencyIdOf<T>) {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			<TradingPairStatus<T>>::remove(currency_id);
+			Self::deposit_event(RawEvent::DisableTradingPair(currency_id));
+		}
+
+		/// Stop swaps and adding liquidity with all the pools, the liquidity providers can still withdraw.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::pause())]
+		fn pause(origin) {
+			ensure_root(origin)?;
+			<Paused>::put(true);
+			Self::deposit_event(RawEvent::Pause);
+		}
+
+		/// Resume swaps and adding liquidity.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::unpause())]
+		fn unpause(origin) {
+			ensure_root(origin)?;
+			<Paused>::kill();
+			Self::deposit_event(RawEvent::Unpause);
+		}
+
+		/// Close the pool of `currency_id` and return all its liquidity to the share holders, up to the max refunds
+		/// per block now and the rest over the following blocks. The locked shares are refunded to the treasury.
+		/// The pool stays closing until the holders from before they were tracked are registered and refunded too.
+		#[weight = SimpleDispatchInfo::FixedOperational(
+			T::WeightInfo::force_close_pool(T::MaxLiquidityRefundsPerBlock::get())
+		)]
+		fn force_close_pool(origin, currency_id: CurrencyIdOf<T>) {
+			ensure_root(origin)?;
+			ensure!(currency_id != T::GetBaseCurrencyId::get(), Error::<T>::BaseCurrencyIdNotAllowed);
+			ensure!(Self::total_shares(currency_id) > 0.into(), Error::<T>::LiquidityPoolNotExists);
+			ensure!(!Self::closing_pools().contains(&currency_id), Error::<T>::PoolIsClosing);
+			<TradingPairStatus<T>>::remove(currency_id);
+			<ClosingPools<T>>::mutate(|pools| pools.push(currency_id));
+			Self::refund_closing_pools(T::MaxLiquidityRefundsPerBlock::get());
+		}
+
+		/// Track the accounts of `holders` holding shares of the pool of `currency_id` from before the share holders
+		/// were tracked, so they're refunded when the pool is closed, and mint the share currency of their shares.
+		/// Anyone can submit them, the accounts already tracked or without shares are skipped.
+		#[weight = SimpleDispatchInfo::FixedNormal(
+			T::WeightInfo::register_share_holders(T::MaxLiquidityRefundsPerBlock::get())
+		)]
+		fn register_share_holders(origin, currency_id: CurrencyIdOf<T>, holders: Vec<T::AccountId>) {
+			ensure_signed(origin)?;
+			ensure!(
+				holders.len() as u32 <= T::MaxLiquidityRefundsPerBlock::get(),
+				Error::<T>::InvalidBatchSize
+			);
+			for who in holders.iter() {
+				Self::register_share_holder(currency_id, who)?;
+			}
+		}
+	}
 }
 
-decl_event!(
-	pub enum Event<T> where
-		<T as system::Trait>::AccountId,
-		<T as Trait>::Share,
-		Balance = BalanceOf<T>,
-		CurrencyId = CurrencyIdOf<T>,
-	{
-		AddLiquidity(AccountId, CurrencyId, Balance, Balance, Share),
-		WithdrawLiquidity(AccountId, CurrencyId, Balance, Balance, Share),
-		Swap(AccountId, CurrencyId, Balance, CurrencyId, Balance),
+impl<T: Trait> Module<T> {
+	pub fn account_id() -> T::AccountId {
+		MODULE_ID.into_account()
+	}
+
+	/// Fail unless the pool of `currency_id` is enabled for liquidity and swaps, and the dex isn't paused.
+	pub fn ensure_trading_pair_enabled(currency_id: CurrencyIdOf<T>) -> DispatchResult {
+		ensure!(!Self::paused(), Error::<T>::Paused);
+		ensure!(
+			Self::trading_pair_status(currency_id),
+			Error::<T>::TradingPairNotAllowed
+		);
+		Ok(())
+	}
+
+	/// Fail unless both sides of the pool of `currency_id` are seeded, nothing could be swapped out of it.
+	pub fn ensure_liquidity_pool_exists(currency_id: CurrencyIdOf<T>) -> DispatchResult {
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+		ensure!(
+			other_currency_pool != 0.into() && base_currency_pool != 0.into(),
+			Error::<T>::LiquidityPoolNotExists
+		);
+		Ok(())
+	}
+
+	/// Fail after the `deadline` block, `None` or zero for no deadline.
+	pub fn ensure_before_deadline(deadline: Option<T::BlockNumber>) -> DispatchResult {
+		if let Some(deadline) = deadline.filter(|deadline| *deadline != 0.into()) {
+			ensure!(
+				<system::Module<T>>::block_number() <= deadline,
+				Error::<T>::PastDeadline
+			);
+		}
+		Ok(())
+	}
+
+	/// The account receiving the protocol fees.
+	pub fn treasury_account_id() -> T::AccountId {
+		T::TreasuryModuleId::get().into_account()
+	}
+
+	/// Add liquidity from `who` to the pool of `other_currency_id`, at no more than the max amounts and at the
+	/// price of the pool, for at least `min_share_increment`. Returns the other currency amount, the base currency
+	/// amount and the shares added.
+	fn do_add_liquidity(
+		who: &T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		max_other_currency_amount: BalanceOf<T>,
+		max_base_currency_amount: BalanceOf<T>,
+		min_share_increment: T::Share,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>, T::Share), DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(
+			other_currency_id != base_currency_id,
+			Error::<T>::BaseCurrencyIdNotAllowed,
+		);
+		// the shares of share currencies could never be minted
+		ensure!(
+			T::DexShareCurrencyIds::convert(other_currency_id).is_some(),
+			Error::<T>::InvalidCurrencyId,
+		);
+		Self::ensure_trading_pair_enabled(other_currency_id)?;
+		ensure!(
+			max_other_currency_amount != 0.into() && max_base_currency_amount != 0.into(),
+			Error::<T>::InvalidBalance,
+		);
+
+		let total_shares = Self::total_shares(other_currency_id);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
+		let locked_share_increment = if total_shares == 0.into() {
+			T::MinimumLiquidity::get()
+		} else {
+			0.into()
+		};
+		let (other_currency_increment, base_currency_increment, share_increment): (
+			BalanceOf<T>,
+			BalanceOf<T>,
+			T::Share,
+		) = if total_shares == 0.into() {
+			// initialize this liquidity pool, the initial share is equal to the max value between base currency amount and other currency amount
+			let initial_share: T::Share = Self::from_u128(Self::to_u128(rstd::cmp::max(
+				max_other_currency_amount,
+				max_base_currency_amount,
+			))?)?;
+
+			// the minimum liquidity is locked out of the initial share, which has to be more than it
+			let share = initial_share.checked_sub(&locked_share_increment).unwrap_or(0.into());
+
+			(max_other_currency_amount, max_base_currency_amount, share)
+		} else {
+			// at the price of the pool, one of the max amounts is rounded down to match the other
+			let (other_currency_amount, base_currency_amount): (BalanceOf<T>, BalanceOf<T>) =
+				if Self::to_u256(max_base_currency_amount)? * Self::to_u256(other_currency_pool)?
+					<= Self::to_u256(base_currency_pool)? * Self::to_u256(max_other_currency_amount)?
+				{
+					let other_currency_amount = Self::multiply_by_rational(
+						Self::to_u256(max_base_currency_amount)?,
+						Self::to_u256(other_currency_pool)?,
+						Self::to_u256(base_currency_pool)?,
+					)?;
+					(other_currency_amount, max_base_currency_amount)
+				} else {
+					let base_currency_amount = Self::multiply_by_rational(
+						Self::to_u256(max_other_currency_amount)?,
+						Self::to_u256(base_currency_pool)?,
+						Self::to_u256(other_currency_pool)?,
+					)?;
+					(max_other_currency_amount, base_currency_amount)
+				};
+			// the shares worth both amounts, rounded down
+			let share: T::Share = rstd::cmp::min(
+				Self::multiply_by_rational(
+					Self::to_u256(other_currency_amount)?,
+					Self::to_u256(total_shares)?,
+					Self::to_u256(other_currency_pool)?,
+				)?,
+				Self::multiply_by_rational(
+					Self::to_u256(base_currency_amount)?,
+					Self::to_u256(total_shares)?,
+					Self::to_u256(base_currency_pool)?,
+				)?,
+			);
+			// only the amounts worth the shares are taken, rounded up so the other shares are never diluted, the
+			// account gives at most a unit of each currency more than its shares are worth
+			(
+				Self::multiply_by_rational_round_up(
+					Self::to_u256(share)?,
+					Self::to_u256(other_currency_pool)?,
+					Self::to_u256(total_shares)?,
+				)?,
+				Self::multiply_by_rational_round_up(
+					Self::to_u256(share)?,
+					Self::to_u256(base_currency_pool)?,
+					Self::to_u256(total_shares)?,
+				)?,
+				share,
+			)
+		};
+
+		ensure!(
+			share_increment > 0.into() && other_currency_increment > 0.into() && base_currency_increment > 0.into(),
+			Error::<T>::InvalidLiquidityIncrement,
+		);
+		ensure!(
+			share_increment >= T::MinimumShareIncrement::get(),
+			Error::<T>::ShareIncrementTooSmall,
+		);
+		// the price of the pool may have moved against the account since it quoted the shares
+		ensure!(
+			share_increment >= min_share_increment,
+			Error::<T>::UnacceptableShareIncrement,
+		);
+		// neither the pool nor the total shares can grow past their max values
+		let new_total_shares = total_shares
+			.checked_add(&share_increment)
+			.and_then(|n| n.checked_add(&locked_share_increment))
+			.ok_or(Error::<T>::NumOverflow)?;
+		let new_pool = other_currency_pool
+			.checked_add(&other_currency_increment)
+			.and_then(|other| {
+				base_currency_pool
+					.checked_add(&base_currency_increment)
+					.map(|base| (other, base))
+			})
+			.ok_or(Error::<T>::NumOverflow)?;
+		if let Some(max_other_currency_amount) = Self::max_other_currency_amount(other_currency_id) {
+			ensure!(new_pool.0 <= max_other_currency_amount, Error::<T>::ExceedPoolCapacity);
+		}
+		// all the shares of the pool can be minted in the share currency
+		Self::share_to_amount(new_total_shares)?;
+		ensure!(
+			T::Currency::ensure_can_withdraw(base_currency_id, who, base_currency_increment).is_ok()
+				&& T::Currency::ensure_can_withdraw(other_currency_id, who, other_currency_increment).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+		Self::register_share_holder(other_currency_id, who)?;
+		Self::update_cumulative_price(other_currency_id);
+		T::Currency::transfer(other_currency_id, who, &Self::account_id(), other_currency_increment)
+			.expect("never failed because after checks");
+		T::Currency::transfer(base_currency_id, who, &Self::account_id(), base_currency_increment)
+			.expect("never failed because after checks");
+		<TotalShares<T>>::insert(other_currency_id, new_total_shares);
+		if locked_share_increment > 0.into() {
+			<Shares<T>>::mutate(other_currency_id, Self::account_id(), |share| {
+				*share += locked_share_increment
+			});
+			Self::update_share_currency_balance(other_currency_id, &Self::account_id(), locked_share_increment, true)
+				.expect("never failed because after checks");
+			Self::update_share_holder(other_currency_id, &Self::account_id());
+		}
+		<Shares<T>>::mutate(other_currency_id, who, |share| *share += share_increment);
+		Self::update_share_currency_balance(other_currency_id, who, share_increment, true)
+			.expect("never failed because after checks");
+		T::OnUpdateShares::on_update_shares(who, other_currency_id, Self::shares(other_currency_id, who));
+		Self::update_share_holder(other_currency_id, who);
+		<LiquidityPool<T>>::insert(other_currency_id, new_pool);
+		// a pool initialized in this block is observed from now on
+		Self::update_cumulative_price(other_currency_id);
+		Self::deposit_event(RawEvent::AddLiquidity(
+			who.clone(),
+			other_currency_id,
+			other_currency_increment,
+			base_currency_increment,
+			share_increment,
+		));
+
+		Ok((other_currency_increment, base_currency_increment, share_increment))
+	}
+
+	/// Withdraw `share_amount` of the pool of `currency_id` to `who`. Returns the other currency amount and the
+	/// base currency amount withdrawn.
+	fn do_withdraw_liquidity(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		share_amount: T::Share,
+		min_withdraw_other_currency_amount: BalanceOf<T>,
+		min_withdraw_base_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(currency_id != base_currency_id, Error::<T>::BaseCurrencyIdNotAllowed);
+		// the shares locked to the module account are never withdrawn
+		ensure!(
+			*who != Self::account_id() && Self::shares(currency_id, who) >= share_amount && share_amount > 0.into(),
+			Error::<T>::ShareNotEnough,
+		);
+		Self::ensure_share_currency_can_withdraw(currency_id, who, share_amount)?;
+
+		let total_shares = Self::total_shares(currency_id);
+		let (withdraw_other_currency_amount, withdraw_base_currency_amount) = if share_amount == total_shares {
+			// the last withdrawer takes the dust the rounding left
+			Self::liquidity_pool(currency_id)
+		} else {
+			Self::get_redeemable_amounts(currency_id, share_amount)?
+		};
+		ensure!(
+			withdraw_other_currency_amount >= min_withdraw_other_currency_amount
+				&& withdraw_base_currency_amount >= min_withdraw_base_currency_amount,
+			Error::<T>::UnacceptableLiquidityWithdrawn,
+		);
+		Self::register_share_holder(currency_id, who)?;
+		Self::update_cumulative_price(currency_id);
+		if withdraw_other_currency_amount > 0.into() {
+			T::Currency::transfer(currency_id, &Self::account_id(), who, withdraw_other_currency_amount)
+				.expect("never failed because after checks");
+		}
+		if withdraw_base_currency_amount > 0.into() {
+			T::Currency::transfer(
+				base_currency_id,
+				&Self::account_id(),
+				who,
+				withdraw_base_currency_amount,
+			)
+			.expect("never failed because after checks");
+		}
+		let shares = Self::shares(currency_id, who);
+		if shares == share_amount {
+			<Shares<T>>::remove(currency_id, who);
+		} else {
+			<Shares<T>>::insert(currency_id, who, shares - share_amount);
+		}
+		Self::update_share_currency_balance(currency_id, who, share_amount, false)
+			.expect("never failed because after checks");
+		T::OnUpdateShares::on_update_shares(who, currency_id, Self::shares(currency_id, who));
+		Self::update_share_holder(currency_id, who);
+		if share_amount == total_shares {
+			<TotalShares<T>>::remove(currency_id);
+			<LiquidityPool<T>>::remove(currency_id);
+		} else {
+			<TotalShares<T>>::insert(currency_id, total_shares - share_amount);
+			<LiquidityPool<T>>::mutate(currency_id, |pool| {
+				let newpool = (
+					pool.0 - withdraw_other_currency_amount,
+					pool.1 - withdraw_base_currency_amount,
+				);
+				*pool = newpool;
+			});
+		}
+
+		Self::deposit_event(RawEvent::WithdrawLiquidity(
+			who.clone(),
+			currency_id,
+			withdraw_other_currency_amount,
+			withdraw_base_currency_amount,
+			share_amount,
+		));
+		if share_amount == total_shares {
+			Self::deposit_event(RawEvent::PoolDestroyed(currency_id));
+		}
+
+		Ok((withdraw_other_currency_amount, withdraw_base_currency_amount))
+	}
+
+	/// Withdraw at least `other_currency_amount` and `base_currency_amount` of the pool of `currency_id` to `who`,
+	/// for the least shares worth them. Returns the other currency amount, the base currency amount and the shares
+	/// withdrawn.
+	fn do_withdraw_liquidity_by_amounts(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		other_currency_amount: BalanceOf<T>,
+		base_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>, T::Share), DispatchError> {
+		ensure!(
+			currency_id != T::GetBaseCurrencyId::get(),
+			Error::<T>::BaseCurrencyIdNotAllowed
+		);
+		let mut share_amount =
+			Self::get_share_amount_by_amounts(currency_id, other_currency_amount, base_currency_amount)?;
+		// the whole position is withdrawn for what all its shares are worth, without leaving the shares worth
+		// less than a unit behind
+		if (other_currency_amount, base_currency_amount) == Self::get_liquidity(who, currency_id) {
+			share_amount = Self::shares(currency_id, who);
+		}
+		let (withdraw_other_currency_amount, withdraw_base_currency_amount) = Self::do_withdraw_liquidity(
+			who,
+			currency_id,
+			share_amount,
+			other_currency_amount,
+			base_currency_amount,
+		)?;
+		Ok((
+			withdraw_other_currency_amount,
+			withdraw_base_currency_amount,
+			share_amount,
+		))
+	}
+
+	/// Move `share_amount` of the pool of `currency_id` from `from` to `to`, the total shares are unchanged.
+	fn do_transfer_shares(
+		from: &T::AccountId,
+		to: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		share_amount: T::Share,
+	) -> DispatchResult {
+		ensure!(from != to, Error::<T>::CanNotTransferToSelf);
+		let from_shares = Self::shares(currency_id, from);
+		ensure!(
+			from_shares >= share_amount && share_amount > 0.into(),
+			Error::<T>::ShareNotEnough,
+		);
+		Self::ensure_share_currency_can_withdraw(currency_id, from, share_amount)?;
+		Self::register_share_holder(currency_id, from)?;
+		Self::register_share_holder(currency_id, to)?;
+		if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+			T::Currency::transfer(share_currency_id, from, to, Self::share_to_balance(share_amount)?)?;
+		}
+
+		if from_shares == share_amount {
+			<Shares<T>>::remove(currency_id, from);
+		} else {
+			<Shares<T>>::insert(currency_id, from, from_shares - share_amount);
+		}
+		<Shares<T>>::mutate(currency_id, to, |share| *share += share_amount);
+		T::OnUpdateShares::on_update_shares(from, currency_id, Self::shares(currency_id, from));
+		T::OnUpdateShares::on_update_shares(to, currency_id, Self::shares(currency_id, to));
+		Self::update_share_holder(currency_id, from);
+		Self::update_share_holder(currency_id, to);
+
+		Self::deposit_event(RawEvent::TransferShares(
+			from.clone(),
+			to.clone(),
+			currency_id,
+			share_amount,
+		));
+		Ok(())
+	}
+
+	/// At most `max_holders` of the share holders of the pool of `currency_id` in order, from `start`, or from the
+	/// first one if `start` is `None` or holds no shares since. Returns them and the holder to continue from, `None`
+	/// once the last one is returned.
+	pub fn share_holders(
+		currency_id: CurrencyIdOf<T>,
+		start: Option<T::AccountId>,
+		max_holders: u32,
+	) -> (Vec<T::AccountId>, Option<T::AccountId>) {
+		let mut next = start
+			.filter(|who| <ShareHolderLinkage<T>>::exists(currency_id, who))
+			.or_else(|| Self::first_share_holder(currency_id));
+		let mut holders = vec![];
+		while let Some(who) = next.take() {
+			if holders.len() as u32 >= max_holders {
+				return (holders, Some(who));
+			}
+			next = Self::share_holder_linkage(currency_id, &who).and_then(|linkage| linkage.next);
+			holders.push(who);
+		}
+		(holders, None)
+	}
+
+	/// Whether `who` holds shares of the pool of `currency_id` from before the share holders were tracked.
+	fn is_untracked_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) -> bool {
+		Self::shares(currency_id, who) > 0.into() && !<ShareHolderLinkage<T>>::exists(currency_id, who)
+	}
+
+	/// The share currency `register_share_holder` mints to `who` for its shares of the pool of `currency_id` from
+	/// before the share holders were tracked, at most what's missing from the pool.
+	fn unminted_share_balance(currency_id: CurrencyIdOf<T>, who: &T::AccountId) -> Result<BalanceOf<T>, DispatchError> {
+		match T::DexShareCurrencyIds::convert(currency_id) {
+			Some(share_currency_id) if Self::is_untracked_share_holder(currency_id, who) => {
+				let unminted_shares = Self::share_to_balance(Self::total_shares(currency_id))?
+					.saturating_sub(T::Currency::total_issuance(share_currency_id));
+				Ok(Self::share_to_balance(Self::shares(currency_id, who))?.min(unminted_shares))
+			}
+			_ => Ok(0.into()),
+		}
+	}
+
+	/// Track `who` if it holds shares of the pool of `currency_id` from before the share holders were tracked, and
+	/// mint the share currency of those shares, which was never minted. Called once an update of the shares of `who`
+	/// is checked, right before it's applied.
+	fn register_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) -> DispatchResult {
+		if !Self::is_untracked_share_holder(currency_id, who) {
+			return Ok(());
+		}
+		let amount = Self::unminted_share_balance(currency_id, who)?;
+		if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+			if amount > 0.into() {
+				T::Currency::deposit(share_currency_id, who, amount)?;
+			}
+		}
+		Self::insert_share_holder(currency_id, who);
+		Ok(())
+	}
+
+	/// Record whether `who` holds shares of the pool of `currency_id`, after its shares are updated.
+	fn update_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let has_shares = Self::shares(currency_id, who) > 0.into();
+		match (has_shares, <ShareHolderLinkage<T>>::exists(currency_id, who)) {
+			(true, false) => Self::insert_share_holder(currency_id, who),
+			(false, true) => Self::remove_share_holder(currency_id, who),
+			_ => {}
+		}
+	}
+
+	/// Link `who` in front of the share holders of the pool of `currency_id`.
+	fn insert_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let next = Self::first_share_holder(currency_id);
+		if let Some(next) = &next {
+			<ShareHolderLinkage<T>>::mutate(currency_id, next, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.previous = Some(who.clone());
+				}
+			});
+		}
+		<ShareHolderLinkage<T>>::insert(currency_id, who, Linkage { previous: None, next });
+		<FirstShareHolder<T>>::insert(currency_id, who);
+	}
+
+	/// Unlink `who` from the share holders of the pool of `currency_id`.
+	fn remove_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let Linkage { previous, next } = match Self::share_holder_linkage(currency_id, who) {
+			Some(linkage) => linkage,
+			None => return,
+		};
+		<ShareHolderLinkage<T>>::remove(currency_id, who);
+		if let Some(next) = &next {
+			<ShareHolderLinkage<T>>::mutate(currency_id, next, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.previous = previous.clone();
+				}
+			});
+		}
+		match previous {
+			Some(previous) => <ShareHolderLinkage<T>>::mutate(currency_id, previous, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.next = next;
+				}
+			}),
+			None => match next {
+				Some(next) => <FirstShareHolder<T>>::insert(currency_id, next),
+				None => <FirstShareHolder<T>>::remove(currency_id),
+			},
+		}
+	}
+
+	/// Refund up to `max_refunds` share holders of the closing pools, in the order the pools are closed, and finish
+	/// closing the pools without shares left.
+	fn refund_closing_pools(max_refunds: u32) {
+		let mut refunds = 0;
+		let mut closed_pools = vec![];
+		for currency_id in Self::closing_pools() {
+			while refunds < max_refunds {
+				// each refunded holder is unlinked, the next one comes first
+				match Self::first_share_holder(currency_id) {
+					Some(who) => Self::refund_share_holder(currency_id, &who),
+					None => break,
+				}
+				refunds += 1;
+			}
+			// the shares of the holders not registered yet can still be withdrawn by their owners
+			if Self::total_shares(currency_id) == 0.into() {
+				Self::finish_closing_pool(currency_id);
+				closed_pools.push(currency_id);
+			} else if refunds >= max_refunds {
+				break;
+			}
+		}
+		if !closed_pools.is_empty() {
+			<ClosingPools<T>>::mutate(|pools| pools.retain(|currency_id| !closed_pools.contains(currency_id)));
+		}
+	}
+
+	/// Return the liquidity of all the shares of `who` in the pool of `currency_id`, the locked shares of the
+	/// module account to the treasury.
+	fn refund_share_holder(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		let share_amount = Self::shares(currency_id, who);
+		let (other_currency_amount, base_currency_amount) = if share_amount == Self::total_shares(currency_id) {
+			// the last holder takes whatever the rounding left
+			Self::liquidity_pool(currency_id)
+		} else {
+			// never fails as the shares are part of the total shares
+			Self::get_redeemable_amounts(currency_id, share_amount).unwrap_or((0.into(), 0.into()))
+		};
+		let module_account = Self::account_id();
+		let to = if *who == module_account {
+			Self::treasury_account_id()
+		} else {
+			who.clone()
+		};
+		if other_currency_amount > 0.into() {
+			T::Currency::transfer(currency_id, &module_account, &to, other_currency_amount)
+				.expect("never failed because the amount is part of the pool");
+		}
+		if base_currency_amount > 0.into() {
+			T::Currency::transfer(base_currency_id, &module_account, &to, base_currency_amount)
+				.expect("never failed because the amount is part of the pool");
+		}
+		<TotalShares<T>>::mutate(currency_id, |share| *share -= share_amount);
+		<Shares<T>>::remove(currency_id, who);
+		// only the free share currency is burnt, what's reserved or moved out of the account stays unbacked
+		if let (Some(share_currency_id), Ok(shares)) = (
+			T::DexShareCurrencyIds::convert(currency_id),
+			Self::share_to_balance(share_amount),
+		) {
+			let free_shares = shares.min(T::Currency::balance(share_currency_id, who));
+			let _ = T::Currency::withdraw(share_currency_id, who, free_shares);
+		}
+		if *who != module_account {
+			T::OnUpdateShares::on_update_shares(who, currency_id, 0.into());
+		}
+		Self::update_share_holder(currency_id, who);
+		<LiquidityPool<T>>::mutate(currency_id, |pool| {
+			*pool = (pool.0 - other_currency_amount, pool.1 - base_currency_amount);
+		});
+		Self::deposit_event(RawEvent::WithdrawLiquidity(
+			who.clone(),
+			currency_id,
+			other_currency_amount,
+			base_currency_amount,
+			share_amount,
+		));
+	}
+
+	/// `share_amount` as a balance of the share currency, a `NumOverflow` error if it doesn't fit.
+	fn share_to_balance(share_amount: T::Share) -> Result<BalanceOf<T>, DispatchError> {
+		Self::from_u128(Self::to_u128(share_amount)?)
+	}
+
+	/// `share_amount` as an amount of the share currency, a `NumOverflow` error if it doesn't fit.
+	fn share_to_amount(share_amount: T::Share) -> Result<AmountOf<T>, DispatchError> {
+		TryInto::<AmountOf<T>>::try_into(Self::share_to_balance(share_amount)?)
+			.map_err(|_| Error::<T>::NumOverflow.into())
+	}
+
+	/// Mint `share_amount` of the share currency of the pool of `currency_id` to `who`, or burn it if `!mint`, as
+	/// the shares of `who` are updated.
+	fn update_share_currency_balance(
+		currency_id: CurrencyIdOf<T>,
+		who: &T::AccountId,
+		share_amount: T::Share,
+		mint: bool,
+	) -> DispatchResult {
+		if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+			let amount = Self::share_to_amount(share_amount)?;
+			T::Currency::update_balance(share_currency_id, who, if mint { amount } else { -amount })?;
+		}
+		Ok(())
+	}
+
+	/// Fail unless `who` has `share_amount` of the share currency of the pool of `currency_id` free, so the shares
+	/// reserved or moved out by other modules, as collateral for one, can't be withdrawn or transferred.
+	fn ensure_share_currency_can_withdraw(
+		currency_id: CurrencyIdOf<T>,
+		who: &T::AccountId,
+		share_amount: T::Share,
+	) -> DispatchResult {
+		if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+			// the share currency of the shares from before the holders were tracked is minted before the withdrawal
+			let amount =
+				Self::share_to_balance(share_amount)?.saturating_sub(Self::unminted_share_balance(currency_id, who)?);
+			ensure!(
+				T::Currency::ensure_can_withdraw(share_currency_id, who, amount).is_ok(),
+				Error::<T>::ShareNotEnough,
+			);
+		}
+		Ok(())
+	}
+
+	/// Clear the emptied pool of `currency_id`.
+	fn finish_closing_pool(currency_id: CurrencyIdOf<T>) {
+		<LiquidityPool<T>>::remove(currency_id);
+		<TotalShares<T>>::remove(currency_id);
+		<CumulativeFee<T>>::remove(currency_id);
+		<CumulativeVolume<T>>::remove(currency_id);
+		<TradeCount<T>>::remove(currency_id);
+		<CumulativePrice<T>>::remove(currency_id);
+		<PriceObservations<T>>::remove(currency_id);
+		Self::deposit_event(RawEvent::PoolClosed(currency_id));
+	}
+
+	/// Apply the pending migrations in order and return the weight consumed. Only the version is read once the
+	/// storage is up to date, so it's safe to run again.
+	pub fn on_runtime_upgrade() -> Weight {
+		let mut version = Self::storage_version();
+		let mut weight = STORAGE_ACCESS_WEIGHT;
+		while let Some(next) = version.next() {
+			weight = weight
+				.saturating_add(Self::migrate_to(next))
+				.saturating_add(STORAGE_ACCESS_WEIGHT);
+			<Version>::put(next);
+			version = next;
+		}
+		weight
+	}
+
+	/// Migrate the storage to the layout of `version` from the one before it, returns the weight consumed.
+	fn migrate_to(version: StorageVersion) -> Weight {
+		match version {
+			// the first version is never migrated to
+			StorageVersion::V0 => 0,
+			// only the version is recorded
+			StorageVersion::V1 => 0,
+			StorageVersion::V2 => Self::mint_share_currency_balances(),
+			StorageVersion::V3 => Self::register_untracked_share_holders(),
+		}
+	}
+
+	/// Mint the share currency missing from the balances of the share holders of the pools to migrate, so their
+	/// balances match their shares, returns the weight consumed.
+	fn mint_share_currency_balances() -> Weight {
+		let mut weight: Weight = 0;
+		for currency_id in T::PoolCurrencyIdsToMigrate::get() {
+			let share_currency_id = match T::DexShareCurrencyIds::convert(currency_id) {
+				Some(share_currency_id) => share_currency_id,
+				None => continue,
+			};
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT);
+			for who in Self::share_holders(currency_id, None, u32::max_value()).0 {
+				let balance = T::Currency::balance(share_currency_id, &who);
+				// the share currency of the pools seeded at genesis is already minted
+				if let Ok(shares) = Self::share_to_balance(Self::shares(currency_id, &who)) {
+					if shares > balance {
+						let _ = T::Currency::deposit(share_currency_id, &who, shares - balance);
+					}
+				}
+				weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT.saturating_mul(3));
+			}
+		}
+		weight
+	}
+
+	/// Link the holders of the shares of the pools to migrate from before the share holders were tracked, the module
+	/// account with the locked shares and the accounts to migrate, and mint the share currency of their shares.
+	/// Returns the weight consumed.
+	fn register_untracked_share_holders() -> Weight {
+		let mut weight: Weight = 0;
+		let holders: Vec<T::AccountId> = rstd::iter::once(Self::account_id())
+			.chain(T::ShareHoldersToMigrate::get())
+			.collect();
+		for currency_id in T::PoolCurrencyIdsToMigrate::get() {
+			for who in holders.iter() {
+				let _ = Self::register_share_holder(currency_id, who);
+				// the shares, the linkage, the total shares and issuance, the deposit and the linkage of the first holder
+				weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT.saturating_mul(10));
+			}
+		}
+		weight
+	}
+
+	/// Check the pools against the balances of the module account, and the total shares against the shares of
+	/// `accounts`, which should be all the liquidity providers, and the shares locked to the module account.
+	#[cfg(feature = "std")]
+	pub fn audit(currency_ids: &[CurrencyIdOf<T>], accounts: &[T::AccountId]) -> AuditResult<CurrencyIdOf<T>> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		let module_account = Self::account_id();
+		let mut auditor = Auditor::default();
+
+		let mut base_currency_reserves: BalanceOf<T> = Zero::zero();
+		for &currency_id in currency_ids
+			.iter()
+			.filter(|&&currency_id| currency_id != base_currency_id)
+		{
+			let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+			base_currency_reserves = base_currency_reserves.saturating_add(base_currency_pool);
+			auditor.check(
+				"dex: pool reserve held",
+				currency_id,
+				other_currency_pool,
+				T::Currency::balance(currency_id, &module_account),
+			);
+
+			let shares = accounts
+				.iter()
+				.fold(Self::shares(currency_id, &module_account), |total, who| {
+					total.saturating_add(Self::shares(currency_id, who))
+				});
+			auditor.check(
+				"dex: total shares",
+				currency_id,
+				Self::total_shares(currency_id),
+				shares,
+			);
+			if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+				let total_shares: u128 = Self::total_shares(currency_id).unique_saturated_into();
+				let issuance: u128 = T::Currency::total_issuance(share_currency_id).unique_saturated_into();
+				auditor.check("dex: share currency issued", currency_id, total_shares, issuance);
+			}
+		}
+		auditor.check(
+			"dex: base reserves held",
+			base_currency_id,
+			base_currency_reserves,
+			T::Currency::balance(base_currency_id, &module_account),
+		);
+
+		auditor.finish()
+	}
+
+	/// The other currency and base currency amounts `share_amount` of the pool of `currency_id` can be withdrawn for.
+	pub fn get_redeemable_amounts(
+		currency_id: CurrencyIdOf<T>,
+		share_amount: T::Share,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+		let share_amount = Self::to_u256(share_amount)?;
+		let total_shares = Self::to_u256(Self::total_shares(currency_id))?;
+		// nothing to withdraw from a pool without shares
+		if total_shares.is_zero() {
+			return Ok((0.into(), 0.into()));
+		}
+		Ok((
+			Self::multiply_by_rational(share_amount, Self::to_u256(other_currency_pool)?, total_shares)?,
+			Self::multiply_by_rational(share_amount, Self::to_u256(base_currency_pool)?, total_shares)?,
+		))
+	}
+
+	/// The reserves and shares of the pool of `currency_id` right now, and the swaps with it.
+	pub fn pool_info(currency_id: CurrencyIdOf<T>) -> PoolInfo<BalanceOf<T>, T::Share> {
+		let (other_reserve, base_reserve) = Self::liquidity_pool(currency_id);
+		PoolInfo {
+			other_reserve,
+			base_reserve,
+			total_shares: Self::total_shares(currency_id),
+			cumulative_volume: Self::cumulative_volume(currency_id),
+			trade_count: Self::trade_count(currency_id),
+		}
+	}
+
+	/// The other currency and base currency amounts all the shares of `who` in the pool of `currency_id` are worth
+	/// right now, `(0, 0)` without shares.
+	pub fn get_liquidity(who: &T::AccountId, currency_id: CurrencyIdOf<T>) -> (BalanceOf<T>, BalanceOf<T>) {
+		// never fails, the shares of an account are part of the total shares
+		Self::get_redeemable_amounts(currency_id, Self::shares(currency_id, who)).unwrap_or((0.into(), 0.into()))
+	}
+
+	/// The least shares of the pool of `currency_id` worth at least `other_currency_amount` and
+	/// `base_currency_amount`, which have to be at the price of the pool within a unit of either currency.
+	pub fn get_share_amount_by_amounts(
+		currency_id: CurrencyIdOf<T>,
+		other_currency_amount: BalanceOf<T>,
+		base_currency_amount: BalanceOf<T>,
+	) -> Result<T::Share, DispatchError> {
+		ensure!(
+			other_currency_amount != 0.into() && base_currency_amount != 0.into(),
+			Error::<T>::InvalidBalance,
+		);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+		let total_shares = Self::to_u256(Self::total_shares(currency_id))?;
+		let (other_currency_pool, base_currency_pool) =
+			(Self::to_u256(other_currency_pool)?, Self::to_u256(base_currency_pool)?);
+		ensure!(
+			!total_shares.is_zero() && !other_currency_pool.is_zero() && !base_currency_pool.is_zero(),
+			Error::<T>::LiquidityPoolNotExists,
+		);
+
+		// shares = amount * total_shares / pool, rounded up so the amount is withdrawn
+		let shares_for = |amount: U256, pool: U256| (amount * total_shares + pool - U256::one()) / pool;
+		let other_currency_shares = shares_for(Self::to_u256(other_currency_amount)?, other_currency_pool);
+		let base_currency_shares = shares_for(Self::to_u256(base_currency_amount)?, base_currency_pool);
+		// the shares of the amounts differ by no more than the shares of a unit of the coarser currency
+		let tolerance = rstd::cmp::max(
+			shares_for(U256::one(), other_currency_pool),
+			shares_for(U256::one(), base_currency_pool),
+		);
+		let (share_amount, difference) = if other_currency_shares > base_currency_shares {
+			(other_currency_shares, other_currency_shares - base_currency_shares)
+		} else {
+			(base_currency_shares, base_currency_shares - other_currency_shares)
+		};
+		ensure!(difference <= tolerance, Error::<T>::InvalidWithdrawAmounts);
+		ensure!(share_amount <= U256::from(u128::max_value()), Error::<T>::NumOverflow);
+		Self::from_u128(share_amount.low_u128())
+	}
+
+	/// The spot price of `currency_id` in the base currency as a fraction, `None` if its pool is empty or too thin
+	/// to be priced.
+	fn pool_price(currency_id: CurrencyIdOf<T>) -> Option<(U512, U512)> {
+		if currency_id == T::GetBaseCurrencyId::get() {
+			return Some((U512::one(), U512::one()));
+		}
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+		if other_currency_pool == 0.into()
+			|| base_currency_pool == 0.into()
+			|| base_currency_pool < T::GetMinimumPoolSizeForPricing::get()
+		{
+			return None;
+		}
+		Some((
+			U512::from(Self::to_u128(base_currency_pool).ok()?),
+			U512::from(Self::to_u128(other_currency_pool).ok()?),
+		))
+	}
+
+	/// The spot price of the other currency of the pool of `currency_id` in the base currency, `None` if the pool is
+	/// empty or the price doesn't fit.
+	fn spot_price(currency_id: CurrencyIdOf<T>) -> Option<FixedU128> {
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+		if other_currency_pool == 0.into() || base_currency_pool == 0.into() {
+			return None;
+		}
+		Self::multiply_by_rational::<u128>(
+			Self::to_u256(base_currency_pool).ok()?,
+			U256::from(FixedU128::accuracy()),
+			Self::to_u256(other_currency_pool).ok()?,
+		)
+		.ok()
+		.map(FixedU128::from_parts)
+	}
+
+	/// `cumulative_price` accumulated with `price` over `blocks`, wrapping on overflow. Only the differences of the
+	/// cumulative prices are meaningful, they are right as long as they fit.
+	fn accumulate_price(cumulative_price: FixedU128, price: FixedU128, blocks: T::BlockNumber) -> FixedU128 {
+		let blocks: u128 = blocks.unique_saturated_into();
+		FixedU128::from_parts(
+			cumulative_price
+				.deconstruct()
+				.wrapping_add(price.deconstruct().wrapping_mul(blocks)),
+		)
+	}
+
+	/// Accumulate the price of the pool of `currency_id` up to this block, before the first update of the pool in
+	/// this block. Only the price at the end of the blocks is accumulated, so it can't be moved within a single
+	/// block.
+	fn update_cumulative_price(currency_id: CurrencyIdOf<T>) {
+		let now = <system::Module<T>>::block_number();
+		let spot_price = match Self::spot_price(currency_id) {
+			Some(price) => price,
+			None => {
+				// an empty pool has no price, it's observed afresh once initialized again
+				<CumulativePrice<T>>::remove(currency_id);
+				<PriceObservations<T>>::remove(currency_id);
+				return;
+			}
+		};
+		let cumulative_price = if <CumulativePrice<T>>::exists(currency_id) {
+			let (cumulative_price, last_updated) = Self::cumulative_price(currency_id);
+			if last_updated == now {
+				return;
+			}
+			Self::accumulate_price(cumulative_price, spot_price, now - last_updated)
+		} else {
+			// the first observation of the pool
+			FixedU128::from_parts(0)
+		};
+		<CumulativePrice<T>>::insert(currency_id, (cumulative_price, now));
+		<PriceObservations<T>>::mutate(currency_id, |observations| {
+			if observations.len() >= MAX_PRICE_OBSERVATIONS {
+				observations.remove(0);
+			}
+			observations.push((cumulative_price, now));
+		});
+	}
+
+	/// The time weighted average price of the other currency of the pool of `currency_id` in the base currency,
+	/// over at least the last `lookback_blocks`, up to this block. `None` if the pool is empty or it isn't observed
+	/// back that far.
+	pub fn get_twap(currency_id: CurrencyIdOf<T>, lookback_blocks: T::BlockNumber) -> Option<Price> {
+		let now = <system::Module<T>>::block_number();
+		let since = now.checked_sub(&lookback_blocks)?;
+		let spot_price = Self::spot_price(currency_id)?;
+		if !<CumulativePrice<T>>::exists(currency_id) {
+			return None;
+		}
+		let (cumulative_price, last_updated) = Self::cumulative_price(currency_id);
+		let current_cumulative_price = Self::accumulate_price(cumulative_price, spot_price, now - last_updated);
+		let (start_cumulative_price, start_block) = Self::price_observations(currency_id)
+			.into_iter()
+			.rev()
+			.find(|(_, block)| *block <= since)?;
+		let blocks: u128 = (now - start_block).unique_saturated_into();
+		if blocks == 0 {
+			return None;
+		}
+		Some(Price::from_parts(
+			current_cumulative_price
+				.deconstruct()
+				.wrapping_sub(start_cumulative_price.deconstruct())
+				/ blocks,
+		))
+	}
+
+	/// The exchange fee of the pool of `currency_id`, the default fee without an override.
+	pub fn get_exchange_fee(currency_id: CurrencyIdOf<T>) -> FixedU128 {
+		Self::exchange_fee(currency_id).unwrap_or_else(T::GetExchangeFee::get)
+	}
+
+	/// The target amount of swapping `supply_amount` with the pools, and the fee charged out of it, both in the
+	/// target currency. Rounded down, so the product of the pools never decreases.
+	pub fn calculate_swap_target_amount(
+		supply_pool: BalanceOf<T>,
+		target_pool: BalanceOf<T>,
+		supply_amount: BalanceOf<T>,
+		fee: FixedU128,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		// the target pool of an empty supply pool would be drained
+		if supply_pool == 0.into() {
+			return Ok((0.into(), 0.into()));
+		}
+		// the supply pool can't grow past the max balance
+		ensure!(
+			supply_pool.checked_add(&supply_amount).is_some(),
+			Error::<T>::NumOverflow
+		);
+
+		// target_amount_without_fee = target_pool * supply_amount / (supply_pool + supply_amount)
+		let supply_amount = Self::to_u256(supply_amount)?;
+		let target_amount_without_fee: BalanceOf<T> = Self::multiply_by_rational(
+			Self::to_u256(target_pool)?,
+			supply_amount,
+			Self::to_u256(supply_pool)? + supply_amount,
+		)?;
+
+		// actual can get = target_amount_without_fee * (1 - fee)
+		let fee_amount = fee
+			.checked_mul_int(&target_amount_without_fee)
+			.ok_or(Error::<T>::NumOverflow)?;
+		let target_amount = target_amount_without_fee
+			.checked_sub(&fee_amount)
+			.ok_or(Error::<T>::NumOverflow)?;
+		Ok((target_amount, fee_amount))
+	}
+
+	/// The supply amount needed to swap for `target_amount` with the pools, with the fee. Rounded up, so the
+	/// product of the pools never decreases.
+	pub fn calculate_swap_supply_amount(
+		supply_pool: BalanceOf<T>,
+		target_pool: BalanceOf<T>,
+		target_amount: BalanceOf<T>,
+		fee: FixedU128,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		// supply_amount = supply_pool * target_amount / (target_pool * (1 - fee) - target_amount), the numerator
+		// is scaled by the accuracy of the fee, which takes more than 256 bits
+		let accuracy = U512::from(FixedU128::accuracy());
+		let supply_pool_u512 = U512::from(Self::to_u128(supply_pool)?);
+		let target_pool = U512::from(Self::to_u128(target_pool)?);
+		let target_amount = U512::from(Self::to_u128(target_amount)?) * accuracy;
+		let denominator = accuracy
+			.checked_sub(U512::from(fee.deconstruct()))
+			.map(|n| target_pool * n)
+			.and_then(|n| n.checked_sub(target_amount))
+			// the target pool is never drained
+			.filter(|n| !n.is_zero())
+			.ok_or(Error::<T>::InsufficientLiquidity)?;
+		let supply_amount = Self::checked_div_round_up(supply_pool_u512 * target_amount, denominator)
+			.ok_or(Error::<T>::InsufficientLiquidity)?;
+		ensure!(supply_amount <= U512::from(u128::max_value()), Error::<T>::NumOverflow);
+		let supply_amount: BalanceOf<T> = Self::from_u128(supply_amount.low_u128())?;
+
+		// the supply pool can't grow past the max balance
+		ensure!(
+			supply_pool.checked_add(&supply_amount).is_some(),
+			Error::<T>::NumOverflow
+		);
+		Ok(supply_amount)
+	}
+
+	/// The price of the pool of `currency_id` and the price of the source, if they deviate past the threshold and
+	/// the pool isn't reported within the interval.
+	fn reportable_price_deviation(currency_id: CurrencyIdOf<T>) -> Result<(Price, Price), DispatchError> {
+		if let Some(last_report) = Self::last_price_deviation_report(currency_id) {
+			ensure!(
+				<system::Module<T>>::block_number()
+					>= last_report.saturating_add(T::PriceDeviationReportInterval::get()),
+				Error::<T>::PriceDeviationNotReportable,
+			);
+		}
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		let dex_price = <Self as PriceProvider<_, _>>::get_price(base_currency_id, currency_id)
+			.ok_or(Error::<T>::PriceDeviationNotReportable)?;
+		let oracle_price =
+			T::PriceSource::get_price(base_currency_id, currency_id).ok_or(Error::<T>::PriceDeviationNotReportable)?;
+		// |dex_price - oracle_price| / oracle_price > threshold
+		let dex_parts = U512::from(dex_price.deconstruct());
+		let oracle_parts = U512::from(oracle_price.deconstruct());
+		let deviation = if dex_parts > oracle_parts {
+			dex_parts - oracle_parts
+		} else {
+			oracle_parts - dex_parts
+		};
+		ensure!(
+			deviation * U512::from(FixedU128::accuracy())
+				> U512::from(T::PriceDeviationThreshold::get().deconstruct()) * oracle_parts,
+			Error::<T>::PriceDeviationNotReportable,
+		);
+		Ok((dex_price, oracle_price))
+	}
+
+	/// Submit a report of each pool deviating from the price source, from the offchain worker.
+	fn submit_price_deviation_reports() {
+		for currency_id in T::PriceDeviationCurrencyIds::get() {
+			if Self::reportable_price_deviation(currency_id).is_ok() {
+				let _ = T::SubmitTransaction::submit_unsigned(Call::report_price_deviation(currency_id));
+			}
+		}
+	}
+
+	/// `n` as `u128`, a `NumOverflow` error if it doesn't fit.
+	fn to_u128<N: TryInto<u128>>(n: N) -> Result<u128, DispatchError> {
+		n.try_into().map_err(|_| Error::<T>::NumOverflow.into())
+	}
+
+	/// `n` as a balance or a share, a `NumOverflow` error if it doesn't fit.
+	fn from_u128<N: TryFrom<u128>>(n: u128) -> Result<N, DispatchError> {
+		N::try_from(n).map_err(|_| Error::<T>::NumOverflow.into())
+	}
+
+	fn to_u256<N: TryInto<u128>>(n: N) -> Result<U256, DispatchError> {
+		Self::to_u128(n).map(U256::from)
+	}
+
+	/// `a * b / c` rounded down, a `NumOverflow` error if `c` is zero or the result doesn't fit.
+	fn multiply_by_rational<N: TryFrom<u128>>(a: U256, b: U256, c: U256) -> Result<N, DispatchError> {
+		let result = a
+			.checked_mul(b)
+			.and_then(|n| n.checked_div(c))
+			.ok_or(Error::<T>::NumOverflow)?;
+		ensure!(result <= U256::from(u128::max_value()), Error::<T>::NumOverflow);
+		Self::from_u128(result.low_u128())
+	}
+
+	/// `a * b / c` rounded up, a `NumOverflow` error if `c` is zero or the result doesn't fit.
+	fn multiply_by_rational_round_up<N: TryFrom<u128>>(a: U256, b: U256, c: U256) -> Result<N, DispatchError> {
+		let result = a
+			.checked_mul(b)
+			.and_then(|n| Self::checked_div_round_up(U512::from(n), U512::from(c)))
+			.ok_or(Error::<T>::NumOverflow)?;
+		ensure!(result <= U512::from(u128::max_value()), Error::<T>::NumOverflow);
+		Self::from_u128(result.low_u128())
+	}
+
+	/// `numerator / denominator` rounded up, `None` if the denominator is zero.
+	fn checked_div_round_up(numerator: U512, denominator: U512) -> Option<U512> {
+		let quotient = numerator.checked_div(denominator)?;
+		if quotient * denominator == numerator {
+			Some(quotient)
+		} else {
+			Some(quotient + U512::one())
+		}
+	}
+
+	/// Swap exactly `supply` for at least the target amount of `target`, returns the supply amount and the
+	/// target amount swapped.
+	fn do_swap_with_exact_supply(
+		who: T::AccountId,
+		supply: (CurrencyIdOf<T>, BalanceOf<T>),
+		target: (CurrencyIdOf<T>, BalanceOf<T>),
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(target.0 != supply.0, Error::<T>::CanNotSwapItself);
+		if target.0 == base_currency_id {
+			Self::swap_other_to_base(who, supply.0, supply.1, target.1)
+		} else if supply.0 == base_currency_id {
+			Self::swap_base_to_other(who, target.0, supply.1, target.1)
+		} else {
+			Self::swap_other_to_other(who, supply.0, supply.1, target.0, target.1)
+		}
+	}
+
+	/// Swap at most the supply amount of `supply` for exactly `target`, returns the supply amount and the
+	/// target amount swapped.
+	fn do_swap_with_exact_target(
+		who: T::AccountId,
+		supply: (CurrencyIdOf<T>, BalanceOf<T>),
+		target: (CurrencyIdOf<T>, BalanceOf<T>),
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(target.0 != supply.0, Error::<T>::CanNotSwapItself);
+		if target.0 == base_currency_id {
+			Self::swap_other_to_exact_base(who, supply.0, supply.1, target.1)
+		} else if supply.0 == base_currency_id {
+			Self::swap_base_to_exact_other(who, target.0, supply.1, target.1)
+		} else {
+			Self::swap_other_to_exact_other(who, supply.0, supply.1, target.0, target.1)
+		}
+	}
+
+	/// Swap exactly `supply_amount` of the first currency of `path` for at least `min_target_amount` of the last
+	/// one, returns the supply amount and the target amount swapped. Every hop is between the other currency of a
+	/// pool and the base currency, and no currency is visited twice so no pool is used twice.
+	pub fn do_swap_by_path(
+		who: T::AccountId,
+		path: &[CurrencyIdOf<T>],
+		supply_amount: BalanceOf<T>,
+		min_target_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(
+			path.len() >= 2 && path.len() <= T::MaxSwapPathLength::get() as usize,
+			Error::<T>::InvalidSwapPath,
+		);
+		for (index, currency_id) in path.iter().enumerate() {
+			ensure!(!path[..index].contains(currency_id), Error::<T>::InvalidSwapPath);
+		}
+		for hop in path.windows(2) {
+			ensure!(
+				(hop[0] == base_currency_id) != (hop[1] == base_currency_id),
+				Error::<T>::InvalidSwapPath,
+			);
+		}
+		ensure!(
+			supply_amount > 0.into() && T::Currency::ensure_can_withdraw(path[0], &who, supply_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+
+		// the amounts of all the hops, before anything is settled
+		let amounts = Self::simulate_swap_by_path(path, supply_amount, &mut vec![])?;
+		let target_amount = amounts[amounts.len() - 1];
+		ensure!(target_amount >= min_target_amount, Error::<T>::InacceptablePrice);
+
+		Self::settle_swap_by_path(&who, path, &amounts)?;
+		Ok((supply_amount, target_amount))
+	}
+
+	/// Swap exactly the supply amount of `supply_currency_id` of each allocation for at least its min target amount
+	/// of its target currency, returns the target amounts swapped. Every allocation is checked against the pools as
+	/// the allocations before it leave them before any is settled, so either all of them are swapped or none.
+	pub fn do_swap_currency_batch(
+		who: T::AccountId,
+		supply_currency_id: CurrencyIdOf<T>,
+		allocations: &[(CurrencyIdOf<T>, BalanceOf<T>, BalanceOf<T>)],
+	) -> Result<Vec<BalanceOf<T>>, DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(
+			!allocations.is_empty() && allocations.len() <= T::MaxBatchSize::get() as usize,
+			Error::<T>::InvalidBatchSize,
+		);
+		let mut total_supply_amount: BalanceOf<T> = 0.into();
+		for (target_currency_id, supply_amount, _) in allocations {
+			ensure!(*target_currency_id != supply_currency_id, Error::<T>::CanNotSwapItself);
+			ensure!(*supply_amount > 0.into(), Error::<T>::TokenNotEnough);
+			total_supply_amount = total_supply_amount.saturating_add(*supply_amount);
+		}
+		ensure!(
+			T::Currency::ensure_can_withdraw(supply_currency_id, &who, total_supply_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+
+		let mut pools = vec![];
+		let mut swaps = vec![];
+		for &(target_currency_id, supply_amount, min_target_amount) in allocations {
+			let path = if supply_currency_id == base_currency_id || target_currency_id == base_currency_id {
+				vec![supply_currency_id, target_currency_id]
+			} else {
+				vec![supply_currency_id, base_currency_id, target_currency_id]
+			};
+			let amounts = Self::simulate_swap_by_path(&path, supply_amount, &mut pools)?;
+			ensure!(
+				amounts[amounts.len() - 1] >= min_target_amount,
+				Error::<T>::InacceptablePrice
+			);
+			swaps.push((path, amounts));
+		}
+
+		let mut target_amounts = vec![];
+		for (path, amounts) in swaps {
+			Self::settle_swap_by_path(&who, &path, &amounts)?;
+			target_amounts.push(amounts[amounts.len() - 1]);
+		}
+		Ok(target_amounts)
+	}
+
+	/// The amounts of all the hops of swapping exactly `supply_amount` along `path`, checked as each hop would be
+	/// settled. The pools are taken from `pools` if they're there and from storage otherwise, and recorded in
+	/// `pools` as the swap would leave them.
+	fn simulate_swap_by_path(
+		path: &[CurrencyIdOf<T>],
+		supply_amount: BalanceOf<T>,
+		pools: &mut Vec<(CurrencyIdOf<T>, (BalanceOf<T>, BalanceOf<T>))>,
+	) -> Result<Vec<BalanceOf<T>>, DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		let mut amounts = vec![supply_amount];
+		for hop in path.windows(2) {
+			let other_currency_id = if hop[0] == base_currency_id { hop[1] } else { hop[0] };
+			Self::ensure_trading_pair_enabled(other_currency_id)?;
+			Self::ensure_liquidity_pool_exists(other_currency_id)?;
+			let index = match pools
+				.iter()
+				.position(|(currency_id, _)| *currency_id == other_currency_id)
+			{
+				Some(index) => index,
+				None => {
+					pools.push((other_currency_id, Self::liquidity_pool(other_currency_id)));
+					pools.len() - 1
+				}
+			};
+			let (other_currency_pool, base_currency_pool) = pools[index].1;
+			let (supply_pool, target_pool) = if hop[0] == base_currency_id {
+				(base_currency_pool, other_currency_pool)
+			} else {
+				(other_currency_pool, base_currency_pool)
+			};
+			let hop_supply_amount = amounts[amounts.len() - 1];
+			let (hop_target_amount, _) = Self::calculate_swap_target_amount(
+				supply_pool,
+				target_pool,
+				hop_supply_amount,
+				Self::get_exchange_fee(other_currency_id),
+			)?;
+			let fee_amount =
+				Self::calculate_fee_amount(supply_pool, target_pool, hop_supply_amount, hop_target_amount)?;
+			let protocol_fee_amount = Self::calculate_protocol_fee_amount(fee_amount)?;
+			// the protocol fee leaves the pool on the target side once the swap is settled
+			let (new_pool, settled_pool) = if hop[0] == base_currency_id {
+				let new_pool = (
+					other_currency_pool.saturating_sub(hop_target_amount),
+					base_currency_pool.saturating_add(hop_supply_amount),
+				);
+				(new_pool, (new_pool.0.saturating_sub(protocol_fee_amount), new_pool.1))
+			} else {
+				let new_pool = (
+					other_currency_pool.saturating_add(hop_supply_amount),
+					base_currency_pool.saturating_sub(hop_target_amount),
+				);
+				(new_pool, (new_pool.0, new_pool.1.saturating_sub(protocol_fee_amount)))
+			};
+			Self::ensure_price_impact_acceptable(other_currency_id, new_pool)?;
+			pools[index].1 = settled_pool;
+			amounts.push(hop_target_amount);
+		}
+		Ok(amounts)
+	}
+
+	/// Settle the hops of a swap along `path` by the `amounts` simulated, after all the checks.
+	fn settle_swap_by_path(who: &T::AccountId, path: &[CurrencyIdOf<T>], amounts: &[BalanceOf<T>]) -> DispatchResult {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		for (hop, hop_amounts) in path.windows(2).zip(amounts.windows(2)) {
+			if hop[0] == base_currency_id {
+				Self::transfer_base_to_other(who.clone(), hop[1], hop_amounts[0], hop_amounts[1])?;
+			} else {
+				Self::transfer_other_to_base(who.clone(), hop[0], hop_amounts[0], hop_amounts[1])?;
+			}
+		}
+		Ok(())
+	}
+
+	// use other currency to swap base currency
+	pub fn swap_other_to_base(
+		who: T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		other_currency_amount: BalanceOf<T>,
+		min_base_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(other_currency_id)?;
+		Self::ensure_liquidity_pool_exists(other_currency_id)?;
+		ensure!(
+			other_currency_amount > 0.into()
+				&& T::Currency::ensure_can_withdraw(other_currency_id, &who, other_currency_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
+		let (base_currency_amount, _) = Self::calculate_swap_target_amount(
+			other_currency_pool,
+			base_currency_pool,
+			other_currency_amount,
+			Self::get_exchange_fee(other_currency_id),
+		)?;
+		ensure!(
+			base_currency_amount >= min_base_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+
+		Self::transfer_other_to_base(who, other_currency_id, other_currency_amount, base_currency_amount)?;
+		Ok((other_currency_amount, base_currency_amount))
+	}
+
+	// use at most the max amount of other currency to swap the exact amount of base currency
+	pub fn swap_other_to_exact_base(
+		who: T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		max_other_currency_amount: BalanceOf<T>,
+		base_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(other_currency_id)?;
+		ensure!(base_currency_amount > 0.into(), Error::<T>::InvalidBalance);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
+		let other_currency_amount = Self::calculate_swap_supply_amount(
+			other_currency_pool,
+			base_currency_pool,
+			base_currency_amount,
+			Self::get_exchange_fee(other_currency_id),
+		)?;
+		ensure!(
+			other_currency_amount > 0.into() && other_currency_amount <= max_other_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+		ensure!(
+			T::Currency::ensure_can_withdraw(other_currency_id, &who, other_currency_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+
+		Self::transfer_other_to_base(who, other_currency_id, other_currency_amount, base_currency_amount)?;
+		Ok((other_currency_amount, base_currency_amount))
+	}
+
+	// use base currency to swap other currency
+	pub fn swap_base_to_other(
+		who: T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		base_currency_amount: BalanceOf<T>,
+		min_other_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(other_currency_id)?;
+		Self::ensure_liquidity_pool_exists(other_currency_id)?;
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(
+			base_currency_amount > 0.into()
+				&& T::Currency::ensure_can_withdraw(base_currency_id, &who, base_currency_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
+		let (other_currency_amount, _) = Self::calculate_swap_target_amount(
+			base_currency_pool,
+			other_currency_pool,
+			base_currency_amount,
+			Self::get_exchange_fee(other_currency_id),
+		)?;
+		ensure!(
+			other_currency_amount >= min_other_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+
+		Self::transfer_base_to_other(who, other_currency_id, base_currency_amount, other_currency_amount)?;
+		Ok((base_currency_amount, other_currency_amount))
+	}
+
+	// use at most the max amount of base currency to swap the exact amount of other currency
+	pub fn swap_base_to_exact_other(
+		who: T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		max_base_currency_amount: BalanceOf<T>,
+		other_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(other_currency_id)?;
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(other_currency_amount > 0.into(), Error::<T>::InvalidBalance);
+		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
+		let base_currency_amount = Self::calculate_swap_supply_amount(
+			base_currency_pool,
+			other_currency_pool,
+			other_currency_amount,
+			Self::get_exchange_fee(other_currency_id),
+		)?;
+		ensure!(
+			base_currency_amount > 0.into() && base_currency_amount <= max_base_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+		ensure!(
+			T::Currency::ensure_can_withdraw(base_currency_id, &who, base_currency_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+
+		Self::transfer_base_to_other(who, other_currency_id, base_currency_amount, other_currency_amount)?;
+		Ok((base_currency_amount, other_currency_amount))
+	}
+
+	// use other currency to swap another other currency
+	pub fn swap_other_to_other(
+		who: T::AccountId,
+		supply_other_currency_id: CurrencyIdOf<T>,
+		supply_other_currency_amount: BalanceOf<T>,
+		target_other_currency_id: CurrencyIdOf<T>,
+		min_target_other_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(supply_other_currency_id)?;
+		Self::ensure_trading_pair_enabled(target_other_currency_id)?;
+		Self::ensure_liquidity_pool_exists(supply_other_currency_id)?;
+		Self::ensure_liquidity_pool_exists(target_other_currency_id)?;
+		ensure!(
+			supply_other_currency_amount > 0.into()
+				&& T::Currency::ensure_can_withdraw(supply_other_currency_id, &who, supply_other_currency_amount)
+					.is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+		let (supply_other_currency_pool, supply_base_currency_pool) = Self::liquidity_pool(supply_other_currency_id);
+		let (intermediate_base_currency_amount, _) = Self::calculate_swap_target_amount(
+			supply_other_currency_pool,
+			supply_base_currency_pool,
+			supply_other_currency_amount,
+			Self::get_exchange_fee(supply_other_currency_id),
+		)?;
+		let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_other_currency_id);
+		let (target_other_currency_amount, _) = Self::calculate_swap_target_amount(
+			target_base_currency_pool,
+			target_other_currency_pool,
+			intermediate_base_currency_amount,
+			Self::get_exchange_fee(target_other_currency_id),
+		)?;
+		ensure!(
+			target_other_currency_amount >= min_target_other_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+
+		Self::transfer_other_to_other(
+			who,
+			supply_other_currency_id,
+			supply_other_currency_amount,
+			intermediate_base_currency_amount,
+			target_other_currency_id,
+			target_other_currency_amount,
+		)?;
+		Ok((supply_other_currency_amount, target_other_currency_amount))
+	}
+
+	// use at most the max amount of other currency to swap the exact amount of another other currency
+	pub fn swap_other_to_exact_other(
+		who: T::AccountId,
+		supply_other_currency_id: CurrencyIdOf<T>,
+		max_supply_other_currency_amount: BalanceOf<T>,
+		target_other_currency_id: CurrencyIdOf<T>,
+		target_other_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::ensure_trading_pair_enabled(supply_other_currency_id)?;
+		Self::ensure_trading_pair_enabled(target_other_currency_id)?;
+		ensure!(target_other_currency_amount > 0.into(), Error::<T>::InvalidBalance);
+		let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_other_currency_id);
+		let intermediate_base_currency_amount = Self::calculate_swap_supply_amount(
+			target_base_currency_pool,
+			target_other_currency_pool,
+			target_other_currency_amount,
+			Self::get_exchange_fee(target_other_currency_id),
+		)?;
+		let (supply_other_currency_pool, supply_base_currency_pool) = Self::liquidity_pool(supply_other_currency_id);
+		let supply_other_currency_amount = Self::calculate_swap_supply_amount(
+			supply_other_currency_pool,
+			supply_base_currency_pool,
+			intermediate_base_currency_amount,
+			Self::get_exchange_fee(supply_other_currency_id),
+		)?;
+		ensure!(
+			intermediate_base_currency_amount > 0.into()
+				&& supply_other_currency_amount > 0.into()
+				&& supply_other_currency_amount <= max_supply_other_currency_amount,
+			Error::<T>::InacceptablePrice,
+		);
+		ensure!(
+			T::Currency::ensure_can_withdraw(supply_other_currency_id, &who, supply_other_currency_amount).is_ok(),
+			Error::<T>::TokenNotEnough,
+		);
+
+		Self::transfer_other_to_other(
+			who,
+			supply_other_currency_id,
+			supply_other_currency_amount,
+			intermediate_base_currency_amount,
+			target_other_currency_id,
+			target_other_currency_amount,
+		)?;
+		Ok((supply_other_currency_amount, target_other_currency_amount))
 	}
-);
 
-decl_error! {
-	/// Error for cdp dex module.
-	pub enum Error {
-		BaseCurrencyIdNotAllowed,
-		TokenNotEnough,
-		ShareNotEnough,
-		InvalidBalance,
-		CanNotSwapItself,
-		InacceptablePrice,
-		InvalidLiquidityIncrement,
+	/// The exchange fee of swapping `supply_amount` for `target_amount` with the pools, in the target currency.
+	pub fn calculate_fee_amount(
+		supply_pool: BalanceOf<T>,
+		target_pool: BalanceOf<T>,
+		supply_amount: BalanceOf<T>,
+		target_amount: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		let (target_amount_without_fee, _) =
+			Self::calculate_swap_target_amount(supply_pool, target_pool, supply_amount, FixedU128::from_natural(0))?;
+		Ok(target_amount_without_fee.saturating_sub(target_amount))
 	}
-}
 
-decl_storage! {
-	trait Store for Module<T: Trait> as Dex {
-		LiquidityPool get(fn liquidity_pool): map CurrencyIdOf<T> => (BalanceOf<T>, BalanceOf<T>);
-		TotalShares get(fn total_shares): map CurrencyIdOf<T> => T::Share;
-		Shares get(fn shares): double_map CurrencyIdOf<T>, blake2_256(T::AccountId) => T::Share;
+	/// The supply amount of swapping for exactly `target_amount` through `pools`, the `(supply pool, target pool,
+	/// exchange fee)` of each leg in order, which are moved as the swap would move them. The pools are left as they
+	/// are if it fails.
+	fn simulate_swap_with_exact_target(
+		pools: &mut [(BalanceOf<T>, BalanceOf<T>, FixedU128)],
+		target_amount: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		// the amounts of the legs from the last one back, the target of a leg is supplied by the one before it
+		let mut amounts = vec![target_amount];
+		for &(supply_pool, target_pool, fee) in pools.iter().rev() {
+			let leg_target_amount = amounts[amounts.len() - 1];
+			amounts.push(Self::calculate_swap_supply_amount(
+				supply_pool,
+				target_pool,
+				leg_target_amount,
+				fee,
+			)?);
+		}
+		amounts.reverse();
+		let mut new_pools = vec![];
+		for (&(supply_pool, target_pool, fee), leg_amounts) in pools.iter().zip(amounts.windows(2)) {
+			// the protocol fee leaves the target pool
+			let fee_amount = Self::calculate_fee_amount(supply_pool, target_pool, leg_amounts[0], leg_amounts[1])?;
+			let protocol_fee_amount = Self::calculate_protocol_fee_amount(fee_amount)?;
+			let new_supply_pool = supply_pool
+				.checked_add(&leg_amounts[0])
+				.ok_or(Error::<T>::NumOverflow)?;
+			let new_target_pool = target_pool
+				.checked_sub(&leg_amounts[1])
+				.and_then(|pool| pool.checked_sub(&protocol_fee_amount))
+				.ok_or(Error::<T>::NumOverflow)?;
+			new_pools.push((new_supply_pool, new_target_pool, fee));
+		}
+		pools.copy_from_slice(&new_pools);
+		Ok(amounts[0])
 	}
-}
-
-decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		fn deposit_event() = default;
 
-		fn swap_currency(origin, supply: (CurrencyIdOf<T>, BalanceOf<T>), target: (CurrencyIdOf<T>, BalanceOf<T>)) {
-			let who = ensure_signed(origin)?;
-			let base_currency_id = T::GetBaseCurrencyId::get();
-			ensure!(
-				target.0 != supply.0,
-				Error::CanNotSwapItself.into(),
-			);
+	/// The protocol part of `fee_amount`, the rest is retained by the pool.
+	pub fn calculate_protocol_fee_amount(fee_amount: BalanceOf<T>) -> Result<BalanceOf<T>, DispatchError> {
+		T::GetProtocolFeeRate::get()
+			.checked_mul_int(&fee_amount)
+			.ok_or_else(|| Error::<T>::NumOverflow.into())
+	}
 
-			if target.0 == base_currency_id {
-				Self::swap_other_to_base(who, supply.0, supply.1, target.1)?;
-			} else if supply.0 == base_currency_id {
-				Self::swap_base_to_other(who, target.0, supply.1, target.1)?;
-			} else {
-				Self::swap_other_to_other(who, supply.0, supply.1, target.0, target.1)?;
-			}
+	/// Pay the protocol fee out of the module account to the treasury account, the pool is updated by the caller.
+	fn pay_protocol_fee(currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+		if amount > 0.into() {
+			T::Currency::transfer(currency_id, &Self::account_id(), &Self::treasury_account_id(), amount)
+				.expect("never failed because the fee is part of the pool");
+			<ProtocolFees<T>>::mutate(currency_id, |fees| *fees += amount);
 		}
+	}
 
-		fn add_liquidity(origin, other_currency_id: CurrencyIdOf<T>, max_other_currency_amount: BalanceOf<T>, max_base_currency_amount: BalanceOf<T>) {
-			let who = ensure_signed(origin)?;
-			let base_currency_id = T::GetBaseCurrencyId::get();
-			ensure!(
-				other_currency_id != base_currency_id,
-				Error::BaseCurrencyIdNotAllowed.into(),
-			);
-			ensure!(
-				max_other_currency_amount != 0.into() && max_base_currency_amount != 0.into(),
-				Error::InvalidBalance.into(),
-			);
+	/// Fail if moving the pool of `currency_id` to `new_pool` by a swap, before the protocol fee, changes its spot
+	/// price by more than the max price impact of the pool.
+	fn ensure_price_impact_acceptable(
+		currency_id: CurrencyIdOf<T>,
+		new_pool: (BalanceOf<T>, BalanceOf<T>),
+	) -> DispatchResult {
+		Self::ensure_price_impact_acceptable_from(currency_id, Self::liquidity_pool(currency_id), new_pool)
+	}
 
-			let total_shares = Self::total_shares(other_currency_id);
-			let (other_currency_increment, base_currency_increment, share_increment): (BalanceOf<T>, BalanceOf<T>, T::Share) =
-			if total_shares == 0.into() {
-				// initialize this liquidity pool, the initial share is equal to the max value between base currency amount and other currency amount
-				let initial_share = TryInto::<T::Share>::try_into(
-					TryInto::<u128>::try_into(
-						rstd::cmp::max(max_other_currency_amount, max_base_currency_amount)
-					).unwrap_or(u128::max_value())
-				).unwrap_or(T::Share::max_value());
-
-				(max_other_currency_amount, max_base_currency_amount, initial_share)
+	/// Fail if moving the pool of `currency_id` from `pool` to `new_pool` changes its spot price by more than the max
+	/// price impact of the pool, for a pool the swaps before left it at.
+	pub fn ensure_price_impact_acceptable_from(
+		currency_id: CurrencyIdOf<T>,
+		pool: (BalanceOf<T>, BalanceOf<T>),
+		new_pool: (BalanceOf<T>, BalanceOf<T>),
+	) -> DispatchResult {
+		if let Some(limit) = Self::max_price_impact(currency_id) {
+			let (other_currency_pool, base_currency_pool) = pool;
+			let (new_other_currency_pool, new_base_currency_pool) = new_pool;
+			// from base / other to new_base / new_other, the relative change is
+			// |new_base * other - base * new_other| / (base * new_other)
+			let base_by_new_other =
+				U512::from(Self::to_u128(base_currency_pool)?) * U512::from(Self::to_u128(new_other_currency_pool)?);
+			let new_base_by_other =
+				U512::from(Self::to_u128(new_base_currency_pool)?) * U512::from(Self::to_u128(other_currency_pool)?);
+			let change = if new_base_by_other > base_by_new_other {
+				new_base_by_other - base_by_new_other
 			} else {
-				let (other_currency_pool, base_currency_pool): (BalanceOf<T>, BalanceOf<T>) = Self::liquidity_pool(other_currency_id);
-
-				let other_base_price = FixedU128::from_rational(
-					TryInto::<u128>::try_into(base_currency_pool).unwrap_or(u128::max_value()),
-					TryInto::<u128>::try_into(other_currency_pool).unwrap_or(u128::max_value()),
-				);
-
-				let input_other_base_price = FixedU128::from_rational(
-					TryInto::<u128>::try_into(max_base_currency_amount).unwrap_or(u128::max_value()),
-					TryInto::<u128>::try_into(max_other_currency_amount).unwrap_or(u128::max_value()),
-				);
-
-				if input_other_base_price <= other_base_price {
-					// max_other_currency_amount may be too much, calculate the actual other currency amount
-					let base_other_price = FixedU128::from_rational(
-						TryInto::<u128>::try_into(other_currency_pool).unwrap_or(u128::max_value()),
-						TryInto::<u128>::try_into(base_currency_pool).unwrap_or(u128::max_value()),
-					);
-					let other_currency_amount = base_other_price.checked_mul_int(&max_base_currency_amount).unwrap_or(BalanceOf::<T>::max_value());
-					let share = FixedU128::from_rational(
-						TryInto::<u128>::try_into(other_currency_amount).unwrap_or(u128::max_value()),
-						TryInto::<u128>::try_into(other_currency_pool).unwrap_or(u128::max_value()),
-					).checked_mul_int(&total_shares).unwrap_or(0.into());
-					(other_currency_amount, max_base_currency_amount, share)
-				} else {
-					// max_base_currency_amount is too much, calculate the actual base currency amount
-					let base_currency_amount = other_base_price.checked_mul_int(&max_other_currency_amount).unwrap_or(BalanceOf::<T>::max_value());
-					let share = FixedU128::from_rational(
-						TryInto::<u128>::try_into(base_currency_amount).unwrap_or(u128::max_value()),
-						TryInto::<u128>::try_into(base_currency_pool).unwrap_or(u128::max_value()),
-					).checked_mul_int(&total_shares).unwrap_or(0.into());
-					(max_other_currency_amount, base_currency_amount, share)
-				}
+				base_by_new_other - new_base_by_other
 			};
-
-			ensure!(
-				share_increment > 0.into() && other_currency_increment > 0.into() && base_currency_increment > 0.into(),
-				Error::InvalidLiquidityIncrement.into(),
-			);
-			ensure!(
-				T::Currency::ensure_can_withdraw(base_currency_id, &who, base_currency_increment).is_ok()
-				&&
-				T::Currency::ensure_can_withdraw(other_currency_id, &who, other_currency_increment).is_ok(),
-				Error::TokenNotEnough.into(),
-			);
-			T::Currency::transfer(other_currency_id, &who, &Self::account_id(), other_currency_increment)
-			.expect("never failed because after checks");
-			T::Currency::transfer(base_currency_id, &who, &Self::account_id(), base_currency_increment)
-			.expect("never failed because after checks");
-			<TotalShares<T>>::mutate(other_currency_id, |share| *share += share_increment);
-			<Shares<T>>::mutate(other_currency_id, &who, |share| *share += share_increment);
-			<LiquidityPool<T>>::mutate(other_currency_id, |pool| {
-				let newpool = (pool.0 + other_currency_increment, pool.1 + base_currency_increment);
-				*pool = newpool;
-			});
-			Self::deposit_event(RawEvent::AddLiquidity(
-				who,
-				other_currency_id,
-				other_currency_increment,
-				base_currency_increment,
-				share_increment,
-			));
-		}
-
-		fn withdraw_liquidity(origin, currency_id: CurrencyIdOf<T>, share_amount: T::Share) {
-			let who = ensure_signed(origin)?;
-			let base_currency_id = T::GetBaseCurrencyId::get();
-			ensure!(
-				currency_id != base_currency_id,
-				Error::BaseCurrencyIdNotAllowed.into(),
-			);
 			ensure!(
-				Self::shares(currency_id, &who) >= share_amount && share_amount > 0.into(),
-				Error::ShareNotEnough.into(),
+				change * U512::from(FixedU128::accuracy()) <= U512::from(limit.deconstruct()) * base_by_new_other,
+				Error::<T>::ExceedPriceImpactLimit,
 			);
-
-			let (other_currency_pool, base_currency_pool): (BalanceOf<T>, BalanceOf<T>) = Self::liquidity_pool(currency_id);
-			let proportion = FixedU128::from_rational(
-				TryInto::<u128>::try_into(share_amount).unwrap_or(u128::max_value()),
-				TryInto::<u128>::try_into(Self::total_shares(currency_id)).unwrap_or(u128::max_value()),
-			);
-			let withdraw_other_currency_amount = proportion.checked_mul_int(&other_currency_pool).unwrap_or(BalanceOf::<T>::max_value());
-			let withdraw_base_currency_amount = proportion.checked_mul_int(&base_currency_pool).unwrap_or(BalanceOf::<T>::max_value());
-			if withdraw_other_currency_amount > 0.into() {
-				T::Currency::transfer(currency_id, &Self::account_id(), &who, withdraw_other_currency_amount)
-				.expect("never failed because after checks");
-			}
-			if withdraw_base_currency_amount > 0.into() {
-				T::Currency::transfer(base_currency_id, &Self::account_id(), &who, withdraw_base_currency_amount)
-				.expect("never failed because after checks");
-			}
-			<TotalShares<T>>::mutate(currency_id, |share| *share -= share_amount);
-			<Shares<T>>::mutate(currency_id, &who, |share| *share -= share_amount);
-			<LiquidityPool<T>>::mutate(currency_id, |pool| {
-				let newpool = (pool.0 - withdraw_other_currency_amount, pool.1 - withdraw_base_currency_amount);
-				*pool = newpool;
-			});
-
-			Self::deposit_event(RawEvent::WithdrawLiquidity(
-				who,
-				currency_id,
-				withdraw_base_currency_amount,
-				withdraw_base_currency_amount,
-				share_amount,
-			));
-		}
-	}
-}
-
-impl<T: Trait> Module<T> {
-	pub fn account_id() -> T::AccountId {
-		MODULE_ID.into_account()
-	}
-
-	pub fn calculate_swap_target_amount(
-		supply_pool: BalanceOf<T>,
-		target_pool: BalanceOf<T>,
-		supply_amount: BalanceOf<T>,
-	) -> BalanceOf<T> {
-		// new_target_pool = supply_pool * target_pool / (supply_amount + supply_pool)
-		let new_target_pool = supply_pool
-			.checked_add(&supply_amount)
-			.and_then(|n| {
-				Some(FixedU128::from_rational(
-					TryInto::<u128>::try_into(supply_pool).unwrap_or(u128::max_value()),
-					TryInto::<u128>::try_into(n).unwrap_or(u128::max_value()),
-				))
-			})
-			.and_then(|n| n.checked_mul_int(&target_pool))
-			.unwrap_or(0.into());
-
-		// new_target_pool should be more then 0
-		if new_target_pool != 0.into() {
-			// actual can get = (target_pool - new_target_pool) * (1 - GetExchangeFee)
-			target_pool
-				.checked_sub(&new_target_pool)
-				.and_then(|n| {
-					n.checked_sub(
-						&T::GetExchangeFee::get()
-							.checked_mul_int(&n)
-							.unwrap_or(BalanceOf::<T>::max_value()),
-					)
-				})
-				.unwrap_or(0.into())
-		} else {
-			0.into()
 		}
+		Ok(())
 	}
 
-	pub fn calculate_swap_supply_amount(
-		supply_pool: BalanceOf<T>,
-		target_pool: BalanceOf<T>,
-		target_amount: BalanceOf<T>,
-	) -> BalanceOf<T> {
-		// new_target_pool = target_pool - target_amount / (1 - GetExchangeFee)
-		// supply_amount = target_pool * supply_pool / new_target_pool - supply_pool
-		FixedU128::from_natural(1)
-			.checked_sub(&T::GetExchangeFee::get())
-			.and_then(|n| FixedU128::from_natural(1).checked_div(&n))
-			.and_then(|n| n.checked_mul_int(&target_amount))
-			.and_then(|n| target_pool.checked_sub(&n))
-			.and_then(|n| {
-				Some(FixedU128::from_rational(
-					TryInto::<u128>::try_into(supply_pool).unwrap_or(u128::max_value()),
-					TryInto::<u128>::try_into(n).unwrap_or(u128::max_value()),
-				))
-			})
-			.and_then(|n| n.checked_mul_int(&target_pool))
-			.and_then(|n| n.checked_sub(&supply_pool))
-			.unwrap_or(0.into())
+	/// Count a swap with the pool of `currency_id` of `base_currency_amount` base currency, the counters saturate.
+	fn record_trade(currency_id: CurrencyIdOf<T>, base_currency_amount: BalanceOf<T>) {
+		<CumulativeVolume<T>>::mutate(currency_id, |volume| {
+			*volume = volume.saturating_add(base_currency_amount)
+		});
+		<TradeCount<T>>::mutate(currency_id, |count| *count = count.saturating_add(1));
 	}
 
-	// use other currency to swap base currency
-	pub fn swap_other_to_base(
+	/// Settle a swap of other currency for base currency with the pool, after all the checks.
+	fn transfer_other_to_base(
 		who: T::AccountId,
 		other_currency_id: CurrencyIdOf<T>,
 		other_currency_amount: BalanceOf<T>,
-		min_base_currency_amount: BalanceOf<T>,
-	) -> result::Result<(), Error> {
-		ensure!(
-			other_currency_amount > 0.into()
-				&& T::Currency::ensure_can_withdraw(other_currency_id, &who, other_currency_amount).is_ok(),
-			Error::TokenNotEnough,
-		);
+		base_currency_amount: BalanceOf<T>,
+	) -> DispatchResult {
 		let base_currency_id = T::GetBaseCurrencyId::get();
 		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
-		let base_currency_amount =
-			Self::calculate_swap_target_amount(other_currency_pool, base_currency_pool, other_currency_amount);
-		ensure!(
-			base_currency_amount >= min_base_currency_amount,
-			Error::InacceptablePrice,
-		);
-
+		Self::ensure_price_impact_acceptable(
+			other_currency_id,
+			(
+				other_currency_pool.saturating_add(other_currency_amount),
+				base_currency_pool.saturating_sub(base_currency_amount),
+			),
+		)?;
+		let fee_amount = Self::calculate_fee_amount(
+			other_currency_pool,
+			base_currency_pool,
+			other_currency_amount,
+			base_currency_amount,
+		)?;
+		let protocol_fee_amount = Self::calculate_protocol_fee_amount(fee_amount)?;
+		Self::update_cumulative_price(other_currency_id);
 		T::Currency::transfer(other_currency_id, &who, &Self::account_id(), other_currency_amount)
 			.expect("never failed because after checks");
 		T::Currency::transfer(base_currency_id, &Self::account_id(), &who, base_currency_amount)
 			.expect("never failed because after checks");
+		Self::pay_protocol_fee(base_currency_id, protocol_fee_amount);
 		<LiquidityPool<T>>::mutate(other_currency_id, |pool| {
-			let newpool = (pool.0 + other_currency_amount, pool.1 - base_currency_amount);
+			let newpool = (
+				pool.0 + other_currency_amount,
+				pool.1 - base_currency_amount - protocol_fee_amount,
+			);
 			*pool = newpool;
 		});
+		<CumulativeFee<T>>::mutate(other_currency_id, |fee| fee.1 += fee_amount - protocol_fee_amount);
+		Self::record_trade(other_currency_id, base_currency_amount);
 		Self::deposit_event(RawEvent::Swap(
 			who,
 			other_currency_id,
 			other_currency_amount,
 			base_currency_id,
 			base_currency_amount,
+			fee_amount,
 		));
 		Ok(())
 	}
 
-	// use base currency to swap other currency
-	pub fn swap_base_to_other(
+	/// Settle a swap of base currency for other currency with the pool, after all the checks.
+	fn transfer_base_to_other(
 		who: T::AccountId,
 		other_currency_id: CurrencyIdOf<T>,
 		base_currency_amount: BalanceOf<T>,
-		min_other_currency_amount: BalanceOf<T>,
-	) -> result::Result<(), Error> {
+		other_currency_amount: BalanceOf<T>,
+	) -> DispatchResult {
 		let base_currency_id = T::GetBaseCurrencyId::get();
-		ensure!(
-			base_currency_amount > 0.into()
-				&& T::Currency::ensure_can_withdraw(base_currency_id, &who, base_currency_amount).is_ok(),
-			Error::TokenNotEnough,
-		);
 		let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(other_currency_id);
-		let other_currency_amount =
-			Self::calculate_swap_target_amount(base_currency_pool, other_currency_pool, base_currency_amount);
-		ensure!(
-			other_currency_amount >= min_other_currency_amount,
-			Error::InacceptablePrice,
-		);
-
+		Self::ensure_price_impact_acceptable(
+			other_currency_id,
+			(
+				other_currency_pool.saturating_sub(other_currency_amount),
+				base_currency_pool.saturating_add(base_currency_amount),
+			),
+		)?;
+		let fee_amount = Self::calculate_fee_amount(
+			base_currency_pool,
+			other_currency_pool,
+			base_currency_amount,
+			other_currency_amount,
+		)?;
+		let protocol_fee_amount = Self::calculate_protocol_fee_amount(fee_amount)?;
+		Self::update_cumulative_price(other_currency_id);
 		T::Currency::transfer(base_currency_id, &who, &Self::account_id(), base_currency_amount)
 			.expect("never failed because after checks");
 		T::Currency::transfer(other_currency_id, &Self::account_id(), &who, other_currency_amount)
 			.expect("never failed because after checks");
+		Self::pay_protocol_fee(other_currency_id, protocol_fee_amount);
 		<LiquidityPool<T>>::mutate(other_currency_id, |pool| {
-			let newpool = (pool.0 - other_currency_amount, pool.1 + base_currency_amount);
+			let newpool = (
+				pool.0 - other_currency_amount - protocol_fee_amount,
+				pool.1 + base_currency_amount,
+			);
 			*pool = newpool;
 		});
+		<CumulativeFee<T>>::mutate(other_currency_id, |fee| fee.0 += fee_amount - protocol_fee_amount);
+		Self::record_trade(other_currency_id, base_currency_amount);
 		Self::deposit_event(RawEvent::Swap(
 			who,
 			base_currency_id,
 			base_currency_amount,
 			other_currency_id,
 			other_currency_amount,
+			fee_amount,
 		));
 		Ok(())
 	}
 
-	// use other currency to swap another other currency
-	pub fn swap_other_to_other(
+	/// Settle a swap of other currency for another other currency with both pools, after all the checks.
+	fn transfer_other_to_other(
 		who: T::AccountId,
 		supply_other_currency_id: CurrencyIdOf<T>,
 		supply_other_currency_amount: BalanceOf<T>,
+		intermediate_base_currency_amount: BalanceOf<T>,
 		target_other_currency_id: CurrencyIdOf<T>,
-		min_target_other_currency_amount: BalanceOf<T>,
-	) -> result::Result<(), Error> {
+		target_other_currency_amount: BalanceOf<T>,
+	) -> DispatchResult {
+		// the pools are settled one for each leg
 		ensure!(
-			supply_other_currency_amount > 0.into()
-				&& T::Currency::ensure_can_withdraw(supply_other_currency_id, &who, supply_other_currency_amount)
-					.is_ok(),
-			Error::TokenNotEnough,
+			supply_other_currency_id != target_other_currency_id,
+			Error::<T>::CanNotSwapItself
 		);
+		// the fees of both legs are taken, in the base currency and in the target currency
 		let (supply_other_currency_pool, supply_base_currency_pool) = Self::liquidity_pool(supply_other_currency_id);
-		let intermediate_base_currency_amount = Self::calculate_swap_target_amount(
+		let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_other_currency_id);
+		// the price impact of both legs is limited
+		Self::ensure_price_impact_acceptable(
+			supply_other_currency_id,
+			(
+				supply_other_currency_pool.saturating_add(supply_other_currency_amount),
+				supply_base_currency_pool.saturating_sub(intermediate_base_currency_amount),
+			),
+		)?;
+		Self::ensure_price_impact_acceptable(
+			target_other_currency_id,
+			(
+				target_other_currency_pool.saturating_sub(target_other_currency_amount),
+				target_base_currency_pool.saturating_add(intermediate_base_currency_amount),
+			),
+		)?;
+		let supply_fee_amount = Self::calculate_fee_amount(
 			supply_other_currency_pool,
 			supply_base_currency_pool,
 			supply_other_currency_amount,
-		);
-		let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_other_currency_id);
-		let target_other_currency_amount = Self::calculate_swap_target_amount(
+			intermediate_base_currency_amount,
+		)?;
+		let supply_protocol_fee_amount = Self::calculate_protocol_fee_amount(supply_fee_amount)?;
+		let target_fee_amount = Self::calculate_fee_amount(
 			target_base_currency_pool,
 			target_other_currency_pool,
 			intermediate_base_currency_amount,
+			target_other_currency_amount,
+		)?;
+		let target_protocol_fee_amount = Self::calculate_protocol_fee_amount(target_fee_amount)?;
+		// both pools are computed before anything moves. The intermediate base currency never leaves the module
+		// account, only the protocol fee of the first leg does, so both pools together hold as much base currency as
+		// before but that fee
+		let new_supply_pool = supply_other_currency_pool
+			.checked_add(&supply_other_currency_amount)
+			.and_then(|other| {
+				supply_base_currency_pool
+					.checked_sub(&intermediate_base_currency_amount)
+					.and_then(|base| base.checked_sub(&supply_protocol_fee_amount))
+					.map(|base| (other, base))
+			})
+			.ok_or(Error::<T>::NumOverflow)?;
+		let new_target_pool = target_other_currency_pool
+			.checked_sub(&target_other_currency_amount)
+			.and_then(|other| other.checked_sub(&target_protocol_fee_amount))
+			.and_then(|other| {
+				target_base_currency_pool
+					.checked_add(&intermediate_base_currency_amount)
+					.map(|base| (other, base))
+			})
+			.ok_or(Error::<T>::NumOverflow)?;
+		let module_account = Self::account_id();
+		debug_assert!(
+			new_supply_pool.1.saturating_add(new_target_pool.1)
+				<= T::Currency::balance(T::GetBaseCurrencyId::get(), &module_account)
+					.saturating_sub(supply_protocol_fee_amount),
+			"the recorded base currency of both pools is more than the module account holds",
 		);
-		ensure!(
-			target_other_currency_amount >= min_target_other_currency_amount,
-			Error::InacceptablePrice,
+		debug_assert!(
+			new_target_pool.0
+				<= T::Currency::balance(target_other_currency_id, &module_account)
+					.saturating_sub(target_other_currency_amount)
+					.saturating_sub(target_protocol_fee_amount),
+			"the recorded target currency of the pool is more than the module account holds",
 		);
-
+		Self::update_cumulative_price(supply_other_currency_id);
+		Self::update_cumulative_price(target_other_currency_id);
 		T::Currency::transfer(
 			supply_other_currency_id,
 			&who,
-			&Self::account_id(),
+			&module_account,
 			supply_other_currency_amount,
 		)
 		.expect("never failed because after checks");
 		T::Currency::transfer(
 			target_other_currency_id,
-			&Self::account_id(),
+			&module_account,
 			&who,
 			target_other_currency_amount,
 		)
 		.expect("never failed because after checks");
-		<LiquidityPool<T>>::mutate(supply_other_currency_id, |pool| {
-			let newpool = (
-				pool.0 + supply_other_currency_amount,
-				pool.1 - intermediate_base_currency_amount,
-			);
-			*pool = newpool;
+		Self::pay_protocol_fee(T::GetBaseCurrencyId::get(), supply_protocol_fee_amount);
+		Self::pay_protocol_fee(target_other_currency_id, target_protocol_fee_amount);
+		<LiquidityPool<T>>::insert(supply_other_currency_id, new_supply_pool);
+		<LiquidityPool<T>>::insert(target_other_currency_id, new_target_pool);
+		<CumulativeFee<T>>::mutate(supply_other_currency_id, |fee| {
+			fee.1 += supply_fee_amount - supply_protocol_fee_amount
 		});
-		<LiquidityPool<T>>::mutate(target_other_currency_id, |pool| {
-			let newpool = (
-				pool.0 - target_other_currency_amount,
-				pool.1 + intermediate_base_currency_amount,
-			);
-			*pool = newpool;
+		<CumulativeFee<T>>::mutate(target_other_currency_id, |fee| {
+			fee.0 += target_fee_amount - target_protocol_fee_amount
 		});
+		Self::record_trade(supply_other_currency_id, intermediate_base_currency_amount);
+		Self::record_trade(target_other_currency_id, intermediate_base_currency_amount);
 		Self::deposit_event(RawEvent::Swap(
 			who,
 			supply_other_currency_id,
 			supply_other_currency_amount,
 			target_other_currency_id,
 			target_other_currency_amount,
+			target_fee_amount,
 		));
 		Ok(())
 	}
 }
 
+/// Only the reports of the price deviations which can be recorded right now are accepted, one per pool at a time.
+impl<T: Trait> ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	fn validate_unsigned(call: &Self::Call) -> TransactionValidity {
+		match call {
+			Call::report_price_deviation(currency_id) => {
+				if Self::reportable_price_deviation(*currency_id).is_err() {
+					return Err(InvalidTransaction::Stale.into());
+				}
+				Ok(unsigned_keeper_transaction(
+					UNSIGNED_REPORT_PRIORITY,
+					(b"dex/price-deviation", currency_id),
+				))
+			}
+			_ => Err(InvalidTransaction::Call.into()),
+		}
+	}
+}
+
+/// The spot prices of the pools, composed through the base currency between two other currencies.
+impl<T: Trait> PriceProvider<CurrencyIdOf<T>, Price> for Module<T> {
+	fn get_price(base: CurrencyIdOf<T>, quote: CurrencyIdOf<T>) -> Option<Price> {
+		// the price of quote in base = the price of quote / the price of base, both in the base currency
+		let (base_numerator, base_denominator) = Self::pool_price(base)?;
+		let (quote_numerator, quote_denominator) = Self::pool_price(quote)?;
+		let parts = (quote_numerator * base_denominator * U512::from(FixedU128::accuracy()))
+			.checked_div(quote_denominator * base_numerator)?;
+		if parts > U512::from(u128::max_value()) {
+			None
+		} else {
+			Some(Price::from_parts(parts.low_u128()))
+		}
+	}
+}
+
 impl<T: Trait> DexManager<T::AccountId, CurrencyIdOf<T>, BalanceOf<T>> for Module<T> {
-	type Error = Error;
+	type Share = T::Share;
 
 	fn get_supply_amount(
 		supply_currency_id: CurrencyIdOf<T>,
 		target_currency_id: CurrencyIdOf<T>,
 		target_currency_amount: BalanceOf<T>,
-	) -> BalanceOf<T> {
+	) -> Result<BalanceOf<T>, DispatchError> {
 		let base_currency_id = T::GetBaseCurrencyId::get();
-		if supply_currency_id == target_currency_id {
-			0.into()
-		} else if target_currency_id == base_currency_id {
+		ensure!(supply_currency_id != target_currency_id, Error::<T>::CanNotSwapItself);
+		let supply_currency_amount = if target_currency_id == base_currency_id {
 			let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(supply_currency_id);
-			Self::calculate_swap_supply_amount(other_currency_pool, base_currency_pool, target_currency_amount)
+			Self::calculate_swap_supply_amount(
+				other_currency_pool,
+				base_currency_pool,
+				target_currency_amount,
+				Self::get_exchange_fee(supply_currency_id),
+			)?
 		} else if supply_currency_id == base_currency_id {
 			let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(target_currency_id);
-			Self::calculate_swap_supply_amount(base_currency_pool, other_currency_pool, target_currency_amount)
+			Self::calculate_swap_supply_amount(
+				base_currency_pool,
+				other_currency_pool,
+				target_currency_amount,
+				Self::get_exchange_fee(target_currency_id),
+			)?
 		} else {
 			let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_currency_id);
 			let intermediate_base_currency_amount = Self::calculate_swap_supply_amount(
 				target_base_currency_pool,
 				target_other_currency_pool,
 				target_currency_amount,
-			);
+				Self::get_exchange_fee(target_currency_id),
+			)?;
 			let (supply_other_currency_pool, supply_base_currency_pool) = Self::liquidity_pool(supply_currency_id);
 			Self::calculate_swap_supply_amount(
 				supply_other_currency_pool,
 				supply_base_currency_pool,
 				intermediate_base_currency_amount,
-			)
+				Self::get_exchange_fee(supply_currency_id),
+			)?
+		};
+		Ok(supply_currency_amount)
+	}
+
+	fn get_target_amount(
+		supply_currency_id: CurrencyIdOf<T>,
+		target_currency_id: CurrencyIdOf<T>,
+		supply_currency_amount: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		ensure!(supply_currency_id != target_currency_id, Error::<T>::CanNotSwapItself);
+		let (target_currency_amount, _) = if target_currency_id == base_currency_id {
+			let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(supply_currency_id);
+			ensure!(
+				other_currency_pool != 0.into() && base_currency_pool != 0.into(),
+				Error::<T>::InsufficientLiquidity
+			);
+			Self::calculate_swap_target_amount(
+				other_currency_pool,
+				base_currency_pool,
+				supply_currency_amount,
+				Self::get_exchange_fee(supply_currency_id),
+			)?
+		} else if supply_currency_id == base_currency_id {
+			let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(target_currency_id);
+			ensure!(
+				other_currency_pool != 0.into() && base_currency_pool != 0.into(),
+				Error::<T>::InsufficientLiquidity
+			);
+			Self::calculate_swap_target_amount(
+				base_currency_pool,
+				other_currency_pool,
+				supply_currency_amount,
+				Self::get_exchange_fee(target_currency_id),
+			)?
+		} else {
+			let (supply_other_currency_pool, supply_base_currency_pool) = Self::liquidity_pool(supply_currency_id);
+			let (target_other_currency_pool, target_base_currency_pool) = Self::liquidity_pool(target_currency_id);
+			ensure!(
+				supply_other_currency_pool != 0.into()
+					&& supply_base_currency_pool != 0.into()
+					&& target_other_currency_pool != 0.into()
+					&& target_base_currency_pool != 0.into(),
+				Error::<T>::InsufficientLiquidity
+			);
+			let (intermediate_base_currency_amount, _) = Self::calculate_swap_target_amount(
+				supply_other_currency_pool,
+				supply_base_currency_pool,
+				supply_currency_amount,
+				Self::get_exchange_fee(supply_currency_id),
+			)?;
+			Self::calculate_swap_target_amount(
+				target_base_currency_pool,
+				target_other_currency_pool,
+				intermediate_base_currency_amount,
+				Self::get_exchange_fee(target_currency_id),
+			)?
+		};
+		Ok(target_currency_amount)
+	}
+
+	fn get_supply_amounts_for_targets(
+		supply_currency_id: CurrencyIdOf<T>,
+		target_currency_id: CurrencyIdOf<T>,
+		target_currency_amounts: Vec<BalanceOf<T>>,
+	) -> Vec<BalanceOf<T>> {
+		let base_currency_id = T::GetBaseCurrencyId::get();
+		if supply_currency_id == target_currency_id {
+			return vec![];
 		}
+		// the pools of the legs of the swap as `(other currency id, whether the base currency is supplied)`
+		let legs = if target_currency_id == base_currency_id {
+			vec![(supply_currency_id, false)]
+		} else if supply_currency_id == base_currency_id {
+			vec![(target_currency_id, true)]
+		} else {
+			vec![(supply_currency_id, false), (target_currency_id, true)]
+		};
+		let mut pools: Vec<_> = legs
+			.into_iter()
+			.map(|(currency_id, base_supplied)| {
+				let (other_currency_pool, base_currency_pool) = Self::liquidity_pool(currency_id);
+				let fee = Self::get_exchange_fee(currency_id);
+				if base_supplied {
+					(base_currency_pool, other_currency_pool, fee)
+				} else {
+					(other_currency_pool, base_currency_pool, fee)
+				}
+			})
+			.collect();
+		target_currency_amounts
+			.into_iter()
+			.map(|target_currency_amount| Self::simulate_swap_with_exact_target(&mut pools, target_currency_amount))
+			.take_while(|supply_currency_amount| supply_currency_amount.is_ok())
+			.filter_map(|supply_currency_amount| supply_currency_amount.ok())
+			.collect()
 	}
 
 	fn exchange_currency(
 		who: T::AccountId,
 		supply: (CurrencyIdOf<T>, BalanceOf<T>),
 		target: (CurrencyIdOf<T>, BalanceOf<T>),
-	) -> Result<(), Self::Error> {
-		let base_currency_id = T::GetBaseCurrencyId::get();
-		ensure!(target.0 != supply.0, Error::CanNotSwapItself.into());
-		if target.0 == base_currency_id {
-			Self::swap_other_to_base(who, supply.0, supply.1, target.1)
-		} else if supply.0 == base_currency_id {
-			Self::swap_base_to_other(who, target.0, supply.1, target.1)
-		} else {
-			Self::swap_other_to_other(who, supply.0, supply.1, target.0, target.1)
-		}
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::do_swap_with_exact_supply(who, supply, target)
+	}
+
+	fn exchange_currency_with_exact_target(
+		who: T::AccountId,
+		supply: (CurrencyIdOf<T>, BalanceOf<T>),
+		target: (CurrencyIdOf<T>, BalanceOf<T>),
+	) -> Result<BalanceOf<T>, DispatchError> {
+		Self::do_swap_with_exact_target(who, supply, target).map(|(supply_amount, _)| supply_amount)
+	}
+
+	fn add_liquidity(
+		who: &T::AccountId,
+		other_currency_id: CurrencyIdOf<T>,
+		max_other_currency_amount: BalanceOf<T>,
+		max_base_currency_amount: BalanceOf<T>,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>, T::Share), DispatchError> {
+		Self::do_add_liquidity(
+			who,
+			other_currency_id,
+			max_other_currency_amount,
+			max_base_currency_amount,
+			0.into(),
+		)
+	}
+
+	fn remove_liquidity(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		share_amount: T::Share,
+	) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+		Self::do_withdraw_liquidity(who, currency_id, share_amount, 0.into(), 0.into())
 	}
 }
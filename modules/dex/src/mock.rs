@@ -3,8 +3,21 @@
 #![cfg(test)]
 
 use frame_support::{impl_outer_event, impl_outer_origin, parameter_types};
-use primitives::H256;
-use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use primitives::{
+	offchain::{
+		testing::{TestOffchainExt, TestTransactionPoolExt},
+		OffchainExt, TransactionPoolExt,
+	},
+	H256,
+};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	ModuleId, Perbill,
+};
+use std::cell::RefCell;
+use support::{FixedU128Ext, Rate};
+use system::offchain::TransactionSubmitter;
 
 use super::*;
 
@@ -31,12 +44,22 @@ parameter_types! {
 	pub const MaximumBlockLength: u32 = 2 * 1024;
 	pub const AvailableBlockRatio: Perbill = Perbill::one();
 	pub const GetBaseCurrencyId: CurrencyId = AUSD;
-	pub const GetExchangeFee: FixedU128 = FixedU128::from_rational(1, 100);
+	pub const GetExchangeFee: Rate = Rate::from_percent(1);
+	pub const GetMinimumPoolSizeForPricing: Balance = 1_000_000;
+	pub const PoolCurrencyIdsToMigrate: Vec<CurrencyId> = vec![BTC, DOT];
+	pub const ShareHoldersToMigrate: Vec<AccountId> = vec![CAROL];
+	pub const PriceDeviationCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
+	pub const PriceDeviationThreshold: Ratio = Ratio::from_percent(10);
+	pub const PriceDeviationReportInterval: BlockNumber = 10;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxBatchSize: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
 }
 
 pub type AccountId = u64;
 pub type BlockNumber = u64;
-pub type CurrencyId = u32;
+pub type CurrencyId = module_primitives::CurrencyId;
 pub type Share = u128;
 pub type Balance = u128;
 pub type Amount = i128;
@@ -57,6 +80,7 @@ impl system::Trait for Runtime {
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = ();
 }
 pub type System = system::Module<Runtime>;
 
@@ -68,12 +92,77 @@ impl orml_tokens::Trait for Runtime {
 }
 pub type Tokens = orml_tokens::Module<Runtime>;
 
+thread_local! {
+	static PROTOCOL_FEE_RATE: RefCell<Rate> = RefCell::new(Rate::from_natural(0));
+	static MINIMUM_LIQUIDITY: RefCell<Share> = RefCell::new(0);
+	static MINIMUM_SHARE_INCREMENT: RefCell<Share> = RefCell::new(0);
+	static BTC_PRICE: RefCell<Option<Price>> = RefCell::new(None);
+}
+
+pub struct MockPriceSource;
+impl MockPriceSource {
+	pub fn set_btc_price(price: Option<Price>) {
+		BTC_PRICE.with(|v| *v.borrow_mut() = price);
+	}
+}
+impl PriceProvider<CurrencyId, Price> for MockPriceSource {
+	fn get_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
+		match (base, quote) {
+			(AUSD, BTC) => BTC_PRICE.with(|v| *v.borrow()),
+			_ => None,
+		}
+	}
+}
+
+pub type Extrinsic = TestXt<Call<Runtime>, ()>;
+
+pub struct GetProtocolFeeRate;
+impl Get<Rate> for GetProtocolFeeRate {
+	fn get() -> Rate {
+		PROTOCOL_FEE_RATE.with(|v| *v.borrow())
+	}
+}
+
+pub struct MinimumLiquidity;
+impl Get<Share> for MinimumLiquidity {
+	fn get() -> Share {
+		MINIMUM_LIQUIDITY.with(|v| *v.borrow())
+	}
+}
+
+pub struct MinimumShareIncrement;
+impl Get<Share> for MinimumShareIncrement {
+	fn get() -> Share {
+		MINIMUM_SHARE_INCREMENT.with(|v| *v.borrow())
+	}
+}
+
 impl Trait for Runtime {
 	type Event = TestEvent;
 	type Currency = Tokens;
 	type Share = Share;
 	type GetBaseCurrencyId = GetBaseCurrencyId;
 	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = MaxBatchSize;
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = PoolCurrencyIdsToMigrate;
+	type ShareHoldersToMigrate = ShareHoldersToMigrate;
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = PriceDeviationCurrencyIds;
+	type PriceDeviationThreshold = PriceDeviationThreshold;
+	type PriceDeviationReportInterval = PriceDeviationReportInterval;
+	type Call = Call<Runtime>;
+	type SubmitTransaction = TransactionSubmitter<(), Call<Runtime>, Extrinsic>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
 }
 pub type DexModule = Module<Runtime>;
 
@@ -81,14 +170,21 @@ pub const ALICE: AccountId = 1;
 pub const BOB: AccountId = 2;
 pub const CAROL: AccountId = 3;
 
-pub const AUSD: CurrencyId = 1;
-pub const BTC: CurrencyId = 2;
-pub const DOT: CurrencyId = 3;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
+/// The share currency of the pool of btc.
+pub const BTC_SHARE: CurrencyId = CurrencyId::DexShare(3);
 
 pub struct ExtBuilder {
 	currency_id: Vec<CurrencyId>,
 	endowed_accounts: Vec<AccountId>,
 	initial_balance: Balance,
+	protocol_fee_rate: Rate,
+	minimum_liquidity: Share,
+	minimum_share_increment: Share,
+	enabled_trading_pairs: Vec<CurrencyId>,
+	initial_pools: Vec<(AccountId, CurrencyId, Balance, Balance)>,
 }
 
 impl Default for ExtBuilder {
@@ -97,12 +193,59 @@ impl Default for ExtBuilder {
 			currency_id: vec![AUSD, BTC, DOT],
 			endowed_accounts: vec![ALICE, BOB],
 			initial_balance: 1_000_000_000_000_000_000u128,
+			protocol_fee_rate: Rate::from_natural(0),
+			minimum_liquidity: 1000,
+			minimum_share_increment: 0,
+			enabled_trading_pairs: vec![BTC, DOT],
+			initial_pools: vec![],
 		}
 	}
 }
 
 impl ExtBuilder {
-	pub fn build(self) -> runtime_io::TestExternalities {
+	/// The balance of every currency the endowed accounts start with.
+	pub fn initial_balance(mut self, balance: Balance) -> Self {
+		self.initial_balance = balance;
+		self
+	}
+
+	pub fn protocol_fee_rate(mut self, rate: Rate) -> Self {
+		self.protocol_fee_rate = rate;
+		self
+	}
+
+	/// The shares locked to the module account by the first liquidity of each pool.
+	pub fn minimum_liquidity(mut self, share: Share) -> Self {
+		self.minimum_liquidity = share;
+		self
+	}
+
+	pub fn minimum_share_increment(mut self, share: Share) -> Self {
+		self.minimum_share_increment = share;
+		self
+	}
+
+	pub fn enabled_trading_pairs(mut self, currency_ids: Vec<CurrencyId>) -> Self {
+		self.enabled_trading_pairs = currency_ids;
+		self
+	}
+
+	/// The pools seeded at genesis as `(who, currency_id, other_currency_amount, base_currency_amount)`.
+	pub fn with_liquidity_pools(mut self, pools: Vec<(AccountId, CurrencyId, Balance, Balance)>) -> Self {
+		self.initial_pools = pools;
+		self
+	}
+
+	pub fn build(self) -> AuditedExternalities {
+		AuditedExternalities(self.build_unaudited())
+	}
+
+	/// Externalities without the audit, for the tests of accounts it doesn't check.
+	pub fn build_unaudited(self) -> runtime_io::TestExternalities {
+		PROTOCOL_FEE_RATE.with(|v| *v.borrow_mut() = self.protocol_fee_rate);
+		MINIMUM_LIQUIDITY.with(|v| *v.borrow_mut() = self.minimum_liquidity);
+		MINIMUM_SHARE_INCREMENT.with(|v| *v.borrow_mut() = self.minimum_share_increment);
+		MockPriceSource::set_btc_price(None);
 		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
 
 		orml_tokens::GenesisConfig::<Runtime> {
@@ -113,6 +256,50 @@ impl ExtBuilder {
 		.assimilate_storage(&mut t)
 		.unwrap();
 
+		GenesisConfig::<Runtime> {
+			enabled_trading_pairs: self.enabled_trading_pairs,
+			initial_pools: self.initial_pools,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
 		t.into()
 	}
 }
+
+/// Check the recorded pools against the balances of the module account and the total shares against the shares
+/// of the accounts, for the tests to call after each operation.
+pub fn assert_pool_consistency() {
+	assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &[ALICE, BOB, CAROL]), Ok(()));
+}
+
+/// Externalities auditing the accounting after every execution.
+pub struct AuditedExternalities(runtime_io::TestExternalities);
+
+impl AuditedExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			assert_pool_consistency();
+			result
+		})
+	}
+
+	/// Register the offchain extensions, with a function taking the transactions submitted by the offchain worker
+	/// so far.
+	pub fn with_offchain(mut self) -> (Self, impl Fn() -> Vec<Extrinsic>) {
+		let (offchain, _) = TestOffchainExt::new();
+		let (pool, pool_state) = TestTransactionPoolExt::new();
+		self.0.register_extension(OffchainExt::new(offchain));
+		self.0.register_extension(TransactionPoolExt::new(pool));
+		let submitted_transactions = move || {
+			pool_state
+				.write()
+				.transactions
+				.drain(..)
+				.map(|transaction| Extrinsic::decode(&mut &*transaction).unwrap())
+				.collect()
+		};
+		(self, submitted_transactions)
+	}
+}
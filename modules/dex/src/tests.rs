@@ -3,207 +3,3224 @@
 #![cfg(test)]
 
 use super::*;
-use frame_support::{assert_noop, assert_ok};
-use mock::{DexModule, ExtBuilder, Origin, System, TestEvent, Tokens, ALICE, AUSD, BOB, BTC, CAROL, DOT};
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::unhashed,
+	weights::{DispatchClass, GetDispatchInfo},
+	StorageDoubleMap, StorageMap, StorageValue,
+};
+use mock::{
+	assert_pool_consistency, AccountId, Balance, CurrencyId, DexModule, ExtBuilder, MaxBatchSize, MaxSwapPathLength,
+	MockPriceSource, Origin, Runtime, System, TestEvent, Tokens, ALICE, AUSD, BOB, BTC, BTC_SHARE, CAROL, DOT,
+};
+use runtime_io::hashing::blake2_256;
+use sp_runtime::traits::{OffchainWorker, OnInitialize};
+use support::{unsigned_pool_order, UNSIGNED_KEEPER_LONGEVITY};
 
 #[test]
 fn calculate_swap_target_amount_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_eq!(DexModule::calculate_swap_target_amount(10000, 10000, 10000), 4950);
+		let fee = FixedU128::from_rational(1, 100);
+		assert_eq!(
+			DexModule::calculate_swap_target_amount(10000, 10000, 10000, fee),
+			Ok((4950, 50))
+		);
 		// when target pool is 1
-		assert_eq!(DexModule::calculate_swap_target_amount(10000, 1, 10000), 0);
-		// when supply is too big
-		assert_eq!(DexModule::calculate_swap_target_amount(100, 100, 9901), 0);
+		assert_eq!(
+			DexModule::calculate_swap_target_amount(10000, 1, 10000, fee),
+			Ok((0, 0))
+		);
+		// the target pool is never drained, however big the supply
+		assert_eq!(
+			DexModule::calculate_swap_target_amount(100, 100, 9901, fee),
+			Ok((99, 0))
+		);
 		// when target amount is too small to no fees
-		assert_eq!(DexModule::calculate_swap_target_amount(100, 100, 9900), 99);
+		assert_eq!(
+			DexModule::calculate_swap_target_amount(100, 100, 9900, fee),
+			Ok((99, 0))
+		);
 	});
 }
 
 #[test]
 fn calculate_swap_supply_amount_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert!(DexModule::calculate_swap_supply_amount(10000, 10000, 4950) <= 10000);
-		assert_eq!(DexModule::calculate_swap_supply_amount(10000, 1, 1), 0);
+		let fee = FixedU128::from_rational(1, 100);
+		assert!(DexModule::calculate_swap_supply_amount(10000, 10000, 4950, fee).unwrap() <= 10000);
+		assert_eq!(DexModule::calculate_swap_supply_amount(10000, 10000, 0, fee), Ok(0));
+		// the target pool is never drained
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(10000, 1, 1, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(10000, 9999, 9900, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(10000, 10000, 10000, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(10000, 10000, 10001, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(0, 0, 1, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		// below the target pool, but not once the fee is added
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(10000, 10000, 9950, fee),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert!(DexModule::calculate_swap_supply_amount(10000, 10000, 9950, FixedU128::from_natural(0)).is_ok());
+	});
+}
+
+/// A deterministic pseudo random sequence, so a failure can be reproduced.
+fn next_random(seed: &mut u64) -> u64 {
+	*seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+	*seed >> 33
+}
+
+#[test]
+fn pool_product_never_decreases_by_swaps() {
+	ExtBuilder::default().build().execute_with(|| {
+		// small pools for the rounding to matter, without fee for DOT
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			1000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			1000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::set_exchange_fee(
+			Origin::ROOT,
+			DOT,
+			Some(FixedU128::from_natural(0))
+		));
+
+		let mut seed = 42;
+		for _ in 0..5000 {
+			let currency_id = if next_random(&mut seed) % 2 == 0 { BTC } else { DOT };
+			let amount = Balance::from(next_random(&mut seed) % 20 + 1);
+			let (other_currency_pool, base_currency_pool) = DexModule::liquidity_pool(currency_id);
+			// tiny swaps can fail to pay anything, only the pools are checked
+			let _ = match next_random(&mut seed) % 4 {
+				0 => DexModule::swap_other_to_base(BOB, currency_id, amount, 0),
+				1 => DexModule::swap_base_to_other(BOB, currency_id, amount, 0),
+				2 => DexModule::swap_other_to_exact_base(BOB, currency_id, Balance::max_value(), amount),
+				_ => DexModule::swap_base_to_exact_other(BOB, currency_id, Balance::max_value(), amount),
+			};
+
+			let (new_other_currency_pool, new_base_currency_pool) = DexModule::liquidity_pool(currency_id);
+			assert!(new_other_currency_pool * new_base_currency_pool >= other_currency_pool * base_currency_pool);
+			assert!(Tokens::balance(currency_id, DexModule::account_id()) >= new_other_currency_pool);
+		}
+		let (btc_pool, dot_pool) = (DexModule::liquidity_pool(BTC), DexModule::liquidity_pool(DOT));
+		assert!(Tokens::balance(AUSD, DexModule::account_id()) >= btc_pool.1 + dot_pool.1);
+		assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &[ALICE, BOB, CAROL]), Ok(()));
 	});
 }
 
 #[test]
 fn add_liquidity_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_noop!(
-			DexModule::add_liquidity(Origin::signed(ALICE), AUSD, 10000, 2000),
-			"BaseCurrencyIdNotAllowed",
+			DexModule::add_liquidity(Origin::signed(ALICE), AUSD, 10000, 2000, 0, None),
+			Error::<Runtime>::BaseCurrencyIdNotAllowed,
 		);
 		assert_eq!(DexModule::liquidity_pool(BTC), (0, 0));
 		assert_eq!(DexModule::total_shares(BTC), 0);
 		assert_eq!(DexModule::shares(BTC, ALICE), 0);
 		assert_noop!(
-			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 0, 10000000),
-			"InvalidBalance",
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 0, 10000000, 0, None),
+			Error::<Runtime>::InvalidBalance,
+		);
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 1, 1000, 0, None),
+			Error::<Runtime>::InvalidLiquidityIncrement,
 		);
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000000));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000000,
+			0,
+			None
+		));
 		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000000));
 		assert_eq!(DexModule::total_shares(BTC), 10000000);
-		assert_eq!(DexModule::shares(BTC, ALICE), 10000000);
-		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 1000));
+		assert_eq!(DexModule::shares(BTC, ALICE), 9999000);
+		assert_eq!(DexModule::shares(BTC, DexModule::account_id()), 1000);
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 1000, 0, None));
 		assert_eq!(DexModule::liquidity_pool(BTC), (10001, 10001000));
 		assert_eq!(DexModule::total_shares(BTC), 10001000);
 		assert_eq!(DexModule::shares(BTC, BOB), 1000);
+		let add_liquidity_event = TestEvent::dex(RawEvent::AddLiquidity(BOB, BTC, 1, 1000, 1000));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == add_liquidity_event));
 		assert_noop!(
-			DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 999),
-			"InvalidLiquidityIncrement",
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 999, 0, None),
+			Error::<Runtime>::InvalidLiquidityIncrement,
 		);
 		assert_eq!(DexModule::liquidity_pool(BTC), (10001, 10001000));
 		assert_eq!(DexModule::total_shares(BTC), 10001000);
 		assert_eq!(DexModule::shares(BTC, BOB), 1000);
-		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 2, 1000));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 2, 1000, 0, None));
 		assert_eq!(DexModule::liquidity_pool(BTC), (10002, 10002000));
-		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 1001));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 1001, 0, None));
 		assert_eq!(DexModule::liquidity_pool(BTC), (10003, 10003000));
 	});
 }
 
+#[test]
+fn add_liquidity_fails_below_min_share_increment() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		// BOB quotes 1000 shares for adding (1000, 1000), and accepts 1% less
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			1000,
+			1000,
+			990,
+			None
+		));
+		assert_eq!(DexModule::shares(BTC, BOB), 1000);
+
+		// front-run by a large swap, the pool only takes a quarter of the base currency
+		assert_ok!(DexModule::swap_other_to_base(ALICE, BTC, 11000, 0));
+		assert_eq!(DexModule::liquidity_pool(BTC), (22000, 5555));
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 1000, 1000, 990, None),
+			Error::<Runtime>::UnacceptableShareIncrement,
+		);
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1000, 1000, 0, None));
+		assert_eq!(DexModule::shares(BTC, BOB), 1499);
+		// the unused amounts are left with BOB
+		let add_liquidity_event = TestEvent::dex(RawEvent::AddLiquidity(BOB, BTC, 998, 252, 499));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == add_liquidity_event));
+	});
+}
+
+#[test]
+fn add_liquidity_takes_only_the_amounts_worth_the_shares() {
+	ExtBuilder::default().build().execute_with(|| {
+		// a unit of BTC is worth 1000 shares
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			1_000_000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::total_shares(BTC), 1_000_000);
+
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 10, 999, 0, None),
+			Error::<Runtime>::InvalidLiquidityIncrement,
+		);
+		// 1999 aUSD only match 1 BTC, the aUSD above the worth of the shares is left
+		let ausd_balance = Tokens::balance(AUSD, BOB);
+		assert_ok!(DexModule::do_add_liquidity(&BOB, BTC, 10, 1999, 0), (1, 1000, 1000));
+		assert_eq!(ausd_balance - Tokens::balance(AUSD, BOB), 1000);
+		assert_ok!(DexModule::do_add_liquidity(&BOB, BTC, 10, 2000, 0), (2, 2000, 2000));
+		assert_eq!(DexModule::liquidity_pool(BTC), (1003, 1_003_000));
+		assert_eq!(DexModule::total_shares(BTC), 1_003_000);
+	});
+
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			3,
+			1_000_000,
+			0,
+			None
+		));
+		// the shares are worth 0.999999 BTC, which is rounded up
+		assert_ok!(
+			DexModule::do_add_liquidity(&BOB, BTC, 1, 333_334, 0),
+			(1, 333_333, 333_333)
+		);
+		assert_eq!(DexModule::liquidity_pool(BTC), (4, 1_333_333));
+		assert_eq!(DexModule::total_shares(BTC), 1_333_333);
+	});
+}
+
+#[test]
+fn add_liquidity_rejects_dust_shares() {
+	ExtBuilder::default()
+		.minimum_share_increment(1000)
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				DexModule::add_liquidity(Origin::signed(ALICE), BTC, 1999, 1999, 0, None),
+				Error::<Runtime>::ShareIncrementTooSmall,
+			);
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				1_000_000,
+				1_000_000,
+				0,
+				None
+			));
+			assert_noop!(
+				DexModule::add_liquidity(Origin::signed(BOB), BTC, 999, 999, 0, None),
+				Error::<Runtime>::ShareIncrementTooSmall,
+			);
+			assert_ok!(
+				DexModule::do_add_liquidity(&BOB, BTC, 1000, 1000, 0),
+				(1000, 1000, 1000)
+			);
+		});
+}
+
 #[test]
 fn withdraw_liquidity_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_eq!(DexModule::liquidity_pool(BTC), (0, 0));
 		assert_eq!(DexModule::total_shares(BTC), 0);
 		assert_eq!(DexModule::shares(BTC, ALICE), 0);
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000000));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000000,
+			0,
+			None
+		));
 		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000000));
 		assert_eq!(DexModule::total_shares(BTC), 10000000);
-		assert_eq!(DexModule::shares(BTC, ALICE), 10000000);
-		assert_ok!(DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 10000));
+		assert_eq!(DexModule::shares(BTC, ALICE), 9999000);
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 10000), Ok((10, 10000)));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			0,
+			0,
+			None
+		));
 		assert_eq!(DexModule::liquidity_pool(BTC), (9990, 9990000));
 		assert_eq!(DexModule::total_shares(BTC), 9990000);
-		assert_eq!(DexModule::shares(BTC, ALICE), 9990000);
-		assert_ok!(DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 100));
+		assert_eq!(DexModule::shares(BTC, ALICE), 9989000);
+		let withdraw_liquidity_event = TestEvent::dex(RawEvent::WithdrawLiquidity(ALICE, BTC, 10, 10000, 10000));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == withdraw_liquidity_event));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			0,
+			0,
+			None
+		));
 		assert_eq!(DexModule::total_shares(BTC), 9989900);
-		assert_eq!(DexModule::shares(BTC, ALICE), 9989900);
+		assert_eq!(DexModule::shares(BTC, ALICE), 9988900);
 	});
 }
 
 #[test]
-fn swap_other_to_base_work() {
+fn withdraw_all_liquidity_destroys_the_pool() {
+	ExtBuilder::default().minimum_liquidity(0).build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 3000, 3000, 0, None));
+		assert!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0).is_ok());
+		assert_eq!(DexModule::shares(BTC, ALICE), 10000);
+		assert_eq!(DexModule::shares(BTC, BOB), 3000);
+
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			0,
+			0,
+			None
+		));
+		assert!(!<Shares<Runtime>>::exists(BTC, ALICE));
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![BOB], None));
+		assert!(<LiquidityPool<Runtime>>::exists(BTC));
+
+		// the last withdrawer takes whatever the rounding left in the pool
+		let (other_currency_pool, base_currency_pool) = DexModule::liquidity_pool(BTC);
+		let btc_balance = Tokens::free_balance(BTC, &BOB);
+		let ausd_balance = Tokens::free_balance(AUSD, &BOB);
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			3000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::free_balance(BTC, &BOB), btc_balance + other_currency_pool);
+		assert_eq!(Tokens::free_balance(AUSD, &BOB), ausd_balance + base_currency_pool);
+		assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 0);
+		assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 0);
+		assert!(!<Shares<Runtime>>::exists(BTC, BOB));
+		assert!(!<TotalShares<Runtime>>::exists(BTC));
+		assert!(!<LiquidityPool<Runtime>>::exists(BTC));
+		assert_eq!(DexModule::first_share_holder(BTC), None);
+		let pool_destroyed_event = TestEvent::dex(RawEvent::PoolDestroyed(BTC));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == pool_destroyed_event));
+
+		// the pool starts over with the next liquidity
+		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 100, 200, 0, None));
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 200));
+		assert_eq!(DexModule::total_shares(BTC), 200);
+	});
+}
+
+#[test]
+fn withdraw_liquidity_with_min_amounts() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000000));
-		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000000));
-		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 10000));
-		assert_eq!(Tokens::balance(BTC, CAROL), 10000);
-		assert_eq!(Tokens::balance(AUSD, CAROL), 0);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 1000), Ok((1000, 1000)));
+
+		// a swap before the withdrawal shifts the pool to more BTC and less AUSD
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 10000, 0), (10000, 4950));
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 1000), Ok((2000, 505)));
 		assert_noop!(
-			DexModule::swap_other_to_base(CAROL, BTC, 10001, 0),
-			Error::TokenNotEnough,
+			DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 1000, 1000, 1000, None),
+			Error::<Runtime>::UnacceptableLiquidityWithdrawn,
 		);
 		assert_noop!(
-			DexModule::swap_other_to_base(CAROL, BTC, 10000, 5000000),
-			Error::InacceptablePrice,
+			DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 1000, 2001, 0, None),
+			Error::<Runtime>::UnacceptableLiquidityWithdrawn,
 		);
-		assert_ok!(DexModule::swap_other_to_base(CAROL, BTC, 10000, 4950000));
-		assert_eq!(Tokens::balance(BTC, CAROL), 0);
-		assert_eq!(Tokens::balance(AUSD, CAROL), 4950000);
-		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050000));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			2000,
+			505,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (18000, 4545));
 	});
 }
 
 #[test]
-fn swap_base_to_other_work() {
+fn get_price_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000));
-		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000));
-		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, AUSD, 10000));
-		assert_eq!(Tokens::balance(BTC, CAROL), 0);
-		assert_eq!(Tokens::balance(AUSD, CAROL), 10000);
+		assert_eq!(DexModule::get_price(AUSD, BTC), None);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000_000_000,
+			2_000_000_000_000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1_000_000_000_000,
+			4_000_000_000_000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::get_price(AUSD, BTC), Some(Price::from_natural(2)));
+		assert_eq!(DexModule::get_price(BTC, AUSD), Some(Price::from_rational(1, 2)));
+		assert_eq!(DexModule::get_price(AUSD, AUSD), Some(Price::from_natural(1)));
+		// through the base currency
+		assert_eq!(DexModule::get_price(BTC, DOT), Some(Price::from_natural(2)));
+		assert_eq!(DexModule::get_price(DOT, BTC), Some(Price::from_rational(1, 2)));
+
+		// the rate of a small swap without the fee is the price less the slippage of the swap, in both directions
+		let no_fee = FixedU128::from_natural(0);
+		let (btc_pool, ausd_pool) = DexModule::liquidity_pool(BTC);
+		let (target_amount, _) =
+			DexModule::calculate_swap_target_amount(btc_pool, ausd_pool, 1_000_000, no_fee).unwrap();
+		assert_eq!(target_amount, 1_999_998);
+		let rate = Price::from_rational(target_amount, 1_000_000);
+		let price = DexModule::get_price(AUSD, BTC).unwrap();
+		assert!(rate < price && price.checked_sub(&rate).unwrap() <= Price::from_rational(1, 100_000));
+		let (target_amount, _) =
+			DexModule::calculate_swap_target_amount(ausd_pool, btc_pool, 1_000_000, no_fee).unwrap();
+		assert_eq!(target_amount, 499_999);
+		let rate = Price::from_rational(target_amount, 1_000_000);
+		let price = DexModule::get_price(BTC, AUSD).unwrap();
+		assert!(rate < price && price.checked_sub(&rate).unwrap() <= Price::from_rational(1, 100_000));
+	});
+}
+
+#[test]
+fn get_price_of_thin_pools_is_none() {
+	ExtBuilder::default().build().execute_with(|| {
+		// below the min pool size of 1_000_000 in the base currency
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000,
+			999_999,
+			0,
+			None
+		));
+		assert_eq!(DexModule::get_price(AUSD, BTC), None);
+		assert_eq!(DexModule::get_price(BTC, AUSD), None);
+		assert_eq!(DexModule::get_price(DOT, BTC), None);
+
+		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 1, 1_000, 0, None));
+		assert_eq!(DexModule::liquidity_pool(BTC), (1_001, 1_000_998));
+		assert!(DexModule::get_price(AUSD, BTC).is_some());
+		// the other pool is empty
+		assert_eq!(DexModule::get_price(DOT, BTC), None);
+	});
+}
+
+#[test]
+fn twap_starts_from_the_first_observation() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_eq!(DexModule::get_twap(BTC, 1), None);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000_000_000,
+			2_000_000_000_000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::cumulative_price(BTC), (FixedU128::from_parts(0), 1));
+		assert_eq!(DexModule::price_observations(BTC), vec![(FixedU128::from_parts(0), 1)]);
+		// nothing to average over within the block
+		assert_eq!(DexModule::get_twap(BTC, 0), None);
+
+		System::set_block_number(5);
+		assert_eq!(DexModule::get_twap(BTC, 4), Some(Price::from_natural(2)));
+		// not observed back that far
+		assert_eq!(DexModule::get_twap(BTC, 5), None);
+		assert_eq!(DexModule::get_twap(BTC, 6), None);
+	});
+}
+
+#[test]
+fn twap_barely_moves_with_a_sandwich_in_one_block() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000_000_000,
+			1_000_000_000_000,
+			0,
+			None
+		));
+
+		// the price is dumped and pumped back within the block
+		System::set_block_number(11);
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1_000_000_000_000, 0));
+		assert!(DexModule::get_price(AUSD, BTC).unwrap() < Price::from_rational(3, 10));
+		assert_eq!(DexModule::get_twap(BTC, 10), Some(Price::from_natural(1)));
+		let (_, ausd_amount) = DexModule::liquidity_pool(BTC);
+		assert_ok!(DexModule::swap_base_to_other(
+			BOB,
+			BTC,
+			1_000_000_000_000 - ausd_amount,
+			0
+		));
+		assert_eq!(DexModule::price_observations(BTC).len(), 2);
+
+		System::set_block_number(12);
+		let spot_price = DexModule::get_price(AUSD, BTC).unwrap();
+		assert!(spot_price < Price::from_rational(99, 100));
+		// only the price the block ended with counts, for one block out of eleven
+		assert_eq!(DexModule::get_twap(BTC, 1), Some(spot_price));
+		let twap = DexModule::get_twap(BTC, 10).unwrap();
+		assert!(twap > Price::from_rational(998, 1000) && twap < Price::from_natural(1));
+	});
+}
+
+#[test]
+fn twap_survives_the_accumulator_overflow() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000_000_000,
+			1_000_000_000_000,
+			0,
+			None
+		));
+		let near_max = FixedU128::from_parts(u128::max_value() - 500_000_000_000_000_000);
+		<CumulativePrice<Runtime>>::insert(BTC, (near_max, 1));
+		<PriceObservations<Runtime>>::insert(BTC, vec![(near_max, 1)]);
+
+		System::set_block_number(11);
+		assert_eq!(DexModule::get_twap(BTC, 10), Some(Price::from_natural(1)));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1_000_000, 0));
+		// wrapped around
+		assert!(DexModule::cumulative_price(BTC).0 < near_max);
+		assert_eq!(DexModule::get_twap(BTC, 10), Some(Price::from_natural(1)));
+	});
+}
+
+#[test]
+fn get_liquidity_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		// no pool, no shares
+		assert_eq!(DexModule::get_liquidity(&ALICE, BTC), (0, 0));
+
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::get_liquidity(&ALICE, BTC), (9000, 9000));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0), (1000, 900));
+		assert_eq!(DexModule::liquidity_pool(BTC), (11000, 9100));
+
+		// BOB enters at the shifted price
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1100, 1000, 0, None));
+		assert_eq!(DexModule::liquidity_pool(BTC), (12100, 10010));
+		assert_eq!(DexModule::shares(BTC, BOB), 1000);
+		assert_eq!(DexModule::get_liquidity(&BOB, BTC), (1100, 910));
+		assert_ok!(DexModule::swap_base_to_other(BOB, BTC, 2000, 0), (2000, 1994));
+		assert_eq!(DexModule::liquidity_pool(BTC), (10106, 12010));
+
+		// both positions follow the pool, with the fees
+		assert_eq!(DexModule::get_liquidity(&ALICE, BTC), (8268, 9826));
+		assert_eq!(DexModule::get_liquidity(&BOB, BTC), (918, 1091));
+		assert_eq!(DexModule::get_liquidity(&CAROL, BTC), (0, 0));
+		assert_eq!(DexModule::get_liquidity(&ALICE, DOT), (0, 0));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			1000,
+			918,
+			1091,
+			None
+		));
+		assert_eq!(DexModule::get_liquidity(&BOB, BTC), (0, 0));
+	});
+}
+
+#[test]
+fn withdraw_liquidity_by_amounts_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::shares(BTC, ALICE), 9000);
 		assert_noop!(
-			DexModule::swap_base_to_other(CAROL, BTC, 10001, 0),
-			Error::TokenNotEnough,
+			DexModule::withdraw_liquidity_by_amounts(Origin::signed(ALICE), BTC, 0, 1000, None),
+			Error::<Runtime>::InvalidBalance,
 		);
+		// not at the price of the pool
 		assert_noop!(
-			DexModule::swap_base_to_other(CAROL, BTC, 10000, 5000),
-			Error::InacceptablePrice,
+			DexModule::withdraw_liquidity_by_amounts(Origin::signed(ALICE), BTC, 10, 100, None),
+			Error::<Runtime>::InvalidWithdrawAmounts,
+		);
+
+		// the same as withdrawing the shares worth the amounts
+		assert_eq!(DexModule::get_share_amount_by_amounts(BTC, 10, 1000), Ok(1000));
+		assert_ok!(DexModule::withdraw_liquidity_by_amounts(
+			Origin::signed(ALICE),
+			BTC,
+			10,
+			1000,
+			None
+		));
+		let withdraw_liquidity_event = TestEvent::dex(RawEvent::WithdrawLiquidity(ALICE, BTC, 10, 1000, 1000));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == withdraw_liquidity_event));
+		assert_eq!(DexModule::liquidity_pool(BTC), (90, 9000));
+		assert_eq!(DexModule::shares(BTC, ALICE), 8000);
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (80, 8000));
+		assert_eq!(DexModule::shares(BTC, ALICE), 7000);
+
+		// within a unit of the other currency, the base currency is paid out at the price of the pool
+		assert_ok!(DexModule::withdraw_liquidity_by_amounts(
+			Origin::signed(ALICE),
+			BTC,
+			1,
+			50,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (79, 7900));
+		assert_eq!(DexModule::shares(BTC, ALICE), 6900);
+		assert_eq!(Tokens::balance(BTC, ALICE), 1_000_000_000_000_000_000 - 79);
+		assert_eq!(Tokens::balance(AUSD, ALICE), 1_000_000_000_000_000_000 - 7900);
+
+		// worth more than the shares of the account
+		assert_noop!(
+			DexModule::withdraw_liquidity_by_amounts(Origin::signed(ALICE), BTC, 70, 7000, None),
+			Error::<Runtime>::ShareNotEnough,
 		);
-		assert_ok!(DexModule::swap_base_to_other(CAROL, BTC, 10000, 4950));
-		assert_eq!(Tokens::balance(BTC, CAROL), 4950);
-		assert_eq!(Tokens::balance(AUSD, CAROL), 0);
-		assert_eq!(DexModule::liquidity_pool(BTC), (5050, 20000));
 	});
 }
 
 #[test]
-fn swap_other_to_other_work() {
+fn withdraw_liquidity_by_amounts_leaves_no_share_dust() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 100, 10000));
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), DOT, 1000, 10000));
-		assert_eq!(DexModule::liquidity_pool(BTC), (100, 10000));
-		assert_eq!(DexModule::liquidity_pool(DOT), (1000, 10000));
-		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, DOT, 1000));
-		assert_eq!(Tokens::balance(BTC, CAROL), 0);
-		assert_eq!(Tokens::balance(DOT, CAROL), 1000);
-		assert_noop!(
-			DexModule::swap_other_to_other(CAROL, DOT, 1001, BTC, 0),
-			Error::TokenNotEnough,
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 2));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 100, 0), (100, 4950));
+		assert_eq!(DexModule::liquidity_pool(BTC), (200, 5050));
+		assert_eq!(DexModule::shares(BTC, ALICE), 8998);
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 8998), Ok((179, 4543)));
+
+		// fewer shares are worth the same amounts, the rest are withdrawn with them
+		assert_eq!(DexModule::get_share_amount_by_amounts(BTC, 179, 4543), Ok(8997));
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 8997), Ok((179, 4543)));
+		assert_ok!(DexModule::withdraw_liquidity_by_amounts(
+			Origin::signed(ALICE),
+			BTC,
+			179,
+			4543,
+			None
+		));
+		assert_eq!(DexModule::shares(BTC, ALICE), 0);
+		assert_eq!(DexModule::total_shares(BTC), 1002);
+		assert_eq!(DexModule::liquidity_pool(BTC), (21, 507));
+	});
+}
+
+#[test]
+fn large_pools_fail_cleanly_on_overflow() {
+	let half = Balance::max_value() / 2;
+	ExtBuilder::default().initial_balance(half).build().execute_with(|| {
+		let fee = FixedU128::from_rational(1, 100);
+		// the supply pool can't grow past the max balance
+		assert_eq!(
+			DexModule::calculate_swap_target_amount(half + 2, half, half, fee),
+			Err(Error::<Runtime>::NumOverflow.into())
+		);
+		// the supply for most of the target pool doesn't fit in a balance
+		assert_eq!(
+			DexModule::calculate_swap_supply_amount(half, half, half / 100 * 99, fee),
+			Err(Error::<Runtime>::NumOverflow.into())
 		);
+
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			half,
+			half,
+			0,
+			None
+		));
+		assert_eq!(DexModule::total_shares(BTC), half);
+		// the shares and the redeemable amounts are exact with pools of any size
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1000, 1000, 0, None));
+		assert_eq!(DexModule::shares(BTC, BOB), 1000);
+		assert_eq!(DexModule::get_redeemable_amounts(BTC, 1000), Ok((1000, 1000)));
 		assert_noop!(
-			DexModule::swap_other_to_other(CAROL, DOT, 1000, BTC, 35),
-			Error::InacceptablePrice,
+			DexModule::swap_with_exact_target(Origin::signed(BOB), (BTC, half), (AUSD, half / 100 * 99), None),
+			Error::<Runtime>::NumOverflow,
 		);
-		assert_ok!(DexModule::swap_other_to_other(CAROL, DOT, 1000, BTC, 34));
-		assert_eq!(Tokens::balance(BTC, CAROL), 34);
-		assert_eq!(Tokens::balance(DOT, CAROL), 0);
-		assert_eq!(DexModule::liquidity_pool(BTC), (66, 14950));
-		assert_eq!(DexModule::liquidity_pool(DOT), (2000, 5050));
 	});
 }
 
 #[test]
-fn swap_currency_work() {
+fn calls_fail_past_deadline() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 100, 10000));
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), DOT, 1000, 10000));
-		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 100));
+		System::set_block_number(10);
+		// zero is no deadline
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			Some(0)
+		));
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000, 0, Some(9)),
+			Error::<Runtime>::PastDeadline,
+		);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			Some(10)
+		));
+
+		assert_ok!(DexModule::swap_with_exact_supply(
+			Origin::signed(BOB),
+			(BTC, 100),
+			(AUSD, 1),
+			Some(10)
+		));
+		assert_ok!(DexModule::swap_with_exact_target(
+			Origin::signed(BOB),
+			(BTC, 100),
+			(AUSD, 1),
+			Some(10)
+		));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			0,
+			0,
+			Some(10)
+		));
+
+		System::set_block_number(11);
 		assert_noop!(
-			DexModule::swap_currency(Origin::signed(CAROL), (BTC, 10000), (BTC, 1000)),
-			"CanNotSwapItself"
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 100), (AUSD, 1), Some(10)),
+			Error::<Runtime>::PastDeadline,
 		);
 		assert_noop!(
-			DexModule::swap_currency(Origin::signed(CAROL), (BTC, 101), (DOT, 1000)),
-			"TokenNotEnough"
+			DexModule::swap_with_exact_target(Origin::signed(BOB), (BTC, 100), (AUSD, 1), Some(10)),
+			Error::<Runtime>::PastDeadline,
 		);
-		assert_ok!(DexModule::swap_currency(
-			Origin::signed(CAROL),
-			(BTC, 100),
-			(AUSD, 4950)
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000, 0, Some(10)),
+			Error::<Runtime>::PastDeadline,
+		);
+		assert_noop!(
+			DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 100, 0, 0, Some(10)),
+			Error::<Runtime>::PastDeadline,
+		);
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			0,
+			0,
+			None
 		));
-		assert_ok!(DexModule::swap_currency(Origin::signed(CAROL), (AUSD, 4950), (BTC, 90)));
-		assert_ok!(DexModule::swap_currency(Origin::signed(CAROL), (BTC, 90), (DOT, 300)));
 	});
 }
 
 #[test]
-fn exchange_currency_work() {
+fn set_exchange_fee_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 100, 10000));
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), DOT, 1000, 10000));
-		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 100));
+		System::set_block_number(1);
+		let fee = FixedU128::from_rational(3, 1000);
+		assert!(DexModule::set_exchange_fee(Origin::signed(ALICE), BTC, Some(fee)).is_err());
 		assert_noop!(
-			DexModule::exchange_currency(CAROL, (BTC, 10000), (BTC, 1000)),
-			Error::CanNotSwapItself
+			DexModule::set_exchange_fee(Origin::ROOT, AUSD, Some(fee)),
+			Error::<Runtime>::BaseCurrencyIdNotAllowed,
 		);
 		assert_noop!(
-			DexModule::exchange_currency(CAROL, (BTC, 101), (DOT, 1000)),
-			Error::TokenNotEnough
+			DexModule::set_exchange_fee(Origin::ROOT, BTC, Some(FixedU128::from_natural(1))),
+			Error::<Runtime>::InvalidExchangeFee,
+		);
+		assert_eq!(DexModule::get_exchange_fee(BTC), FixedU128::from_rational(1, 100));
+
+		assert_ok!(DexModule::set_exchange_fee(Origin::ROOT, BTC, Some(fee)));
+		assert_eq!(DexModule::exchange_fee(BTC), Some(fee));
+		assert_eq!(DexModule::get_exchange_fee(BTC), fee);
+		assert_eq!(DexModule::get_exchange_fee(DOT), FixedU128::from_rational(1, 100));
+		let update_event = TestEvent::dex(RawEvent::UpdateExchangeFee(BTC, Some(fee)));
+		assert!(System::events().iter().any(|record| record.event == update_event));
+
+		assert_ok!(DexModule::set_exchange_fee(Origin::ROOT, BTC, None));
+		assert_eq!(DexModule::exchange_fee(BTC), None);
+		assert_eq!(DexModule::get_exchange_fee(BTC), FixedU128::from_rational(1, 100));
+	});
+}
+
+#[test]
+fn trading_pair_whitelist_work() {
+	ExtBuilder::default()
+		.enabled_trading_pairs(vec![DOT])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				DOT,
+				10000,
+				10000,
+				0,
+				None
+			));
+			assert_noop!(
+				DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000, 0, None),
+				Error::<Runtime>::TradingPairNotAllowed,
+			);
+			assert!(DexModule::enable_trading_pair(Origin::signed(ALICE), BTC).is_err());
+			assert_noop!(
+				DexModule::enable_trading_pair(Origin::ROOT, AUSD),
+				Error::<Runtime>::BaseCurrencyIdNotAllowed,
+			);
+
+			assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, BTC));
+			assert!(DexModule::trading_pair_status(BTC));
+			let enable_event = TestEvent::dex(RawEvent::EnableTradingPair(BTC));
+			assert!(System::events().iter().any(|record| record.event == enable_event));
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				10000,
+				10000,
+				0,
+				None
+			));
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				(BTC, 1000),
+				(AUSD, 1),
+				None
+			));
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				(DOT, 1000),
+				(BTC, 1),
+				None
+			));
+
+			assert!(DexModule::disable_trading_pair(Origin::signed(ALICE), BTC).is_err());
+			assert_ok!(DexModule::disable_trading_pair(Origin::ROOT, BTC));
+			assert!(!DexModule::trading_pair_status(BTC));
+			let disable_event = TestEvent::dex(RawEvent::DisableTradingPair(BTC));
+			assert!(System::events().iter().any(|record| record.event == disable_event));
+			assert_noop!(
+				DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 1000), (AUSD, 1), None),
+				Error::<Runtime>::TradingPairNotAllowed,
+			);
+			assert_noop!(
+				DexModule::swap_with_exact_target(Origin::signed(BOB), (AUSD, 1000), (BTC, 1), None),
+				Error::<Runtime>::TradingPairNotAllowed,
+			);
+			assert_noop!(
+				DexModule::exchange_currency(BOB, (DOT, 1000), (BTC, 1)),
+				Error::<Runtime>::TradingPairNotAllowed,
+			);
+			assert_noop!(
+				DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000, 0, None),
+				Error::<Runtime>::TradingPairNotAllowed,
+			);
+
+			// the liquidity providers can still exit
+			let shares = DexModule::shares(BTC, ALICE);
+			assert_ok!(DexModule::withdraw_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				shares,
+				0,
+				0,
+				None
+			));
+			assert_eq!(DexModule::shares(BTC, ALICE), 0);
+		});
+}
+
+#[test]
+fn pause_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+
+		assert!(DexModule::pause(Origin::signed(ALICE)).is_err());
+		assert_ok!(DexModule::pause(Origin::ROOT));
+		assert!(DexModule::paused());
+		let pause_event = TestEvent::dex(RawEvent::Pause);
+		assert!(System::events().iter().any(|record| record.event == pause_event));
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 1000), (AUSD, 1), None),
+			Error::<Runtime>::Paused,
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_target(Origin::signed(BOB), (AUSD, 1000), (BTC, 1), None),
+			Error::<Runtime>::Paused,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, AUSD, DOT], 1000, 1, None),
+			Error::<Runtime>::Paused,
+		);
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 1000, 1000, 0, None),
+			Error::<Runtime>::Paused,
+		);
+		// nor through the dex manager by the other modules
+		assert_noop!(
+			DexModule::exchange_currency(BOB, (DOT, 1000), (BTC, 1)),
+			Error::<Runtime>::Paused,
+		);
+		assert_noop!(
+			<DexModule as DexManager<_, _, _>>::add_liquidity(&BOB, BTC, 1000, 1000),
+			Error::<Runtime>::Paused,
 		);
-		assert_ok!(DexModule::exchange_currency(CAROL, (BTC, 100), (AUSD, 4950)));
-		assert_ok!(DexModule::exchange_currency(CAROL, (AUSD, 4950), (BTC, 90)));
-		assert_ok!(DexModule::exchange_currency(CAROL, (BTC, 90), (DOT, 300)));
+
+		// the liquidity providers can still exit
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 1000));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			1000,
+			0,
+			0,
+			None
+		));
+		assert_ok!(DexModule::withdraw_liquidity_by_amounts(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			1000,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (8000, 8000));
+
+		assert!(DexModule::unpause(Origin::signed(ALICE)).is_err());
+		assert_ok!(DexModule::unpause(Origin::ROOT));
+		assert!(!DexModule::paused());
+		let unpause_event = TestEvent::dex(RawEvent::Unpause);
+		assert!(System::events().iter().any(|record| record.event == unpause_event));
+		assert_ok!(DexModule::swap_with_exact_supply(
+			Origin::signed(BOB),
+			(BTC, 1000),
+			(AUSD, 1),
+			None
+		));
+		assert_ok!(DexModule::swap_with_exact_target(
+			Origin::signed(BOB),
+			(AUSD, 1000),
+			(BTC, 1),
+			None
+		));
+		assert_ok!(DexModule::swap_by_path(
+			Origin::signed(BOB),
+			vec![BTC, AUSD, DOT],
+			1000,
+			1,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1000, 1000, 0, None));
+		assert_ok!(DexModule::exchange_currency(BOB, (DOT, 1000), (BTC, 1)));
+		assert_ok!(<DexModule as DexManager<_, _, _>>::add_liquidity(&BOB, DOT, 1000, 1000));
 	});
 }
 
 #[test]
-fn get_supply_amount_work() {
+fn force_close_pool_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_ok!(DexModule::add_liquidity(Origin::signed(ALICE), BTC, 10000, 10000));
-		let supply_amount = DexModule::get_supply_amount(BTC, AUSD, 4950);
-		assert_ok!(DexModule::exchange_currency(BOB, (BTC, supply_amount), (AUSD, 4950)));
+		System::set_block_number(1);
+		let module_account = DexModule::account_id();
+		let treasury = DexModule::treasury_account_id();
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			7001,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 3000, 5000, 0, None));
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, CAROL, 2000));
+		assert_eq!(DexModule::liquidity_pool(BTC), (13000, 9102));
+		// the last one to become a holder comes first
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![CAROL, BOB, ALICE, module_account], None)
+		);
+
+		assert!(DexModule::force_close_pool(Origin::signed(ALICE), BTC).is_err());
+		assert_noop!(
+			DexModule::force_close_pool(Origin::ROOT, AUSD),
+			Error::<Runtime>::BaseCurrencyIdNotAllowed,
+		);
+		assert_noop!(
+			DexModule::force_close_pool(Origin::ROOT, DOT),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_ok!(DexModule::force_close_pool(Origin::ROOT, BTC));
+
+		// the last share holders are refunded right away, up to the max refunds per block
+		assert_eq!(DexModule::closing_pools(), vec![BTC]);
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![ALICE, module_account], None)
+		);
+		assert_eq!(DexModule::liquidity_pool(BTC), (8000, 5602));
+		assert_eq!(DexModule::total_shares(BTC), 8000);
+		let refund_event = TestEvent::dex(RawEvent::WithdrawLiquidity(CAROL, BTC, 2000, 1400, 2000));
+		assert!(System::events().iter().any(|record| record.event == refund_event));
+		let refund_event = TestEvent::dex(RawEvent::WithdrawLiquidity(BOB, BTC, 3000, 2100, 3000));
+		assert!(System::events().iter().any(|record| record.event == refund_event));
+		assert_eq!(Tokens::balance(BTC, CAROL), 2000);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 1400);
+
+		// the pool can be neither traded nor reopened until it is closed
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 1000), (AUSD, 1), None),
+			Error::<Runtime>::TradingPairNotAllowed,
+		);
+		assert_noop!(
+			DexModule::enable_trading_pair(Origin::ROOT, BTC),
+			Error::<Runtime>::PoolIsClosing,
+		);
+		assert_noop!(
+			DexModule::force_close_pool(Origin::ROOT, BTC),
+			Error::<Runtime>::PoolIsClosing,
+		);
+
+		DexModule::on_initialize(2);
+		let refund_event = TestEvent::dex(RawEvent::WithdrawLiquidity(ALICE, BTC, 7000, 4901, 7000));
+		assert!(System::events().iter().any(|record| record.event == refund_event));
+		// the locked shares take whatever the rounding left, for the treasury
+		let refund_event = TestEvent::dex(RawEvent::WithdrawLiquidity(module_account, BTC, 1000, 701, 1000));
+		assert!(System::events().iter().any(|record| record.event == refund_event));
+		let closed_event = TestEvent::dex(RawEvent::PoolClosed(BTC));
+		assert!(System::events().iter().any(|record| record.event == closed_event));
+		assert_eq!(Tokens::balance(BTC, treasury), 1000);
+		assert_eq!(Tokens::balance(AUSD, treasury), 701);
+		assert_eq!(Tokens::balance(BTC, module_account), 0);
+		assert_eq!(Tokens::balance(AUSD, module_account), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (0, 0));
+		assert_eq!(DexModule::total_shares(BTC), 0);
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![], None));
+		assert!(DexModule::closing_pools().is_empty());
+
+		// a closed pool can be enabled and initialized again
+		assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, BTC));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 2000, 2000, 0, None));
+		assert_eq!(DexModule::shares(BTC, BOB), 1000);
 	});
 }
+
+#[test]
+fn share_holders_from_before_they_were_tracked_are_registered() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		let module_account = DexModule::account_id();
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 3000, 3000, 0, None));
+		// the shares of bob from before the share holders were tracked
+		DexModule::remove_share_holder(BTC, &BOB);
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![ALICE, module_account], None)
+		);
+
+		// tracked once the shares are updated
+		assert_ok!(DexModule::transfer_shares(Origin::signed(BOB), BTC, CAROL, 1000));
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![CAROL, BOB, ALICE, module_account], None)
+		);
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(CAROL),
+			BTC,
+			1000,
+			0,
+			0,
+			None
+		));
+		DexModule::remove_share_holder(BTC, &BOB);
+
+		// the pool stays closing with the shares of the holders which aren't tracked
+		assert_ok!(DexModule::force_close_pool(Origin::ROOT, BTC));
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![], None));
+		assert_eq!(DexModule::total_shares(BTC), 2000);
+		DexModule::on_initialize(2);
+		assert_eq!(DexModule::closing_pools(), vec![BTC]);
+
+		assert_noop!(
+			DexModule::register_share_holders(Origin::signed(CAROL), BTC, vec![ALICE, BOB, CAROL]),
+			Error::<Runtime>::InvalidBatchSize,
+		);
+		// the accounts without shares stay untracked
+		assert_ok!(DexModule::register_share_holders(
+			Origin::signed(CAROL),
+			BTC,
+			vec![BOB, CAROL]
+		));
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![BOB], None));
+		DexModule::on_initialize(3);
+		let refund_event = TestEvent::dex(RawEvent::WithdrawLiquidity(BOB, BTC, 2000, 2000, 2000));
+		assert!(System::events().iter().any(|record| record.event == refund_event));
+		let closed_event = TestEvent::dex(RawEvent::PoolClosed(BTC));
+		assert!(System::events().iter().any(|record| record.event == closed_event));
+		assert!(DexModule::closing_pools().is_empty());
+		assert_eq!(Tokens::balance(BTC, module_account), 0);
+		assert_eq!(Tokens::balance(AUSD, module_account), 0);
+	});
+}
+
+#[test]
+fn share_holders_from_before_they_were_tracked_stay_untracked_when_the_update_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		let module_account = DexModule::account_id();
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		// the shares of alice from before they were minted in the share currency and the holders were tracked
+		assert_ok!(Tokens::withdraw(BTC_SHARE, &ALICE, 9000));
+		DexModule::remove_share_holder(BTC, &ALICE);
+
+		assert_noop!(
+			DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 1000, 1001, 0, None),
+			Error::<Runtime>::UnacceptableLiquidityWithdrawn,
+		);
+		assert_noop!(
+			DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 9001),
+			Error::<Runtime>::ShareNotEnough,
+		);
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 1000, 1000, 1001, None),
+			Error::<Runtime>::UnacceptableShareIncrement,
+		);
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![module_account], None));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 0);
+
+		// the check counts the share currency minted once alice is registered
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 9000));
+		assert_eq!(Tokens::balance(BTC_SHARE, &BOB), 9000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+	});
+}
+
+#[test]
+fn offchain_worker_reports_price_deviations() {
+	let (mut ext, submitted_transactions) = ExtBuilder::default()
+		.with_liquidity_pools(vec![
+			(ALICE, BTC, 1_000_000, 2_000_000),
+			(ALICE, DOT, 1_000_000, 1_000_000),
+		])
+		.build()
+		.with_offchain();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		// no price of the source
+		DexModule::offchain_worker(1);
+		assert_eq!(submitted_transactions(), vec![]);
+
+		// within the threshold of the price of the pool, 2
+		MockPriceSource::set_btc_price(Some(Price::from_rational(21, 10)));
+		DexModule::offchain_worker(1);
+		assert_eq!(submitted_transactions(), vec![]);
+
+		MockPriceSource::set_btc_price(Some(Price::from_rational(25, 10)));
+		DexModule::offchain_worker(1);
+		let transactions = submitted_transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].0, None);
+		assert_eq!(transactions[0].1, Call::report_price_deviation(BTC));
+	});
+}
+
+#[test]
+fn report_price_deviation_work() {
+	ExtBuilder::default()
+		.with_liquidity_pools(vec![(ALICE, BTC, 1_000_000, 2_000_000)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			let report = Call::<Runtime>::report_price_deviation(BTC);
+			assert_noop!(
+				DexModule::report_price_deviation(Origin::NONE, BTC),
+				Error::<Runtime>::PriceDeviationNotReportable,
+			);
+			assert_eq!(
+				DexModule::validate_unsigned(&report),
+				Err(InvalidTransaction::Stale.into())
+			);
+
+			MockPriceSource::set_btc_price(Some(Price::from_rational(25, 10)));
+			assert!(DexModule::validate_unsigned(&report).is_ok());
+			assert!(DexModule::report_price_deviation(Origin::signed(ALICE), BTC).is_err());
+			assert_ok!(DexModule::report_price_deviation(Origin::NONE, BTC));
+			assert_eq!(DexModule::last_price_deviation_report(BTC), Some(1));
+			let deviation_event = TestEvent::dex(RawEvent::PriceDeviation(
+				BTC,
+				Price::from_natural(2),
+				Price::from_rational(25, 10),
+			));
+			assert!(System::events().iter().any(|record| record.event == deviation_event));
+
+			// once per interval
+			System::set_block_number(10);
+			assert_noop!(
+				DexModule::report_price_deviation(Origin::NONE, BTC),
+				Error::<Runtime>::PriceDeviationNotReportable,
+			);
+			assert_eq!(
+				DexModule::validate_unsigned(&report),
+				Err(InvalidTransaction::Stale.into())
+			);
+			System::set_block_number(11);
+			assert!(DexModule::validate_unsigned(&report).is_ok());
+			assert_ok!(DexModule::report_price_deviation(Origin::NONE, BTC));
+			assert_eq!(DexModule::last_price_deviation_report(BTC), Some(11));
+
+			assert_eq!(
+				DexModule::validate_unsigned(&Call::<Runtime>::pause()),
+				Err(InvalidTransaction::Call.into())
+			);
+		});
+}
+
+#[test]
+fn price_deviation_reports_are_deduplicated_in_the_pool() {
+	ExtBuilder::default()
+		.with_liquidity_pools(vec![
+			(ALICE, BTC, 1_000_000, 2_000_000),
+			(ALICE, DOT, 1_000_000, 2_000_000),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			MockPriceSource::set_btc_price(Some(Price::from_rational(25, 10)));
+			let validity = DexModule::validate_unsigned(&Call::<Runtime>::report_price_deviation(BTC)).unwrap();
+			assert_eq!(validity.priority, UNSIGNED_REPORT_PRIORITY);
+			assert_eq!(validity.longevity, UNSIGNED_KEEPER_LONGEVITY);
+			assert_eq!(validity.provides, vec![(b"dex/price-deviation", BTC).encode()]);
+
+			// the reports of every validator are kept once, dot has no oracle price to deviate from
+			assert_eq!(
+				unsigned_pool_order::<DexModule>(vec![
+					Call::report_price_deviation(BTC),
+					Call::report_price_deviation(DOT),
+					Call::report_price_deviation(BTC),
+				]),
+				vec![Call::report_price_deviation(BTC)]
+			);
+
+			// none once reported
+			assert_ok!(DexModule::report_price_deviation(Origin::NONE, BTC));
+			assert!(unsigned_pool_order::<DexModule>(vec![Call::report_price_deviation(BTC)]).is_empty());
+		});
+}
+
+#[test]
+fn swap_with_the_exchange_fee_of_the_pool() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::set_exchange_fee(
+			Origin::ROOT,
+			DOT,
+			Some(FixedU128::from_rational(5, 100))
+		));
+
+		// the same swaps on pools of the same size, with fees of 1% and 5%
+		assert_eq!(DexModule::get_supply_amount(BTC, AUSD, 1000), Ok(1124));
+		assert_eq!(DexModule::get_supply_amount(DOT, AUSD, 1000), Ok(1177));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 10000, 0), (10000, 4950));
+		assert_ok!(DexModule::swap_other_to_base(BOB, DOT, 10000, 0), (10000, 4750));
+		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050));
+		assert_eq!(DexModule::liquidity_pool(DOT), (20000, 5250));
+	});
+}
+
+#[test]
+fn max_price_impact_limits_swaps() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000,
+			1_000_000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1_000_000,
+			1_000_000,
+			0,
+			None
+		));
+
+		let limit = Some(Ratio::from_rational(1, 10));
+		assert!(DexModule::set_max_price_impact(Origin::signed(ALICE), BTC, limit).is_err());
+		assert_noop!(
+			DexModule::set_max_price_impact(Origin::ROOT, AUSD, limit),
+			Error::<Runtime>::BaseCurrencyIdNotAllowed,
+		);
+		assert_ok!(DexModule::set_max_price_impact(Origin::ROOT, BTC, limit));
+		assert_eq!(DexModule::max_price_impact(BTC), limit);
+		let update_event = TestEvent::dex(RawEvent::UpdateMaxPriceImpact(BTC, limit));
+		assert!(System::events().iter().any(|record| record.event == update_event));
+
+		// the price of BTC drops by just over 10%
+		assert_noop!(
+			DexModule::swap_other_to_base(BOB, BTC, 54_379, 0),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 54_379), (AUSD, 0), None),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+		// and by just under
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 54_378, 0), (54_378, 51_058));
+
+		// no limit for DOT
+		assert_ok!(DexModule::swap_other_to_base(BOB, DOT, 500_000, 0));
+
+		assert_ok!(DexModule::set_max_price_impact(Origin::ROOT, BTC, None));
+		assert_eq!(DexModule::max_price_impact(BTC), None);
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 500_000, 0));
+	});
+}
+
+#[test]
+fn max_other_currency_amount_caps_adding_liquidity() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert!(DexModule::set_max_other_currency_amount(Origin::signed(ALICE), BTC, Some(20000)).is_err());
+		assert_noop!(
+			DexModule::set_max_other_currency_amount(Origin::ROOT, AUSD, Some(20000)),
+			Error::<Runtime>::BaseCurrencyIdNotAllowed,
+		);
+		assert_ok!(DexModule::set_max_other_currency_amount(Origin::ROOT, BTC, Some(20000)));
+		assert_eq!(DexModule::max_other_currency_amount(BTC), Some(20000));
+		let update_event = TestEvent::dex(RawEvent::UpdateMaxOtherCurrencyAmount(BTC, Some(20000)));
+		assert!(System::events().iter().any(|record| record.event == update_event));
+
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 20001, 20001, 0, None),
+			Error::<Runtime>::ExceedPoolCapacity,
+		);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		// one more than the cap
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 10001, 10001, 0, None),
+			Error::<Runtime>::ExceedPoolCapacity,
+		);
+		// up to the cap exactly
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 20000));
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(BOB), BTC, 1, 1, 0, None),
+			Error::<Runtime>::ExceedPoolCapacity,
+		);
+
+		// swaps still move the reserve past the cap
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0));
+		assert_eq!(DexModule::liquidity_pool(BTC).0, 21000);
+		// no cap for DOT
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			30000,
+			30000,
+			0,
+			None
+		));
+
+		assert_ok!(DexModule::set_max_other_currency_amount(Origin::ROOT, BTC, None));
+		assert_eq!(DexModule::max_other_currency_amount(BTC), None);
+		let update_event = TestEvent::dex(RawEvent::UpdateMaxOtherCurrencyAmount(BTC, None));
+		assert!(System::events().iter().any(|record| record.event == update_event));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+	});
+}
+
+#[test]
+fn max_price_impact_of_both_legs() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1_000_000,
+			1_000_000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1_000_000,
+			1_000_000,
+			0,
+			None
+		));
+		let limit = Some(Ratio::from_rational(1, 10));
+		assert_ok!(DexModule::set_max_price_impact(Origin::ROOT, BTC, limit));
+		assert_ok!(DexModule::set_max_price_impact(Origin::ROOT, DOT, limit));
+
+		// 51_058 aUSD out of the BTC pool, just under the limit, raise the price of DOT by over 10%
+		assert_noop!(
+			DexModule::swap_other_to_other(BOB, BTC, 54_378, DOT, 0),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, AUSD, DOT], 54_378, 0, None),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+		assert_ok!(DexModule::set_max_price_impact(Origin::ROOT, DOT, None));
+		assert_noop!(
+			DexModule::swap_other_to_other(BOB, BTC, 54_379, DOT, 0),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+		assert_ok!(DexModule::swap_other_to_other(BOB, BTC, 54_378, DOT, 0));
+
+		// the second hop is checked before the first one is settled
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![DOT, AUSD, BTC], 200_000, 0, None),
+			Error::<Runtime>::ExceedPriceImpactLimit,
+		);
+	});
+}
+
+#[test]
+fn protocol_fee_paid_to_treasury() {
+	ExtBuilder::default()
+		.protocol_fee_rate(FixedU128::from_rational(1, 2))
+		.build()
+		.execute_with(|| {
+			let treasury = DexModule::treasury_account_id();
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				10000,
+				10000,
+				0,
+				None
+			));
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				DOT,
+				10000,
+				10000,
+				0,
+				None
+			));
+
+			// half of the fee of 50 is taken out of the base currency pool
+			assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 10000, 4950), (10000, 4950));
+			assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5025));
+			assert_eq!(Tokens::balance(AUSD, treasury), 25);
+			assert_eq!(DexModule::cumulative_fee(BTC), (0, 25));
+
+			assert_ok!(DexModule::swap_base_to_other(BOB, BTC, 5025, 9900), (5025, 9900));
+			assert_eq!(DexModule::liquidity_pool(BTC), (10050, 10050));
+			assert_eq!(Tokens::balance(BTC, treasury), 50);
+
+			// both legs pay the protocol fee
+			assert_ok!(DexModule::swap_other_to_other(BOB, BTC, 1000, DOT, 817), (1000, 817));
+			assert_eq!(DexModule::liquidity_pool(BTC), (11050, 9146));
+			assert_eq!(DexModule::liquidity_pool(DOT), (9179, 10900));
+			assert_eq!(Tokens::balance(AUSD, treasury), 29);
+			assert_eq!(Tokens::balance(DOT, treasury), 4);
+
+			assert_eq!(DexModule::protocol_fees(AUSD), 29);
+			assert_eq!(DexModule::protocol_fees(BTC), 50);
+			assert_eq!(DexModule::protocol_fees(DOT), 4);
+			assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &[ALICE, BOB, CAROL]), Ok(()));
+		});
+}
+
+#[test]
+fn no_protocol_fee_without_rate() {
+	ExtBuilder::default().build().execute_with(|| {
+		let treasury = DexModule::treasury_account_id();
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 10000, 4950), (10000, 4950));
+		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050));
+		assert_eq!(Tokens::balance(AUSD, treasury), 0);
+		assert_eq!(DexModule::protocol_fees(AUSD), 0);
+		assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &[ALICE, BOB, CAROL]), Ok(()));
+	});
+}
+
+#[test]
+fn cumulative_fee_is_the_sum_of_swap_fees() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0), (1000, 900));
+		assert_ok!(DexModule::swap_base_to_other(BOB, BTC, 2000, 0), (2000, 1962));
+		assert_ok!(DexModule::swap_other_to_exact_base(BOB, BTC, 1000, 500), (431, 500));
+		assert_ok!(DexModule::swap_base_to_exact_other(BOB, BTC, 1000, 700), (856, 700));
+
+		let (mut other_currency_fee, mut base_currency_fee) = (0, 0);
+		for record in System::events() {
+			if let TestEvent::dex(RawEvent::Swap(_, supply_currency_id, _, _, _, fee)) = record.event {
+				if supply_currency_id == BTC {
+					base_currency_fee += fee;
+				} else {
+					other_currency_fee += fee;
+				}
+			}
+		}
+		assert_eq!((other_currency_fee, base_currency_fee), (26, 14));
+		assert_eq!(DexModule::cumulative_fee(BTC), (other_currency_fee, base_currency_fee));
+	});
+}
+
+#[test]
+fn cumulative_fee_of_both_legs() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+
+		// the fee of the first leg in base currency, then the fee of the second leg in DOT
+		assert_ok!(DexModule::swap_other_to_other(BOB, BTC, 1000, DOT, 0), (1000, 817));
+		assert_eq!(DexModule::cumulative_fee(BTC), (0, 9));
+		assert_eq!(DexModule::cumulative_fee(DOT), (8, 0));
+		let swap_event = TestEvent::dex(RawEvent::Swap(BOB, BTC, 1000, DOT, 817, 8));
+		assert!(System::events().iter().any(|record| record.event == swap_event));
+	});
+}
+
+#[test]
+fn pool_info_counts_the_swaps() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(
+			DexModule::pool_info(BTC),
+			PoolInfo {
+				other_reserve: 10000,
+				base_reserve: 10000,
+				total_shares: 10000,
+				cumulative_volume: 0,
+				trade_count: 0,
+			}
+		);
+
+		// the volume is counted in base currency, whichever side is supplied
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0), (1000, 900));
+		assert_ok!(DexModule::swap_base_to_other(BOB, BTC, 1000, 0), (1000, 1079));
+		assert_eq!(DexModule::cumulative_volume(BTC), 1900);
+		assert_eq!(DexModule::trade_count(BTC), 2);
+
+		// both legs are counted with their pools, at the intermediate base currency amount
+		assert_ok!(DexModule::swap_other_to_other(BOB, BTC, 1000, DOT, 0), (1000, 830));
+		assert_eq!(
+			DexModule::pool_info(BTC),
+			PoolInfo {
+				other_reserve: 10921,
+				base_reserve: 9185,
+				total_shares: 10000,
+				cumulative_volume: 2815,
+				trade_count: 3,
+			}
+		);
+		assert_eq!(
+			DexModule::pool_info(DOT),
+			PoolInfo {
+				other_reserve: 9170,
+				base_reserve: 10915,
+				total_shares: 10000,
+				cumulative_volume: 915,
+				trade_count: 1,
+			}
+		);
+		assert_eq!(DexModule::pool_info(AUSD), Default::default());
+	});
+}
+
+#[test]
+fn pool_info_counters_saturate() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		<CumulativeVolume<Runtime>>::insert(BTC, Balance::max_value() - 100);
+		<TradeCount<Runtime>>::insert(BTC, u64::max_value());
+
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0), (1000, 900));
+		assert_eq!(DexModule::cumulative_volume(BTC), Balance::max_value());
+		assert_eq!(DexModule::trade_count(BTC), u64::max_value());
+	});
+}
+
+#[test]
+fn transfer_shares_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::shares(BTC, ALICE), 9000);
+		assert_noop!(
+			DexModule::transfer_shares(Origin::signed(ALICE), BTC, ALICE, 1000),
+			Error::<Runtime>::CanNotTransferToSelf,
+		);
+		assert_noop!(
+			DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 9001),
+			Error::<Runtime>::ShareNotEnough,
+		);
+		assert_noop!(
+			DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 0),
+			Error::<Runtime>::ShareNotEnough,
+		);
+
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 1000));
+		assert_eq!(DexModule::shares(BTC, ALICE), 8000);
+		assert_eq!(DexModule::shares(BTC, BOB), 1000);
+		assert_eq!(DexModule::total_shares(BTC), 10000);
+		let transfer_shares_event = TestEvent::dex(RawEvent::TransferShares(ALICE, BOB, BTC, 1000));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == transfer_shares_event));
+
+		// the entry of the sender is removed with the full balance
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, CAROL, 8000));
+		assert!(!<Shares<Runtime>>::exists(BTC, ALICE));
+		assert_eq!(DexModule::shares(BTC, CAROL), 8000);
+		assert_eq!(DexModule::total_shares(BTC), 10000);
+
+		// the shares are withdrawn by the receiver
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(CAROL),
+			BTC,
+			8000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC, CAROL), 8000);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 8000);
+	});
+}
+
+#[test]
+fn shares_are_minted_in_the_share_currency() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC_SHARE, 10000, 10000, 0, None),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+		assert_noop!(
+			DexModule::enable_trading_pair(Origin::ROOT, BTC_SHARE),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 9000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &DexModule::account_id()), 1000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 2000));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 7000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &BOB), 2000);
+
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			2000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC_SHARE, &BOB), 0);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), 8000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+	});
+}
+
+#[test]
+fn shares_moved_out_of_the_dex_cannot_be_withdrawn() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		// moved by another module, as collateral for one
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(BTC_SHARE, &ALICE, &CAROL, 5000));
+
+		assert_noop!(
+			DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 5000, 0, 0, None),
+			Error::<Runtime>::ShareNotEnough,
+		);
+		assert_noop!(
+			DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 5000),
+			Error::<Runtime>::ShareNotEnough,
+		);
+		// the holder of the share currency has no shares to withdraw
+		assert_noop!(
+			DexModule::withdraw_liquidity(Origin::signed(CAROL), BTC, 5000, 0, 0, None),
+			Error::<Runtime>::ShareNotEnough,
+		);
+
+		// the shares backed by the share currency left are still withdrawn
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			4000,
+			0,
+			0,
+			None
+		));
+		assert_ok!(<Tokens as MultiCurrency<_>>::transfer(BTC_SHARE, &CAROL, &ALICE, 5000));
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			5000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 0);
+	});
+}
+
+#[test]
+fn minimum_liquidity_is_locked() {
+	ExtBuilder::default().build().execute_with(|| {
+		let module_account = DexModule::account_id();
+		assert_noop!(
+			DexModule::add_liquidity(Origin::signed(ALICE), BTC, 1, 1, 0, None),
+			Error::<Runtime>::InvalidLiquidityIncrement,
+		);
+
+		// the first provider only gets the shares above the minimum liquidity
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1001,
+			1001,
+			0,
+			None
+		));
+		assert_eq!(DexModule::total_shares(BTC), 1001);
+		assert_eq!(DexModule::shares(BTC, ALICE), 1);
+		assert_eq!(DexModule::shares(BTC, module_account), 1000);
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 1001, 1001, 0, None));
+		assert_eq!(DexModule::shares(BTC, BOB), 1001);
+
+		// the first provider can't get back what was deposited
+		assert_ok!(DexModule::withdraw_liquidity(Origin::signed(ALICE), BTC, 1, 0, 0, None));
+		assert_eq!(Tokens::balance(BTC, ALICE), 1_000_000_000_000_000_000 - 1000);
+		assert_eq!(Tokens::balance(AUSD, ALICE), 1_000_000_000_000_000_000 - 1000);
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(BOB),
+			BTC,
+			1001,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC, BOB), 1_000_000_000_000_000_000);
+		assert_eq!(Tokens::balance(AUSD, BOB), 1_000_000_000_000_000_000);
+
+		// the locked shares keep the pool from being drained
+		assert_eq!(DexModule::liquidity_pool(BTC), (1000, 1000));
+		assert_eq!(DexModule::total_shares(BTC), 1000);
+		assert_noop!(
+			DexModule::remove_liquidity(&module_account, BTC, 1000),
+			Error::<Runtime>::ShareNotEnough,
+		);
+	});
+}
+
+#[test]
+fn swap_with_genesis_liquidity_pools() {
+	ExtBuilder::default()
+		.enabled_trading_pairs(vec![])
+		.with_liquidity_pools(vec![(ALICE, BTC, 10000, 10000), (ALICE, DOT, 1000, 10000)])
+		.build()
+		.execute_with(|| {
+			assert!(DexModule::trading_pair_status(BTC));
+			assert!(DexModule::trading_pair_status(DOT));
+			assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000));
+			assert_eq!(DexModule::total_shares(BTC), 10000);
+			assert_eq!(DexModule::shares(BTC, ALICE), 9000);
+			assert_eq!(DexModule::shares(BTC, DexModule::account_id()), 1000);
+			assert_eq!(Tokens::balance(BTC, ALICE), 1_000_000_000_000_000_000 - 10000);
+			assert_eq!(Tokens::balance(AUSD, ALICE), 1_000_000_000_000_000_000 - 20000);
+
+			assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 10000, 4950), (10000, 4950));
+			assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050));
+			assert_ok!(DexModule::withdraw_liquidity(
+				Origin::signed(ALICE),
+				DOT,
+				9000,
+				0,
+				0,
+				None
+			));
+			assert_eq!(DexModule::liquidity_pool(DOT), (100, 1000));
+		});
+}
+
+#[test]
+#[should_panic(expected = "duplicate currency id in the initial pools")]
+fn genesis_liquidity_pools_reject_duplicate_currency_ids() {
+	ExtBuilder::default()
+		.with_liquidity_pools(vec![(ALICE, BTC, 10000, 10000), (BOB, BTC, 10000, 10000)])
+		.build();
+}
+
+#[test]
+#[should_panic(expected = "zero amount in the initial pools")]
+fn genesis_liquidity_pools_reject_zero_amounts() {
+	ExtBuilder::default()
+		.with_liquidity_pools(vec![(ALICE, BTC, 0, 10000)])
+		.build();
+}
+
+#[test]
+fn swap_other_to_base_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000000));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 10000));
+		assert_eq!(Tokens::balance(BTC, CAROL), 10000);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 0);
+		assert_noop!(
+			DexModule::swap_other_to_base(CAROL, BTC, 10001, 0),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_base(CAROL, BTC, 10000, 5000000),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_ok!(
+			DexModule::swap_other_to_base(CAROL, BTC, 10000, 4950000),
+			(10000, 4950000)
+		);
+		assert_eq!(Tokens::balance(BTC, CAROL), 0);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 4950000);
+		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050000));
+	});
+}
+
+#[test]
+fn swap_base_to_other_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (10000, 10000));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, AUSD, 10000));
+		assert_eq!(Tokens::balance(BTC, CAROL), 0);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 10000);
+		assert_noop!(
+			DexModule::swap_base_to_other(CAROL, BTC, 10001, 0),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_noop!(
+			DexModule::swap_base_to_other(CAROL, BTC, 10000, 5000),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_ok!(DexModule::swap_base_to_other(CAROL, BTC, 10000, 4950), (10000, 4950));
+		assert_eq!(Tokens::balance(BTC, CAROL), 4950);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (5050, 20000));
+	});
+}
+
+#[test]
+fn swap_other_to_other_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 10000));
+		assert_eq!(DexModule::liquidity_pool(DOT), (1000, 10000));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, DOT, 1000));
+		assert_eq!(Tokens::balance(BTC, CAROL), 0);
+		assert_eq!(Tokens::balance(DOT, CAROL), 1000);
+		assert_noop!(
+			DexModule::swap_other_to_other(CAROL, DOT, 1001, BTC, 0),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_other(CAROL, DOT, 1000, BTC, 34),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_ok!(DexModule::swap_other_to_other(CAROL, DOT, 1000, BTC, 33), (1000, 33));
+		assert_eq!(Tokens::balance(BTC, CAROL), 33);
+		assert_eq!(Tokens::balance(DOT, CAROL), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (67, 14950));
+		assert_eq!(DexModule::liquidity_pool(DOT), (2000, 5050));
+	});
+}
+
+#[test]
+fn swap_other_to_other_with_extreme_pool_ratios() {
+	ExtBuilder::default()
+		.protocol_fee_rate(FixedU128::from_rational(1, 2))
+		.build()
+		.execute_with(|| {
+			let treasury = DexModule::treasury_account_id();
+			// BTC is worth little base currency, and DOT even less
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				1_000_000_000_000,
+				1_000_000,
+				0,
+				None
+			));
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				DOT,
+				1_000_000_000_000_000,
+				1000,
+				0,
+				None
+			));
+			assert_pool_consistency();
+
+			assert_ok!(
+				DexModule::swap_other_to_other(BOB, BTC, 10_000_000_000, DOT, 0),
+				(10_000_000_000, 898_341_820_201_833)
+			);
+			assert_eq!(DexModule::liquidity_pool(BTC), (1_010_000_000_000, 990_150));
+			assert_eq!(DexModule::liquidity_pool(DOT), (97_121_099_898_158, 10_801));
+			assert_eq!(Tokens::balance(AUSD, treasury), 49);
+			assert_eq!(Tokens::balance(DOT, treasury), 4_537_079_900_009);
+			assert_pool_consistency();
+
+			// the intermediate amount rounds down to nothing
+			assert_noop!(
+				DexModule::swap_other_to_other(BOB, DOT, 1_000_000, BTC, 1),
+				Error::<Runtime>::InacceptablePrice,
+			);
+			assert_pool_consistency();
+
+			assert_ok!(
+				DexModule::swap_other_to_other(BOB, DOT, 100_000_000_000_000, BTC, 0),
+				(100_000_000_000_000, 5_448_567_411)
+			);
+			assert_eq!(DexModule::liquidity_pool(BTC), (1_004_523_914_572, 995_575));
+			assert_eq!(DexModule::liquidity_pool(DOT), (197_121_099_898_158, 5349));
+			assert_pool_consistency();
+
+			// the DOT pool is nearly drained of DOT
+			assert_ok!(
+				DexModule::swap_other_to_other(BOB, BTC, 1_000_000_000_000, DOT, 0),
+				(1_000_000_000_000, 193_049_772_097_905)
+			);
+			assert_eq!(DexModule::liquidity_pool(BTC), (2_004_523_914_572, 501_394));
+			assert_eq!(DexModule::liquidity_pool(DOT), (3_096_328_951_274, 497_047));
+			assert_eq!(Tokens::balance(AUSD, treasury), 2559);
+			assert_eq!(Tokens::balance(BTC, treasury), 27_518_017);
+			assert_eq!(Tokens::balance(DOT, treasury), 5_512_078_748_988);
+			assert_pool_consistency();
+		});
+}
+
+#[test]
+fn swap_other_to_other_rejects_the_same_pool() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_noop!(
+			DexModule::swap_other_to_other(BOB, BTC, 1000, BTC, 0),
+			Error::<Runtime>::CanNotSwapItself,
+		);
+		assert_pool_consistency();
+	});
+}
+
+#[test]
+fn swap_with_empty_pool_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (0, 0));
+
+		// no minimum target, the supply would be taken for nothing
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 1000), (AUSD, 0), None),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (AUSD, 1000), (BTC, 0), None),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (DOT, 1000), (BTC, 0), None),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(BOB), (BTC, 1000), (DOT, 0), None),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![DOT, AUSD, BTC], 1000, 0, None),
+			Error::<Runtime>::LiquidityPoolNotExists,
+		);
+		assert_eq!(Tokens::balance(BTC, DexModule::account_id()), 0);
+	});
+}
+
+#[test]
+fn swap_other_to_exact_base_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 10000));
+		assert_noop!(
+			DexModule::swap_other_to_exact_base(CAROL, BTC, 10000, 0),
+			Error::<Runtime>::InvalidBalance,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_base(CAROL, BTC, 10000, 10000000),
+			Error::<Runtime>::InsufficientLiquidity,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_base(CAROL, BTC, 9999, 4950000),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_base(CAROL, BTC, 20000, 4951000),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_ok!(
+			DexModule::swap_other_to_exact_base(CAROL, BTC, 10000, 4950000),
+			(10000, 4950000)
+		);
+		assert_eq!(Tokens::balance(BTC, CAROL), 0);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 4950000);
+		assert_eq!(DexModule::liquidity_pool(BTC), (20000, 5050000));
+	});
+}
+
+#[test]
+fn swap_base_to_exact_other_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, AUSD, 10000));
+		assert_noop!(
+			DexModule::swap_base_to_exact_other(CAROL, BTC, 10000, 0),
+			Error::<Runtime>::InvalidBalance,
+		);
+		assert_noop!(
+			DexModule::swap_base_to_exact_other(CAROL, BTC, 10000, 10000),
+			Error::<Runtime>::InsufficientLiquidity,
+		);
+		assert_noop!(
+			DexModule::swap_base_to_exact_other(CAROL, BTC, 9999, 4950),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_noop!(
+			DexModule::swap_base_to_exact_other(CAROL, BTC, 20000, 4951),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_ok!(
+			DexModule::swap_base_to_exact_other(CAROL, BTC, 10000, 4950),
+			(10000, 4950)
+		);
+		assert_eq!(Tokens::balance(BTC, CAROL), 4950);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 0);
+		assert_eq!(DexModule::liquidity_pool(BTC), (5050, 20000));
+	});
+}
+
+#[test]
+fn swap_other_to_exact_other_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, DOT, 1000));
+		assert_noop!(
+			DexModule::swap_other_to_exact_other(CAROL, DOT, 1000, BTC, 0),
+			Error::<Runtime>::InvalidBalance,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_other(CAROL, DOT, 1000, BTC, 100),
+			Error::<Runtime>::InsufficientLiquidity,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_other(CAROL, DOT, 783, BTC, 30),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_noop!(
+			DexModule::swap_other_to_exact_other(CAROL, DOT, 2000, BTC, 34),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_ok!(
+			DexModule::swap_other_to_exact_other(CAROL, DOT, 784, BTC, 30),
+			(784, 30)
+		);
+		assert_eq!(Tokens::balance(BTC, CAROL), 30);
+		assert_eq!(Tokens::balance(DOT, CAROL), 216);
+		assert_eq!(DexModule::liquidity_pool(BTC), (70, 14348));
+		assert_eq!(DexModule::liquidity_pool(DOT), (1784, 5652));
+	});
+}
+
+#[test]
+fn swap_by_path_work() {
+	let with_pools = || {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+	};
+	let state = || {
+		(
+			DexModule::liquidity_pool(BTC),
+			DexModule::liquidity_pool(DOT),
+			Tokens::balance(BTC, BOB),
+			Tokens::balance(AUSD, BOB),
+			Tokens::balance(DOT, BOB),
+		)
+	};
+
+	let by_path = ExtBuilder::default().build().execute_with(|| {
+		with_pools();
+		assert_ok!(DexModule::swap_by_path(
+			Origin::signed(BOB),
+			vec![BTC, AUSD, DOT],
+			1000,
+			817,
+			None
+		));
+		state()
+	});
+	let composed = ExtBuilder::default().build().execute_with(|| {
+		with_pools();
+		assert_ok!(DexModule::swap_other_to_base(BOB, BTC, 1000, 0), (1000, 900));
+		assert_ok!(DexModule::swap_base_to_other(BOB, DOT, 900, 0), (900, 817));
+		state()
+	});
+	assert_eq!(by_path, composed);
+	assert_eq!(by_path.0, (11000, 9100));
+	assert_eq!(by_path.1, (9183, 10900));
+}
+
+#[test]
+fn swap_currency_batch_work() {
+	let with_pools = || {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+	};
+	let state = || {
+		(
+			DexModule::liquidity_pool(BTC),
+			DexModule::liquidity_pool(DOT),
+			Tokens::balance(BTC, BOB),
+			Tokens::balance(AUSD, BOB),
+			Tokens::balance(DOT, BOB),
+		)
+	};
+
+	// both allocations swap through the pool of BTC, the second one at the price the first one leaves
+	for protocol_fee_rate in vec![FixedU128::from_natural(0), FixedU128::from_rational(1, 2)] {
+		let batch = ExtBuilder::default()
+			.protocol_fee_rate(protocol_fee_rate)
+			.build()
+			.execute_with(|| {
+				with_pools();
+				let target_amounts =
+					DexModule::do_swap_currency_batch(BOB, BTC, &[(AUSD, 1000, 0), (DOT, 1000, 0)]).unwrap();
+				(target_amounts, state())
+			});
+		let sequential = ExtBuilder::default()
+			.protocol_fee_rate(protocol_fee_rate)
+			.build()
+			.execute_with(|| {
+				with_pools();
+				let (_, ausd_amount) = DexModule::swap_other_to_base(BOB, BTC, 1000, 0).unwrap();
+				let (_, dot_amount) = DexModule::do_swap_by_path(BOB, &[BTC, AUSD, DOT], 1000, 0).unwrap();
+				(vec![ausd_amount, dot_amount], state())
+			});
+		assert_eq!(batch, sequential);
+	}
+
+	ExtBuilder::default().build().execute_with(|| {
+		with_pools();
+		assert_ok!(DexModule::swap_currency_batch(
+			Origin::signed(BOB),
+			AUSD,
+			vec![(BTC, 1000, 900), (DOT, 2000, 1000)],
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC).1, 11000);
+		assert_eq!(DexModule::liquidity_pool(DOT).1, 12000);
+	});
+}
+
+#[test]
+fn swap_currency_batch_is_all_or_nothing() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+
+		// the first allocation would be swapped on its own
+		assert_noop!(
+			DexModule::swap_currency_batch(Origin::signed(BOB), BTC, vec![(AUSD, 1000, 0), (DOT, 1000, 10000)]),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_noop!(
+			DexModule::swap_currency_batch(Origin::signed(BOB), BTC, vec![(AUSD, 1000, 0), (BTC, 1000, 0)]),
+			Error::<Runtime>::CanNotSwapItself,
+		);
+		let balance = Tokens::balance(BTC, BOB);
+		assert_noop!(
+			DexModule::swap_currency_batch(Origin::signed(BOB), BTC, vec![(AUSD, balance, 0), (DOT, 1, 0)]),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_noop!(
+			DexModule::swap_currency_batch(Origin::signed(BOB), BTC, vec![]),
+			Error::<Runtime>::InvalidBatchSize,
+		);
+		assert_noop!(
+			DexModule::swap_currency_batch(
+				Origin::signed(BOB),
+				BTC,
+				vec![(AUSD, 100, 0); MaxBatchSize::get() as usize + 1]
+			),
+			Error::<Runtime>::InvalidBatchSize,
+		);
+	});
+}
+
+#[test]
+fn swap_by_path_rejects_invalid_paths() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC], 1000, 0, None),
+			Error::<Runtime>::InvalidSwapPath,
+		);
+		// longer than the max swap path length
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, AUSD, DOT, AUSD], 1000, 0, None),
+			Error::<Runtime>::InvalidSwapPath,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, AUSD, BTC], 1000, 0, None),
+			Error::<Runtime>::InvalidSwapPath,
+		);
+		// no pool between two other currencies
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, DOT], 1000, 0, None),
+			Error::<Runtime>::InvalidSwapPath,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(BOB), vec![BTC, AUSD, DOT], 1000, 818, None),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_noop!(
+			DexModule::swap_by_path(Origin::signed(CAROL), vec![BTC, AUSD, DOT], 1000, 0, None),
+			Error::<Runtime>::TokenNotEnough,
+		);
+		assert_ok!(DexModule::swap_by_path(
+			Origin::signed(BOB),
+			vec![AUSD, BTC],
+			1000,
+			0,
+			None
+		));
+	});
+}
+
+#[test]
+fn swap_with_exact_supply_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 100));
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(CAROL), (BTC, 10000), (BTC, 1000), None),
+			Error::<Runtime>::CanNotSwapItself
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_supply(Origin::signed(CAROL), (BTC, 101), (DOT, 1000), None),
+			Error::<Runtime>::TokenNotEnough
+		);
+		assert_ok!(DexModule::swap_with_exact_supply(
+			Origin::signed(CAROL),
+			(BTC, 100),
+			(AUSD, 4950),
+			None
+		));
+		let swap_event = TestEvent::dex(RawEvent::Swap(CAROL, BTC, 100, AUSD, 4950, 50));
+		assert!(System::events().iter().any(|record| record.event == swap_event));
+		assert_ok!(DexModule::swap_with_exact_supply(
+			Origin::signed(CAROL),
+			(AUSD, 4950),
+			(BTC, 90),
+			None
+		));
+		assert_ok!(DexModule::swap_with_exact_supply(
+			Origin::signed(CAROL),
+			(BTC, 90),
+			(DOT, 300),
+			None
+		));
+	});
+}
+
+#[test]
+fn swap_with_exact_target_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 100));
+		assert_noop!(
+			DexModule::swap_with_exact_target(Origin::signed(CAROL), (BTC, 10000), (BTC, 1000), None),
+			Error::<Runtime>::CanNotSwapItself
+		);
+		assert_noop!(
+			DexModule::swap_with_exact_target(Origin::signed(CAROL), (BTC, 1000), (DOT, 1000), None),
+			Error::<Runtime>::InsufficientLiquidity
+		);
+
+		assert_ok!(DexModule::swap_with_exact_target(
+			Origin::signed(CAROL),
+			(BTC, 100),
+			(AUSD, 4950),
+			None
+		));
+		assert_eq!(Tokens::balance(BTC, CAROL), 0);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 4950);
+		assert_eq!(DexModule::liquidity_pool(BTC), (200, 5050));
+
+		assert_ok!(DexModule::swap_with_exact_target(
+			Origin::signed(CAROL),
+			(AUSD, 4950),
+			(BTC, 90),
+			None
+		));
+		assert_eq!(Tokens::balance(BTC, CAROL), 90);
+		assert_eq!(Tokens::balance(AUSD, CAROL), 741);
+		assert_eq!(DexModule::liquidity_pool(BTC), (110, 9259));
+
+		assert_ok!(DexModule::swap_with_exact_target(
+			Origin::signed(CAROL),
+			(BTC, 90),
+			(DOT, 200),
+			None
+		));
+		assert_eq!(Tokens::balance(BTC, CAROL), 48);
+		assert_eq!(Tokens::balance(DOT, CAROL), 200);
+		assert_eq!(DexModule::liquidity_pool(BTC), (152, 6727));
+		assert_eq!(DexModule::liquidity_pool(DOT), (800, 12532));
+
+		// the actual amounts of both sides
+		let swap_event = TestEvent::dex(RawEvent::Swap(CAROL, BTC, 42, DOT, 200, 2));
+		assert!(System::events().iter().any(|record| record.event == swap_event));
+	});
+}
+
+#[test]
+fn exchange_currency_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(Tokens::transfer(Origin::signed(BOB), CAROL, BTC, 100));
+		assert_noop!(
+			DexModule::exchange_currency(CAROL, (BTC, 10000), (BTC, 1000)),
+			Error::<Runtime>::CanNotSwapItself
+		);
+		assert_noop!(
+			DexModule::exchange_currency(CAROL, (BTC, 101), (DOT, 1000)),
+			Error::<Runtime>::TokenNotEnough
+		);
+		assert_ok!(
+			DexModule::exchange_currency(CAROL, (BTC, 100), (AUSD, 4950)),
+			(100, 4950)
+		);
+		assert_ok!(DexModule::exchange_currency(CAROL, (AUSD, 4950), (BTC, 90)), (4950, 99));
+		assert_ok!(DexModule::exchange_currency(CAROL, (BTC, 90), (DOT, 300)), (90, 315));
+	});
+}
+
+#[test]
+fn exchange_currency_returns_actual_amounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			100,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+		let btc_balance = Tokens::balance(BTC, BOB);
+		let dot_balance = Tokens::balance(DOT, BOB);
+
+		// the target received is above the min target amount
+		let (supply_amount, target_amount) = DexModule::exchange_currency(BOB, (DOT, 1000), (BTC, 30)).unwrap();
+		assert_eq!((supply_amount, target_amount), (1000, 33));
+		assert_eq!(dot_balance - Tokens::balance(DOT, BOB), supply_amount);
+		assert_eq!(Tokens::balance(BTC, BOB) - btc_balance, target_amount);
+		let swap_event = TestEvent::dex(RawEvent::Swap(BOB, DOT, supply_amount, BTC, target_amount, 0));
+		assert!(System::events().iter().any(|record| record.event == swap_event));
+	});
+}
+
+/// The liquidation path of a caller of the dex manager: cover exactly `debit` by selling at most `collateral`,
+/// `None` to fall back to an auction.
+fn liquidate_through_dex(who: AccountId, collateral: (CurrencyId, Balance), debit: Balance) -> Option<Balance> {
+	<DexModule as DexManager<_, _, _>>::exchange_currency_with_exact_target(who, collateral, (AUSD, debit)).ok()
+}
+
+#[test]
+fn exchange_currency_with_exact_target_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		let btc_balance = Tokens::balance(BTC, BOB);
+		let ausd_balance = Tokens::balance(AUSD, BOB);
+
+		// 1124 BTC are needed for 1000 aUSD
+		assert_noop!(
+			DexModule::exchange_currency_with_exact_target(BOB, (BTC, 1123), (AUSD, 1000)),
+			Error::<Runtime>::InacceptablePrice,
+		);
+		assert_eq!(liquidate_through_dex(BOB, (BTC, 1123), 1000), None);
+		// the pool can't provide the target
+		assert_noop!(
+			DexModule::exchange_currency_with_exact_target(BOB, (BTC, 1_000_000), (AUSD, 10000)),
+			Error::<Runtime>::InsufficientLiquidity,
+		);
+
+		assert_eq!(liquidate_through_dex(BOB, (BTC, 1200), 1000), Some(1124));
+		assert_eq!(btc_balance - Tokens::balance(BTC, BOB), 1124);
+		assert_eq!(Tokens::balance(AUSD, BOB) - ausd_balance, 1000);
+		assert_eq!(DexModule::liquidity_pool(BTC), (11124, 9000));
+	});
+}
+
+#[test]
+fn get_supply_amount_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		let supply_amount = DexModule::get_supply_amount(BTC, AUSD, 4950).unwrap();
+		assert_ok!(
+			DexModule::exchange_currency(BOB, (BTC, supply_amount), (AUSD, 4950)),
+			(supply_amount, 4950)
+		);
+		assert_eq!(
+			DexModule::get_supply_amount(BTC, BTC, 100),
+			Err(Error::<Runtime>::CanNotSwapItself.into())
+		);
+		assert_eq!(
+			DexModule::get_supply_amount(BTC, AUSD, 10000),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		// the pool of DOT is empty
+		assert_eq!(
+			DexModule::get_supply_amount(AUSD, DOT, 1),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_eq!(
+			DexModule::get_supply_amount(DOT, BTC, 1),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+	});
+}
+
+/// Compare the simulated supply amounts of the targets with executing the swaps one by one.
+fn assert_supply_amounts_for_targets(
+	supply_currency_id: CurrencyId,
+	target_currency_id: CurrencyId,
+	target_amounts: Vec<Balance>,
+) {
+	ExtBuilder::default()
+		.protocol_fee_rate(FixedU128::from_rational(1, 2))
+		.build()
+		.execute_with(|| {
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				BTC,
+				10000,
+				20000,
+				0,
+				None
+			));
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				DOT,
+				30000,
+				10000,
+				0,
+				None
+			));
+			let pools = (DexModule::liquidity_pool(BTC), DexModule::liquidity_pool(DOT));
+			let supply_amounts = DexModule::get_supply_amounts_for_targets(
+				supply_currency_id,
+				target_currency_id,
+				target_amounts.clone(),
+			);
+			// nothing is swapped by the simulation
+			assert_eq!((DexModule::liquidity_pool(BTC), DexModule::liquidity_pool(DOT)), pools);
+			assert_eq!(supply_amounts.len(), target_amounts.len());
+
+			for (supply_amount, target_amount) in supply_amounts.into_iter().zip(target_amounts) {
+				assert_eq!(
+					DexModule::exchange_currency_with_exact_target(
+						BOB,
+						(supply_currency_id, 1_000_000),
+						(target_currency_id, target_amount)
+					),
+					Ok(supply_amount)
+				);
+			}
+		});
+}
+
+#[test]
+fn get_supply_amounts_for_targets_match_the_swaps() {
+	assert_supply_amounts_for_targets(BTC, AUSD, vec![1000, 3000, 1, 2500]);
+	assert_supply_amounts_for_targets(AUSD, BTC, vec![1000, 3000, 1, 2500]);
+	assert_supply_amounts_for_targets(BTC, DOT, vec![1000, 3000, 1, 2500]);
+	// the base currency of the pool of dot runs out first
+	assert_supply_amounts_for_targets(DOT, BTC, vec![100, 300, 1, 250]);
+}
+
+#[test]
+fn get_supply_amounts_for_targets_stop_past_the_liquidity() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		let first_supply_amount = DexModule::get_supply_amount(BTC, AUSD, 5000).unwrap();
+		// the second target is more than the pool has left, nothing after it is quoted
+		assert_eq!(
+			DexModule::get_supply_amounts_for_targets(BTC, AUSD, vec![5000, 5000, 1]),
+			vec![first_supply_amount]
+		);
+		// a single quote is the quote of the current pool
+		assert_eq!(
+			DexModule::get_supply_amounts_for_targets(BTC, AUSD, vec![5000]),
+			vec![first_supply_amount]
+		);
+		assert_eq!(DexModule::get_supply_amounts_for_targets(BTC, BTC, vec![100]), vec![]);
+		assert_eq!(DexModule::get_supply_amounts_for_targets(DOT, AUSD, vec![100]), vec![]);
+	});
+}
+
+#[test]
+fn get_target_amount_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(
+			DexModule::get_target_amount(BTC, BTC, 100),
+			Err(Error::<Runtime>::CanNotSwapItself.into())
+		);
+		assert_eq!(
+			DexModule::get_target_amount(BTC, AUSD, 100),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_eq!(
+			DexModule::get_target_amount(BTC, DOT, 100),
+			Err(Error::<Runtime>::InsufficientLiquidity.into())
+		);
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			1000,
+			10000,
+			0,
+			None
+		));
+
+		// the same as the amount received by the swap, for all the routes
+		for &(supply_currency_id, target_currency_id) in &[(BTC, AUSD), (AUSD, BTC), (BTC, DOT), (DOT, BTC)] {
+			let target_amount = DexModule::get_target_amount(supply_currency_id, target_currency_id, 100).unwrap();
+			assert!(target_amount > 0);
+			let balance = Tokens::balance(target_currency_id, BOB);
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				(supply_currency_id, 100),
+				(target_currency_id, target_amount),
+				None
+			));
+			assert_eq!(Tokens::balance(target_currency_id, BOB), balance + target_amount);
+		}
+	});
+}
+
+/// The raw key of `key` in the map `storage` from before the storage was versioned, hashed with the prefix of the
+/// map.
+fn old_map_key(storage: &[u8], key: impl Encode) -> Vec<u8> {
+	let mut prefixed_key = b"Dex ".to_vec();
+	prefixed_key.extend_from_slice(storage);
+	key.encode_to(&mut prefixed_key);
+	blake2_256(&prefixed_key).to_vec()
+}
+
+/// The raw key of `key1` and `key2` in the double map `storage` from before the storage was versioned, the first key
+/// hashed with the prefix of the map.
+fn old_double_map_key(storage: &[u8], key1: impl Encode, key2: impl Encode) -> Vec<u8> {
+	let mut key = old_map_key(storage, key1);
+	key.extend_from_slice(&blake2_256(&key2.encode()));
+	key
+}
+
+#[test]
+fn on_runtime_upgrade_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		let module_account = DexModule::account_id();
+		// a pool written before the storage is versioned
+		unhashed::put(&old_map_key(b"LiquidityPool", BTC), &(100u128, 10000u128));
+		unhashed::put(&old_map_key(b"TotalShares", BTC), &10000u128);
+		unhashed::put(&old_double_map_key(b"Shares", BTC, module_account), &1000u128);
+		unhashed::put(&old_double_map_key(b"Shares", BTC, CAROL), &6000u128);
+		unhashed::put(&old_double_map_key(b"Shares", BTC, ALICE), &3000u128);
+		assert_ok!(Tokens::deposit(BTC, &module_account, 100));
+		assert_ok!(Tokens::deposit(AUSD, &module_account, 10000));
+		assert!(!<Version>::exists());
+		assert_eq!(DexModule::storage_version(), StorageVersion::V0);
+
+		// the module account and carol, the holder to migrate, are linked and their shares minted
+		assert_eq!(DexModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT * 46);
+		assert_eq!(DexModule::storage_version(), StorageVersion::V3);
+		assert_eq!(DexModule::storage_version().next(), None);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 10000));
+		assert_eq!(DexModule::total_shares(BTC), 10000);
+		assert_eq!(DexModule::shares(BTC, module_account), 1000);
+		assert_eq!(DexModule::shares(BTC, CAROL), 6000);
+		assert_eq!(DexModule::shares(BTC, ALICE), 3000);
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![CAROL, module_account], None)
+		);
+		assert_eq!(Tokens::balance(BTC_SHARE, &module_account), 1000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &CAROL), 6000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 0);
+
+		// nothing pending, only the version is read
+		assert_eq!(DexModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT);
+		assert_eq!(DexModule::storage_version(), StorageVersion::V3);
+		assert_eq!(DexModule::liquidity_pool(BTC), (100, 10000));
+		assert_eq!(DexModule::total_shares(BTC), 10000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), 7000);
+
+		// the holders which weren't migrated are registered once their shares are updated
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			3000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(DexModule::liquidity_pool(BTC), (70, 7000));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 0);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![CAROL, module_account], None)
+		);
+	});
+}
+
+#[test]
+fn migrations_are_left_to_the_runtime_upgrade() {
+	ExtBuilder::default().build().execute_with(|| {
+		DexModule::on_initialize(1);
+		assert_eq!(DexModule::storage_version(), StorageVersion::V0);
+		DexModule::on_runtime_upgrade();
+		assert_eq!(DexModule::storage_version(), StorageVersion::V3);
+	});
+}
+
+#[test]
+fn migration_mints_the_share_currency_of_the_shares() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			10000,
+			10000,
+			0,
+			None
+		));
+		assert_ok!(DexModule::transfer_shares(Origin::signed(ALICE), BTC, BOB, 1000));
+		// the shares from before they were minted in the share currency
+		assert_ok!(Tokens::withdraw(BTC_SHARE, &DexModule::account_id(), 1000));
+		assert_ok!(Tokens::withdraw(BTC_SHARE, &ALICE, 8000));
+		assert_ok!(Tokens::withdraw(BTC_SHARE, &BOB, 1000));
+		<Version>::put(StorageVersion::V1);
+
+		assert_eq!(DexModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT * 54);
+		assert_eq!(DexModule::storage_version(), StorageVersion::V3);
+		assert_eq!(Tokens::balance(BTC_SHARE, &DexModule::account_id()), 1000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 8000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &BOB), 1000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+	});
+}
+
+#[test]
+fn share_currency_is_minted_for_the_shares_from_before_the_holders_were_tracked() {
+	ExtBuilder::default().build().execute_with(|| {
+		let module_account = DexModule::account_id();
+		// a pool from before the share holders were tracked and the shares were minted in the share currency
+		<LiquidityPool<Runtime>>::insert(BTC, (10000, 10000));
+		<TotalShares<Runtime>>::insert(BTC, 10000);
+		<Shares<Runtime>>::insert(BTC, module_account, 1000);
+		<Shares<Runtime>>::insert(BTC, ALICE, 6000);
+		<Shares<Runtime>>::insert(BTC, BOB, 3000);
+		assert_ok!(Tokens::deposit(BTC, &module_account, 10000));
+		assert_ok!(Tokens::deposit(AUSD, &module_account, 10000));
+		<Version>::put(StorageVersion::V1);
+
+		// the migration only finds the module account of the holders
+		DexModule::on_runtime_upgrade();
+		assert_eq!(DexModule::storage_version(), StorageVersion::V3);
+		assert_eq!(Tokens::balance(BTC_SHARE, &module_account), 1000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), 1000);
+		assert_eq!(DexModule::share_holders(BTC, None, 10), (vec![module_account], None));
+
+		// minted before the first withdrawal
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			1000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(DexModule::shares(BTC, ALICE), 5000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 5000);
+
+		// and the first transfer, for the receiver too
+		assert_ok!(DexModule::transfer_shares(Origin::signed(BOB), BTC, ALICE, 1000));
+		assert_eq!(Tokens::balance(BTC_SHARE, &BOB), 2000);
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 6000);
+
+		// only once
+		assert_ok!(DexModule::withdraw_liquidity(
+			Origin::signed(ALICE),
+			BTC,
+			6000,
+			0,
+			0,
+			None
+		));
+		assert_eq!(Tokens::balance(BTC_SHARE, &ALICE), 0);
+		assert_eq!(Tokens::balance(BTC, &ALICE), Tokens::balance(BTC, &BOB) + 7000);
+		assert_eq!(Tokens::total_issuance(BTC_SHARE), DexModule::total_shares(BTC));
+		assert_eq!(
+			DexModule::share_holders(BTC, None, 10),
+			(vec![BOB, module_account], None)
+		);
+	});
+}
+
+#[test]
+fn dispatchables_are_weighted() {
+	// a swap through two pools weighs more than the default weight of a call
+	let info = Call::<Runtime>::swap_with_exact_supply((BTC, 1), (DOT, 0), None).get_dispatch_info();
+	assert_eq!(info.weight, <() as WeightInfo>::swap_with_exact_supply());
+	assert!(info.weight > 10_000);
+	assert_eq!(
+		Call::<Runtime>::swap_by_path(vec![BTC, AUSD], 1, 0, None)
+			.get_dispatch_info()
+			.weight,
+		<() as WeightInfo>::swap_by_path(MaxSwapPathLength::get())
+	);
+	assert_eq!(
+		<() as WeightInfo>::swap_by_path(3),
+		<() as WeightInfo>::swap_with_exact_supply()
+	);
+	assert_eq!(
+		Call::<Runtime>::add_liquidity(BTC, 1, 1, 0, None)
+			.get_dispatch_info()
+			.weight,
+		<() as WeightInfo>::add_liquidity()
+	);
+	assert_eq!(
+		Call::<Runtime>::withdraw_liquidity(BTC, 1, 0, 0, None)
+			.get_dispatch_info()
+			.weight,
+		<() as WeightInfo>::withdraw_liquidity()
+	);
+	let info = Call::<Runtime>::enable_trading_pair(BTC).get_dispatch_info();
+	assert_eq!(info.weight, <() as WeightInfo>::enable_trading_pair());
+	assert_eq!(info.class, DispatchClass::Operational);
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+#[test]
+fn benchmarks_dispatch_from_their_setup() {
+	use sp_runtime::traits::Dispatchable;
+
+	let accounts: Vec<AccountId> = (0..3).map(benchmarking::account::<Runtime>).collect();
+	for benchmark in benchmarking::benchmarks::<Runtime>() {
+		ExtBuilder::default().build_unaudited().execute_with(|| {
+			let (who, call) = benchmarking::setup::<Runtime>(benchmark, (BTC, DOT)).unwrap();
+			assert_ok!(call.dispatch(Origin::signed(who)));
+			assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &accounts), Ok(()));
+		});
+	}
+}
@@ -0,0 +1,97 @@
+//! Weights of the dispatchables of the dex.
+
+use frame_support::weights::Weight;
+use support::STORAGE_ACCESS_WEIGHT;
+
+/// The weights of the dispatchables of the module.
+pub trait WeightInfo {
+	fn swap_with_exact_supply() -> Weight;
+	fn swap_with_exact_target() -> Weight;
+	/// A swap through a path of `path_length` currencies.
+	fn swap_by_path(path_length: u32) -> Weight;
+	/// A batch swap of `batch_size` allocations.
+	fn swap_currency_batch(batch_size: u32) -> Weight;
+	fn add_liquidity() -> Weight;
+	fn withdraw_liquidity() -> Weight;
+	fn withdraw_liquidity_by_amounts() -> Weight;
+	fn transfer_shares() -> Weight;
+	fn set_exchange_fee() -> Weight;
+	fn set_max_price_impact() -> Weight;
+	fn set_max_other_currency_amount() -> Weight;
+	fn enable_trading_pair() -> Weight;
+	fn disable_trading_pair() -> Weight;
+	fn pause() -> Weight;
+	fn unpause() -> Weight;
+	/// Closing a pool with up to `refunds` share holders refunded.
+	fn force_close_pool(refunds: u32) -> Weight;
+	/// Registering up to `holders` share holders.
+	fn register_share_holders(holders: u32) -> Weight;
+	fn report_price_deviation() -> Weight;
+}
+
+/// The weight of a dispatchable apart from its storage accesses.
+const BASE_WEIGHT: Weight = 10_000;
+
+/// The storage accesses of a swap with one pool: the trading pair, the fee, the pool and its price, the transfers
+/// of both sides, the protocol fee and the trade counters.
+const SWAP_HOP_STORAGE_ACCESSES: Weight = 20;
+
+/// Counted from the storage accesses of the worst case of each dispatchable: the swaps go through two pools and the
+/// shares are of an existing pool. The swaps, `add_liquidity` and `withdraw_liquidity` are dispatched from the same
+/// cases by `benchmarking`, update them from the output of `runtime/tests/dex_benchmarks.rs`.
+impl WeightInfo for () {
+	fn swap_with_exact_supply() -> Weight {
+		BASE_WEIGHT + (2 * SWAP_HOP_STORAGE_ACCESSES + 1) * STORAGE_ACCESS_WEIGHT
+	}
+	fn swap_with_exact_target() -> Weight {
+		BASE_WEIGHT + (2 * SWAP_HOP_STORAGE_ACCESSES + 1) * STORAGE_ACCESS_WEIGHT
+	}
+	fn swap_by_path(path_length: u32) -> Weight {
+		BASE_WEIGHT + (path_length.saturating_sub(1) * SWAP_HOP_STORAGE_ACCESSES + 1) * STORAGE_ACCESS_WEIGHT
+	}
+	fn swap_currency_batch(batch_size: u32) -> Weight {
+		BASE_WEIGHT + (batch_size * 2 * SWAP_HOP_STORAGE_ACCESSES + 1) * STORAGE_ACCESS_WEIGHT
+	}
+	fn add_liquidity() -> Weight {
+		BASE_WEIGHT + 25 * STORAGE_ACCESS_WEIGHT
+	}
+	fn withdraw_liquidity() -> Weight {
+		BASE_WEIGHT + 20 * STORAGE_ACCESS_WEIGHT
+	}
+	fn withdraw_liquidity_by_amounts() -> Weight {
+		BASE_WEIGHT + 23 * STORAGE_ACCESS_WEIGHT
+	}
+	fn transfer_shares() -> Weight {
+		BASE_WEIGHT + 6 * STORAGE_ACCESS_WEIGHT
+	}
+	fn set_exchange_fee() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn set_max_price_impact() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn set_max_other_currency_amount() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn enable_trading_pair() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn disable_trading_pair() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn pause() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn unpause() -> Weight {
+		BASE_WEIGHT + STORAGE_ACCESS_WEIGHT
+	}
+	fn force_close_pool(refunds: u32) -> Weight {
+		BASE_WEIGHT + (4 + refunds * 14) * STORAGE_ACCESS_WEIGHT
+	}
+	fn register_share_holders(holders: u32) -> Weight {
+		BASE_WEIGHT + holders * 6 * STORAGE_ACCESS_WEIGHT
+	}
+	fn report_price_deviation() -> Weight {
+		BASE_WEIGHT + 6 * STORAGE_ACCESS_WEIGHT
+	}
+}
@@ -0,0 +1,234 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use orml_traits::{MultiCurrency, PriceProvider};
+use rstd::prelude::*;
+use sp_runtime::{
+	traits::{CheckedSub, Zero},
+	RuntimeDebug,
+};
+use support::{
+	AuctionManagerExtended, CDPTreasury, EmergencyShutdown, FixedU128Ext, OnEmergencyShutdown, Price,
+	PriceProviderExtended, Ratio,
+};
+use system::{ensure_root, ensure_signed};
+
+mod mock;
+mod tests;
+
+type CurrencyIdOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type BalanceOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Phases of the wind-down, moved forward by `emergency_shutdown` and then `open_collateral_refund`.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ShutdownPhase {
+	Normal,
+	/// The prices are locked, the auctions are drained and the CDPs are settled.
+	Shutdown,
+	/// The collaterals are refunded for stable coin.
+	Refund,
+}
+
+pub trait Trait: system::Trait + cdp_engine::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type PriceSource: PriceProvider<CurrencyIdOf<Self>, Price>;
+	type AuctionManagerHandler: AuctionManagerExtended<
+		Self::AccountId,
+		CurrencyId = CurrencyIdOf<Self>,
+		Balance = BalanceOf<Self>,
+	>;
+	type OnShutdown: OnEmergencyShutdown;
+	type MaxAuctionsCancelledPerBlock: Get<u32>;
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		<T as system::Trait>::BlockNumber,
+		CurrencyId = CurrencyIdOf<T>,
+		Balance = BalanceOf<T>,
+	{
+		/// Emergency shutdown (block_number)
+		Shutdown(BlockNumber),
+		/// Open collateral refund (block_number)
+		OpenRefund(BlockNumber),
+		/// Stable coin burned for the refunded collaterals (account, amount, refund_list)
+		Refund(AccountId, Balance, Vec<(CurrencyId, Balance)>),
+	}
+);
+
+decl_error! {
+	/// Error for emergency shutdown module.
+	pub enum Error for Module<T: Trait> {
+		AlreadyShutdown,
+		MustAfterShutdown,
+		AlreadyOpenRefund,
+		ExistUnhandledAuction,
+		ExistUnsettledCdp,
+		CanNotRefund,
+		InvalidRefundAmount,
+		StableCoinNotEnough,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as EmergencyShutdown {
+		pub IsShutdown get(fn is_shutdown): bool;
+		pub CanRefund get(fn can_refund): bool;
+		pub LockedPrices get(fn locked_price): map CurrencyIdOf<T> => Option<Price>;
+		pub RefundRatios get(fn refund_ratio): map CurrencyIdOf<T> => Ratio;
+		pub StableCoinToRefund get(fn stable_coin_to_refund): BalanceOf<T>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		pub fn emergency_shutdown(origin) {
+			ensure_root(origin)?;
+			ensure!(!Self::is_shutdown(), Error::<T>::AlreadyShutdown);
+
+			// lock the price of every collateral in stable coin
+			let stable_currency_id = <T as cdp_engine::Trait>::GetStableCurrencyId::get();
+			for currency_id in <T as cdp_engine::Trait>::CollateralCurrencyIds::get() {
+				if let Some(price) = <T as Trait>::PriceSource::get_relative_price(stable_currency_id, currency_id) {
+					<LockedPrices<T>>::insert(currency_id, price);
+				}
+			}
+
+			<IsShutdown>::put(true);
+			T::OnShutdown::on_emergency_shutdown();
+			Self::deposit_event(RawEvent::Shutdown(<system::Module<T>>::block_number()));
+		}
+
+		pub fn open_collateral_refund(origin) {
+			ensure_root(origin)?;
+			ensure!(Self::is_shutdown(), Error::<T>::MustAfterShutdown);
+			ensure!(!Self::can_refund(), Error::<T>::AlreadyOpenRefund);
+			ensure!(
+				<T as Trait>::AuctionManagerHandler::active_auction_count().is_zero(),
+				Error::<T>::ExistUnhandledAuction,
+			);
+			let collateral_currency_ids = <T as cdp_engine::Trait>::CollateralCurrencyIds::get();
+			for currency_id in collateral_currency_ids.iter() {
+				ensure!(
+					<vaults::Module<T>>::total_debits(currency_id).is_zero(),
+					Error::<T>::ExistUnsettledCdp,
+				);
+			}
+
+			// the stable coin held by the treasury isn't refundable
+			let stable_coin_to_refund = <T as vaults::Trait>::Currency::total_issuance(
+				<T as cdp_engine::Trait>::GetStableCurrencyId::get(),
+			)
+			.checked_sub(&<T as cdp_engine::Trait>::Treasury::get_surplus_pool())
+			.unwrap_or_else(Zero::zero);
+			for currency_id in collateral_currency_ids {
+				let refund_ratio = Ratio::checked_from_rational(
+					<T as cdp_engine::Trait>::Treasury::get_total_collaterals(currency_id),
+					stable_coin_to_refund,
+				)
+				.unwrap_or_else(|| Ratio::from_parts(0));
+				<RefundRatios<T>>::insert(currency_id, refund_ratio);
+			}
+			<StableCoinToRefund<T>>::put(stable_coin_to_refund);
+
+			<CanRefund>::put(true);
+			Self::deposit_event(RawEvent::OpenRefund(<system::Module<T>>::block_number()));
+		}
+
+		pub fn refund_collaterals(origin, amount: BalanceOf<T>) {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::can_refund(), Error::<T>::CanNotRefund);
+			let stable_coin_to_refund = Self::stable_coin_to_refund();
+			ensure!(
+				!amount.is_zero() && amount <= stable_coin_to_refund,
+				Error::<T>::InvalidRefundAmount,
+			);
+			let stable_currency_id = <T as cdp_engine::Trait>::GetStableCurrencyId::get();
+			ensure!(
+				<T as vaults::Trait>::Currency::balance(stable_currency_id, &who) >= amount,
+				Error::<T>::StableCoinNotEnough,
+			);
+
+			// the last redeemer takes all the remaining collaterals, nothing is left by rounding
+			let is_last_refund = amount == stable_coin_to_refund;
+			let refund_list: Vec<(CurrencyIdOf<T>, BalanceOf<T>)> = <T as cdp_engine::Trait>::CollateralCurrencyIds::get()
+				.into_iter()
+				.map(|currency_id| {
+					let remaining = <T as cdp_engine::Trait>::Treasury::get_total_collaterals(currency_id);
+					let refund_amount = if is_last_refund {
+						remaining
+					} else {
+						Self::refund_ratio(currency_id)
+							.checked_mul_int(&amount)
+							.map_or(remaining, |refund_amount| rstd::cmp::min(refund_amount, remaining))
+					};
+					(currency_id, refund_amount)
+				})
+				.filter(|(_, refund_amount)| !refund_amount.is_zero())
+				.collect();
+
+			<T as vaults::Trait>::Currency::withdraw(stable_currency_id, &who, amount)
+				.map_err(|_| Error::<T>::StableCoinNotEnough)?;
+			for (currency_id, refund_amount) in refund_list.iter() {
+				<T as cdp_engine::Trait>::Treasury::withdraw_collateral(&who, *currency_id, *refund_amount)?;
+			}
+			<StableCoinToRefund<T>>::put(stable_coin_to_refund - amount);
+
+			Self::deposit_event(RawEvent::Refund(who, amount, refund_list));
+		}
+
+		fn on_finalize(_now: T::BlockNumber) {
+			// drain the auctions across blocks after shutdown
+			if Self::is_shutdown() && !Self::can_refund() {
+				<T as Trait>::AuctionManagerHandler::emergency_cancel_all(T::MaxAuctionsCancelledPerBlock::get());
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	pub fn phase() -> ShutdownPhase {
+		if Self::can_refund() {
+			ShutdownPhase::Refund
+		} else if Self::is_shutdown() {
+			ShutdownPhase::Shutdown
+		} else {
+			ShutdownPhase::Normal
+		}
+	}
+
+	/// The price of `currency_id` in stable coin, the locked one if any.
+	fn stable_coin_price(currency_id: CurrencyIdOf<T>) -> Option<Price> {
+		Self::locked_price(currency_id).or_else(|| {
+			<T as Trait>::PriceSource::get_relative_price(
+				<T as cdp_engine::Trait>::GetStableCurrencyId::get(),
+				currency_id,
+			)
+		})
+	}
+}
+
+/// Proxy of `PriceSource` which returns the locked prices after shutdown.
+impl<T: Trait> PriceProvider<CurrencyIdOf<T>, Price> for Module<T> {
+	fn get_price(base: CurrencyIdOf<T>, quote: CurrencyIdOf<T>) -> Option<Price> {
+		if Self::locked_price(base).is_none() && Self::locked_price(quote).is_none() {
+			return <T as Trait>::PriceSource::get_price(base, quote);
+		}
+
+		let base_price = Self::stable_coin_price(base)?;
+		let quote_price = Self::stable_coin_price(quote)?;
+		Price::checked_from_rational(quote_price.deconstruct(), base_price.deconstruct())
+	}
+}
+
+impl<T: Trait> EmergencyShutdown for Module<T> {
+	fn is_shutdown() -> bool {
+		Self::is_shutdown()
+	}
+}
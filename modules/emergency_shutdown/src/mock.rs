@@ -0,0 +1,330 @@
+//! Mocks for the emergency shutdown module.
+
+#![cfg(test)]
+
+use frame_support::{impl_outer_origin, parameter_types};
+use primitives::H256;
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	DispatchResult, ModuleId, Perbill,
+};
+use std::cell::RefCell;
+use support::{AuctionManager, ExchangeRate, Rate};
+
+use super::*;
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+	pub const ExistentialDeposit: u64 = 0;
+	pub const TransferFee: u64 = 0;
+	pub const CreationFee: u64 = 2;
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
+	pub const GlobalStabilityFee: Rate = Rate::from_percent(0);
+	pub const DefaultLiquidationRatio: Ratio = Ratio::from_percent(150);
+	pub const DefaulDebitExchangeRate: ExchangeRate = ExchangeRate::from_natural(1);
+	pub const MinimumDebitValue: Balance = 2;
+	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
+	pub const GetNativeCurrencyId: CurrencyId = ACA;
+	pub const MaxAuctionsCancelledPerBlock: u32 = 2;
+}
+
+pub type AccountId = u64;
+pub type BlockNumber = u64;
+pub type Balance = u64;
+pub type DebitBalance = u64;
+pub type Amount = i64;
+pub type Share = u64;
+pub type AuctionId = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+
+impl system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type ModuleToIndex = ();
+}
+
+impl orml_tokens::Trait for Runtime {
+	type Event = ();
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+impl pallet_balances::Trait for Runtime {
+	type Balance = Balance;
+	type OnFreeBalanceZero = ();
+	type OnNewAccount = ();
+	type TransferPayment = ();
+	type DustRemoval = ();
+	type Event = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type TransferFee = TransferFee;
+	type CreationFee = CreationFee;
+}
+pub type PalletBalances = pallet_balances::Module<Runtime>;
+
+pub type AdaptedBasicCurrency =
+	orml_currencies::BasicCurrencyAdapter<Runtime, PalletBalances, Balance, orml_tokens::Error>;
+
+impl orml_currencies::Trait for Runtime {
+	type Event = ();
+	type MultiCurrency = Tokens;
+	type NativeCurrency = AdaptedBasicCurrency;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+}
+pub type Currencies = orml_currencies::Module<Runtime>;
+
+impl debits::Trait for Runtime {
+	type CurrencyId = CurrencyId;
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type DebitBalance = DebitBalance;
+	type Convert = cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type DebitAmount = Amount;
+}
+pub type DebitsCurrency = debits::Module<Runtime>;
+
+impl vaults::Trait for Runtime {
+	type Event = ();
+	type Convert = cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type Currency = Currencies;
+	type DebitCurrency = DebitsCurrency;
+	type RiskManager = CdpEngineModule;
+	type OnUpdateLoan = ();
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = ();
+}
+pub type VaultsModule = vaults::Module<Runtime>;
+
+thread_local! {
+	static BTC_PRICE: RefCell<Option<Price>> = RefCell::new(Some(Price::from_natural(1)));
+	static ACTIVE_AUCTIONS: RefCell<u32> = RefCell::new(0);
+}
+
+pub struct MockPriceSource;
+impl MockPriceSource {
+	pub fn set_btc_price(price: Option<Price>) {
+		BTC_PRICE.with(|v| *v.borrow_mut() = price);
+	}
+}
+impl PriceProvider<CurrencyId, Price> for MockPriceSource {
+	fn get_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
+		match (base, quote) {
+			(AUSD, BTC) => BTC_PRICE.with(|v| *v.borrow()),
+			(AUSD, DOT) => Some(Price::from_natural(2)),
+			_ => None,
+		}
+	}
+}
+
+/// Only keeps the number of active auctions, which are drained by the shutdown module.
+pub struct MockAuctionManager;
+impl MockAuctionManager {
+	pub fn set_active_auctions(count: u32) {
+		ACTIVE_AUCTIONS.with(|v| *v.borrow_mut() = count);
+	}
+}
+
+impl AuctionManager<AccountId> for MockAuctionManager {
+	type CurrencyId = CurrencyId;
+	type Balance = Balance;
+	type AuctionId = AuctionId;
+	#[allow(unused_variables)]
+	fn new_collateral_auction(
+		who: AccountId,
+		currency_id: Self::CurrencyId,
+		amount: Self::Balance,
+		target: Self::Balance,
+	) -> Vec<Self::AuctionId> {
+		vec![]
+	}
+
+	#[allow(unused_variables)]
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn new_debit_auction(initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn cancel_auction(id: Self::AuctionId) -> DispatchResult {
+		Ok(())
+	}
+
+	#[allow(unused_variables)]
+	fn get_total_collateral_in_auction(currency_id: Self::CurrencyId) -> Self::Balance {
+		0
+	}
+
+	fn get_total_surplus_in_auction() -> Self::Balance {
+		0
+	}
+
+	fn get_total_debit_in_auction() -> Self::Balance {
+		0
+	}
+}
+
+impl AuctionManagerExtended<AccountId> for MockAuctionManager {
+	fn emergency_cancel_all(max_per_block: u32) -> u32 {
+		ACTIVE_AUCTIONS.with(|v| {
+			let cancelled = rstd::cmp::min(*v.borrow(), max_per_block);
+			*v.borrow_mut() -= cancelled;
+			cancelled
+		})
+	}
+
+	fn active_auction_count() -> u32 {
+		ACTIVE_AUCTIONS.with(|v| *v.borrow())
+	}
+}
+
+impl dex::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+impl cdp_treasury::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = MockAuctionManager;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = DexModule;
+	type PriceSource = MockPriceSource;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = cdp_treasury::Module<Runtime>;
+
+impl cdp_engine::Trait for Runtime {
+	type Event = ();
+	type AuctionManagerHandler = MockAuctionManager;
+	type Currency = Currencies;
+	type PriceSource = EmergencyShutdownModule;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type GlobalStabilityFee = GlobalStabilityFee;
+	type StabilityFeeModel = ();
+	type DefaultLiquidationRatio = DefaultLiquidationRatio;
+	type DefaulDebitExchangeRate = DefaulDebitExchangeRate;
+	type MinimumDebitValue = MinimumDebitValue;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type Dex = DexModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type Call = cdp_engine::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), cdp_engine::Call<Runtime>, TestXt<cdp_engine::Call<Runtime>, ()>>;
+	type MaxPositionsCheckedPerBlock = ();
+}
+pub type CdpEngineModule = cdp_engine::Module<Runtime>;
+
+impl Trait for Runtime {
+	type Event = ();
+	type PriceSource = MockPriceSource;
+	type AuctionManagerHandler = MockAuctionManager;
+	type OnShutdown = (CdpEngineModule, CdpTreasuryModule);
+	type MaxAuctionsCancelledPerBlock = MaxAuctionsCancelledPerBlock;
+}
+pub type EmergencyShutdownModule = Module<Runtime>;
+
+pub struct ExtBuilder {
+	currency_ids: Vec<CurrencyId>,
+	endowed_accounts: Vec<AccountId>,
+	initial_balance: Balance,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			currency_ids: vec![ACA, BTC, DOT],
+			endowed_accounts: vec![ALICE, BOB],
+			initial_balance: 1000,
+		}
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> runtime_io::TestExternalities {
+		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+		orml_tokens::GenesisConfig::<Runtime> {
+			tokens: self.currency_ids,
+			initial_balance: self.initial_balance,
+			endowed_accounts: self.endowed_accounts,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		t.into()
+	}
+}
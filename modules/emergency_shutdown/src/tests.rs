@@ -0,0 +1,172 @@
+//! Unit tests for the emergency shutdown module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{
+	CdpEngineModule, CdpTreasuryModule, Currencies, EmergencyShutdownModule, ExtBuilder, MockAuctionManager,
+	MockPriceSource, Origin, Runtime, VaultsModule, ALICE, AUSD, BOB, BTC, DOT,
+};
+use sp_runtime::traits::OnFinalize;
+
+#[test]
+fn emergency_shutdown_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(EmergencyShutdownModule::emergency_shutdown(Origin::signed(ALICE)).is_err());
+		assert_eq!(<EmergencyShutdownModule as EmergencyShutdown>::is_shutdown(), false);
+		assert_ok!(EmergencyShutdownModule::emergency_shutdown(Origin::ROOT));
+		assert_eq!(EmergencyShutdownModule::is_shutdown(), true);
+		assert_eq!(<EmergencyShutdownModule as EmergencyShutdown>::is_shutdown(), true);
+		assert_eq!(CdpEngineModule::is_shutdown(), true);
+		assert_eq!(CdpTreasuryModule::is_shutdown(), true);
+		assert_eq!(EmergencyShutdownModule::locked_price(BTC), Some(Price::from_natural(1)));
+		assert_eq!(EmergencyShutdownModule::locked_price(DOT), Some(Price::from_natural(2)));
+		assert_noop!(
+			EmergencyShutdownModule::emergency_shutdown(Origin::ROOT),
+			Error::<Runtime>::AlreadyShutdown,
+		);
+	});
+}
+
+#[test]
+fn locked_price_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		MockPriceSource::set_btc_price(Some(Price::from_natural(3)));
+		assert_eq!(
+			EmergencyShutdownModule::get_price(AUSD, BTC),
+			Some(Price::from_natural(3))
+		);
+		assert_ok!(EmergencyShutdownModule::emergency_shutdown(Origin::ROOT));
+
+		// the feed price is ignored after shutdown
+		MockPriceSource::set_btc_price(None);
+		assert_eq!(
+			EmergencyShutdownModule::get_price(AUSD, BTC),
+			Some(Price::from_natural(3))
+		);
+		assert_eq!(
+			EmergencyShutdownModule::get_price(DOT, BTC),
+			Some(Price::from_rational(3, 2))
+		);
+	});
+}
+
+#[test]
+fn drain_auctions_after_shutdown() {
+	ExtBuilder::default().build().execute_with(|| {
+		MockAuctionManager::set_active_auctions(3);
+		EmergencyShutdownModule::on_finalize(1);
+		assert_eq!(MockAuctionManager::active_auction_count(), 3);
+
+		assert_ok!(EmergencyShutdownModule::emergency_shutdown(Origin::ROOT));
+		EmergencyShutdownModule::on_finalize(2);
+		assert_eq!(MockAuctionManager::active_auction_count(), 1);
+		assert_noop!(
+			EmergencyShutdownModule::open_collateral_refund(Origin::ROOT),
+			Error::<Runtime>::ExistUnhandledAuction,
+		);
+		EmergencyShutdownModule::on_finalize(3);
+		assert_eq!(MockAuctionManager::active_auction_count(), 0);
+		assert_ok!(EmergencyShutdownModule::open_collateral_refund(Origin::ROOT));
+	});
+}
+
+#[test]
+fn shutdown_to_refund_lifecycle_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		for currency_id in vec![BTC, DOT] {
+			assert_ok!(CdpEngineModule::set_collateral_params(
+				Origin::ROOT,
+				currency_id,
+				None,
+				None,
+				None,
+				None,
+				Some(10000),
+				None,
+			));
+		}
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_ok!(CdpEngineModule::update_position(BOB, DOT, 100, 60));
+		MockAuctionManager::set_active_auctions(1);
+		assert_eq!(EmergencyShutdownModule::phase(), ShutdownPhase::Normal);
+		assert_noop!(
+			EmergencyShutdownModule::open_collateral_refund(Origin::ROOT),
+			Error::<Runtime>::MustAfterShutdown,
+		);
+
+		// shutdown and drain the auctions
+		assert_ok!(EmergencyShutdownModule::emergency_shutdown(Origin::ROOT));
+		assert_eq!(EmergencyShutdownModule::phase(), ShutdownPhase::Shutdown);
+		assert_noop!(
+			CdpEngineModule::update_position(ALICE, BTC, 0, 10),
+			cdp_engine::Error::<Runtime>::AlreadyShutdown,
+		);
+		EmergencyShutdownModule::on_finalize(1);
+		assert_noop!(
+			EmergencyShutdownModule::open_collateral_refund(Origin::ROOT),
+			Error::<Runtime>::ExistUnsettledCdp,
+		);
+
+		// settle cdps at the locked prices
+		assert_ok!(CdpEngineModule::settle_cdp_has_debit(ALICE, BTC));
+		assert_ok!(CdpEngineModule::settle_cdp_has_debit(BOB, DOT));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(BOB, DOT), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 950);
+		assert_eq!(Currencies::balance(DOT, &BOB), 970);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 50);
+		assert_eq!(CdpTreasuryModule::total_collaterals(DOT), 30);
+
+		assert_noop!(
+			EmergencyShutdownModule::refund_collaterals(Origin::signed(ALICE), 50),
+			Error::<Runtime>::CanNotRefund,
+		);
+		assert!(EmergencyShutdownModule::open_collateral_refund(Origin::signed(ALICE)).is_err());
+		assert_ok!(EmergencyShutdownModule::open_collateral_refund(Origin::ROOT));
+		assert_eq!(EmergencyShutdownModule::can_refund(), true);
+		assert_eq!(EmergencyShutdownModule::phase(), ShutdownPhase::Refund);
+		assert_eq!(EmergencyShutdownModule::stable_coin_to_refund(), 110);
+		assert_eq!(
+			EmergencyShutdownModule::refund_ratio(BTC),
+			Ratio::from_rational(50, 110)
+		);
+		assert_eq!(
+			EmergencyShutdownModule::refund_ratio(DOT),
+			Ratio::from_rational(30, 110)
+		);
+		assert_noop!(
+			EmergencyShutdownModule::open_collateral_refund(Origin::ROOT),
+			Error::<Runtime>::AlreadyOpenRefund,
+		);
+
+		// refund in proportion, rounded down
+		assert_noop!(
+			EmergencyShutdownModule::refund_collaterals(Origin::signed(ALICE), 51),
+			Error::<Runtime>::StableCoinNotEnough,
+		);
+		assert_ok!(EmergencyShutdownModule::refund_collaterals(Origin::signed(ALICE), 50));
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 922);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 1013);
+		assert_eq!(EmergencyShutdownModule::stable_coin_to_refund(), 60);
+
+		// the last redeemer takes all the remaining collaterals
+		assert_noop!(
+			EmergencyShutdownModule::refund_collaterals(Origin::signed(BOB), 61),
+			Error::<Runtime>::InvalidRefundAmount,
+		);
+		assert_ok!(EmergencyShutdownModule::refund_collaterals(Origin::signed(BOB), 60));
+		assert_eq!(Currencies::balance(AUSD, &BOB), 0);
+		assert_eq!(Currencies::balance(BTC, &BOB), 1028);
+		assert_eq!(Currencies::balance(DOT, &BOB), 917);
+		assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 0);
+		assert_eq!(CdpTreasuryModule::total_collaterals(DOT), 0);
+		assert_eq!(EmergencyShutdownModule::stable_coin_to_refund(), 0);
+
+		// the remaining collaterals of settled cdps can still be withdrawn
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, -50, 0));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 972);
+	});
+}
@@ -0,0 +1,102 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use orml_traits::{MultiCurrency, MultiCurrencyExtended};
+use rstd::{convert::TryInto, prelude::*};
+use sp_runtime::traits::Saturating;
+use system::{ensure_root, ensure_signed};
+
+mod mock;
+mod tests;
+
+type BalanceOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type AmountOf<T> = <<T as Trait>::Currency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
+
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: MultiCurrencyExtended<Self::AccountId>;
+	/// Currencies which can be requested from the faucet.
+	type FaucetCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The drip amount of currencies without one set by governance.
+	type DefaultDripAmount: Get<BalanceOf<Self>>;
+	/// The blocks an account has to wait between two requests.
+	type DripPeriod: Get<Self::BlockNumber>;
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		CurrencyId = CurrencyIdOf<T>,
+		Balance = BalanceOf<T>,
+	{
+		/// Tokens dripped to account (account, currency_id, amount)
+		RequestTokens(AccountId, CurrencyId, Balance),
+		/// Update the drip amount of currency (currency_id, amount)
+		UpdateDripAmount(CurrencyId, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for faucet module.
+	pub enum Error for Module<T: Trait> {
+		InvalidCurrencyId,
+		RequestTooFrequent,
+		AmountConvertFailed,
+		UpdateBalanceFailed,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Faucet {
+		pub DripAmounts get(fn drip_amounts): map CurrencyIdOf<T> => Option<BalanceOf<T>>;
+		pub LastDrips get(fn last_drip): map T::AccountId => Option<T::BlockNumber>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		pub fn request_tokens(origin, currency_id: CurrencyIdOf<T>) {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				T::FaucetCurrencyIds::get().contains(&currency_id),
+				Error::<T>::InvalidCurrencyId,
+			);
+			let now = <system::Module<T>>::block_number();
+			if let Some(last_drip) = Self::last_drip(&who) {
+				ensure!(
+					now >= last_drip.saturating_add(T::DripPeriod::get()),
+					Error::<T>::RequestTooFrequent,
+				);
+			}
+
+			let amount = Self::drip_amount(currency_id);
+			let update_amount = TryInto::<AmountOf<T>>::try_into(amount).map_err(|_| Error::<T>::AmountConvertFailed)?;
+			T::Currency::update_balance(currency_id, &who, update_amount).map_err(|_| Error::<T>::UpdateBalanceFailed)?;
+			<LastDrips<T>>::insert(&who, now);
+
+			Self::deposit_event(RawEvent::RequestTokens(who, currency_id, amount));
+		}
+
+		pub fn set_drip_amount(origin, currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+			ensure_root(origin)?;
+			ensure!(
+				T::FaucetCurrencyIds::get().contains(&currency_id),
+				Error::<T>::InvalidCurrencyId,
+			);
+			<DripAmounts<T>>::insert(currency_id, amount);
+			Self::deposit_event(RawEvent::UpdateDripAmount(currency_id, amount));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	pub fn drip_amount(currency_id: CurrencyIdOf<T>) -> BalanceOf<T> {
+		Self::drip_amounts(currency_id).unwrap_or_else(T::DefaultDripAmount::get)
+	}
+}
@@ -0,0 +1,69 @@
+//! Unit tests for the faucet module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{ExtBuilder, FaucetModule, Origin, Runtime, System, Tokens, ALICE, AUSD, BOB, BTC, DOT};
+
+#[test]
+fn request_tokens_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(FaucetModule::request_tokens(Origin::signed(ALICE), AUSD));
+		assert_eq!(Tokens::balance(AUSD, &ALICE), 100);
+		assert_eq!(Tokens::total_issuance(AUSD), 100);
+		assert_eq!(FaucetModule::last_drip(ALICE), Some(1));
+	});
+}
+
+#[test]
+fn request_unknown_currency_failed() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			FaucetModule::request_tokens(Origin::signed(ALICE), DOT),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+		assert_noop!(
+			FaucetModule::set_drip_amount(Origin::ROOT, DOT, 10),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+	});
+}
+
+#[test]
+fn request_tokens_rate_limited() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(FaucetModule::request_tokens(Origin::signed(ALICE), AUSD));
+
+		// the limit is per account, whatever the currency
+		System::set_block_number(10);
+		assert_noop!(
+			FaucetModule::request_tokens(Origin::signed(ALICE), BTC),
+			Error::<Runtime>::RequestTooFrequent,
+		);
+		assert_ok!(FaucetModule::request_tokens(Origin::signed(BOB), BTC));
+
+		System::set_block_number(11);
+		assert_ok!(FaucetModule::request_tokens(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(BTC, &ALICE), 100);
+		assert_noop!(
+			FaucetModule::request_tokens(Origin::signed(ALICE), AUSD),
+			Error::<Runtime>::RequestTooFrequent,
+		);
+	});
+}
+
+#[test]
+fn set_drip_amount_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(FaucetModule::set_drip_amount(Origin::signed(ALICE), BTC, 5).is_err());
+		assert_ok!(FaucetModule::set_drip_amount(Origin::ROOT, BTC, 5));
+		assert_eq!(FaucetModule::drip_amount(BTC), 5);
+		assert_eq!(FaucetModule::drip_amount(AUSD), 100);
+
+		assert_ok!(FaucetModule::request_tokens(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(BTC, &ALICE), 5);
+	});
+}
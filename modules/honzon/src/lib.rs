@@ -1,25 +1,66 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{decl_error, decl_event, decl_module, decl_storage};
+use cdp_engine::DebitExchangeRateConvertor;
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::Get,
+	weights::{ClassifyDispatch, DispatchClass, PaysFee, SimpleDispatchInfo, WeighData, Weight},
+};
 use frame_system::{self as system, ensure_signed};
-use orml_traits::{MultiCurrency, MultiCurrencyExtended};
-use sp_runtime::traits::StaticLookup;
+use orml_traits::{arithmetic::Signed, MultiCurrency, MultiCurrencyExtended};
+use rstd::{convert::TryInto, prelude::*};
+use sp_runtime::{
+	traits::{CheckedSub, Saturating, StaticLookup, Zero},
+	DispatchResult,
+};
+use support::DebitConvertor;
 
 mod mock;
 mod tests;
 
 pub trait Trait: system::Trait + cdp_engine::Trait + vaults::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	/// The most loans adjusted by one `adjust_loans`.
+	type MaxAdjustmentsPerCall: Get<u32>;
 }
 
+type BalanceOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
 type CurrencyIdOf<T> = <<T as vaults::Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type DebitBalanceOf<T> =
+	<<T as vaults::Trait>::DebitCurrency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
 type AmountOf<T> = <<T as vaults::Trait>::Currency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
 type DebitAmountOf<T> =
 	<<T as vaults::Trait>::DebitCurrency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
 
+/// The weight of adjusting one loan.
+const ADJUSTMENT_WEIGHT: Weight = 10_000;
+
+/// Weighs a dispatchable by the number of loans it adjusts, the adjustments are its only argument.
+pub struct WeightPerAdjustment(Weight);
+
+impl<Adjustment> WeighData<(&Vec<Adjustment>,)> for WeightPerAdjustment {
+	fn weigh_data(&self, (adjustments,): (&Vec<Adjustment>,)) -> Weight {
+		self.0.saturating_mul(adjustments.len() as Weight)
+	}
+}
+
+impl<Adjustment> ClassifyDispatch<(&Vec<Adjustment>,)> for WeightPerAdjustment {
+	fn classify_dispatch(&self, _: (&Vec<Adjustment>,)) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+impl PaysFee for WeightPerAdjustment {
+	fn pays_fee(&self) -> bool {
+		true
+	}
+}
+
 decl_storage! {
 	trait Store for Module<T: Trait> as Honzon {
 		pub Authorization get(fn authorization): double_map T::AccountId, blake2_256((CurrencyIdOf<T>, T::AccountId)) => bool;
+		/// All the authorizations granted by account, `Authorization` can't be iterated by account.
+		pub Authorizations get(fn authorizations): map T::AccountId => Vec<(CurrencyIdOf<T>, T::AccountId)>;
 	}
 }
 
@@ -32,53 +73,163 @@ decl_event!(
 	{
 		/// liquidate `who` `currency` vault
 		Liquidate(AccountId, CurrencyId),
-		/// update vault success (from, to, currency_id)
-		UpdateVault(AccountId, CurrencyId, Amount, DebitAmount),
-		/// transfer vault success (from, to, currency_id)
-		TransferVault(AccountId, AccountId, CurrencyId),
+		/// settle `who` `currency` vault after shutdown
+		SettleCdp(AccountId, CurrencyId),
+		/// the loan adjusted (who, currency_id, collateral_adjustment, debit_adjustment), by the owner or an
+		/// authorized account, positive adjustments are deposits and borrows
+		LoanAdjusted(AccountId, CurrencyId, Amount, DebitAmount),
+		/// the whole loan moved (from, to, currency_id)
+		LoanTransferred(AccountId, AccountId, CurrencyId),
+		/// the loan repaid and withdrawn to zero (who, currency_id)
+		LoanClosed(AccountId, CurrencyId),
 		/// authorization (from, to, currency_id)
-		Authorization(AccountId, AccountId, CurrencyId),
+		Authorized(AccountId, AccountId, CurrencyId),
 		/// cancel authorization (from, to, currency_id)
-		UnAuthorization(AccountId, AccountId, CurrencyId),
+		Unauthorized(AccountId, AccountId, CurrencyId),
 		/// cancel all authorization
-		UnAuthorizationAll(AccountId),
+		UnauthorizedAll(AccountId),
 	}
 );
 
 decl_error! {
-	pub enum Error {
+	/// Error for honzon module.
+	pub enum Error for Module<T: Trait> {
 		AccountUnSigned,
 		NoAuthorization,
-		TransferVaultFailed,
-		UpdatePositionFailed,
-		LiquidateFailed,
+		NoLoanAdjustment,
+		TooManyAdjustments,
+		DuplicateLoanAdjustment,
+		StableCoinInsufficient,
+		AmountConvertFailed,
 	}
 }
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
 
 		fn deposit_event() = default;
 
+		/// Liquidate the unsafe position of `who` in `currency_id`, operational so liquidations aren't crowded out.
+		#[weight = SimpleDispatchInfo::FixedOperational(10_000)]
 		fn liquidate(_origin, who: <T::Lookup as StaticLookup>::Source, currency_id: CurrencyIdOf<T>) {
-			let who = T::Lookup::lookup(who).map_err(|_| Error::AccountUnSigned)?;
+			let who = T::Lookup::lookup(who).map_err(|_| Error::<T>::AccountUnSigned)?;
 
-			<cdp_engine::Module<T>>::liquidate_unsafe_cdp(who.clone(), currency_id).map_err(|_| Error::LiquidateFailed)?;
+			<cdp_engine::Module<T>>::liquidate_unsafe_cdp(who.clone(), currency_id)?;
 
 			Self::deposit_event(RawEvent::Liquidate(who, currency_id));
 		}
 
+		fn settle_cdp(_origin, who: <T::Lookup as StaticLookup>::Source, currency_id: CurrencyIdOf<T>) {
+			let who = T::Lookup::lookup(who).map_err(|_| Error::<T>::AccountUnSigned)?;
+
+			<cdp_engine::Module<T>>::settle_cdp_has_debit(who.clone(), currency_id)?;
+
+			Self::deposit_event(RawEvent::SettleCdp(who, currency_id));
+		}
+
 		fn update_vault(
 			origin,
 			currency_id: CurrencyIdOf<T>,
 			collateral: AmountOf<T>,
 			debit: DebitAmountOf<T>
 		) {
-			let who = ensure_signed(origin).map_err(|_| Error::AccountUnSigned)?;
+			let who = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+
+			<cdp_engine::Module<T>>::update_position(who.clone(), currency_id, collateral, debit)?;
+
+			Self::deposit_event(RawEvent::LoanAdjusted(who, currency_id, collateral, debit));
+		}
+
+		/// Adjust the collateral and the debit of the `currency_id` loan of `origin` together, positive adjustments
+		/// deposit and borrow, negative ones withdraw and repay. The loan is only checked to be safe after both.
+		fn update_loan(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			collateral_adjustment: AmountOf<T>,
+			debit_adjustment: DebitAmountOf<T>
+		) {
+			let who = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			ensure!(
+				!collateral_adjustment.is_zero() || !debit_adjustment.is_zero(),
+				Error::<T>::NoLoanAdjustment,
+			);
 
-			<cdp_engine::Module<T>>::update_position(who.clone(), currency_id, collateral, debit).map_err(|_| Error::UpdatePositionFailed)?;
+			<cdp_engine::Module<T>>::update_position(who.clone(), currency_id, collateral_adjustment, debit_adjustment)?;
 
-			Self::deposit_event(RawEvent::UpdateVault(who, currency_id, collateral, debit));
+			Self::deposit_event(RawEvent::LoanAdjusted(who, currency_id, collateral_adjustment, debit_adjustment));
+		}
+
+		/// Repay the whole debit of the `currency_id` loan of `origin`, the stable coin it is worth at the current
+		/// debit exchange rate, so no debit is left behind by the stability fee accrued since it was queried.
+		fn repay_all(origin, currency_id: CurrencyIdOf<T>) {
+			let who = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let debit = <vaults::Module<T>>::debits(&who, currency_id);
+			ensure!(!debit.is_zero(), Error::<T>::NoLoanAdjustment);
+			let debit_adjustment =
+				-TryInto::<DebitAmountOf<T>>::try_into(debit).map_err(|_| Error::<T>::AmountConvertFailed)?;
+
+			<cdp_engine::Module<T>>::update_position(who.clone(), currency_id, Zero::zero(), debit_adjustment)?;
+
+			Self::deposit_event(RawEvent::LoanAdjusted(who, currency_id, Zero::zero(), debit_adjustment));
+		}
+
+		/// Adjust several loans of `origin` in order, each as `update_loan` does. All the adjustments are checked
+		/// before the first applies, so either all of them apply or none, and the stable coin borrowed by an
+		/// adjustment can repay the debit of the later ones. Each loan can only be adjusted once.
+		#[weight = WeightPerAdjustment(ADJUSTMENT_WEIGHT)]
+		fn adjust_loans(origin, adjustments: Vec<(CurrencyIdOf<T>, AmountOf<T>, DebitAmountOf<T>)>) {
+			let who = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			Self::check_adjust_loans(&who, &adjustments)?;
+
+			for (currency_id, collateral_adjustment, debit_adjustment) in adjustments {
+				<cdp_engine::Module<T>>::update_position(who.clone(), currency_id, collateral_adjustment, debit_adjustment)?;
+
+				Self::deposit_event(RawEvent::LoanAdjusted(
+					who.clone(),
+					currency_id,
+					collateral_adjustment,
+					debit_adjustment,
+				));
+			}
+		}
+
+		/// Adjust the `currency_id` loan of `owner` as `update_loan` does, if `owner` allowed `origin` to manipulate
+		/// it. The collateral comes from and goes to the account of `owner`, and so does the debit.
+		fn adjust_loan_by_authorized(
+			origin,
+			owner: <T::Lookup as StaticLookup>::Source,
+			currency_id: CurrencyIdOf<T>,
+			collateral_adjustment: AmountOf<T>,
+			debit_adjustment: DebitAmountOf<T>
+		) {
+			let from = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let owner = T::Lookup::lookup(owner).map_err(|_| Error::<T>::AccountUnSigned)?;
+			Self::check_authorization(&owner, &from, currency_id)?;
+			ensure!(
+				!collateral_adjustment.is_zero() || !debit_adjustment.is_zero(),
+				Error::<T>::NoLoanAdjustment,
+			);
+
+			<cdp_engine::Module<T>>::update_position(owner.clone(), currency_id, collateral_adjustment, debit_adjustment)?;
+
+			Self::deposit_event(RawEvent::LoanAdjusted(
+				owner,
+				currency_id,
+				collateral_adjustment,
+				debit_adjustment,
+			));
+		}
+
+		/// Close the `currency_id` loan of `origin` by selling at most `max_collateral_to_sell` of its collateral on
+		/// the dex to repay the debit, the rest of the collateral is withdrawn. Nothing changes if the dex can't cover
+		/// the debit within the cap.
+		fn close_loan_by_dex(origin, currency_id: CurrencyIdOf<T>, max_collateral_to_sell: BalanceOf<T>) {
+			let who = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+
+			<cdp_engine::Module<T>>::close_position_by_dex(who.clone(), currency_id, max_collateral_to_sell)?;
+
+			Self::deposit_event(RawEvent::LoanClosed(who, currency_id));
 		}
 
 		fn transfer_vault(
@@ -86,32 +237,51 @@ decl_module! {
 			currency_id: CurrencyIdOf<T>,
 			to: <T::Lookup as StaticLookup>::Source
 		) {
-			let from = ensure_signed(origin).map_err(|_| Error::AccountUnSigned)?;
-			let to = T::Lookup::lookup(to).map_err(|_| Error::AccountUnSigned)?;
+			let from = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let to = T::Lookup::lookup(to).map_err(|_| Error::<T>::AccountUnSigned)?;
 
 			// check authorization if `from` can manipulate `to`
 			Self::check_authorization(&to, &from, currency_id)?;
 
-			<vaults::Module<T>>::transfer(from.clone(), to.clone(), currency_id).map_err(|_|
-				Error::TransferVaultFailed
-			)?;
+			<vaults::Module<T>>::transfer(from.clone(), to.clone(), currency_id)?;
 
-			Self::deposit_event(RawEvent::TransferVault(from, to, currency_id));
+			Self::deposit_event(RawEvent::LoanTransferred(from, to, currency_id));
 		 }
 
+		/// Move the whole `currency_id` loan of `from` into the loan of `origin`, if `from` allowed `origin` to
+		/// manipulate it. Only the collateral and the debit of the loans move, the balances of both accounts are
+		/// unchanged, and the merged loan has to be safe.
+		fn transfer_loan_from(
+			origin,
+			currency_id: CurrencyIdOf<T>,
+			from: <T::Lookup as StaticLookup>::Source
+		) {
+			let to = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let from = T::Lookup::lookup(from).map_err(|_| Error::<T>::AccountUnSigned)?;
+
+			Self::check_authorization(&from, &to, currency_id)?;
+
+			<vaults::Module<T>>::transfer(from.clone(), to.clone(), currency_id)?;
+
+			Self::deposit_event(RawEvent::LoanTransferred(from, to, currency_id));
+		}
+
 		/// `origin` allow `to` to manipulate the `currency_id` vault
 		fn authorize(
 			origin,
 			currency_id: CurrencyIdOf<T>,
 			to: <T::Lookup as StaticLookup>::Source
 		) {
-			let from = ensure_signed(origin).map_err(|_| Error::AccountUnSigned)?;
-			let to = T::Lookup::lookup(to).map_err(|_| Error::AccountUnSigned)?;
+			let from = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let to = T::Lookup::lookup(to).map_err(|_| Error::<T>::AccountUnSigned)?;
 
 			// update authorization
-			<Authorization<T>>::insert(&from, (currency_id, &to), true);
+			if !Self::authorization(&from, (currency_id, &to)) {
+				<Authorization<T>>::insert(&from, (currency_id, &to), true);
+				<Authorizations<T>>::mutate(&from, |authorizations| authorizations.push((currency_id, to.clone())));
+			}
 
-			Self::deposit_event(RawEvent::Authorization(from, to, currency_id));
+			Self::deposit_event(RawEvent::Authorized(from, to, currency_id));
 		}
 
 		/// `origin` refuse `to` to manipulate the vault  of `currency_id`
@@ -120,34 +290,34 @@ decl_module! {
 			currency_id: CurrencyIdOf<T>,
 			to: <T::Lookup as StaticLookup>::Source
 		) {
-			let from = ensure_signed(origin).map_err(|_| Error::AccountUnSigned)?;
-			let to = T::Lookup::lookup(to).map_err(|_| Error::AccountUnSigned)?;
+			let from = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
+			let to = T::Lookup::lookup(to).map_err(|_| Error::<T>::AccountUnSigned)?;
 
 			// update authorization
 			<Authorization<T>>::remove(&from, (currency_id, &to));
+			<Authorizations<T>>::mutate(&from, |authorizations| {
+				authorizations.retain(|authorization| *authorization != (currency_id, to.clone()))
+			});
 
-			Self::deposit_event(RawEvent::UnAuthorization(from, to, currency_id));
+			Self::deposit_event(RawEvent::Unauthorized(from, to, currency_id));
 		}
 
 		/// `origin` refuse anyone to manipulate its vault
 		fn unauthorize_all(origin) {
-			let from = ensure_signed(origin).map_err(|_| Error::AccountUnSigned)?;
+			let from = ensure_signed(origin).map_err(|_| Error::<T>::AccountUnSigned)?;
 
 			// update authorization
 			<Authorization<T>>::remove_prefix(&from);
+			<Authorizations<T>>::remove(&from);
 
-			Self::deposit_event(RawEvent::UnAuthorizationAll(from));
+			Self::deposit_event(RawEvent::UnauthorizedAll(from));
 		}
 	}
 }
 
 impl<T: Trait> Module<T> {
 	/// check if `from` allow `to` to manipulate its vault
-	pub fn check_authorization(
-		from: &T::AccountId,
-		to: &T::AccountId,
-		currency_id: CurrencyIdOf<T>,
-	) -> Result<(), Error> {
+	pub fn check_authorization(from: &T::AccountId, to: &T::AccountId, currency_id: CurrencyIdOf<T>) -> DispatchResult {
 		if from == to {
 			return Ok(());
 		}
@@ -156,6 +326,56 @@ impl<T: Trait> Module<T> {
 			return Ok(());
 		}
 
-		Err(Error::NoAuthorization)
+		Err(Error::<T>::NoAuthorization.into())
+	}
+
+	/// Ensure `adjustments` of the loans of `who` would all apply in order, each loan and the stable coin of `who`
+	/// as left by the adjustments before it.
+	fn check_adjust_loans(
+		who: &T::AccountId,
+		adjustments: &[(CurrencyIdOf<T>, AmountOf<T>, DebitAmountOf<T>)],
+	) -> DispatchResult {
+		ensure!(!adjustments.is_empty(), Error::<T>::NoLoanAdjustment);
+		ensure!(
+			adjustments.len() <= T::MaxAdjustmentsPerCall::get() as usize,
+			Error::<T>::TooManyAdjustments,
+		);
+
+		let stable_currency_id = <T as cdp_engine::Trait>::GetStableCurrencyId::get();
+		let mut stable_coin_balance = <T as vaults::Trait>::Currency::balance(stable_currency_id, who);
+		for (index, &(currency_id, collateral_adjustment, debit_adjustment)) in adjustments.iter().enumerate() {
+			ensure!(
+				!collateral_adjustment.is_zero() || !debit_adjustment.is_zero(),
+				Error::<T>::NoLoanAdjustment,
+			);
+			// the loans are checked against storage, which a second adjustment of a loan would not see
+			ensure!(
+				adjustments[..index]
+					.iter()
+					.all(|(adjusted, _, _)| *adjusted != currency_id),
+				Error::<T>::DuplicateLoanAdjustment,
+			);
+			<cdp_engine::Module<T>>::check_position_update(who, currency_id, collateral_adjustment, debit_adjustment)?;
+			<vaults::Module<T>>::check_balance(who, currency_id, collateral_adjustment)?;
+
+			// the debits module mints the stable coin the debit is worth rounded down, and burns it rounded up
+			let debit_balance = TryInto::<DebitBalanceOf<T>>::try_into(debit_adjustment.abs())
+				.map_err(|_| Error::<T>::AmountConvertFailed)?;
+			stable_coin_balance = if debit_adjustment.is_positive() {
+				stable_coin_balance.saturating_add(DebitExchangeRateConvertor::<T>::convert_debit_to_balance_floor(
+					currency_id,
+					debit_balance,
+				))
+			} else {
+				stable_coin_balance
+					.checked_sub(&DebitExchangeRateConvertor::<T>::convert_debit_to_balance_ceil(
+						currency_id,
+						debit_balance,
+					))
+					.ok_or(Error::<T>::StableCoinInsufficient)?
+			};
+		}
+
+		Ok(())
 	}
 }
@@ -2,15 +2,32 @@
 
 #![cfg(test)]
 
-use frame_support::{impl_outer_origin, parameter_types};
+use frame_support::{impl_outer_event, impl_outer_origin, parameter_types, traits::ModuleToIndex};
 use primitives::H256;
-use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::IdentityLookup,
+	DispatchResult, ModuleId, Perbill,
+};
 
 use orml_traits::PriceProvider;
-use support::{AuctionManager, ExchangeRate, Price, Rate, Ratio};
+use support::{AuctionManager, AuctionManagerExtended, ExchangeRate, FixedU128Ext, Price, Rate, Ratio};
+
+use std::any::TypeId;
 
 use super::*;
 
+mod honzon {
+	pub use super::super::*;
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Runtime {
+		honzon<T>,
+		vaults<T>,
+	}
+}
+
 impl_outer_origin! {
 	pub enum Origin for Runtime {}
 }
@@ -24,12 +41,22 @@ parameter_types! {
 	pub const TransferFee: u64 = 0;
 	pub const CreationFee: u64 = 2;
 	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
-	pub const GlobalStabilityFee: Rate = Rate::from_parts(0);
-	pub const DefaultLiquidationRatio: Ratio = Ratio::from_rational(3, 2);
+	pub const GlobalStabilityFee: Rate = Rate::from_percent(0);
+	pub const DefaultLiquidationRatio: Ratio = Ratio::from_percent(150);
 	pub const DefaulDebitExchangeRate: ExchangeRate = ExchangeRate::from_natural(1);
 	pub const MinimumDebitValue: Balance = 2;
 	pub const GetNativeCurrencyId: CurrencyId = ACA;
 	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 2;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
+	pub const MaxAdjustmentsPerCall: u32 = 2;
 }
 
 pub type AccountId = u64;
@@ -37,21 +64,44 @@ pub type BlockNumber = u64;
 pub type Balance = u64;
 pub type DebitBalance = u64;
 pub type Amount = i64;
+pub type Share = u64;
 pub type DebitAmount = i64;
-pub type CurrencyId = u32;
+pub type AuctionId = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
 
 pub const ALICE: AccountId = 1;
 pub const BOB: AccountId = 2;
 pub const ALIEX: AccountId = 3;
 
-pub const ACA: CurrencyId = 0;
-pub const AUSD: CurrencyId = 1;
-pub const BTC: CurrencyId = 2;
-pub const DOT: CurrencyId = 3;
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Runtime;
 
+pub const VAULTS_INDEX: usize = 1;
+pub const CDP_ENGINE_INDEX: usize = 2;
+pub const HONZON_INDEX: usize = 3;
+
+// the index of modules as `construct_runtime!` assigns them, other modules share index 0
+pub struct MockModuleToIndex;
+impl ModuleToIndex for MockModuleToIndex {
+	fn module_to_index<M: 'static>() -> Option<usize> {
+		let type_id = TypeId::of::<M>();
+		if type_id == TypeId::of::<vaults::Module<Runtime>>() {
+			Some(VAULTS_INDEX)
+		} else if type_id == TypeId::of::<cdp_engine::Module<Runtime>>() {
+			Some(CDP_ENGINE_INDEX)
+		} else if type_id == TypeId::of::<Module<Runtime>>() {
+			Some(HONZON_INDEX)
+		} else {
+			Some(0)
+		}
+	}
+}
+
 impl system::Trait for Runtime {
 	type Origin = Origin;
 	type Index = u64;
@@ -62,14 +112,17 @@ impl system::Trait for Runtime {
 	type AccountId = AccountId;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = MockModuleToIndex;
 }
 
+pub type System = system::Module<Runtime>;
+
 impl orml_tokens::Trait for Runtime {
 	type Event = ();
 	type Balance = Balance;
@@ -114,11 +167,14 @@ impl debits::Trait for Runtime {
 pub type DebitCurrency = debits::Module<Runtime>;
 
 impl vaults::Trait for Runtime {
-	type Event = ();
+	type Event = TestEvent;
 	type Convert = cdp_engine::DebitExchangeRateConvertor<Runtime>;
 	type Currency = Tokens;
 	type DebitCurrency = DebitCurrency;
 	type RiskManager = CdpEngineModule;
+	type OnUpdateLoan = ();
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = ();
 }
 pub type VaultsModule = vaults::Module<Runtime>;
 
@@ -134,22 +190,99 @@ pub struct MockAuctionManager;
 impl AuctionManager<AccountId> for MockAuctionManager {
 	type CurrencyId = CurrencyId;
 	type Balance = Balance;
-	type Amount = Amount;
-
-	#[allow(unused_variables)]
-	fn increase_surplus(increment: Self::Balance) {}
-
+	type AuctionId = AuctionId;
 	#[allow(unused_variables)]
 	fn new_collateral_auction(
 		who: AccountId,
 		currency_id: Self::CurrencyId,
 		amount: Self::Balance,
 		target: Self::Balance,
-		bad_debt: Self::Balance,
-	) {
+	) -> Vec<Self::AuctionId> {
+		vec![]
+	}
+
+	#[allow(unused_variables)]
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn new_debit_auction(initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId> {
+		None
+	}
+
+	#[allow(unused_variables)]
+	fn cancel_auction(id: Self::AuctionId) -> DispatchResult {
+		Ok(())
+	}
+
+	#[allow(unused_variables)]
+	fn get_total_collateral_in_auction(currency_id: Self::CurrencyId) -> Self::Balance {
+		0
+	}
+
+	fn get_total_surplus_in_auction() -> Self::Balance {
+		0
+	}
+
+	fn get_total_debit_in_auction() -> Self::Balance {
+		0
 	}
 }
 
+impl AuctionManagerExtended<AccountId> for MockAuctionManager {
+	fn emergency_cancel_all(_max_per_block: u32) -> u32 {
+		0
+	}
+
+	fn active_auction_count() -> u32 {
+		0
+	}
+}
+
+impl dex::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+impl cdp_treasury::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = MockAuctionManager;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = DexModule;
+	type PriceSource = MockPriceSource;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = cdp_treasury::Module<Runtime>;
+
 impl cdp_engine::Trait for Runtime {
 	type Event = ();
 	type AuctionManagerHandler = MockAuctionManager;
@@ -157,16 +290,26 @@ impl cdp_engine::Trait for Runtime {
 	type PriceSource = MockPriceSource;
 	type CollateralCurrencyIds = CollateralCurrencyIds;
 	type GlobalStabilityFee = GlobalStabilityFee;
+	type StabilityFeeModel = ();
 	type DefaultLiquidationRatio = DefaultLiquidationRatio;
 	type DefaulDebitExchangeRate = DefaulDebitExchangeRate;
 	type MinimumDebitValue = MinimumDebitValue;
 	type GetStableCurrencyId = GetStableCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type Dex = DexModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type Call = cdp_engine::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), cdp_engine::Call<Runtime>, TestXt<cdp_engine::Call<Runtime>, ()>>;
+	type MaxPositionsCheckedPerBlock = ();
 }
 
 pub type CdpEngineModule = cdp_engine::Module<Runtime>;
 
 impl Trait for Runtime {
-	type Event = ();
+	type Event = TestEvent;
+	type MaxAdjustmentsPerCall = MaxAdjustmentsPerCall;
 }
 
 pub type HonzonModule = Module<Runtime>;
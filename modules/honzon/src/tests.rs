@@ -3,15 +3,19 @@
 #![cfg(test)]
 
 use super::*;
-use frame_support::{assert_noop, assert_ok};
+use codec::{Decode, Encode};
+use frame_support::{assert_noop, assert_ok, weights::GetDispatchInfo};
 use mock::{
-	CdpEngineModule, Currencies, ExtBuilder, HonzonModule, Origin, VaultsModule, ALICE, ALIEX, AUSD, BOB, BTC, DOT,
+	CdpEngineModule, Currencies, DexModule, ExtBuilder, HonzonModule, MockPriceSource, Origin, Runtime, System,
+	TestEvent, VaultsModule, ALICE, ALIEX, AUSD, BOB, BTC, CDP_ENGINE_INDEX, DOT, HONZON_INDEX, VAULTS_INDEX,
 };
-use support::{Rate, Ratio};
+use sp_runtime::{traits::OnFinalize, DispatchError};
+use support::{OnEmergencyShutdown, Price, PriceProviderExtended, Rate, Ratio};
 
 #[test]
 fn liquidate_unsafe_cdp_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
@@ -20,6 +24,7 @@ fn liquidate_unsafe_cdp_work() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
 		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
 		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
@@ -28,7 +33,7 @@ fn liquidate_unsafe_cdp_work() {
 		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
 		assert_noop!(
 			HonzonModule::liquidate(Origin::signed(ALIEX), ALICE, BTC),
-			"LiquidateFailed",
+			cdp_engine::Error::<Runtime>::CollateralRatioStillSafe,
 		);
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
@@ -37,9 +42,16 @@ fn liquidate_unsafe_cdp_work() {
 			Some(Some(Ratio::from_rational(3, 1))),
 			None,
 			None,
+			None,
 			None
 		));
 		assert_ok!(HonzonModule::liquidate(Origin::signed(ALIEX), ALICE, BTC));
+		let liquidate_event = TestEvent::honzon(RawEvent::Liquidate(ALICE, BTC));
+		assert!(System::events().iter().any(|record| record.event == liquidate_event));
+		let position_confiscated_event = TestEvent::vaults(vaults::RawEvent::PositionConfiscated(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_confiscated_event));
 		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
 		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
 		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
@@ -47,24 +59,66 @@ fn liquidate_unsafe_cdp_work() {
 	});
 }
 
+#[test]
+fn settle_cdp_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::update_position(ALICE, BTC, 100, 50));
+		assert_noop!(
+			HonzonModule::settle_cdp(Origin::signed(ALIEX), ALICE, BTC),
+			cdp_engine::Error::<Runtime>::MustAfterShutdown,
+		);
+		CdpEngineModule::on_emergency_shutdown();
+		assert_ok!(HonzonModule::settle_cdp(Origin::signed(ALIEX), ALICE, BTC));
+		let settle_cdp_event = TestEvent::honzon(RawEvent::SettleCdp(ALICE, BTC));
+		assert!(System::events().iter().any(|record| record.event == settle_cdp_event));
+		let position_confiscated_event = TestEvent::vaults(vaults::RawEvent::PositionConfiscated(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_confiscated_event));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 950);
+	});
+}
+
 #[test]
 fn authorize_should_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+		let authorized_event = TestEvent::honzon(RawEvent::Authorized(ALICE, BOB, BTC));
+		assert!(System::events().iter().any(|record| record.event == authorized_event));
 		assert_ok!(HonzonModule::check_authorization(&ALICE, &BOB, BTC));
+		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+		assert_eq!(HonzonModule::authorizations(ALICE), vec![(BTC, BOB)]);
 	});
 }
 
 #[test]
 fn unauthorize_should_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
 		assert_ok!(HonzonModule::check_authorization(&ALICE, &BOB, BTC));
 
 		assert_ok!(HonzonModule::unauthorize(Origin::signed(ALICE), BTC, BOB));
+		let unauthorized_event = TestEvent::honzon(RawEvent::Unauthorized(ALICE, BOB, BTC));
+		assert!(System::events().iter().any(|record| record.event == unauthorized_event));
+		assert_eq!(HonzonModule::authorizations(ALICE), vec![]);
 		assert_noop!(
 			HonzonModule::check_authorization(&ALICE, &BOB, BTC),
-			Error::NoAuthorization
+			Error::<Runtime>::NoAuthorization
 		);
 	});
 }
@@ -72,16 +126,23 @@ fn unauthorize_should_work() {
 #[test]
 fn unauthorize_all_should_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
 		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), DOT, ALIEX));
+		assert_eq!(HonzonModule::authorizations(ALICE), vec![(BTC, BOB), (DOT, ALIEX)]);
 		assert_ok!(HonzonModule::unauthorize_all(Origin::signed(ALICE)));
+		let unauthorized_all_event = TestEvent::honzon(RawEvent::UnauthorizedAll(ALICE));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == unauthorized_all_event));
+		assert_eq!(HonzonModule::authorizations(ALICE), vec![]);
 		assert_noop!(
 			HonzonModule::check_authorization(&ALICE, &BOB, BTC),
-			Error::NoAuthorization
+			Error::<Runtime>::NoAuthorization
 		);
 		assert_noop!(
 			HonzonModule::check_authorization(&ALICE, &BOB, DOT),
-			Error::NoAuthorization
+			Error::<Runtime>::NoAuthorization
 		);
 	});
 }
@@ -89,6 +150,7 @@ fn unauthorize_all_should_work() {
 #[test]
 fn transfer_vault_should_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
@@ -97,10 +159,15 @@ fn transfer_vault_should_work() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
 		assert_ok!(HonzonModule::update_vault(Origin::signed(ALICE), BTC, 100, 50));
 		assert_ok!(HonzonModule::authorize(Origin::signed(BOB), BTC, ALICE));
 		assert_ok!(HonzonModule::transfer_vault(Origin::signed(ALICE), BTC, BOB));
+		let loan_transferred_event = TestEvent::honzon(RawEvent::LoanTransferred(ALICE, BOB, BTC));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_transferred_event));
 		assert_eq!(VaultsModule::collaterals(BOB, BTC), 100);
 		assert_eq!(VaultsModule::debits(BOB, BTC), 50);
 	});
@@ -109,16 +176,100 @@ fn transfer_vault_should_work() {
 #[test]
 fn transfer_unauthorization_vaults_should_not_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_noop!(
 			HonzonModule::transfer_vault(Origin::signed(ALICE), BTC, BOB),
-			"NoAuthorization"
+			Error::<Runtime>::NoAuthorization
+		);
+	});
+}
+
+#[test]
+fn transfer_loan_from_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 50));
+		assert_noop!(
+			HonzonModule::transfer_loan_from(Origin::signed(BOB), BTC, ALICE),
+			Error::<Runtime>::NoAuthorization
+		);
+		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+
+		// into an empty loan
+		assert_ok!(HonzonModule::transfer_loan_from(Origin::signed(BOB), BTC, ALICE));
+		let loan_transferred_event = TestEvent::honzon(RawEvent::LoanTransferred(ALICE, BOB, BTC));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_transferred_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(BOB, BTC), 100);
+		assert_eq!(VaultsModule::debits(BOB, BTC), 50);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(Currencies::balance(BTC, &BOB), 1000);
+		assert_eq!(Currencies::balance(AUSD, &BOB), 0);
+
+		// merged into an existing loan
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 200, 80));
+		assert_ok!(HonzonModule::transfer_loan_from(Origin::signed(BOB), BTC, ALICE));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(BOB, BTC), 300);
+		assert_eq!(VaultsModule::debits(BOB, BTC), 130);
+	});
+}
+
+#[test]
+fn transfer_loan_from_fails_if_the_merged_loan_is_unsafe() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 55));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(BOB), BTC, 100, 40));
+		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			None,
+			None,
+			Some(Some(Ratio::from_rational(13, 5))),
+			None,
+			None,
+		));
+		assert_noop!(
+			HonzonModule::transfer_loan_from(Origin::signed(BOB), BTC, ALICE),
+			cdp_engine::Error::<Runtime>::BelowRequiredCollateralRatio
 		);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 55);
 	});
 }
 
 #[test]
 fn update_vault_should_work() {
 	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
 		assert_ok!(CdpEngineModule::set_collateral_params(
 			Origin::ROOT,
 			BTC,
@@ -127,9 +278,448 @@ fn update_vault_should_work() {
 			Some(Some(Rate::from_rational(2, 10))),
 			Some(Some(Ratio::from_rational(9, 5))),
 			Some(10000),
+			None,
 		));
 		assert_ok!(HonzonModule::update_vault(Origin::signed(ALICE), BTC, 100, 50));
+		let loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_adjusted_event));
+		let position_updated_event = TestEvent::vaults(vaults::RawEvent::PositionUpdated(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_updated_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+	});
+}
+
+#[test]
+fn update_loan_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			HonzonModule::update_loan(Origin::signed(ALICE), BTC, 0, 0),
+			Error::<Runtime>::NoLoanAdjustment
+		);
+
+		// deposit and borrow
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 50));
 		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
 		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+
+		// repay and withdraw
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, -40, -20));
+		let loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, -40, -20));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_adjusted_event));
+		let position_updated_event = TestEvent::vaults(vaults::RawEvent::PositionUpdated(ALICE, BTC, -40, -20));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_updated_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 60);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 30);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 940);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 30);
+	});
+}
+
+#[test]
+fn update_loan_checks_the_loan_after_both_adjustments() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 50));
+
+		// borrowing more is only safe with the collateral deposited with it
+		assert_noop!(
+			HonzonModule::update_loan(Origin::signed(ALICE), BTC, 0, 10),
+			cdp_engine::Error::<Runtime>::BelowRequiredCollateralRatio
+		);
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 20, 10));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 120);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 60);
+
+		// withdrawing is only safe with the debit repaid with it
+		assert_noop!(
+			HonzonModule::update_loan(Origin::signed(ALICE), BTC, -60, 0),
+			cdp_engine::Error::<Runtime>::BelowRequiredCollateralRatio
+		);
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, -60, -30));
+		let loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, -60, -30));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_adjusted_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 60);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 30);
 	});
 }
+
+#[test]
+fn repay_all_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 10))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			HonzonModule::repay_all(Origin::signed(ALICE), BTC),
+			Error::<Runtime>::NoLoanAdjustment
+		);
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 50));
+		for block in 1..4 {
+			CdpEngineModule::on_finalize(block);
+		}
+
+		// the debit is worth more than the stable coin borrowed with it, and is repaid rounded up
+		let debit_value = cdp_engine::DebitExchangeRateConvertor::<Runtime>::convert_debit_to_balance_ceil(BTC, 50);
+		assert_eq!(debit_value, 67);
+		assert_noop!(
+			HonzonModule::repay_all(Origin::signed(ALICE), BTC),
+			vaults::Error::<Runtime>::UpdateStableCoinFailed
+		);
+		assert_ok!(<Currencies as MultiCurrency<_>>::deposit(AUSD, &ALICE, 20));
+		assert_ok!(HonzonModule::repay_all(Origin::signed(ALICE), BTC));
+		let loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, 0, -50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_adjusted_event));
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 70 - debit_value);
+	});
+}
+
+#[test]
+fn adjust_loans_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		for &currency_id in &[BTC, DOT] {
+			assert_ok!(CdpEngineModule::set_collateral_params(
+				Origin::ROOT,
+				currency_id,
+				Some(Some(Rate::from_rational(1, 100000))),
+				Some(Some(Ratio::from_rational(3, 2))),
+				Some(Some(Rate::from_rational(2, 10))),
+				Some(Some(Ratio::from_rational(9, 5))),
+				Some(10000),
+				None,
+			));
+		}
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![]),
+			Error::<Runtime>::NoLoanAdjustment
+		);
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![(BTC, 100, 50), (DOT, 0, 0)]),
+			Error::<Runtime>::NoLoanAdjustment
+		);
+		assert_noop!(
+			HonzonModule::adjust_loans(
+				Origin::signed(ALICE),
+				vec![(BTC, 100, 50), (DOT, 200, 100), (BTC, 100, 50)]
+			),
+			Error::<Runtime>::TooManyAdjustments
+		);
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![(BTC, 100, 50), (BTC, 100, 50)]),
+			Error::<Runtime>::DuplicateLoanAdjustment
+		);
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![(BTC, 100, 50), (AUSD, 100, 50)]),
+			cdp_engine::Error::<Runtime>::NotValidCurrencyId
+		);
+
+		assert_ok!(HonzonModule::adjust_loans(
+			Origin::signed(ALICE),
+			vec![(BTC, 100, 50), (DOT, 200, 100)]
+		));
+		let btc_loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, 100, 50));
+		let dot_loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, DOT, 200, 100));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == btc_loan_adjusted_event));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == dot_loan_adjusted_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+		assert_eq!(VaultsModule::collaterals(ALICE, DOT), 200);
+		assert_eq!(VaultsModule::debits(ALICE, DOT), 100);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 150);
+
+		// the stable coin borrowed by an adjustment only repays the debit of the later ones
+		assert_ok!(<Currencies as MultiCurrency<_>>::transfer(AUSD, &ALICE, &BOB, 100));
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![(DOT, -200, -100), (BTC, 100, 50)]),
+			Error::<Runtime>::StableCoinInsufficient
+		);
+		assert_ok!(HonzonModule::adjust_loans(
+			Origin::signed(ALICE),
+			vec![(BTC, 100, 50), (DOT, -200, -100)]
+		));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 200);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::collaterals(ALICE, DOT), 0);
+		assert_eq!(VaultsModule::debits(ALICE, DOT), 0);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+	});
+}
+
+#[test]
+fn adjust_loans_is_all_or_nothing() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			DOT,
+			None,
+			Some(Some(Ratio::from_rational(3, 2))),
+			None,
+			None,
+			Some(10000),
+			None,
+		));
+
+		// the dot loan would be below the liquidation ratio, so the btc loan is not opened either
+		assert_noop!(
+			HonzonModule::adjust_loans(Origin::signed(ALICE), vec![(BTC, 100, 50), (DOT, 100, 80)]),
+			cdp_engine::Error::<Runtime>::BelowLiquidationRatio
+		);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1000);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+	});
+}
+
+#[test]
+fn adjust_loans_is_weighted_by_the_adjustments() {
+	assert_eq!(
+		Call::<Runtime>::adjust_loans(vec![(BTC, 100, 50)])
+			.get_dispatch_info()
+			.weight,
+		ADJUSTMENT_WEIGHT
+	);
+	assert_eq!(
+		Call::<Runtime>::adjust_loans(vec![(BTC, 100, 50), (DOT, 200, 100)])
+			.get_dispatch_info()
+			.weight,
+		2 * ADJUSTMENT_WEIGHT
+	);
+}
+
+#[test]
+fn adjust_loan_by_authorized_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_noop!(
+			HonzonModule::adjust_loan_by_authorized(Origin::signed(BOB), ALICE, BTC, 100, 50),
+			Error::<Runtime>::NoAuthorization
+		);
+		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+		assert_noop!(
+			HonzonModule::adjust_loan_by_authorized(Origin::signed(BOB), ALICE, DOT, 100, 50),
+			Error::<Runtime>::NoAuthorization
+		);
+
+		// the collateral and the debit are of the owner
+		assert_ok!(HonzonModule::adjust_loan_by_authorized(
+			Origin::signed(BOB),
+			ALICE,
+			BTC,
+			100,
+			50
+		));
+		let loan_adjusted_event = TestEvent::honzon(RawEvent::LoanAdjusted(ALICE, BTC, 100, 50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == loan_adjusted_event));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 100);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 50);
+		assert_eq!(VaultsModule::collaterals(BOB, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(Currencies::balance(BTC, &BOB), 1000);
+		assert_eq!(Currencies::balance(AUSD, &BOB), 0);
+
+		// revoked between two adjustments
+		assert_ok!(HonzonModule::adjust_loan_by_authorized(
+			Origin::signed(BOB),
+			ALICE,
+			BTC,
+			-20,
+			-10
+		));
+		assert_ok!(HonzonModule::unauthorize(Origin::signed(ALICE), BTC, BOB));
+		assert_noop!(
+			HonzonModule::adjust_loan_by_authorized(Origin::signed(BOB), ALICE, BTC, -20, -10),
+			Error::<Runtime>::NoAuthorization
+		);
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 80);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 40);
+
+		assert_ok!(HonzonModule::authorize(Origin::signed(ALICE), BTC, BOB));
+		assert_ok!(HonzonModule::unauthorize_all(Origin::signed(ALICE)));
+		assert_noop!(
+			HonzonModule::adjust_loan_by_authorized(Origin::signed(BOB), ALICE, BTC, -20, -10),
+			Error::<Runtime>::NoAuthorization
+		);
+
+		// the owner needs no authorization
+		assert_ok!(HonzonModule::adjust_loan_by_authorized(
+			Origin::signed(ALICE),
+			ALICE,
+			BTC,
+			-20,
+			-10
+		));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 60);
+	});
+}
+
+#[test]
+fn close_loan_by_dex_should_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, BTC));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(BOB), BTC, 600, 300));
+		assert_ok!(DexModule::add_liquidity(Origin::signed(BOB), BTC, 300, 300, 0, None));
+		assert_ok!(HonzonModule::update_loan(Origin::signed(ALICE), BTC, 100, 50));
+		assert_eq!(Currencies::balance(BTC, &ALICE), 900);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+
+		// the debit costs 60 of the collateral
+		assert_noop!(
+			HonzonModule::close_loan_by_dex(Origin::signed(ALICE), BTC, 59),
+			dex::Error::<Runtime>::InacceptablePrice
+		);
+		assert_ok!(HonzonModule::close_loan_by_dex(Origin::signed(ALICE), BTC, 100));
+		let loan_closed_event = TestEvent::honzon(RawEvent::LoanClosed(ALICE, BTC));
+		assert!(System::events().iter().any(|record| record.event == loan_closed_event));
+		// the collateral sold is taken out first, then the rest is withdrawn with the debit repaid
+		let sold_collateral_event = TestEvent::vaults(vaults::RawEvent::PositionUpdated(ALICE, BTC, -60, 0));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == sold_collateral_event));
+		let position_updated_event = TestEvent::vaults(vaults::RawEvent::PositionUpdated(ALICE, BTC, -40, -50));
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == position_updated_event));
+		assert_eq!(DexModule::liquidity_pool(BTC), (360, 250));
+		assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+		assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 940);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 50);
+		assert_eq!(Currencies::balance(BTC, &VaultsModule::account_id()), 600);
+		assert_eq!(Currencies::balance(AUSD, &VaultsModule::account_id()), 0);
+	});
+}
+
+fn module_index_of(error: DispatchError) -> Option<usize> {
+	match DispatchError::decode(&mut &error.encode()[..]) {
+		Ok(DispatchError::Module { index, .. }) => Some(index as usize),
+		_ => None,
+	}
+}
+
+#[test]
+fn errors_can_be_decoded_back_to_their_source() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(CdpEngineModule::set_collateral_params(
+			Origin::ROOT,
+			BTC,
+			Some(Some(Rate::from_rational(1, 100000))),
+			Some(Some(Ratio::from_rational(3, 2))),
+			Some(Some(Rate::from_rational(2, 10))),
+			Some(Some(Ratio::from_rational(9, 5))),
+			Some(10000),
+			None,
+		));
+		// honzon -> cdp_engine -> vaults, the error isn't wrapped on the way back
+		let error = HonzonModule::update_vault(Origin::signed(ALICE), BTC, 10000, 50).unwrap_err();
+		assert_eq!(error, vaults::Error::<Runtime>::CollateralInSufficient.into());
+		assert_eq!(module_index_of(error), Some(VAULTS_INDEX));
+		match DispatchError::decode(&mut &error.encode()[..]) {
+			Ok(DispatchError::Module { error, .. }) => {
+				assert_eq!(error, vaults::Error::<Runtime>::CollateralInSufficient.as_u8())
+			}
+			_ => panic!("should be a module error"),
+		}
+
+		let error = HonzonModule::update_vault(Origin::signed(ALICE), ACA, 100, 50).unwrap_err();
+		assert_eq!(error, cdp_engine::Error::<Runtime>::NotValidCurrencyId.into());
+		assert_eq!(module_index_of(error), Some(CDP_ENGINE_INDEX));
+
+		let error = HonzonModule::transfer_vault(Origin::signed(ALICE), BTC, BOB).unwrap_err();
+		assert_eq!(module_index_of(error), Some(HONZON_INDEX));
+	});
+}
+
+#[test]
+fn mock_price_source_extended_work() {
+	assert_eq!(
+		MockPriceSource::get_relative_price(AUSD, BTC),
+		Some(Price::from_natural(1))
+	);
+	assert_eq!(MockPriceSource::get_value(AUSD, BTC, 100u64), Some(100u64));
+}
@@ -0,0 +1,299 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use orml_traits::MultiCurrency;
+use rstd::prelude::*;
+use sp_runtime::{
+	traits::{AccountIdConversion, Convert, Saturating, Zero},
+	DispatchResult, ModuleId, RuntimeDebug,
+};
+use support::{FixedU128Ext, OnEmergencyShutdown, OnUpdateLoan, OnUpdateShares, Ratio, VestingManager};
+use system::{ensure_root, ensure_signed};
+
+mod mock;
+mod tests;
+
+const MODULE_ID: ModuleId = ModuleId(*b"aca/inct");
+
+type BalanceOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type PoolIdOf<T> = PoolId<CurrencyIdOf<T>>;
+
+/// The pools sharing the reward of every block.
+#[derive(Encode, Decode, Eq, PartialEq, Copy, Clone, RuntimeDebug)]
+pub enum PoolId<CurrencyId> {
+	/// Loans of a collateral, shared by debit.
+	Loans(CurrencyId),
+	/// Liquidity of a dex pool, shared by liquidity shares.
+	DexLiquidity(CurrencyId),
+}
+
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: MultiCurrency<Self::AccountId>;
+	/// The currency rewarded to borrowers and liquidity providers, minted when claimed.
+	type RewardCurrencyId: Get<CurrencyIdOf<Self>>;
+	type CollateralCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// Vest the claimed rewards of pools with a vesting schedule.
+	type Vesting: VestingManager<Self::AccountId, BalanceOf<Self>, Self::BlockNumber>;
+	/// The currency the shares of the dex pool of each currency are minted in, staked to share the rewards of its
+	/// liquidity.
+	type DexShareCurrencyIds: Convert<CurrencyIdOf<Self>, Option<CurrencyIdOf<Self>>>;
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		<T as system::Trait>::BlockNumber,
+		PoolId = PoolIdOf<T>,
+		Balance = BalanceOf<T>,
+	{
+		/// Update the reward emitted per block for pool (pool_id, amount)
+		UpdateRewardPerBlock(PoolId, Balance),
+		/// Update the vesting of the rewards claimed from pool (pool_id, cliff, duration)
+		UpdateRewardVesting(PoolId, BlockNumber, BlockNumber),
+		/// Rewards of pool claimed (account, pool_id, amount)
+		ClaimRewards(AccountId, PoolId, Balance),
+		/// Dex shares staked in pool (account, pool_id, amount)
+		StakeShares(AccountId, PoolId, Balance),
+		/// Dex shares unstaked from pool (account, pool_id, amount)
+		UnstakeShares(AccountId, PoolId, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for incentives module.
+	pub enum Error for Module<T: Trait> {
+		InvalidCurrencyId,
+		RewardDepositFailed,
+		StakedSharesNotEnough,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Incentives {
+		pub RewardPerBlock get(fn reward_per_block): linked_map PoolIdOf<T> => BalanceOf<T>;
+		/// The (cliff, duration) in blocks of vesting the rewards claimed from pool, paid at once if both are zero.
+		pub RewardVesting get(fn reward_vesting): map PoolIdOf<T> => (T::BlockNumber, T::BlockNumber);
+		/// Accumulated reward per share of pool.
+		pub RewardIndex get(fn reward_index): map PoolIdOf<T> => Ratio;
+		pub TotalShares get(fn total_shares): map PoolIdOf<T> => BalanceOf<T>;
+		pub Shares get(fn shares): double_map T::AccountId, blake2_256(PoolIdOf<T>) => BalanceOf<T>;
+		/// The part of `shares * reward_index` which isn't owed to the account.
+		pub RewardDebts get(fn reward_debt): double_map T::AccountId, blake2_256(PoolIdOf<T>) => BalanceOf<T>;
+		pub UnclaimedRewards get(fn unclaimed_rewards): double_map T::AccountId, blake2_256(PoolIdOf<T>) => BalanceOf<T>;
+		pub IsShutdown get(fn is_shutdown): bool;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		pub fn set_reward_per_block(origin, currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+			ensure_root(origin)?;
+			ensure!(
+				T::CollateralCurrencyIds::get().contains(&currency_id),
+				Error::<T>::InvalidCurrencyId,
+			);
+			Self::update_reward_per_block(PoolId::Loans(currency_id), amount);
+		}
+
+		pub fn set_lp_reward_per_block(origin, currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+			ensure_root(origin)?;
+			Self::update_reward_per_block(PoolId::DexLiquidity(currency_id), amount);
+		}
+
+		pub fn set_reward_vesting(origin, pool_id: PoolIdOf<T>, cliff: T::BlockNumber, duration: T::BlockNumber) {
+			ensure_root(origin)?;
+			if let PoolId::Loans(currency_id) = pool_id {
+				ensure!(
+					T::CollateralCurrencyIds::get().contains(&currency_id),
+					Error::<T>::InvalidCurrencyId,
+				);
+			}
+			if cliff.is_zero() && duration.is_zero() {
+				<RewardVesting<T>>::remove(pool_id);
+			} else {
+				<RewardVesting<T>>::insert(pool_id, (cliff, duration));
+			}
+			Self::deposit_event(RawEvent::UpdateRewardVesting(pool_id, cliff, duration));
+		}
+
+		pub fn claim_rewards(origin, currency_id: CurrencyIdOf<T>) {
+			let who = ensure_signed(origin)?;
+			Self::claim(who, PoolId::Loans(currency_id))?;
+		}
+
+		pub fn claim_lp_rewards(origin, currency_id: CurrencyIdOf<T>) {
+			let who = ensure_signed(origin)?;
+			Self::claim(who, PoolId::DexLiquidity(currency_id))?;
+		}
+
+		/// Stake `amount` of the shares of the dex pool of `currency_id` to share the rewards of its liquidity. The
+		/// share currency is held by the module account, so the staked shares can't be withdrawn from the pool.
+		pub fn stake_lp_shares(origin, currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+			let who = ensure_signed(origin)?;
+			let share_currency_id = T::DexShareCurrencyIds::convert(currency_id).ok_or(Error::<T>::InvalidCurrencyId)?;
+			T::Currency::transfer(share_currency_id, &who, &Self::account_id(), amount)?;
+
+			let pool_id = PoolId::DexLiquidity(currency_id);
+			Self::update_shares(&who, pool_id, Self::shares(&who, pool_id).saturating_add(amount));
+			Self::deposit_event(RawEvent::StakeShares(who, pool_id, amount));
+		}
+
+		/// Unstake `amount` of the staked shares of the dex pool of `currency_id`, the rewards earned so far can still
+		/// be claimed.
+		pub fn unstake_lp_shares(origin, currency_id: CurrencyIdOf<T>, amount: BalanceOf<T>) {
+			let who = ensure_signed(origin)?;
+			let share_currency_id = T::DexShareCurrencyIds::convert(currency_id).ok_or(Error::<T>::InvalidCurrencyId)?;
+			let pool_id = PoolId::DexLiquidity(currency_id);
+			let staked_shares = Self::shares(&who, pool_id);
+			ensure!(staked_shares >= amount, Error::<T>::StakedSharesNotEnough);
+			T::Currency::transfer(share_currency_id, &Self::account_id(), &who, amount)?;
+
+			Self::update_shares(&who, pool_id, staked_shares - amount);
+			Self::deposit_event(RawEvent::UnstakeShares(who, pool_id, amount));
+		}
+
+		fn on_finalize(_now: T::BlockNumber) {
+			// no more emission after shutdown
+			if Self::is_shutdown() {
+				return;
+			}
+
+			for (pool_id, reward_per_block) in <RewardPerBlock<T>>::enumerate() {
+				Self::accrue_rewards(pool_id, reward_per_block);
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The account holding the staked dex shares.
+	pub fn account_id() -> T::AccountId {
+		MODULE_ID.into_account()
+	}
+
+	/// Rewards of `who` in `pool_id` which can be claimed now.
+	pub fn pending_rewards(who: &T::AccountId, pool_id: PoolIdOf<T>) -> BalanceOf<T> {
+		Self::unclaimed_rewards(who, pool_id).saturating_add(Self::accumulated_rewards(who, pool_id))
+	}
+
+	fn update_reward_per_block(pool_id: PoolIdOf<T>, amount: BalanceOf<T>) {
+		if amount.is_zero() {
+			<RewardPerBlock<T>>::remove(pool_id);
+		} else {
+			<RewardPerBlock<T>>::insert(pool_id, amount);
+		}
+		Self::deposit_event(RawEvent::UpdateRewardPerBlock(pool_id, amount));
+	}
+
+	fn claim(who: T::AccountId, pool_id: PoolIdOf<T>) -> DispatchResult {
+		Self::settle_rewards(&who, pool_id);
+		let amount = Self::unclaimed_rewards(&who, pool_id);
+		if !amount.is_zero() {
+			let (cliff, duration) = Self::reward_vesting(pool_id);
+			// everything is paid at once after shutdown
+			if Self::is_shutdown() || (cliff.is_zero() && duration.is_zero()) {
+				T::Currency::deposit(T::RewardCurrencyId::get(), &who, amount)
+					.map_err(|_| Error::<T>::RewardDepositFailed)?;
+			} else {
+				T::Vesting::vest(&who, amount, cliff, duration)?;
+			}
+			<UnclaimedRewards<T>>::remove(&who, pool_id);
+			Self::deposit_event(RawEvent::ClaimRewards(who, pool_id, amount));
+		}
+
+		Ok(())
+	}
+
+	/// Share the reward of this block in `pool_id`, nothing is emitted without shares.
+	fn accrue_rewards(pool_id: PoolIdOf<T>, reward_per_block: BalanceOf<T>) {
+		let total_shares = Self::total_shares(pool_id);
+		if total_shares.is_zero() {
+			return;
+		}
+
+		if let Some(increment) = Ratio::checked_from_rational(reward_per_block, total_shares) {
+			<RewardIndex<T>>::mutate(pool_id, |index| {
+				*index = index.checked_add(&increment).unwrap_or_else(|| *index);
+			});
+		}
+	}
+
+	/// Rewards accumulated by the current shares of `who` since its reward debt was last reset.
+	fn accumulated_rewards(who: &T::AccountId, pool_id: PoolIdOf<T>) -> BalanceOf<T> {
+		Self::reward_index(pool_id)
+			.checked_mul_int(&Self::shares(who, pool_id))
+			.unwrap_or_else(Zero::zero)
+			.saturating_sub(Self::reward_debt(who, pool_id))
+	}
+
+	/// Move the accumulated rewards of `who` to unclaimed, and reset its reward debt.
+	fn settle_rewards(who: &T::AccountId, pool_id: PoolIdOf<T>) {
+		let accumulated_rewards = Self::accumulated_rewards(who, pool_id);
+		if !accumulated_rewards.is_zero() {
+			<UnclaimedRewards<T>>::mutate(who, pool_id, |balance| {
+				*balance = balance.saturating_add(accumulated_rewards)
+			});
+			<RewardDebts<T>>::mutate(who, pool_id, |balance| {
+				*balance = balance.saturating_add(accumulated_rewards)
+			});
+		}
+	}
+
+	/// Reset the shares of `who` in `pool_id`, the rewards of the previous shares are settled first.
+	fn update_shares(who: &T::AccountId, pool_id: PoolIdOf<T>, shares: BalanceOf<T>) {
+		Self::settle_rewards(who, pool_id);
+
+		let previous_shares = Self::shares(who, pool_id);
+		<TotalShares<T>>::mutate(pool_id, |total_shares| {
+			*total_shares = total_shares.saturating_sub(previous_shares).saturating_add(shares)
+		});
+
+		if shares.is_zero() {
+			<Shares<T>>::remove(who, pool_id);
+			<RewardDebts<T>>::remove(who, pool_id);
+		} else {
+			// rounded up so the rewards paid never add up to more than the rewards accrued, the dust stays unclaimed
+			let reward_debt = Self::reward_index(pool_id)
+				.checked_mul_int_round_up(&shares)
+				.unwrap_or_else(Zero::zero);
+			<Shares<T>>::insert(who, pool_id, shares);
+			<RewardDebts<T>>::insert(who, pool_id, reward_debt);
+		}
+	}
+}
+
+impl<T: Trait> OnUpdateLoan<T::AccountId, CurrencyIdOf<T>, BalanceOf<T>> for Module<T> {
+	fn on_update_loan(who: &T::AccountId, currency_id: CurrencyIdOf<T>, debit_balance: BalanceOf<T>) {
+		Self::update_shares(who, PoolId::Loans(currency_id), debit_balance);
+	}
+}
+
+/// The staked shares can't be withdrawn from the dex, they only outnumber the shares left to `who` once the pool is
+/// closed and its shares are refunded. The staked shares are cut down to those left, and their share currency, which
+/// no longer has liquidity behind it, is burnt.
+impl<T: Trait> OnUpdateShares<T::AccountId, CurrencyIdOf<T>, BalanceOf<T>> for Module<T> {
+	fn on_update_shares(who: &T::AccountId, currency_id: CurrencyIdOf<T>, shares: BalanceOf<T>) {
+		let pool_id = PoolId::DexLiquidity(currency_id);
+		let staked_shares = Self::shares(who, pool_id);
+		if staked_shares > shares {
+			if let Some(share_currency_id) = T::DexShareCurrencyIds::convert(currency_id) {
+				let _ = T::Currency::withdraw(share_currency_id, &Self::account_id(), staked_shares - shares);
+			}
+			Self::update_shares(who, pool_id, shares);
+		}
+	}
+}
+
+impl<T: Trait> OnEmergencyShutdown for Module<T> {
+	fn on_emergency_shutdown() {
+		<IsShutdown>::put(true);
+	}
+}
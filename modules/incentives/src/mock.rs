@@ -0,0 +1,119 @@
+//! Mocks for the incentives module.
+
+#![cfg(test)]
+
+use frame_support::{impl_outer_origin, parameter_types};
+use primitives::H256;
+use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+
+use super::*;
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+
+// Workaround for https://github.com/rust-lang/rust/issues/26925 . Remove when sorted.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+	pub const GetNativeCurrencyId: CurrencyId = ACA;
+	pub const MaxVestingSchedules: u32 = 3;
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
+}
+
+pub type AccountId = u64;
+pub type BlockNumber = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
+pub type Balance = u64;
+pub type Amount = i64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CAROL: AccountId = 3;
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
+pub const DOT_SHARE: CurrencyId = CurrencyId::DexShare(2);
+
+impl system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type ModuleToIndex = ();
+}
+pub type System = system::Module<Runtime>;
+
+impl orml_tokens::Trait for Runtime {
+	type Event = ();
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+impl module_vesting::Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type VestingCurrencyId = GetNativeCurrencyId;
+	type MaxVestingSchedules = MaxVestingSchedules;
+}
+pub type VestingModule = module_vesting::Module<Runtime>;
+
+impl Trait for Runtime {
+	type Event = ();
+	type Currency = Tokens;
+	type RewardCurrencyId = GetNativeCurrencyId;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type Vesting = VestingModule;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+}
+pub type IncentivesModule = Module<Runtime>;
+
+pub struct ExtBuilder {
+	currency_ids: Vec<CurrencyId>,
+	endowed_accounts: Vec<AccountId>,
+	initial_balance: Balance,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			currency_ids: vec![ACA],
+			endowed_accounts: vec![],
+			initial_balance: 0,
+		}
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> runtime_io::TestExternalities {
+		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+		orml_tokens::GenesisConfig::<Runtime> {
+			tokens: self.currency_ids,
+			initial_balance: self.initial_balance,
+			endowed_accounts: self.endowed_accounts,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		t.into()
+	}
+}
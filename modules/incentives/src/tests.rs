@@ -0,0 +1,361 @@
+//! Unit tests for the incentives module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{
+	ExtBuilder, IncentivesModule, Origin, Runtime, System, Tokens, VestingModule, ACA, ALICE, AUSD, BOB, BTC, CAROL,
+	DOT, DOT_SHARE,
+};
+use sp_runtime::traits::OnFinalize;
+
+#[test]
+fn set_reward_per_block_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(IncentivesModule::set_reward_per_block(Origin::signed(ALICE), BTC, 100).is_err());
+		assert_noop!(
+			IncentivesModule::set_reward_per_block(Origin::ROOT, AUSD, 100),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		assert_eq!(IncentivesModule::reward_per_block(PoolId::Loans(BTC)), 100);
+		assert_eq!(IncentivesModule::reward_per_block(PoolId::Loans(DOT)), 0);
+	});
+}
+
+#[test]
+fn on_update_loan_update_shares() {
+	ExtBuilder::default().build().execute_with(|| {
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_update_loan(&BOB, BTC, 50);
+		assert_eq!(IncentivesModule::shares(ALICE, PoolId::Loans(BTC)), 100);
+		assert_eq!(IncentivesModule::total_shares(PoolId::Loans(BTC)), 150);
+
+		IncentivesModule::on_update_loan(&ALICE, BTC, 20);
+		assert_eq!(IncentivesModule::shares(ALICE, PoolId::Loans(BTC)), 20);
+		assert_eq!(IncentivesModule::total_shares(PoolId::Loans(BTC)), 70);
+
+		IncentivesModule::on_update_loan(&BOB, BTC, 0);
+		assert_eq!(IncentivesModule::shares(BOB, PoolId::Loans(BTC)), 0);
+		assert_eq!(IncentivesModule::total_shares(PoolId::Loans(BTC)), 20);
+		assert_eq!(IncentivesModule::total_shares(PoolId::Loans(DOT)), 0);
+	});
+}
+
+#[test]
+fn no_emission_without_loans() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		IncentivesModule::on_finalize(1);
+		assert_eq!(IncentivesModule::reward_index(PoolId::Loans(BTC)), Ratio::from_parts(0));
+
+		// the reward of past blocks isn't owed to new loans
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 0);
+		IncentivesModule::on_finalize(2);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 100);
+
+		// loans of collateral without emission earn nothing
+		IncentivesModule::on_update_loan(&ALICE, DOT, 100);
+		IncentivesModule::on_finalize(3);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(DOT)), 0);
+	});
+}
+
+#[test]
+fn accrue_rewards_with_joins_and_exits() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+
+		// block 1: alice alone
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_finalize(1);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 100);
+
+		// block 2 to 4: bob joins with the same shares
+		IncentivesModule::on_update_loan(&BOB, BTC, 100);
+		for block_number in 2..5 {
+			IncentivesModule::on_finalize(block_number);
+		}
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 250);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 150);
+
+		// block 5: alice exits, her rewards are kept
+		IncentivesModule::on_update_loan(&ALICE, BTC, 0);
+		IncentivesModule::on_finalize(5);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 250);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 250);
+
+		// block 6: carol joins with three times the shares of bob
+		IncentivesModule::on_update_loan(&CAROL, BTC, 300);
+		IncentivesModule::on_finalize(6);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 250);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 275);
+		assert_eq!(IncentivesModule::pending_rewards(&CAROL, PoolId::Loans(BTC)), 75);
+
+		// block 7 to 8: bob ups the shares after the rate halves
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 50));
+		IncentivesModule::on_update_loan(&BOB, BTC, 200);
+		IncentivesModule::on_finalize(7);
+		IncentivesModule::on_finalize(8);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 315);
+		assert_eq!(IncentivesModule::pending_rewards(&CAROL, PoolId::Loans(BTC)), 135);
+
+		// every reward emitted is owed to someone
+		assert_eq!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC))
+				+ IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC))
+				+ IncentivesModule::pending_rewards(&CAROL, PoolId::Loans(BTC)),
+			700
+		);
+	});
+}
+
+#[test]
+fn reward_debt_is_rounded_up() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+
+		// block 1: the index of the shares of alice is a third of the reward
+		IncentivesModule::on_update_loan(&ALICE, BTC, 3);
+		IncentivesModule::on_finalize(1);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 99);
+
+		// block 2: bob joins with a debt of 33.33.. rounded up, so he's owed nothing of the rewards before he joined
+		IncentivesModule::on_update_loan(&BOB, BTC, 1);
+		assert_eq!(IncentivesModule::reward_debt(BOB, PoolId::Loans(BTC)), 34);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 0);
+		IncentivesModule::on_finalize(2);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 174);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC)), 24);
+
+		// the rounding dust is never paid
+		assert!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC))
+				+ IncentivesModule::pending_rewards(&BOB, PoolId::Loans(BTC))
+				<= 200
+		);
+	});
+}
+
+#[test]
+fn claim_rewards_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_update_loan(&BOB, BTC, 300);
+		IncentivesModule::on_finalize(1);
+		IncentivesModule::on_finalize(2);
+
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 50);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 0);
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 50);
+
+		IncentivesModule::on_finalize(3);
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(BOB), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 75);
+		assert_eq!(Tokens::balance(ACA, &BOB), 225);
+		assert_eq!(Tokens::total_issuance(ACA), 300);
+	});
+}
+
+#[test]
+fn set_reward_vesting_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(IncentivesModule::set_reward_vesting(Origin::signed(ALICE), PoolId::Loans(BTC), 10, 100).is_err());
+		assert_noop!(
+			IncentivesModule::set_reward_vesting(Origin::ROOT, PoolId::Loans(AUSD), 10, 100),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+		assert_ok!(IncentivesModule::set_reward_vesting(
+			Origin::ROOT,
+			PoolId::Loans(BTC),
+			10,
+			100
+		));
+		assert_ok!(IncentivesModule::set_reward_vesting(
+			Origin::ROOT,
+			PoolId::DexLiquidity(AUSD),
+			0,
+			50
+		));
+		assert_eq!(IncentivesModule::reward_vesting(PoolId::Loans(BTC)), (10, 100));
+		assert_eq!(IncentivesModule::reward_vesting(PoolId::DexLiquidity(AUSD)), (0, 50));
+		assert_eq!(IncentivesModule::reward_vesting(PoolId::Loans(DOT)), (0, 0));
+
+		assert_ok!(IncentivesModule::set_reward_vesting(
+			Origin::ROOT,
+			PoolId::Loans(BTC),
+			0,
+			0
+		));
+		assert_eq!(IncentivesModule::reward_vesting(PoolId::Loans(BTC)), (0, 0));
+	});
+}
+
+#[test]
+fn claim_rewards_with_vesting() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		assert_ok!(IncentivesModule::set_reward_vesting(
+			Origin::ROOT,
+			PoolId::Loans(BTC),
+			2,
+			4
+		));
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_finalize(1);
+
+		// vested instead of paid
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 0);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 0);
+		assert_eq!(
+			VestingModule::vesting_schedules(ALICE),
+			vec![module_vesting::VestingSchedule {
+				start: 3,
+				duration: 4,
+				total: 100,
+				claimed: 0,
+			}]
+		);
+
+		System::set_block_number(5);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 50);
+
+		// pools without vesting are still paid at once
+		assert_ok!(Tokens::deposit(DOT_SHARE, &ALICE, 100));
+		assert_ok!(IncentivesModule::stake_lp_shares(Origin::signed(ALICE), DOT, 100));
+		assert_ok!(IncentivesModule::set_lp_reward_per_block(Origin::ROOT, DOT, 20));
+		IncentivesModule::on_finalize(5);
+		assert_ok!(IncentivesModule::claim_lp_rewards(Origin::signed(ALICE), DOT));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 70);
+	});
+}
+
+#[test]
+fn claim_rewards_paid_at_once_after_emergency_shutdown() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		assert_ok!(IncentivesModule::set_reward_vesting(
+			Origin::ROOT,
+			PoolId::Loans(BTC),
+			10,
+			10
+		));
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_finalize(1);
+
+		IncentivesModule::on_emergency_shutdown();
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 100);
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
+
+#[test]
+fn no_emission_after_emergency_shutdown() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(IncentivesModule::set_reward_per_block(Origin::ROOT, BTC, 100));
+		IncentivesModule::on_update_loan(&ALICE, BTC, 100);
+		IncentivesModule::on_finalize(1);
+
+		IncentivesModule::on_emergency_shutdown();
+		assert_eq!(IncentivesModule::is_shutdown(), true);
+		IncentivesModule::on_finalize(2);
+		assert_eq!(IncentivesModule::pending_rewards(&ALICE, PoolId::Loans(BTC)), 100);
+
+		// the rewards earned before shutdown can still be claimed
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(ALICE), BTC));
+		assert_eq!(Tokens::balance(ACA, &ALICE), 100);
+	});
+}
+
+#[test]
+fn accrue_lp_rewards_with_staked_shares() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert!(IncentivesModule::set_lp_reward_per_block(Origin::signed(ALICE), DOT, 100).is_err());
+		assert_ok!(IncentivesModule::set_lp_reward_per_block(Origin::ROOT, DOT, 100));
+		assert_eq!(IncentivesModule::reward_per_block(PoolId::DexLiquidity(DOT)), 100);
+		assert_ok!(Tokens::deposit(DOT_SHARE, &ALICE, 200));
+		assert_ok!(Tokens::deposit(DOT_SHARE, &BOB, 100));
+		assert!(IncentivesModule::stake_lp_shares(Origin::signed(ALICE), DOT, 201).is_err());
+		assert_noop!(
+			IncentivesModule::stake_lp_shares(Origin::signed(ALICE), DOT_SHARE, 100),
+			Error::<Runtime>::InvalidCurrencyId,
+		);
+
+		// block 1: alice stakes half of her shares, the rest earns nothing
+		assert_ok!(IncentivesModule::stake_lp_shares(Origin::signed(ALICE), DOT, 100));
+		assert_eq!(Tokens::balance(DOT_SHARE, &ALICE), 100);
+		assert_eq!(Tokens::balance(DOT_SHARE, &IncentivesModule::account_id()), 100);
+		assert_eq!(IncentivesModule::shares(ALICE, PoolId::DexLiquidity(DOT)), 100);
+		assert_eq!(IncentivesModule::total_shares(PoolId::DexLiquidity(DOT)), 100);
+		// the shares updated by the dex don't change the staked shares
+		IncentivesModule::on_update_shares(&ALICE, DOT, 200);
+		assert_eq!(IncentivesModule::shares(ALICE, PoolId::DexLiquidity(DOT)), 100);
+		IncentivesModule::on_finalize(1);
+
+		// block 2: bob stakes
+		assert_ok!(IncentivesModule::stake_lp_shares(Origin::signed(BOB), DOT, 100));
+		IncentivesModule::on_finalize(2);
+		assert_eq!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::DexLiquidity(DOT)),
+			150
+		);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::DexLiquidity(DOT)), 50);
+
+		// block 3: alice unstakes half of the staked shares and transfers 150 shares to carol, who stakes 50
+		assert_noop!(
+			IncentivesModule::unstake_lp_shares(Origin::signed(ALICE), DOT, 101),
+			Error::<Runtime>::StakedSharesNotEnough,
+		);
+		assert_ok!(IncentivesModule::unstake_lp_shares(Origin::signed(ALICE), DOT, 50));
+		assert_eq!(Tokens::balance(DOT_SHARE, &ALICE), 150);
+		assert_ok!(Tokens::transfer(DOT_SHARE, &ALICE, &CAROL, 150));
+		assert_ok!(IncentivesModule::stake_lp_shares(Origin::signed(CAROL), DOT, 50));
+		assert_eq!(IncentivesModule::total_shares(PoolId::DexLiquidity(DOT)), 200);
+		assert_eq!(IncentivesModule::pending_rewards(&CAROL, PoolId::DexLiquidity(DOT)), 0);
+		IncentivesModule::on_finalize(3);
+		assert_eq!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::DexLiquidity(DOT)),
+			175
+		);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::DexLiquidity(DOT)), 100);
+		assert_eq!(IncentivesModule::pending_rewards(&CAROL, PoolId::DexLiquidity(DOT)), 25);
+
+		// block 4: the pool is closed and bob is refunded, his staked share currency is burnt
+		IncentivesModule::on_update_shares(&BOB, DOT, 0);
+		assert_eq!(IncentivesModule::shares(BOB, PoolId::DexLiquidity(DOT)), 0);
+		assert_eq!(Tokens::balance(DOT_SHARE, &IncentivesModule::account_id()), 100);
+		IncentivesModule::on_finalize(4);
+		assert_eq!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::DexLiquidity(DOT)),
+			225
+		);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::DexLiquidity(DOT)), 100);
+		assert_eq!(IncentivesModule::pending_rewards(&CAROL, PoolId::DexLiquidity(DOT)), 75);
+
+		// liquidity rewards are claimed separately from loans rewards
+		assert_ok!(IncentivesModule::claim_rewards(Origin::signed(BOB), DOT));
+		assert_eq!(Tokens::balance(ACA, &BOB), 0);
+		assert_ok!(IncentivesModule::claim_lp_rewards(Origin::signed(BOB), DOT));
+		assert_eq!(Tokens::balance(ACA, &BOB), 100);
+		assert_eq!(IncentivesModule::pending_rewards(&BOB, PoolId::DexLiquidity(DOT)), 0);
+
+		// stop the emission
+		assert_ok!(IncentivesModule::set_lp_reward_per_block(Origin::ROOT, DOT, 0));
+		IncentivesModule::on_finalize(5);
+		assert_eq!(
+			IncentivesModule::pending_rewards(&ALICE, PoolId::DexLiquidity(DOT)),
+			225
+		);
+	});
+}
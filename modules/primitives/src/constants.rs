@@ -0,0 +1,31 @@
+//! Currency precisions and balance helpers shared by genesis configs and tests.
+
+use crate::{Balance, CurrencyId};
+
+/// Prices are quoted per unit of balance, so every currency uses the same precision to keep them comparable.
+pub const DEFAULT_DECIMALS: u32 = 18;
+
+impl CurrencyId {
+	pub fn decimals(&self) -> u32 {
+		match self {
+			CurrencyId::ACA | CurrencyId::AUSD | CurrencyId::DOT | CurrencyId::BTC | CurrencyId::Token(_) => {
+				DEFAULT_DECIMALS
+			}
+			// the initial shares of a pool are the larger of its amounts
+			CurrencyId::DexShare(_) => DEFAULT_DECIMALS,
+		}
+	}
+}
+
+/// One whole unit of `currency_id`.
+pub fn dollar(currency_id: CurrencyId) -> Balance {
+	10u128.pow(currency_id.decimals())
+}
+
+pub fn cent(currency_id: CurrencyId) -> Balance {
+	dollar(currency_id) / 100
+}
+
+pub fn millicent(currency_id: CurrencyId) -> Balance {
+	cent(currency_id) / 1000
+}
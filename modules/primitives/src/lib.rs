@@ -1,24 +1,103 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use sp_runtime::RuntimeDebug;
+use rstd::convert::TryFrom;
+use sp_runtime::{traits::Convert, RuntimeDebug};
 
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
+pub mod constants;
+mod tests;
+
+/// Balance of an account.
+pub type Balance = u128;
+
 #[derive(Encode, Decode, Eq, PartialEq, Copy, Clone, RuntimeDebug)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum TokensCurrencyId {
 	AUSD = 1,
 	DOT,
-	XBTC,
+	BTC,
 }
 
-#[derive(Encode, Decode, Eq, PartialEq, Copy, Clone, RuntimeDebug)]
+#[derive(Encode, Decode, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, RuntimeDebug)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum CurrencyId {
-	ACA = 0,
+	ACA,
 	AUSD,
 	DOT,
-	XBTC,
+	BTC,
+	Token(u8),
+	/// The shares of the dex pool of the currency with the `u32` id.
+	DexShare(u32),
+}
+
+/// The first `u32` id of `CurrencyId::Token`, ids from here on map to `Token(id - TOKEN_ID_OFFSET)`.
+pub const TOKEN_ID_OFFSET: u32 = 256;
+
+/// The first `u32` id of `CurrencyId::DexShare`, ids from here on map to `DexShare(id - DEX_SHARE_ID_OFFSET)`.
+pub const DEX_SHARE_ID_OFFSET: u32 = 1 << 24;
+
+impl CurrencyId {
+	/// The currency of the shares of the dex pool of this currency, `None` for a share currency, which has no pool.
+	pub fn dex_share_currency_id(self) -> Option<CurrencyId> {
+		match self {
+			CurrencyId::DexShare(_) => None,
+			currency_id => Some(CurrencyId::DexShare(u32::from(currency_id))),
+		}
+	}
+
+	/// Whether this is the currency of the shares of a dex pool.
+	pub fn is_dex_share_currency_id(&self) -> bool {
+		if let CurrencyId::DexShare(_) = self {
+			true
+		} else {
+			false
+		}
+	}
+}
+
+/// Converts the currency of a dex pool to the currency of its shares.
+pub struct DexShareCurrencyIds;
+
+impl Convert<CurrencyId, Option<CurrencyId>> for DexShareCurrencyIds {
+	fn convert(currency_id: CurrencyId) -> Option<CurrencyId> {
+		currency_id.dex_share_currency_id()
+	}
+}
+
+impl TryFrom<u32> for CurrencyId {
+	type Error = ();
+
+	fn try_from(id: u32) -> Result<Self, Self::Error> {
+		match id {
+			0 => Ok(CurrencyId::ACA),
+			1 => Ok(CurrencyId::AUSD),
+			2 => Ok(CurrencyId::DOT),
+			3 => Ok(CurrencyId::BTC),
+			// only the shares of the currencies with pools
+			_ if id >= DEX_SHARE_ID_OFFSET => {
+				let pool_currency_id = CurrencyId::try_from(id - DEX_SHARE_ID_OFFSET)?;
+				pool_currency_id.dex_share_currency_id().ok_or(())
+			}
+			_ => {
+				let token_id = id.checked_sub(TOKEN_ID_OFFSET).ok_or(())?;
+				u8::try_from(token_id).map(CurrencyId::Token).map_err(|_| ())
+			}
+		}
+	}
+}
+
+impl From<CurrencyId> for u32 {
+	fn from(currency_id: CurrencyId) -> u32 {
+		match currency_id {
+			CurrencyId::ACA => 0,
+			CurrencyId::AUSD => 1,
+			CurrencyId::DOT => 2,
+			CurrencyId::BTC => 3,
+			CurrencyId::Token(token_id) => TOKEN_ID_OFFSET + u32::from(token_id),
+			CurrencyId::DexShare(pool_currency_id) => DEX_SHARE_ID_OFFSET.saturating_add(pool_currency_id),
+		}
+	}
 }
@@ -0,0 +1,59 @@
+//! Unit tests for the primitives.
+
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn currency_id_u32_conversion_work() {
+	for currency_id in vec![
+		CurrencyId::ACA,
+		CurrencyId::AUSD,
+		CurrencyId::DOT,
+		CurrencyId::BTC,
+		CurrencyId::Token(0),
+		CurrencyId::Token(255),
+		CurrencyId::DexShare(u32::from(CurrencyId::BTC)),
+		CurrencyId::DexShare(u32::from(CurrencyId::Token(255))),
+	] {
+		assert_eq!(CurrencyId::try_from(u32::from(currency_id)), Ok(currency_id));
+	}
+	assert_eq!(u32::from(CurrencyId::Token(1)), 257);
+	assert_eq!(CurrencyId::try_from(4), Err(()));
+	assert_eq!(CurrencyId::try_from(TOKEN_ID_OFFSET + 256), Err(()));
+	assert_eq!(u32::from(CurrencyId::DexShare(3)), DEX_SHARE_ID_OFFSET + 3);
+	// neither the shares of unknown currencies nor the shares of shares
+	assert_eq!(CurrencyId::try_from(DEX_SHARE_ID_OFFSET + 4), Err(()));
+	assert_eq!(CurrencyId::try_from(DEX_SHARE_ID_OFFSET + DEX_SHARE_ID_OFFSET), Err(()));
+}
+
+#[test]
+fn dex_share_currency_id_work() {
+	assert_eq!(CurrencyId::BTC.dex_share_currency_id(), Some(CurrencyId::DexShare(3)));
+	assert_eq!(
+		DexShareCurrencyIds::convert(CurrencyId::Token(1)),
+		Some(CurrencyId::DexShare(257))
+	);
+	assert_eq!(CurrencyId::DexShare(3).dex_share_currency_id(), None);
+	assert!(CurrencyId::DexShare(3).is_dex_share_currency_id());
+	assert!(!CurrencyId::BTC.is_dex_share_currency_id());
+}
+
+#[test]
+fn currency_id_ordering_work() {
+	assert!(CurrencyId::ACA < CurrencyId::AUSD);
+	assert!(CurrencyId::BTC < CurrencyId::Token(0));
+	assert!(CurrencyId::Token(0) < CurrencyId::Token(1));
+}
+
+#[test]
+fn currency_balance_helpers_work() {
+	assert_eq!(constants::dollar(CurrencyId::AUSD), 1_000_000_000_000_000_000);
+	assert_eq!(constants::cent(CurrencyId::AUSD), 10_000_000_000_000_000);
+	assert_eq!(constants::millicent(CurrencyId::AUSD), 10_000_000_000_000);
+	assert_eq!(constants::dollar(CurrencyId::DOT), constants::dollar(CurrencyId::BTC));
+	assert_eq!(
+		constants::dollar(CurrencyId::Token(1)),
+		constants::dollar(CurrencyId::ACA)
+	);
+}
@@ -0,0 +1,67 @@
+//! Std-only audits of the module accounting, checking the counters of a module against the balances and the items
+//! they stand for.
+
+use sp_runtime::traits::UniqueSaturatedInto;
+
+/// An accounting invariant found broken by an audit.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvariantViolation<CurrencyId> {
+	/// Name of the broken invariant, prefixed by the module.
+	pub invariant: &'static str,
+	pub currency_id: CurrencyId,
+	/// The amount recorded by the counter.
+	pub recorded: u128,
+	/// The amount of the balances or items the counter stands for.
+	pub actual: u128,
+}
+
+/// All the invariants found broken, `Ok` if there is none.
+pub type AuditResult<CurrencyId> = Result<(), Vec<InvariantViolation<CurrencyId>>>;
+
+/// Collects the violations found by the checks of an audit.
+pub struct Auditor<CurrencyId> {
+	violations: Vec<InvariantViolation<CurrencyId>>,
+}
+
+impl<CurrencyId> Default for Auditor<CurrencyId> {
+	fn default() -> Self {
+		Auditor { violations: vec![] }
+	}
+}
+
+impl<CurrencyId> Auditor<CurrencyId> {
+	/// Record a violation of `invariant` unless `recorded` equals `actual`.
+	pub fn check<Balance: UniqueSaturatedInto<u128>>(
+		&mut self,
+		invariant: &'static str,
+		currency_id: CurrencyId,
+		recorded: Balance,
+		actual: Balance,
+	) {
+		let recorded = recorded.unique_saturated_into();
+		let actual = actual.unique_saturated_into();
+		if recorded != actual {
+			self.violations.push(InvariantViolation {
+				invariant,
+				currency_id,
+				recorded,
+				actual,
+			});
+		}
+	}
+
+	/// Record the violations found by another audit.
+	pub fn merge(&mut self, result: AuditResult<CurrencyId>) {
+		if let Err(violations) = result {
+			self.violations.extend(violations);
+		}
+	}
+
+	pub fn finish(self) -> AuditResult<CurrencyId> {
+		if self.violations.is_empty() {
+			Ok(())
+		} else {
+			Err(self.violations)
+		}
+	}
+}
@@ -1,52 +1,430 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
+use frame_support::{weights::Weight, Parameter};
+use orml_traits::PriceProvider;
 use orml_utilities::FixedU128;
+use rstd::{
+	convert::{TryFrom, TryInto},
+	marker,
+	prelude::*,
+};
+use sp_runtime::{
+	traits::{Bounded, Member, SimpleArithmetic, Zero},
+	DispatchError, DispatchResult, RuntimeDebug,
+};
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+mod audit;
+mod tests;
+mod unsigned;
+
+#[cfg(feature = "std")]
+pub use unsigned::unsigned_pool_order;
+pub use unsigned::{
+	unsigned_keeper_transaction, unsigned_urgent_priority, UNSIGNED_KEEPER_LONGEVITY, UNSIGNED_REPORT_PRIORITY,
+};
+
+#[cfg(feature = "std")]
+pub use audit::{AuditResult, Auditor, InvariantViolation};
 
 pub type Price = FixedU128;
 pub type ExchangeRate = FixedU128;
 pub type Ratio = FixedU128;
 pub type Rate = FixedU128;
 
-pub trait RiskManager<AccountId, CurrencyId, Amount, DebitAmount> {
-	type Error: Into<&'static str>;
+/// Weight of a storage read or write, for the weight consumed by storage migrations.
+pub const STORAGE_ACCESS_WEIGHT: Weight = 1_000;
+
+/// The collateral and debit of a CDP.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, Default, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Position<Balance, DebitBalance> {
+	pub collateral: Balance,
+	pub debit: DebitBalance,
+}
+
+impl<Balance: Zero, DebitBalance: Zero> Position<Balance, DebitBalance> {
+	pub fn new(collateral: Balance, debit: DebitBalance) -> Self {
+		Position { collateral, debit }
+	}
+
+	/// A position without collateral and debit, which doesn't need to be kept in storage.
+	pub fn is_empty(&self) -> bool {
+		self.collateral.is_zero() && self.debit.is_zero()
+	}
+}
+
+/// The neighbours of an item of a list linked through a storage map, so the list can be walked from any of its
+/// items and an item can be unlinked without reading the others.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug)]
+pub struct Linkage<Key> {
+	pub previous: Option<Key>,
+	pub next: Option<Key>,
+}
+
+/// A pair of currencies, always stored in ascending order so that both directions share one key.
+#[derive(Encode, Decode, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TradingPair<CurrencyId>(CurrencyId, CurrencyId);
+
+impl<CurrencyId: Ord + Copy> TradingPair<CurrencyId> {
+	pub fn new(currency_id_a: CurrencyId, currency_id_b: CurrencyId) -> Self {
+		if currency_id_a > currency_id_b {
+			TradingPair(currency_id_b, currency_id_a)
+		} else {
+			TradingPair(currency_id_a, currency_id_b)
+		}
+	}
+
+	/// Same as `new`, but `None` if both currencies are the same.
+	pub fn from_currency_ids(currency_id_a: CurrencyId, currency_id_b: CurrencyId) -> Option<Self> {
+		if currency_id_a == currency_id_b {
+			None
+		} else {
+			Some(Self::new(currency_id_a, currency_id_b))
+		}
+	}
+
+	pub fn first(&self) -> CurrencyId {
+		self.0
+	}
+
+	pub fn second(&self) -> CurrencyId {
+		self.1
+	}
+
+	pub fn contains(&self, currency_id: CurrencyId) -> bool {
+		currency_id == self.0 || currency_id == self.1
+	}
+}
+
+/// Convenience constructors for `FixedU128` based rates and ratios.
+pub trait FixedU128Ext: Sized {
+	/// `percent / 100`, exact.
+	fn from_percent(percent: u32) -> Self;
+
+	/// `permill / 1_000_000`, exact.
+	fn from_permill(permill: u32) -> Self;
+
+	/// `basis_points / 10_000`, exact.
+	fn from_basis_points(basis_points: u32) -> Self;
+
+	/// Split an annual rate evenly across `blocks_per_year` blocks without compounding.
+	/// The result is rounded down to the nearest representable value, and is zero when
+	/// `blocks_per_year` is zero.
+	fn from_apr_to_per_block(apr: Self, blocks_per_year: u32) -> Self;
+
+	/// `numerator / denominator`, `None` if the denominator is zero or either side doesn't fit in `u128`.
+	fn checked_from_rational<N: TryInto<u128>>(numerator: N, denominator: N) -> Option<Self>;
 
+	fn is_more_than(&self, other: &Self) -> bool;
+
+	/// `self - other`, zero if `other` is larger than `self`.
+	fn saturating_sub(&self, other: &Self) -> Self;
+
+	/// `self * other` rounded up, `None` on overflow.
+	fn checked_mul_int_round_up<N: TryFrom<u128> + TryInto<u128> + Copy>(&self, other: &N) -> Option<N>;
+}
+
+impl FixedU128Ext for FixedU128 {
+	fn from_percent(percent: u32) -> Self {
+		FixedU128::from_rational(percent.into(), 100)
+	}
+
+	fn from_permill(permill: u32) -> Self {
+		FixedU128::from_rational(permill.into(), 1_000_000)
+	}
+
+	fn from_basis_points(basis_points: u32) -> Self {
+		FixedU128::from_rational(basis_points.into(), 10_000)
+	}
+
+	fn from_apr_to_per_block(apr: Self, blocks_per_year: u32) -> Self {
+		FixedU128::from_parts(
+			apr.deconstruct()
+				.checked_div(blocks_per_year.into())
+				.unwrap_or_default(),
+		)
+	}
+
+	fn checked_from_rational<N: TryInto<u128>>(numerator: N, denominator: N) -> Option<Self> {
+		let numerator = TryInto::<u128>::try_into(numerator).ok()?;
+		let denominator = TryInto::<u128>::try_into(denominator).ok()?;
+		if denominator == 0 {
+			None
+		} else {
+			Some(FixedU128::from_rational(numerator, denominator))
+		}
+	}
+
+	fn is_more_than(&self, other: &Self) -> bool {
+		self > other
+	}
+
+	fn saturating_sub(&self, other: &Self) -> Self {
+		self.checked_sub(other).unwrap_or_else(|| FixedU128::from_parts(0))
+	}
+
+	fn checked_mul_int_round_up<N: TryFrom<u128> + TryInto<u128> + Copy>(&self, other: &N) -> Option<N> {
+		let other = TryInto::<u128>::try_into(*other).ok()?;
+		let floor = self.checked_mul_int(&other)?;
+		let accuracy = FixedU128::accuracy();
+		// the fractional part of the result is (self * other) mod 1
+		let remainder = (self.deconstruct() % accuracy) * (other % accuracy) % accuracy;
+		let result = if remainder != 0 { floor.checked_add(1)? } else { floor };
+		TryFrom::try_from(result).ok()
+	}
+}
+
+pub trait RiskManager<AccountId, CurrencyId, Amount, DebitAmount> {
 	fn check_position_adjustment(
 		account_id: &AccountId,
 		currency_id: CurrencyId,
 		collaterals: Amount,
 		debits: DebitAmount,
-	) -> Result<(), Self::Error>;
+	) -> DispatchResult;
 
-	fn check_debit_cap(currency_id: CurrencyId, debits: DebitAmount) -> Result<(), Self::Error>;
+	fn check_debit_cap(currency_id: CurrencyId, debits: DebitAmount) -> DispatchResult;
 }
 
 pub trait AuctionManager<AccountId> {
 	type CurrencyId;
 	type Balance;
-	type Amount;
-
-	fn increase_surplus(increment: Self::Balance);
+	type AuctionId: Parameter + Member + Copy;
 
+	/// Create collateral auctions for `amount` of `currency_id` held by the treasury, split into lots
+	/// if necessary, and return the ids of the created auctions.
 	fn new_collateral_auction(
 		who: AccountId,
 		currency_id: Self::CurrencyId,
 		amount: Self::Balance,
 		target: Self::Balance,
-		bad_debt: Self::Balance,
-	);
+	) -> Vec<Self::AuctionId>;
+
+	/// Auction `amount` of the treasury surplus for native currency.
+	fn new_surplus_auction(amount: Self::Balance) -> Option<Self::AuctionId>;
+
+	/// Auction newly issued native currency, starting from `initial_amount`, for `fix_debit` of stable coin.
+	fn new_debit_auction(initial_amount: Self::Balance, fix_debit: Self::Balance) -> Option<Self::AuctionId>;
+
+	fn cancel_auction(id: Self::AuctionId) -> DispatchResult;
+
+	fn get_total_collateral_in_auction(currency_id: Self::CurrencyId) -> Self::Balance;
+
+	fn get_total_surplus_in_auction() -> Self::Balance;
+
+	fn get_total_debit_in_auction() -> Self::Balance;
+}
+
+pub trait AuctionManagerExtended<AccountId>: AuctionManager<AccountId> {
+	/// Cancel at most `max_per_block` auctions, returns how many of them have been cancelled.
+	/// Called every block until `active_auction_count` is zero to drain the auctions across blocks.
+	fn emergency_cancel_all(max_per_block: u32) -> u32;
+
+	fn active_auction_count() -> u32;
+}
+
+/// The treasury of the CDP system, owning its surplus, bad debt and collateral.
+pub trait CDPTreasury<AccountId> {
+	type CurrencyId;
+	type Balance;
+
+	fn get_surplus_pool() -> Self::Balance;
+
+	fn get_debit_pool() -> Self::Balance;
+
+	fn get_total_collaterals(currency_id: Self::CurrencyId) -> Self::Balance;
+
+	/// Record bad debt of the system, which should be offset by surplus later.
+	fn on_system_debit(amount: Self::Balance) -> DispatchResult;
+
+	/// Issue stable coin to the treasury as surplus, e.g. the stability fee.
+	fn on_system_surplus(amount: Self::Balance) -> DispatchResult;
+
+	/// Issue stable coin to `who` and record it as bad debt.
+	fn issue_debit(who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Transfer stable coin from `from` to the treasury as surplus.
+	fn deposit_surplus(from: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Transfer stable coin from the surplus of the treasury to `to`.
+	fn withdraw_surplus(to: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Transfer collateral from `from` to the treasury.
+	fn deposit_collateral(from: &AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult;
+
+	/// Transfer collateral of the treasury to `to`.
+	fn withdraw_collateral(to: &AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult;
+}
+
+pub trait EmergencyShutdown {
+	fn is_shutdown() -> bool;
+}
+
+/// Handler called once the system is shut down, implemented for tuples to fan out to every module.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnEmergencyShutdown {
+	fn on_emergency_shutdown();
+}
+
+/// Handler called after the debit of a loan is updated, with the new debit balance of `who`.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnUpdateLoan<AccountId, CurrencyId: Copy, Balance: Copy> {
+	fn on_update_loan(who: &AccountId, currency_id: CurrencyId, debit_balance: Balance);
+}
+
+/// Handler called after the liquidity shares of `who` in the pool of `currency_id` are updated, with the new
+/// share balance of `who`.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnUpdateShares<AccountId, CurrencyId: Copy, Share: Copy> {
+	fn on_update_shares(who: &AccountId, currency_id: CurrencyId, shares: Share);
+}
+
+/// Pay out to accounts gradually instead of at once.
+pub trait VestingManager<AccountId, Balance, BlockNumber> {
+	/// Vest `amount` to `who`, locked for `cliff` blocks from now and then released linearly over `duration`
+	/// blocks. The amounts already vesting to `who` aren't delayed by it.
+	fn vest(who: &AccountId, amount: Balance, cliff: BlockNumber, duration: BlockNumber) -> DispatchResult;
 }
 
 pub trait DexManager<AccountId, CurrencyId, Balance> {
-	type Error: Into<&'static str>;
+	type Share: Parameter + Member + SimpleArithmetic + Default + Copy;
 
+	/// The supply amount needed to swap for exactly `target_currency_amount`, fails if the pools can't
+	/// supply the target amount.
 	fn get_supply_amount(
 		supply_currency_id: CurrencyId,
 		target_currency_id: CurrencyId,
 		target_currency_amount: Balance,
-	) -> Balance;
+	) -> Result<Balance, DispatchError>;
+	/// The target amount received by swapping exactly `supply_currency_amount` right now, fails if a pool of
+	/// the swap is empty.
+	fn get_target_amount(
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		supply_currency_amount: Balance,
+	) -> Result<Balance, DispatchError>;
+	/// The supply amounts needed to swap for each of `target_currency_amounts` in turn, every swap against the
+	/// pools as the swaps before it leave them. Stops at the first target the pools can't supply.
+	fn get_supply_amounts_for_targets(
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		target_currency_amounts: Vec<Balance>,
+	) -> Vec<Balance>;
+	/// Swap exactly the supply amount for at least the target amount, returns the supply amount used and the
+	/// target amount received.
 	fn exchange_currency(
 		who: AccountId,
 		supply: (CurrencyId, Balance),
 		target: (CurrencyId, Balance),
-	) -> Result<(), Self::Error>;
+	) -> Result<(Balance, Balance), DispatchError>;
+	/// Swap at most the supply amount for exactly the target amount, returns the supply amount used. Fails without
+	/// swapping if the pools can't provide the target amount or it takes more than the max supply amount.
+	fn exchange_currency_with_exact_target(
+		who: AccountId,
+		supply: (CurrencyId, Balance),
+		target: (CurrencyId, Balance),
+	) -> Result<Balance, DispatchError>;
+	/// Add liquidity from `who` at no more than the max amounts, returns the other currency amount, the base
+	/// currency amount and the shares added.
+	fn add_liquidity(
+		who: &AccountId,
+		other_currency_id: CurrencyId,
+		max_other_currency_amount: Balance,
+		max_base_currency_amount: Balance,
+	) -> Result<(Balance, Balance, Self::Share), DispatchError>;
+	/// Withdraw `share_amount` of liquidity to `who`, returns the other currency amount and the base currency
+	/// amount withdrawn.
+	fn remove_liquidity(
+		who: &AccountId,
+		currency_id: CurrencyId,
+		share_amount: Self::Share,
+	) -> Result<(Balance, Balance), DispatchError>;
+}
+
+pub trait ExchangeRateProvider<CurrencyId> {
+	fn get_exchange_rate(currency_id: CurrencyId) -> ExchangeRate;
+}
+
+/// Convert debit to the stable coin it is worth, rounded in favour of the system: down for the stable coin paid
+/// out, up for the stable coin owed.
+pub trait DebitConvertor<CurrencyId, DebitBalance, Balance> {
+	/// The stable coin paid out for `debit_balance`, as when it is minted.
+	fn convert_debit_to_balance_floor(currency_id: CurrencyId, debit_balance: DebitBalance) -> Balance;
+
+	/// The stable coin owed for `debit_balance`, as when it is repaid or the collateral ratio is checked.
+	fn convert_debit_to_balance_ceil(currency_id: CurrencyId, debit_balance: DebitBalance) -> Balance;
+}
+
+/// Relative price and valuation helpers, available on every `PriceProvider` through the blanket impl.
+pub trait PriceProviderExtended<CurrencyId: PartialEq>: PriceProvider<CurrencyId, Price> {
+	/// The price of `quote` in terms of `base`, always one for the same currency.
+	fn get_relative_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
+		if base == quote {
+			Some(Price::from_natural(1))
+		} else {
+			Self::get_price(base, quote)
+		}
+	}
+
+	/// The value of `amount` of `currency_id` in terms of `base`, `None` if the price is missing or on overflow.
+	fn get_value<Balance>(base: CurrencyId, currency_id: CurrencyId, amount: Balance) -> Option<Balance>
+	where
+		Balance: TryInto<u128> + TryFrom<u128>,
+	{
+		let price = Self::get_relative_price(base, currency_id)?;
+		let amount = TryInto::<u128>::try_into(amount).ok()?;
+		price
+			.checked_mul_int(&amount)
+			.and_then(|value| TryFrom::<u128>::try_from(value).ok())
+	}
+}
+
+impl<CurrencyId: PartialEq, P: PriceProvider<CurrencyId, Price>> PriceProviderExtended<CurrencyId> for P {}
+
+/// Convert debit balance to stable coin balance with the exchange rate of `P`,
+/// saturating to the max value of `Balance` on overflow.
+pub struct DebitExchangeRateConvertor<P>(marker::PhantomData<P>);
+
+impl<P> DebitExchangeRateConvertor<P> {
+	fn convert<CurrencyId, DebitBalance, Balance>(
+		currency_id: CurrencyId,
+		debit_balance: DebitBalance,
+		round_up: bool,
+	) -> Balance
+	where
+		P: ExchangeRateProvider<CurrencyId>,
+		DebitBalance: TryInto<u128>,
+		Balance: TryFrom<u128> + Bounded,
+	{
+		let exchange_rate = P::get_exchange_rate(currency_id);
+		let debit_balance = TryInto::<u128>::try_into(debit_balance).unwrap_or(u128::max_value());
+		let balance = if round_up {
+			exchange_rate.checked_mul_int_round_up(&debit_balance)
+		} else {
+			exchange_rate.checked_mul_int(&debit_balance)
+		}
+		.unwrap_or(u128::max_value());
+		TryInto::<Balance>::try_into(balance).unwrap_or(Balance::max_value())
+	}
+}
+
+impl<P, CurrencyId, DebitBalance, Balance> DebitConvertor<CurrencyId, DebitBalance, Balance>
+	for DebitExchangeRateConvertor<P>
+where
+	P: ExchangeRateProvider<CurrencyId>,
+	DebitBalance: TryInto<u128>,
+	Balance: TryFrom<u128> + Bounded,
+{
+	fn convert_debit_to_balance_floor(currency_id: CurrencyId, debit_balance: DebitBalance) -> Balance {
+		Self::convert(currency_id, debit_balance, false)
+	}
+
+	fn convert_debit_to_balance_ceil(currency_id: CurrencyId, debit_balance: DebitBalance) -> Balance {
+		Self::convert(currency_id, debit_balance, true)
+	}
 }
@@ -0,0 +1,263 @@
+//! Unit tests for the support module.
+
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn from_percent_work() {
+	assert_eq!(Rate::from_percent(0), Rate::from_parts(0));
+	assert_eq!(Rate::from_percent(3), Rate::from_rational(3, 100));
+	assert_eq!(Ratio::from_percent(150), Ratio::from_rational(3, 2));
+}
+
+#[test]
+fn from_permill_work() {
+	assert_eq!(Rate::from_permill(1), Rate::from_parts(1_000_000_000_000));
+	assert_eq!(Rate::from_permill(1_000_000), Rate::from_natural(1));
+}
+
+#[test]
+fn from_basis_points_work() {
+	assert_eq!(Rate::from_basis_points(1), Rate::from_parts(100_000_000_000_000));
+	assert_eq!(Rate::from_basis_points(25), Rate::from_rational(25, 10_000));
+	assert_eq!(Rate::from_basis_points(10_000), Rate::from_percent(100));
+}
+
+#[test]
+fn from_apr_to_per_block_work() {
+	// 5% / 3 = 0.0166...6 is rounded down
+	assert_eq!(
+		Rate::from_apr_to_per_block(Rate::from_percent(5), 3),
+		Rate::from_parts(16_666_666_666_666_666)
+	);
+	assert_eq!(
+		Rate::from_apr_to_per_block(Rate::from_percent(10), 10),
+		Rate::from_percent(1)
+	);
+	assert_eq!(
+		Rate::from_apr_to_per_block(Rate::from_percent(5), 0),
+		Rate::from_parts(0)
+	);
+}
+
+#[test]
+fn checked_from_rational_work() {
+	assert_eq!(
+		Ratio::checked_from_rational(3u64, 2u64),
+		Some(Ratio::from_rational(3, 2))
+	);
+	assert_eq!(Ratio::checked_from_rational(0u64, 2u64), Some(Ratio::from_parts(0)));
+	assert_eq!(Ratio::checked_from_rational(3u64, 0u64), None);
+	assert_eq!(Ratio::checked_from_rational(0u64, 0u64), None);
+	assert_eq!(Ratio::checked_from_rational(-1i64, 2i64), None);
+}
+
+#[test]
+fn is_more_than_work() {
+	assert!(Ratio::from_percent(151).is_more_than(&Ratio::from_percent(150)));
+	assert!(!Ratio::from_percent(150).is_more_than(&Ratio::from_percent(150)));
+	assert!(!Ratio::from_percent(149).is_more_than(&Ratio::from_percent(150)));
+}
+
+#[test]
+fn saturating_sub_work() {
+	assert_eq!(
+		Ratio::from_percent(150).saturating_sub(&Ratio::from_percent(100)),
+		Ratio::from_percent(50)
+	);
+	assert_eq!(
+		Ratio::from_percent(100).saturating_sub(&Ratio::from_percent(150)),
+		Ratio::from_parts(0)
+	);
+}
+
+#[test]
+fn checked_mul_int_round_up_work() {
+	assert_eq!(Ratio::from_rational(1, 3).checked_mul_int(&3u64), Some(0));
+	assert_eq!(Ratio::from_rational(1, 3).checked_mul_int_round_up(&3u64), Some(1));
+	assert_eq!(Ratio::from_natural(2).checked_mul_int_round_up(&5u64), Some(10));
+	assert_eq!(Ratio::from_natural(0).checked_mul_int_round_up(&5u64), Some(0));
+	assert_eq!(Ratio::from_natural(2).checked_mul_int_round_up(&u64::max_value()), None);
+}
+
+#[test]
+fn unsigned_urgent_priority_work() {
+	let least_urgent = unsigned_urgent_priority(Ratio::from_parts(0));
+	assert_eq!(least_urgent, u64::max_value() / 2);
+	assert!(least_urgent > UNSIGNED_REPORT_PRIORITY);
+	assert!(unsigned_urgent_priority(Ratio::from_percent(10)) > least_urgent);
+	assert!(unsigned_urgent_priority(Ratio::from_percent(50)) > unsigned_urgent_priority(Ratio::from_percent(10)));
+	// capped at one
+	assert_eq!(
+		unsigned_urgent_priority(Ratio::from_natural(2)),
+		unsigned_urgent_priority(Ratio::from_natural(1))
+	);
+}
+
+#[test]
+fn unsigned_keeper_transaction_work() {
+	let validity = unsigned_keeper_transaction(UNSIGNED_REPORT_PRIORITY, (b"tag", 1u32));
+	assert_eq!(validity.priority, UNSIGNED_REPORT_PRIORITY);
+	assert!(validity.requires.is_empty());
+	assert_eq!(validity.provides, vec![(b"tag", 1u32).encode()]);
+	assert_eq!(validity.longevity, UNSIGNED_KEEPER_LONGEVITY);
+	assert!(validity.propagate);
+}
+
+#[test]
+fn position_is_empty_work() {
+	assert!(Position::<u64, u64>::default().is_empty());
+	assert!(Position::<u64, u64>::new(0, 0).is_empty());
+	assert!(!Position::<u64, u64>::new(1, 0).is_empty());
+	assert!(!Position::<u64, u64>::new(0, 1).is_empty());
+}
+
+#[test]
+fn trading_pair_canonical_ordering_work() {
+	let pair = TradingPair::new(3u32, 2u32);
+	assert_eq!(pair, TradingPair::new(2u32, 3u32));
+	assert_eq!(pair.first(), 2);
+	assert_eq!(pair.second(), 3);
+	assert!(pair.contains(2));
+	assert!(pair.contains(3));
+	assert!(!pair.contains(1));
+}
+
+#[test]
+fn trading_pair_from_currency_ids_work() {
+	assert_eq!(
+		TradingPair::from_currency_ids(3u32, 2u32),
+		Some(TradingPair::new(2u32, 3u32))
+	);
+	assert_eq!(TradingPair::from_currency_ids(2u32, 2u32), None);
+}
+
+pub struct MockPriceSource;
+impl PriceProvider<u32, Price> for MockPriceSource {
+	fn get_price(base: u32, quote: u32) -> Option<Price> {
+		match (base, quote) {
+			(1, 2) => Some(Price::from_rational(3, 2)),
+			(1, 3) => Some(Price::from_natural(u128::max_value() / Price::accuracy())),
+			_ => None,
+		}
+	}
+}
+
+#[test]
+fn get_relative_price_work() {
+	assert_eq!(
+		MockPriceSource::get_relative_price(1, 2),
+		Some(Price::from_rational(3, 2))
+	);
+	assert_eq!(MockPriceSource::get_relative_price(2, 2), Some(Price::from_natural(1)));
+	assert_eq!(MockPriceSource::get_relative_price(2, 1), None);
+}
+
+#[test]
+fn get_value_work() {
+	assert_eq!(MockPriceSource::get_value(1, 2, 100u64), Some(150u64));
+	assert_eq!(MockPriceSource::get_value(2, 2, 100u64), Some(100u64));
+	assert_eq!(MockPriceSource::get_value(2, 1, 100u64), None);
+	assert_eq!(MockPriceSource::get_value(1, 3, 100u64), None);
+}
+
+pub struct OneThirdExchangeRate;
+impl ExchangeRateProvider<u32> for OneThirdExchangeRate {
+	fn get_exchange_rate(_currency_id: u32) -> ExchangeRate {
+		ExchangeRate::from_rational(1, 3)
+	}
+}
+
+pub struct OneExchangeRate;
+impl ExchangeRateProvider<u32> for OneExchangeRate {
+	fn get_exchange_rate(_currency_id: u32) -> ExchangeRate {
+		ExchangeRate::from_natural(1)
+	}
+}
+
+pub struct OneAndATenthExchangeRate;
+impl ExchangeRateProvider<u32> for OneAndATenthExchangeRate {
+	fn get_exchange_rate(_currency_id: u32) -> ExchangeRate {
+		ExchangeRate::from_rational(11, 10)
+	}
+}
+
+fn floor<P: ExchangeRateProvider<u32>>(debit_balance: u64) -> u64 {
+	DebitExchangeRateConvertor::<P>::convert_debit_to_balance_floor(0, debit_balance)
+}
+
+fn ceil<P: ExchangeRateProvider<u32>>(debit_balance: u64) -> u64 {
+	DebitExchangeRateConvertor::<P>::convert_debit_to_balance_ceil(0, debit_balance)
+}
+
+#[test]
+fn debit_exchange_rate_convertor_rounding_work() {
+	assert_eq!(floor::<OneThirdExchangeRate>(10), 3);
+	assert_eq!(ceil::<OneThirdExchangeRate>(10), 4);
+	// 1/3 is stored as 0.333...3, so 3 * 1/3 is slightly less than 1
+	assert_eq!(floor::<OneThirdExchangeRate>(3), 0);
+	assert_eq!(ceil::<OneThirdExchangeRate>(3), 1);
+	assert_eq!(floor::<OneThirdExchangeRate>(0), 0);
+	assert_eq!(ceil::<OneThirdExchangeRate>(0), 0);
+}
+
+#[test]
+fn debit_exchange_rate_convertor_non_integer_rate_work() {
+	assert_eq!(floor::<OneAndATenthExchangeRate>(1), 1);
+	assert_eq!(ceil::<OneAndATenthExchangeRate>(1), 2);
+	assert_eq!(floor::<OneAndATenthExchangeRate>(15), 16);
+	assert_eq!(ceil::<OneAndATenthExchangeRate>(15), 17);
+	assert_eq!(floor::<OneAndATenthExchangeRate>(999), 1098);
+	assert_eq!(ceil::<OneAndATenthExchangeRate>(999), 1099);
+	// no rounding when the value is whole
+	assert_eq!(floor::<OneAndATenthExchangeRate>(10), 11);
+	assert_eq!(ceil::<OneAndATenthExchangeRate>(10), 11);
+}
+
+#[test]
+fn debit_exchange_rate_convertor_exact_work() {
+	assert_eq!(floor::<OneExchangeRate>(10), 10);
+	assert_eq!(ceil::<OneExchangeRate>(10), 10);
+}
+
+#[test]
+fn debit_exchange_rate_convertor_saturate_work() {
+	type Convertor = DebitExchangeRateConvertor<OneExchangeRate>;
+
+	assert_eq!(
+		<Convertor as DebitConvertor<u32, u128, u64>>::convert_debit_to_balance_ceil(0, u128::max_value()),
+		u64::max_value()
+	);
+}
+
+#[test]
+fn auditor_reports_broken_invariants() {
+	let mut auditor = Auditor::default();
+	auditor.check("held", 0u32, 10u64, 10u64);
+	assert_eq!(auditor.finish(), Ok(()));
+
+	let mut auditor = Auditor::default();
+	auditor.check("held", 0u32, 10u64, 10u64);
+	auditor.check("held", 1u32, 10u64, 9u64);
+	let mut other = Auditor::default();
+	other.check("total", 2u32, 0u128, u128::max_value());
+	auditor.merge(other.finish());
+	assert_eq!(
+		auditor.finish(),
+		Err(vec![
+			InvariantViolation {
+				invariant: "held",
+				currency_id: 1,
+				recorded: 10,
+				actual: 9,
+			},
+			InvariantViolation {
+				invariant: "total",
+				currency_id: 2,
+				recorded: 0,
+				actual: u128::max_value(),
+			},
+		])
+	);
+}
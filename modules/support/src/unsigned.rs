@@ -0,0 +1,58 @@
+//! Validity of the unsigned transactions submitted by the offchain workers of every validator.
+
+use super::*;
+use sp_runtime::transaction_validity::{TransactionLongevity, TransactionPriority, ValidTransaction};
+
+#[cfg(feature = "std")]
+use sp_runtime::traits::ValidateUnsigned;
+
+/// The priority of unsigned transactions which only report, the lowest so they never push out the transactions
+/// paying a fee.
+pub const UNSIGNED_REPORT_PRIORITY: TransactionPriority = TransactionPriority::min_value();
+
+/// Unsigned keeper transactions are kept in the pool for a few blocks only, the offchain workers resubmit what's
+/// still due with the urgency of then.
+pub const UNSIGNED_KEEPER_LONGEVITY: TransactionLongevity = 5;
+
+/// The priority of urgent unsigned transactions, above the transactions paying a fee from halfway up the priorities,
+/// and growing with `urgency`, how far the target is past its threshold as a part of the threshold, up to one.
+pub fn unsigned_urgent_priority(urgency: Ratio) -> TransactionPriority {
+	// in parts per billion
+	let urgency = (urgency.deconstruct() / 1_000_000_000).min(1_000_000_000);
+	(TransactionPriority::max_value() / 2).saturating_add(urgency as TransactionPriority)
+}
+
+/// The validity of an unsigned keeper transaction providing the `tag` of its target, so only one of the duplicates
+/// submitted by the offchain workers of several validators is kept in the pool.
+pub fn unsigned_keeper_transaction<Tag: Encode>(priority: TransactionPriority, tag: Tag) -> ValidTransaction {
+	ValidTransaction {
+		priority,
+		requires: vec![],
+		provides: vec![tag.encode()],
+		longevity: UNSIGNED_KEEPER_LONGEVITY,
+		propagate: true,
+	}
+}
+
+/// The unsigned `calls` the transaction pool keeps, in the order it includes them. A call providing a tag already
+/// provided in the pool only replaces it with a higher priority, and the higher priorities are included first.
+#[cfg(feature = "std")]
+pub fn unsigned_pool_order<V: ValidateUnsigned>(calls: Vec<V::Call>) -> Vec<V::Call> {
+	let mut pool: Vec<(V::Call, ValidTransaction)> = vec![];
+	for call in calls {
+		let validity = match V::validate_unsigned(&call) {
+			Ok(validity) => validity,
+			Err(_) => continue,
+		};
+		let conflict = pool
+			.iter()
+			.position(|(_, pooled)| pooled.provides.iter().any(|tag| validity.provides.contains(tag)));
+		match conflict {
+			Some(index) if pool[index].1.priority < validity.priority => pool[index] = (call, validity),
+			Some(_) => {}
+			None => pool.push((call, validity)),
+		}
+	}
+	pool.sort_by(|a, b| b.1.priority.cmp(&a.1.priority));
+	pool.into_iter().map(|(call, _)| call).collect()
+}
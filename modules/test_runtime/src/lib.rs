@@ -0,0 +1,511 @@
+//! A test runtime assembling all the modules, for the scenarios across modules. The unit tests of each module keep
+//! their own mock, which only wires what the module needs.
+
+use frame_support::{assert_ok, impl_outer_origin, parameter_types, traits::Get};
+use orml_traits::{MultiCurrency, PriceProvider};
+use primitives::H256;
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{Dispatchable, IdentityLookup, OnFinalize, OnInitialize},
+	DispatchResult, ModuleId, Perbill,
+};
+use std::{cell::RefCell, collections::BTreeMap};
+use support::{AuctionManagerExtended, DexManager, ExchangeRate, FixedU128Ext, Price, Rate, Ratio};
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+	pub const ExistentialDeposit: u64 = 0;
+	pub const TransferFee: u64 = 0;
+	pub const CreationFee: u64 = 0;
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
+	pub const GlobalStabilityFee: Rate = Rate::from_percent(0);
+	pub const DefaultLiquidationRatio: Ratio = Ratio::from_percent(150);
+	pub const DefaulDebitExchangeRate: ExchangeRate = ExchangeRate::from_natural(1);
+	pub const MinimumDebitValue: Balance = 2;
+	pub const GetNativeCurrencyId: CurrencyId = ACA;
+	pub const GetStableCurrencyId: CurrencyId = AUSD;
+	pub const GetExchangeFee: Rate = Rate::from_percent(0);
+	pub const MinimumLiquidity: Share = 0;
+	pub const MinimumShareIncrement: Share = 0;
+	pub const GetMinimumPoolSizeForPricing: Balance = 0;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 50;
+	pub const GetProtocolFeeRate: Rate = Rate::from_percent(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
+	pub const MinimumIncrementSize: Rate = Rate::from_percent(5);
+	pub const AuctionTimeToClose: u64 = 100;
+	pub const AuctionDurationSoftCap: u64 = 2000;
+	pub const MaxAuctionsCancelledPerBlock: u32 = 2;
+}
+
+pub type AccountId = u64;
+pub type BlockNumber = u64;
+pub type Balance = u64;
+pub type DebitBalance = u64;
+pub type Amount = i64;
+pub type DebitAmount = i64;
+pub type Share = u64;
+pub type AuctionId = u64;
+pub type CurrencyId = module_primitives::CurrencyId;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CAROL: AccountId = 3;
+/// The provider of the liquidity of the pools given to `ExtBuilder`.
+pub const LIQUIDITY_PROVIDER: AccountId = 4;
+
+pub const ACA: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const BTC: CurrencyId = CurrencyId::BTC;
+pub const DOT: CurrencyId = CurrencyId::DOT;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+
+impl system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type ModuleToIndex = ();
+}
+pub type System = system::Module<Runtime>;
+
+impl orml_tokens::Trait for Runtime {
+	type Event = ();
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+impl pallet_balances::Trait for Runtime {
+	type Balance = Balance;
+	type OnFreeBalanceZero = ();
+	type OnNewAccount = ();
+	type TransferPayment = ();
+	type DustRemoval = ();
+	type Event = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type TransferFee = TransferFee;
+	type CreationFee = CreationFee;
+}
+pub type PalletBalances = pallet_balances::Module<Runtime>;
+
+pub type AdaptedBasicCurrency =
+	orml_currencies::BasicCurrencyAdapter<Runtime, PalletBalances, Balance, orml_tokens::Error>;
+
+impl orml_currencies::Trait for Runtime {
+	type Event = ();
+	type MultiCurrency = Tokens;
+	type NativeCurrency = AdaptedBasicCurrency;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+}
+pub type Currencies = orml_currencies::Module<Runtime>;
+
+impl orml_auction::Trait for Runtime {
+	type Event = ();
+	type Balance = Balance;
+	type AuctionId = AuctionId;
+	type Handler = AuctionManagerModule;
+}
+pub type Auction = orml_auction::Module<Runtime>;
+
+thread_local! {
+	static PRICES: RefCell<BTreeMap<CurrencyId, Price>> = RefCell::new(BTreeMap::new());
+}
+
+/// The feed prices of the currencies in stable coin, the stable coin is always worth one.
+pub struct MockPriceSource;
+impl MockPriceSource {
+	pub fn set_price(currency_id: CurrencyId, price: Option<Price>) {
+		PRICES.with(|v| match price {
+			Some(price) => v.borrow_mut().insert(currency_id, price),
+			None => v.borrow_mut().remove(&currency_id),
+		});
+	}
+
+	fn reset() {
+		PRICES.with(|v| v.borrow_mut().clear());
+	}
+
+	fn stable_coin_price(currency_id: CurrencyId) -> Option<Price> {
+		if currency_id == AUSD {
+			Some(Price::from_natural(1))
+		} else {
+			PRICES.with(|v| v.borrow().get(&currency_id).copied())
+		}
+	}
+}
+impl PriceProvider<CurrencyId, Price> for MockPriceSource {
+	fn get_price(base: CurrencyId, quote: CurrencyId) -> Option<Price> {
+		let base_price = Self::stable_coin_price(base)?;
+		let quote_price = Self::stable_coin_price(quote)?;
+		Price::checked_from_rational(quote_price.deconstruct(), base_price.deconstruct())
+	}
+}
+
+impl debits::Trait for Runtime {
+	type CurrencyId = CurrencyId;
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type DebitBalance = DebitBalance;
+	type Convert = cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type DebitAmount = DebitAmount;
+}
+pub type DebitsCurrency = debits::Module<Runtime>;
+
+impl vaults::Trait for Runtime {
+	type Event = ();
+	type Convert = cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type Currency = Currencies;
+	type DebitCurrency = DebitsCurrency;
+	type RiskManager = CdpEngineModule;
+	type OnUpdateLoan = ();
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = ();
+}
+pub type VaultsModule = vaults::Module<Runtime>;
+
+impl dex::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = ();
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = ();
+	type ShareHoldersToMigrate = ();
+	type PriceSource = MockPriceSource;
+	type PriceDeviationCurrencyIds = ();
+	type PriceDeviationThreshold = ();
+	type PriceDeviationReportInterval = ();
+	type Call = dex::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), dex::Call<Runtime>, TestXt<dex::Call<Runtime>, ()>>;
+	type OnUpdateShares = ();
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+pub type DexModule = dex::Module<Runtime>;
+
+impl cdp_treasury::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = AuctionManagerModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = DexModule;
+	type PriceSource = EmergencyShutdownModule;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+pub type CdpTreasuryModule = cdp_treasury::Module<Runtime>;
+
+impl auction_manager::Trait for Runtime {
+	type Event = ();
+	type Currency = Currencies;
+	type CurrencyId = CurrencyId;
+	type Balance = Balance;
+	type Amount = Amount;
+	type Auction = Auction;
+	type MinimumIncrementSize = MinimumIncrementSize;
+	type AuctionTimeToClose = AuctionTimeToClose;
+	type AuctionDurationSoftCap = AuctionDurationSoftCap;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+}
+pub type AuctionManagerModule = auction_manager::Module<Runtime>;
+
+impl cdp_engine::Trait for Runtime {
+	type Event = ();
+	type AuctionManagerHandler = AuctionManagerModule;
+	type Currency = Currencies;
+	type PriceSource = EmergencyShutdownModule;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type GlobalStabilityFee = GlobalStabilityFee;
+	type StabilityFeeModel = ();
+	type DefaultLiquidationRatio = DefaultLiquidationRatio;
+	type DefaulDebitExchangeRate = DefaulDebitExchangeRate;
+	type MinimumDebitValue = MinimumDebitValue;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type Treasury = CdpTreasuryModule;
+	type Dex = DexModule;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type Call = cdp_engine::Call<Runtime>;
+	type SubmitTransaction =
+		system::offchain::TransactionSubmitter<(), cdp_engine::Call<Runtime>, TestXt<cdp_engine::Call<Runtime>, ()>>;
+	type MaxPositionsCheckedPerBlock = ();
+}
+pub type CdpEngineModule = cdp_engine::Module<Runtime>;
+
+impl honzon::Trait for Runtime {
+	type Event = ();
+	type MaxAdjustmentsPerCall = ();
+}
+pub type HonzonModule = honzon::Module<Runtime>;
+
+impl emergency_shutdown::Trait for Runtime {
+	type Event = ();
+	type PriceSource = MockPriceSource;
+	type AuctionManagerHandler = AuctionManagerModule;
+	type OnShutdown = (CdpEngineModule, CdpTreasuryModule);
+	type MaxAuctionsCancelledPerBlock = MaxAuctionsCancelledPerBlock;
+}
+pub type EmergencyShutdownModule = emergency_shutdown::Module<Runtime>;
+
+/// The risk parameters of a collateral, as set by `CdpEngineModule::set_collateral_params`.
+#[derive(Clone)]
+pub struct RiskParams {
+	pub stability_fee: Option<Rate>,
+	pub liquidation_ratio: Option<Ratio>,
+	pub liquidation_penalty: Option<Rate>,
+	pub required_collateral_ratio: Option<Ratio>,
+	pub maximum_total_debit_value: Balance,
+	pub minimum_collateral_amount: Balance,
+}
+
+impl Default for RiskParams {
+	fn default() -> Self {
+		Self {
+			stability_fee: None,
+			liquidation_ratio: None,
+			liquidation_penalty: Some(Rate::from_percent(10)),
+			required_collateral_ratio: None,
+			maximum_total_debit_value: 10_000,
+			minimum_collateral_amount: 0,
+		}
+	}
+}
+
+pub struct ExtBuilder {
+	balances: Vec<(AccountId, CurrencyId, Balance)>,
+	prices: Vec<(CurrencyId, Price)>,
+	risk_params: Vec<(CurrencyId, RiskParams)>,
+	pools: Vec<(CurrencyId, Balance, Balance)>,
+	positions: Vec<(AccountId, CurrencyId, Amount, DebitAmount)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			balances: vec![ALICE, BOB, CAROL]
+				.into_iter()
+				.flat_map(|who| vec![(who, BTC, 1000), (who, DOT, 1000)])
+				.collect(),
+			prices: vec![(BTC, Price::from_natural(1)), (DOT, Price::from_natural(2))],
+			risk_params: vec![(BTC, RiskParams::default()), (DOT, RiskParams::default())],
+			pools: vec![],
+			positions: vec![],
+		}
+	}
+}
+
+impl ExtBuilder {
+	/// Replace the initial balances.
+	pub fn balances(mut self, balances: Vec<(AccountId, CurrencyId, Balance)>) -> Self {
+		self.balances = balances;
+		self
+	}
+
+	/// Replace the initial feed prices in stable coin.
+	pub fn prices(mut self, prices: Vec<(CurrencyId, Price)>) -> Self {
+		self.prices = prices;
+		self
+	}
+
+	/// Replace the risk parameters of the collaterals.
+	pub fn risk_params(mut self, risk_params: Vec<(CurrencyId, RiskParams)>) -> Self {
+		self.risk_params = risk_params;
+		self
+	}
+
+	/// The pools given as `(currency_id, other_currency_amount, stable_amount)`, all provided by
+	/// `LIQUIDITY_PROVIDER`.
+	pub fn pools(mut self, pools: Vec<(CurrencyId, Balance, Balance)>) -> Self {
+		self.pools = pools;
+		self
+	}
+
+	/// The positions given as `(who, currency_id, collateral, debit)`, opened at the initial prices from the
+	/// balances of their owners.
+	pub fn positions(mut self, positions: Vec<(AccountId, CurrencyId, Amount, DebitAmount)>) -> Self {
+		self.positions = positions;
+		self
+	}
+
+	pub fn build(self) -> AuditedExternalities {
+		let t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		let mut ext: runtime_io::TestExternalities = t.into();
+
+		MockPriceSource::reset();
+		ext.execute_with(|| {
+			// the migrations of a new chain are done in the first block
+			System::set_block_number(1);
+			on_initialize(1);
+
+			for (who, currency_id, amount) in self.balances {
+				assert_ok!(Currencies::deposit(currency_id, &who, amount));
+			}
+			for (currency_id, price) in self.prices {
+				set_price(currency_id, price);
+			}
+			for (currency_id, params) in self.risk_params {
+				assert_ok!(CdpEngineModule::set_collateral_params(
+					Origin::ROOT,
+					currency_id,
+					Some(params.stability_fee),
+					Some(params.liquidation_ratio),
+					Some(params.liquidation_penalty),
+					Some(params.required_collateral_ratio),
+					Some(params.maximum_total_debit_value),
+					Some(params.minimum_collateral_amount),
+				));
+			}
+			for (currency_id, other_currency_amount, stable_amount) in self.pools {
+				assert_ok!(DexModule::enable_trading_pair(Origin::ROOT, currency_id));
+				assert_ok!(Currencies::deposit(
+					currency_id,
+					&LIQUIDITY_PROVIDER,
+					other_currency_amount
+				));
+				assert_ok!(Currencies::deposit(AUSD, &LIQUIDITY_PROVIDER, stable_amount));
+				assert!(<DexModule as DexManager<_, _, _>>::add_liquidity(
+					&LIQUIDITY_PROVIDER,
+					currency_id,
+					other_currency_amount,
+					stable_amount,
+				)
+				.is_ok());
+			}
+			for (who, currency_id, collateral, debit) in self.positions {
+				assert_ok!(CdpEngineModule::update_position(who, currency_id, collateral, debit));
+			}
+		});
+
+		AuditedExternalities(ext)
+	}
+}
+
+/// Externalities auditing the accounting of all the modules after every execution.
+pub struct AuditedExternalities(runtime_io::TestExternalities);
+
+impl AuditedExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			let collateral_currency_ids = CollateralCurrencyIds::get();
+			let mut liquidity_providers = vec![ALICE, BOB, CAROL, LIQUIDITY_PROVIDER, CdpTreasuryModule::account_id()];
+			liquidity_providers.extend(
+				collateral_currency_ids
+					.iter()
+					.map(|currency_id| CdpTreasuryModule::sub_account_id(*currency_id)),
+			);
+
+			assert_eq!(
+				VaultsModule::audit(&collateral_currency_ids, &[ALICE, BOB, CAROL]),
+				Ok(())
+			);
+			assert_eq!(CdpTreasuryModule::audit(&collateral_currency_ids), Ok(()));
+			assert_eq!(AuctionManagerModule::audit(&collateral_currency_ids), Ok(()));
+			assert_eq!(DexModule::audit(&[AUSD, BTC, DOT], &liquidity_providers), Ok(()));
+			result
+		})
+	}
+}
+
+fn on_initialize(now: BlockNumber) {
+	DexModule::on_initialize(now);
+	VaultsModule::on_initialize(now);
+	CdpTreasuryModule::on_initialize(now);
+	AuctionManagerModule::on_initialize(now);
+}
+
+fn on_finalize(now: BlockNumber) {
+	Auction::on_finalize(now);
+	CdpEngineModule::on_finalize(now);
+	CdpTreasuryModule::on_finalize(now);
+	EmergencyShutdownModule::on_finalize(now);
+}
+
+/// Finalize the current block and initialize the next one, `count` times.
+pub fn advance_blocks(count: BlockNumber) {
+	for _ in 0..count {
+		let now = System::block_number();
+		on_finalize(now);
+		System::set_block_number(now + 1);
+		on_initialize(now + 1);
+	}
+}
+
+/// Set the feed price of `currency_id` in stable coin. The prices of the collaterals are locked after shutdown.
+pub fn set_price(currency_id: CurrencyId, price: Price) {
+	MockPriceSource::set_price(currency_id, Some(price));
+}
+
+/// Shut down, drain the auctions across blocks, settle `cdps` and open the refund. The position of the CDPs left
+/// after settlement, if any, can be withdrawn by their owners.
+pub fn run_to_shutdown(cdps: &[(AccountId, CurrencyId)]) {
+	assert_ok!(EmergencyShutdownModule::emergency_shutdown(Origin::ROOT));
+	while AuctionManagerModule::active_auction_count() > 0 {
+		advance_blocks(1);
+	}
+	for &(who, currency_id) in cdps {
+		assert_ok!(settle_cdp(who, currency_id));
+	}
+	assert_ok!(EmergencyShutdownModule::open_collateral_refund(Origin::ROOT));
+}
+
+pub fn update_vault(who: AccountId, currency_id: CurrencyId, collateral: Amount, debit: DebitAmount) -> DispatchResult {
+	honzon::Call::<Runtime>::update_vault(currency_id, collateral, debit).dispatch(Origin::signed(who))
+}
+
+pub fn liquidate(who: AccountId, currency_id: CurrencyId) -> DispatchResult {
+	honzon::Call::<Runtime>::liquidate(who, currency_id).dispatch(Origin::NONE)
+}
+
+pub fn settle_cdp(who: AccountId, currency_id: CurrencyId) -> DispatchResult {
+	honzon::Call::<Runtime>::settle_cdp(who, currency_id).dispatch(Origin::NONE)
+}
+
+pub fn swap_with_exact_supply(
+	who: AccountId,
+	supply: (CurrencyId, Balance),
+	target: (CurrencyId, Balance),
+) -> DispatchResult {
+	dex::Call::<Runtime>::swap_with_exact_supply(supply, target, None).dispatch(Origin::signed(who))
+}
+
+pub fn bid(who: AccountId, auction_id: AuctionId, amount: Balance) -> DispatchResult {
+	Auction::bid(Origin::signed(who), auction_id, amount)
+}
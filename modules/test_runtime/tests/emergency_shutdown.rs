@@ -0,0 +1,53 @@
+//! Emergency shutdown from the live system to the refund of all the stable coin.
+
+use frame_support::assert_ok;
+use module_test_runtime::{
+	liquidate, run_to_shutdown, set_price, update_vault, AuctionManagerModule, CdpTreasuryModule,
+	EmergencyShutdownModule, ExtBuilder, Origin, Tokens, VaultsModule, ALICE, AUSD, BOB, BTC, CAROL, DOT,
+};
+use orml_traits::MultiCurrency;
+use support::{AuctionManagerExtended, Price};
+
+#[test]
+fn shutdown_refunds_all_the_collaterals() {
+	ExtBuilder::default()
+		.positions(vec![(ALICE, BTC, 200, 50), (BOB, DOT, 100, 100), (CAROL, BTC, 100, 60)])
+		.build()
+		.execute_with(|| {
+			// CAROL is liquidated, the auction has no bid before shutdown
+			set_price(BTC, Price::from_rational(6, 10));
+			assert_ok!(liquidate(CAROL, BTC));
+			assert_eq!(AuctionManagerModule::active_auction_count(), 1);
+
+			run_to_shutdown(&[(ALICE, BTC), (BOB, DOT)]);
+
+			// the lot went back to the treasury, the settled CDPs handed over the collateral worth their debits
+			assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+			assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 183);
+			assert_eq!(CdpTreasuryModule::total_collaterals(DOT), 50);
+			assert_eq!(CdpTreasuryModule::debit_pool(), 210);
+			assert_eq!(EmergencyShutdownModule::stable_coin_to_refund(), 210);
+
+			// the remaining collaterals are returned on settlement, nothing can be borrowed
+			assert!(update_vault(ALICE, BTC, 0, 10).is_err());
+			assert_eq!(Tokens::balance(BTC, &ALICE), 917);
+			assert_eq!(VaultsModule::total_collaterals(BTC), 0);
+			assert_eq!(VaultsModule::total_collaterals(DOT), 0);
+
+			assert_ok!(EmergencyShutdownModule::refund_collaterals(Origin::signed(ALICE), 50));
+			assert_ok!(EmergencyShutdownModule::refund_collaterals(Origin::signed(CAROL), 60));
+			// the last refund takes all the remaining collaterals
+			assert_ok!(EmergencyShutdownModule::refund_collaterals(Origin::signed(BOB), 100));
+
+			assert_eq!(EmergencyShutdownModule::stable_coin_to_refund(), 0);
+			assert_eq!(Tokens::total_issuance(AUSD), 0);
+			assert_eq!(CdpTreasuryModule::total_collaterals(BTC), 0);
+			assert_eq!(CdpTreasuryModule::total_collaterals(DOT), 0);
+			assert_eq!(Tokens::balance(BTC, &ALICE), 960);
+			assert_eq!(Tokens::balance(DOT, &ALICE), 1011);
+			assert_eq!(Tokens::balance(BTC, &CAROL), 952);
+			assert_eq!(Tokens::balance(DOT, &CAROL), 1014);
+			assert_eq!(Tokens::balance(BTC, &BOB), 1088);
+			assert_eq!(Tokens::balance(DOT, &BOB), 975);
+		});
+}
@@ -0,0 +1,91 @@
+//! Liquidation of unsafe CDPs across the cdp engine, the treasury, the auctions and the dex. The collateral is sold
+//! with the dex if the pool is deep enough, otherwise it goes through a collateral auction.
+
+use frame_support::assert_ok;
+use module_test_runtime::{
+	advance_blocks, bid, liquidate, set_price, swap_with_exact_supply, AuctionManagerModule, CdpTreasuryModule,
+	DexModule, ExtBuilder, Tokens, VaultsModule, ALICE, AUSD, BTC, CAROL,
+};
+use orml_traits::MultiCurrency;
+use support::Price;
+
+#[test]
+fn liquidation_via_auction() {
+	ExtBuilder::default()
+		.balances(vec![(ALICE, BTC, 1000), (CAROL, AUSD, 100)])
+		.positions(vec![(ALICE, BTC, 100, 50)])
+		.build()
+		.execute_with(|| {
+			assert_eq!(Tokens::balance(AUSD, &ALICE), 50);
+
+			// 120% is below the liquidation ratio
+			set_price(BTC, Price::from_rational(6, 10));
+			assert_ok!(liquidate(ALICE, BTC));
+			assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+			assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+			assert_eq!(CdpTreasuryModule::debit_pool(), 50);
+			assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 100);
+			// the bad debt with the 10% penalty
+			assert_eq!(AuctionManagerModule::total_target_in_auction(), 55);
+
+			assert_ok!(bid(CAROL, 0, 55));
+			assert_eq!(Tokens::balance(AUSD, &CAROL), 45);
+			advance_blocks(101);
+
+			assert_eq!(Tokens::balance(BTC, &CAROL), 100);
+			assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+			assert_eq!(AuctionManagerModule::total_target_in_auction(), 0);
+			// the bad debt is offset, the penalty is left as surplus
+			assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+			assert_eq!(CdpTreasuryModule::surplus_pool(), 5);
+			assert_eq!(Tokens::total_issuance(AUSD), 100);
+		});
+}
+
+#[test]
+fn liquidation_bid_with_stable_coin_from_dex() {
+	ExtBuilder::default()
+		.balances(vec![(ALICE, BTC, 1000), (CAROL, BTC, 1000)])
+		.pools(vec![(BTC, 1000, 600)])
+		.positions(vec![(ALICE, BTC, 100, 50)])
+		.build()
+		.execute_with(|| {
+			set_price(BTC, Price::from_rational(6, 10));
+			assert_ok!(liquidate(ALICE, BTC));
+
+			// the keeper sells collateral for the target of the auction and buys the lot back with it
+			assert_ok!(swap_with_exact_supply(CAROL, (BTC, 101), (AUSD, 55)));
+			assert_eq!(Tokens::balance(AUSD, &CAROL), 55);
+			assert_eq!(DexModule::liquidity_pool(BTC), (1101, 545));
+			assert_ok!(bid(CAROL, 0, 55));
+			advance_blocks(101);
+
+			assert_eq!(Tokens::balance(BTC, &CAROL), 999);
+			assert_eq!(Tokens::balance(AUSD, &CAROL), 0);
+			assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+			assert_eq!(CdpTreasuryModule::debit_pool(), 0);
+			assert_eq!(CdpTreasuryModule::surplus_pool(), 5);
+		});
+}
+
+#[test]
+fn liquidation_via_dex() {
+	ExtBuilder::default()
+		.balances(vec![(ALICE, BTC, 1000)])
+		.pools(vec![(BTC, 10000, 6000)])
+		.positions(vec![(ALICE, BTC, 100, 50)])
+		.build()
+		.execute_with(|| {
+			set_price(BTC, Price::from_rational(6, 10));
+			assert_ok!(liquidate(ALICE, BTC));
+
+			// the bad debt with the 10% penalty is bought with the collateral, the rest is left for the owner
+			assert_eq!(DexModule::liquidity_pool(BTC), (10093, 5945));
+			assert_eq!(Tokens::balance(BTC, &ALICE), 907);
+			assert_eq!(VaultsModule::collaterals(ALICE, BTC), 0);
+			assert_eq!(VaultsModule::debits(ALICE, BTC), 0);
+			assert_eq!(AuctionManagerModule::total_collateral_in_auction(BTC), 0);
+			assert_eq!(CdpTreasuryModule::debit_pool(), 50);
+			assert_eq!(CdpTreasuryModule::surplus_pool(), 55);
+		});
+}
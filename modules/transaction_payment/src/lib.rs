@@ -0,0 +1,147 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{
+	decl_error, decl_module,
+	traits::{Currency, Get},
+	weights::DispatchInfo,
+};
+use orml_traits::MultiCurrency;
+use rstd::{fmt, prelude::*, result};
+use sp_runtime::{
+	traits::{Bounded, Saturating, SignedExtension, Zero},
+	transaction_validity::{InvalidTransaction, TransactionValidity, TransactionValidityError},
+	DispatchResult,
+};
+use support::{DexManager, Ratio};
+
+mod mock;
+mod tests;
+
+type PalletBalanceOf<T> =
+	<<T as pallet_transaction_payment::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type CurrencyIdOf<T> = <<T as Trait>::MultiCurrency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+
+pub trait Trait: pallet_transaction_payment::Trait {
+	type MultiCurrency: MultiCurrency<Self::AccountId, Balance = PalletBalanceOf<Self>>;
+	type DEX: DexManager<Self::AccountId, CurrencyIdOf<Self>, PalletBalanceOf<Self>>;
+	type NativeCurrencyId: Get<CurrencyIdOf<Self>>;
+	/// Currencies swapped to native currency to pay the fee, in order of priority.
+	type FeeCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The max extra supply allowed above the quoted supply amount when swapping.
+	type MaxSlippageSwapWithDEX: Get<Ratio>;
+}
+
+decl_error! {
+	/// Error for transaction payment module.
+	pub enum Error for Module<T: Trait> {
+		InsufficientBalance,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Make sure `who` has enough native currency to pay `fee` and keep alive, swapping the shortfall
+	/// from the fee currencies in order of priority with DEX if necessary.
+	///
+	/// The native currency swapped is never converted back, the surplus is kept in the account.
+	pub fn ensure_can_pay_fee(who: &T::AccountId, fee: PalletBalanceOf<T>) -> DispatchResult {
+		let native_balance = <T as pallet_transaction_payment::Trait>::Currency::free_balance(who);
+		let required_balance =
+			fee.saturating_add(<T as pallet_transaction_payment::Trait>::Currency::minimum_balance());
+		if fee.is_zero() || native_balance >= required_balance {
+			return Ok(());
+		}
+
+		let shortfall = required_balance - native_balance;
+		let native_currency_id = T::NativeCurrencyId::get();
+		let max_slippage = T::MaxSlippageSwapWithDEX::get();
+		for currency_id in T::FeeCurrencyIds::get() {
+			if currency_id == native_currency_id {
+				continue;
+			}
+
+			let quoted_supply_amount = match T::DEX::get_supply_amount(currency_id, native_currency_id, shortfall) {
+				Ok(amount) => amount,
+				Err(_) => continue,
+			};
+			let balance = T::MultiCurrency::balance(currency_id, who);
+			if quoted_supply_amount.is_zero() || balance < quoted_supply_amount {
+				continue;
+			}
+
+			let max_supply_amount = quoted_supply_amount.saturating_add(
+				max_slippage
+					.checked_mul_int(&quoted_supply_amount)
+					.unwrap_or_else(Bounded::max_value),
+			);
+			let supply_amount = rstd::cmp::min(balance, max_supply_amount);
+			if T::DEX::exchange_currency(
+				who.clone(),
+				(currency_id, supply_amount),
+				(native_currency_id, shortfall),
+			)
+			.is_ok()
+			{
+				return Ok(());
+			}
+		}
+
+		Err(Error::<T>::InsufficientBalance.into())
+	}
+}
+
+/// Drop-in replacement of `pallet_transaction_payment::ChargeTransactionPayment` with the same encoding,
+/// which swaps the fee from other currencies if the native currency can't afford it.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct ChargeTransactionPayment<T: Trait + Send + Sync>(#[codec(compact)] PalletBalanceOf<T>);
+
+impl<T: Trait + Send + Sync> ChargeTransactionPayment<T> {
+	/// utility constructor. Used only in client/factory code.
+	pub fn from(fee: PalletBalanceOf<T>) -> Self {
+		Self(fee)
+	}
+}
+
+impl<T: Trait + Send + Sync> fmt::Debug for ChargeTransactionPayment<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "ChargeTransactionPayment<{:?}>", self.0)
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Trait + Send + Sync> SignedExtension for ChargeTransactionPayment<T>
+where
+	PalletBalanceOf<T>: Send + Sync,
+{
+	type AccountId = T::AccountId;
+	type Call = T::Call;
+	type AdditionalSigned = ();
+	type DispatchInfo = DispatchInfo;
+	type Pre = ();
+	fn additional_signed(&self) -> result::Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: Self::DispatchInfo,
+		len: usize,
+	) -> TransactionValidity {
+		let fee = pallet_transaction_payment::ChargeTransactionPayment::<T>::compute_fee(len as u32, info, self.0);
+		<Module<T>>::ensure_can_pay_fee(who, fee).map_err(|_| InvalidTransaction::Payment)?;
+
+		pallet_transaction_payment::ChargeTransactionPayment::<T>::from(self.0).validate(who, call, info, len)
+	}
+}
@@ -0,0 +1,84 @@
+//! Unit tests for the transaction payment module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_ok, weights::DispatchClass};
+use mock::{
+	Currencies, DexModule, ExtBuilder, Origin, PalletBalances, Runtime, TransactionPaymentModule, ACA, ALICE, AUSD,
+	BOB, DOT,
+};
+use sp_runtime::traits::Dispatchable;
+
+const CALL: &<Runtime as system::Trait>::Call = &();
+
+const INFO: DispatchInfo = DispatchInfo {
+	weight: 0,
+	class: DispatchClass::Normal,
+	pays_fee: true,
+};
+
+fn add_liquidity(currency_id: mock::CurrencyId) {
+	assert_ok!(dex::Call::<Runtime>::add_liquidity(currency_id, 10_000, 10_000, 0, None).dispatch(Origin::signed(BOB)));
+}
+
+#[test]
+fn charge_fee_in_native_currency() {
+	ExtBuilder::default().build().execute_with(|| {
+		let fee = pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::compute_fee(20, INFO, 0);
+		assert_eq!(fee, 20);
+		assert!(ChargeTransactionPayment::<Runtime>::from(0)
+			.pre_dispatch(&BOB, CALL, INFO, 20)
+			.is_ok());
+		assert_eq!(PalletBalances::free_balance(BOB), 100_000 - 20);
+		assert_eq!(Currencies::balance(AUSD, &BOB), 100_000);
+	});
+}
+
+#[test]
+fn charge_fee_by_swapping_stable_coin() {
+	ExtBuilder::default().build().execute_with(|| {
+		add_liquidity(ACA);
+		assert_eq!(PalletBalances::free_balance(ALICE), 0);
+
+		// swap the fee and the existential deposit from aUSD
+		assert!(ChargeTransactionPayment::<Runtime>::from(0)
+			.pre_dispatch(&ALICE, CALL, INFO, 20)
+			.is_ok());
+		assert_eq!(PalletBalances::free_balance(ALICE), 1);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 100_000 - 22);
+		assert_eq!(DexModule::liquidity_pool(ACA), (10_000 - 21, 10_000 + 22));
+	});
+}
+
+#[test]
+fn charge_fee_by_swapping_next_fee_currency() {
+	ExtBuilder::default().build().execute_with(|| {
+		add_liquidity(ACA);
+		add_liquidity(DOT);
+		assert_ok!(<Currencies as MultiCurrency<_>>::transfer(AUSD, &ALICE, &BOB, 100_000));
+
+		// no aUSD, swap DOT to native currency through aUSD
+		assert!(ChargeTransactionPayment::<Runtime>::from(0)
+			.pre_dispatch(&ALICE, CALL, INFO, 20)
+			.is_ok());
+		assert_eq!(PalletBalances::free_balance(ALICE), 1);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 100_000 - 23);
+	});
+}
+
+#[test]
+fn charge_fee_failed_when_dex_can_not_cover() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(
+			TransactionPaymentModule::ensure_can_pay_fee(&ALICE, 20),
+			Err(Error::<Runtime>::InsufficientBalance.into())
+		);
+		assert_eq!(
+			ChargeTransactionPayment::<Runtime>::from(0).pre_dispatch(&ALICE, CALL, INFO, 20),
+			Err(InvalidTransaction::Payment.into())
+		);
+		assert_eq!(PalletBalances::free_balance(ALICE), 0);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 100_000);
+	});
+}
@@ -1,26 +1,66 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure};
+use codec::{Decode, Encode, EncodeLike};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get, weights::Weight};
 use orml_traits::{arithmetic::Signed, MultiCurrency, MultiCurrencyExtended};
-use rstd::{convert::TryInto, result};
+use rstd::{convert::TryInto, prelude::*};
 use sp_runtime::{
-	traits::{AccountIdConversion, CheckedAdd, CheckedSub, Convert},
-	ModuleId,
+	traits::{AccountIdConversion, CheckedAdd, CheckedSub},
+	DispatchError, DispatchResult, ModuleId, RuntimeDebug,
 };
 
-use support::RiskManager;
+use support::{DebitConvertor, Linkage, OnUpdateLoan, Position, RiskManager, STORAGE_ACCESS_WEIGHT};
+
+#[cfg(feature = "std")]
+use sp_runtime::traits::{Saturating, Zero};
+#[cfg(feature = "std")]
+use support::{AuditResult, Auditor};
 
 mod mock;
 mod tests;
 
 const MODULE_ID: ModuleId = ModuleId(*b"xr1d84ts");
 
+/// Layouts of the module storage, in the order they are migrated through.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum StorageVersion {
+	/// Before the storage is versioned.
+	V0,
+	/// The collaterals and debits of the positions moved from `Collaterals` and `Debits` into `Positions`.
+	V1,
+	/// The owners of the positions from before they were linked in `PositionOwners` linked.
+	V2,
+}
+
+impl Default for StorageVersion {
+	fn default() -> Self {
+		StorageVersion::V0
+	}
+}
+
+impl StorageVersion {
+	/// The version migrated to from this one, `None` for the latest.
+	pub fn next(self) -> Option<Self> {
+		match self {
+			StorageVersion::V0 => Some(StorageVersion::V1),
+			StorageVersion::V1 => Some(StorageVersion::V2),
+			StorageVersion::V2 => None,
+		}
+	}
+}
+
 pub trait Trait: system::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
-	type Convert: Convert<(CurrencyIdOf<Self>, DebitBalanceOf<Self>), BalanceOf<Self>>;
+	type Convert: DebitConvertor<CurrencyIdOf<Self>, DebitBalanceOf<Self>, BalanceOf<Self>>;
 	type Currency: MultiCurrencyExtended<Self::AccountId>;
 	type DebitCurrency: MultiCurrencyExtended<Self::AccountId, CurrencyId = CurrencyIdOf<Self>>;
 	type RiskManager: RiskManager<Self::AccountId, CurrencyIdOf<Self>, AmountOf<Self>, DebitAmountOf<Self>>;
+	type OnUpdateLoan: OnUpdateLoan<Self::AccountId, CurrencyIdOf<Self>, DebitBalanceOf<Self>>;
+	/// The currencies positions can be opened in.
+	type CollateralCurrencyIds: Get<Vec<CurrencyIdOf<Self>>>;
+	/// The accounts which may have positions from before they were stored in `Positions` or linked in
+	/// `PositionOwners`, migrated to `V1` and `V2`.
+	type PositionOwnersToMigrate: Get<Vec<Self::AccountId>>;
 }
 
 type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
@@ -31,12 +71,24 @@ type DebitBalanceOf<T> = <<T as Trait>::DebitCurrency as MultiCurrency<<T as sys
 type AmountOf<T> = <<T as Trait>::Currency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
 type DebitAmountOf<T> = <<T as Trait>::DebitCurrency as MultiCurrencyExtended<<T as system::Trait>::AccountId>>::Amount;
 
+pub type PositionOf<T> = Position<BalanceOf<T>, DebitBalanceOf<T>>;
+
 decl_storage! {
 	trait Store for Module<T: Trait> as Vaults {
-		pub Debits get(fn debits): double_map T::AccountId, blake2_256(CurrencyIdOf<T>) => DebitBalanceOf<T>;
-		pub Collaterals get(fn collaterals): double_map T::AccountId, blake2_256(CurrencyIdOf<T>) => BalanceOf<T>;
+		pub Positions get(fn positions): double_map T::AccountId, blake2_256(CurrencyIdOf<T>) => PositionOf<T>;
 		pub TotalDebits get(fn total_debits): map CurrencyIdOf<T> => DebitBalanceOf<T>;
 		pub TotalCollaterals get(fn total_collaterals): map CurrencyIdOf<T> => BalanceOf<T>;
+		/// The accounts with a position in the currency, linked from the last one to open it, as the positions can't
+		/// be iterated.
+		pub PositionOwners get(fn position_owner_linkage): double_map CurrencyIdOf<T>, blake2_256(T::AccountId) =>
+			Option<Linkage<T::AccountId>>;
+		/// The first of the `PositionOwners` of the currency.
+		pub FirstPositionOwner get(fn first_position_owner): map CurrencyIdOf<T> => Option<T::AccountId>;
+		/// The debits of the positions before `V1`, moved into `Positions` since.
+		Debits: double_map T::AccountId, blake2_256(CurrencyIdOf<T>) => DebitBalanceOf<T>;
+		/// The collaterals of the positions before `V1`, moved into `Positions` since.
+		Collaterals: double_map T::AccountId, blake2_256(CurrencyIdOf<T>) => BalanceOf<T>;
+		Version get(fn storage_version): StorageVersion;
 	}
 }
 
@@ -46,11 +98,13 @@ decl_event!(
 		CurrencyId = CurrencyIdOf<T>,
 		DebitAmount = DebitAmountOf<T>,
 		Amount = AmountOf<T>,
+		Balance = BalanceOf<T>,
+		DebitBalance = DebitBalanceOf<T>,
 	{
-		/// Update Position success (account, currency_id, collaterals, debits)
-		UpdatePosition(AccountId, CurrencyId, Amount, DebitAmount),
-		/// Update collaterals and debits success (account, currency_id, collaterals, debits)
-		UpdateCollateralsAndDebits(AccountId, CurrencyId, Amount, DebitAmount),
+		/// The position is adjusted (account, currency_id, collateral_adjustment, debit_adjustment)
+		PositionUpdated(AccountId, CurrencyId, Amount, DebitAmount),
+		/// The position is confiscated from (account, currency_id, collateral_seized, debit_cancelled)
+		PositionConfiscated(AccountId, CurrencyId, Balance, DebitBalance),
 		/// Transfer vault (from, to)
 		TransferVault(AccountId, AccountId, CurrencyId),
 	}
@@ -58,20 +112,22 @@ decl_event!(
 
 decl_error! {
 	/// Error for vaults module.
-	pub enum Error {
+	pub enum Error for Module<T: Trait> {
 		DebitOverflow,
 		CollateralOverflow,
 		AmountIntoBalanceFailed,
 		BalanceIntoAmountFailed,
-		PositionWillUnsafe,
-		ExceedDebitValueHardCap,
 		UpdateStableCoinFailed,
 		CollateralInSufficient,
+		/// The totals of the currency hold less than a position taken out of them
+		TotalsInconsistent,
 	}
 }
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
 		fn deposit_event() = default;
 	}
 }
@@ -81,52 +137,250 @@ impl<T: Trait> Module<T> {
 		MODULE_ID.into_account()
 	}
 
+	/// Apply the pending migrations in order and return the weight consumed. Only the version is read once the
+	/// storage is up to date, so it's safe to run again.
+	pub fn on_runtime_upgrade() -> Weight {
+		let mut version = Self::storage_version();
+		let mut weight = STORAGE_ACCESS_WEIGHT;
+		while let Some(next) = version.next() {
+			weight = weight
+				.saturating_add(Self::migrate_to(next))
+				.saturating_add(STORAGE_ACCESS_WEIGHT);
+			<Version>::put(next);
+			version = next;
+		}
+		weight
+	}
+
+	/// Migrate the storage to the layout of `version` from the one before it, returns the weight consumed.
+	fn migrate_to(version: StorageVersion) -> Weight {
+		match version {
+			// the first version is never migrated to
+			StorageVersion::V0 => 0,
+			StorageVersion::V1 => Self::move_positions(),
+			StorageVersion::V2 => Self::link_position_owners(),
+		}
+	}
+
+	/// Move the positions of the accounts to migrate from `Collaterals` and `Debits` into `Positions`, and remove
+	/// what is left of the accounts in the old maps, returns the weight consumed. The totals already count them.
+	fn move_positions() -> Weight {
+		let currency_ids = T::CollateralCurrencyIds::get();
+		let mut weight: Weight = 0;
+		for who in T::PositionOwnersToMigrate::get() {
+			for &currency_id in currency_ids.iter() {
+				let position = Position::new(
+					<Collaterals<T>>::take(&who, currency_id),
+					<Debits<T>>::take(&who, currency_id),
+				);
+				if !position.is_empty() {
+					<Positions<T>>::insert(&who, currency_id, position);
+				}
+				weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT.saturating_mul(3));
+			}
+			<Collaterals<T>>::remove_prefix(&who);
+			<Debits<T>>::remove_prefix(&who);
+			weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT.saturating_mul(2));
+		}
+		weight
+	}
+
+	/// Link the accounts to migrate with a position which isn't linked in `PositionOwners` yet, so the offchain
+	/// worker scans them, returns the weight consumed.
+	fn link_position_owners() -> Weight {
+		let currency_ids = T::CollateralCurrencyIds::get();
+		let mut weight: Weight = 0;
+		for who in T::PositionOwnersToMigrate::get() {
+			for &currency_id in currency_ids.iter() {
+				if !Self::positions(&who, currency_id).is_empty() && !<PositionOwners<T>>::exists(currency_id, &who) {
+					Self::insert_position_owner(currency_id, &who);
+				}
+				// the position, the linkage, the first owner and its linkage, the new linkage and the first owner
+				weight = weight.saturating_add(STORAGE_ACCESS_WEIGHT.saturating_mul(6));
+			}
+		}
+		weight
+	}
+
+	/// Check the total collaterals against the balances of the module account, and the totals against the
+	/// positions of `accounts`, which should be all the owners of CDPs.
+	#[cfg(feature = "std")]
+	pub fn audit(currency_ids: &[CurrencyIdOf<T>], accounts: &[T::AccountId]) -> AuditResult<CurrencyIdOf<T>> {
+		let module_account = Self::account_id();
+		let mut auditor = Auditor::default();
+
+		for &currency_id in currency_ids {
+			let total_collaterals = Self::total_collaterals(currency_id);
+			auditor.check(
+				"vaults: collaterals held",
+				currency_id,
+				total_collaterals,
+				T::Currency::balance(currency_id, &module_account),
+			);
+
+			let (collaterals, debits) = accounts.iter().map(|who| Self::positions(who, currency_id)).fold(
+				(BalanceOf::<T>::zero(), DebitBalanceOf::<T>::zero()),
+				|(collaterals, debits), position| {
+					(
+						collaterals.saturating_add(position.collateral),
+						debits.saturating_add(position.debit),
+					)
+				},
+			);
+			auditor.check("vaults: total collaterals", currency_id, total_collaterals, collaterals);
+			auditor.check(
+				"vaults: total debits",
+				currency_id,
+				Self::total_debits(currency_id),
+				debits,
+			);
+		}
+
+		auditor.finish()
+	}
+
+	/// At most `max_owners` of the position owners of `currency_id` in order, from `start`, or from the first one if
+	/// `start` is `None` or has closed its position since. Returns them and the owner to continue from, `None` once
+	/// the last one is returned.
+	pub fn position_owners(
+		currency_id: CurrencyIdOf<T>,
+		start: Option<T::AccountId>,
+		max_owners: u32,
+	) -> (Vec<T::AccountId>, Option<T::AccountId>) {
+		let mut next = start
+			.filter(|who| <PositionOwners<T>>::exists(currency_id, who))
+			.or_else(|| Self::first_position_owner(currency_id));
+		let mut owners = vec![];
+		while let Some(who) = next.take() {
+			if owners.len() as u32 >= max_owners {
+				return (owners, Some(who));
+			}
+			next = Self::position_owner_linkage(currency_id, &who).and_then(|linkage| linkage.next);
+			owners.push(who);
+		}
+		(owners, None)
+	}
+
+	/// Link `who` in front of the position owners of `currency_id`, as its position is opened.
+	fn insert_position_owner(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let next = Self::first_position_owner(currency_id);
+		if let Some(next) = &next {
+			<PositionOwners<T>>::mutate(currency_id, next, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.previous = Some(who.clone());
+				}
+			});
+		}
+		<PositionOwners<T>>::insert(currency_id, who, Linkage { previous: None, next });
+		<FirstPositionOwner<T>>::insert(currency_id, who);
+	}
+
+	/// Unlink `who` from the position owners of `currency_id`, as its position is closed.
+	fn remove_position_owner(currency_id: CurrencyIdOf<T>, who: &T::AccountId) {
+		let Linkage { previous, next } = match Self::position_owner_linkage(currency_id, who) {
+			Some(linkage) => linkage,
+			None => return,
+		};
+		<PositionOwners<T>>::remove(currency_id, who);
+		if let Some(next) = &next {
+			<PositionOwners<T>>::mutate(currency_id, next, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.previous = previous.clone();
+				}
+			});
+		}
+		match previous {
+			Some(previous) => <PositionOwners<T>>::mutate(currency_id, previous, |linkage| {
+				if let Some(linkage) = linkage {
+					linkage.next = next;
+				}
+			}),
+			None => match next {
+				Some(next) => <FirstPositionOwner<T>>::insert(currency_id, next),
+				None => <FirstPositionOwner<T>>::remove(currency_id),
+			},
+		}
+	}
+
+	pub fn debits(who: impl EncodeLike<T::AccountId>, currency_id: CurrencyIdOf<T>) -> DebitBalanceOf<T> {
+		Self::positions(who, currency_id).debit
+	}
+
+	pub fn collaterals(who: impl EncodeLike<T::AccountId>, currency_id: CurrencyIdOf<T>) -> BalanceOf<T> {
+		Self::positions(who, currency_id).collateral
+	}
+
 	// mutate collaterlas and debits, don't check position safe and don't mutate token
 	pub fn update_collaterals_and_debits(
 		who: T::AccountId,
 		currency_id: CurrencyIdOf<T>,
 		collaterals: AmountOf<T>,
 		debits: DebitAmountOf<T>,
-	) -> result::Result<(), Error> {
+	) -> DispatchResult {
 		// ensure mutate safe
 		Self::check_add_and_sub(&who, currency_id, collaterals, debits)?;
 		Self::update_vault(&who, currency_id, collaterals, debits)?;
-		Self::deposit_event(RawEvent::UpdateCollateralsAndDebits(
-			who,
-			currency_id,
-			collaterals,
-			debits,
-		));
+		Self::deposit_event(RawEvent::PositionUpdated(who, currency_id, collaterals, debits));
 
 		Ok(())
 	}
 
-	// mulate collaterals and debits and then mulate stable coin
-	pub fn update_position(
+	/// Take `collateral` and `debit` away from the position of `who` without moving the tokens, the collateral is
+	/// for the caller to move out of the module account, e.g. when the position is liquidated or settled.
+	pub fn confiscate_collateral_and_debit(
 		who: T::AccountId,
 		currency_id: CurrencyIdOf<T>,
+		collateral: BalanceOf<T>,
+		debit: DebitBalanceOf<T>,
+	) -> DispatchResult {
+		let collateral_adjustment =
+			-TryInto::<AmountOf<T>>::try_into(collateral).map_err(|_| Error::<T>::BalanceIntoAmountFailed)?;
+		let debit_adjustment =
+			-TryInto::<DebitAmountOf<T>>::try_into(debit).map_err(|_| Error::<T>::BalanceIntoAmountFailed)?;
+
+		// ensure mutate safe
+		Self::check_add_and_sub(&who, currency_id, collateral_adjustment, debit_adjustment)?;
+		Self::update_vault(&who, currency_id, collateral_adjustment, debit_adjustment)?;
+		Self::deposit_event(RawEvent::PositionConfiscated(who, currency_id, collateral, debit));
+
+		Ok(())
+	}
+
+	/// Ensure the position of `who` can be adjusted, the balances moved by `update_position` are left unchecked.
+	pub fn check_position_update(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
 		collaterals: AmountOf<T>,
 		debits: DebitAmountOf<T>,
-	) -> result::Result<(), Error> {
+	) -> DispatchResult {
 		// ensure mutate safe
-		Self::check_add_and_sub(&who, currency_id, collaterals, debits)?;
+		Self::check_add_and_sub(who, currency_id, collaterals, debits)?;
 
 		// ensure debits cap
-		T::RiskManager::check_debit_cap(currency_id, debits).map_err(|_| Error::ExceedDebitValueHardCap)?;
+		T::RiskManager::check_debit_cap(currency_id, debits)?;
 
 		// ensure cdp safe
-		T::RiskManager::check_position_adjustment(&who, currency_id, collaterals, debits)
-			.map_err(|_| Error::PositionWillUnsafe)?;
+		T::RiskManager::check_position_adjustment(who, currency_id, collaterals, debits)
+	}
+
+	// mulate collaterals and debits and then mulate stable coin
+	pub fn update_position(
+		who: T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		collaterals: AmountOf<T>,
+		debits: DebitAmountOf<T>,
+	) -> DispatchResult {
+		Self::check_position_update(&who, currency_id, collaterals, debits)?;
 
 		// ensure account has sufficient balance
 		Self::check_balance(&who, currency_id, collaterals)?;
 
 		// amount -> balance
 		let collateral_balance =
-			TryInto::<BalanceOf<T>>::try_into(collaterals.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
+			TryInto::<BalanceOf<T>>::try_into(collaterals.abs()).map_err(|_| Error::<T>::AmountIntoBalanceFailed)?;
 
 		// update stable coin
-		T::DebitCurrency::update_balance(currency_id, &who, debits).map_err(|_| Error::UpdateStableCoinFailed)?;
+		T::DebitCurrency::update_balance(currency_id, &who, debits).map_err(|_| Error::<T>::UpdateStableCoinFailed)?;
 
 		let module_account = Self::account_id();
 		// update collateral asset
@@ -142,32 +396,30 @@ impl<T: Trait> Module<T> {
 		Self::update_vault(&who, currency_id, collaterals, debits)
 			.expect("Will never fail ensured by check_add_and_sub");
 
-		Self::deposit_event(RawEvent::UpdatePosition(who, currency_id, collaterals, debits));
+		Self::deposit_event(RawEvent::PositionUpdated(who, currency_id, collaterals, debits));
 
 		Ok(())
 	}
 
 	// transfer vault
-	pub fn transfer(from: T::AccountId, to: T::AccountId, currency_id: CurrencyIdOf<T>) -> result::Result<(), Error> {
+	pub fn transfer(from: T::AccountId, to: T::AccountId, currency_id: CurrencyIdOf<T>) -> DispatchResult {
 		// get `from` position data
 		let collateral: BalanceOf<T> = Self::collaterals(&from, currency_id);
 		let debit: DebitBalanceOf<T> = Self::debits(&from, currency_id);
 
 		// banlance -> amount
 		let collateral: AmountOf<T> =
-			TryInto::<AmountOf<T>>::try_into(collateral).map_err(|_| Error::BalanceIntoAmountFailed)?;
+			TryInto::<AmountOf<T>>::try_into(collateral).map_err(|_| Error::<T>::BalanceIntoAmountFailed)?;
 		let debit: DebitAmountOf<T> =
-			TryInto::<DebitAmountOf<T>>::try_into(debit).map_err(|_| Error::BalanceIntoAmountFailed)?;
+			TryInto::<DebitAmountOf<T>>::try_into(debit).map_err(|_| Error::<T>::BalanceIntoAmountFailed)?;
 
 		// ensure mutate safe
 		Self::check_add_and_sub(&from, currency_id, -collateral, -debit)?;
 		Self::check_add_and_sub(&to, currency_id, collateral, debit)?;
 
 		// ensure positions are safe after transfered
-		T::RiskManager::check_position_adjustment(&from, currency_id, -collateral, -debit)
-			.map_err(|_| Error::PositionWillUnsafe)?;
-		T::RiskManager::check_position_adjustment(&to, currency_id, collateral, debit)
-			.map_err(|_| Error::PositionWillUnsafe)?;
+		T::RiskManager::check_position_adjustment(&from, currency_id, -collateral, -debit)?;
+		T::RiskManager::check_position_adjustment(&to, currency_id, collateral, debit)?;
 
 		// execute transfer
 		Self::update_vault(&from, currency_id, -collateral, -debit)
@@ -180,21 +432,20 @@ impl<T: Trait> Module<T> {
 	}
 
 	/// check `who` has sufficient balance
-	fn check_balance(
-		who: &T::AccountId,
-		currency_id: CurrencyIdOf<T>,
-		collateral: AmountOf<T>,
-	) -> result::Result<(), Error> {
+	pub fn check_balance(who: &T::AccountId, currency_id: CurrencyIdOf<T>, collateral: AmountOf<T>) -> DispatchResult {
 		let collaterals_balance =
-			TryInto::<BalanceOf<T>>::try_into(collateral.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
+			TryInto::<BalanceOf<T>>::try_into(collateral.abs()).map_err(|_| Error::<T>::AmountIntoBalanceFailed)?;
 
 		let module_balance = T::Currency::balance(currency_id, &Self::account_id());
 		let who_balance = T::Currency::balance(currency_id, who);
 
 		if collateral.is_positive() {
-			ensure!(who_balance >= collaterals_balance, Error::CollateralInSufficient);
+			ensure!(who_balance >= collaterals_balance, Error::<T>::CollateralInSufficient);
 		} else {
-			ensure!(module_balance >= collaterals_balance, Error::CollateralInSufficient);
+			ensure!(
+				module_balance >= collaterals_balance,
+				Error::<T>::CollateralInSufficient
+			);
 		}
 
 		Ok(())
@@ -206,95 +457,98 @@ impl<T: Trait> Module<T> {
 		currency_id: CurrencyIdOf<T>,
 		collaterals: AmountOf<T>,
 		debits: DebitAmountOf<T>,
-	) -> result::Result<(), Error> {
+	) -> DispatchResult {
+		Self::adjusted_vault(who, currency_id, collaterals, debits).map(|_| ())
+	}
+
+	/// The position of `who` and the totals of `currency_id` after the adjustment, checked for overflow. Taking more
+	/// out of the totals than they hold while the position has it means the totals are out of sync with the
+	/// positions.
+	fn adjusted_vault(
+		who: &T::AccountId,
+		currency_id: CurrencyIdOf<T>,
+		collaterals: AmountOf<T>,
+		debits: DebitAmountOf<T>,
+	) -> Result<(PositionOf<T>, BalanceOf<T>, DebitBalanceOf<T>), DispatchError> {
 		// judge collaterals and debits are negative or positive
 		let collaterals_balance =
-			TryInto::<BalanceOf<T>>::try_into(collaterals.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
+			TryInto::<BalanceOf<T>>::try_into(collaterals.abs()).map_err(|_| Error::<T>::AmountIntoBalanceFailed)?;
 		let debits_balance =
-			TryInto::<DebitBalanceOf<T>>::try_into(debits.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
+			TryInto::<DebitBalanceOf<T>>::try_into(debits.abs()).map_err(|_| Error::<T>::AmountIntoBalanceFailed)?;
 
-		// check collaterals update
-		if collaterals.is_positive() {
-			ensure!(
-				Self::collaterals(who, currency_id)
-					.checked_add(&collaterals_balance)
-					.is_some(),
-				Error::CollateralOverflow
-			);
-			ensure!(
-				Self::total_collaterals(currency_id)
-					.checked_add(&collaterals_balance)
-					.is_some(),
-				Error::CollateralOverflow
-			);
-		} else {
-			ensure!(
-				Self::collaterals(who, currency_id)
-					.checked_sub(&collaterals_balance)
-					.is_some(),
-				Error::CollateralOverflow
-			);
-			ensure!(
-				Self::total_collaterals(currency_id)
-					.checked_sub(&collaterals_balance)
-					.is_some(),
-				Error::CollateralOverflow
-			);
-		}
+		let mut position = Self::positions(who, currency_id);
+		let total_collaterals = Self::total_collaterals(currency_id);
+		let total_debits = Self::total_debits(currency_id);
 
 		// check collaterals update
-		if debits.is_positive() {
-			ensure!(
-				Self::debits(who, currency_id).checked_add(&debits_balance).is_some(),
-				Error::DebitOverflow
-			);
-			ensure!(
-				Self::total_debits(currency_id).checked_add(&debits_balance).is_some(),
-				Error::DebitOverflow
-			);
+		let total_collaterals = if collaterals.is_positive() {
+			position.collateral = position
+				.collateral
+				.checked_add(&collaterals_balance)
+				.ok_or(Error::<T>::CollateralOverflow)?;
+			total_collaterals
+				.checked_add(&collaterals_balance)
+				.ok_or(Error::<T>::CollateralOverflow)?
 		} else {
-			ensure!(
-				Self::debits(who, currency_id).checked_sub(&debits_balance).is_some(),
-				Error::DebitOverflow
-			);
-			ensure!(
-				Self::total_debits(currency_id).checked_sub(&debits_balance).is_some(),
-				Error::DebitOverflow
-			);
-		}
-
-		Ok(())
+			position.collateral = position
+				.collateral
+				.checked_sub(&collaterals_balance)
+				.ok_or(Error::<T>::CollateralOverflow)?;
+			total_collaterals
+				.checked_sub(&collaterals_balance)
+				.ok_or(Error::<T>::TotalsInconsistent)?
+		};
+
+		// check debits update
+		let total_debits = if debits.is_positive() {
+			position.debit = position
+				.debit
+				.checked_add(&debits_balance)
+				.ok_or(Error::<T>::DebitOverflow)?;
+			total_debits
+				.checked_add(&debits_balance)
+				.ok_or(Error::<T>::DebitOverflow)?
+		} else {
+			position.debit = position
+				.debit
+				.checked_sub(&debits_balance)
+				.ok_or(Error::<T>::DebitOverflow)?;
+			total_debits
+				.checked_sub(&debits_balance)
+				.ok_or(Error::<T>::TotalsInconsistent)?
+		};
+
+		Ok((position, total_collaterals, total_debits))
 	}
 
+	/// Apply the adjustment to the position of `who` and the totals of `currency_id` together, nothing is written if
+	/// any of them would overflow.
 	fn update_vault(
 		who: &T::AccountId,
 		currency_id: CurrencyIdOf<T>,
 		collaterals: AmountOf<T>,
 		debits: DebitAmountOf<T>,
-	) -> result::Result<(), Error> {
-		// judge collaterals and debits are negative or positive
-		let collaterals_balance =
-			TryInto::<BalanceOf<T>>::try_into(collaterals.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
-		let debits_balance =
-			TryInto::<DebitBalanceOf<T>>::try_into(debits.abs()).map_err(|_| Error::AmountIntoBalanceFailed)?;
-
-		// updaet collaterals record
-		if collaterals.is_positive() {
-			<Collaterals<T>>::mutate(who, currency_id, |balance| *balance += collaterals_balance);
-			<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance += collaterals_balance);
-		} else {
-			<Collaterals<T>>::mutate(who, currency_id, |balance| *balance -= collaterals_balance);
-			<TotalCollaterals<T>>::mutate(currency_id, |balance| *balance -= collaterals_balance);
-		}
-
-		// updaet debits record
-		if debits.is_positive() {
-			<Debits<T>>::mutate(who, currency_id, |balance| *balance += debits_balance);
-			<TotalDebits<T>>::mutate(currency_id, |balance| *balance += debits_balance);
+	) -> DispatchResult {
+		let was_empty = Self::positions(who, currency_id).is_empty();
+		let (position, total_collaterals, total_debits) = Self::adjusted_vault(who, currency_id, collaterals, debits)?;
+
+		<TotalCollaterals<T>>::insert(currency_id, total_collaterals);
+		<TotalDebits<T>>::insert(currency_id, total_debits);
+
+		let debit_balance = position.debit;
+		// remove empty position from storage
+		if position.is_empty() {
+			<Positions<T>>::remove(who, currency_id);
+			if !was_empty {
+				Self::remove_position_owner(currency_id, who);
+			}
 		} else {
-			<Debits<T>>::mutate(who, currency_id, |balance| *balance -= debits_balance);
-			<TotalDebits<T>>::mutate(currency_id, |balance| *balance -= debits_balance);
+			<Positions<T>>::insert(who, currency_id, position);
+			if was_empty {
+				Self::insert_position_owner(currency_id, who);
+			}
 		}
+		T::OnUpdateLoan::on_update_loan(who, currency_id, debit_balance);
 
 		Ok(())
 	}
@@ -3,9 +3,9 @@
 #![cfg(test)]
 
 use frame_support::{impl_outer_origin, parameter_types};
-use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use sp_runtime::{testing::Header, traits::IdentityLookup, DispatchError, DispatchResult, Perbill};
 use su_primitives::H256;
-use support::RiskManager;
+use support::{DebitExchangeRateConvertor, ExchangeRate, ExchangeRateProvider, RiskManager};
 
 use super::*;
 
@@ -29,18 +29,19 @@ pub type Balance = u64;
 pub type DebitBalance = u64;
 pub type Amount = i64;
 pub type DebitAmount = i64;
-pub type CurrencyId = u32;
+pub type CurrencyId = primitives::CurrencyId;
 pub const ALICE: AccountId = 1;
-pub const NATIVE_CURRENCY_ID: CurrencyId = 0;
-pub const AUSD: CurrencyId = 1;
-pub const X_TOKEN_ID: CurrencyId = 2;
-pub const Y_TOKEN_ID: CurrencyId = 3;
-
-// mock convert
-pub struct MockConvert;
-impl Convert<(CurrencyId, DebitBalance), Balance> for MockConvert {
-	fn convert(a: (CurrencyId, DebitBalance)) -> Balance {
-		(a.1 / DebitBalance::from(2u64)).into()
+pub const BOB: AccountId = 2;
+pub const NATIVE_CURRENCY_ID: CurrencyId = CurrencyId::ACA;
+pub const AUSD: CurrencyId = CurrencyId::AUSD;
+pub const X_TOKEN_ID: CurrencyId = CurrencyId::BTC;
+pub const Y_TOKEN_ID: CurrencyId = CurrencyId::DOT;
+
+// mock exchange rate
+pub struct MockExchangeRateProvider;
+impl ExchangeRateProvider<CurrencyId> for MockExchangeRateProvider {
+	fn get_exchange_rate(_currency_id: CurrencyId) -> ExchangeRate {
+		ExchangeRate::from_rational(1, 2)
 	}
 }
 
@@ -95,7 +96,7 @@ impl debits::Trait for Runtime {
 	type DebitBalance = DebitBalance;
 	type CurrencyId = CurrencyId;
 	type DebitAmount = DebitAmount;
-	type Convert = MockConvert;
+	type Convert = DebitExchangeRateConvertor<MockExchangeRateProvider>;
 }
 
 // debit module
@@ -104,36 +105,43 @@ pub type DebitCurrency = debits::Module<Runtime>;
 // mock risk manager
 pub struct MockRiskManager;
 impl RiskManager<AccountId, CurrencyId, Amount, DebitAmount> for MockRiskManager {
-	type Error = &'static str;
 	#[allow(unused_variables)]
 	fn check_position_adjustment(
 		account_id: &AccountId,
 		currency_id: CurrencyId,
 		collaterals: Amount,
 		debits: DebitAmount,
-	) -> Result<(), Self::Error> {
+	) -> DispatchResult {
 		match currency_id {
-			2u32 => Err("mock error"),
-			3u32 => Ok(()),
-			_ => Err("mock error"),
+			X_TOKEN_ID => Err(DispatchError::Other("mock position unsafe")),
+			Y_TOKEN_ID => Ok(()),
+			_ => Err(DispatchError::Other("mock position unsafe")),
 		}
 	}
 	#[allow(unused_variables)]
-	fn check_debit_cap(currency_id: CurrencyId, debits: DebitAmount) -> Result<(), Self::Error> {
+	fn check_debit_cap(currency_id: CurrencyId, debits: DebitAmount) -> DispatchResult {
 		match (currency_id, debits) {
-			(2u32, 1000i64) => Err("mock error"),
-			(3u32, 1000i64) => Err("mock error"),
+			(X_TOKEN_ID, 1000i64) => Err(DispatchError::Other("mock exceed debit value cap")),
+			(Y_TOKEN_ID, 1000i64) => Err(DispatchError::Other("mock exceed debit value cap")),
 			(_, _) => Ok(()),
 		}
 	}
 }
 
+parameter_types! {
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![X_TOKEN_ID, Y_TOKEN_ID];
+	pub const PositionOwnersToMigrate: Vec<AccountId> = vec![ALICE, BOB];
+}
+
 impl Trait for Runtime {
 	type Event = ();
-	type Convert = MockConvert;
+	type Convert = DebitExchangeRateConvertor<MockExchangeRateProvider>;
 	type Currency = Currencies;
 	type DebitCurrency = DebitCurrency;
 	type RiskManager = MockRiskManager;
+	type OnUpdateLoan = ();
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = PositionOwnersToMigrate;
 }
 
 pub type VaultsModule = Module<Runtime>;
@@ -154,6 +162,7 @@ impl system::Trait for Runtime {
 	type MaximumBlockLength = MaximumBlockLength;
 	type AvailableBlockRatio = AvailableBlockRatio;
 	type Version = ();
+	type ModuleToIndex = ();
 }
 
 pub struct ExtBuilder {
@@ -173,7 +182,12 @@ impl Default for ExtBuilder {
 }
 
 impl ExtBuilder {
-	pub fn build(self) -> runtime_io::TestExternalities {
+	pub fn build(self) -> AuditedExternalities {
+		AuditedExternalities(self.build_unaudited())
+	}
+
+	/// Externalities without the audit, for the tests breaking the accounting on purpose.
+	pub fn build_unaudited(self) -> runtime_io::TestExternalities {
 		let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
 		orml_tokens::GenesisConfig::<Runtime> {
 			tokens: self.currency_ids,
@@ -185,3 +199,16 @@ impl ExtBuilder {
 		t.into()
 	}
 }
+
+/// Externalities auditing the accounting after every execution.
+pub struct AuditedExternalities(runtime_io::TestExternalities);
+
+impl AuditedExternalities {
+	pub fn execute_with<R>(&mut self, execute: impl FnOnce() -> R) -> R {
+		self.0.execute_with(|| {
+			let result = execute();
+			assert_eq!(VaultsModule::audit(&[X_TOKEN_ID, Y_TOKEN_ID], &[ALICE, BOB]), Ok(()));
+			result
+		})
+	}
+}
@@ -3,8 +3,12 @@
 #![cfg(test)]
 
 use super::*;
-use frame_support::{assert_noop, assert_ok};
-use mock::{Currencies, ExtBuilder, VaultsModule, ALICE, AUSD, X_TOKEN_ID, Y_TOKEN_ID};
+use frame_support::{assert_noop, assert_ok, storage::unhashed, StorageDoubleMap, StorageMap, StorageValue};
+use mock::{
+	AccountId, Currencies, CurrencyId, ExtBuilder, Runtime, VaultsModule, ALICE, AUSD, BOB, X_TOKEN_ID, Y_TOKEN_ID,
+};
+use runtime_io::hashing::blake2_256;
+use sp_runtime::DispatchError;
 
 #[test]
 fn update_position_should_work() {
@@ -20,7 +24,7 @@ fn update_position_with_larger_than_collater_currency_should_not_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_noop!(
 			VaultsModule::update_position(ALICE, Y_TOKEN_ID, 100000, 100),
-			Error::CollateralInSufficient
+			Error::<Runtime>::CollateralInSufficient
 		);
 	});
 }
@@ -62,7 +66,7 @@ fn update_position_with_under_safe_should_not_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_noop!(
 			VaultsModule::update_position(ALICE, X_TOKEN_ID, 1, 1),
-			Error::PositionWillUnsafe
+			DispatchError::Other("mock position unsafe")
 		);
 	});
 }
@@ -72,14 +76,36 @@ fn update_position_with_overflow_debits_cap_should_not_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_noop!(
 			VaultsModule::update_position(ALICE, X_TOKEN_ID, 100, 1000),
-			Error::ExceedDebitValueHardCap
+			DispatchError::Other("mock exceed debit value cap")
 		);
 	});
 }
 
 #[test]
-fn update_collaterals_and_debits_should_work() {
+fn check_position_update_leaves_balances_unchecked() {
 	ExtBuilder::default().build().execute_with(|| {
+		// more collateral than alice has passes, update_position checks the balance
+		assert_ok!(VaultsModule::check_position_update(&ALICE, Y_TOKEN_ID, 100000, 100));
+		assert_noop!(
+			VaultsModule::update_position(ALICE, Y_TOKEN_ID, 100000, 100),
+			Error::<Runtime>::CollateralInSufficient
+		);
+		assert_eq!(
+			VaultsModule::check_position_update(&ALICE, X_TOKEN_ID, 1, 1),
+			Err(DispatchError::Other("mock position unsafe"))
+		);
+		assert_eq!(
+			VaultsModule::check_position_update(&ALICE, X_TOKEN_ID, 100, 1000),
+			Err(DispatchError::Other("mock exceed debit value cap"))
+		);
+		assert_eq!(VaultsModule::positions(ALICE, Y_TOKEN_ID), Default::default());
+	});
+}
+
+#[test]
+fn update_collaterals_and_debits_should_work() {
+	// the collaterals are recorded without being held by the module
+	ExtBuilder::default().build_unaudited().execute_with(|| {
 		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, 100, 100));
 		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, -10, -10));
 		assert_eq!(VaultsModule::collaterals(ALICE, Y_TOKEN_ID), 90);
@@ -98,3 +124,296 @@ fn update_collaterals_and_debits_with_zero_should_work() {
 		assert_eq!(VaultsModule::debits(ALICE, Y_TOKEN_ID), 0);
 	});
 }
+
+#[test]
+fn confiscate_collateral_and_debit_should_work() {
+	// the collaterals are recorded without being held by the module
+	ExtBuilder::default().build_unaudited().execute_with(|| {
+		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, 100, 100));
+		assert_ok!(VaultsModule::confiscate_collateral_and_debit(ALICE, Y_TOKEN_ID, 40, 60));
+		assert_eq!(VaultsModule::collaterals(ALICE, Y_TOKEN_ID), 60);
+		assert_eq!(VaultsModule::debits(ALICE, Y_TOKEN_ID), 40);
+		assert_eq!(VaultsModule::total_collaterals(Y_TOKEN_ID), 60);
+		assert_eq!(VaultsModule::total_debits(Y_TOKEN_ID), 40);
+		assert_noop!(
+			VaultsModule::confiscate_collateral_and_debit(ALICE, Y_TOKEN_ID, 61, 0),
+			Error::<Runtime>::CollateralOverflow
+		);
+	});
+}
+
+#[test]
+fn inconsistent_totals_should_not_work() {
+	ExtBuilder::default().build_unaudited().execute_with(|| {
+		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, 100, 100));
+		<TotalCollaterals<Runtime>>::insert(Y_TOKEN_ID, 50);
+		<TotalDebits<Runtime>>::insert(Y_TOKEN_ID, 50);
+		assert_noop!(
+			VaultsModule::confiscate_collateral_and_debit(ALICE, Y_TOKEN_ID, 60, 0),
+			Error::<Runtime>::TotalsInconsistent
+		);
+		assert_noop!(
+			VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, 0, -60),
+			Error::<Runtime>::TotalsInconsistent
+		);
+	});
+}
+
+/// Each of `owners` of `currency_id` is linked back to the one before it.
+fn assert_owners_linked(currency_id: CurrencyId, owners: &[AccountId]) {
+	let mut previous = None;
+	for who in owners {
+		assert_eq!(
+			VaultsModule::position_owner_linkage(currency_id, who).map(|linkage| linkage.previous),
+			Some(previous)
+		);
+		previous = Some(*who);
+	}
+}
+
+/// The totals of every currency are the sums of the positions of `accounts`, and the owners are the accounts with
+/// a position.
+fn assert_totals_match_positions(accounts: &[AccountId]) {
+	for currency_id in vec![X_TOKEN_ID, Y_TOKEN_ID] {
+		let positions: Vec<_> = accounts
+			.iter()
+			.map(|who| (*who, VaultsModule::positions(who, currency_id)))
+			.collect();
+		let (collaterals, debits) = positions.iter().fold((0, 0), |(collaterals, debits), (_, position)| {
+			(collaterals + position.collateral, debits + position.debit)
+		});
+		assert_eq!(VaultsModule::total_collaterals(currency_id), collaterals);
+		assert_eq!(VaultsModule::total_debits(currency_id), debits);
+
+		let (mut owners, next) = VaultsModule::position_owners(currency_id, None, u32::max_value());
+		assert_eq!(next, None);
+		assert_owners_linked(currency_id, &owners);
+		owners.sort();
+		let expected_owners: Vec<_> = positions
+			.iter()
+			.filter(|(_, position)| !position.is_empty())
+			.map(|(who, _)| *who)
+			.collect();
+		assert_eq!(owners, expected_owners);
+	}
+}
+
+#[test]
+fn totals_should_follow_random_operations() {
+	// the collaterals are recorded without being held by the module
+	ExtBuilder::default().build_unaudited().execute_with(|| {
+		let accounts: Vec<AccountId> = vec![ALICE, 2, 3, 4];
+		let mut seed: u64 = 42;
+		let mut next = |bound: u64| {
+			seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			(seed >> 33) % bound
+		};
+		let mut applied = 0;
+
+		for _ in 0..500 {
+			let who = accounts[next(4) as usize];
+			let currency_id = if next(2) == 0 { X_TOKEN_ID } else { Y_TOKEN_ID };
+			let result = match next(4) {
+				0 => VaultsModule::update_collaterals_and_debits(
+					who,
+					currency_id,
+					next(151) as i64 - 50,
+					next(151) as i64 - 50,
+				),
+				1 => VaultsModule::confiscate_collateral_and_debit(who, currency_id, next(60), next(60)),
+				2 => VaultsModule::transfer(who, accounts[next(4) as usize], currency_id),
+				_ => VaultsModule::update_position(who, currency_id, next(151) as i64 - 50, next(151) as i64 - 50),
+			};
+			if result.is_ok() {
+				applied += 1;
+			}
+			assert_totals_match_positions(&accounts);
+		}
+		assert!(applied > 50);
+	});
+}
+
+#[test]
+fn empty_position_should_be_removed_from_storage() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(VaultsModule::update_collaterals_and_debits(ALICE, Y_TOKEN_ID, 100, 100));
+		assert_eq!(
+			VaultsModule::positions(ALICE, Y_TOKEN_ID),
+			Position {
+				collateral: 100,
+				debit: 100
+			}
+		);
+		assert_eq!(<Positions<Runtime>>::exists(ALICE, Y_TOKEN_ID), true);
+		assert_ok!(VaultsModule::update_collaterals_and_debits(
+			ALICE, Y_TOKEN_ID, -100, -100
+		));
+		assert_eq!(<Positions<Runtime>>::exists(ALICE, Y_TOKEN_ID), false);
+	});
+}
+
+#[test]
+fn position_owners_should_be_tracked() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![], None));
+		assert_ok!(VaultsModule::update_position(ALICE, Y_TOKEN_ID, 100, 50));
+		assert_ok!(VaultsModule::update_position(ALICE, Y_TOKEN_ID, 10, 0));
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![ALICE], None));
+		assert_eq!(VaultsModule::position_owners(X_TOKEN_ID, None, 10), (vec![], None));
+		assert_ok!(VaultsModule::update_position(ALICE, Y_TOKEN_ID, -110, -50));
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![], None));
+		assert_eq!(VaultsModule::first_position_owner(Y_TOKEN_ID), None);
+	});
+}
+
+#[test]
+fn position_owners_should_be_paged() {
+	// the collaterals are recorded without being held by the module
+	ExtBuilder::default().build_unaudited().execute_with(|| {
+		for who in 1..=5 {
+			assert_ok!(VaultsModule::update_collaterals_and_debits(who, Y_TOKEN_ID, 100, 0));
+		}
+		// the last one to open a position comes first
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, None, 10),
+			(vec![5, 4, 3, 2, 1], None)
+		);
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, None, 2),
+			(vec![5, 4], Some(3))
+		);
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, Some(3), 2),
+			(vec![3, 2], Some(1))
+		);
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, Some(1), 2), (vec![1], None));
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 0), (vec![], Some(5)));
+
+		// closed in the middle, at the front and at the back
+		assert_ok!(VaultsModule::update_collaterals_and_debits(3, Y_TOKEN_ID, -100, 0));
+		assert_ok!(VaultsModule::update_collaterals_and_debits(5, Y_TOKEN_ID, -100, 0));
+		assert_ok!(VaultsModule::update_collaterals_and_debits(1, Y_TOKEN_ID, -100, 0));
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![4, 2], None));
+		assert_owners_linked(Y_TOKEN_ID, &[4, 2]);
+		// an owner who has closed its position since starts over from the first one
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, Some(3), 10),
+			(vec![4, 2], None)
+		);
+
+		assert_ok!(VaultsModule::update_collaterals_and_debits(3, Y_TOKEN_ID, 100, 0));
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, None, 10),
+			(vec![3, 4, 2], None)
+		);
+		assert_owners_linked(Y_TOKEN_ID, &[3, 4, 2]);
+	});
+}
+
+/// The raw key of the position of `who` in `currency_id` in the double map `storage` from before `V1`, the first
+/// key hashed with the prefix of the map.
+fn old_position_key(storage: &[u8], who: AccountId, currency_id: CurrencyId) -> Vec<u8> {
+	let mut prefixed_who = b"Vaults ".to_vec();
+	prefixed_who.extend_from_slice(storage);
+	who.encode_to(&mut prefixed_who);
+	let mut key = blake2_256(&prefixed_who).to_vec();
+	key.extend_from_slice(&blake2_256(&currency_id.encode()));
+	key
+}
+
+#[test]
+fn on_runtime_upgrade_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		// written before the storage is versioned, in the double maps of the collaterals and the debits
+		unhashed::put(&old_position_key(b"Collaterals", ALICE, Y_TOKEN_ID), &100u64);
+		unhashed::put(&old_position_key(b"Debits", ALICE, Y_TOKEN_ID), &50u64);
+		unhashed::put(&old_position_key(b"Collaterals", ALICE, X_TOKEN_ID), &10u64);
+		unhashed::put(&old_position_key(b"Collaterals", BOB, Y_TOKEN_ID), &30u64);
+		unhashed::put(&old_position_key(b"Debits", BOB, Y_TOKEN_ID), &20u64);
+		<TotalCollaterals<Runtime>>::insert(X_TOKEN_ID, 10);
+		<TotalCollaterals<Runtime>>::insert(Y_TOKEN_ID, 130);
+		<TotalDebits<Runtime>>::insert(Y_TOKEN_ID, 70);
+		assert_ok!(Currencies::deposit(X_TOKEN_ID, &VaultsModule::account_id(), 10));
+		assert_ok!(Currencies::deposit(Y_TOKEN_ID, &VaultsModule::account_id(), 130));
+		assert!(!<Version>::exists());
+		assert_eq!(VaultsModule::storage_version(), StorageVersion::V0);
+
+		// both currencies of both accounts to migrate are moved, and the owners linked
+		assert_eq!(VaultsModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT * 43);
+		assert_eq!(VaultsModule::storage_version(), StorageVersion::V2);
+		assert_eq!(VaultsModule::storage_version().next(), None);
+		assert_eq!(VaultsModule::positions(ALICE, Y_TOKEN_ID), Position::new(100, 50));
+		assert_eq!(VaultsModule::positions(ALICE, X_TOKEN_ID), Position::new(10, 0));
+		assert_eq!(VaultsModule::positions(BOB, Y_TOKEN_ID), Position::new(30, 20));
+		assert!(!<Positions<Runtime>>::exists(BOB, X_TOKEN_ID));
+		for key in &[
+			old_position_key(b"Collaterals", ALICE, Y_TOKEN_ID),
+			old_position_key(b"Debits", ALICE, Y_TOKEN_ID),
+			old_position_key(b"Collaterals", ALICE, X_TOKEN_ID),
+			old_position_key(b"Collaterals", BOB, Y_TOKEN_ID),
+			old_position_key(b"Debits", BOB, Y_TOKEN_ID),
+		] {
+			assert_eq!(unhashed::get::<u64>(key), None);
+		}
+		assert_eq!(VaultsModule::total_collaterals(Y_TOKEN_ID), 130);
+		assert_eq!(VaultsModule::total_debits(Y_TOKEN_ID), 70);
+		assert_totals_match_positions(&[ALICE, BOB]);
+
+		// nothing pending, only the version is read
+		assert_eq!(VaultsModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT);
+		assert_eq!(VaultsModule::storage_version(), StorageVersion::V2);
+		assert_eq!(VaultsModule::positions(ALICE, Y_TOKEN_ID), Position::new(100, 50));
+		assert_eq!(VaultsModule::positions(BOB, Y_TOKEN_ID), Position::new(30, 20));
+		assert_eq!(VaultsModule::total_collaterals(Y_TOKEN_ID), 130);
+		assert_eq!(VaultsModule::total_debits(Y_TOKEN_ID), 70);
+	});
+}
+
+#[test]
+fn migration_links_the_owners_of_the_positions() {
+	ExtBuilder::default().build().execute_with(|| {
+		// positions from before their owners were linked
+		<Positions<Runtime>>::insert(ALICE, Y_TOKEN_ID, Position::new(100, 50));
+		<Positions<Runtime>>::insert(BOB, Y_TOKEN_ID, Position::new(30, 20));
+		<TotalCollaterals<Runtime>>::insert(Y_TOKEN_ID, 130);
+		<TotalDebits<Runtime>>::insert(Y_TOKEN_ID, 70);
+		assert_ok!(Currencies::deposit(Y_TOKEN_ID, &VaultsModule::account_id(), 130));
+		<Version>::put(StorageVersion::V1);
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![], None));
+
+		assert_eq!(VaultsModule::on_runtime_upgrade(), STORAGE_ACCESS_WEIGHT * 26);
+		assert_eq!(VaultsModule::storage_version(), StorageVersion::V2);
+		assert_eq!(
+			VaultsModule::position_owners(Y_TOKEN_ID, None, 10),
+			(vec![BOB, ALICE], None)
+		);
+		assert_eq!(VaultsModule::position_owners(X_TOKEN_ID, None, 10), (vec![], None));
+		assert_totals_match_positions(&[ALICE, BOB]);
+
+		// the linked owners are unlinked as their positions are closed
+		assert_ok!(Currencies::deposit(AUSD, &ALICE, 25));
+		assert_ok!(VaultsModule::update_position(ALICE, Y_TOKEN_ID, -100, -50));
+		assert_eq!(VaultsModule::position_owners(Y_TOKEN_ID, None, 10), (vec![BOB], None));
+		assert_totals_match_positions(&[ALICE, BOB]);
+	});
+}
+
+#[test]
+fn migrated_positions_can_be_adjusted() {
+	ExtBuilder::default().build().execute_with(|| {
+		unhashed::put(&old_position_key(b"Collaterals", ALICE, Y_TOKEN_ID), &100u64);
+		unhashed::put(&old_position_key(b"Debits", ALICE, Y_TOKEN_ID), &50u64);
+		<TotalCollaterals<Runtime>>::insert(Y_TOKEN_ID, 100);
+		<TotalDebits<Runtime>>::insert(Y_TOKEN_ID, 50);
+		assert_ok!(Currencies::deposit(Y_TOKEN_ID, &VaultsModule::account_id(), 100));
+		// the stable coin borrowed for the debit
+		assert_ok!(Currencies::deposit(AUSD, &ALICE, 25));
+		VaultsModule::on_runtime_upgrade();
+
+		assert_ok!(VaultsModule::update_position(ALICE, Y_TOKEN_ID, -100, -50));
+		assert!(!<Positions<Runtime>>::exists(ALICE, Y_TOKEN_ID));
+		assert_eq!(VaultsModule::total_collaterals(Y_TOKEN_ID), 0);
+		assert_eq!(VaultsModule::total_debits(Y_TOKEN_ID), 0);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 0);
+	});
+}
@@ -0,0 +1,199 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use orml_traits::MultiCurrency;
+use rstd::prelude::*;
+use sp_runtime::{
+	traits::{Saturating, SimpleArithmetic, Zero},
+	DispatchResult, RuntimeDebug,
+};
+use support::{FixedU128Ext, OnEmergencyShutdown, Ratio, VestingManager};
+use system::ensure_signed;
+
+mod mock;
+mod tests;
+
+type BalanceOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+type CurrencyIdOf<T> = <<T as Trait>::Currency as MultiCurrency<<T as system::Trait>::AccountId>>::CurrencyId;
+type VestingScheduleOf<T> = VestingSchedule<BalanceOf<T>, <T as system::Trait>::BlockNumber>;
+
+/// `total` released linearly over `duration` blocks since `start`.
+#[derive(Encode, Decode, Eq, PartialEq, Copy, Clone, Default, RuntimeDebug)]
+pub struct VestingSchedule<Balance, BlockNumber> {
+	/// Nothing is released before this block.
+	pub start: BlockNumber,
+	pub duration: BlockNumber,
+	/// Total amount of the schedule, including the claimed.
+	pub total: Balance,
+	pub claimed: Balance,
+}
+
+impl<Balance: SimpleArithmetic + Copy, BlockNumber: SimpleArithmetic + Copy> VestingSchedule<Balance, BlockNumber> {
+	pub fn end(&self) -> BlockNumber {
+		self.start.saturating_add(self.duration)
+	}
+
+	/// Amount released by block `now`, claimed or not. Rounded down until fully released.
+	pub fn released(&self, now: BlockNumber) -> Balance {
+		if now < self.start {
+			return Zero::zero();
+		}
+
+		let elapsed = now - self.start;
+		if elapsed >= self.duration {
+			return self.total;
+		}
+
+		Ratio::checked_from_rational(elapsed, self.duration)
+			.and_then(|ratio| ratio.checked_mul_int(&self.total))
+			.unwrap_or_else(Zero::zero)
+	}
+
+	/// Amount released but not claimed at block `now`.
+	pub fn claimable(&self, now: BlockNumber) -> Balance {
+		self.released(now).saturating_sub(self.claimed)
+	}
+}
+
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: MultiCurrency<Self::AccountId>;
+	/// The currency vested, minted when claimed.
+	type VestingCurrencyId: Get<CurrencyIdOf<Self>>;
+	/// The most schedules an account can vest at once, vesting more fails until one of them is fully claimed.
+	type MaxVestingSchedules: Get<u32>;
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+	{
+		/// Amount vested to account in a new schedule (account, amount)
+		Vest(AccountId, Balance),
+		/// Released amount claimed (account, amount)
+		ClaimVested(AccountId, Balance),
+	}
+);
+
+decl_error! {
+	/// Error for vesting module.
+	pub enum Error for Module<T: Trait> {
+		DepositFailed,
+		TooManyVestingSchedules,
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Vesting {
+		/// The schedules of the account which aren't fully claimed yet, in the order they were vested.
+		pub VestingSchedules get(fn vesting_schedules): map T::AccountId => Vec<VestingScheduleOf<T>>;
+		pub IsShutdown get(fn is_shutdown): bool;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		pub fn claim_vested(origin) {
+			let who = ensure_signed(origin)?;
+			Self::release(&who)?;
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Amount of `who` which can be claimed now, everything is released after shutdown.
+	pub fn claimable(who: &T::AccountId) -> BalanceOf<T> {
+		let now = <system::Module<T>>::block_number();
+		let is_shutdown = Self::is_shutdown();
+		Self::vesting_schedules(who)
+			.iter()
+			.fold(Zero::zero(), |amount: BalanceOf<T>, schedule| {
+				amount.saturating_add(Self::schedule_claimable(schedule, now, is_shutdown))
+			})
+	}
+
+	fn schedule_claimable(schedule: &VestingScheduleOf<T>, now: T::BlockNumber, is_shutdown: bool) -> BalanceOf<T> {
+		if is_shutdown {
+			schedule.total.saturating_sub(schedule.claimed)
+		} else {
+			schedule.claimable(now)
+		}
+	}
+
+	/// Deposit the claimable amount of every schedule to `who`, the schedules fully claimed are removed.
+	fn release(who: &T::AccountId) -> DispatchResult {
+		let mut schedules = Self::vesting_schedules(who);
+		if schedules.is_empty() {
+			return Ok(());
+		}
+
+		let now = <system::Module<T>>::block_number();
+		let is_shutdown = Self::is_shutdown();
+		let mut amount: BalanceOf<T> = Zero::zero();
+		for schedule in schedules.iter_mut() {
+			let claimable = Self::schedule_claimable(schedule, now, is_shutdown);
+			schedule.claimed = schedule.claimed.saturating_add(claimable);
+			amount = amount.saturating_add(claimable);
+		}
+
+		if !amount.is_zero() {
+			T::Currency::deposit(T::VestingCurrencyId::get(), who, amount).map_err(|_| Error::<T>::DepositFailed)?;
+			Self::deposit_event(RawEvent::ClaimVested(who.clone(), amount));
+		}
+
+		schedules.retain(|schedule| schedule.claimed < schedule.total);
+		if schedules.is_empty() {
+			<VestingSchedules<T>>::remove(who);
+		} else {
+			<VestingSchedules<T>>::insert(who, schedules);
+		}
+
+		Ok(())
+	}
+}
+
+impl<T: Trait> VestingManager<T::AccountId, BalanceOf<T>, T::BlockNumber> for Module<T> {
+	/// `amount` vests in a schedule of its own, so the amounts already vesting are never delayed. The released
+	/// amounts are claimed first, which frees the schedules fully released for the new one.
+	fn vest(
+		who: &T::AccountId,
+		amount: BalanceOf<T>,
+		cliff: T::BlockNumber,
+		duration: T::BlockNumber,
+	) -> DispatchResult {
+		if amount.is_zero() {
+			return Ok(());
+		}
+
+		Self::release(who)?;
+
+		let mut schedules = Self::vesting_schedules(who);
+		ensure!(
+			schedules.len() < T::MaxVestingSchedules::get() as usize,
+			Error::<T>::TooManyVestingSchedules,
+		);
+		schedules.push(VestingSchedule {
+			start: <system::Module<T>>::block_number().saturating_add(cliff),
+			duration,
+			total: amount,
+			claimed: Zero::zero(),
+		});
+		<VestingSchedules<T>>::insert(who, schedules);
+		Self::deposit_event(RawEvent::Vest(who.clone(), amount));
+
+		Ok(())
+	}
+}
+
+impl<T: Trait> OnEmergencyShutdown for Module<T> {
+	fn on_emergency_shutdown() {
+		<IsShutdown>::put(true);
+	}
+}
@@ -0,0 +1,206 @@
+//! Unit tests for the vesting module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mock::{ExtBuilder, Origin, Runtime, System, Tokens, VestingModule, ACA, ALICE, BOB};
+
+#[test]
+fn released_follows_unlock_curve() {
+	let schedule = VestingSchedule {
+		start: 10u64,
+		duration: 4u64,
+		total: 100u64,
+		claimed: 0u64,
+	};
+	assert_eq!(schedule.end(), 14);
+	let released: Vec<u64> = (9..=15).map(|now| schedule.released(now)).collect();
+	assert_eq!(released, vec![0, 0, 25, 50, 75, 100, 100]);
+
+	let schedule = VestingSchedule {
+		claimed: 50,
+		..schedule
+	};
+	assert_eq!(schedule.claimable(11), 0);
+	assert_eq!(schedule.claimable(13), 25);
+	assert_eq!(schedule.claimable(14), 50);
+}
+
+#[test]
+fn released_rounds_down_until_end() {
+	let schedule = VestingSchedule {
+		start: 0u64,
+		duration: 3u64,
+		total: 10u64,
+		claimed: 0u64,
+	};
+	let released: Vec<u64> = (0..=3).map(|now| schedule.released(now)).collect();
+	assert_eq!(released, vec![0, 3, 6, 10]);
+
+	let schedule = VestingSchedule {
+		duration: 0,
+		..schedule
+	};
+	assert_eq!(schedule.released(0), 10);
+}
+
+#[test]
+fn vest_unlocks_block_by_block() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(VestingModule::vest(&ALICE, 100, 2, 4));
+		assert_eq!(
+			VestingModule::vesting_schedules(ALICE),
+			vec![VestingSchedule {
+				start: 3,
+				duration: 4,
+				total: 100,
+				claimed: 0,
+			}]
+		);
+
+		let mut claimable = vec![];
+		for now in 1..=8 {
+			System::set_block_number(now);
+			claimable.push(VestingModule::claimable(&ALICE));
+		}
+		assert_eq!(claimable, vec![0, 0, 0, 25, 50, 75, 100, 100]);
+	});
+}
+
+#[test]
+fn claim_vested_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 0);
+
+		assert_ok!(VestingModule::vest(&ALICE, 100, 2, 4));
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 0);
+
+		System::set_block_number(5);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 50);
+		assert_eq!(VestingModule::vesting_schedules(ALICE)[0].claimed, 50);
+		assert_eq!(VestingModule::claimable(&ALICE), 0);
+
+		System::set_block_number(6);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 75);
+
+		System::set_block_number(10);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 100);
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
+
+#[test]
+fn vest_keeps_schedules_apart() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(VestingModule::vest(&ALICE, 100, 0, 10));
+
+		// the released part is claimed, the rest keeps vesting as it was
+		System::set_block_number(3);
+		assert_ok!(VestingModule::vest(&ALICE, 40, 2, 2));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 20);
+		assert_eq!(
+			VestingModule::vesting_schedules(ALICE),
+			vec![
+				VestingSchedule {
+					start: 1,
+					duration: 10,
+					total: 100,
+					claimed: 20,
+				},
+				VestingSchedule {
+					start: 5,
+					duration: 2,
+					total: 40,
+					claimed: 0,
+				},
+			]
+		);
+
+		System::set_block_number(6);
+		assert_eq!(VestingModule::claimable(&ALICE), 30 + 20);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 70);
+
+		// the fully claimed schedule is removed
+		System::set_block_number(7);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 100);
+		assert_eq!(VestingModule::vesting_schedules(ALICE).len(), 1);
+
+		System::set_block_number(11);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 140);
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
+
+#[test]
+fn repeated_vests_never_delay_the_amounts_vesting() {
+	ExtBuilder::default().build().execute_with(|| {
+		// vested and claimed every block, each amount is released on its own curve
+		let mut balances = vec![];
+		for now in 1..=10 {
+			System::set_block_number(now);
+			if now <= 3 {
+				assert_ok!(VestingModule::vest(&ALICE, 100, 2, 4));
+			}
+			assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+			balances.push(Tokens::free_balance(ACA, &ALICE));
+		}
+		assert_eq!(balances, vec![0, 0, 0, 25, 75, 150, 225, 275, 300, 300]);
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
+
+#[test]
+fn vest_is_bounded() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		for _ in 0..3 {
+			assert_ok!(VestingModule::vest(&ALICE, 100, 0, 10));
+		}
+		assert_noop!(
+			VestingModule::vest(&ALICE, 100, 0, 10),
+			Error::<Runtime>::TooManyVestingSchedules,
+		);
+		assert_ok!(VestingModule::vest(&BOB, 100, 0, 10));
+
+		// the fully released schedules are claimed first, making room for the new one
+		System::set_block_number(11);
+		assert_ok!(VestingModule::vest(&ALICE, 100, 0, 10));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 300);
+		assert_eq!(VestingModule::vesting_schedules(ALICE).len(), 1);
+	});
+}
+
+#[test]
+fn vest_zero_is_ignored() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(VestingModule::vest(&ALICE, 0, 2, 4));
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
+
+#[test]
+fn everything_claimable_after_shutdown() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(VestingModule::vest(&ALICE, 100, 10, 10));
+		assert_eq!(VestingModule::claimable(&ALICE), 0);
+
+		VestingModule::on_emergency_shutdown();
+		assert_eq!(VestingModule::claimable(&ALICE), 100);
+		assert_ok!(VestingModule::claim_vested(Origin::signed(ALICE)));
+		assert_eq!(Tokens::free_balance(ACA, &ALICE), 100);
+		assert!(VestingModule::vesting_schedules(ALICE).is_empty());
+	});
+}
@@ -0,0 +1,152 @@
+//! RPC of the dex quotes, for front-ends to show the amounts of a swap or a liquidity position before signing.
+
+use std::sync::Arc;
+
+pub use acala_rpc_runtime_api::DexApi as DexRuntimeApi;
+use acala_rpc_runtime_api::{BalanceWrapper, PoolInfo};
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, ProvideRuntimeApi},
+};
+
+/// The runtime api error code.
+const RUNTIME_ERROR: i64 = 1;
+
+#[rpc]
+pub trait DexApi<BlockHash, AccountId, CurrencyId, Balance> {
+	/// The supply amount needed to swap for `target_currency_amount` at the block, with the exchange fees. `null`
+	/// if a pool of the swap is empty.
+	#[rpc(name = "dex_getSupplyAmount")]
+	fn get_supply_amount(
+		&self,
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		target_currency_amount: BalanceWrapper<Balance>,
+		at: Option<BlockHash>,
+	) -> Result<Option<BalanceWrapper<Balance>>>;
+
+	/// The target amount received by swapping `supply_currency_amount` at the block, with the exchange fees.
+	/// `null` if a pool of the swap is empty.
+	#[rpc(name = "dex_getTargetAmount")]
+	fn get_target_amount(
+		&self,
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		supply_currency_amount: BalanceWrapper<Balance>,
+		at: Option<BlockHash>,
+	) -> Result<Option<BalanceWrapper<Balance>>>;
+
+	/// The other currency and base currency amounts all the shares of `who` in the pool of `currency_id` are worth
+	/// at the block.
+	#[rpc(name = "dex_getLiquidity")]
+	fn get_liquidity(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<BlockHash>,
+	) -> Result<(BalanceWrapper<Balance>, BalanceWrapper<Balance>)>;
+
+	/// The reserves and shares of the pool of `currency_id` at the block, and the swaps with it.
+	#[rpc(name = "dex_getPoolInfo")]
+	fn pool_info(&self, currency_id: CurrencyId, at: Option<BlockHash>) -> Result<PoolInfo<Balance>>;
+}
+
+/// The dex quotes, at the best block unless given.
+pub struct Dex<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Dex<C, B> {
+	pub fn new(client: Arc<C>) -> Self {
+		Dex {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+fn runtime_error(error: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to quote the swap.".into(),
+		data: Some(format!("{:?}", error).into()),
+	}
+}
+
+impl<C, Block, AccountId, CurrencyId, Balance> DexApi<<Block as BlockT>::Hash, AccountId, CurrencyId, Balance>
+	for Dex<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+	C::Api: DexRuntimeApi<Block, AccountId, CurrencyId, Balance>,
+	AccountId: Codec,
+	CurrencyId: Codec,
+	Balance: Codec,
+{
+	fn get_supply_amount(
+		&self,
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		target_currency_amount: BalanceWrapper<Balance>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<BalanceWrapper<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_supply_amount(
+			&at,
+			supply_currency_id,
+			target_currency_id,
+			target_currency_amount.into_inner(),
+		)
+		.map(|amount| amount.map(BalanceWrapper))
+		.map_err(runtime_error)
+	}
+
+	fn get_target_amount(
+		&self,
+		supply_currency_id: CurrencyId,
+		target_currency_id: CurrencyId,
+		supply_currency_amount: BalanceWrapper<Balance>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<BalanceWrapper<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_target_amount(
+			&at,
+			supply_currency_id,
+			target_currency_id,
+			supply_currency_amount.into_inner(),
+		)
+		.map(|amount| amount.map(BalanceWrapper))
+		.map_err(runtime_error)
+	}
+
+	fn get_liquidity(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<(BalanceWrapper<Balance>, BalanceWrapper<Balance>)> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_liquidity(&at, who, currency_id)
+			.map(|(other_currency_amount, base_currency_amount)| {
+				(
+					BalanceWrapper(other_currency_amount),
+					BalanceWrapper(base_currency_amount),
+				)
+			})
+			.map_err(runtime_error)
+	}
+
+	fn pool_info(&self, currency_id: CurrencyId, at: Option<<Block as BlockT>::Hash>) -> Result<PoolInfo<Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.pool_info(&at, currency_id).map_err(runtime_error)
+	}
+}
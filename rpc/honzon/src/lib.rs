@@ -0,0 +1,133 @@
+//! RPC of the honzon positions, for front-ends to show the health of a loan and dashboards the health of the system.
+
+use std::sync::Arc;
+
+use acala_rpc_runtime_api::{BalanceWrapper, PositionInfo, SystemInfo};
+pub use acala_rpc_runtime_api::{CdpEngineApi as CdpEngineRuntimeApi, HonzonApi as HonzonRuntimeApi};
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, ProvideRuntimeApi},
+};
+
+/// The runtime api error code.
+const RUNTIME_ERROR: i64 = 1;
+
+#[rpc]
+pub trait HonzonApi<BlockHash, AccountId, CurrencyId, Balance> {
+	/// The position of `who` in collateral `currency_id` at the price and parameters of the block.
+	#[rpc(name = "honzon_getPositionInfo")]
+	fn get_position_info(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<BlockHash>,
+	) -> Result<PositionInfo<Balance>>;
+
+	/// The largest debit `who` can borrow more in collateral `currency_id` at the price and parameters of the block.
+	#[rpc(name = "honzon_getMaxIssuableDebit")]
+	fn max_issuable_debit(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<BlockHash>,
+	) -> Result<BalanceWrapper<Balance>>;
+}
+
+#[rpc]
+pub trait CdpEngineApi<BlockHash, CurrencyId, Balance> {
+	/// The positions of all the collaterals at the prices and exchange rates of the block.
+	#[rpc(name = "cdpEngine_getSystemInfo")]
+	fn get_system_info(&self, at: Option<BlockHash>) -> Result<SystemInfo<CurrencyId, Balance>>;
+}
+
+/// The honzon positions, at the best block unless given.
+pub struct Honzon<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Honzon<C, B> {
+	pub fn new(client: Arc<C>) -> Self {
+		Honzon {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+fn runtime_error(error: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to get the position.".into(),
+		data: Some(format!("{:?}", error).into()),
+	}
+}
+
+impl<C, Block, AccountId, CurrencyId, Balance> HonzonApi<<Block as BlockT>::Hash, AccountId, CurrencyId, Balance>
+	for Honzon<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+	C::Api: HonzonRuntimeApi<Block, AccountId, CurrencyId, Balance>,
+	AccountId: Codec,
+	CurrencyId: Codec,
+	Balance: Codec,
+{
+	fn get_position_info(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<PositionInfo<Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_position_info(&at, who, currency_id).map_err(runtime_error)
+	}
+
+	fn max_issuable_debit(
+		&self,
+		who: AccountId,
+		currency_id: CurrencyId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<BalanceWrapper<Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.max_issuable_debit(&at, who, currency_id)
+			.map(BalanceWrapper)
+			.map_err(runtime_error)
+	}
+}
+
+/// The positions of all the collaterals, at the best block unless given.
+pub struct CdpEngine<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> CdpEngine<C, B> {
+	pub fn new(client: Arc<C>) -> Self {
+		CdpEngine {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block, CurrencyId, Balance> CdpEngineApi<<Block as BlockT>::Hash, CurrencyId, Balance> for CdpEngine<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+	C::Api: CdpEngineRuntimeApi<Block, CurrencyId, Balance>,
+	CurrencyId: Codec,
+	Balance: Codec,
+{
+	fn get_system_info(&self, at: Option<<Block as BlockT>::Hash>) -> Result<SystemInfo<CurrencyId, Balance>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_system_info(&at).map_err(runtime_error)
+	}
+}
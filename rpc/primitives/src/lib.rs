@@ -0,0 +1,100 @@
+//! Primitives of the Acala runtime APIs and RPCs.
+//!
+//! JS clients lose precision above `2^53`, so the big numbers are wrapped to be serialized as strings. The
+//! wrappers are encoded the same as the inner values.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use orml_utilities::FixedU128;
+use sp_runtime::RuntimeDebug;
+
+#[cfg(feature = "std")]
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use std::{convert::TryFrom, fmt::Display};
+
+mod tests;
+
+/// A balance, serialized as a decimal string.
+///
+/// A number, a decimal string or a `0x` prefixed hex string are all accepted when deserializing.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct BalanceWrapper<Balance>(pub Balance);
+
+impl<Balance> BalanceWrapper<Balance> {
+	pub fn into_inner(self) -> Balance {
+		self.0
+	}
+}
+
+impl<Balance> From<Balance> for BalanceWrapper<Balance> {
+	fn from(balance: Balance) -> Self {
+		Self(balance)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<Balance: Display> Serialize for BalanceWrapper<Balance> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&self.0.to_string())
+	}
+}
+
+#[cfg(feature = "std")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+	Number(u64),
+	String(String),
+}
+
+#[cfg(feature = "std")]
+impl<'de, Balance: TryFrom<u128>> Deserialize<'de> for BalanceWrapper<Balance> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let value = match NumberOrString::deserialize(deserializer)? {
+			NumberOrString::Number(n) => u128::from(n),
+			NumberOrString::String(s) => {
+				let parsed = if s.starts_with("0x") {
+					u128::from_str_radix(&s[2..], 16)
+				} else {
+					s.parse::<u128>()
+				};
+				parsed.map_err(|_| Error::custom("Parse from string failed"))?
+			}
+		};
+		Balance::try_from(value)
+			.map(Self)
+			.map_err(|_| Error::custom("Balance overflow"))
+	}
+}
+
+/// A `FixedU128`, serialized as the decimal string of its inner parts.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct FixedU128Wrapper(pub FixedU128);
+
+impl FixedU128Wrapper {
+	pub fn into_inner(self) -> FixedU128 {
+		self.0
+	}
+}
+
+impl From<FixedU128> for FixedU128Wrapper {
+	fn from(fixed: FixedU128) -> Self {
+		Self(fixed)
+	}
+}
+
+#[cfg(feature = "std")]
+impl Serialize for FixedU128Wrapper {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		BalanceWrapper(self.0.deconstruct()).serialize(serializer)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for FixedU128Wrapper {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		BalanceWrapper::<u128>::deserialize(deserializer).map(|parts| Self(FixedU128::from_parts(parts.0)))
+	}
+}
@@ -0,0 +1,68 @@
+//! Unit tests for the rpc primitives.
+
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn balance_wrapper_serde_works_at_max() {
+	let balance = BalanceWrapper(u128::max_value());
+	let json = serde_json::to_string(&balance).unwrap();
+	assert_eq!(json, r#""340282366920938463463374607431768211455""#);
+	assert_eq!(serde_json::from_str::<BalanceWrapper<u128>>(&json).unwrap(), balance);
+}
+
+#[test]
+fn balance_wrapper_accepts_number_and_hex() {
+	assert_eq!(
+		serde_json::from_str::<BalanceWrapper<u128>>("100").unwrap(),
+		BalanceWrapper(100)
+	);
+	assert_eq!(
+		serde_json::from_str::<BalanceWrapper<u128>>(r#""0xffffffffffffffffffffffffffffffff""#).unwrap(),
+		BalanceWrapper(u128::max_value())
+	);
+	assert!(serde_json::from_str::<BalanceWrapper<u128>>(r#""0x1g""#).is_err());
+	assert!(serde_json::from_str::<BalanceWrapper<u128>>(r#""-1""#).is_err());
+}
+
+#[test]
+fn balance_wrapper_rejects_overflow() {
+	assert_eq!(
+		serde_json::from_str::<BalanceWrapper<u8>>(r#""255""#).unwrap(),
+		BalanceWrapper(255)
+	);
+	assert!(serde_json::from_str::<BalanceWrapper<u8>>(r#""256""#).is_err());
+}
+
+#[test]
+fn fixed_u128_wrapper_serde_works_at_max() {
+	let fixed = FixedU128Wrapper(FixedU128::from_parts(u128::max_value()));
+	let json = serde_json::to_string(&fixed).unwrap();
+	assert_eq!(json, r#""340282366920938463463374607431768211455""#);
+	assert_eq!(serde_json::from_str::<FixedU128Wrapper>(&json).unwrap(), fixed);
+
+	let ratio: FixedU128Wrapper = FixedU128::from_rational(3, 2).into();
+	assert_eq!(serde_json::to_string(&ratio).unwrap(), r#""1500000000000000000""#);
+}
+
+#[test]
+fn wrappers_are_encoded_same_as_inner() {
+	assert_eq!(BalanceWrapper(42u128).encode(), 42u128.encode());
+	let fixed = FixedU128::from_natural(2);
+	assert_eq!(FixedU128Wrapper(fixed).encode(), fixed.encode());
+	assert_eq!(
+		FixedU128Wrapper::decode(&mut &fixed.encode()[..]).unwrap().into_inner(),
+		fixed
+	);
+}
+
+#[test]
+fn conversions_work() {
+	let balance: BalanceWrapper<u128> = 10u128.into();
+	assert_eq!(balance.into_inner(), 10);
+	assert_eq!(
+		FixedU128Wrapper::from(FixedU128::from_natural(1)).into_inner(),
+		FixedU128::from_natural(1)
+	);
+}
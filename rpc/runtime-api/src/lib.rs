@@ -0,0 +1,313 @@
+//! Runtime APIs of Acala, the numbers are wrapped to be serialized as strings to be safe for JS clients.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use acala_rpc_primitives::{BalanceWrapper, FixedU128Wrapper};
+use codec::{Codec, Decode, Encode};
+use rstd::prelude::*;
+use sp_runtime::{DispatchError, RuntimeDebug};
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+mod tests;
+
+/// Free balance of a currency.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct CurrencyBalance<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	pub balance: BalanceWrapper<Balance>,
+}
+
+/// Liquidity shares in the dex pool of `currency_id`, and the amounts they can be withdrawn for.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct DexShareOverview<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	pub shares: BalanceWrapper<Balance>,
+	pub other_currency_amount: BalanceWrapper<Balance>,
+	pub base_currency_amount: BalanceWrapper<Balance>,
+}
+
+/// The dex pool of a currency: its reserves and shares, and the swaps with it since it was initialized.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "Balance: std::fmt::Display",
+		deserialize = "Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct PoolInfo<Balance> {
+	pub other_reserve: BalanceWrapper<Balance>,
+	pub base_reserve: BalanceWrapper<Balance>,
+	pub total_shares: BalanceWrapper<Balance>,
+	/// The base currency swapped with the pool.
+	pub cumulative_volume: BalanceWrapper<Balance>,
+	pub trade_count: BalanceWrapper<u64>,
+}
+
+/// CDP of collateral `currency_id`, the debit value and liquidation price are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct PositionOverview<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	pub collateral: BalanceWrapper<Balance>,
+	pub debit: BalanceWrapper<Balance>,
+	pub debit_value: BalanceWrapper<Balance>,
+	/// `None` if the price of collateral is unavailable.
+	pub collateral_ratio: Option<FixedU128Wrapper>,
+	pub liquidation_price: Option<FixedU128Wrapper>,
+}
+
+/// Position of an account in collateral `currency_id`, the debit value and liquidation price are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "Balance: std::fmt::Display",
+		deserialize = "Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct PositionInfo<Balance> {
+	pub collateral: BalanceWrapper<Balance>,
+	pub debit: BalanceWrapper<Balance>,
+	pub debit_value: BalanceWrapper<Balance>,
+	/// `None` if the price of collateral is unavailable.
+	pub collateral_ratio: Option<FixedU128Wrapper>,
+	pub liquidation_ratio: FixedU128Wrapper,
+	/// `None` if the position has no collateral.
+	pub liquidation_price: Option<FixedU128Wrapper>,
+}
+
+/// The positions of collateral `currency_id` all together, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct CollateralInfo<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	pub total_collateral: BalanceWrapper<Balance>,
+	pub total_debit_value: BalanceWrapper<Balance>,
+	/// `None` if the price of collateral is unavailable.
+	pub total_collateral_value: Option<BalanceWrapper<Balance>>,
+	/// `None` if the price of collateral is unavailable.
+	pub collateral_ratio: Option<FixedU128Wrapper>,
+}
+
+/// The positions of all the collaterals, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct SystemInfo<CurrencyId, Balance> {
+	pub collaterals: Vec<CollateralInfo<CurrencyId, Balance>>,
+	pub total_debit_value: BalanceWrapper<Balance>,
+	/// Total collateral value over total debit value. `None` if the price of any collateral is unavailable.
+	pub collateral_ratio: Option<FixedU128Wrapper>,
+}
+
+/// Vault of `currency_id` the account allows `to` to manipulate.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct AuthorizationOverview<AccountId, CurrencyId> {
+	pub currency_id: CurrencyId,
+	pub to: AccountId,
+}
+
+/// Everything a wallet needs to render the portfolio of an account.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "AccountId: Serialize, CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "AccountId: Deserialize<'de>, CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct AccountOverview<AccountId, CurrencyId, Balance> {
+	pub balances: Vec<CurrencyBalance<CurrencyId, Balance>>,
+	pub dex_shares: Vec<DexShareOverview<CurrencyId, Balance>>,
+	pub positions: Vec<PositionOverview<CurrencyId, Balance>>,
+	/// Rewards of loans, by collateral.
+	pub pending_loans_rewards: Vec<CurrencyBalance<CurrencyId, Balance>>,
+	/// Rewards of dex liquidity, by pool.
+	pub pending_dex_rewards: Vec<CurrencyBalance<CurrencyId, Balance>>,
+	pub authorizations: Vec<AuthorizationOverview<AccountId, CurrencyId>>,
+}
+
+/// Collateral of `currency_id` backing the stable coin, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct CollateralStats<CurrencyId, Balance> {
+	pub currency_id: CurrencyId,
+	/// Locked in all the CDPs of `currency_id`.
+	pub total_collateral: BalanceWrapper<Balance>,
+	/// `None` if the price of collateral is unavailable.
+	pub total_collateral_value: Option<BalanceWrapper<Balance>>,
+	/// Stable coin issued against the collateral.
+	pub total_debit_value: BalanceWrapper<Balance>,
+	pub collateral_in_auction: BalanceWrapper<Balance>,
+}
+
+/// Health of the whole system, the values are in stable coin.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(
+	feature = "std",
+	serde(bound(
+		serialize = "CurrencyId: Serialize, Balance: std::fmt::Display",
+		deserialize = "CurrencyId: Deserialize<'de>, Balance: std::convert::TryFrom<u128>"
+	))
+)]
+pub struct SystemStats<CurrencyId, Balance> {
+	pub collaterals: Vec<CollateralStats<CurrencyId, Balance>>,
+	/// Stable coin issued by all the CDPs.
+	pub total_debit_value: BalanceWrapper<Balance>,
+	/// Total collateral value over total debit value. `None` if there is no debit or a price is unavailable.
+	pub global_collateral_ratio: Option<FixedU128Wrapper>,
+	pub surplus_pool: BalanceWrapper<Balance>,
+	/// Bad debt not offset by surplus yet.
+	pub debit_pool: BalanceWrapper<Balance>,
+	/// Stable coin the collateral auctions in progress are to raise.
+	pub target_in_auction: BalanceWrapper<Balance>,
+	/// Both sides of all the dex pools, valued at the pool prices.
+	pub dex_total_value_locked: BalanceWrapper<Balance>,
+	/// Both sides of the dex liquidity of the treasury, valued at the pool prices.
+	pub treasury_dex_liquidity_value: BalanceWrapper<Balance>,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait AccountOverviewApi<AccountId, CurrencyId, Balance> where
+		AccountId: Codec,
+		CurrencyId: Codec,
+		Balance: Codec,
+	{
+		fn account_overview(who: AccountId) -> AccountOverview<AccountId, CurrencyId, Balance>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait DryRunApi<AccountId, Call> where
+		AccountId: Codec,
+		Call: Codec,
+	{
+		/// Dispatch the protocol `call` signed by `who` and report the outcome. Nothing is committed, the changes are
+		/// discarded with the overlay of the api call.
+		fn dry_run_call(who: AccountId, call: Call) -> Result<(), DispatchError>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait SystemStatsApi<CurrencyId, Balance> where
+		CurrencyId: Codec,
+		Balance: Codec,
+	{
+		fn system_stats() -> SystemStats<CurrencyId, Balance>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait DexApi<AccountId, CurrencyId, Balance> where
+		AccountId: Codec,
+		CurrencyId: Codec,
+		Balance: Codec,
+	{
+		/// The supply amount needed to swap for `target_currency_amount` right now, with the exchange fees. `None`
+		/// if a pool of the swap is empty.
+		fn get_supply_amount(
+			supply_currency_id: CurrencyId,
+			target_currency_id: CurrencyId,
+			target_currency_amount: Balance,
+		) -> Option<Balance>;
+		/// The target amount received by swapping `supply_currency_amount` right now, with the exchange fees. `None`
+		/// if a pool of the swap is empty.
+		fn get_target_amount(
+			supply_currency_id: CurrencyId,
+			target_currency_id: CurrencyId,
+			supply_currency_amount: Balance,
+		) -> Option<Balance>;
+		/// The other currency and base currency amounts all the shares of `who` in the pool of `currency_id` are
+		/// worth right now.
+		fn get_liquidity(who: AccountId, currency_id: CurrencyId) -> (Balance, Balance);
+		/// The reserves and shares of the pool of `currency_id` right now, and the swaps with it. The counters
+		/// saturate at their max values.
+		fn pool_info(currency_id: CurrencyId) -> PoolInfo<Balance>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait HonzonApi<AccountId, CurrencyId, Balance> where
+		AccountId: Codec,
+		CurrencyId: Codec,
+		Balance: Codec,
+	{
+		/// The position of `who` in collateral `currency_id` at the current price and parameters.
+		fn get_position_info(who: AccountId, currency_id: CurrencyId) -> PositionInfo<Balance>;
+		/// The largest debit `who` can borrow more in collateral `currency_id`, zero if the price is missing or the
+		/// debit value cap is reached.
+		fn max_issuable_debit(who: AccountId, currency_id: CurrencyId) -> Balance;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait CdpEngineApi<CurrencyId, Balance> where
+		CurrencyId: Codec,
+		Balance: Codec,
+	{
+		/// The positions of all the collaterals at the current prices and exchange rates.
+		fn get_system_info() -> SystemInfo<CurrencyId, Balance>;
+	}
+}
@@ -0,0 +1,100 @@
+//! Unit tests for the runtime api types.
+
+#![cfg(test)]
+
+use super::*;
+use orml_utilities::FixedU128;
+
+#[test]
+fn balances_serialized_as_strings() {
+	let balance = CurrencyBalance::<u8, u128> {
+		currency_id: 1,
+		balance: u128::max_value().into(),
+	};
+	let json = serde_json::to_string(&balance).unwrap();
+	assert_eq!(
+		json,
+		r#"{"currencyId":1,"balance":"340282366920938463463374607431768211455"}"#
+	);
+	assert_eq!(
+		serde_json::from_str::<CurrencyBalance<u8, u128>>(&json).unwrap(),
+		balance
+	);
+}
+
+#[test]
+fn position_overview_serde_works() {
+	let position = PositionOverview::<u8, u128> {
+		currency_id: 2,
+		collateral: 100.into(),
+		debit: 500.into(),
+		debit_value: 50.into(),
+		collateral_ratio: Some(FixedU128::from_rational(2, 1).into()),
+		liquidation_price: None,
+	};
+	let json = serde_json::to_string(&position).unwrap();
+	assert_eq!(
+		json,
+		r#"{"currencyId":2,"collateral":"100","debit":"500","debitValue":"50","collateralRatio":"2000000000000000000","liquidationPrice":null}"#
+	);
+	assert_eq!(
+		serde_json::from_str::<PositionOverview<u8, u128>>(&json).unwrap(),
+		position
+	);
+}
+
+#[test]
+fn position_info_serde_works() {
+	let info = PositionInfo::<u128> {
+		collateral: 100.into(),
+		debit: 500.into(),
+		debit_value: 50.into(),
+		collateral_ratio: None,
+		liquidation_ratio: FixedU128::from_rational(3, 2).into(),
+		liquidation_price: Some(FixedU128::from_rational(3, 4).into()),
+	};
+	let json = serde_json::to_string(&info).unwrap();
+	assert_eq!(
+		json,
+		r#"{"collateral":"100","debit":"500","debitValue":"50","collateralRatio":null,"liquidationRatio":"1500000000000000000","liquidationPrice":"750000000000000000"}"#
+	);
+	assert_eq!(serde_json::from_str::<PositionInfo<u128>>(&json).unwrap(), info);
+}
+
+#[test]
+fn system_info_serde_works() {
+	let info = SystemInfo::<u8, u128> {
+		collaterals: vec![CollateralInfo {
+			currency_id: 3,
+			total_collateral: 1500.into(),
+			total_debit_value: 500.into(),
+			total_collateral_value: None,
+			collateral_ratio: None,
+		}],
+		total_debit_value: 500.into(),
+		collateral_ratio: Some(FixedU128::from_rational(3, 1).into()),
+	};
+	let json = serde_json::to_string(&info).unwrap();
+	assert_eq!(
+		json,
+		r#"{"collaterals":[{"currencyId":3,"totalCollateral":"1500","totalDebitValue":"500","totalCollateralValue":null,"collateralRatio":null}],"totalDebitValue":"500","collateralRatio":"3000000000000000000"}"#
+	);
+	assert_eq!(serde_json::from_str::<SystemInfo<u8, u128>>(&json).unwrap(), info);
+}
+
+#[test]
+fn pool_info_serde_works() {
+	let info = PoolInfo::<u128> {
+		other_reserve: 110_000.into(),
+		base_reserve: 90_919.into(),
+		total_shares: 100_000.into(),
+		cumulative_volume: u128::max_value().into(),
+		trade_count: u64::max_value().into(),
+	};
+	let json = serde_json::to_string(&info).unwrap();
+	assert_eq!(
+		json,
+		r#"{"otherReserve":"110000","baseReserve":"90919","totalShares":"100000","cumulativeVolume":"340282366920938463463374607431768211455","tradeCount":"18446744073709551615"}"#
+	);
+	assert_eq!(serde_json::from_str::<PoolInfo<u128>>(&json).unwrap(), info);
+}
@@ -0,0 +1,122 @@
+//! Assemble the `AccountOverview` from the query helpers of the modules.
+
+use acala_rpc_runtime_api::{
+	AccountOverview, AuthorizationOverview, CurrencyBalance, DexShareOverview, PositionOverview,
+};
+use frame_support::traits::Get;
+use module_incentives::PoolId;
+use module_support::{DebitConvertor, PriceProviderExtended};
+use orml_traits::MultiCurrency;
+use rstd::prelude::*;
+use sp_runtime::traits::Zero;
+
+use crate::{
+	AccountId, Balance, CollateralCurrencyIds, CurrencyId, DebitExchangeRateConvertor, GetStableCurrencyId, Runtime,
+};
+
+type Currencies = orml_currencies::Module<Runtime>;
+type Dex = module_dex::Module<Runtime>;
+type Vaults = module_vaults::Module<Runtime>;
+type CdpEngine = module_cdp_engine::Module<Runtime>;
+type EmergencyShutdown = module_emergency_shutdown::Module<Runtime>;
+type Incentives = module_incentives::Module<Runtime>;
+type Honzon = module_honzon::Module<Runtime>;
+
+/// All the currencies known by the runtime.
+const CURRENCY_IDS: [CurrencyId; 4] = [CurrencyId::ACA, CurrencyId::AUSD, CurrencyId::DOT, CurrencyId::BTC];
+
+pub fn account_overview(who: AccountId) -> AccountOverview<AccountId, CurrencyId, Balance> {
+	let stable_currency_id = GetStableCurrencyId::get();
+
+	let balances = CURRENCY_IDS
+		.iter()
+		.map(|&currency_id| CurrencyBalance {
+			currency_id,
+			balance: Currencies::free_balance(currency_id, &who).into(),
+		})
+		.collect();
+
+	// the base currency has no pool of its own
+	let dex_shares = CURRENCY_IDS
+		.iter()
+		.filter(|&&currency_id| currency_id != stable_currency_id)
+		.filter_map(|&currency_id| {
+			let shares = Dex::shares(currency_id, &who);
+			if shares.is_zero() {
+				return None;
+			}
+			let (other_currency_amount, base_currency_amount) = Dex::get_liquidity(&who, currency_id);
+			Some(DexShareOverview {
+				currency_id,
+				shares: shares.into(),
+				other_currency_amount: other_currency_amount.into(),
+				base_currency_amount: base_currency_amount.into(),
+			})
+		})
+		.collect();
+
+	let positions = CollateralCurrencyIds::get()
+		.into_iter()
+		.filter_map(|currency_id| {
+			let position = Vaults::positions(&who, currency_id);
+			if position.is_empty() {
+				return None;
+			}
+			let collateral_ratio =
+				EmergencyShutdown::get_relative_price(stable_currency_id, currency_id).map(|price| {
+					CdpEngine::calculate_collateral_ratio(currency_id, position.collateral, position.debit, price)
+						.into()
+				});
+			Some(PositionOverview {
+				currency_id,
+				collateral: position.collateral.into(),
+				debit: position.debit.into(),
+				debit_value: DebitExchangeRateConvertor::<CdpEngine>::convert_debit_to_balance_ceil(
+					currency_id,
+					position.debit,
+				)
+				.into(),
+				collateral_ratio,
+				liquidation_price: CdpEngine::calculate_liquidation_price(
+					currency_id,
+					position.collateral,
+					position.debit,
+				)
+				.map(Into::into),
+			})
+		})
+		.collect();
+
+	let pending_loans_rewards = CollateralCurrencyIds::get()
+		.into_iter()
+		.map(|currency_id| CurrencyBalance {
+			currency_id,
+			balance: Incentives::pending_rewards(&who, PoolId::Loans(currency_id)).into(),
+		})
+		.filter(|reward| !reward.balance.0.is_zero())
+		.collect();
+
+	let pending_dex_rewards = CURRENCY_IDS
+		.iter()
+		.filter(|&&currency_id| currency_id != stable_currency_id)
+		.map(|&currency_id| CurrencyBalance {
+			currency_id,
+			balance: Incentives::pending_rewards(&who, PoolId::DexLiquidity(currency_id)).into(),
+		})
+		.filter(|reward| !reward.balance.0.is_zero())
+		.collect();
+
+	let authorizations = Honzon::authorizations(&who)
+		.into_iter()
+		.map(|(currency_id, to)| AuthorizationOverview { currency_id, to })
+		.collect();
+
+	AccountOverview {
+		balances,
+		dex_shares,
+		positions,
+		pending_loans_rewards,
+		pending_dex_rewards,
+		authorizations,
+	}
+}
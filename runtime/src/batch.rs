@@ -0,0 +1,319 @@
+//! Check the atomic batches of dex swaps, loan updates and token transfers before they are dispatched.
+//!
+//! The runtime has no storage transactions, so a batch is only all-or-nothing if no call fails once dispatched.
+//! The calls are checked in order against a ledger of the balances and the dex pools the calls before left, and the
+//! batch is refused before anything is dispatched if one of them would fail.
+
+use frame_support::{ensure, traits::Get};
+use module_batch::BatchChecker;
+use module_support::DebitConvertor;
+use orml_traits::MultiCurrency;
+use rstd::{collections::btree_map::BTreeMap, convert::TryInto};
+use sp_runtime::{traits::StaticLookup, DispatchError, DispatchResult};
+
+use crate::{AccountId, Amount, Balance, Call, CurrencyId, GetNativeCurrencyId, GetStableCurrencyId, Runtime};
+
+type Currencies = orml_currencies::Module<Runtime>;
+type Dex = module_dex::Module<Runtime>;
+type CdpEngine = module_cdp_engine::Module<Runtime>;
+type Vaults = module_vaults::Module<Runtime>;
+type DebitConvert = <Runtime as module_debits::Trait>::Convert;
+type Lookup = <Runtime as system::Trait>::Lookup;
+
+/// The balances and the dex pools as left by the calls of the batch checked so far.
+#[derive(Default)]
+struct Ledger {
+	balances: BTreeMap<(AccountId, CurrencyId), Balance>,
+	pools: BTreeMap<CurrencyId, (Balance, Balance)>,
+	/// The collateral and debit adjustments of the loans of the batch account so far.
+	loan_adjustments: BTreeMap<CurrencyId, (Amount, Amount)>,
+}
+
+impl Ledger {
+	fn balance(&self, who: &AccountId, currency_id: CurrencyId) -> Balance {
+		self.balances
+			.get(&(who.clone(), currency_id))
+			.copied()
+			.unwrap_or_else(|| Currencies::balance(currency_id, who))
+	}
+
+	fn deposit(&mut self, who: &AccountId, currency_id: CurrencyId, amount: Balance) -> DispatchResult {
+		let balance = self
+			.balance(who, currency_id)
+			.checked_add(amount)
+			.ok_or(DispatchError::Other("Balance overflow"))?;
+		self.balances.insert((who.clone(), currency_id), balance);
+		Ok(())
+	}
+
+	fn withdraw(
+		&mut self,
+		who: &AccountId,
+		currency_id: CurrencyId,
+		amount: Balance,
+		error: DispatchError,
+	) -> DispatchResult {
+		let balance = self.balance(who, currency_id).checked_sub(amount).ok_or(error)?;
+		self.balances.insert((who.clone(), currency_id), balance);
+		Ok(())
+	}
+
+	fn transfer(
+		&mut self,
+		from: &AccountId,
+		to: &AccountId,
+		currency_id: CurrencyId,
+		amount: Balance,
+		error: DispatchError,
+	) -> DispatchResult {
+		self.withdraw(from, currency_id, amount, error)?;
+		self.deposit(to, currency_id, amount)
+	}
+
+	/// The (other currency, base currency) pool of `currency_id`.
+	fn pool(&self, currency_id: CurrencyId) -> (Balance, Balance) {
+		self.pools
+			.get(&currency_id)
+			.copied()
+			.unwrap_or_else(|| Dex::liquidity_pool(currency_id))
+	}
+
+	/// Swap `supply_amount` of the base currency for `other_currency_id`, the other currency amount is returned.
+	fn swap_base_to_other(
+		&mut self,
+		other_currency_id: CurrencyId,
+		supply_amount: Balance,
+	) -> Result<Balance, DispatchError> {
+		let (other_currency_pool, base_currency_pool) = self.pool(other_currency_id);
+		let (target_amount, _) = Dex::calculate_swap_target_amount(
+			base_currency_pool,
+			other_currency_pool,
+			supply_amount,
+			Dex::get_exchange_fee(other_currency_id),
+		)?;
+		let new_pool = (
+			other_currency_pool.checked_sub(target_amount).ok_or(num_overflow())?,
+			base_currency_pool.checked_add(supply_amount).ok_or(num_overflow())?,
+		);
+		Dex::ensure_price_impact_acceptable_from(
+			other_currency_id,
+			(other_currency_pool, base_currency_pool),
+			new_pool,
+		)?;
+		// the protocol fee leaves the pool
+		let fee_amount =
+			Dex::calculate_fee_amount(base_currency_pool, other_currency_pool, supply_amount, target_amount)?;
+		let protocol_fee_amount = Dex::calculate_protocol_fee_amount(fee_amount)?;
+		self.pools.insert(
+			other_currency_id,
+			(
+				new_pool.0.checked_sub(protocol_fee_amount).ok_or(num_overflow())?,
+				new_pool.1,
+			),
+		);
+		Ok(target_amount)
+	}
+
+	/// Swap `supply_amount` of `other_currency_id` for the base currency, the base currency amount is returned.
+	fn swap_other_to_base(
+		&mut self,
+		other_currency_id: CurrencyId,
+		supply_amount: Balance,
+	) -> Result<Balance, DispatchError> {
+		let (other_currency_pool, base_currency_pool) = self.pool(other_currency_id);
+		let (target_amount, _) = Dex::calculate_swap_target_amount(
+			other_currency_pool,
+			base_currency_pool,
+			supply_amount,
+			Dex::get_exchange_fee(other_currency_id),
+		)?;
+		let new_pool = (
+			other_currency_pool.checked_add(supply_amount).ok_or(num_overflow())?,
+			base_currency_pool.checked_sub(target_amount).ok_or(num_overflow())?,
+		);
+		Dex::ensure_price_impact_acceptable_from(
+			other_currency_id,
+			(other_currency_pool, base_currency_pool),
+			new_pool,
+		)?;
+		let fee_amount =
+			Dex::calculate_fee_amount(other_currency_pool, base_currency_pool, supply_amount, target_amount)?;
+		let protocol_fee_amount = Dex::calculate_protocol_fee_amount(fee_amount)?;
+		self.pools.insert(
+			other_currency_id,
+			(
+				new_pool.0,
+				new_pool.1.checked_sub(protocol_fee_amount).ok_or(num_overflow())?,
+			),
+		);
+		Ok(target_amount)
+	}
+
+	/// Check `module_dex::Call::swap_with_exact_supply` and apply it.
+	fn swap_with_exact_supply(
+		&mut self,
+		who: &AccountId,
+		supply: (CurrencyId, Balance),
+		target: (CurrencyId, Balance),
+	) -> DispatchResult {
+		ensure_not_native(supply.0)?;
+		ensure_not_native(target.0)?;
+		ensure!(target.0 != supply.0, module_dex::Error::<Runtime>::CanNotSwapItself);
+		let base_currency_id = <Runtime as module_dex::Trait>::GetBaseCurrencyId::get();
+		// checked against storage, the swaps of a batch never empty a pool
+		for currency_id in &[supply.0, target.0] {
+			if *currency_id != base_currency_id {
+				Dex::ensure_trading_pair_enabled(*currency_id)?;
+				Dex::ensure_liquidity_pool_exists(*currency_id)?;
+			}
+		}
+		ensure!(supply.1 > 0, module_dex::Error::<Runtime>::TokenNotEnough);
+		self.withdraw(
+			who,
+			supply.0,
+			supply.1,
+			module_dex::Error::<Runtime>::TokenNotEnough.into(),
+		)?;
+
+		let target_amount = if target.0 == base_currency_id {
+			self.swap_other_to_base(supply.0, supply.1)?
+		} else if supply.0 == base_currency_id {
+			self.swap_base_to_other(target.0, supply.1)?
+		} else {
+			let intermediate_amount = self.swap_other_to_base(supply.0, supply.1)?;
+			self.swap_base_to_other(target.0, intermediate_amount)?
+		};
+		ensure!(
+			target_amount >= target.1,
+			module_dex::Error::<Runtime>::InacceptablePrice
+		);
+
+		self.deposit(who, target.0, target_amount)
+	}
+
+	/// Check `module_honzon::Call::update_vault` and apply it.
+	fn update_vault(
+		&mut self,
+		who: &AccountId,
+		currency_id: CurrencyId,
+		collateral: Amount,
+		debit: Amount,
+	) -> DispatchResult {
+		// the updates of a loan are checked together against its position in storage, which is at least as strict as
+		// checking them one by one only when they all adjust the collateral and the debit in the same direction
+		let (collateral_adjustment, debit_adjustment) =
+			self.loan_adjustments.get(&currency_id).copied().unwrap_or_default();
+		ensure!(
+			same_direction(collateral_adjustment, collateral) && same_direction(debit_adjustment, debit),
+			DispatchError::Other("Loan adjusted in opposite directions in a batch")
+		);
+		let collateral_adjustment = collateral_adjustment
+			.checked_add(collateral)
+			.ok_or(module_vaults::Error::<Runtime>::CollateralOverflow)?;
+		let debit_adjustment = debit_adjustment
+			.checked_add(debit)
+			.ok_or(module_vaults::Error::<Runtime>::DebitOverflow)?;
+		CdpEngine::check_position_update(who, currency_id, collateral_adjustment, debit_adjustment)?;
+		self.loan_adjustments
+			.insert(currency_id, (collateral_adjustment, debit_adjustment));
+
+		let collateral_balance = TryInto::<Balance>::try_into(collateral.abs())
+			.map_err(|_| module_vaults::Error::<Runtime>::AmountIntoBalanceFailed)?;
+		let collateral_insufficient: DispatchError = module_vaults::Error::<Runtime>::CollateralInSufficient.into();
+		if collateral > 0 {
+			self.transfer(
+				who,
+				&Vaults::account_id(),
+				currency_id,
+				collateral_balance,
+				collateral_insufficient,
+			)?;
+		} else {
+			self.transfer(
+				&Vaults::account_id(),
+				who,
+				currency_id,
+				collateral_balance,
+				collateral_insufficient,
+			)?;
+		}
+
+		// the stable coin the debit is minted for rounded down, or burned for rounded up
+		let debit_balance = TryInto::<Balance>::try_into(debit.abs())
+			.map_err(|_| module_vaults::Error::<Runtime>::AmountIntoBalanceFailed)?;
+		if debit > 0 {
+			self.deposit(
+				who,
+				GetStableCurrencyId::get(),
+				DebitConvert::convert_debit_to_balance_floor(currency_id, debit_balance),
+			)
+		} else {
+			self.withdraw(
+				who,
+				GetStableCurrencyId::get(),
+				DebitConvert::convert_debit_to_balance_ceil(currency_id, debit_balance),
+				module_vaults::Error::<Runtime>::UpdateStableCoinFailed.into(),
+			)
+		}
+	}
+
+	fn check_call(&mut self, who: &AccountId, call: &Call) -> DispatchResult {
+		match call {
+			Call::Dex(module_dex::Call::swap_with_exact_supply(supply, target, deadline)) => {
+				Dex::ensure_before_deadline(*deadline)?;
+				self.swap_with_exact_supply(who, *supply, *target)
+			}
+			Call::Honzon(module_honzon::Call::update_vault(currency_id, collateral, debit)) => {
+				self.update_vault(who, *currency_id, *collateral, *debit)
+			}
+			Call::Currencies(orml_currencies::Call::transfer(dest, currency_id, amount)) => {
+				ensure_not_native(*currency_id)?;
+				// the shares only move with the dex
+				ensure!(
+					!currency_id.is_dex_share_currency_id(),
+					module_batch::Error::<Runtime>::CallNotAllowed
+				);
+				let to = Lookup::lookup(dest.clone()).map_err(|_| DispatchError::Other("Invalid destination"))?;
+				self.transfer(
+					who,
+					&to,
+					*currency_id,
+					*amount,
+					module_batch::Error::<Runtime>::BalanceTooLow.into(),
+				)
+			}
+			_ => Err(module_batch::Error::<Runtime>::CallNotAllowed.into()),
+		}
+	}
+}
+
+/// The pools are settled with checked arithmetic.
+fn num_overflow() -> DispatchError {
+	module_dex::Error::<Runtime>::NumOverflow.into()
+}
+
+fn same_direction(adjustment: Amount, other_adjustment: Amount) -> bool {
+	!(adjustment > 0 && other_adjustment < 0) && !(adjustment < 0 && other_adjustment > 0)
+}
+
+/// The native currency is left out of the batches, its locks and existential deposit aren't in the ledger.
+fn ensure_not_native(currency_id: CurrencyId) -> DispatchResult {
+	ensure!(
+		currency_id != GetNativeCurrencyId::get(),
+		module_batch::Error::<Runtime>::CallNotAllowed
+	);
+	Ok(())
+}
+
+/// Only accepts the dex swaps, the loan updates and the token transfers which succeed after the calls before them.
+pub struct ProtocolBatchChecker;
+
+impl BatchChecker<AccountId, Call> for ProtocolBatchChecker {
+	fn check_batch(who: &AccountId, calls: &[Call]) -> Result<(), (u32, DispatchError)> {
+		let mut ledger = Ledger::default();
+		for (index, call) in calls.iter().enumerate() {
+			ledger.check_call(who, call).map_err(|error| (index as u32, error))?;
+		}
+		Ok(())
+	}
+}
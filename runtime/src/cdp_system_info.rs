@@ -0,0 +1,28 @@
+//! The positions of all the collaterals together, for dashboards to alert on under-collateralization.
+
+use acala_rpc_runtime_api::{CollateralInfo, SystemInfo};
+use rstd::prelude::*;
+
+use crate::{Balance, CurrencyId, Runtime};
+
+type CdpEngine = module_cdp_engine::Module<Runtime>;
+
+/// The positions of all the collaterals at the current prices and exchange rates.
+pub fn get_system_info() -> SystemInfo<CurrencyId, Balance> {
+	let info = CdpEngine::system_info();
+	SystemInfo {
+		collaterals: info
+			.collaterals
+			.into_iter()
+			.map(|collateral_info| CollateralInfo {
+				currency_id: collateral_info.currency_id,
+				total_collateral: collateral_info.total_collateral.into(),
+				total_debit_value: collateral_info.total_debit_value.into(),
+				total_collateral_value: collateral_info.total_collateral_value.map(Into::into),
+				collateral_ratio: collateral_info.collateral_ratio.map(Into::into),
+			})
+			.collect(),
+		total_debit_value: info.total_debit_value.into(),
+		collateral_ratio: info.collateral_ratio.map(Into::into),
+	}
+}
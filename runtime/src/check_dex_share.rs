@@ -0,0 +1,68 @@
+//! Reject the transfers of the dex share currencies through the currency modules, they only move with
+//! `Dex::transfer_shares` so the dex keeps the shares of each account in step with their balances.
+
+use codec::{Decode, Encode};
+use frame_support::weights::DispatchInfo;
+use rstd::fmt;
+use sp_runtime::{
+	traits::SignedExtension,
+	transaction_validity::{InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction},
+};
+
+use crate::{AccountId, Call};
+
+/// Whether `call` moves the balance of a dex share currency outside of the dex.
+pub fn is_dex_share_transfer(call: &Call) -> bool {
+	match call {
+		Call::Currencies(orml_currencies::Call::transfer(_, currency_id, _))
+		| Call::Tokens(orml_tokens::Call::transfer(_, currency_id, _)) => currency_id.is_dex_share_currency_id(),
+		Call::Batch(module_batch::Call::batch_atomic(calls)) => calls.iter().any(is_dex_share_transfer),
+		_ => false,
+	}
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, Default)]
+pub struct CheckDexShareTransfer;
+
+impl CheckDexShareTransfer {
+	/// utility constructor. Used only in client/factory code.
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl fmt::Debug for CheckDexShareTransfer {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckDexShareTransfer")
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+		Ok(())
+	}
+}
+
+impl SignedExtension for CheckDexShareTransfer {
+	type AccountId = AccountId;
+	type Call = Call;
+	type AdditionalSigned = ();
+	type DispatchInfo = DispatchInfo;
+	type Pre = ();
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		call: &Self::Call,
+		_info: Self::DispatchInfo,
+		_len: usize,
+	) -> TransactionValidity {
+		if is_dex_share_transfer(call) {
+			return Err(InvalidTransaction::Call.into());
+		}
+
+		Ok(ValidTransaction::default())
+	}
+}
@@ -0,0 +1,92 @@
+//! Only accept the calls allowed in the current phase of the emergency shutdown, rejecting the rest before they
+//! enter the pool instead of letting them fail at dispatch and take the weight of blocks.
+
+use codec::{Decode, Encode};
+use frame_support::weights::DispatchInfo;
+use module_emergency_shutdown::ShutdownPhase;
+use rstd::fmt;
+use sp_runtime::{
+	traits::SignedExtension,
+	transaction_validity::{InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction},
+};
+
+use crate::{AccountId, Call, EmergencyShutdown};
+
+/// The allow-list of calls in `phase`. Everything is allowed before shutdown, then only settlement, repayments,
+/// withdrawals and transfers, with refunds in place of settlement once refund opens. Root calls go through sudo.
+pub fn is_allowed_in_phase(call: &Call, phase: ShutdownPhase) -> bool {
+	match (phase, call) {
+		(ShutdownPhase::Normal, _) => true,
+		(_, Call::Sudo(..)) => true,
+		(_, Call::Batch(module_batch::Call::batch_atomic(calls))) => {
+			calls.iter().all(|call| is_allowed_in_phase(call, phase))
+		}
+		(_, Call::Balances(pallet_balances::Call::transfer(..)))
+		| (_, Call::Balances(pallet_balances::Call::transfer_keep_alive(..)))
+		| (_, Call::Currencies(orml_currencies::Call::transfer(..)))
+		| (_, Call::Currencies(orml_currencies::Call::transfer_native_currency(..))) => true,
+		(_, Call::Honzon(module_honzon::Call::update_vault(_, _, debit)))
+		| (_, Call::Honzon(module_honzon::Call::update_loan(_, _, debit)))
+		| (_, Call::Honzon(module_honzon::Call::adjust_loan_by_authorized(_, _, _, debit))) => *debit <= 0,
+		(_, Call::Honzon(module_honzon::Call::repay_all(..))) => true,
+		(_, Call::Honzon(module_honzon::Call::adjust_loans(adjustments))) => {
+			adjustments.iter().all(|(_, _, debit)| *debit <= 0)
+		}
+		(_, Call::Dex(module_dex::Call::withdraw_liquidity(..)))
+		| (_, Call::Dex(module_dex::Call::withdraw_liquidity_by_amounts(..)))
+		| (_, Call::Dex(module_dex::Call::transfer_shares(..))) => true,
+		(_, Call::Incentives(module_incentives::Call::unstake_lp_shares(..))) => true,
+		(_, Call::Vesting(module_vesting::Call::claim_vested())) => true,
+		(ShutdownPhase::Shutdown, Call::Honzon(module_honzon::Call::settle_cdp(..))) => true,
+		(ShutdownPhase::Refund, Call::EmergencyShutdown(module_emergency_shutdown::Call::refund_collaterals(..))) => {
+			true
+		}
+		_ => false,
+	}
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, Default)]
+pub struct CheckShutdown;
+
+impl CheckShutdown {
+	/// utility constructor. Used only in client/factory code.
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl fmt::Debug for CheckShutdown {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckShutdown")
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+		Ok(())
+	}
+}
+
+impl SignedExtension for CheckShutdown {
+	type AccountId = AccountId;
+	type Call = Call;
+	type AdditionalSigned = ();
+	type DispatchInfo = DispatchInfo;
+	type Pre = ();
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		call: &Self::Call,
+		_info: Self::DispatchInfo,
+		_len: usize,
+	) -> TransactionValidity {
+		if !is_allowed_in_phase(call, EmergencyShutdown::phase()) {
+			return Err(InvalidTransaction::Call.into());
+		}
+
+		Ok(ValidTransaction::default())
+	}
+}
@@ -0,0 +1,43 @@
+//! Quotes of dex swaps and liquidity at the current pools, for front-ends to show before signing.
+
+use acala_rpc_runtime_api::PoolInfo;
+use module_support::DexManager;
+
+use crate::{AccountId, Balance, CurrencyId, Runtime};
+
+type Dex = module_dex::Module<Runtime>;
+
+/// The supply amount needed to swap for `target_currency_amount`, `None` if a pool of the swap is empty.
+pub fn get_supply_amount(
+	supply_currency_id: CurrencyId,
+	target_currency_id: CurrencyId,
+	target_currency_amount: Balance,
+) -> Option<Balance> {
+	<Dex as DexManager<_, _, _>>::get_supply_amount(supply_currency_id, target_currency_id, target_currency_amount).ok()
+}
+
+/// The target amount received by swapping `supply_currency_amount`, `None` if a pool of the swap is empty.
+pub fn get_target_amount(
+	supply_currency_id: CurrencyId,
+	target_currency_id: CurrencyId,
+	supply_currency_amount: Balance,
+) -> Option<Balance> {
+	<Dex as DexManager<_, _, _>>::get_target_amount(supply_currency_id, target_currency_id, supply_currency_amount).ok()
+}
+
+/// The other currency and base currency amounts all the shares of `who` in the pool of `currency_id` are worth.
+pub fn get_liquidity(who: AccountId, currency_id: CurrencyId) -> (Balance, Balance) {
+	Dex::get_liquidity(&who, currency_id)
+}
+
+/// The reserves and shares of the pool of `currency_id`, and the swaps with it since it was initialized.
+pub fn pool_info(currency_id: CurrencyId) -> PoolInfo<Balance> {
+	let info = Dex::pool_info(currency_id);
+	PoolInfo {
+		other_reserve: info.other_reserve.into(),
+		base_reserve: info.base_reserve.into(),
+		total_shares: info.total_shares.into(),
+		cumulative_volume: info.cumulative_volume.into(),
+		trade_count: info.trade_count.into(),
+	}
+}
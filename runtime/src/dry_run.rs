@@ -0,0 +1,37 @@
+//! Dry run of protocol calls, for integrators to check if a call would succeed right now.
+//!
+//! The runtime has no storage transactions, so the changes made by the call are only discarded with the overlay of
+//! the runtime api call. It must never be called during block execution.
+
+use sp_runtime::{traits::Dispatchable, DispatchError, DispatchResult};
+
+use crate::{AccountId, Call, Origin};
+
+/// The protocol calls which can be dry run.
+pub fn is_dry_run_allowed(call: &Call) -> bool {
+	match call {
+		Call::Dex(module_dex::Call::swap_with_exact_supply(..))
+		| Call::Dex(module_dex::Call::swap_with_exact_target(..))
+		| Call::Dex(module_dex::Call::swap_by_path(..))
+		| Call::Dex(module_dex::Call::swap_currency_batch(..))
+		| Call::Dex(module_dex::Call::add_liquidity(..))
+		| Call::Dex(module_dex::Call::withdraw_liquidity(..))
+		| Call::Dex(module_dex::Call::withdraw_liquidity_by_amounts(..))
+		| Call::Auction(orml_auction::Call::bid(..))
+		| Call::Honzon(module_honzon::Call::update_vault(..))
+		| Call::Honzon(module_honzon::Call::update_loan(..))
+		| Call::Honzon(module_honzon::Call::adjust_loan_by_authorized(..))
+		| Call::Honzon(module_honzon::Call::adjust_loans(..))
+		| Call::Honzon(module_honzon::Call::repay_all(..))
+		| Call::Honzon(module_honzon::Call::close_loan_by_dex(..)) => true,
+		_ => false,
+	}
+}
+
+pub fn dry_run_call(who: AccountId, call: Call) -> DispatchResult {
+	if !is_dry_run_allowed(&call) {
+		return Err(DispatchError::Other("Call not allowed in dry run"));
+	}
+
+	call.dispatch(Origin::signed(who))
+}
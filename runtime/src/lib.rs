@@ -15,16 +15,37 @@ use sp_api::impl_runtime_apis;
 use sp_runtime::traits::{BlakeTwo256, Block as BlockT, ConvertInto, IdentifyAccount, NumberFor, StaticLookup, Verify};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys, transaction_validity::TransactionValidity, ApplyExtrinsicResult,
-	MultiSignature,
+	ModuleId, MultiSignature,
 };
 #[cfg(feature = "std")]
 use version::NativeVersion;
 use version::RuntimeVersion;
 
 use aura_primitives::sr25519::AuthorityId as AuraId;
+use frame_support::traits::Contains;
 use orml_oracle::OperatorProvider;
 use pallet_grandpa::fg_primitives;
 use pallet_grandpa::AuthorityList as GrandpaAuthorityList;
+use system::offchain::TransactionSubmitter;
+
+mod account_overview;
+mod batch;
+mod cdp_system_info;
+mod check_dex_share;
+mod check_shutdown;
+mod dex_quote;
+mod dry_run;
+mod migrations;
+mod position_info;
+mod system_stats;
+pub use account_overview::account_overview;
+use batch::ProtocolBatchChecker;
+pub use check_dex_share::{is_dex_share_transfer, CheckDexShareTransfer};
+pub use check_shutdown::{is_allowed_in_phase, CheckShutdown};
+pub use dex_quote::{get_liquidity, get_supply_amount, get_target_amount, pool_info};
+pub use dry_run::{dry_run_call, is_dry_run_allowed};
+pub use migrations::{on_runtime_upgrade, AllModulesWithMigrations};
+pub use system_stats::system_stats;
 
 // A few exports that help ease life for downstream crates.
 
@@ -34,8 +55,12 @@ pub use pallet_timestamp::Call as TimestampCall;
 pub use sp_runtime::BuildStorage;
 pub use sp_runtime::{Perbill, Permill};
 
-pub use module_primitives::CurrencyId;
-pub use module_support::{ExchangeRate, Price, Rate, Ratio};
+pub use module_primitives::{
+	constants::{cent, dollar, millicent},
+	CurrencyId,
+};
+use module_support::DebitExchangeRateConvertor;
+pub use module_support::{ExchangeRate, FixedU128Ext, Price, Rate, Ratio};
 pub use orml_currencies::BasicCurrencyAdapter;
 
 /// An index to a block.
@@ -53,7 +78,7 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 pub type AccountIndex = u32;
 
 /// Balance of an account.
-pub type Balance = u128;
+pub type Balance = module_primitives::Balance;
 
 /// Signed version of Balance
 pub type Amount = i128;
@@ -69,6 +94,9 @@ pub type DigestItem = generic::DigestItem<Hash>;
 
 pub type AuctionId = u32;
 
+/// Share of a liquidity pool of dex.
+pub type Share = u128;
+
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
 /// of data like extrinsics, allowing for them to continue syncing the network through upgrades
@@ -162,6 +190,8 @@ impl system::Trait for Runtime {
 	type AvailableBlockRatio = AvailableBlockRatio;
 	/// Version of the runtime.
 	type Version = Version;
+	/// Index of the modules in the runtime, recorded in module errors.
+	type ModuleToIndex = ModuleToIndex;
 }
 
 impl pallet_aura::Trait for Runtime {
@@ -261,14 +291,24 @@ impl orml_auction::Trait for Runtime {
 	type Handler = module_auction_manager::Module<Runtime>;
 }
 
-pub struct OperatorCollectiveProvider;
-impl OperatorProvider<AccountId> for OperatorCollectiveProvider {
+/// The accounts allowed to feed the oracle, the members of the operator membership.
+pub struct OracleOperators;
+impl Contains<AccountId> for OracleOperators {
+	fn sorted_members() -> Vec<AccountId> {
+		OperatorMembership::members()
+	}
+}
+
+/// Only lets the accounts `C` contains feed the oracle and only combines their values, so an operator removed from
+/// `C` can't feed any more and its values are left out of the prices combined from then on.
+pub struct OperatorsOf<C>(rstd::marker::PhantomData<C>);
+impl<C: Contains<AccountId>> OperatorProvider<AccountId> for OperatorsOf<C> {
 	fn can_feed_data(who: &AccountId) -> bool {
-		OperatorCollective::is_member(who)
+		C::contains(who)
 	}
 
 	fn operators() -> Vec<AccountId> {
-		OperatorCollective::members()
+		C::sorted_members()
 	}
 }
 
@@ -280,7 +320,7 @@ parameter_types! {
 impl orml_oracle::Trait for Runtime {
 	type Event = Event;
 	type OnNewData = (); // TODO: update this
-	type OperatorProvider = OperatorCollectiveProvider;
+	type OperatorProvider = OperatorsOf<OracleOperators>;
 	type CombineData = orml_oracle::DefaultCombineData<Runtime, MinimumCount, ExpiresIn>;
 	type Time = Timestamp;
 	type OracleKey = CurrencyId;
@@ -312,7 +352,7 @@ impl orml_currencies::Trait for Runtime {
 }
 
 parameter_types! {
-	pub const MinimumIncrementSize: Rate = Rate::from_rational(1, 50);
+	pub const MinimumIncrementSize: Rate = Rate::from_percent(2);
 	pub const AuctionTimeToClose: BlockNumber = 100;
 	pub const AuctionDurationSoftCap: BlockNumber = 200;
 }
@@ -328,6 +368,21 @@ impl module_auction_manager::Trait for Runtime {
 	type AuctionTimeToClose = AuctionTimeToClose;
 	type AuctionDurationSoftCap = AuctionDurationSoftCap;
 	type GetStableCurrencyId = GetStableCurrencyId;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type Treasury = module_cdp_treasury::Module<Runtime>;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+}
+
+impl module_cdp_treasury::Trait for Runtime {
+	type Event = Event;
+	type Currency = orml_currencies::Module<Runtime>;
+	type GetStableCurrencyId = GetStableCurrencyId;
+	type AuctionManagerHandler = module_auction_manager::Module<Runtime>;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type Dex = module_dex::Module<Runtime>;
+	type PriceSource = module_emergency_shutdown::Module<Runtime>;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
 }
 
 impl module_debits::Trait for Runtime {
@@ -336,71 +391,237 @@ impl module_debits::Trait for Runtime {
 	type GetStableCurrencyId = GetStableCurrencyId;
 	type DebitBalance = Balance;
 	type DebitAmount = Amount;
-	type Convert = module_cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type Convert = DebitExchangeRateConvertor<module_cdp_engine::Module<Runtime>>;
+}
+
+parameter_types! {
+	// the chains launched since the positions are stored in `Positions` and their owners linked have none to migrate
+	pub const VaultsPositionOwnersToMigrate: Vec<AccountId> = vec![];
 }
 
 impl module_vaults::Trait for Runtime {
 	type Event = Event;
-	type Convert = module_cdp_engine::DebitExchangeRateConvertor<Runtime>;
+	type Convert = DebitExchangeRateConvertor<module_cdp_engine::Module<Runtime>>;
 	type Currency = orml_currencies::Module<Runtime>;
 	type DebitCurrency = module_debits::Module<Runtime>;
 	type RiskManager = module_cdp_engine::Module<Runtime>;
+	type OnUpdateLoan = module_incentives::Module<Runtime>;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type PositionOwnersToMigrate = VaultsPositionOwnersToMigrate;
 }
 
 parameter_types! {
-	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![CurrencyId::DOT, CurrencyId::XBTC];
-	pub const GlobalStabilityFee: Rate = Rate::from_rational(0, 0);
-	pub const DefaultLiquidationRatio: Ratio = Ratio::from_rational(3, 2);
+	pub const CollateralCurrencyIds: Vec<CurrencyId> = vec![CurrencyId::DOT, CurrencyId::BTC];
+	pub const GlobalStabilityFee: Rate = Rate::from_percent(0);
+	pub const DefaultLiquidationRatio: Ratio = Ratio::from_percent(150);
 	pub const DefaulDebitExchangeRate: ExchangeRate = ExchangeRate::from_rational(1, 1);
 	pub const MinimumDebitValue: Balance = 1_000_000_000_000_000;
+	pub const MaxPositionsCheckedPerBlock: u32 = 100;
 }
+
+pub type CdpEngineSubmitTransaction = TransactionSubmitter<(), Runtime, UncheckedExtrinsic>;
+
 impl module_cdp_engine::Trait for Runtime {
 	type Event = Event;
 	type AuctionManagerHandler = module_auction_manager::Module<Runtime>;
 	type Currency = orml_currencies::Module<Runtime>;
-	type PriceSource = orml_prices::Module<Runtime>;
+	type PriceSource = module_emergency_shutdown::Module<Runtime>;
 	type CollateralCurrencyIds = CollateralCurrencyIds;
 	type GlobalStabilityFee = GlobalStabilityFee;
+	type StabilityFeeModel = ();
 	type DefaultLiquidationRatio = DefaultLiquidationRatio;
 	type DefaulDebitExchangeRate = DefaulDebitExchangeRate;
 	type MinimumDebitValue = MinimumDebitValue;
 	type GetStableCurrencyId = GetStableCurrencyId;
+	type Treasury = module_cdp_treasury::Module<Runtime>;
+	type Dex = module_dex::Module<Runtime>;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type Call = Call;
+	type SubmitTransaction = CdpEngineSubmitTransaction;
+	type MaxPositionsCheckedPerBlock = MaxPositionsCheckedPerBlock;
+}
+
+parameter_types! {
+	pub const MaxAdjustmentsPerCall: u32 = 2;
 }
 
 impl module_honzon::Trait for Runtime {
 	type Event = Event;
+	type MaxAdjustmentsPerCall = MaxAdjustmentsPerCall;
 }
 
-construct_runtime!(
-	pub enum Runtime where
-		Block = Block,
-		NodeBlock = opaque::Block,
-		UncheckedExtrinsic = UncheckedExtrinsic
-	{
-		System: system::{Module, Call, Storage, Config, Event},
-		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
-		Aura: pallet_aura::{Module, Storage, Config<T>, Inherent(Timestamp)},
-		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
-		Indices: pallet_indices,
-		Balances: pallet_balances,
-		TransactionPayment: pallet_transaction_payment::{Module, Storage},
-		Sudo: pallet_sudo,
-		RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Module, Call, Storage},
-		OperatorCollective: pallet_collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
-		OperatorMembership: pallet_membership::<Instance1>::{Module, Call, Storage, Event<T>, Config<T>},
-
-		Currencies: orml_currencies::{Module, Call, Event<T>},
-		Oracle: orml_oracle::{Module, Storage, Call, Event<T>},
-		Tokens: orml_tokens::{Module, Storage, Call, Event<T>, Config<T>},
-		Auction: orml_auction::{Module, Storage, Call, Event<T>},
-		AuctionManager: module_auction_manager::{Module, Storage, Call, Event<T>},
-		Debits: module_debits::{Module},
-		Vaults: module_vaults::{Module, Storage, Call, Event<T>},
-		CdpEngine: module_cdp_engine::{Module, Storage, Call, Event<T>},
-		Honzon: module_honzon::{Module, Storage, Call, Event<T>},
+parameter_types! {
+	pub const MaxAuctionsCancelledPerBlock: u32 = 50;
+}
 
-	}
-);
+impl module_emergency_shutdown::Trait for Runtime {
+	type Event = Event;
+	type PriceSource = orml_prices::Module<Runtime>;
+	type AuctionManagerHandler = module_auction_manager::Module<Runtime>;
+	type OnShutdown = (CdpEngine, CdpTreasury, Incentives, Vesting);
+	type MaxAuctionsCancelledPerBlock = MaxAuctionsCancelledPerBlock;
+}
+
+parameter_types! {
+	pub const GetExchangeFee: Rate = Rate::from_rational(1, 1000);
+	pub const MinimumLiquidity: Share = 1_000;
+	pub const MinimumShareIncrement: Share = 1_000;
+	pub const MaxSwapPathLength: u32 = 3;
+	pub const MaxSwapBatchSize: u32 = 3;
+	pub const MaxLiquidityRefundsPerBlock: u32 = 50;
+	// the whole exchange fee goes to the liquidity providers for now
+	pub const GetProtocolFeeRate: Rate = Rate::from_natural(0);
+	pub const DexTreasuryModuleId: ModuleId = ModuleId(*b"aca/dext");
+	// 1_000 aUSD
+	pub const GetMinimumPoolSizeForPricing: Balance = 1_000 * 1_000_000_000_000_000_000;
+	pub const DexPoolCurrencyIdsToMigrate: Vec<CurrencyId> = vec![CurrencyId::DOT, CurrencyId::BTC];
+	// the chains launched since the share holders are tracked have none to migrate
+	pub const DexShareHoldersToMigrate: Vec<AccountId> = vec![];
+	pub const DexPriceDeviationCurrencyIds: Vec<CurrencyId> = vec![CurrencyId::DOT, CurrencyId::BTC];
+	pub const DexPriceDeviationThreshold: Ratio = Ratio::from_percent(5);
+	pub const DexPriceDeviationReportInterval: BlockNumber = 10 * MINUTES;
+}
+
+pub type DexSubmitTransaction = TransactionSubmitter<(), Runtime, UncheckedExtrinsic>;
+
+impl module_dex::Trait for Runtime {
+	type Event = Event;
+	type Currency = orml_currencies::Module<Runtime>;
+	type Share = Share;
+	type GetBaseCurrencyId = GetStableCurrencyId;
+	type GetExchangeFee = GetExchangeFee;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumShareIncrement = MinimumShareIncrement;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type MaxBatchSize = MaxSwapBatchSize;
+	type MaxLiquidityRefundsPerBlock = MaxLiquidityRefundsPerBlock;
+	type GetProtocolFeeRate = GetProtocolFeeRate;
+	type TreasuryModuleId = DexTreasuryModuleId;
+	type GetMinimumPoolSizeForPricing = GetMinimumPoolSizeForPricing;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+	type PoolCurrencyIdsToMigrate = DexPoolCurrencyIdsToMigrate;
+	type ShareHoldersToMigrate = DexShareHoldersToMigrate;
+	type PriceSource = orml_prices::Module<Runtime>;
+	type PriceDeviationCurrencyIds = DexPriceDeviationCurrencyIds;
+	type PriceDeviationThreshold = DexPriceDeviationThreshold;
+	type PriceDeviationReportInterval = DexPriceDeviationReportInterval;
+	type Call = Call;
+	type SubmitTransaction = DexSubmitTransaction;
+	type OnUpdateShares = module_incentives::Module<Runtime>;
+	type UpdateOrigin = system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const FeeCurrencyIds: Vec<CurrencyId> = vec![CurrencyId::AUSD, CurrencyId::DOT, CurrencyId::BTC];
+	pub const MaxSlippageSwapWithDEX: Ratio = Ratio::from_percent(1);
+}
+
+impl module_transaction_payment::Trait for Runtime {
+	type MultiCurrency = orml_currencies::Module<Runtime>;
+	type DEX = module_dex::Module<Runtime>;
+	type NativeCurrencyId = GetNativeCurrencyId;
+	type FeeCurrencyIds = FeeCurrencyIds;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+}
+
+impl module_incentives::Trait for Runtime {
+	type Event = Event;
+	type Currency = orml_currencies::Module<Runtime>;
+	type RewardCurrencyId = GetNativeCurrencyId;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type Vesting = module_vesting::Module<Runtime>;
+	type DexShareCurrencyIds = module_primitives::DexShareCurrencyIds;
+}
+
+parameter_types! {
+	pub const MaxVestingSchedules: u32 = 32;
+}
+
+impl module_vesting::Trait for Runtime {
+	type Event = Event;
+	type Currency = orml_currencies::Module<Runtime>;
+	type VestingCurrencyId = GetNativeCurrencyId;
+	type MaxVestingSchedules = MaxVestingSchedules;
+}
+
+parameter_types! {
+	pub const MaxBatchSize: u32 = 8;
+}
+
+impl module_batch::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type BatchChecker = ProtocolBatchChecker;
+	type MaxBatchSize = MaxBatchSize;
+}
+
+parameter_types! {
+	pub const FaucetCurrencyIds: Vec<CurrencyId> = vec![CurrencyId::AUSD, CurrencyId::DOT, CurrencyId::BTC];
+	// 1000 dollars of 18 decimals
+	pub const DefaultDripAmount: Balance = 1_000 * 1_000_000_000_000_000_000;
+	pub const DripPeriod: BlockNumber = HOURS;
+}
+
+#[cfg(feature = "faucet")]
+impl module_faucet::Trait for Runtime {
+	type Event = Event;
+	type Currency = orml_currencies::Module<Runtime>;
+	type FaucetCurrencyIds = FaucetCurrencyIds;
+	type DefaultDripAmount = DefaultDripAmount;
+	type DripPeriod = DripPeriod;
+}
+
+// The development modules are only included with the `faucet` feature, never enable it for production.
+macro_rules! construct_acala_runtime {
+	($($dev_modules:tt)*) => {
+		construct_runtime!(
+			pub enum Runtime where
+				Block = Block,
+				NodeBlock = opaque::Block,
+				UncheckedExtrinsic = UncheckedExtrinsic
+			{
+				System: system::{Module, Call, Storage, Config, Event},
+				Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+				Aura: pallet_aura::{Module, Storage, Config<T>, Inherent(Timestamp)},
+				Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
+				Indices: pallet_indices,
+				Balances: pallet_balances,
+				TransactionPayment: pallet_transaction_payment::{Module, Storage},
+				Sudo: pallet_sudo,
+				RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Module, Call, Storage},
+				OperatorCollective: pallet_collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
+				OperatorMembership: pallet_membership::<Instance1>::{Module, Call, Storage, Event<T>, Config<T>},
+
+				Currencies: orml_currencies::{Module, Call, Event<T>},
+				Oracle: orml_oracle::{Module, Storage, Call, Event<T>},
+				Tokens: orml_tokens::{Module, Storage, Call, Event<T>, Config<T>},
+				Auction: orml_auction::{Module, Storage, Call, Event<T>},
+				AuctionManager: module_auction_manager::{Module, Storage, Call, Event<T>},
+				Debits: module_debits::{Module},
+				Vaults: module_vaults::{Module, Storage, Call, Event<T>},
+				CdpEngine: module_cdp_engine::{Module, Storage, Call, Event<T>, ValidateUnsigned},
+				Honzon: module_honzon::{Module, Storage, Call, Event<T>},
+				CdpTreasury: module_cdp_treasury::{Module, Storage, Call, Event<T>},
+				EmergencyShutdown: module_emergency_shutdown::{Module, Storage, Call, Event<T>},
+				Dex: module_dex::{Module, Storage, Call, Event<T>, Config<T>, ValidateUnsigned},
+				Incentives: module_incentives::{Module, Storage, Call, Event<T>},
+				Vesting: module_vesting::{Module, Storage, Call, Event<T>},
+				Batch: module_batch::{Module, Call, Event},
+				$($dev_modules)*
+			}
+		);
+	};
+}
+
+#[cfg(feature = "faucet")]
+construct_acala_runtime! {
+	Faucet: module_faucet::{Module, Storage, Call, Event<T>},
+}
+
+#[cfg(not(feature = "faucet"))]
+construct_acala_runtime! {}
 
 /// The address format for describing accounts.
 pub type Address = <Indices as StaticLookup>::Source;
@@ -417,16 +638,20 @@ pub type SignedExtra = (
 	system::CheckVersion<Runtime>,
 	system::CheckGenesis<Runtime>,
 	system::CheckEra<Runtime>,
+	// before the nonce and fee, nothing is written for the rejected calls
+	CheckShutdown,
+	CheckDexShareTransfer,
 	system::CheckNonce<Runtime>,
 	system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	module_transaction_payment::ChargeTransactionPayment<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
 /// Extrinsic type that has already been checked.
 pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Call, SignedExtra>;
-/// Executive: handles dispatch to the various modules.
-pub type Executive = frame_executive::Executive<Runtime, Block, system::ChainContext<Runtime>, Runtime, AllModules>;
+/// Executive: handles dispatch to the various modules, the storage migrations are applied once per runtime upgrade.
+pub type Executive =
+	frame_executive::Executive<Runtime, Block, system::ChainContext<Runtime>, Runtime, AllModulesWithMigrations>;
 
 impl_runtime_apis! {
 	impl sp_api::Core<Block> for Runtime {
@@ -507,4 +732,64 @@ impl_runtime_apis! {
 			Grandpa::grandpa_authorities()
 		}
 	}
+
+	impl acala_rpc_runtime_api::AccountOverviewApi<Block, AccountId, CurrencyId, Balance> for Runtime {
+		fn account_overview(who: AccountId) -> acala_rpc_runtime_api::AccountOverview<AccountId, CurrencyId, Balance> {
+			account_overview::account_overview(who)
+		}
+	}
+
+	impl acala_rpc_runtime_api::DexApi<Block, AccountId, CurrencyId, Balance> for Runtime {
+		fn get_supply_amount(
+			supply_currency_id: CurrencyId,
+			target_currency_id: CurrencyId,
+			target_currency_amount: Balance,
+		) -> Option<Balance> {
+			dex_quote::get_supply_amount(supply_currency_id, target_currency_id, target_currency_amount)
+		}
+
+		fn get_target_amount(
+			supply_currency_id: CurrencyId,
+			target_currency_id: CurrencyId,
+			supply_currency_amount: Balance,
+		) -> Option<Balance> {
+			dex_quote::get_target_amount(supply_currency_id, target_currency_id, supply_currency_amount)
+		}
+
+		fn get_liquidity(who: AccountId, currency_id: CurrencyId) -> (Balance, Balance) {
+			dex_quote::get_liquidity(who, currency_id)
+		}
+
+		fn pool_info(currency_id: CurrencyId) -> acala_rpc_runtime_api::PoolInfo<Balance> {
+			dex_quote::pool_info(currency_id)
+		}
+	}
+
+	impl acala_rpc_runtime_api::HonzonApi<Block, AccountId, CurrencyId, Balance> for Runtime {
+		fn get_position_info(who: AccountId, currency_id: CurrencyId) -> acala_rpc_runtime_api::PositionInfo<Balance> {
+			position_info::get_position_info(who, currency_id)
+		}
+
+		fn max_issuable_debit(who: AccountId, currency_id: CurrencyId) -> Balance {
+			position_info::max_issuable_debit(who, currency_id)
+		}
+	}
+
+	impl acala_rpc_runtime_api::CdpEngineApi<Block, CurrencyId, Balance> for Runtime {
+		fn get_system_info() -> acala_rpc_runtime_api::SystemInfo<CurrencyId, Balance> {
+			cdp_system_info::get_system_info()
+		}
+	}
+
+	impl acala_rpc_runtime_api::DryRunApi<Block, AccountId, Call> for Runtime {
+		fn dry_run_call(who: AccountId, call: Call) -> Result<(), sp_runtime::DispatchError> {
+			dry_run::dry_run_call(who, call)
+		}
+	}
+
+	impl acala_rpc_runtime_api::SystemStatsApi<Block, CurrencyId, Balance> for Runtime {
+		fn system_stats() -> acala_rpc_runtime_api::SystemStats<CurrencyId, Balance> {
+			system_stats::system_stats()
+		}
+	}
 }
@@ -0,0 +1,53 @@
+//! Apply the storage migrations of the modules once per runtime upgrade, before anything else in the first block
+//! initialized by the new runtime.
+
+use frame_support::{storage::unhashed, weights::WeighBlock};
+use sp_runtime::traits::{OffchainWorker, OnFinalize, OnInitialize};
+
+use crate::{AllModules, AuctionManager, BlockNumber, CdpTreasury, Dex, Runtime, Vaults, Weight, VERSION};
+
+/// The key of the spec version the migrations were last applied for, in the raw storage.
+const MIGRATED_SPEC_VERSION: &[u8] = b":acala:migrated_spec_version";
+
+/// Apply the pending migrations of every module and return the weight consumed.
+pub fn on_runtime_upgrade() -> Weight {
+	AuctionManager::on_runtime_upgrade()
+		.saturating_add(Vaults::on_runtime_upgrade())
+		.saturating_add(CdpTreasury::on_runtime_upgrade())
+		.saturating_add(Dex::on_runtime_upgrade())
+}
+
+/// The hooks of all the modules, with the migrations applied first in the first block of each spec version.
+pub struct AllModulesWithMigrations;
+
+impl OnInitialize<BlockNumber> for AllModulesWithMigrations {
+	fn on_initialize(n: BlockNumber) {
+		if unhashed::get::<u32>(MIGRATED_SPEC_VERSION) != Some(VERSION.spec_version) {
+			<system::Module<Runtime>>::register_extra_weight_unchecked(on_runtime_upgrade());
+			unhashed::put(MIGRATED_SPEC_VERSION, &VERSION.spec_version);
+		}
+		<AllModules as OnInitialize<BlockNumber>>::on_initialize(n);
+	}
+}
+
+impl OnFinalize<BlockNumber> for AllModulesWithMigrations {
+	fn on_finalize(n: BlockNumber) {
+		<AllModules as OnFinalize<BlockNumber>>::on_finalize(n);
+	}
+}
+
+impl OffchainWorker<BlockNumber> for AllModulesWithMigrations {
+	fn offchain_worker(n: BlockNumber) {
+		<AllModules as OffchainWorker<BlockNumber>>::offchain_worker(n);
+	}
+}
+
+impl WeighBlock<BlockNumber> for AllModulesWithMigrations {
+	fn on_initialize(n: BlockNumber) -> Weight {
+		<AllModules as WeighBlock<BlockNumber>>::on_initialize(n)
+	}
+
+	fn on_finalize(n: BlockNumber) -> Weight {
+		<AllModules as WeighBlock<BlockNumber>>::on_finalize(n)
+	}
+}
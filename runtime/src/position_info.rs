@@ -0,0 +1,25 @@
+//! The position of an account with the values derived from it, for front-ends to show the health of a loan.
+
+use acala_rpc_runtime_api::PositionInfo;
+
+use crate::{AccountId, Balance, CurrencyId, Runtime};
+
+type CdpEngine = module_cdp_engine::Module<Runtime>;
+
+/// The position of `who` in collateral `currency_id` at the current price and parameters.
+pub fn get_position_info(who: AccountId, currency_id: CurrencyId) -> PositionInfo<Balance> {
+	let info = CdpEngine::position_info(&who, currency_id);
+	PositionInfo {
+		collateral: info.collateral.into(),
+		debit: info.debit.into(),
+		debit_value: info.debit_value.into(),
+		collateral_ratio: info.collateral_ratio.map(Into::into),
+		liquidation_ratio: info.liquidation_ratio.into(),
+		liquidation_price: info.liquidation_price.map(Into::into),
+	}
+}
+
+/// The largest debit `who` can borrow more in collateral `currency_id` at the current price and parameters.
+pub fn max_issuable_debit(who: AccountId, currency_id: CurrencyId) -> Balance {
+	CdpEngine::max_issuable_debit(&who, currency_id)
+}
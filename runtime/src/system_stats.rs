@@ -0,0 +1,89 @@
+//! Assemble the `SystemStats` from the counters of the modules.
+
+use acala_rpc_runtime_api::{CollateralStats, SystemStats};
+use frame_support::traits::Get;
+use module_support::{DebitConvertor, FixedU128Ext, PriceProviderExtended, Ratio};
+use rstd::prelude::*;
+use sp_runtime::traits::{Saturating, Zero};
+
+use crate::{Balance, CollateralCurrencyIds, CurrencyId, DebitExchangeRateConvertor, GetStableCurrencyId, Runtime};
+
+type Dex = module_dex::Module<Runtime>;
+type Vaults = module_vaults::Module<Runtime>;
+type CdpEngine = module_cdp_engine::Module<Runtime>;
+type CdpTreasury = module_cdp_treasury::Module<Runtime>;
+type AuctionManager = module_auction_manager::Module<Runtime>;
+type EmergencyShutdown = module_emergency_shutdown::Module<Runtime>;
+
+/// All the currencies known by the runtime.
+const CURRENCY_IDS: [CurrencyId; 4] = [CurrencyId::ACA, CurrencyId::AUSD, CurrencyId::DOT, CurrencyId::BTC];
+
+pub fn system_stats() -> SystemStats<CurrencyId, Balance> {
+	let stable_currency_id = GetStableCurrencyId::get();
+
+	let collaterals: Vec<CollateralStats<CurrencyId, Balance>> = CollateralCurrencyIds::get()
+		.into_iter()
+		.map(|currency_id| {
+			let total_collateral = Vaults::total_collaterals(currency_id);
+			CollateralStats {
+				currency_id,
+				total_collateral: total_collateral.into(),
+				total_collateral_value: EmergencyShutdown::get_value(stable_currency_id, currency_id, total_collateral)
+					.map(Into::into),
+				total_debit_value: DebitExchangeRateConvertor::<CdpEngine>::convert_debit_to_balance_ceil(
+					currency_id,
+					Vaults::total_debits(currency_id),
+				)
+				.into(),
+				collateral_in_auction: AuctionManager::total_collateral_in_auction(currency_id).into(),
+			}
+		})
+		.collect();
+
+	let total_debit_value = collaterals.iter().fold(Balance::zero(), |total, collateral| {
+		total.saturating_add(collateral.total_debit_value.0)
+	});
+
+	// unknown if the price of any collateral is unavailable
+	let global_collateral_ratio = collaterals
+		.iter()
+		.try_fold(Balance::zero(), |total, collateral| {
+			collateral
+				.total_collateral_value
+				.as_ref()
+				.map(|value| total.saturating_add(value.0))
+		})
+		.filter(|_| !total_debit_value.is_zero())
+		.and_then(|total_collateral_value| Ratio::checked_from_rational(total_collateral_value, total_debit_value))
+		.map(Into::into);
+
+	// the pools are balanced at their own prices, so both sides are worth the base side
+	let dex_total_value_locked = CURRENCY_IDS
+		.iter()
+		.filter(|&&currency_id| currency_id != stable_currency_id)
+		.fold(Balance::zero(), |total, &currency_id| {
+			let (_, base_currency_amount) = Dex::liquidity_pool(currency_id);
+			total.saturating_add(base_currency_amount.saturating_mul(2))
+		});
+
+	let treasury_dex_liquidity_value = CURRENCY_IDS
+		.iter()
+		.map(|&currency_id| (currency_id, CdpTreasury::dex_shares(currency_id)))
+		.filter(|(_, share_amount)| !share_amount.is_zero())
+		.fold(Balance::zero(), |total, (currency_id, share_amount)| {
+			Dex::get_redeemable_amounts(currency_id, share_amount).map_or(total, |(_, base_currency_amount)| {
+				total.saturating_add(base_currency_amount.saturating_mul(2))
+			})
+		});
+
+	SystemStats {
+		collaterals,
+		total_debit_value: total_debit_value.into(),
+		global_collateral_ratio,
+		surplus_pool: CdpTreasury::surplus_pool().into(),
+		debit_pool: CdpTreasury::debit_pool().into(),
+		target_in_auction: AuctionManager::total_target_in_auction().into(),
+		dex_total_value_locked: dex_total_value_locked.into(),
+		treasury_dex_liquidity_value: treasury_dex_liquidity_value.into(),
+	}
+}
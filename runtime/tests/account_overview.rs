@@ -0,0 +1,115 @@
+//! Integration tests of the account overview runtime api.
+
+use acala_rpc_runtime_api::{AuthorizationOverview, BalanceWrapper, CurrencyBalance, DexShareOverview};
+use acala_runtime::{AccountId, Balance, Call, CurrencyId, Origin, Runtime};
+use frame_support::traits::OnFinalize;
+use sp_runtime::{traits::Dispatchable, BuildStorage};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const BOB: [u8; 32] = [2u8; 32];
+const INITIAL_BALANCE: Balance = 1_000_000;
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::BTC],
+		initial_balance: INITIAL_BALANCE,
+		endowed_accounts: vec![AccountId::from(ALICE), AccountId::from(BOB)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::BTC],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	t.into()
+}
+
+#[test]
+fn account_overview_reflects_actions() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let bob = AccountId::from(BOB);
+
+		let overview = acala_runtime::account_overview(alice.clone());
+		assert_eq!(
+			overview.balances,
+			vec![
+				CurrencyBalance {
+					currency_id: CurrencyId::ACA,
+					balance: BalanceWrapper(0)
+				},
+				CurrencyBalance {
+					currency_id: CurrencyId::AUSD,
+					balance: BalanceWrapper(INITIAL_BALANCE)
+				},
+				CurrencyBalance {
+					currency_id: CurrencyId::DOT,
+					balance: BalanceWrapper(0)
+				},
+				CurrencyBalance {
+					currency_id: CurrencyId::BTC,
+					balance: BalanceWrapper(INITIAL_BALANCE)
+				},
+			]
+		);
+		assert!(overview.dex_shares.is_empty());
+		assert!(overview.positions.is_empty());
+		assert!(overview.authorizations.is_empty());
+
+		assert!(
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None))
+				.dispatch(Origin::signed(alice.clone()))
+				.is_ok()
+		);
+		assert!(
+			Call::Incentives(module_incentives::Call::stake_lp_shares(CurrencyId::BTC, 9_000))
+				.dispatch(Origin::signed(alice.clone()))
+				.is_ok()
+		);
+		assert!(
+			Call::Incentives(module_incentives::Call::set_lp_reward_per_block(CurrencyId::BTC, 50))
+				.dispatch(Origin::ROOT)
+				.is_ok()
+		);
+		<module_incentives::Module<Runtime> as OnFinalize<_>>::on_finalize(1);
+		assert!(
+			Call::Honzon(module_honzon::Call::authorize(CurrencyId::BTC, bob.clone().into()))
+				.dispatch(Origin::signed(alice.clone()))
+				.is_ok()
+		);
+
+		let overview = acala_runtime::account_overview(alice);
+		assert_eq!(overview.balances[1].balance.into_inner(), INITIAL_BALANCE - 10_000);
+		assert_eq!(overview.balances[3].balance.into_inner(), INITIAL_BALANCE - 100);
+		assert_eq!(
+			overview.dex_shares,
+			vec![DexShareOverview {
+				currency_id: CurrencyId::BTC,
+				shares: 9_000.into(),
+				other_currency_amount: 90.into(),
+				base_currency_amount: 9_000.into(),
+			}]
+		);
+		assert_eq!(
+			overview.pending_dex_rewards,
+			vec![CurrencyBalance {
+				currency_id: CurrencyId::BTC,
+				balance: BalanceWrapper(50)
+			}]
+		);
+		assert!(overview.pending_loans_rewards.is_empty());
+		assert_eq!(
+			overview.authorizations,
+			vec![AuthorizationOverview {
+				currency_id: CurrencyId::BTC,
+				to: bob,
+			}]
+		);
+	});
+}
@@ -0,0 +1,218 @@
+//! Integration tests of the atomic batches of dex swaps, loan updates and token transfers.
+
+use acala_runtime::{dollar, AccountId, Amount, Balance, Call, CurrencyId, Event, Origin, Price, Runtime};
+use frame_support::StorageMap;
+use orml_traits::MultiCurrency;
+use sp_runtime::{traits::Dispatchable, BuildStorage, DispatchError};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const BOB: [u8; 32] = [2u8; 32];
+
+type Currencies = orml_currencies::Module<Runtime>;
+type Dex = module_dex::Module<Runtime>;
+type System = system::Module<Runtime>;
+type Vaults = module_vaults::Module<Runtime>;
+
+fn dollars(currency_id: CurrencyId, amount: u128) -> Balance {
+	amount * dollar(currency_id)
+}
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::DOT],
+		initial_balance: dollars(CurrencyId::AUSD, 10_000),
+		endowed_accounts: vec![AccountId::from(ALICE), AccountId::from(BOB)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::DOT],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	let mut ext: runtime_io::TestExternalities = t.into();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		// a pool at 10 AUSD per DOT, and the same price for the loans
+		assert!(Call::Dex(module_dex::Call::add_liquidity(
+			CurrencyId::DOT,
+			dollars(CurrencyId::DOT, 1_000),
+			dollars(CurrencyId::AUSD, 10_000),
+			0,
+			None
+		))
+		.dispatch(Origin::signed(AccountId::from(BOB)))
+		.is_ok());
+		module_emergency_shutdown::LockedPrices::<Runtime>::insert(CurrencyId::DOT, Price::from_natural(10));
+		module_cdp_engine::MaximumTotalDebitValue::<Runtime>::insert(
+			CurrencyId::DOT,
+			dollars(CurrencyId::AUSD, 10_000),
+		);
+	});
+	ext
+}
+
+/// Swap 100 AUSD for DOT, deposit 9 DOT of it and mint `debit` AUSD against it.
+fn swap_deposit_and_mint(debit: Balance) -> Vec<Call> {
+	vec![
+		Call::Dex(module_dex::Call::swap_with_exact_supply(
+			(CurrencyId::AUSD, dollars(CurrencyId::AUSD, 100)),
+			(CurrencyId::DOT, dollars(CurrencyId::DOT, 9)),
+			None,
+		)),
+		Call::Honzon(module_honzon::Call::update_vault(
+			CurrencyId::DOT,
+			dollars(CurrencyId::DOT, 9) as Amount,
+			0,
+		)),
+		Call::Honzon(module_honzon::Call::update_vault(CurrencyId::DOT, 0, debit as Amount)),
+	]
+}
+
+fn batch_atomic(who: &AccountId, calls: Vec<Call>) -> Result<(), DispatchError> {
+	Call::Batch(module_batch::Call::batch_atomic(calls))
+		.dispatch(Origin::signed(who.clone()))
+		.map_err(Into::into)
+}
+
+fn has_event(event: module_batch::Event) -> bool {
+	System::events()
+		.iter()
+		.any(|record| record.event == Event::module_batch(event.clone()))
+}
+
+/// The balances of `who`, the pool and the loan the batches change.
+fn state_of(who: &AccountId) -> (Balance, Balance, (Balance, Balance), Balance, Balance) {
+	(
+		Currencies::balance(CurrencyId::AUSD, who),
+		Currencies::balance(CurrencyId::DOT, who),
+		Dex::liquidity_pool(CurrencyId::DOT),
+		Vaults::collaterals(who, CurrencyId::DOT),
+		Vaults::debits(who, CurrencyId::DOT),
+	)
+}
+
+#[test]
+fn batch_is_refused_when_the_mint_breaks_the_collateral_ratio() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let before = state_of(&alice);
+
+		// 9 DOT worth 90 AUSD can't back 70 AUSD at the liquidation ratio of 150%
+		let error: DispatchError = module_cdp_engine::Error::<Runtime>::BelowLiquidationRatio.into();
+		assert_eq!(
+			batch_atomic(&alice, swap_deposit_and_mint(dollars(CurrencyId::AUSD, 70))),
+			Err(error.clone())
+		);
+		assert!(has_event(module_batch::Event::BatchRefused(2, error)));
+		assert_eq!(state_of(&alice), before);
+		assert_eq!(module_vaults::TotalCollaterals::<Runtime>::get(CurrencyId::DOT), 0);
+		assert_eq!(module_vaults::TotalDebits::<Runtime>::get(CurrencyId::DOT), 0);
+	});
+}
+
+#[test]
+fn batch_swaps_deposits_and_mints_at_once() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let (ausd_balance, dot_balance, (dot_pool, ausd_pool), _, _) = state_of(&alice);
+		let (swapped, _) = Dex::calculate_swap_target_amount(
+			ausd_pool,
+			dot_pool,
+			dollars(CurrencyId::AUSD, 100),
+			Dex::get_exchange_fee(CurrencyId::DOT),
+		)
+		.unwrap();
+
+		assert_eq!(
+			batch_atomic(&alice, swap_deposit_and_mint(dollars(CurrencyId::AUSD, 50))),
+			Ok(())
+		);
+		assert!(has_event(module_batch::Event::BatchCompleted));
+		assert_eq!(
+			state_of(&alice),
+			(
+				ausd_balance - dollars(CurrencyId::AUSD, 100) + dollars(CurrencyId::AUSD, 50),
+				dot_balance + swapped - dollars(CurrencyId::DOT, 9),
+				(dot_pool - swapped, ausd_pool + dollars(CurrencyId::AUSD, 100)),
+				dollars(CurrencyId::DOT, 9),
+				dollars(CurrencyId::AUSD, 50),
+			)
+		);
+	});
+}
+
+#[test]
+fn batch_can_not_deposit_what_it_sent_away() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let bob = AccountId::from(BOB);
+		let before = state_of(&alice);
+
+		// alice has the DOT, but sends it away before depositing it
+		let calls = vec![
+			Call::Currencies(orml_currencies::Call::transfer(
+				bob.into(),
+				CurrencyId::DOT,
+				dollars(CurrencyId::DOT, 10_000),
+			)),
+			Call::Honzon(module_honzon::Call::update_vault(
+				CurrencyId::DOT,
+				dollars(CurrencyId::DOT, 9) as Amount,
+				0,
+			)),
+		];
+		let error: DispatchError = module_vaults::Error::<Runtime>::CollateralInSufficient.into();
+		assert_eq!(batch_atomic(&alice, calls), Err(error.clone()));
+		assert!(has_event(module_batch::Event::BatchRefused(1, error)));
+		assert_eq!(state_of(&alice), before);
+	});
+}
+
+#[test]
+fn batch_refuses_other_calls() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let bob = AccountId::from(BOB);
+		let before = state_of(&alice);
+
+		let calls = vec![
+			Call::Dex(module_dex::Call::swap_with_exact_supply(
+				(CurrencyId::AUSD, dollars(CurrencyId::AUSD, 100)),
+				(CurrencyId::DOT, 0),
+				None,
+			)),
+			Call::Honzon(module_honzon::Call::authorize(CurrencyId::DOT, bob.into())),
+		];
+		let error: DispatchError = module_batch::Error::<Runtime>::CallNotAllowed.into();
+		assert_eq!(batch_atomic(&alice, calls), Err(error.clone()));
+		assert!(has_event(module_batch::Event::BatchRefused(1, error)));
+		assert_eq!(state_of(&alice), before);
+	});
+}
+
+#[test]
+fn batch_refuses_dex_share_transfers() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let bob = AccountId::from(BOB);
+		let share_currency_id = CurrencyId::DOT.dex_share_currency_id().unwrap();
+		let shares = Currencies::balance(share_currency_id, &bob);
+		assert!(shares > 0);
+
+		let calls = vec![Call::Currencies(orml_currencies::Call::transfer(
+			alice.into(),
+			share_currency_id,
+			shares,
+		))];
+		let error: DispatchError = module_batch::Error::<Runtime>::CallNotAllowed.into();
+		assert_eq!(batch_atomic(&bob, calls), Err(error.clone()));
+		assert!(has_event(module_batch::Event::BatchRefused(0, error)));
+		assert_eq!(Currencies::balance(share_currency_id, &bob), shares);
+	});
+}
@@ -0,0 +1,227 @@
+//! Executive level tests of accepting only the calls allowed in the phase of the emergency shutdown.
+
+use acala_runtime::{
+	AccountId, Address, Balance, Call, CheckDexShareTransfer, CheckShutdown, CurrencyId, Executive, Header, Origin,
+	Runtime, UncheckedExtrinsic,
+};
+use codec::Encode;
+use sp_keyring::AccountKeyring;
+use sp_runtime::{
+	generic::{Era, SignedPayload},
+	traits::{Dispatchable, Header as HeaderT},
+	transaction_validity::InvalidTransaction,
+	BuildStorage,
+};
+
+const INITIAL_BALANCE: Balance = 1_000_000_000;
+
+fn alice() -> AccountId {
+	AccountKeyring::Alice.to_account_id()
+}
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	pallet_balances::GenesisConfig::<Runtime> {
+		balances: vec![(alice(), INITIAL_BALANCE)],
+		vesting: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::BTC],
+		initial_balance: INITIAL_BALANCE,
+		endowed_accounts: vec![alice()],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::BTC],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	let mut ext: runtime_io::TestExternalities = t.into();
+	ext.execute_with(|| {
+		Executive::initialize_block(&Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		))
+	});
+	ext
+}
+
+fn sign(call: Call) -> UncheckedExtrinsic {
+	let nonce = system::Module::<Runtime>::account_nonce(&alice());
+	let extra = (
+		system::CheckVersion::<Runtime>::new(),
+		system::CheckGenesis::<Runtime>::new(),
+		system::CheckEra::<Runtime>::from(Era::Immortal),
+		CheckShutdown::new(),
+		CheckDexShareTransfer::new(),
+		system::CheckNonce::<Runtime>::from(nonce),
+		system::CheckWeight::<Runtime>::new(),
+		module_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+	);
+	let payload = SignedPayload::new(call, extra).unwrap();
+	let signature = payload.using_encoded(|payload| AccountKeyring::Alice.sign(payload));
+	let (call, extra, _) = payload.deconstruct();
+	UncheckedExtrinsic::new_signed(call, Address::from(alice()), signature.into(), extra)
+}
+
+fn swap() -> Call {
+	Call::Dex(module_dex::Call::swap_with_exact_supply(
+		(CurrencyId::AUSD, 100),
+		(CurrencyId::BTC, 1),
+		None,
+	))
+}
+
+fn transfer() -> Call {
+	Call::Currencies(orml_currencies::Call::transfer(
+		Address::from(AccountKeyring::Bob.to_account_id()),
+		CurrencyId::AUSD,
+		100,
+	))
+}
+
+fn sudo(call: Call) -> Call {
+	Call::Sudo(pallet_sudo::Call::sudo(Box::new(call)))
+}
+
+fn emergency_shutdown() {
+	assert!(
+		Call::EmergencyShutdown(module_emergency_shutdown::Call::emergency_shutdown())
+			.dispatch(Origin::ROOT)
+			.is_ok()
+	);
+}
+
+fn open_collateral_refund() {
+	assert!(
+		Call::EmergencyShutdown(module_emergency_shutdown::Call::open_collateral_refund())
+			.dispatch(Origin::ROOT)
+			.is_ok()
+	);
+}
+
+/// The calls never reach dispatch, neither the nonce nor the fee is taken.
+fn assert_rejected(calls: Vec<Call>) {
+	let nonce = system::Module::<Runtime>::account_nonce(&alice());
+	let free_balance = pallet_balances::Module::<Runtime>::free_balance(alice());
+	for call in calls {
+		assert_eq!(
+			Executive::validate_transaction(sign(call.clone())),
+			Err(InvalidTransaction::Call.into())
+		);
+		assert_eq!(
+			Executive::apply_extrinsic(sign(call)),
+			Err(InvalidTransaction::Call.into())
+		);
+	}
+	assert_eq!(system::Module::<Runtime>::account_nonce(&alice()), nonce);
+	assert_eq!(pallet_balances::Module::<Runtime>::free_balance(alice()), free_balance);
+}
+
+/// The calls are dispatched, successfully or not.
+fn assert_accepted(calls: Vec<Call>) {
+	for call in calls {
+		let nonce = system::Module::<Runtime>::account_nonce(&alice());
+		assert!(Executive::validate_transaction(sign(call.clone())).is_ok());
+		assert!(Executive::apply_extrinsic(sign(call)).is_ok());
+		assert_eq!(system::Module::<Runtime>::account_nonce(&alice()), nonce + 1);
+	}
+}
+
+#[test]
+fn calls_reach_dispatch_before_shutdown() {
+	new_test_ext().execute_with(|| {
+		assert!(Executive::validate_transaction(sign(swap())).is_ok());
+		// dispatched and failed without liquidity, the fee is still charged
+		assert!(Executive::apply_extrinsic(sign(swap())).unwrap().is_err());
+		assert_eq!(system::Module::<Runtime>::account_nonce(&alice()), 1);
+		assert!(pallet_balances::Module::<Runtime>::free_balance(alice()) < INITIAL_BALANCE);
+
+		assert_accepted(vec![
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None)),
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, 100, 50)),
+			Call::Auction(orml_auction::Call::bid(0, 100)),
+			Call::Honzon(module_honzon::Call::settle_cdp(Address::from(alice()), CurrencyId::BTC)),
+			Call::EmergencyShutdown(module_emergency_shutdown::Call::refund_collaterals(100)),
+			transfer(),
+		]);
+	});
+}
+
+#[test]
+fn only_wind_down_calls_reach_dispatch_after_shutdown() {
+	new_test_ext().execute_with(|| {
+		emergency_shutdown();
+
+		assert_rejected(vec![
+			swap(),
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None)),
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, 100, 50)),
+			Call::Honzon(module_honzon::Call::liquidate(Address::from(alice()), CurrencyId::BTC)),
+			Call::Auction(orml_auction::Call::bid(0, 100)),
+			Call::Incentives(module_incentives::Call::claim_rewards(CurrencyId::BTC)),
+			Call::Batch(module_batch::Call::batch_atomic(vec![transfer(), swap()])),
+			// not before refund opens
+			Call::EmergencyShutdown(module_emergency_shutdown::Call::refund_collaterals(100)),
+		]);
+
+		assert_accepted(vec![
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, -100, 0)),
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, 0, -50)),
+			Call::Honzon(module_honzon::Call::settle_cdp(Address::from(alice()), CurrencyId::BTC)),
+			Call::Dex(module_dex::Call::withdraw_liquidity(CurrencyId::BTC, 100, 0, 0, None)),
+			Call::Dex(module_dex::Call::withdraw_liquidity_by_amounts(
+				CurrencyId::BTC,
+				1,
+				100,
+				None,
+			)),
+			Call::Dex(module_dex::Call::transfer_shares(
+				CurrencyId::BTC,
+				Address::from(AccountKeyring::Bob.to_account_id()),
+				100,
+			)),
+			Call::Incentives(module_incentives::Call::unstake_lp_shares(CurrencyId::BTC, 100)),
+			Call::Vesting(module_vesting::Call::claim_vested()),
+			transfer(),
+			Call::Batch(module_batch::Call::batch_atomic(vec![transfer()])),
+			sudo(Call::EmergencyShutdown(
+				module_emergency_shutdown::Call::open_collateral_refund(),
+			)),
+		]);
+	});
+}
+
+#[test]
+fn only_refund_and_withdrawal_calls_reach_dispatch_after_refund_opens() {
+	new_test_ext().execute_with(|| {
+		emergency_shutdown();
+		open_collateral_refund();
+
+		assert_rejected(vec![
+			swap(),
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, 100, 50)),
+			Call::Auction(orml_auction::Call::bid(0, 100)),
+			// all the cdps are settled before refund opens
+			Call::Honzon(module_honzon::Call::settle_cdp(Address::from(alice()), CurrencyId::BTC)),
+		]);
+
+		assert_accepted(vec![
+			Call::EmergencyShutdown(module_emergency_shutdown::Call::refund_collaterals(100)),
+			Call::Honzon(module_honzon::Call::update_vault(CurrencyId::BTC, -100, 0)),
+			Call::Dex(module_dex::Call::withdraw_liquidity(CurrencyId::BTC, 100, 0, 0, None)),
+			transfer(),
+		]);
+	});
+}
@@ -0,0 +1,42 @@
+//! Runs the benchmarks of the dex against the runtime, the weights of `modules/dex/src/weights.rs` are checked
+//! against their output:
+//!
+//! cargo test -p acala-runtime --release --features runtime-benchmarks --test dex_benchmarks -- --nocapture
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use acala_runtime::{CurrencyId, Runtime};
+use module_dex::benchmarking::{self, Benchmark};
+use sp_runtime::BuildStorage;
+
+/// The runs of each benchmark, the median is reported.
+const REPEATS: usize = 50;
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	system::GenesisConfig::default()
+		.build_storage::<Runtime>()
+		.unwrap()
+		.into()
+}
+
+/// The median nanoseconds of `benchmark`, each run from a new storage.
+fn run(benchmark: Benchmark) -> u128 {
+	let mut times: Vec<u128> = (0..REPEATS)
+		.map(|_| {
+			new_test_ext().execute_with(|| {
+				benchmarking::measure::<Runtime>(benchmark, (CurrencyId::BTC, CurrencyId::DOT))
+					.unwrap_or_else(|error| panic!("benchmark {:?} failed: {:?}", benchmark, error))
+			})
+		})
+		.collect();
+	times.sort();
+	times[REPEATS / 2]
+}
+
+#[test]
+fn dex_benchmarks() {
+	for benchmark in benchmarking::benchmarks::<Runtime>() {
+		// a weight of 1_000 an access to the storage, about a microsecond
+		println!("{:?}: {} ns", benchmark, run(benchmark));
+	}
+}
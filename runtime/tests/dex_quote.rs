@@ -0,0 +1,153 @@
+//! Integration tests of the dex quotes runtime api.
+
+use acala_runtime::{AccountId, Balance, Call, CurrencyId, Origin, Runtime};
+use orml_traits::MultiCurrency;
+use sp_runtime::{traits::Dispatchable, BuildStorage};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const INITIAL_BALANCE: Balance = 1_000_000;
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::BTC],
+		initial_balance: INITIAL_BALANCE,
+		endowed_accounts: vec![AccountId::from(ALICE)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::BTC],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	t.into()
+}
+
+fn balance(currency_id: CurrencyId) -> Balance {
+	orml_tokens::Module::<Runtime>::free_balance(currency_id, &AccountId::from(ALICE))
+}
+
+#[test]
+fn quotes_are_none_for_empty_pools() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			acala_runtime::get_target_amount(CurrencyId::BTC, CurrencyId::AUSD, 100),
+			None
+		);
+		assert_eq!(
+			acala_runtime::get_supply_amount(CurrencyId::BTC, CurrencyId::AUSD, 100),
+			None
+		);
+		assert_eq!(
+			acala_runtime::get_target_amount(CurrencyId::AUSD, CurrencyId::DOT, 100),
+			None
+		);
+	});
+}
+
+#[test]
+fn quotes_match_swaps() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		assert!(Call::Dex(module_dex::Call::add_liquidity(
+			CurrencyId::BTC,
+			100_000,
+			100_000,
+			0,
+			None
+		))
+		.dispatch(Origin::signed(alice.clone()))
+		.is_ok());
+
+		// 9_090 without the exchange fee of 0.1%
+		let target_amount = acala_runtime::get_target_amount(CurrencyId::BTC, CurrencyId::AUSD, 10_000);
+		assert_eq!(target_amount, Some(9_081));
+		let ausd_balance = balance(CurrencyId::AUSD);
+		assert!(Call::Dex(module_dex::Call::swap_with_exact_supply(
+			(CurrencyId::BTC, 10_000),
+			(CurrencyId::AUSD, 9_081),
+			None
+		))
+		.dispatch(Origin::signed(alice.clone()))
+		.is_ok());
+		assert_eq!(balance(CurrencyId::AUSD) - ausd_balance, 9_081);
+
+		let supply_amount = acala_runtime::get_supply_amount(CurrencyId::AUSD, CurrencyId::BTC, 1_000).unwrap();
+		let ausd_balance = balance(CurrencyId::AUSD);
+		assert!(Call::Dex(module_dex::Call::swap_with_exact_target(
+			(CurrencyId::AUSD, supply_amount),
+			(CurrencyId::BTC, 1_000),
+			None
+		))
+		.dispatch(Origin::signed(alice))
+		.is_ok());
+		assert_eq!(ausd_balance - balance(CurrencyId::AUSD), supply_amount);
+	});
+}
+
+#[test]
+fn liquidity_follows_the_pool() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		assert_eq!(acala_runtime::get_liquidity(alice.clone(), CurrencyId::BTC), (0, 0));
+		assert!(Call::Dex(module_dex::Call::add_liquidity(
+			CurrencyId::BTC,
+			100_000,
+			100_000,
+			0,
+			None
+		))
+		.dispatch(Origin::signed(alice.clone()))
+		.is_ok());
+		// the minimum liquidity is locked out of the first deposit
+		assert_eq!(
+			acala_runtime::get_liquidity(alice.clone(), CurrencyId::BTC),
+			(99_000, 99_000)
+		);
+
+		assert!(Call::Dex(module_dex::Call::swap_with_exact_supply(
+			(CurrencyId::BTC, 10_000),
+			(CurrencyId::AUSD, 0),
+			None
+		))
+		.dispatch(Origin::signed(alice.clone()))
+		.is_ok());
+		// 99% of the pool of (110_000, 90_919)
+		assert_eq!(acala_runtime::get_liquidity(alice, CurrencyId::BTC), (108_900, 90_009));
+	});
+}
+
+#[test]
+fn pool_info_follows_the_swaps() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		assert!(Call::Dex(module_dex::Call::add_liquidity(
+			CurrencyId::BTC,
+			100_000,
+			100_000,
+			0,
+			None
+		))
+		.dispatch(Origin::signed(alice.clone()))
+		.is_ok());
+		assert!(Call::Dex(module_dex::Call::swap_with_exact_supply(
+			(CurrencyId::BTC, 10_000),
+			(CurrencyId::AUSD, 0),
+			None
+		))
+		.dispatch(Origin::signed(alice))
+		.is_ok());
+
+		let info = acala_runtime::pool_info(CurrencyId::BTC);
+		assert_eq!(info.other_reserve.into_inner(), 110_000);
+		assert_eq!(info.base_reserve.into_inner(), 90_919);
+		assert_eq!(info.total_shares.into_inner(), 100_000);
+		assert_eq!(info.cumulative_volume.into_inner(), 9_081);
+		assert_eq!(info.trade_count.into_inner(), 1);
+	});
+}
@@ -0,0 +1,103 @@
+//! Tests of dry running protocol calls.
+
+use acala_runtime::{AccountId, Balance, Call, CurrencyId, Origin, Runtime};
+use orml_traits::MultiCurrency;
+use sp_runtime::{traits::Dispatchable, BuildStorage, DispatchError, Storage};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const BOB: [u8; 32] = [2u8; 32];
+const INITIAL_BALANCE: Balance = 1_000_000;
+
+/// State with a BTC pool of (100 BTC, 10_000 AUSD).
+fn storage_with_pool() -> Storage {
+	let mut storage = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::BTC],
+		initial_balance: INITIAL_BALANCE,
+		endowed_accounts: vec![AccountId::from(ALICE), AccountId::from(BOB)],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::BTC],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+
+	frame_support::BasicExternalities::execute_with_storage(&mut storage, || {
+		assert!(
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None))
+				.dispatch(Origin::signed(AccountId::from(ALICE)))
+				.is_ok()
+		);
+	});
+
+	storage
+}
+
+fn swap(min_target_amount: Balance) -> Call {
+	Call::Dex(module_dex::Call::swap_with_exact_supply(
+		(CurrencyId::AUSD, 1_000),
+		(CurrencyId::BTC, min_target_amount),
+		None,
+	))
+}
+
+fn btc_pool() -> (Balance, Balance) {
+	module_dex::Module::<Runtime>::liquidity_pool(CurrencyId::BTC)
+}
+
+/// Dry run `call` the way the client runs a runtime api call, on an overlay of `storage` which is thrown away.
+fn dry_run(storage: &Storage, call: Call) -> Result<(), DispatchError> {
+	runtime_io::TestExternalities::new(storage.clone())
+		.execute_with(|| acala_runtime::dry_run_call(AccountId::from(BOB), call))
+}
+
+#[test]
+fn dry_run_predicts_swap_without_touching_pools() {
+	let storage = storage_with_pool();
+	let mut ext = runtime_io::TestExternalities::new(storage.clone());
+
+	// the slippage is too high
+	let predicted = dry_run(&storage, swap(50));
+	assert!(predicted.is_err());
+	ext.execute_with(|| {
+		assert_eq!(btc_pool(), (100, 10_000));
+		assert_eq!(swap(50).dispatch(Origin::signed(AccountId::from(BOB))), predicted);
+		assert_eq!(btc_pool(), (100, 10_000));
+	});
+
+	let predicted = dry_run(&storage, swap(1));
+	assert_eq!(predicted, Ok(()));
+	ext.execute_with(|| {
+		assert_eq!(btc_pool(), (100, 10_000));
+		assert_eq!(swap(1).dispatch(Origin::signed(AccountId::from(BOB))), predicted);
+		assert_ne!(btc_pool(), (100, 10_000));
+	});
+}
+
+#[test]
+fn only_protocol_calls_can_be_dry_run() {
+	let storage = storage_with_pool();
+	let transfer = Call::Currencies(orml_currencies::Call::transfer(
+		AccountId::from(ALICE).into(),
+		CurrencyId::AUSD,
+		100,
+	));
+	assert!(!acala_runtime::is_dry_run_allowed(&transfer));
+	assert!(acala_runtime::is_dry_run_allowed(&swap(1)));
+
+	runtime_io::TestExternalities::new(storage).execute_with(|| {
+		assert_eq!(
+			acala_runtime::dry_run_call(AccountId::from(BOB), transfer),
+			Err(DispatchError::Other("Call not allowed in dry run"))
+		);
+		assert_eq!(
+			orml_tokens::Module::<Runtime>::free_balance(CurrencyId::AUSD, &AccountId::from(BOB)),
+			INITIAL_BALANCE
+		);
+	});
+}
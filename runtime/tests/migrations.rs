@@ -0,0 +1,58 @@
+//! Executive level tests of applying the storage migrations once per runtime upgrade.
+
+use acala_runtime::{AuctionManager, CdpTreasury, Dex, Executive, Header, Runtime, Vaults, VERSION};
+use frame_support::storage::unhashed;
+use sp_runtime::traits::Header as HeaderT;
+
+const MIGRATED_SPEC_VERSION: &[u8] = b":acala:migrated_spec_version";
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	system::GenesisConfig::default()
+		.build_storage::<Runtime>()
+		.unwrap()
+		.into()
+}
+
+fn initialize_block(number: u32) {
+	Executive::initialize_block(&Header::new(
+		number,
+		Default::default(),
+		Default::default(),
+		Default::default(),
+		Default::default(),
+	));
+}
+
+fn assert_migrated() {
+	assert_eq!(
+		AuctionManager::storage_version(),
+		module_auction_manager::StorageVersion::V1
+	);
+	assert_eq!(Vaults::storage_version(), module_vaults::StorageVersion::V2);
+	assert_eq!(CdpTreasury::storage_version(), module_cdp_treasury::StorageVersion::V1);
+	assert_eq!(Dex::storage_version(), module_dex::StorageVersion::V3);
+}
+
+#[test]
+fn migrations_are_applied_in_the_first_block() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Dex::storage_version(), module_dex::StorageVersion::V0);
+		initialize_block(1);
+		assert_migrated();
+		assert_eq!(unhashed::get::<u32>(MIGRATED_SPEC_VERSION), Some(VERSION.spec_version));
+	});
+}
+
+#[test]
+fn migrations_are_applied_once_per_spec_version() {
+	new_test_ext().execute_with(|| {
+		unhashed::put(MIGRATED_SPEC_VERSION, &VERSION.spec_version);
+		initialize_block(1);
+		assert_eq!(Dex::storage_version(), module_dex::StorageVersion::V0);
+
+		// the first block of a new spec version
+		unhashed::put(MIGRATED_SPEC_VERSION, &(VERSION.spec_version - 1));
+		initialize_block(2);
+		assert_migrated();
+	});
+}
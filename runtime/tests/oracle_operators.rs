@@ -0,0 +1,73 @@
+//! Integration tests of the oracle operators, the members of the operator membership.
+
+use acala_runtime::{AccountId, Call, OperatorMembership, Origin, Runtime};
+use orml_oracle::OperatorProvider;
+use sp_runtime::{traits::Dispatchable, BuildStorage};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const BOB: [u8; 32] = [2u8; 32];
+
+type Operators = <Runtime as orml_oracle::Trait>::OperatorProvider;
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	pallet_membership::GenesisConfig::<Runtime, pallet_membership::Instance1> {
+		members: vec![AccountId::from(ALICE)],
+		phantom: Default::default(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	t.into()
+}
+
+/// The origin of a motion the whole operator collective agreed on.
+fn operator_collective() -> Origin {
+	pallet_collective::RawOrigin::<AccountId, pallet_collective::Instance1>::Members(1, 1).into()
+}
+
+#[test]
+fn only_members_feed_the_oracle() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		let bob = AccountId::from(BOB);
+		assert!(Operators::can_feed_data(&alice));
+		assert!(!Operators::can_feed_data(&bob));
+		assert_eq!(Operators::operators(), vec![alice.clone()]);
+
+		// an operator alone can't add another
+		assert!(
+			Call::OperatorMembership(pallet_membership::Call::add_member(bob.clone()))
+				.dispatch(Origin::signed(alice.clone()))
+				.is_err()
+		);
+		assert!(!Operators::can_feed_data(&bob));
+
+		// bob can feed once the operator collective adds him
+		assert!(
+			Call::OperatorMembership(pallet_membership::Call::add_member(bob.clone()))
+				.dispatch(operator_collective())
+				.is_ok()
+		);
+		assert!(Operators::can_feed_data(&bob));
+		assert_eq!(Operators::operators(), OperatorMembership::members());
+		assert_eq!(Operators::operators().len(), 2);
+	});
+}
+
+#[test]
+fn removed_members_can_not_feed_the_oracle() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		assert!(
+			Call::OperatorMembership(pallet_membership::Call::remove_member(alice.clone()))
+				.dispatch(operator_collective())
+				.is_ok()
+		);
+
+		// no operator left, no value is combined into a price
+		assert!(!Operators::can_feed_data(&alice));
+		assert!(Operators::operators().is_empty());
+	});
+}
@@ -0,0 +1,140 @@
+//! Integration tests of the system stats runtime api.
+
+use acala_runtime::{AccountId, Balance, Call, CurrencyId, ExchangeRate, Origin, Price, Ratio, Runtime};
+use frame_support::StorageMap;
+use module_support::{AuctionManager, CDPTreasury};
+use sp_runtime::{traits::Dispatchable, BuildStorage};
+
+const ALICE: [u8; 32] = [1u8; 32];
+const INITIAL_BALANCE: Balance = 1_000_000;
+
+type CdpTreasury = module_cdp_treasury::Module<Runtime>;
+type AuctionManagerModule = module_auction_manager::Module<Runtime>;
+
+fn new_test_ext() -> runtime_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	orml_tokens::GenesisConfig::<Runtime> {
+		tokens: vec![CurrencyId::AUSD, CurrencyId::BTC],
+		initial_balance: INITIAL_BALANCE,
+		endowed_accounts: vec![AccountId::from(ALICE)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	module_dex::GenesisConfig::<Runtime> {
+		enabled_trading_pairs: vec![CurrencyId::BTC],
+		initial_pools: vec![],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	t.into()
+}
+
+/// CDPs of 30 BTC for 1_000 debit at the exchange rate of 1.1, and 200 DOT for 500 debit at the default rate.
+fn seed_vaults() {
+	module_vaults::TotalCollaterals::<Runtime>::insert(CurrencyId::BTC, 30);
+	module_vaults::TotalDebits::<Runtime>::insert(CurrencyId::BTC, 1_000);
+	module_cdp_engine::DebitExchangeRate::<Runtime>::insert(CurrencyId::BTC, ExchangeRate::from_rational(11, 10));
+	module_vaults::TotalCollaterals::<Runtime>::insert(CurrencyId::DOT, 200);
+	module_vaults::TotalDebits::<Runtime>::insert(CurrencyId::DOT, 500);
+}
+
+fn lock_prices() {
+	module_emergency_shutdown::LockedPrices::<Runtime>::insert(CurrencyId::BTC, Price::from_natural(100));
+	module_emergency_shutdown::LockedPrices::<Runtime>::insert(CurrencyId::DOT, Price::from_natural(10));
+}
+
+#[test]
+fn system_stats_reconcile_with_seeded_state() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		seed_vaults();
+		lock_prices();
+		assert!(CdpTreasury::on_system_surplus(300).is_ok());
+		assert!(CdpTreasury::on_system_debit(100).is_ok());
+		assert!(CdpTreasury::deposit_collateral(&alice, CurrencyId::BTC, 10).is_ok());
+		assert_eq!(
+			AuctionManagerModule::new_collateral_auction(alice.clone(), CurrencyId::BTC, 10, 500).len(),
+			1
+		);
+		assert!(
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None))
+				.dispatch(Origin::signed(alice))
+				.is_ok()
+		);
+
+		let stats = acala_runtime::system_stats();
+
+		let dot = &stats.collaterals[0];
+		assert_eq!(dot.currency_id, CurrencyId::DOT);
+		assert_eq!(dot.total_collateral.into_inner(), 200);
+		assert_eq!(dot.total_collateral_value.map(|value| value.into_inner()), Some(2_000));
+		assert_eq!(dot.total_debit_value.into_inner(), 500);
+		assert_eq!(dot.collateral_in_auction.into_inner(), 0);
+
+		let btc = &stats.collaterals[1];
+		assert_eq!(btc.currency_id, CurrencyId::BTC);
+		assert_eq!(btc.total_collateral.into_inner(), 30);
+		assert_eq!(btc.total_collateral_value.map(|value| value.into_inner()), Some(3_000));
+		assert_eq!(btc.total_debit_value.into_inner(), 1_100);
+		assert_eq!(btc.collateral_in_auction.into_inner(), 10);
+
+		// (30 * 100 + 200 * 10) / (1_000 * 1.1 + 500)
+		assert_eq!(stats.total_debit_value.into_inner(), 1_600);
+		assert_eq!(
+			stats.global_collateral_ratio.map(|ratio| ratio.0),
+			Some(Ratio::from_rational(5_000, 1_600))
+		);
+
+		assert_eq!(stats.surplus_pool.into_inner(), 300);
+		assert_eq!(stats.debit_pool.into_inner(), 100);
+		assert_eq!(stats.target_in_auction.into_inner(), 500);
+		assert_eq!(stats.dex_total_value_locked.into_inner(), 20_000);
+		assert_eq!(stats.treasury_dex_liquidity_value.into_inner(), 0);
+	});
+}
+
+#[test]
+fn global_ratio_unknown_without_prices_or_debit() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(acala_runtime::system_stats().global_collateral_ratio, None);
+
+		seed_vaults();
+		let stats = acala_runtime::system_stats();
+		assert_eq!(stats.total_debit_value.into_inner(), 1_600);
+		assert_eq!(stats.collaterals[1].total_collateral_value, None);
+		assert_eq!(stats.global_collateral_ratio, None);
+
+		lock_prices();
+		assert!(acala_runtime::system_stats().global_collateral_ratio.is_some());
+	});
+}
+
+#[test]
+fn system_stats_value_treasury_dex_liquidity() {
+	new_test_ext().execute_with(|| {
+		let alice = AccountId::from(ALICE);
+		assert!(
+			Call::Dex(module_dex::Call::add_liquidity(CurrencyId::BTC, 100, 10_000, 0, None))
+				.dispatch(Origin::signed(alice.clone()))
+				.is_ok()
+		);
+		assert!(CdpTreasury::on_system_surplus(10_000).is_ok());
+		assert!(CdpTreasury::deposit_collateral(&alice, CurrencyId::BTC, 100).is_ok());
+		assert!(Call::CdpTreasury(module_cdp_treasury::Call::deposit_liquidity_to_dex(
+			CurrencyId::BTC,
+			100,
+			10_000
+		))
+		.dispatch(Origin::ROOT)
+		.is_ok());
+
+		// half of the pool of (200 BTC, 20_000 AUSD)
+		let stats = acala_runtime::system_stats();
+		assert_eq!(stats.surplus_pool.into_inner(), 0);
+		assert_eq!(stats.dex_total_value_locked.into_inner(), 40_000);
+		assert_eq!(stats.treasury_dex_liquidity_value.into_inner(), 20_000);
+	});
+}
@@ -3,7 +3,7 @@ use grandpa_primitives::AuthorityId as GrandpaId;
 use hex_literal::hex;
 use primitives::{crypto::UncheckedInto, sr25519, Pair, Public};
 use runtime::{
-	AccountId, AuraConfig, BalancesConfig, CurrencyId, GenesisConfig, GrandpaConfig, IndicesConfig,
+	dollar, AccountId, AuraConfig, BalancesConfig, CurrencyId, DexConfig, GenesisConfig, GrandpaConfig, IndicesConfig,
 	OperatorMembershipConfig, Signature, SudoConfig, SystemConfig, TokensConfig, WASM_BINARY,
 };
 use sc_service;
@@ -196,10 +196,14 @@ fn testnet_genesis(
 			phantom: Default::default(),
 		}),
 		orml_tokens: Some(TokensConfig {
-			tokens: vec![CurrencyId::DOT, CurrencyId::XBTC],
-			initial_balance: 1_000_000_000_000_000_000_000_u128, // $1M
+			tokens: vec![CurrencyId::DOT, CurrencyId::BTC],
+			initial_balance: 1_000_000 * dollar(CurrencyId::AUSD), // $1M
 			endowed_accounts: endowed_accounts.clone(),
 		}),
+		module_dex: Some(DexConfig {
+			enabled_trading_pairs: vec![CurrencyId::DOT, CurrencyId::BTC],
+			initial_pools: vec![],
+		}),
 	}
 }
 
@@ -233,9 +237,13 @@ fn alphanet_genesis(
 			phantom: Default::default(),
 		}),
 		orml_tokens: Some(TokensConfig {
-			tokens: vec![CurrencyId::DOT, CurrencyId::XBTC],
-			initial_balance: 1_000_000_000_000_000_000_000_u128, // $1M
+			tokens: vec![CurrencyId::DOT, CurrencyId::BTC],
+			initial_balance: 1_000_000 * dollar(CurrencyId::AUSD), // $1M
 			endowed_accounts: endowed_accounts.clone(),
 		}),
+		module_dex: Some(DexConfig {
+			enabled_trading_pairs: vec![CurrencyId::DOT, CurrencyId::BTC],
+			initial_pools: vec![],
+		}),
 	}
 }
@@ -69,6 +69,26 @@ macro_rules! new_full_start {
 			import_setup = Some((grandpa_block_import, grandpa_link));
 
 			Ok(import_queue)
+		})?
+		.with_rpc_extensions(|client, _pool, _backend, _fetcher, _remote_blockchain| {
+			use acala_dex_rpc::{Dex, DexApi};
+			use acala_honzon_rpc::{CdpEngine, CdpEngineApi, Honzon, HonzonApi};
+
+			let mut io = jsonrpc_core::IoHandler::<sc_rpc::Metadata>::default();
+			io.extend_with(
+				DexApi::<_, runtime::AccountId, runtime::CurrencyId, runtime::Balance>::to_delegate(Dex::new(
+					client.clone(),
+				)),
+			);
+			io.extend_with(
+				HonzonApi::<_, runtime::AccountId, runtime::CurrencyId, runtime::Balance>::to_delegate(Honzon::new(
+					client.clone(),
+				)),
+			);
+			io.extend_with(CdpEngineApi::<_, runtime::CurrencyId, runtime::Balance>::to_delegate(
+				CdpEngine::new(client),
+			));
+			Ok(io)
 		})?;
 
 		(builder, import_setup, inherent_data_providers)